@@ -10,11 +10,7 @@ use libafl::{
     fuzzer::{Evaluator, Fuzzer, StdFuzzer},
     inputs::{BytesInput, HasTargetBytes},
     monitors::SimpleMonitor,
-    mutators::{
-        havoc_mutations, scheduled::StdScheduledMutator, GrimoireExtensionMutator,
-        GrimoireRandomDeleteMutator, GrimoireRecursiveReplacementMutator,
-        GrimoireStringReplacementMutator, Tokens,
-    },
+    mutators::{grimoire_mutations, havoc_mutations, scheduled::StdScheduledMutator, Tokens},
     observers::{CanTrack, StdMapObserver},
     schedulers::QueueScheduler,
     stages::{mutational::StdMutationalStage, GeneralizationStage},
@@ -140,17 +136,7 @@ pub fn main() {
 
     // Setup a mutational stage with a basic bytes mutator
     let mutator = StdScheduledMutator::with_max_stack_pow(havoc_mutations(), 2);
-    let grimoire_mutator = StdScheduledMutator::with_max_stack_pow(
-        tuple_list!(
-            GrimoireExtensionMutator::new(),
-            GrimoireRecursiveReplacementMutator::new(),
-            GrimoireStringReplacementMutator::new(),
-            // give more probability to avoid large inputs
-            GrimoireRandomDeleteMutator::new(),
-            GrimoireRandomDeleteMutator::new(),
-        ),
-        3,
-    );
+    let grimoire_mutator = StdScheduledMutator::with_max_stack_pow(grimoire_mutations(), 3);
     let mut stages = tuple_list!(
         generalization,
         StdMutationalStage::new(mutator),
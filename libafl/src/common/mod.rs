@@ -2,11 +2,12 @@
 
 #![allow(unused, missing_docs)]
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::any::type_name;
 
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
+pub mod serialization;
 
 use libafl_bolts::{
     serdeany::{NamedSerdeAnyMap, SerdeAny, SerdeAnyMap},
@@ -80,6 +81,21 @@ pub trait HasMetadata {
     }
 }
 
+/// The separator between the namespace and the name of namespaced metadata,
+/// see [`HasNamedMetadata::add_namespaced_metadata`]
+pub const METADATA_NAMESPACE_SEPARATOR: &str = "::";
+
+/// The name of the named metadata `name` in `namespace`.
+/// The namespace must not contain the [`METADATA_NAMESPACE_SEPARATOR`].
+#[must_use]
+pub fn namespaced_metadata_name(namespace: &str, name: &str) -> String {
+    debug_assert!(
+        !namespace.contains(METADATA_NAMESPACE_SEPARATOR),
+        "Metadata namespace {namespace} contains the separator"
+    );
+    format!("{namespace}{METADATA_NAMESPACE_SEPARATOR}{name}")
+}
+
 /// Trait for elements offering named metadata
 pub trait HasNamedMetadata {
     /// A map, storing all metadata
@@ -151,4 +167,122 @@ pub trait HasNamedMetadata {
             .get_mut::<M>(name)
             .ok_or_else(|| Error::key_not_found(format!("{} not found", type_name::<M>())))
     }
+
+    /// Add a metadata under `name` in `namespace`.
+    ///
+    /// Namespaces keep the metadata of different components, such as plugins or experiments, apart,
+    /// even if they use the same metadata type, and let them be enumerated and dropped as a whole,
+    /// see [`Self::remove_metadata_namespace`].
+    #[inline]
+    fn add_namespaced_metadata<M>(&mut self, namespace: &str, name: &str, meta: M)
+    where
+        M: SerdeAny,
+    {
+        self.add_named_metadata(&namespaced_metadata_name(namespace, name), meta);
+    }
+
+    /// Remove a metadata under `name` in `namespace`
+    #[inline]
+    fn remove_namespaced_metadata<M>(&mut self, namespace: &str, name: &str) -> Option<Box<M>>
+    where
+        M: SerdeAny,
+    {
+        self.remove_named_metadata(&namespaced_metadata_name(namespace, name))
+    }
+
+    /// Gets a metadata under `name` in `namespace`, or inserts it using the given construction function `default`
+    fn namespaced_metadata_or_insert_with<M>(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        default: impl FnOnce() -> M,
+    ) -> &mut M
+    where
+        M: SerdeAny,
+    {
+        self.named_metadata_or_insert_with(&namespaced_metadata_name(namespace, name), default)
+    }
+
+    /// Check for a metadata under `name` in `namespace`
+    #[inline]
+    fn has_namespaced_metadata<M>(&self, namespace: &str, name: &str) -> bool
+    where
+        M: SerdeAny,
+    {
+        self.has_named_metadata::<M>(&namespaced_metadata_name(namespace, name))
+    }
+
+    /// To get a metadata under `name` in `namespace`
+    #[inline]
+    fn namespaced_metadata<M>(&self, namespace: &str, name: &str) -> Result<&M, Error>
+    where
+        M: SerdeAny,
+    {
+        self.named_metadata(&namespaced_metadata_name(namespace, name))
+    }
+
+    /// To get a mutable metadata under `name` in `namespace`
+    #[inline]
+    fn namespaced_metadata_mut<M>(&mut self, namespace: &str, name: &str) -> Result<&mut M, Error>
+    where
+        M: SerdeAny,
+    {
+        self.named_metadata_mut(&namespaced_metadata_name(namespace, name))
+    }
+
+    /// All namespaces that hold metadata, sorted
+    fn metadata_namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self
+            .named_metadata_map()
+            .names()
+            .filter_map(|name| name.split_once(METADATA_NAMESPACE_SEPARATOR))
+            .map(|(namespace, _)| namespace.into())
+            .collect();
+        namespaces.sort_unstable();
+        namespaces.dedup();
+        namespaces
+    }
+
+    /// Remove all metadata in `namespace`, of any type, for example to start an experiment over on resume.
+    /// Returns the number of removed entries.
+    fn remove_metadata_namespace(&mut self, namespace: &str) -> usize {
+        self.named_metadata_map_mut().retain_names(|name| {
+            name.split_once(METADATA_NAMESPACE_SEPARATOR)
+                .map(|(entry_namespace, _)| entry_namespace)
+                != Some(namespace)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::HasNamedMetadata, feedbacks::MapFeedbackMetadata, inputs::BytesInput,
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_metadata_namespaces() {
+        let mut state = test_std_state::<BytesInput>();
+        state.add_named_metadata("edges", MapFeedbackMetadata::<u8>::new(4));
+        state.add_namespaced_metadata("plugin", "edges", MapFeedbackMetadata::<u8>::new(8));
+        state.add_namespaced_metadata("experiment", "edges", MapFeedbackMetadata::<u8>::new(16));
+        state.add_namespaced_metadata("experiment", "cmps", MapFeedbackMetadata::<u64>::new(2));
+
+        // The same type and name do not collide across namespaces
+        let edges = state
+            .named_metadata::<MapFeedbackMetadata<u8>>("edges")
+            .unwrap();
+        assert_eq!(edges.history_map.len(), 4);
+        let edges = state
+            .namespaced_metadata::<MapFeedbackMetadata<u8>>("plugin", "edges")
+            .unwrap();
+        assert_eq!(edges.history_map.len(), 8);
+        assert_eq!(state.metadata_namespaces(), vec!["experiment", "plugin"]);
+
+        assert_eq!(state.remove_metadata_namespace("experiment"), 2);
+        assert_eq!(state.metadata_namespaces(), vec!["plugin"]);
+        assert!(state.has_named_metadata::<MapFeedbackMetadata<u8>>("edges"));
+        assert!(state.has_namespaced_metadata::<MapFeedbackMetadata<u8>>("plugin", "edges"));
+    }
 }
@@ -2,6 +2,7 @@ pub mod chunkstore;
 pub mod context;
 pub mod mutator;
 pub mod newtypes;
+pub mod parser;
 pub mod recursion_info;
 pub mod rule;
 pub mod tree;
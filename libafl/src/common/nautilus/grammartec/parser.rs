@@ -0,0 +1,302 @@
+//! An Earley parser, turning unparsed bytes back into a [`Tree`] of the grammar.
+//!
+//! Earley parsing handles any context-free grammar, including left-recursive and ambiguous ones.
+//! Regex rules match any span their regex matches as a whole. Script rules can not be inverted,
+//! so inputs that need them do not parse.
+
+use alloc::vec::Vec;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::common::nautilus::grammartec::{
+    context::Context,
+    newtypes::{NTermId, RuleId},
+    rule::{Rule, RuleChild, RuleIdOrCustom},
+    tree::Tree,
+};
+
+/// A rule, parsed up to `dot`, starting at `origin`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Item {
+    rule: RuleId,
+    dot: usize,
+    origin: usize,
+}
+
+struct Parser<'a> {
+    ctx: &'a Context,
+    input: &'a [u8],
+    sets: Vec<Vec<Item>>,
+    seen: Vec<HashSet<Item>>,
+    predicted: Vec<HashSet<NTermId>>,
+    /// The ends, and rules, of the nonterminals completed at each start
+    completed: HashMap<(NTermId, usize), Vec<(usize, RuleId)>>,
+    regexes: HashMap<RuleId, Option<regex::bytes::Regex>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(ctx: &'a Context, input: &'a [u8]) -> Self {
+        let positions = input.len() + 1;
+        Self {
+            ctx,
+            input,
+            sets: vec![vec![]; positions],
+            seen: vec![HashSet::new(); positions],
+            predicted: vec![HashSet::new(); positions],
+            completed: HashMap::new(),
+            regexes: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, pos: usize, item: Item) {
+        if self.seen[pos].insert(item) {
+            self.sets[pos].push(item);
+        }
+    }
+
+    fn children(&self, rule: RuleId) -> &'a [RuleChild] {
+        match self.ctx.get_rule(rule) {
+            Rule::Plain(plain) => &plain.children,
+            Rule::Script(_) | Rule::RegExp(_) => &[],
+        }
+    }
+
+    fn complete(&mut self, nt: NTermId, rule: RuleId, start: usize, end: usize) {
+        let ends = self.completed.entry((nt, start)).or_default();
+        if ends.contains(&(end, rule)) {
+            return;
+        }
+        ends.push((end, rule));
+        // Advance everything waiting for `nt` at `start`. Items added to the set at `start` later,
+        // if it is still being processed, pick up this completion when they predict `nt`.
+        let mut i = 0;
+        while i < self.sets[start].len() {
+            let item = self.sets[start][i];
+            if self.children(item.rule).get(item.dot) == Some(&RuleChild::NTerm(nt)) {
+                self.add(
+                    end,
+                    Item {
+                        dot: item.dot + 1,
+                        ..item
+                    },
+                );
+            }
+            i += 1;
+        }
+    }
+
+    fn regex_matches(&mut self, rule: RuleId, bytes: &[u8]) -> bool {
+        let regex = self.regexes.entry(rule).or_insert_with(|| {
+            let Rule::RegExp(regex) = self.ctx.get_rule(rule) else {
+                return None;
+            };
+            regex::bytes::Regex::new(&format!("^(?:{})$", regex.hir)).ok()
+        });
+        regex.as_ref().is_some_and(|regex| regex.is_match(bytes))
+    }
+
+    fn predict(&mut self, nt: NTermId, pos: usize) {
+        if !self.predicted[pos].insert(nt) {
+            return;
+        }
+        let (ctx, input) = (self.ctx, self.input);
+        for &rule in ctx.get_rules_for_nt(nt) {
+            match ctx.get_rule(rule) {
+                Rule::Plain(_) => self.add(
+                    pos,
+                    Item {
+                        rule,
+                        dot: 0,
+                        origin: pos,
+                    },
+                ),
+                Rule::RegExp(_) => {
+                    for end in pos..=input.len() {
+                        if self.regex_matches(rule, &input[pos..end]) {
+                            self.complete(nt, rule, pos, end);
+                        }
+                    }
+                }
+                Rule::Script(_) => {}
+            }
+        }
+    }
+
+    fn recognize(&mut self, start: NTermId) -> bool {
+        self.predict(start, 0);
+        for pos in 0..=self.input.len() {
+            let mut i = 0;
+            while i < self.sets[pos].len() {
+                let item = self.sets[pos][i];
+                i += 1;
+                let advanced = Item {
+                    dot: item.dot + 1,
+                    ..item
+                };
+                match self.children(item.rule).get(item.dot) {
+                    Some(RuleChild::Term(term)) => {
+                        if self.input[pos..].starts_with(term) {
+                            self.add(pos + term.len(), advanced);
+                        }
+                    }
+                    Some(RuleChild::NTerm(nt)) => {
+                        let nt = *nt;
+                        self.predict(nt, pos);
+                        let ends: Vec<usize> = self
+                            .completed
+                            .get(&(nt, pos))
+                            .into_iter()
+                            .flatten()
+                            .map(|(end, _)| *end)
+                            .collect();
+                        for end in ends {
+                            self.add(end, advanced);
+                        }
+                    }
+                    None => {
+                        let nt = self.ctx.get_rule(item.rule).nonterm();
+                        self.complete(nt, item.rule, item.origin, pos);
+                    }
+                }
+            }
+        }
+        self.completed
+            .get(&(start, 0))
+            .is_some_and(|ends| ends.iter().any(|(end, _)| *end == self.input.len()))
+    }
+
+    /// Appends a derivation of `nt` spanning `start..end` to `rules`, in pre-order
+    fn derive(
+        &self,
+        nt: NTermId,
+        start: usize,
+        end: usize,
+        rules: &mut Vec<RuleIdOrCustom>,
+        active: &mut HashSet<(NTermId, usize, usize)>,
+    ) -> bool {
+        // Cycles of rules deriving the same span never lead to a finite tree
+        if !active.insert((nt, start, end)) {
+            return false;
+        }
+        let derived = self
+            .completed
+            .get(&(nt, start))
+            .into_iter()
+            .flatten()
+            .filter(|(rule_end, _)| *rule_end == end)
+            .any(|(_, rule)| {
+                let len = rules.len();
+                let derived = match self.ctx.get_rule(*rule) {
+                    Rule::Plain(plain) => {
+                        rules.push(RuleIdOrCustom::Rule(*rule));
+                        self.derive_children(&plain.children, start, end, rules, active)
+                    }
+                    Rule::RegExp(_) => {
+                        rules.push(RuleIdOrCustom::Custom(
+                            *rule,
+                            self.input[start..end].to_vec(),
+                        ));
+                        true
+                    }
+                    Rule::Script(_) => false,
+                };
+                if !derived {
+                    rules.truncate(len);
+                }
+                derived
+            });
+        active.remove(&(nt, start, end));
+        derived
+    }
+
+    fn derive_children(
+        &self,
+        children: &[RuleChild],
+        pos: usize,
+        end: usize,
+        rules: &mut Vec<RuleIdOrCustom>,
+        active: &mut HashSet<(NTermId, usize, usize)>,
+    ) -> bool {
+        match children.split_first() {
+            None => pos == end,
+            Some((RuleChild::Term(term), rest)) => {
+                self.input[pos..end].starts_with(term)
+                    && self.derive_children(rest, pos + term.len(), end, rules, active)
+            }
+            Some((RuleChild::NTerm(nt), rest)) => self
+                .completed
+                .get(&(*nt, pos))
+                .into_iter()
+                .flatten()
+                .filter(|(child_end, _)| *child_end <= end)
+                .any(|(child_end, _)| {
+                    let len = rules.len();
+                    let derived = self.derive(*nt, pos, *child_end, rules, active)
+                        && self.derive_children(rest, *child_end, end, rules, active);
+                    if !derived {
+                        rules.truncate(len);
+                    }
+                    derived
+                }),
+        }
+    }
+}
+
+/// Parses `input` as the nonterminal `nt` of the grammar in `ctx`.
+/// Returns `None` if the grammar does not derive `input`.
+/// If it derives `input` in several ways, one of them is returned.
+#[must_use]
+pub fn parse_from_nt(ctx: &Context, nt: NTermId, input: &[u8]) -> Option<Tree> {
+    let mut parser = Parser::new(ctx, input);
+    if !parser.recognize(nt) {
+        return None;
+    }
+    let mut rules = vec![];
+    if !parser.derive(nt, 0, input.len(), &mut rules, &mut HashSet::new()) {
+        return None;
+    }
+    Some(Tree::from_rule_vec(rules, ctx))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::common::nautilus::grammartec::{
+        context::Context, parser::parse_from_nt, rule::RuleIdOrCustom, tree::TreeLike,
+    };
+
+    #[test]
+    fn test_parse() {
+        let mut ctx = Context::new();
+        let add = ctx.add_rule("E", b"{E}+{E}");
+        let num = ctx.add_rule("E", b"{N}");
+        let digits = ctx.add_regex("N", "[0-9]+");
+        let _ = ctx.add_rule("L", b"");
+        let _ = ctx.add_rule("L", b"{L}x");
+        ctx.initialize(10);
+
+        let tree = parse_from_nt(&ctx, ctx.nt_id("E"), b"12+3").unwrap();
+        assert_eq!(
+            tree.rules,
+            [
+                RuleIdOrCustom::Rule(add),
+                RuleIdOrCustom::Rule(num),
+                RuleIdOrCustom::Custom(digits, b"12".to_vec()),
+                RuleIdOrCustom::Rule(num),
+                RuleIdOrCustom::Custom(digits, b"3".to_vec()),
+            ]
+        );
+        let mut data: Vec<u8> = vec![];
+        tree.unparse_to(&ctx, &mut data);
+        assert_eq!(data, b"12+3");
+
+        // Left recursion, and empty rules
+        let tree = parse_from_nt(&ctx, ctx.nt_id("L"), b"xxx").unwrap();
+        assert_eq!(tree.rules.len(), 4);
+        assert!(parse_from_nt(&ctx, ctx.nt_id("L"), b"").is_some());
+
+        assert!(parse_from_nt(&ctx, ctx.nt_id("E"), b"12+").is_none());
+        assert!(parse_from_nt(&ctx, ctx.nt_id("E"), b"a").is_none());
+    }
+}
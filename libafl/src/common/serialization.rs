@@ -0,0 +1,329 @@
+//! Pluggable serialization formats, for state snapshots, checkpoints, and the events sent over LLMP.
+//!
+//! `postcard` is compact and fast, and stays the default. `bincode` and JSON need `std`;
+//! JSON is meant for debugging, as it can be read and edited with any text editor.
+//! Serialized data that is stored records the format it was written with, see [`serialize_with_header`].
+//!
+//! Observer buffers inside events, and the hashes used to recognize known testcases, are always [`Postcard`],
+//! so that nodes using different formats can still use each other's observers and agree on the hashes.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use libafl_bolts::{
+    hash_std,
+    llmp::{Flags, LLMP_FLAG_BINCODE, LLMP_FLAG_INITIALIZED, LLMP_FLAG_JSON},
+    Error,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The magic bytes at the start of data written by [`serialize_with_header`]
+pub const SERIALIZATION_HEADER_MAGIC: [u8; 4] = *b"LAFS";
+
+/// The length of the header written by [`serialize_with_header`]
+pub const SERIALIZATION_HEADER_LEN: usize = SERIALIZATION_HEADER_MAGIC.len() + 1;
+
+/// A serialization format
+pub trait SerdeFormat {
+    /// The [`SerializationFormat`] this is, to record it next to the serialized data
+    const FORMAT: SerializationFormat;
+
+    /// Serialize `value`
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized;
+
+    /// Deserialize a value from `bytes`
+    fn deserialize<T>(bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned;
+}
+
+/// The [`postcard`] format, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+impl SerdeFormat for Postcard {
+    const FORMAT: SerializationFormat = SerializationFormat::Postcard;
+
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn deserialize<T>(bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+/// The [`bincode`] format
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "std")]
+impl SerdeFormat for Bincode {
+    const FORMAT: SerializationFormat = SerializationFormat::Bincode;
+
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        bincode::serialize(value).map_err(|err| Error::serialize(format!("{err:?}")))
+    }
+
+    fn deserialize<T>(bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        bincode::deserialize(bytes).map_err(|err| Error::serialize(format!("{err:?}")))
+    }
+}
+
+/// The JSON format, human-readable, for debugging
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "std")]
+impl SerdeFormat for Json {
+    const FORMAT: SerializationFormat = SerializationFormat::Json;
+
+    fn serialize<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn deserialize<T>(bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// The serialization formats, to choose one at runtime, or to read data tagged with its format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum SerializationFormat {
+    /// [`Postcard`]
+    #[default]
+    Postcard = 0,
+    /// [`Bincode`]
+    #[cfg(feature = "std")]
+    Bincode = 1,
+    /// [`Json`]
+    #[cfg(feature = "std")]
+    Json = 2,
+}
+
+impl fmt::Display for SerializationFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Postcard => f.write_str("postcard"),
+            #[cfg(feature = "std")]
+            Self::Bincode => f.write_str("bincode"),
+            #[cfg(feature = "std")]
+            Self::Json => f.write_str("json"),
+        }
+    }
+}
+
+impl SerializationFormat {
+    /// The id of this format, as recorded in headers
+    #[must_use]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// The format with the given `id`
+    pub fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            0 => Ok(Self::Postcard),
+            #[cfg(feature = "std")]
+            1 => Ok(Self::Bincode),
+            #[cfg(feature = "std")]
+            2 => Ok(Self::Json),
+            _ => Err(Error::illegal_argument(format!(
+                "Unknown or unsupported serialization format {id}"
+            ))),
+        }
+    }
+
+    /// The format of an LLMP message with the given `flags`
+    pub fn from_llmp_flags(flags: Flags) -> Result<Self, Error> {
+        let bincode = flags & LLMP_FLAG_BINCODE == LLMP_FLAG_BINCODE;
+        let json = flags & LLMP_FLAG_JSON == LLMP_FLAG_JSON;
+        match (bincode, json) {
+            (false, false) => Ok(Self::Postcard),
+            #[cfg(feature = "std")]
+            (true, false) => Ok(Self::Bincode),
+            #[cfg(feature = "std")]
+            (false, true) => Ok(Self::Json),
+            _ => Err(Error::illegal_argument(format!(
+                "Unsupported serialization format in LLMP message flags {flags:?}"
+            ))),
+        }
+    }
+
+    /// The flags to send along with LLMP messages in this format
+    #[must_use]
+    pub fn llmp_flags(self) -> Flags {
+        match self {
+            Self::Postcard => LLMP_FLAG_INITIALIZED,
+            #[cfg(feature = "std")]
+            Self::Bincode => LLMP_FLAG_BINCODE,
+            #[cfg(feature = "std")]
+            Self::Json => LLMP_FLAG_JSON,
+        }
+    }
+
+    /// Serialize `value` in this format
+    pub fn serialize<T>(self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Self::Postcard => Postcard::serialize(value),
+            #[cfg(feature = "std")]
+            Self::Bincode => Bincode::serialize(value),
+            #[cfg(feature = "std")]
+            Self::Json => Json::serialize(value),
+        }
+    }
+
+    /// Deserialize a value from `bytes` in this format
+    pub fn deserialize<T>(self, bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        match self {
+            Self::Postcard => Postcard::deserialize(bytes),
+            #[cfg(feature = "std")]
+            Self::Bincode => Bincode::deserialize(bytes),
+            #[cfg(feature = "std")]
+            Self::Json => Json::deserialize(bytes),
+        }
+    }
+}
+
+impl TryFrom<&str> for SerializationFormat {
+    type Error = Error;
+
+    fn try_from(name: &str) -> Result<Self, Error> {
+        match name {
+            "postcard" => Ok(Self::Postcard),
+            #[cfg(feature = "std")]
+            "bincode" => Ok(Self::Bincode),
+            #[cfg(feature = "std")]
+            "json" => Ok(Self::Json),
+            _ => Err(Error::illegal_argument(format!(
+                "Unknown or unsupported serialization format {name}"
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for SerializationFormat {
+    type Error = Error;
+
+    fn try_from(name: String) -> Result<Self, Error> {
+        Self::try_from(name.as_str())
+    }
+}
+
+/// Serialize `value` in `format`, behind a header recording the format,
+/// so it can be read back with [`deserialize_with_header`] whatever format it was written in.
+pub fn serialize_with_header<T>(format: SerializationFormat, value: &T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut bytes = Vec::from(SERIALIZATION_HEADER_MAGIC);
+    bytes.push(format.id());
+    bytes.extend(format.serialize(value)?);
+    Ok(bytes)
+}
+
+/// Read the header written by [`serialize_with_header`].
+/// Returns the format, and the serialized value behind the header.
+pub fn read_header(bytes: &[u8]) -> Result<(SerializationFormat, &[u8]), Error> {
+    if bytes.len() < SERIALIZATION_HEADER_LEN || bytes[..4] != SERIALIZATION_HEADER_MAGIC {
+        return Err(Error::illegal_argument(
+            "Missing serialization header, the data is corrupted or was written by an older version",
+        ));
+    }
+    let format = SerializationFormat::from_id(bytes[4])?;
+    Ok((format, &bytes[SERIALIZATION_HEADER_LEN..]))
+}
+
+/// Deserialize a value written by [`serialize_with_header`], in the format recorded in the header
+pub fn deserialize_with_header<T>(bytes: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let (format, payload) = read_header(bytes)?;
+    format.deserialize(payload)
+}
+
+/// Hash `value` over its [`Postcard`] serialization, so the hash is the same whatever format the events use
+pub fn hash_serialized<T>(value: &T) -> Result<u64, Error>
+where
+    T: Serialize + ?Sized,
+{
+    Ok(hash_std(&Postcard::serialize(value)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::common::serialization::{
+        deserialize_with_header, read_header, serialize_with_header, SerializationFormat,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Value {
+        name: String,
+        bytes: Vec<u8>,
+    }
+
+    #[test]
+    fn test_serialization_formats() {
+        let value = Value {
+            name: "value".into(),
+            bytes: vec![1, 2, 3],
+        };
+        let mut formats = vec![SerializationFormat::Postcard];
+        #[cfg(feature = "std")]
+        formats.extend([SerializationFormat::Bincode, SerializationFormat::Json]);
+
+        for format in formats {
+            let bytes = serialize_with_header(format, &value).unwrap();
+            assert_eq!(read_header(&bytes).unwrap().0, format);
+            assert_eq!(deserialize_with_header::<Value>(&bytes).unwrap(), value);
+            assert_eq!(SerializationFormat::from_id(format.id()).unwrap(), format);
+            assert_eq!(
+                SerializationFormat::from_llmp_flags(format.llmp_flags()).unwrap(),
+                format
+            );
+            assert_eq!(
+                SerializationFormat::try_from(format.to_string().as_str()).unwrap(),
+                format
+            );
+        }
+
+        assert!(deserialize_with_header::<Value>(&[1, 2, 3]).is_err());
+    }
+}
@@ -52,6 +52,7 @@ where
 #[derive(Debug)]
 pub struct MapCorpusMinimizer<C, E, O, T, TS> {
     observer_handle: Handle<C>,
+    disable: bool,
     phantom: PhantomData<(E, O, T, TS)>,
 }
 
@@ -71,9 +72,18 @@ where
     pub fn new(obs: &C) -> Self {
         Self {
             observer_handle: obs.handle(),
+            disable: false,
             phantom: PhantomData,
         }
     }
+
+    /// Moves redundant entries to the disabled entries of the corpus, instead of removing them,
+    /// so they stay available on disk and to [`Corpus::get_from_all`].
+    #[must_use]
+    pub fn with_disable(mut self, disable: bool) -> Self {
+        self.disable = disable;
+        self
+    }
 }
 
 impl<C, E, O, T, TS> CorpusMinimizer<E> for MapCorpusMinimizer<C, E, O, T, TS>
@@ -225,11 +235,15 @@ where
             removed.sort_unstable_by(|id1, id2| id2.cmp(id1));
             for id in removed {
                 let removed = state.corpus_mut().remove(id)?;
+                let disabled = self.disable.then(|| removed.clone());
                 // scheduler needs to know we've removed the input, or it will continue to try
                 // to use now-missing inputs
                 fuzzer
                     .scheduler_mut()
                     .on_remove(state, id, &Some(removed))?;
+                if let Some(testcase) = disabled {
+                    state.corpus_mut().add_disabled(testcase)?;
+                }
             }
             Ok(())
         } else {
@@ -2,7 +2,7 @@ use alloc::vec::Vec;
 use core::{fmt::Debug, marker::PhantomData};
 
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
+use libafl_bolts::compress::GzipCompressor;
 use libafl_bolts::{
     llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
     shmem::ShMemProvider,
@@ -12,7 +12,7 @@ use libafl_bolts::{
 #[cfg(feature = "llmp_compression")]
 use crate::events::COMPRESS_THRESHOLD;
 use crate::{
-    events::{BrokerEventResult, Event, _LLMP_TAG_TO_MAIN},
+    events::{broker_hooks::decode_event, BrokerEventResult, Event, _LLMP_TAG_TO_MAIN},
     inputs::Input,
 };
 
@@ -33,25 +33,20 @@ where
         _broker_inner: &mut LlmpBrokerInner<SP>,
         client_id: ClientId,
         msg_tag: &mut Tag,
-        _msg_flags: &mut Flags,
+        msg_flags: &mut Flags,
         msg: &mut [u8],
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
         if *msg_tag == _LLMP_TAG_TO_MAIN {
-            #[cfg(feature = "llmp_compression")]
-            let compressor = &self.compressor;
-            #[cfg(not(feature = "llmp_compression"))]
-            let event_bytes = msg;
-            #[cfg(feature = "llmp_compression")]
-            let compressed;
-            #[cfg(feature = "llmp_compression")]
-            let event_bytes = if *_msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = compressor.decompress(msg)?;
-                &compressed
-            } else {
-                &*msg
+            let Some(event) = decode_event::<I>(
+                #[cfg(feature = "llmp_compression")]
+                &self.compressor,
+                client_id,
+                *msg_flags,
+                msg,
+            ) else {
+                return Ok(LlmpMsgHookResult::Handled);
             };
-            let event: Event<I> = postcard::from_bytes(event_bytes)?;
             match Self::handle_in_broker(client_id, &event)? {
                 BrokerEventResult::Forward => Ok(LlmpMsgHookResult::ForwardToClients),
                 BrokerEventResult::Handled => Ok(LlmpMsgHookResult::Handled),
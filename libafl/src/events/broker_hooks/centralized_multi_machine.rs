@@ -1,16 +1,14 @@
 use std::{
     fmt::{Debug, Display},
     marker::PhantomData,
-    slice,
     sync::Arc,
     vec::Vec,
 };
 
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::llmp::LLMP_FLAG_COMPRESSED;
+use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
 use libafl_bolts::{
     llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
-    ownedref::OwnedRef,
     shmem::ShMemProvider,
     ClientId, Error,
 };
@@ -22,8 +20,11 @@ use tokio::{
 };
 
 use crate::{
+    common::serialization::{Postcard, SerdeFormat, SerializationFormat},
     events::{
+        broker_hooks::decode_event,
         centralized::_LLMP_TAG_TO_MAIN,
+        llmp::LLMP_TAG_EVENT_TO_BOTH,
         multi_machine::{MultiMachineMsg, TcpMultiMachineState},
         Event,
     },
@@ -66,8 +67,8 @@ impl<T> NullLock<T> {
     }
 }
 
-/// The Receiving side of the multi-machine architecture
-/// It is responsible for receiving messages from other neighbours.
+/// The Sending side of the multi-machine architecture
+/// It is responsible for forwarding interesting inputs and stats to other neighbours.
 /// Please check [`crate::events::multi_machine`] for more information.
 #[derive(Debug)]
 pub struct TcpMultiMachineLlmpSenderHook<A, I>
@@ -78,6 +79,8 @@ where
     shared_state: Arc<RwLock<TcpMultiMachineState<A>>>,
     /// the tokio runtime used to interact with other machines. Keep it outside to avoid locking it.
     rt: Arc<Runtime>,
+    #[cfg(feature = "llmp_compression")]
+    compressor: GzipCompressor,
     phantom: PhantomData<I>,
 }
 
@@ -109,6 +112,8 @@ where
         Self {
             shared_state,
             rt,
+            #[cfg(feature = "llmp_compression")]
+            compressor: GzipCompressor::new(),
             phantom: PhantomData,
         }
     }
@@ -141,11 +146,12 @@ where
         state_lock: &mut RwLockWriteGuard<TcpMultiMachineState<A>>,
         event: &Event<I>,
     ) -> Result<(Flags, Vec<u8>), Error> {
-        let serialized = postcard::to_allocvec(&event)?;
+        let serialized = Postcard::serialize(&event)?;
+        let flags = SerializationFormat::Postcard.llmp_flags();
 
         match state_lock.compressor().maybe_compress(&serialized) {
-            Some(comp_buf) => Ok((LLMP_FLAG_COMPRESSED, comp_buf)),
-            None => Ok((Flags(0), serialized)),
+            Some(comp_buf) => Ok((flags | LLMP_FLAG_COMPRESSED, comp_buf)),
+            None => Ok((flags, serialized)),
         }
     }
 
@@ -154,7 +160,10 @@ where
         _state_lock: &mut RwLockWriteGuard<TcpMultiMachineState<A>>,
         event: &Event<I>,
     ) -> Result<(Flags, Vec<u8>), Error> {
-        Ok((Flags(0), postcard::to_allocvec(&event)?))
+        Ok((
+            SerializationFormat::Postcard.llmp_flags(),
+            Postcard::serialize(&event)?,
+        ))
     }
 }
 
@@ -164,53 +173,45 @@ where
     SP: ShMemProvider,
     I: Input + Send + Sync + 'static,
 {
-    /// check for received messages, and forward them alongside the incoming message to inner.
+    /// Forward the interesting inputs and stats of this node to the other nodes, in the background.
     fn on_new_message(
         &mut self,
         _broker_inner: &mut LlmpBrokerInner<SP>,
-        _client_id: ClientId,
-        _msg_tag: &mut Tag,
-        _msg_flags: &mut Flags,
+        client_id: ClientId,
+        msg_tag: &mut Tag,
+        msg_flags: &mut Flags,
         msg: &mut [u8],
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
-        let shared_state = self.shared_state.clone();
+        if *msg_tag != LLMP_TAG_EVENT_TO_BOTH {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
 
-        // Here, we suppose msg will never be written again and will always be available.
-        // Thus, it is safe to handle this in a separate thread.
-        let msg_lock = unsafe { NullLock::new((msg.as_ptr(), msg.len())) };
-        // let flags = msg_flags.clone();
+        let Some(event) = decode_event::<I>(
+            #[cfg(feature = "llmp_compression")]
+            &self.compressor,
+            client_id,
+            *msg_flags,
+            msg,
+        ) else {
+            return Ok(LlmpMsgHookResult::Handled);
+        };
+
+        // Only inputs and stats are of interest for other nodes
+        if !matches!(
+            event,
+            Event::NewTestcase { .. } | Event::UpdateUserStats { .. }
+        ) {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
 
+        let shared_state = self.shared_state.clone();
         let _handle: JoinHandle<Result<(), Error>> = self.rt.spawn(async move {
             let mut state_wr_lock = shared_state.write().await;
-            let (msg_ptr, msg_len) = msg_lock.into_innter();
-            let msg: &[u8] = unsafe { slice::from_raw_parts(msg_ptr, msg_len) }; // most likely crash here
-
-            // #[cfg(not(feature = "llmp_compression"))]
-            // let event_bytes = msg;
-            // #[cfg(feature = "llmp_compression")]
-            // let compressed;
-            // #[cfg(feature = "llmp_compression")]
-            // let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-            //     compressed = state_wr_lock.compressor().decompress(msg)?;
-            //     &compressed
-            // } else {
-            //     &*msg
-            // };
-            // let event: Event<I> = postcard::from_bytes(event_bytes)?;
-
-            let mm_msg: MultiMachineMsg<I> = MultiMachineMsg::llmp_msg(OwnedRef::Ref(msg));
-
-            // TODO: do not copy here
-            state_wr_lock.add_past_msg(msg);
 
             log::debug!("Sending msg...");
 
-            state_wr_lock
-                .send_interesting_event_to_nodes(&mm_msg)
-                .await?;
-
-            Ok(())
+            state_wr_lock.forward_event(event).await
         });
 
         Ok(LlmpMsgHookResult::ForwardToClients)
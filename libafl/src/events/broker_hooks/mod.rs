@@ -1,7 +1,8 @@
 //! Hooks called on broker side
-use alloc::vec::Vec;
-use core::marker::PhantomData;
+use alloc::{borrow::Cow, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
 
+use hashbrown::HashMap;
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
 use libafl_bolts::{
@@ -13,9 +14,10 @@ use libafl_bolts::{
 #[cfg(feature = "llmp_compression")]
 use crate::events::llmp::COMPRESS_THRESHOLD;
 use crate::{
+    common::serialization::SerializationFormat,
     events::{llmp::LLMP_TAG_EVENT_TO_BOTH, BrokerEventResult, Event},
     inputs::Input,
-    monitors::Monitor,
+    monitors::{AggregatorOps, Monitor, UserStats, UserStatsValue},
     Error,
 };
 
@@ -31,12 +33,56 @@ pub mod centralized_multi_machine;
 #[cfg(all(unix, feature = "multi_machine"))]
 pub use centralized_multi_machine::*;
 
+/// Decodes the event of an llmp message, or logs why it is malformed and returns `None`.
+/// Hooks drop such messages, a single client sending garbage must not take down the broker.
+pub(crate) fn decode_event<I>(
+    #[cfg(feature = "llmp_compression")] compressor: &GzipCompressor,
+    client_id: ClientId,
+    flags: Flags,
+    msg: &[u8],
+) -> Option<Event<I>>
+where
+    I: Input,
+{
+    #[cfg(feature = "llmp_compression")]
+    let decompressed;
+    #[cfg(feature = "llmp_compression")]
+    let msg = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+        decompressed = match compressor.decompress(msg) {
+            Ok(decompressed) => decompressed,
+            Err(e) => {
+                log::warn!("Dropping event of client {client_id:?} that failed to decompress: {e}");
+                return None;
+            }
+        };
+        &decompressed
+    } else {
+        msg
+    };
+    match SerializationFormat::from_llmp_flags(flags).and_then(|format| format.deserialize(msg)) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            log::warn!("Dropping malformed event of client {client_id:?}: {e}");
+            None
+        }
+    }
+}
+
+/// How often the broker looks for unresponsive clients, at most
+const LIVENESS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 /// An LLMP-backed event hook for scalable multi-processed fuzzing
 #[derive(Debug)]
 pub struct StdLlmpEventHook<I, MT> {
     monitor: MT,
     #[cfg(feature = "llmp_compression")]
     compressor: GzipCompressor,
+    /// Clients that did not send a message for this long are reported as unresponsive
+    client_timeout: Option<Duration>,
+    /// The time each client was last heard of, and whether it has already been reported as unresponsive
+    last_seen: HashMap<ClientId, (Duration, bool)>,
+    /// The last time the clients were checked for being unresponsive
+    last_liveness_check: Duration,
     phantom: PhantomData<I>,
 }
 
@@ -51,28 +97,26 @@ where
         _broker_inner: &mut LlmpBrokerInner<SP>,
         client_id: ClientId,
         msg_tag: &mut Tag,
-        #[cfg(feature = "llmp_compression")] msg_flags: &mut Flags,
-        #[cfg(not(feature = "llmp_compression"))] _msg_flags: &mut Flags,
+        msg_flags: &mut Flags,
         msg: &mut [u8],
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
+        self.client_seen(client_id);
+
         let monitor = &mut self.monitor;
         #[cfg(feature = "llmp_compression")]
         let compressor = &self.compressor;
 
         if *msg_tag == LLMP_TAG_EVENT_TO_BOTH {
-            #[cfg(not(feature = "llmp_compression"))]
-            let event_bytes = msg;
-            #[cfg(feature = "llmp_compression")]
-            let compressed;
-            #[cfg(feature = "llmp_compression")]
-            let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = compressor.decompress(msg)?;
-                &compressed
-            } else {
-                &*msg
+            let Some(event) = decode_event::<I>(
+                #[cfg(feature = "llmp_compression")]
+                compressor,
+                client_id,
+                *msg_flags,
+                msg,
+            ) else {
+                return Ok(LlmpMsgHookResult::Handled);
             };
-            let event: Event<I> = postcard::from_bytes(event_bytes)?;
             match Self::handle_in_broker(monitor, client_id, &event)? {
                 BrokerEventResult::Forward => Ok(LlmpMsgHookResult::ForwardToClients),
                 BrokerEventResult::Handled => Ok(LlmpMsgHookResult::Handled),
@@ -83,6 +127,7 @@ where
     }
 
     fn on_timeout(&mut self) -> Result<(), Error> {
+        self.check_liveness();
         self.monitor.display("Broker Heartbeat", ClientId(0));
         Ok(())
     }
@@ -99,10 +144,56 @@ where
             monitor,
             #[cfg(feature = "llmp_compression")]
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            client_timeout: None,
+            last_seen: HashMap::new(),
+            last_liveness_check: Duration::ZERO,
             phantom: PhantomData,
         })
     }
 
+    /// Report clients that did not send any message for `client_timeout` as unresponsive.
+    /// Respawning them is up to the restarting manager running them, see `RestartingMgr::client_timeout`.
+    #[must_use]
+    pub fn with_client_timeout(mut self, client_timeout: Duration) -> Self {
+        self.client_timeout = Some(client_timeout);
+        self
+    }
+
+    /// Remember that we just heard of the client
+    fn client_seen(&mut self, client_id: ClientId) {
+        if self.client_timeout.is_none() {
+            return;
+        }
+        let now = libafl_bolts::current_time();
+        if let Some((_, true)) = self.last_seen.insert(client_id, (now, false)) {
+            log::info!("Client {client_id:?} is responsive again");
+        }
+        self.check_liveness();
+    }
+
+    /// Warn (once) about each client that has been silent for longer than the `client_timeout`.
+    /// Since this is called for each message, the clients are only checked every [`LIVENESS_CHECK_INTERVAL`].
+    fn check_liveness(&mut self) {
+        let Some(client_timeout) = self.client_timeout else {
+            return;
+        };
+        let now = libafl_bolts::current_time();
+        if now.saturating_sub(self.last_liveness_check) < LIVENESS_CHECK_INTERVAL {
+            return;
+        }
+        self.last_liveness_check = now;
+        for (client_id, (last_seen, reported)) in &mut self.last_seen {
+            let silent_for = now.saturating_sub(*last_seen);
+            if !*reported && silent_for > client_timeout {
+                log::warn!(
+                    "Client {client_id:?} did not report for {}s, it may be stuck or dead",
+                    silent_for.as_secs()
+                );
+                *reported = true;
+            }
+        }
+    }
+
     /// Handle arriving events in the broker
     #[allow(clippy::unnecessary_wraps)]
     fn handle_in_broker(
@@ -136,6 +227,19 @@ where
                 monitor.display(event.name(), id);
                 Ok(BrokerEventResult::Forward)
             }
+            Event::NewTestcaseAnnounced {
+                corpus_size,
+                time,
+                executions,
+                ..
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_corpus_size(*corpus_size as u64);
+                client.update_executions(*executions, *time);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Forward)
+            }
             Event::UpdateExecStats {
                 time,
                 executions,
@@ -207,8 +311,49 @@ where
                 log::log!((*severity_level).into(), "{message}");
                 Ok(BrokerEventResult::Handled)
             }
+            Event::ClientRestarted { reason, restarts } => {
+                log::warn!("Client {client_id:?} got restarted: {reason}");
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_user_stats(
+                    Cow::Borrowed("restarts"),
+                    UserStats::new(UserStatsValue::Number(*restarts), AggregatorOps::Sum),
+                );
+                monitor.aggregate("restarts");
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
             //_ => Ok(BrokerEventResult::Forward),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::thread;
+
+    use libafl_bolts::ClientId;
+
+    use crate::{events::StdLlmpEventHook, inputs::BytesInput, monitors::NopMonitor};
+
+    #[test]
+    fn test_liveness_check_interval() {
+        let mut hook = StdLlmpEventHook::<BytesInput, _>::new(NopMonitor::new())
+            .unwrap()
+            .with_client_timeout(Duration::from_millis(5));
+        let reported =
+            |hook: &StdLlmpEventHook<BytesInput, NopMonitor>| hook.last_seen[&ClientId(1)].1;
+
+        hook.client_seen(ClientId(1));
+        thread::sleep(Duration::from_millis(10));
+        // the clients were just checked, so further messages do not check them again
+        hook.client_seen(ClientId(2));
+        assert!(!reported(&hook));
+
+        hook.last_liveness_check = Duration::ZERO;
+        hook.check_liveness();
+        assert!(reported(&hook));
+    }
+}
@@ -6,17 +6,19 @@
 // 2. The "centralized broker, the broker that gathers all the testcases from all the fuzzer clients
 // 3. The "main evaluator", the evaluator node that will evaluate all the testcases pass by the centralized event manager to see if the testcases are worth propagating
 // 4. The "main broker", the gathers the stats from the fuzzer clients and broadcast the newly found testcases from the main evaluator.
+//
+// With the novelty filter enabled, the fuzzer clients do not send each new testcase to the main evaluator right away.
+// They first send a small query with the hashes of the input and its observers, over the centralized broker.
+// The main evaluator answers if it has seen neither of them before, only then the full testcase is transmitted.
 
-use alloc::{boxed::Box, string::String, vec::Vec};
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
 use core::{fmt::Debug, time::Duration};
 use std::{marker::PhantomData, process};
 
 #[cfg(feature = "llmp_compression")]
+use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
-};
-use libafl_bolts::{
+    hash_std,
     llmp::{LlmpClient, LlmpClientDescription, Tag},
     shmem::{NopShMemProvider, ShMemProvider},
     tuples::Handle,
@@ -30,10 +32,11 @@ use crate::events::llmp::COMPRESS_THRESHOLD;
 #[cfg(feature = "scalability_introspection")]
 use crate::state::HasScalabilityMonitor;
 use crate::{
+    common::serialization::{hash_serialized, Postcard, SerdeFormat, SerializationFormat},
     events::{
-        AdaptiveSerializer, CustomBufEventResult, Event, EventConfig, EventFirer, EventManager,
-        EventManagerHooksTuple, EventManagerId, EventProcessor, EventRestarter,
-        HasCustomBufHandlers, HasEventManagerId, LogSeverity, ProgressReporter,
+        filter::KnownHashes, AdaptiveSerializer, CustomBufEventResult, Event, EventConfig,
+        EventFirer, EventManager, EventManagerHooksTuple, EventManagerId, EventProcessor,
+        EventRestarter, HasCustomBufHandlers, HasEventManagerId, LogSeverity, ProgressReporter,
     },
     executors::{Executor, HasObservers},
     fuzzer::{EvaluatorObservers, ExecutionProcessor},
@@ -44,6 +47,35 @@ use crate::{
 };
 
 pub(crate) const _LLMP_TAG_TO_MAIN: Tag = Tag(0x3453453);
+/// Novelty queries from the secondary nodes, and the answers of the main node
+pub(crate) const _LLMP_TAG_NOVELTY: Tag = Tag(0x3453454);
+
+/// The maximum amount of testcases a secondary node keeps around while waiting for the main node to ask for them.
+/// If more are pending, the oldest ones are dropped.
+const NOVELTY_MAX_PENDING: usize = 1024;
+
+/// The messages exchanged between the secondary nodes and the main node for novelty filtering
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+enum NoveltyMsg {
+    /// A secondary node found a new testcase, and asks the main node if it wants it
+    Query {
+        /// The id of the query, unique per secondary node
+        id: u64,
+        /// The hash of the serialized input
+        input_hash: u64,
+        /// The hash of the serialized observers, if they got serialized
+        observers_hash: Option<u64>,
+    },
+    /// The answer of the main node to a query
+    Reply {
+        /// The secondary node that sent the query
+        client_id: ClientId,
+        /// The id of the query
+        id: u64,
+        /// If the main node wants the full testcase
+        wanted: bool,
+    },
+}
 
 /// A wrapper manager to implement a main-secondary architecture with another broker
 #[derive(Debug)]
@@ -62,6 +94,18 @@ where
     time_ref: Option<Handle<TimeObserver>>,
     hooks: EMH,
     is_main: bool,
+    /// If secondary nodes should query the main node before sending a new testcase
+    novelty_filter: bool,
+    /// The id of the next novelty query
+    next_query_id: u64,
+    /// The testcases waiting for an answer of the main node, by query id
+    pending: BTreeMap<u64, Event<<EM::State as UsesInput>::Input>>,
+    /// The hashes of the inputs of all testcases the main node received
+    known_inputs: KnownHashes,
+    /// The hashes of the observers of all testcases the main node received
+    known_observers: KnownHashes,
+    /// The format of the events and novelty messages we send, recorded in the message flags
+    serialization: SerializationFormat,
     phantom: PhantomData<S>,
 }
 
@@ -84,6 +128,8 @@ impl
 #[derive(Debug)]
 pub struct CentralizedEventManagerBuilder {
     is_main: bool,
+    novelty_filter: bool,
+    serialization: SerializationFormat,
 }
 
 impl Default for CentralizedEventManagerBuilder {
@@ -96,13 +142,39 @@ impl CentralizedEventManagerBuilder {
     /// The constructor
     #[must_use]
     pub fn new() -> Self {
-        Self { is_main: false }
+        Self {
+            is_main: false,
+            novelty_filter: false,
+            serialization: SerializationFormat::Postcard,
+        }
     }
 
     /// Make this a main evaluator node
     #[must_use]
     pub fn is_main(self, is_main: bool) -> Self {
-        Self { is_main }
+        Self { is_main, ..self }
+    }
+
+    /// Only send the hashes of new testcases to the main evaluator node, and transmit the full testcase only if the
+    /// main node has not seen the same input, or the same observers, before.
+    /// This cuts down the traffic of duplicate testcases in large fleets, at the cost of one round trip per testcase.
+    /// Only affects secondary nodes, the main node always answers queries.
+    #[must_use]
+    pub fn novelty_filter(self, novelty_filter: bool) -> Self {
+        Self {
+            novelty_filter,
+            ..self
+        }
+    }
+
+    /// Serialize the events this manager sends to the main node in `serialization`, for example as JSON to debug them.
+    /// Received messages are deserialized in whatever format their sender used.
+    #[must_use]
+    pub fn serialization_format(self, serialization: SerializationFormat) -> Self {
+        Self {
+            serialization,
+            ..self
+        }
     }
 
     /// Creates a new [`CentralizedEventManager`].
@@ -127,6 +199,12 @@ impl CentralizedEventManagerBuilder {
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
             time_ref: time_obs,
             is_main: self.is_main,
+            novelty_filter: self.novelty_filter,
+            next_query_id: 0,
+            pending: BTreeMap::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
+            serialization: self.serialization,
             phantom: PhantomData,
         })
     }
@@ -159,6 +237,12 @@ impl CentralizedEventManagerBuilder {
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
             time_ref: time_obs,
             is_main: self.is_main,
+            novelty_filter: self.novelty_filter,
+            next_query_id: 0,
+            pending: BTreeMap::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
+            serialization: self.serialization,
             phantom: PhantomData,
         })
     }
@@ -188,6 +272,12 @@ impl CentralizedEventManagerBuilder {
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
             time_ref: time_obs,
             is_main: self.is_main,
+            novelty_filter: self.novelty_filter,
+            next_query_id: 0,
+            pending: BTreeMap::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
+            serialization: self.serialization,
             phantom: PhantomData,
         })
     }
@@ -216,6 +306,12 @@ impl CentralizedEventManagerBuilder {
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
             time_ref: time_obs,
             is_main: self.is_main,
+            novelty_filter: self.novelty_filter,
+            next_query_id: 0,
+            pending: BTreeMap::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
+            serialization: self.serialization,
             phantom: PhantomData,
         })
     }
@@ -299,6 +395,10 @@ where
             };
 
             if should_be_forwarded {
+                if is_tc && self.novelty_filter {
+                    // only send the hashes for now, the main node will ask for the full testcase if it wants it
+                    return self.query_novelty(event);
+                }
                 self.forward_to_main(&event)?;
                 if is_tc {
                     // early return here because we only send it to centralized not main broker.
@@ -388,6 +488,9 @@ where
             // self.inner.process(fuzzer, state, executor)
         } else {
             // The main node does not process incoming events from the broker ATM
+            if self.novelty_filter {
+                self.receive_novelty_replies()?;
+            }
             self.inner.process(fuzzer, state, executor)
         }
     }
@@ -484,8 +587,8 @@ where
     where
         I: Input,
     {
-        let serialized = postcard::to_allocvec(event)?;
-        let flags = LLMP_FLAG_INITIALIZED;
+        let serialized = self.serialization.serialize(event)?;
+        let flags = self.serialization.llmp_flags();
 
         match self.compressor.maybe_compress(&serialized) {
             Some(comp_buf) => {
@@ -496,7 +599,8 @@ where
                 )?;
             }
             None => {
-                self.client.send_buf(_LLMP_TAG_TO_MAIN, &serialized)?;
+                self.client
+                    .send_buf_with_flags(_LLMP_TAG_TO_MAIN, flags, &serialized)?;
             }
         }
         Ok(())
@@ -507,11 +611,106 @@ where
     where
         I: Input,
     {
-        let serialized = postcard::to_allocvec(event)?;
-        self.client.send_buf(_LLMP_TAG_TO_MAIN, &serialized)?;
+        let serialized = self.serialization.serialize(event)?;
+        self.client.send_buf_with_flags(
+            _LLMP_TAG_TO_MAIN,
+            self.serialization.llmp_flags(),
+            &serialized,
+        )?;
         Ok(())
     }
 
+    /// Sends the hashes of a new testcase to the main node, keeping the testcase until the main node answered
+    fn query_novelty(
+        &mut self,
+        event: Event<<EM::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        let Event::NewTestcase {
+            input,
+            observers_buf,
+            ..
+        } = &event
+        else {
+            return self.forward_to_main(&event);
+        };
+
+        let query = NoveltyMsg::Query {
+            id: self.next_query_id,
+            input_hash: hash_serialized(input)?,
+            observers_hash: observers_buf.as_deref().map(hash_std),
+        };
+        self.send_novelty_msg(&query)?;
+
+        self.pending.insert(self.next_query_id, event);
+        self.next_query_id = self.next_query_id.wrapping_add(1);
+        if self.pending.len() > NOVELTY_MAX_PENDING {
+            if let Some((id, _)) = self.pending.pop_first() {
+                log::debug!("No answer from the main node for testcase query {id}, dropping it");
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the testcases the main node asked for, and drops all others
+    fn receive_novelty_replies(&mut self) -> Result<(), Error> {
+        let self_id = self.client.sender().id();
+        while let Some((_, tag, flags, msg)) = self.client.recv_buf_with_flags()? {
+            if tag != _LLMP_TAG_NOVELTY {
+                continue;
+            }
+            let NoveltyMsg::Reply {
+                client_id,
+                id,
+                wanted,
+            } = SerializationFormat::from_llmp_flags(flags)?.deserialize(msg)?
+            else {
+                continue;
+            };
+            if client_id != self_id {
+                continue;
+            }
+            if let Some(event) = self.pending.remove(&id) {
+                if wanted {
+                    self.forward_to_main(&event)?;
+                } else {
+                    log::debug!("The main node already knows testcase {id}, not sending it");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells a secondary node if we want its testcase, i.e., if we have received neither its input nor its observers yet.
+    ///
+    /// The hashes are only remembered once the full testcase arrived, so testcases the secondary node dropped
+    /// in the meantime are asked for again. Concurrent queries for the same testcase may both get a yes.
+    fn answer_novelty_query(
+        &mut self,
+        client_id: ClientId,
+        id: u64,
+        input_hash: u64,
+        observers_hash: Option<u64>,
+    ) -> Result<(), Error> {
+        let new_input = !self.known_inputs.contains(input_hash);
+        let new_observers = !observers_hash.is_some_and(|hash| self.known_observers.contains(hash));
+
+        let reply = NoveltyMsg::Reply {
+            client_id,
+            id,
+            wanted: new_input && new_observers,
+        };
+        self.send_novelty_msg(&reply)
+    }
+
+    /// Sends a novelty query or reply, uncompressed, as it is small anyway
+    fn send_novelty_msg(&mut self, msg: &NoveltyMsg) -> Result<(), Error> {
+        self.client.send_buf_with_flags(
+            _LLMP_TAG_NOVELTY,
+            self.serialization.llmp_flags(),
+            &self.serialization.serialize(msg)?,
+        )
+    }
+
     fn receive_from_secondary<E, Z>(
         &mut self,
         fuzzer: &mut Z,
@@ -528,28 +727,42 @@ where
         // TODO: Get around local event copy by moving handle_in_client
         let self_id = self.client.sender().id();
         let mut count = 0;
-        while let Some((client_id, tag, _flags, msg)) = self.client.recv_buf_with_flags()? {
-            assert!(
-                tag == _LLMP_TAG_TO_MAIN,
-                "Only _LLMP_TAG_TO_MAIN parcel should have arrived in the main node!"
-            );
-
+        while let Some((client_id, tag, flags, msg)) = self.client.recv_buf_with_flags()? {
             if client_id == self_id {
                 continue;
             }
+            // The sender may use another serialization format than we do
+            let format = SerializationFormat::from_llmp_flags(flags)?;
+
+            if tag == _LLMP_TAG_NOVELTY {
+                if let NoveltyMsg::Query {
+                    id,
+                    input_hash,
+                    observers_hash,
+                } = format.deserialize(msg)?
+                {
+                    self.answer_novelty_query(client_id, id, input_hash, observers_hash)?;
+                }
+                continue;
+            }
+
+            assert!(
+                tag == _LLMP_TAG_TO_MAIN,
+                "Only _LLMP_TAG_TO_MAIN or _LLMP_TAG_NOVELTY parcels should have arrived in the main node!"
+            );
             #[cfg(not(feature = "llmp_compression"))]
             let event_bytes = msg;
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
                 compressed = self.compressor.decompress(msg)?;
                 &compressed
             } else {
                 msg
             };
             let event: Event<<<Self as UsesState>::State as UsesInput>::Input> =
-                postcard::from_bytes(event_bytes)?;
+                format.deserialize(event_bytes)?;
             log::debug!("Processor received message {}", event.name_detailed());
             self.handle_in_main(fuzzer, executor, state, client_id, event)?;
             count += 1;
@@ -595,10 +808,16 @@ where
                     event_name
                 );
 
+                // Remember the testcase for the novelty queries of secondary nodes
+                self.known_inputs.insert(hash_serialized(&input)?);
+                if let Some(observers_buf) = observers_buf.as_deref() {
+                    self.known_observers.insert(hash_std(observers_buf));
+                }
+
                 let res =
                     if client_config.match_with(&self.configuration()) && observers_buf.is_some() {
                         let observers: E::Observers =
-                            postcard::from_bytes(observers_buf.as_ref().unwrap())?;
+                            Postcard::deserialize(observers_buf.as_ref().unwrap())?;
                         #[cfg(feature = "scalability_introspection")]
                         {
                             state.scalability_monitor_mut().testcase_with_observers += 1;
@@ -662,6 +881,10 @@ where
                     log::debug!("[{}] {} was discarded...)", process::id(), event_name);
                 }
             }
+            Event::UpdateUserStats { .. } => {
+                // user stats of other machines, pass them on to our monitor (and parent)
+                self.inner.fire(state, event)?;
+            }
             _ => {
                 return Err(Error::unknown(format!(
                     "Received illegal message that message should not have arrived: {:?}.",
@@ -0,0 +1,277 @@
+//! Client-side filters for outbound events, to keep slow links between fuzzer nodes from getting saturated.
+
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+use hashbrown::HashMap;
+use libafl_bolts::current_time;
+
+use crate::{
+    events::{Event, LogSeverity},
+    inputs::Input,
+};
+
+/// Decides which events an event manager actually sends out.
+///
+/// By default, all events pass. Objectives, as well as custom buffers, are never dropped.
+#[derive(Debug, Clone)]
+pub struct EventFilter {
+    /// Drop new testcases whose serialized event is larger than this many bytes
+    max_testcase_size: Option<usize>,
+    /// Send at most this many new testcases per second
+    max_testcases_per_sec: Option<u32>,
+    /// Drop log events below this severity
+    min_log_severity: LogSeverity,
+    /// If user stats (and performance stats) are sent at all
+    user_stats: bool,
+    /// The start of the current one-second window of the rate limit
+    window_start: Duration,
+    /// The testcases sent in the current window
+    sent_in_window: u32,
+    /// The amount of events dropped so far
+    dropped: u64,
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventFilter {
+    /// Create a new [`EventFilter`], letting all events pass
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_testcase_size: None,
+            max_testcases_per_sec: None,
+            min_log_severity: LogSeverity::Debug,
+            user_stats: true,
+            window_start: Duration::ZERO,
+            sent_in_window: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Do not broadcast new testcases whose serialized event, including the input and observers,
+    /// is larger than `max_testcase_size` bytes
+    #[must_use]
+    pub fn max_testcase_size(mut self, max_testcase_size: usize) -> Self {
+        self.max_testcase_size = Some(max_testcase_size);
+        self
+    }
+
+    /// Broadcast at most `max_testcases_per_sec` new testcases per second, drop the rest
+    #[must_use]
+    pub fn max_testcases_per_sec(mut self, max_testcases_per_sec: u32) -> Self {
+        self.max_testcases_per_sec = Some(max_testcases_per_sec);
+        self
+    }
+
+    /// Do not send log events below `min_log_severity`
+    #[must_use]
+    pub fn min_log_severity(mut self, min_log_severity: LogSeverity) -> Self {
+        self.min_log_severity = min_log_severity;
+        self
+    }
+
+    /// Send user stats, as well as performance stats, or not.
+    /// The executions are still reported, so the monitor keeps working.
+    #[must_use]
+    pub fn user_stats(mut self, user_stats: bool) -> Self {
+        self.user_stats = user_stats;
+        self
+    }
+
+    /// The amount of events dropped so far
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Check if the `event`, which serializes to `serialized_len` bytes, should be sent.
+    /// Counts the event against the rate limit, if it passes.
+    pub fn allows<I>(&mut self, event: &Event<I>, serialized_len: usize) -> bool
+    where
+        I: Input,
+    {
+        let allowed = match event {
+            Event::NewTestcase { .. } => self.allows_testcase(serialized_len),
+            Event::UpdateUserStats { .. } => self.user_stats,
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { .. } => self.user_stats,
+            Event::Log { severity_level, .. } => *severity_level >= self.min_log_severity,
+            // The announced testcase itself already passed the filter
+            Event::NewTestcaseAnnounced { .. }
+            | Event::UpdateExecStats { .. }
+            | Event::Objective { .. }
+            | Event::CustomBuf { .. }
+            | Event::ClientRestarted { .. } => true,
+        };
+        if !allowed {
+            self.dropped += 1;
+        }
+        allowed
+    }
+
+    fn allows_testcase(&mut self, serialized_len: usize) -> bool {
+        if self
+            .max_testcase_size
+            .is_some_and(|max_testcase_size| serialized_len > max_testcase_size)
+        {
+            return false;
+        }
+        if let Some(max_testcases_per_sec) = self.max_testcases_per_sec {
+            let now = current_time();
+            if now.saturating_sub(self.window_start) >= Duration::from_secs(1) {
+                self.window_start = now;
+                self.sent_in_window = 0;
+            }
+            if self.sent_in_window >= max_testcases_per_sec {
+                return false;
+            }
+            self.sent_in_window += 1;
+        }
+        true
+    }
+}
+
+/// The amount of testcase hashes a [`KnownHashes`] remembers by default
+pub(crate) const KNOWN_HASHES_CAPACITY: usize = 1 << 18;
+
+/// The hashes of the testcases an event manager already has, so it does not ask for them again.
+/// Optionally, a value is remembered alongside each hash.
+///
+/// The set is bounded, once full, the oldest hashes are forgotten first.
+/// Forgetting a hash at worst means receiving a duplicate testcase.
+#[derive(Debug, Clone)]
+pub(crate) struct KnownHashes<V = ()> {
+    hashes: HashMap<u64, V>,
+    /// The hashes, oldest first
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl<V> Default for KnownHashes<V> {
+    fn default() -> Self {
+        Self::new(KNOWN_HASHES_CAPACITY)
+    }
+}
+
+impl<V> KnownHashes<V> {
+    /// Create a new [`KnownHashes`], remembering at most `capacity` hashes
+    #[must_use]
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            hashes: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// If the hash is known
+    #[must_use]
+    pub(crate) fn contains(&self, hash: u64) -> bool {
+        self.hashes.contains_key(&hash)
+    }
+
+    /// The value remembered for the hash, if it is known
+    #[must_use]
+    #[cfg_attr(
+        not(all(unix, feature = "std", feature = "multi_machine")),
+        allow(dead_code)
+    )]
+    pub(crate) fn get(&self, hash: u64) -> Option<&V> {
+        self.hashes.get(&hash)
+    }
+
+    /// Remembers the hash with its value, forgetting the oldest one if the set is full.
+    /// Returns `false`, keeping the old value, if the hash was known already.
+    pub(crate) fn insert_with(&mut self, hash: u64, value: V) -> bool {
+        if self.contains(hash) {
+            return false;
+        }
+        self.hashes.insert(hash, value);
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+impl KnownHashes {
+    /// Remembers the hash, forgetting the oldest one if the set is full.
+    /// Returns `false` if the hash was known already.
+    pub(crate) fn insert(&mut self, hash: u64) -> bool {
+        self.insert_with(hash, ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{marker::PhantomData, time::Duration};
+
+    use crate::{
+        events::{filter::KnownHashes, Event, EventConfig, EventFilter, LogSeverity},
+        executors::ExitKind,
+        inputs::BytesInput,
+    };
+
+    fn testcase() -> Event<BytesInput> {
+        Event::NewTestcase {
+            input: BytesInput::new(vec![0; 16]),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 1,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            executions: 0,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        }
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let mut filter = EventFilter::new()
+            .max_testcase_size(100)
+            .max_testcases_per_sec(2)
+            .min_log_severity(LogSeverity::Warn);
+
+        assert!(!filter.allows(&testcase(), 101));
+        assert!(filter.allows(&testcase(), 100));
+        assert!(filter.allows(&testcase(), 100));
+        // rate limited
+        assert!(!filter.allows(&testcase(), 100));
+
+        let log = |severity_level| Event::<BytesInput>::Log {
+            severity_level,
+            message: "test".into(),
+            phantom: PhantomData,
+        };
+        assert!(!filter.allows(&log(LogSeverity::Info), 10));
+        assert!(filter.allows(&log(LogSeverity::Error), 10));
+
+        assert_eq!(filter.dropped(), 3);
+    }
+
+    #[test]
+    fn test_known_hashes() {
+        let mut known = KnownHashes::new(2);
+        assert!(known.insert(1));
+        assert!(!known.insert(1));
+        assert!(known.insert(2));
+        assert!(known.insert(3));
+
+        // The oldest hash got forgotten
+        assert!(!known.contains(1));
+        assert!(known.contains(2));
+        assert!(known.contains(3));
+        assert!(known.insert(1));
+        assert!(!known.contains(2));
+    }
+}
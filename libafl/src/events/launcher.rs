@@ -8,12 +8,15 @@
 //!
 //! To connect multiple nodes together via TCP, we can use the `remote_broker_addr`.
 //! (this requires the `llmp_bind_public` compile-time feature for `LibAFL`).
+//! The [`Launcher`] can also start the same fuzzer on other machines over SSH, see [`RemoteHost`].
 //!
 //! On `Unix` systems, the [`Launcher`] will use `fork` if the `fork` feature is used for `LibAFL`.
 //! Else, it will start subsequent nodes with the same commandline, and will set special `env` variables accordingly.
 
 use alloc::string::ToString;
 #[cfg(feature = "std")]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
 use core::time::Duration;
 use core::{
     fmt::{self, Debug, Formatter},
@@ -21,21 +24,28 @@ use core::{
 };
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use std::boxed::Box;
-#[cfg(feature = "std")]
-use std::net::SocketAddr;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
 use std::process::Stdio;
 #[cfg(all(unix, feature = "std"))]
 use std::{fs::File, os::unix::io::AsRawFd};
+#[cfg(feature = "std")]
+use std::{
+    net::SocketAddr,
+    process::{Child, Command},
+};
 
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::llmp::Brokers;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::llmp::LlmpBroker;
+#[cfg(feature = "llmp_auth")]
+use libafl_bolts::llmp::{LlmpAuth, LLMP_AUTH_KEY_ENV};
 #[cfg(all(unix, feature = "std"))]
 use libafl_bolts::os::dup2;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
-use libafl_bolts::os::startable_self;
+use libafl_bolts::os::{startable_self, supervisor::Supervisor};
+#[cfg(feature = "std")]
+use libafl_bolts::rands::random_seed;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::{
     core_affinity::get_core_ids,
@@ -43,9 +53,12 @@ use libafl_bolts::{
 };
 use libafl_bolts::{
     core_affinity::{CoreId, Cores},
+    impl_serdeany,
+    rands::StreamRand,
     shmem::ShMemProvider,
     tuples::{tuple_list, Handle},
 };
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use typed_builder::TypedBuilder;
 
@@ -65,13 +78,14 @@ use crate::observers::TimeObserver;
 use crate::state::UsesState;
 #[cfg(feature = "std")]
 use crate::{
+    common::serialization::SerializationFormat,
     events::{
         llmp::{LlmpRestartingEventManager, LlmpShouldSaveState, ManagerKind, RestartingMgr},
-        EventConfig,
+        EventConfig, EventFilter,
     },
     monitors::Monitor,
     state::{HasExecutions, State},
-    Error,
+    Error, HasMetadata,
 };
 
 /// The (internal) `env` that indicates we're running as client.
@@ -81,6 +95,89 @@ const _AFL_LAUNCHER_CLIENT: &str = "AFL_LAUNCHER_CLIENT";
 #[cfg(all(feature = "fork", unix))]
 const LIBAFL_DEBUG_OUTPUT: &str = "LIBAFL_DEBUG_OUTPUT";
 
+/// The (internal) `env` that passes the cores to a [`Launcher`] started on a [`RemoteHost`]
+#[cfg(feature = "std")]
+const LIBAFL_REMOTE_CORES: &str = "LIBAFL_REMOTE_CORES";
+
+/// The (internal) `env` that passes the address of the main broker to a [`Launcher`] started on a [`RemoteHost`]
+#[cfg(feature = "std")]
+const LIBAFL_REMOTE_BROKER_ADDR: &str = "LIBAFL_REMOTE_BROKER_ADDR";
+
+/// The (internal) `env` that passes the campaign seed to the clients, and to the [`Launcher`]s on [`RemoteHost`]s
+#[cfg(feature = "std")]
+const LIBAFL_CAMPAIGN_SEED: &str = "LIBAFL_CAMPAIGN_SEED";
+
+/// The (internal) `env` that passes the [`CampaignSeedMetadata::node`] to a [`Launcher`] started on a [`RemoteHost`]
+#[cfg(feature = "std")]
+const LIBAFL_CAMPAIGN_NODE: &str = "LIBAFL_CAMPAIGN_NODE";
+
+/// The (internal) `env` that passes the [`CampaignSeedMetadata::client`] to a client
+#[cfg(feature = "std")]
+const LIBAFL_CAMPAIGN_CLIENT: &str = "LIBAFL_CAMPAIGN_CLIENT";
+
+/// The seed of a campaign started by a [`Launcher`], and the stream of this client in it.
+///
+/// Create the rand of a client with [`CampaignSeedMetadata::rand`], instead of seeding it with the current time.
+/// The [`Launcher`] adds this metadata to the state it restores after each restart; on the first run,
+/// add [`CampaignSeedMetadata::from_env`] to the new state yourself, to have it from the start.
+/// The whole campaign can then be repeated by passing the seed it recorded
+/// (and the [`Launcher`] logged) as the `campaign_seed` of the [`Launcher`].
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CampaignSeedMetadata {
+    /// The seed of the whole campaign
+    pub seed: u64,
+    /// The machine the client runs on: `0` for the one the campaign was launched on, `i + 1` for the `i`th [`RemoteHost`]
+    pub node: u64,
+    /// The client on its machine, which is the id of the core it was launched on
+    pub client: u64,
+}
+
+impl_serdeany!(CampaignSeedMetadata);
+
+impl CampaignSeedMetadata {
+    /// Create a new [`CampaignSeedMetadata`]
+    #[must_use]
+    pub fn new(seed: u64, node: u64, client: u64) -> Self {
+        Self { seed, node, client }
+    }
+
+    /// The campaign seed passed to this client by the [`Launcher`], if we have been launched by one
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok()?.parse().ok();
+        Some(Self {
+            seed: var(LIBAFL_CAMPAIGN_SEED)?,
+            node: var(LIBAFL_CAMPAIGN_NODE).unwrap_or(0),
+            client: var(LIBAFL_CAMPAIGN_CLIENT)?,
+        })
+    }
+
+    /// The rand of this client. It is independent of the ones of all other clients in the campaign.
+    #[must_use]
+    pub fn rand<R>(&self) -> R
+    where
+        R: StreamRand,
+    {
+        R::for_stream(self.seed, self.node, self.client)
+    }
+}
+
+/// Adds the [`CampaignSeedMetadata`] passed by the [`Launcher`] to the `state` of a client, if it was restored
+#[cfg(feature = "std")]
+fn add_campaign_seed<S>(state: &mut Option<S>)
+where
+    S: HasMetadata,
+{
+    if let (Some(state), Some(campaign_seed)) = (state, CampaignSeedMetadata::from_env()) {
+        state.add_metadata(campaign_seed);
+    }
+}
+
 /// Provides a [`Launcher`], which can be used to launch a fuzzing run on a specified list of cores
 ///
 /// Will hide child output, unless the settings indicate otherwise, or the `LIBAFL_DEBUG_OUTPUT` env variable is set.
@@ -104,7 +201,8 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// The broker port to use (or to attach to, in case [`Self::spawn_broker`] is `false`)
     #[builder(default = 1337_u16)]
     broker_port: u16,
-    /// The list of cores to run on
+    /// The list of cores to run on.
+    /// On systems mixing fast and slow cores, pick the fast ones with [`Cores::of_kind`].
     cores: &'a Cores,
     /// A file name to write all client output to
     #[cfg(all(unix, feature = "std"))]
@@ -142,6 +240,41 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// Kill and respawn clients that stopped reporting for this long, see [`RestartingMgr`].
+    /// Clients report in between stages, so choose it well above the duration of the longest stage,
+    /// it is never lower than [`crate::events::MIN_CLIENT_TIMEOUT`].
+    #[builder(default = None)]
+    client_timeout: Option<Duration>,
+    /// Filter the events the clients send out, to save bandwidth
+    #[builder(default = EventFilter::new())]
+    event_filter: EventFilter,
+    /// Only announce the hashes of new testcases, and let peers fetch the ones they are missing,
+    /// see [`crate::events::LlmpEventManagerBuilder::announce_testcases`].
+    #[builder(default = false)]
+    announce_testcases: bool,
+    /// Bind each client, and the shared maps it creates, to the NUMA node of its core.
+    /// To spread clients evenly across nodes, pick the cores with [`Cores::spread_across_numa_nodes`].
+    #[builder(default = false)]
+    numa_aware: bool,
+    /// The format of the events the clients send, and of the states they keep across restarts
+    #[builder(default = SerializationFormat::Postcard)]
+    serialization_format: SerializationFormat,
+    /// Other machines to start this fuzzer on, over SSH.
+    /// Each of them runs its own broker on its own cores, connected to this [`Launcher`]'s broker.
+    #[builder(default = &[])]
+    remote_hosts: &'a [RemoteHost],
+    /// The cores passed by the [`Launcher`] that started us on a [`RemoteHost`], if any
+    #[builder(setter(skip), default = None)]
+    remote_cores: Option<Cores>,
+    /// The seed the clients derive their rands from, see [`CampaignSeedMetadata`].
+    /// If not set, a random one is picked and logged, so that the campaign can be repeated.
+    #[builder(default = None, setter(strip_option))]
+    campaign_seed: Option<u64>,
+    /// The pre-shared key the broker and the clients authenticate their tcp connections with, see [`LlmpAuth`].
+    /// If not set, the key in the [`LLMP_AUTH_KEY_ENV`] env var is used, if any.
+    #[cfg(feature = "llmp_auth")]
+    #[builder(default = None, setter(strip_option))]
+    llmp_auth: Option<LlmpAuth>,
 }
 
 impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
@@ -152,7 +285,9 @@ impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
             .field("broker_port", &self.broker_port)
             .field("core", &self.cores)
             .field("spawn_broker", &self.spawn_broker)
-            .field("remote_broker_addr", &self.remote_broker_addr);
+            .field("remote_broker_addr", &self.remote_broker_addr)
+            .field("remote_hosts", &self.remote_hosts)
+            .field("campaign_seed", &self.campaign_seed);
         #[cfg(all(unix, feature = "std"))]
         {
             dbg_struct
@@ -164,6 +299,199 @@ impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
     }
 }
 
+/// How the fuzzer binary gets onto a [`RemoteHost`]
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteBinary {
+    /// Copy the currently running binary to `remote_path` on the remote host, using `scp`, before each launch
+    Copy {
+        /// Where to put the binary on the remote host
+        remote_path: String,
+    },
+    /// The binary has already been deployed to `path` on the remote host
+    PreDeployed {
+        /// The path of the binary on the remote host
+        path: String,
+    },
+}
+
+/// A machine the [`Launcher`] starts the fuzzer on, over SSH.
+///
+/// The fuzzer is started with the same commandline as the local one, unless specified otherwise.
+/// On the remote host, the [`Launcher`] then spawns its own broker and its clients on the given cores,
+/// and connects the broker to `broker_addr` via TCP, just like with `remote_broker_addr`.
+/// The local broker thus needs to be reachable from the remote host,
+/// which requires the `llmp_bind_public` compile-time feature.
+///
+/// The remote fuzzer runs for as long as the SSH session lasts.
+/// Pass `-tt` to [`RemoteHost::ssh_arg`] so that it gets killed together with the local broker.
+///
+/// With the `llmp_auth` feature, the llmp auth key is passed on in the environment of the remote command.
+/// Other users of the remote host may see it in the process list, use a dedicated machine.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteHost {
+    /// The SSH destination, such as `user@host`
+    target: String,
+    /// The cores to use on the remote host, in the format of [`Cores::from_cmdline`]
+    cores: String,
+    /// The address of the local broker, as seen from the remote host
+    broker_addr: SocketAddr,
+    /// How to get the binary onto the remote host
+    binary: RemoteBinary,
+    /// Additional arguments for `ssh` and `scp`
+    ssh_args: Vec<String>,
+    /// The arguments to start the remote fuzzer with, instead of our own
+    args: Option<Vec<String>>,
+}
+
+#[cfg(feature = "std")]
+impl RemoteHost {
+    /// Create a new [`RemoteHost`], running clients on `cores` of the SSH destination `target`,
+    /// connecting to the local broker at `broker_addr`.
+    /// By default, the running binary is copied to `/tmp` on the remote host.
+    #[must_use]
+    pub fn new<T, C>(target: T, cores: C, broker_addr: SocketAddr) -> Self
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        let name = std::env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                exe.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "libafl_fuzzer".into());
+        Self {
+            target: target.into(),
+            cores: cores.into(),
+            broker_addr,
+            binary: RemoteBinary::Copy {
+                remote_path: format!("/tmp/{name}"),
+            },
+            ssh_args: vec![],
+            args: None,
+        }
+    }
+
+    /// Set how the binary gets onto the remote host
+    #[must_use]
+    pub fn binary(mut self, binary: RemoteBinary) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// Pass an additional argument to `ssh` and `scp`, such as `-i` or `-oPort=2222`
+    #[must_use]
+    pub fn ssh_arg<A>(mut self, arg: A) -> Self
+    where
+        A: Into<String>,
+    {
+        self.ssh_args.push(arg.into());
+        self
+    }
+
+    /// Start the remote fuzzer with `args`, instead of the arguments of the local one
+    #[must_use]
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = Some(args);
+        self
+    }
+
+    /// Copy the binary, if needed, and start the fuzzer on the remote host, as `node` of the campaign seeded with `seed`.
+    /// Returns the running `ssh` process.
+    fn spawn(&self, seed: u64, node: u64) -> Result<Child, Error> {
+        let remote_exe = match &self.binary {
+            RemoteBinary::Copy { remote_path } => {
+                let status = Command::new("scp")
+                    .args(&self.ssh_args)
+                    .arg(std::env::current_exe()?)
+                    .arg(format!("{}:{remote_path}", self.target))
+                    .status()?;
+                if !status.success() {
+                    return Err(Error::unknown(format!(
+                        "Failed to copy the fuzzer to {} ({status})",
+                        self.target
+                    )));
+                }
+                remote_path
+            }
+            RemoteBinary::PreDeployed { path } => path,
+        };
+
+        let mut command = format!(
+            "{LIBAFL_REMOTE_CORES}={} {LIBAFL_REMOTE_BROKER_ADDR}={} {LIBAFL_CAMPAIGN_SEED}={seed} {LIBAFL_CAMPAIGN_NODE}={node} {}",
+            shell_quote(&self.cores),
+            shell_quote(&self.broker_addr.to_string()),
+            shell_quote(remote_exe)
+        );
+        #[cfg(feature = "llmp_auth")]
+        if let Ok(key) = std::env::var(LLMP_AUTH_KEY_ENV) {
+            command = format!("{LLMP_AUTH_KEY_ENV}={} {command}", shell_quote(&key));
+        }
+        let args = self
+            .args
+            .clone()
+            .unwrap_or_else(|| std::env::args().skip(1).collect());
+        for arg in &args {
+            command.push(' ');
+            command.push_str(&shell_quote(arg));
+        }
+
+        log::info!("spawning on {} with cores {}", self.target, self.cores);
+        Ok(Command::new("ssh")
+            .args(&self.ssh_args)
+            .arg(&self.target)
+            .arg(command)
+            .spawn()?)
+    }
+}
+
+/// Quote `arg` for a POSIX shell
+#[cfg(feature = "std")]
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// The running `ssh` processes of the [`RemoteHost`]s.
+/// They are killed when dropped, so a launch failing halfway does not leave remote fuzzers behind.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+struct RemoteHostChildren(Vec<Child>);
+
+#[cfg(feature = "std")]
+impl RemoteHostChildren {
+    /// Forget about the processes, in a forked client, as they belong to the parent
+    #[cfg(all(unix, feature = "fork"))]
+    fn disown(&mut self) {
+        self.0.clear();
+    }
+
+    /// Wait for all remote fuzzers to exit
+    fn wait(mut self) -> Result<(), Error> {
+        for child in &mut self.0 {
+            let status = child.wait()?;
+            if !status.success() {
+                log::info!("Remote host {child:?} exited with {status:?}");
+            }
+        }
+        self.0.clear();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for RemoteHostChildren {
+    fn drop(&mut self) {
+        for child in &mut self.0 {
+            if let Err(err) = child.kill().and_then(|()| child.wait().map(drop)) {
+                log::warn!("Failed to kill remote host {child:?}: {err}");
+            }
+        }
+    }
+}
+
 impl<'a, CF, MT, SP> Launcher<'a, CF, MT, SP>
 where
     MT: Monitor + Clone,
@@ -173,7 +501,7 @@ where
     #[cfg(all(unix, feature = "std", feature = "fork"))]
     pub fn launch<S>(&mut self) -> Result<(), Error>
     where
-        S: State + HasExecutions,
+        S: State + HasExecutions + HasMetadata,
         CF: FnOnce(Option<S>, LlmpRestartingEventManager<(), S, SP>, CoreId) -> Result<(), Error>,
     {
         Self::launch_with_hooks(self, tuple_list!())
@@ -184,7 +512,7 @@ where
     #[allow(unused_mut, clippy::match_wild_err_arm)]
     pub fn launch<S>(&mut self) -> Result<(), Error>
     where
-        S: State + HasExecutions,
+        S: State + HasExecutions + HasMetadata,
         CF: FnOnce(Option<S>, LlmpRestartingEventManager<(), S, SP>, CoreId) -> Result<(), Error>,
     {
         Self::launch_with_hooks(self, tuple_list!())
@@ -197,17 +525,80 @@ where
     MT: Monitor + Clone,
     SP: ShMemProvider,
 {
+    /// The cores to spawn clients on, either the configured ones, or the ones passed by a remote [`Launcher`]
+    fn cores(&self) -> &Cores {
+        self.remote_cores.as_ref().unwrap_or(self.cores)
+    }
+
+    /// Puts the configured [`LlmpAuth`] key into the env, where the broker, the clients,
+    /// and all child processes we spawn pick it up
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    fn export_llmp_auth(&self) -> Result<(), Error> {
+        #[cfg(feature = "llmp_auth")]
+        if let Some(llmp_auth) = &self.llmp_auth {
+            llmp_auth.export_to_env()?;
+        }
+        Ok(())
+    }
+
+    /// Take over the campaign seed passed by the [`Launcher`] that started us on a [`RemoteHost`].
+    /// Else, pick the configured or a random one. Either way, pass it on to the clients.
+    fn init_campaign_seed(&mut self) -> Result<u64, Error> {
+        let seed = if let Ok(seed) = std::env::var(LIBAFL_CAMPAIGN_SEED) {
+            seed.parse().map_err(|err| {
+                Error::illegal_argument(format!("Invalid {LIBAFL_CAMPAIGN_SEED} {seed}: {err}"))
+            })?
+        } else {
+            let seed = self.campaign_seed.unwrap_or_else(random_seed);
+            std::env::set_var(LIBAFL_CAMPAIGN_SEED, seed.to_string());
+            seed
+        };
+        log::info!("campaign seed: {seed}");
+        self.campaign_seed = Some(seed);
+        Ok(seed)
+    }
+
+    /// If we have been started on a [`RemoteHost`] by another [`Launcher`], take over the cores and
+    /// the broker address it passed. Else, start the fuzzer on all configured remote hosts.
+    fn spawn_remote_hosts(&mut self) -> Result<RemoteHostChildren, Error> {
+        let seed = self.init_campaign_seed()?;
+
+        if let Ok(cores) = std::env::var(LIBAFL_REMOTE_CORES) {
+            self.remote_cores = Some(Cores::from_cmdline(&cores)?);
+            let broker_addr = std::env::var(LIBAFL_REMOTE_BROKER_ADDR).map_err(|_| {
+                Error::illegal_argument(format!(
+                    "{LIBAFL_REMOTE_CORES} is set, but {LIBAFL_REMOTE_BROKER_ADDR} is missing"
+                ))
+            })?;
+            self.remote_broker_addr = Some(broker_addr.parse().map_err(|err| {
+                Error::illegal_argument(format!(
+                    "Invalid {LIBAFL_REMOTE_BROKER_ADDR} {broker_addr}: {err}"
+                ))
+            })?);
+            return Ok(RemoteHostChildren::default());
+        }
+
+        let mut children = RemoteHostChildren::default();
+        for (host, node) in self.remote_hosts.iter().zip(1..) {
+            children.0.push(host.spawn(seed, node)?);
+        }
+        Ok(children)
+    }
+
     /// Launch the broker and the clients and fuzz with a user-supplied hook
     #[cfg(all(unix, feature = "std", feature = "fork"))]
     #[allow(clippy::similar_names)]
     #[allow(clippy::too_many_lines)]
     pub fn launch_with_hooks<EMH, S>(&mut self, hooks: EMH) -> Result<(), Error>
     where
-        S: State + HasExecutions,
+        S: State + HasExecutions + HasMetadata,
         EMH: EventManagerHooksTuple<S> + Clone + Copy,
         CF: FnOnce(Option<S>, LlmpRestartingEventManager<EMH, S, SP>, CoreId) -> Result<(), Error>,
     {
-        if self.cores.ids.is_empty() {
+        self.export_llmp_auth()?;
+        let mut remote_handles = self.spawn_remote_hosts()?;
+
+        if self.cores().ids.is_empty() {
             return Err(Error::illegal_argument(
                 "No cores to spawn on given, cannot launch anything.",
             ));
@@ -223,7 +614,7 @@ where
         let num_cores = core_ids.len();
         let mut handles = vec![];
 
-        log::info!("spawning on cores: {:?}", self.cores);
+        log::info!("spawning on cores: {:?}", self.cores());
 
         self.opened_stdout_file = self
             .stdout_file
@@ -238,7 +629,7 @@ where
         // Spawn clients
         let mut index = 0_u64;
         for (id, bind_to) in core_ids.iter().enumerate().take(num_cores) {
-            if self.cores.ids.iter().any(|&x| x == id.into()) {
+            if self.cores().ids.iter().any(|&x| x == id.into()) {
                 index += 1;
                 self.shmem_provider.pre_fork()?;
                 // # Safety
@@ -251,10 +642,12 @@ where
                         log::info!("child spawned and bound to core {id}");
                     }
                     ForkResult::Child => {
+                        remote_handles.disown();
                         // # Safety
                         // A call to `getpid` is safe.
                         log::info!("{:?} PostFork", unsafe { libc::getpid() });
                         self.shmem_provider.post_fork(true)?;
+                        std::env::set_var(LIBAFL_CAMPAIGN_CLIENT, id.to_string());
 
                         #[cfg(feature = "std")]
                         std::thread::sleep(Duration::from_millis(index * self.launch_delay));
@@ -280,9 +673,15 @@ where
                             })
                             .configuration(self.configuration)
                             .serialize_state(self.serialize_state)
+                            .client_timeout(self.client_timeout)
+                            .event_filter(self.event_filter.clone())
+                            .announce_testcases(self.announce_testcases)
+                            .numa_aware(self.numa_aware)
+                            .serialization_format(self.serialization_format)
                             .hooks(hooks);
                         let builder = builder.time_ref(self.time_ref.clone());
-                        let (state, mgr) = builder.build().launch()?;
+                        let (mut state, mgr) = builder.build().launch()?;
+                        add_campaign_seed(&mut state);
 
                         return (self.run_client.take().unwrap())(state, mgr, *bind_to);
                     }
@@ -301,9 +700,14 @@ where
                 .broker_port(self.broker_port)
                 .kind(ManagerKind::Broker)
                 .remote_broker_addr(self.remote_broker_addr)
-                .exit_cleanly_after(Some(NonZeroUsize::try_from(self.cores.ids.len()).unwrap()))
+                .exit_cleanly_after(Some(
+                    NonZeroUsize::try_from(self.cores().ids.len()).unwrap(),
+                ))
                 .configuration(self.configuration)
                 .serialize_state(self.serialize_state)
+                .client_timeout(self.client_timeout)
+                .event_filter(self.event_filter.clone())
+                .announce_testcases(self.announce_testcases)
                 .hooks(hooks);
 
             let builder = builder.time_ref(self.time_ref.clone());
@@ -318,6 +722,7 @@ where
                     libc::kill(*handle, libc::SIGINT);
                 }
             }
+            drop(remote_handles);
         } else {
             for handle in &handles {
                 let mut status = 0;
@@ -329,6 +734,7 @@ where
                     }
                 }
             }
+            remote_handles.wait()?;
         }
 
         Ok(())
@@ -339,14 +745,16 @@ where
     #[allow(unused_mut, clippy::match_wild_err_arm)]
     pub fn launch_with_hooks<EMH, S>(&mut self, hooks: EMH) -> Result<(), Error>
     where
-        S: State + HasExecutions,
+        S: State + HasExecutions + HasMetadata,
         EMH: EventManagerHooksTuple<S> + Clone + Copy,
         CF: FnOnce(Option<S>, LlmpRestartingEventManager<EMH, S, SP>, CoreId) -> Result<(), Error>,
     {
-        use libafl_bolts::core_affinity;
+        use libafl_bolts::{core_affinity, os::supervisor::ChildExit};
 
+        self.export_llmp_auth()?;
         let is_client = std::env::var(_AFL_LAUNCHER_CLIENT);
 
+        let remote_handles;
         let mut handles = match is_client {
             Ok(core_conf) => {
                 let core_id = core_conf.parse()?;
@@ -360,23 +768,33 @@ where
                     })
                     .configuration(self.configuration)
                     .serialize_state(self.serialize_state)
+                    .client_timeout(self.client_timeout)
+                    .event_filter(self.event_filter.clone())
+                    .announce_testcases(self.announce_testcases)
+                    .numa_aware(self.numa_aware)
+                    .serialization_format(self.serialization_format)
                     .hooks(hooks);
 
                 let builder = builder.time_ref(self.time_ref.clone());
 
-                let (state, mgr) = builder.build().launch()?;
+                let (mut state, mgr) = builder.build().launch()?;
+                add_campaign_seed(&mut state);
 
                 return (self.run_client.take().unwrap())(state, mgr, CoreId(core_id));
             }
             Err(std::env::VarError::NotPresent) => {
                 // I am a broker
                 // before going to the broker loop, spawn n clients
+                remote_handles = self.spawn_remote_hosts()?;
 
                 let core_ids = core_affinity::get_core_ids().unwrap();
                 let num_cores = core_ids.len();
                 let mut handles = vec![];
+                // If the broker runs here, it cleans up the clients, including the children they respawn.
+                // Otherwise, leave them in our process group, so they get the Ctrl-C from the terminal.
+                let supervisor = Supervisor::new().kill_tree(self.spawn_broker);
 
-                log::info!("spawning on cores: {:?}", self.cores);
+                log::info!("spawning on cores: {:?}", self.cores());
 
                 let debug_output = std::env::var("LIBAFL_DEBUG_OUTPUT").is_ok();
                 #[cfg(all(feature = "std", unix))]
@@ -401,7 +819,7 @@ where
                 }
                 //spawn clients
                 for (id, _) in core_ids.iter().enumerate().take(num_cores) {
-                    if self.cores.ids.iter().any(|&x| x == id.into()) {
+                    if self.cores().ids.iter().any(|&x| x == id.into()) {
                         // Forward own stdio to child processes, if requested by user
                         let (mut stdout, mut stderr) = (Stdio::null(), Stdio::null());
                         #[cfg(all(feature = "std", unix))]
@@ -416,14 +834,14 @@ where
                         std::thread::sleep(Duration::from_millis(id as u64 * self.launch_delay));
 
                         std::env::set_var(_AFL_LAUNCHER_CLIENT, id.to_string());
+                        std::env::set_var(LIBAFL_CAMPAIGN_CLIENT, id.to_string());
                         let mut child = startable_self()?;
-                        let child = (if debug_output {
+                        let child = supervisor.spawn(if debug_output {
                             &mut child
                         } else {
                             child.stdout(stdout);
                             child.stderr(stderr)
-                        })
-                        .spawn()?;
+                        })?;
                         handles.push(child);
                     }
                 }
@@ -435,7 +853,7 @@ where
 
         // It's fine to check this after the client spawn loop - since we won't have spawned any clients...
         // Doing it later means one less check in each spawned process.
-        if self.cores.ids.is_empty() {
+        if self.cores().ids.is_empty() {
             return Err(Error::illegal_argument(
                 "No cores to spawn on given, cannot launch anything.",
             ));
@@ -451,9 +869,14 @@ where
                 .broker_port(self.broker_port)
                 .kind(ManagerKind::Broker)
                 .remote_broker_addr(self.remote_broker_addr)
-                .exit_cleanly_after(Some(NonZeroUsize::try_from(self.cores.ids.len()).unwrap()))
+                .exit_cleanly_after(Some(
+                    NonZeroUsize::try_from(self.cores().ids.len()).unwrap(),
+                ))
                 .configuration(self.configuration)
                 .serialize_state(self.serialize_state)
+                .client_timeout(self.client_timeout)
+                .event_filter(self.event_filter.clone())
+                .announce_testcases(self.announce_testcases)
                 .hooks(hooks);
 
             let builder = builder.time_ref(self.time_ref.clone());
@@ -464,14 +887,17 @@ where
             for handle in &mut handles {
                 handle.kill()?;
             }
+            drop(remote_handles);
         } else {
             log::info!("Not spawning broker (spawn_broker is false). Waiting for fuzzer children to exit...");
-            for handle in &mut handles {
-                let ecode = handle.wait()?;
-                if !ecode.success() {
-                    log::info!("Client with handle {handle:?} exited with {ecode:?}");
+            for handle in handles {
+                let id = handle.id();
+                let outcome = handle.wait()?;
+                if outcome.exit != ChildExit::Ok {
+                    log::info!("Client with pid {id} exited with {:?}", outcome.exit);
                 }
             }
+            remote_handles.wait()?;
         }
 
         Ok(())
@@ -543,6 +969,18 @@ pub struct CentralizedLauncher<'a, CF, MF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// Kill and respawn clients that stopped reporting for this long, see [`RestartingMgr`].
+    /// Clients report in between stages, so choose it well above the duration of the longest stage,
+    /// it is never lower than [`crate::events::MIN_CLIENT_TIMEOUT`].
+    #[builder(default = None)]
+    client_timeout: Option<Duration>,
+    /// Filter the events the clients send out, to save bandwidth
+    #[builder(default = EventFilter::new())]
+    event_filter: EventFilter,
+    /// Let the secondary nodes query the main node before sending new testcases,
+    /// see [`crate::events::CentralizedEventManagerBuilder::novelty_filter`].
+    #[builder(default = false)]
+    novelty_filter: bool,
 }
 
 #[cfg(all(unix, feature = "std", feature = "fork"))]
@@ -596,6 +1034,8 @@ where
                 })
                 .configuration(centralized_launcher.configuration)
                 .serialize_state(centralized_launcher.serialize_state)
+                .client_timeout(centralized_launcher.client_timeout)
+                .event_filter(centralized_launcher.event_filter.clone())
                 .hooks(tuple_list!());
 
             let builder = builder.time_ref(centralized_launcher.time_obs.clone());
@@ -723,7 +1163,8 @@ where
                             let (state, mgr) =
                                 secondary_inner_mgr_builder.take().unwrap()(self, *bind_to)?;
 
-                            let centralized_builder = CentralizedEventManager::builder();
+                            let centralized_builder = CentralizedEventManager::builder()
+                                .novelty_filter(self.novelty_filter);
 
                             let c_mgr = centralized_builder.build_on_port(
                                 mgr,
@@ -787,15 +1228,16 @@ where
         if self.spawn_broker {
             log::info!("I am broker!!.");
 
+            let mut std_hook = StdLlmpEventHook::<S::Input, MT>::new(self.monitor.clone())?;
+            if let Some(client_timeout) = self.client_timeout {
+                std_hook = std_hook.with_client_timeout(client_timeout);
+            }
+
             #[cfg(not(feature = "multi_machine"))]
-            let llmp_hook =
-                tuple_list!(StdLlmpEventHook::<S::Input, MT>::new(self.monitor.clone())?);
+            let llmp_hook = tuple_list!(std_hook);
 
             #[cfg(feature = "multi_machine")]
-            let llmp_hook = tuple_list!(
-                StdLlmpEventHook::<S::Input, MT>::new(self.monitor.clone())?,
-                multi_machine_sender_hook,
-            );
+            let llmp_hook = tuple_list!(std_hook, multi_machine_sender_hook);
 
             let mut broker = LlmpBroker::create_attach_to_tcp(
                 self.shmem_provider.clone(),
@@ -838,3 +1280,35 @@ where
         Err(Error::shutting_down())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_shell_quote() {
+        use super::shell_quote;
+
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("plain"), "'plain'");
+        assert_eq!(shell_quote("with spaces"), "'with spaces'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("\"$HOME\""), "'\"$HOME\"'");
+    }
+    #[cfg(all(unix, feature = "std"))]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_remote_host_children_killed_on_drop() {
+        use std::process::Command;
+
+        use super::RemoteHostChildren;
+
+        let child = Command::new("sleep").arg("60").spawn().unwrap();
+        let pid = libc::pid_t::try_from(child.id()).unwrap();
+        drop(RemoteHostChildren(vec![child]));
+        // The child was killed and reaped, so its pid is gone
+        assert_eq!(unsafe { libc::kill(pid, 0) }, -1);
+
+        let child = Command::new("true").spawn().unwrap();
+        RemoteHostChildren(vec![child]).wait().unwrap();
+    }
+}
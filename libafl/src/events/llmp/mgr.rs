@@ -1,28 +1,32 @@
 /// An [`EventManager`] that forwards all events to other attached fuzzers on shared maps or via tcp,
 /// using low-level message passing, [`llmp`].
 
+// With testcase announcements enabled, a client that found a new testcase only broadcasts the hashes of the input
+// and its observers at first, in an `Event::NewTestcaseAnnounced`, and keeps the full testcase around.
+// Peers that know neither hash ask for the full testcase with a `FetchMsg::Request`.
+// The announcing client answers the first request with a single broadcast `FetchMsg::Testcase`,
+// which all peers that asked for it pick up.
+
 #[cfg(feature = "std")]
 use alloc::string::ToString;
-use alloc::{boxed::Box, vec::Vec};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::{marker::PhantomData, time::Duration};
 #[cfg(feature = "std")]
 use std::net::TcpStream;
 
+use hashbrown::HashSet;
 #[cfg(feature = "llmp_compression")]
+use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
-};
-use libafl_bolts::{
-    current_time,
-    llmp::{LlmpClient, LlmpClientDescription},
+    current_time, hash_std,
+    llmp::{LlmpClient, LlmpClientDescription, Tag},
     shmem::{NopShMemProvider, ShMemProvider},
     tuples::Handle,
     ClientId,
 };
 #[cfg(feature = "std")]
 use libafl_bolts::{
-    llmp::{recv_tcp_msg, send_tcp_msg, TcpRequest, TcpResponse},
+    llmp::{recv_tcp_msg, send_tcp_request, TcpRequest, TcpResponse},
     IP_LOCALHOST,
 };
 use serde::{Deserialize, Serialize};
@@ -30,11 +34,13 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "llmp_compression")]
 use crate::events::llmp::COMPRESS_THRESHOLD;
 use crate::{
+    common::serialization::{hash_serialized, Postcard, SerdeFormat, SerializationFormat},
     events::{
-        llmp::{LLMP_TAG_EVENT_TO_BOTH, _LLMP_TAG_EVENT_TO_BROKER},
+        filter::KnownHashes,
+        llmp::{_LLMP_TAG_EVENT_TO_BROKER, LLMP_TAG_EVENT_TO_BOTH, LLMP_TAG_FETCH},
         AdaptiveSerializer, CustomBufEventResult, CustomBufHandlerFn, Event, EventConfig,
-        EventFirer, EventManager, EventManagerHooksTuple, EventManagerId, EventProcessor,
-        EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
+        EventFilter, EventFirer, EventManager, EventManagerHooksTuple, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
     },
     executors::{Executor, HasObservers},
     fuzzer::{Evaluator, EvaluatorObservers, ExecutionProcessor},
@@ -44,6 +50,27 @@ use crate::{
     Error, HasMetadata,
 };
 
+/// The maximum amount of announced testcases a client keeps around for peers to fetch,
+/// and of testcases it waits for at once. If more are pending, the oldest ones are dropped.
+const ANNOUNCE_MAX_PENDING: usize = 1024;
+
+/// The messages to fetch announced testcases
+#[derive(Serialize, Deserialize, Debug)]
+enum FetchMsg {
+    /// Asks the client that announced the input with this hash for the full testcase
+    Request {
+        /// The hash of the serialized input
+        input_hash: u64,
+    },
+    /// The full testcase, for all clients that asked for it
+    Testcase {
+        /// The hash of the serialized input
+        input_hash: u64,
+        /// The serialized [`Event::NewTestcase`]
+        event: Vec<u8>,
+    },
+}
+
 /// An [`EventManager`] that forwards all events to other attached fuzzers on shared maps or via tcp,
 /// using low-level message passing, `llmp`.
 pub struct LlmpEventManager<EMH, S, SP>
@@ -53,10 +80,24 @@ where
 {
     /// We only send 1 testcase for every `throttle` second
     pub(crate) throttle: Option<Duration>,
+    /// Decides which events we send out at all
+    filter: EventFilter,
     /// Treat the incoming testcase as interesting always without evaluating them
     always_interesting: bool,
+    /// Only announce the hashes of new testcases, and send them in full when a peer asks for them
+    announce_testcases: bool,
+    /// Our announced testcases, serialized, with the hashes of their inputs
+    announced: VecDeque<(u64, Vec<u8>)>,
+    /// The hashes of the inputs we asked our peers for, but did not receive yet
+    wanted: HashSet<u64>,
+    /// The hashes of all inputs we found or received
+    known_inputs: KnownHashes,
+    /// The hashes of the observers of all testcases we found or received
+    known_observers: KnownHashes,
     /// We sent last message at `last_sent`
     last_sent: Duration,
+    /// The format of the events we send, recorded in the message flags
+    serialization: SerializationFormat,
     hooks: EMH,
     /// The LLMP client for inter process communication
     llmp: LlmpClient<SP>,
@@ -85,11 +126,14 @@ impl LlmpEventManager<(), NopState<NopInput>, NopShMemProvider> {
 }
 
 /// Builder for `LlmpEventManager`
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct LlmpEventManagerBuilder<EMH> {
     throttle: Option<Duration>,
+    filter: EventFilter,
     hooks: EMH,
     always_interesting: bool,
+    announce_testcases: bool,
+    serialization: SerializationFormat,
 }
 
 impl Default for LlmpEventManagerBuilder<()> {
@@ -104,8 +148,11 @@ impl LlmpEventManagerBuilder<()> {
     pub fn new() -> Self {
         Self {
             throttle: None,
+            filter: EventFilter::new(),
             hooks: (),
             always_interesting: false,
+            announce_testcases: false,
+            serialization: SerializationFormat::Postcard,
         }
     }
 
@@ -113,8 +160,11 @@ impl LlmpEventManagerBuilder<()> {
     pub fn hooks<EMH>(self, hooks: EMH) -> LlmpEventManagerBuilder<EMH> {
         LlmpEventManagerBuilder {
             throttle: self.throttle,
+            filter: self.filter,
             hooks,
             always_interesting: self.always_interesting,
+            announce_testcases: self.announce_testcases,
+            serialization: self.serialization,
         }
     }
 
@@ -123,13 +173,24 @@ impl LlmpEventManagerBuilder<()> {
     pub fn always_interesting(self, always_interesting: bool) -> LlmpEventManagerBuilder<()> {
         LlmpEventManagerBuilder {
             throttle: self.throttle,
+            filter: self.filter,
             hooks: self.hooks,
             always_interesting,
+            announce_testcases: self.announce_testcases,
+            serialization: self.serialization,
         }
     }
 }
 
 impl<EMH> LlmpEventManagerBuilder<EMH> {
+    /// Serialize the events this manager sends in `serialization`, for example as JSON to debug them.
+    /// Received events are deserialized in whatever format their sender used.
+    #[must_use]
+    pub fn serialization_format(mut self, serialization: SerializationFormat) -> Self {
+        self.serialization = serialization;
+        self
+    }
+
     /// Change the sampling rate
     #[must_use]
     pub fn throttle(mut self, throttle: Duration) -> Self {
@@ -137,6 +198,23 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
         self
     }
 
+    /// Filter the events this manager sends out, to save bandwidth
+    #[must_use]
+    pub fn filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Only broadcast the hashes of the input and the observers of a new testcase at first.
+    /// Peers that know neither fetch the full testcase, so large fleets of similar fuzzers
+    /// do not transfer the same inputs over and over again.
+    /// Announced testcases are lost if this client restarts before a peer fetched them.
+    #[must_use]
+    pub fn announce_testcases(mut self, announce_testcases: bool) -> Self {
+        self.announce_testcases = announce_testcases;
+        self
+    }
+
     /// Create a manager from a raw LLMP client
     pub fn build_from_client<S, SP>(
         self,
@@ -150,9 +228,16 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
     {
         Ok(LlmpEventManager {
             throttle: self.throttle,
+            filter: self.filter,
             last_sent: Duration::from_secs(0),
+            serialization: self.serialization,
             hooks: self.hooks,
             always_interesting: self.always_interesting,
+            announce_testcases: self.announce_testcases,
+            announced: VecDeque::new(),
+            wanted: HashSet::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
             llmp,
             #[cfg(feature = "llmp_compression")]
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
@@ -186,9 +271,16 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
         let llmp = LlmpClient::create_attach_to_tcp(shmem_provider, port)?;
         Ok(LlmpEventManager {
             throttle: self.throttle,
+            filter: self.filter,
             last_sent: Duration::from_secs(0),
+            serialization: self.serialization,
             hooks: self.hooks,
             always_interesting: self.always_interesting,
+            announce_testcases: self.announce_testcases,
+            announced: VecDeque::new(),
+            wanted: HashSet::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
             llmp,
             #[cfg(feature = "llmp_compression")]
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
@@ -220,9 +312,16 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
         let llmp = LlmpClient::on_existing_from_env(shmem_provider, env_name)?;
         Ok(LlmpEventManager {
             throttle: self.throttle,
+            filter: self.filter,
             last_sent: Duration::from_secs(0),
+            serialization: self.serialization,
             hooks: self.hooks,
             always_interesting: self.always_interesting,
+            announce_testcases: self.announce_testcases,
+            announced: VecDeque::new(),
+            wanted: HashSet::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
             llmp,
             #[cfg(feature = "llmp_compression")]
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
@@ -252,9 +351,16 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
         let llmp = LlmpClient::existing_client_from_description(shmem_provider, description)?;
         Ok(LlmpEventManager {
             throttle: self.throttle,
+            filter: self.filter,
             last_sent: Duration::from_secs(0),
+            serialization: self.serialization,
             hooks: self.hooks,
             always_interesting: self.always_interesting,
+            announce_testcases: self.announce_testcases,
+            announced: VecDeque::new(),
+            wanted: HashSet::new(),
+            known_inputs: KnownHashes::default(),
+            known_observers: KnownHashes::default(),
             llmp,
             #[cfg(feature = "llmp_compression")]
             compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
@@ -354,18 +460,15 @@ where
             return Ok(());
         };
         // The broker tells us hello we don't care we just tell it our client died
-        let TcpResponse::BrokerConnectHello {
-            broker_shmem_description: _,
-            hostname: _,
-        } = recv_tcp_msg(&mut stream)?.try_into()?
-        else {
+        let hello: TcpResponse = recv_tcp_msg(&mut stream)?.try_into()?;
+        let TcpResponse::BrokerConnectHello { .. } = hello else {
             return Err(Error::illegal_state(
                 "Received unexpected Broker Hello".to_string(),
             ));
         };
         let msg = TcpRequest::ClientQuit { client_id };
         // Send this mesasge off and we are leaving.
-        match send_tcp_msg(&mut stream, &msg) {
+        match send_tcp_request(&mut stream, &hello, &msg) {
             Ok(_) => (),
             Err(e) => log::error!("Failed to send tcp message {:#?}", e),
         }
@@ -424,6 +527,10 @@ where
                 #[cfg(feature = "std")]
                 log::debug!("[{}] Received new Testcase {evt_name} from {client_id:?} ({client_config:?}, forward {forward_id:?})", std::process::id());
 
+                if self.announce_testcases {
+                    self.remember_testcase(&input, observers_buf.as_deref())?;
+                }
+
                 if self.always_interesting {
                     let item = fuzzer.add_input(state, executor, self, input)?;
                     log::debug!("Added received Testcase as item #{item}");
@@ -433,7 +540,7 @@ where
                     {
                         let start = current_time();
                         let observers: E::Observers =
-                            postcard::from_bytes(observers_buf.as_ref().unwrap())?;
+                            Postcard::deserialize(observers_buf.as_ref().unwrap())?;
                         {
                             self.deserialization_time = current_time() - start;
                         }
@@ -460,6 +567,26 @@ where
                     }
                 }
             }
+            Event::NewTestcaseAnnounced {
+                input_hash,
+                observers_hash,
+                client_config,
+                ..
+            } => {
+                let known_observers = !self.always_interesting
+                    && client_config.match_with(&self.configuration)
+                    && observers_hash.is_some_and(|hash| self.known_observers.contains(hash));
+                // The hashes are only remembered once the testcase arrived, so a lost fetch can be retried
+                if !self.known_inputs.contains(input_hash)
+                    && !self.wanted.contains(&input_hash)
+                    && !known_observers
+                {
+                    log::debug!("Fetching announced {evt_name} from {client_id:?}");
+                    self.fetch(input_hash)?;
+                } else {
+                    log::debug!("Announced {evt_name} from {client_id:?} is not novel, skipping");
+                }
+            }
             Event::CustomBuf { tag, buf } => {
                 for handler in &mut self.custom_buf_handlers {
                     if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
@@ -487,6 +614,131 @@ impl<EMH, S: State, SP: ShMemProvider> LlmpEventManager<EMH, S, SP> {
     pub fn send_exiting(&mut self) -> Result<(), Error> {
         self.llmp.sender_mut().send_exiting()
     }
+
+    /// Serialize and send an event to the broker, without the need for a state
+    pub(crate) fn send_event(&mut self, event: &Event<S::Input>) -> Result<(), Error> {
+        let serialized = self.serialization.serialize(event)?;
+        if !self.filter.allows(event, serialized.len()) {
+            return Ok(());
+        }
+        match event {
+            // Forwarded testcases are always sent in full
+            Event::NewTestcase {
+                input,
+                observers_buf,
+                corpus_size,
+                client_config,
+                time,
+                executions,
+                forward_id: None,
+                ..
+            } if self.announce_testcases => {
+                let (input_hash, observers_hash) =
+                    self.remember_testcase(input, observers_buf.as_deref())?;
+                let announcement = Event::<S::Input>::NewTestcaseAnnounced {
+                    input_hash,
+                    observers_hash,
+                    corpus_size: *corpus_size,
+                    client_config: *client_config,
+                    time: *time,
+                    executions: *executions,
+                    phantom: PhantomData,
+                };
+                self.announced.push_back((input_hash, serialized));
+                if self.announced.len() > ANNOUNCE_MAX_PENDING {
+                    self.announced.pop_front();
+                }
+                self.send_serialized(
+                    LLMP_TAG_EVENT_TO_BOTH,
+                    &self.serialization.serialize(&announcement)?,
+                )
+            }
+            _ => self.send_serialized(LLMP_TAG_EVENT_TO_BOTH, &serialized),
+        }
+    }
+
+    /// The format of the events this manager sends
+    #[must_use]
+    pub fn serialization_format(&self) -> SerializationFormat {
+        self.serialization
+    }
+
+    /// Send an already serialized message with the given tag
+    #[cfg(feature = "llmp_compression")]
+    fn send_serialized(&mut self, tag: Tag, serialized: &[u8]) -> Result<(), Error> {
+        let flags = self.serialization.llmp_flags();
+
+        match self.compressor.maybe_compress(serialized) {
+            Some(comp_buf) => {
+                self.llmp
+                    .send_buf_with_flags(tag, flags | LLMP_FLAG_COMPRESSED, &comp_buf)?;
+            }
+            None => {
+                self.llmp.send_buf_with_flags(tag, flags, serialized)?;
+            }
+        }
+        self.last_sent = current_time();
+
+        Ok(())
+    }
+
+    /// Send an already serialized message with the given tag
+    #[cfg(not(feature = "llmp_compression"))]
+    fn send_serialized(&mut self, tag: Tag, serialized: &[u8]) -> Result<(), Error> {
+        self.llmp
+            .send_buf_with_flags(tag, self.serialization.llmp_flags(), serialized)?;
+        Ok(())
+    }
+
+    /// Remembers the hashes of a testcase we found or received, so we do not fetch it again.
+    /// Returns the hashes of the input and the observers.
+    fn remember_testcase(
+        &mut self,
+        input: &S::Input,
+        observers_buf: Option<&[u8]>,
+    ) -> Result<(u64, Option<u64>), Error> {
+        let input_hash = hash_serialized(input)?;
+        let observers_hash = observers_buf.map(hash_std);
+        self.known_inputs.insert(input_hash);
+        if let Some(observers_hash) = observers_hash {
+            self.known_observers.insert(observers_hash);
+        }
+        Ok((input_hash, observers_hash))
+    }
+
+    /// Asks the peers for the announced testcase with the given input hash
+    fn fetch(&mut self, input_hash: u64) -> Result<(), Error> {
+        if self.wanted.len() >= ANNOUNCE_MAX_PENDING {
+            log::debug!("Too many announced testcases did not arrive, giving up on them");
+            self.wanted.clear();
+        }
+        self.wanted.insert(input_hash);
+        self.send_serialized(
+            LLMP_TAG_FETCH,
+            &self
+                .serialization
+                .serialize(&FetchMsg::Request { input_hash })?,
+        )
+    }
+
+    /// Sends the full testcase for the given input hash, if we announced it.
+    /// Each testcase is sent only once, all peers that asked for it receive the same message.
+    fn answer_fetch(&mut self, input_hash: u64) -> Result<(), Error> {
+        let Some(idx) = self
+            .announced
+            .iter()
+            .position(|(hash, _)| *hash == input_hash)
+        else {
+            return Ok(());
+        };
+        let (_, event) = self.announced.remove(idx).unwrap();
+        self.send_serialized(
+            LLMP_TAG_FETCH,
+            &self
+                .serialization
+                .serialize(&FetchMsg::Testcase { input_hash, event })?,
+        )
+    }
 }
 
 impl<EMH, S, SP> UsesState for LlmpEventManager<EMH, S, SP>
@@ -510,41 +762,12 @@ where
         }
     }
 
-    #[cfg(feature = "llmp_compression")]
     fn fire(
         &mut self,
         _state: &mut Self::State,
         event: Event<<Self::State as UsesInput>::Input>,
     ) -> Result<(), Error> {
-        let serialized = postcard::to_allocvec(&event)?;
-        let flags = LLMP_FLAG_INITIALIZED;
-
-        match self.compressor.maybe_compress(&serialized) {
-            Some(comp_buf) => {
-                self.llmp.send_buf_with_flags(
-                    LLMP_TAG_EVENT_TO_BOTH,
-                    flags | LLMP_FLAG_COMPRESSED,
-                    &comp_buf,
-                )?;
-            }
-            None => {
-                self.llmp.send_buf(LLMP_TAG_EVENT_TO_BOTH, &serialized)?;
-            }
-        }
-        self.last_sent = current_time();
-
-        Ok(())
-    }
-
-    #[cfg(not(feature = "llmp_compression"))]
-    fn fire(
-        &mut self,
-        _state: &mut Self::State,
-        event: Event<<Self::State as UsesInput>::Input>,
-    ) -> Result<(), Error> {
-        let serialized = postcard::to_allocvec(&event)?;
-        self.llmp.send_buf(LLMP_TAG_EVENT_TO_BOTH, &serialized)?;
-        Ok(())
+        self.send_event(&event)
     }
 
     fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
@@ -598,7 +821,7 @@ where
         // TODO: Get around local event copy by moving handle_in_client
         let self_id = self.llmp.sender().id();
         let mut count = 0;
-        while let Some((client_id, tag, _flags, msg)) = self.llmp.recv_buf_with_flags()? {
+        while let Some((client_id, tag, flags, msg)) = self.llmp.recv_buf_with_flags()? {
             assert!(
                 tag != _LLMP_TAG_EVENT_TO_BROKER,
                 "EVENT_TO_BROKER parcel should not have arrived in the client!"
@@ -612,13 +835,29 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
                 compressed = self.compressor.decompress(msg)?;
                 &compressed
             } else {
                 msg
             };
-            let event: Event<S::Input> = postcard::from_bytes(event_bytes)?;
+            // The sender may use another serialization format than we do
+            let format = SerializationFormat::from_llmp_flags(flags)?;
+            if tag == LLMP_TAG_FETCH {
+                match format.deserialize(event_bytes)? {
+                    FetchMsg::Request { input_hash } => self.answer_fetch(input_hash)?,
+                    FetchMsg::Testcase { input_hash, event } => {
+                        if self.wanted.remove(&input_hash) {
+                            let event: Event<S::Input> = format.deserialize(&event)?;
+                            log::debug!("Received fetched {}", event.name_detailed());
+                            self.handle_in_client(fuzzer, executor, state, client_id, event)?;
+                            count += 1;
+                        }
+                    }
+                }
+                continue;
+            }
+            let event: Event<S::Input> = format.deserialize(event_bytes)?;
             log::debug!("Received event in normal llmp {}", event.name_detailed());
             self.handle_in_client(fuzzer, executor, state, client_id, event)?;
             count += 1;
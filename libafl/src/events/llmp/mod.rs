@@ -16,6 +16,7 @@ use libafl_bolts::{
 use serde::Deserialize;
 
 use crate::{
+    common::serialization::SerializationFormat,
     events::{CustomBufEventResult, CustomBufHandlerFn, Event, EventFirer},
     executors::{Executor, HasObservers},
     fuzzer::{EvaluatorObservers, ExecutionProcessor},
@@ -43,6 +44,8 @@ pub(crate) const _LLMP_TAG_EVENT_TO_BROKER: Tag = Tag(0x2B80438);
 pub(crate) const LLMP_TAG_EVENT_TO_BOTH: Tag = Tag(0x2B0741);
 pub(crate) const _LLMP_TAG_RESTART: Tag = Tag(0x8357A87);
 pub(crate) const _LLMP_TAG_NO_RESTART: Tag = Tag(0x57A7EE71);
+/// Requests for announced testcases, and the testcases sent in response
+pub(crate) const LLMP_TAG_FETCH: Tag = Tag(0xFE7C4);
 
 /// The minimum buffer size at which to compress LLMP IPC messages.
 #[cfg(feature = "llmp_compression")]
@@ -329,6 +332,10 @@ where
                 }
                 Ok(())
             }
+            Event::NewTestcaseAnnounced { input_hash, .. } => {
+                log::debug!("Ignoring announced testcase {input_hash:016x} from {client_id:?}");
+                Ok(())
+            }
             _ => Err(Error::unknown(format!(
                 "Received illegal message that message should not have arrived: {:?}.",
                 event.name()
@@ -354,13 +361,14 @@ where
         // TODO: Get around local event copy by moving handle_in_client
         let self_id = self.llmp.sender().id();
         let mut count = 0;
-        while let Some((client_id, tag, _flags, msg)) = self.llmp.recv_buf_with_flags()? {
+        while let Some((client_id, tag, flags, msg)) = self.llmp.recv_buf_with_flags()? {
             assert!(
                 tag != _LLMP_TAG_EVENT_TO_BROKER,
                 "EVENT_TO_BROKER parcel should not have arrived in the client!"
             );
 
-            if client_id == self_id {
+            // We cannot fetch announced testcases, they are of another input type
+            if client_id == self_id || tag == LLMP_TAG_FETCH {
                 continue;
             }
             #[cfg(not(feature = "llmp_compression"))]
@@ -368,14 +376,15 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
                 compressed = self.compressor.decompress(msg)?;
                 &compressed
             } else {
                 msg
             };
 
-            let event: Event<DI> = postcard::from_bytes(event_bytes)?;
+            let event: Event<DI> =
+                SerializationFormat::from_llmp_flags(flags)?.deserialize(event_bytes)?;
             log::debug!("Processor received message {}", event.name_detailed());
             self.handle_in_client(fuzzer, executor, state, manager, client_id, event)?;
             count += 1;
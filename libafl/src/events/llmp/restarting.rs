@@ -4,6 +4,8 @@
 //! restart/refork it.
 
 use alloc::vec::Vec;
+#[cfg(all(feature = "std", feature = "fork", unix))]
+use core::mem::size_of;
 #[cfg(all(unix, not(miri), feature = "std"))]
 use core::ptr::addr_of_mut;
 #[cfg(feature = "std")]
@@ -22,6 +24,8 @@ use libafl_bolts::os::startable_self;
 use libafl_bolts::os::unix_signals::setup_signal_handler;
 #[cfg(all(feature = "std", feature = "fork", unix))]
 use libafl_bolts::os::{fork, ForkResult};
+#[cfg(feature = "std")]
+use libafl_bolts::shmem::ShMem;
 use libafl_bolts::{
     llmp::LlmpBroker,
     shmem::ShMemProvider,
@@ -29,21 +33,30 @@ use libafl_bolts::{
 };
 #[cfg(feature = "std")]
 use libafl_bolts::{
-    llmp::LlmpConnection, os::CTRL_C_EXIT, shmem::StdShMemProvider, staterestore::StateRestorer,
+    llmp::{LlmpClientDescription, LlmpConnection},
+    os::CTRL_C_EXIT,
+    shmem::StdShMemProvider,
+    staterestore::StateRestorer,
 };
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use typed_builder::TypedBuilder;
 
+#[cfg(feature = "std")]
+use crate::common::serialization::{
+    deserialize_with_header, serialize_with_header, SerializationFormat,
+};
 #[cfg(feature = "std")]
 use crate::events::AdaptiveSerializer;
+#[cfg(all(feature = "std", feature = "fork", unix))]
+use crate::events::ClientRestartReason;
 #[cfg(all(unix, feature = "std", not(miri)))]
 use crate::events::EVENTMGR_SIGHANDLER_STATE;
 use crate::{
     events::{
-        Event, EventConfig, EventFirer, EventManager, EventManagerHooksTuple, EventManagerId,
-        EventProcessor, EventRestarter, HasEventManagerId, LlmpEventManager, LlmpShouldSaveState,
-        ProgressReporter, StdLlmpEventHook,
+        Event, EventConfig, EventFilter, EventFirer, EventManager, EventManagerHooksTuple,
+        EventManagerId, EventProcessor, EventRestarter, HasEventManagerId, LlmpEventManager,
+        LlmpShouldSaveState, ProgressReporter, StdLlmpEventHook,
     },
     executors::{Executor, HasObservers},
     fuzzer::{Evaluator, EvaluatorObservers, ExecutionProcessor},
@@ -54,6 +67,11 @@ use crate::{
     Error, HasMetadata,
 };
 
+/// The minimum `client_timeout` of the [`RestartingMgr`]. Clients only report in between stages,
+/// so shorter timeouts would kill healthy clients that run a long stage.
+#[cfg(feature = "std")]
+pub const MIN_CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// A manager that can restart on the fly, storing states in-between (in `on_restart`)
 #[cfg(feature = "std")]
 #[derive(Debug)]
@@ -69,6 +87,8 @@ where
     staterestorer: StateRestorer<SP>,
     /// Decide if the state restorer must save the serialized state
     save_state: LlmpShouldSaveState,
+    /// The shared heartbeat the restarter watches, if it runs a watchdog
+    heartbeat: Option<SP::ShMem>,
 }
 
 #[cfg(feature = "std")]
@@ -141,6 +161,7 @@ where
         state: &mut Self::State,
         event: Event<<Self::State as UsesInput>::Input>,
     ) -> Result<(), Error> {
+        self.beat();
         // Check if we are going to crash in the event, in which case we store our current state for the next runner
         self.llmp_mgr.fire(state, event)?;
         self.intermediate_save()?;
@@ -179,14 +200,18 @@ where
 
         // First, reset the page to 0 so the next iteration can read read from the beginning of this page
         self.staterestorer.reset();
-        self.staterestorer.save(&(
-            if self.save_state.on_restart() {
-                Some(state)
-            } else {
-                None
-            },
-            &self.llmp_mgr.describe()?,
-        ))?;
+        let serialized = serialize_with_header(
+            self.llmp_mgr.serialization_format(),
+            &(
+                if self.save_state.on_restart() {
+                    Some(state)
+                } else {
+                    None
+                },
+                &self.llmp_mgr.describe()?,
+            ),
+        )?;
+        self.staterestorer.save(&serialized)?;
 
         log::info!("Waiting for broker...");
         self.await_restart_safe();
@@ -214,6 +239,7 @@ where
         + Evaluator<E, LlmpEventManager<EMH, S, SP>>,
 {
     fn process(&mut self, fuzzer: &mut Z, state: &mut S, executor: &mut E) -> Result<usize, Error> {
+        self.beat();
         let res = self.llmp_mgr.process(fuzzer, state, executor)?;
         self.intermediate_save()?;
         Ok(res)
@@ -264,6 +290,7 @@ where
             llmp_mgr,
             staterestorer,
             save_state: LlmpShouldSaveState::OnRestart,
+            heartbeat: None,
         }
     }
 
@@ -277,6 +304,14 @@ where
             llmp_mgr,
             staterestorer,
             save_state,
+            heartbeat: None,
+        }
+    }
+
+    /// Tell the restarter watching this client that we are still alive
+    fn beat(&mut self) {
+        if let Some(heartbeat) = &mut self.heartbeat {
+            write_heartbeat(heartbeat);
         }
     }
 
@@ -295,8 +330,11 @@ where
         // First, reset the page to 0 so the next iteration can read read from the beginning of this page
         if self.save_state.oom_safe() {
             self.staterestorer.reset();
-            self.staterestorer
-                .save(&(None::<S>, &self.llmp_mgr.describe()?))?;
+            let serialized = serialize_with_header(
+                self.llmp_mgr.serialization_format(),
+                &(None::<S>, &self.llmp_mgr.describe()?),
+            )?;
+            self.staterestorer.save(&serialized)?;
         }
         Ok(())
     }
@@ -422,6 +460,30 @@ pub struct RestartingMgr<EMH, MT, S, SP> {
     hooks: EMH,
     #[builder(default = None)]
     time_ref: Option<Handle<TimeObserver>>,
+    /// Kill and respawn clients that did not fire or process any events for this long.
+    /// The broker also uses this to warn about clients that stopped reporting.
+    ///
+    /// Clients report from the fuzzing loop, in between stages, so the timeout needs to be well above
+    /// the longest time a single fuzzing iteration, including all of its stages, can take.
+    /// Timeouts below [`MIN_CLIENT_TIMEOUT`] are raised to it.
+    ///
+    /// The watchdog is only available on unix with the `fork` feature.
+    /// Clients can only be respawned if they keep their state, i.e. if `serialize_state` is OOM safe.
+    #[builder(default = None)]
+    client_timeout: Option<Duration>,
+    /// Filter the events the clients send out, to save bandwidth
+    #[builder(default = EventFilter::new())]
+    event_filter: EventFilter,
+    /// Only announce the hashes of new testcases, see [`LlmpEventManagerBuilder::announce_testcases`]
+    #[builder(default = false)]
+    announce_testcases: bool,
+    /// Bind clients to their core before connecting to the broker, and prefer the memory of the core's NUMA node,
+    /// so that their shared maps are local to the core, see [`CoreId::set_affinity_numa`]
+    #[builder(default = false)]
+    numa_aware: bool,
+    /// The format of the events the clients send, and of the states they keep across restarts
+    #[builder(default = SerializationFormat::Postcard)]
+    serialization_format: SerializationFormat,
     #[builder(setter(skip), default = PhantomData)]
     phantom_data: PhantomData<(EMH, S)>,
 }
@@ -437,9 +499,22 @@ where
 {
     /// Launch the broker and the clients and fuzz
     pub fn launch(&mut self) -> Result<(Option<S>, LlmpRestartingEventManager<EMH, S, SP>), Error> {
+        if let Some(client_timeout) = self.client_timeout {
+            if client_timeout < MIN_CLIENT_TIMEOUT {
+                log::warn!(
+                    "A client timeout of {}s would kill clients in long stages, using {}s instead",
+                    client_timeout.as_secs(),
+                    MIN_CLIENT_TIMEOUT.as_secs()
+                );
+                self.client_timeout = Some(MIN_CLIENT_TIMEOUT);
+            }
+        }
+
         // We start ourselves as child process to actually fuzz
-        let (staterestorer, new_shmem_provider, core_id) = if std::env::var(_ENV_FUZZER_SENDER)
-            .is_err()
+        let (staterestorer, new_shmem_provider, core_id, heartbeat, restart) = if std::env::var(
+            _ENV_FUZZER_SENDER,
+        )
+        .is_err()
         {
             let broker_things = |mut broker: LlmpBroker<_, SP>, remote_broker_addr| {
                 if let Some(remote_broker_addr) = remote_broker_addr {
@@ -467,9 +542,12 @@ where
                         LlmpConnection::on_port(self.shmem_provider.clone(), self.broker_port)?;
                     match connection {
                         LlmpConnection::IsBroker { broker } => {
-                            let llmp_hook = StdLlmpEventHook::<S::Input, MT>::new(
+                            let mut llmp_hook = StdLlmpEventHook::<S::Input, MT>::new(
                                 self.monitor.take().unwrap(),
                             )?;
+                            if let Some(client_timeout) = self.client_timeout {
+                                llmp_hook = llmp_hook.with_client_timeout(client_timeout);
+                            }
 
                             // Yep, broker. Just loop here.
                             log::info!(
@@ -487,6 +565,9 @@ where
                             let mgr: LlmpEventManager<EMH, S, SP> = LlmpEventManager::builder()
                                 .always_interesting(self.always_interesting)
                                 .hooks(self.hooks)
+                                .filter(self.event_filter.clone())
+                                .announce_testcases(self.announce_testcases)
+                                .serialization_format(self.serialization_format)
                                 .build_from_client(
                                     client,
                                     self.configuration,
@@ -497,7 +578,10 @@ where
                     }
                 }
                 ManagerKind::Broker => {
-                    let llmp_hook = StdLlmpEventHook::new(self.monitor.take().unwrap())?;
+                    let mut llmp_hook = StdLlmpEventHook::new(self.monitor.take().unwrap())?;
+                    if let Some(client_timeout) = self.client_timeout {
+                        llmp_hook = llmp_hook.with_client_timeout(client_timeout);
+                    }
 
                     let broker = LlmpBroker::create_attach_to_tcp(
                         self.shmem_provider.clone(),
@@ -510,9 +594,19 @@ where
                 }
                 ManagerKind::Client { cpu_core } => {
                     // We are a client
+                    if self.numa_aware {
+                        if let Some(core_id) = cpu_core {
+                            log::info!("Binding to {core_id:?} and its NUMA node");
+                            core_id.set_affinity_numa()?;
+                        }
+                    }
+
                     let mgr = LlmpEventManager::builder()
                         .always_interesting(self.always_interesting)
                         .hooks(self.hooks)
+                        .filter(self.event_filter.clone())
+                        .announce_testcases(self.announce_testcases)
+                        .serialization_format(self.serialization_format)
                         .build_on_port(
                             self.shmem_provider.clone(),
                             self.broker_port,
@@ -544,6 +638,17 @@ where
             // Store the information to a map.
             staterestorer.write_to_env(_ENV_FUZZER_SENDER)?;
 
+            // The heartbeat the clients write to, so we can detect if they got stuck.
+            #[cfg(all(unix, feature = "fork"))]
+            let mut heartbeat = match self.client_timeout {
+                Some(_) => Some(self.shmem_provider.new_shmem(size_of::<u64>())?),
+                None => None,
+            };
+            // Why the last client had to be restarted, reported by the next one.
+            // Only tracked with the watchdog, so plain crash restarts stay quiet.
+            #[cfg(all(unix, feature = "fork"))]
+            let mut restart_reason: Option<ClientRestartReason> = None;
+
             let mut ctr: u64 = 0;
             // Client->parent loop
             loop {
@@ -552,6 +657,9 @@ where
                 // On Unix, we fork (when fork feature is enabled)
                 #[cfg(all(unix, feature = "fork"))]
                 let child_status = {
+                    if let Some(heartbeat) = &mut heartbeat {
+                        write_heartbeat(heartbeat);
+                    }
                     self.shmem_provider.pre_fork()?;
                     match unsafe { fork() }? {
                         ForkResult::Parent(handle) => {
@@ -559,7 +667,12 @@ where
                                 libc::signal(libc::SIGINT, libc::SIG_IGN);
                             }
                             self.shmem_provider.post_fork(false)?;
-                            handle.status()
+                            let (child_status, reason) =
+                                watch_child(&handle, heartbeat.as_ref().zip(self.client_timeout));
+                            if self.client_timeout.is_some() {
+                                restart_reason = Some(reason);
+                            }
+                            child_status
                         }
                         ForkResult::Child => {
                             log::debug!(
@@ -568,7 +681,13 @@ where
                                 std::process::id()
                             );
                             self.shmem_provider.post_fork(true)?;
-                            break (staterestorer, self.shmem_provider.clone(), core_id);
+                            break (
+                                staterestorer,
+                                self.shmem_provider.clone(),
+                                core_id,
+                                heartbeat,
+                                restart_reason.map(|reason| (reason, ctr)),
+                            );
                         }
                     }
                 };
@@ -607,6 +726,10 @@ where
                     if let Err(err) = mgr.detach_from_broker(self.broker_port) {
                         log::error!("Failed to detach from broker: {err}");
                     }
+                    #[cfg(all(unix, feature = "fork"))]
+                    if let Some(ClientRestartReason::Unresponsive { .. }) = restart_reason {
+                        panic!("Fuzzer-respawner: Killed an unresponsive client, but it did not store its state, so it cannot be respawned. Use an OOM safe `serialize_state` together with `client_timeout`.");
+                    }
                     #[cfg(unix)]
                     if child_status == 9 {
                         panic!("Target received SIGKILL!. This could indicate the target crashed due to OOM, user sent SIGKILL, or the target was in an unrecoverable situation and could not save state to restart");
@@ -625,6 +748,8 @@ where
                 StateRestorer::from_env(&mut self.shmem_provider, _ENV_FUZZER_SENDER)?,
                 self.shmem_provider.clone(),
                 None,
+                None,
+                None,
             )
         };
 
@@ -641,46 +766,57 @@ where
             core_id.set_affinity()?;
         }
 
-        // If we're restarting, deserialize the old state.
-        let (state, mut mgr) =
-            if let Some((state_opt, mgr_description)) = staterestorer.restore()? {
-                let llmp_mgr = LlmpEventManager::builder()
-                    .hooks(self.hooks)
-                    .build_existing_client_from_description(
-                        new_shmem_provider,
-                        &mgr_description,
-                        self.configuration,
-                        self.time_ref.clone(),
-                    )?;
-                (
-                    state_opt,
-                    LlmpRestartingEventManager::with_save_state(
-                        llmp_mgr,
-                        staterestorer,
-                        self.serialize_state,
-                    ),
-                )
-            } else {
-                log::info!("First run. Let's set it all up");
-                // Mgr to send and receive msgs from/to all other fuzzer instances
-                let mgr = LlmpEventManager::builder()
-                    .hooks(self.hooks)
-                    .build_existing_client_from_env(
-                        new_shmem_provider,
-                        _ENV_FUZZER_BROKER_CLIENT_INITIAL,
-                        self.configuration,
-                        self.time_ref.clone(),
-                    )?;
+        // If we're restarting, deserialize the old state, in the format it was written with.
+        let restored = match staterestorer.restore::<Vec<u8>>()? {
+            Some(serialized) => {
+                Some(deserialize_with_header::<(Option<S>, LlmpClientDescription)>(&serialized)?)
+            }
+            None => None,
+        };
+        let (state, mut mgr) = if let Some((state_opt, mgr_description)) = restored {
+            let llmp_mgr = LlmpEventManager::builder()
+                .hooks(self.hooks)
+                .filter(self.event_filter.clone())
+                .announce_testcases(self.announce_testcases)
+                .serialization_format(self.serialization_format)
+                .build_existing_client_from_description(
+                    new_shmem_provider,
+                    &mgr_description,
+                    self.configuration,
+                    self.time_ref.clone(),
+                )?;
+            (
+                state_opt,
+                LlmpRestartingEventManager::with_save_state(
+                    llmp_mgr,
+                    staterestorer,
+                    self.serialize_state,
+                ),
+            )
+        } else {
+            log::info!("First run. Let's set it all up");
+            // Mgr to send and receive msgs from/to all other fuzzer instances
+            let mgr = LlmpEventManager::builder()
+                .hooks(self.hooks)
+                .filter(self.event_filter.clone())
+                .announce_testcases(self.announce_testcases)
+                .serialization_format(self.serialization_format)
+                .build_existing_client_from_env(
+                    new_shmem_provider,
+                    _ENV_FUZZER_BROKER_CLIENT_INITIAL,
+                    self.configuration,
+                    self.time_ref.clone(),
+                )?;
 
-                (
-                    None,
-                    LlmpRestartingEventManager::with_save_state(
-                        mgr,
-                        staterestorer,
-                        self.serialize_state,
-                    ),
-                )
-            };
+            (
+                None,
+                LlmpRestartingEventManager::with_save_state(
+                    mgr,
+                    staterestorer,
+                    self.serialize_state,
+                ),
+            )
+        };
         // We reset the staterestorer, the next staterestorer and receiver (after crash) will reuse the page from the initial message.
         if self.serialize_state.oom_safe() {
             mgr.intermediate_save()?;
@@ -688,6 +824,15 @@ where
             mgr.staterestorer.reset();
         }
 
+        if let Some(heartbeat) = heartbeat {
+            mgr.heartbeat = Some(heartbeat);
+            mgr.beat();
+        }
+        if let Some((reason, restarts)) = restart {
+            mgr.llmp_mgr
+                .send_event(&Event::ClientRestarted { reason, restarts })?;
+        }
+
         /* TODO: Not sure if this is needed
         // We commit an empty NO_RESTART message to this buf, against infinite loops,
         // in case something crashes in the fuzzer.
@@ -698,10 +843,78 @@ where
     }
 }
 
+/// Writes the current time, in milliseconds, to the heartbeat map
+#[cfg(feature = "std")]
+fn write_heartbeat<SHM>(heartbeat: &mut SHM)
+where
+    SHM: ShMem,
+{
+    let ptr = heartbeat.as_mut_ptr_of::<u64>().unwrap();
+    unsafe {
+        ptr.write_volatile(libafl_bolts::current_milliseconds());
+    }
+}
+
+/// Reads the last time, in milliseconds, a client wrote to the heartbeat map
+#[cfg(all(feature = "std", feature = "fork", unix))]
+fn read_heartbeat<SHM>(heartbeat: &SHM) -> u64
+where
+    SHM: ShMem,
+{
+    let ptr = heartbeat.as_ptr_of::<u64>().unwrap();
+    unsafe { ptr.read_volatile() }
+}
+
+/// Waits for the child to exit.
+/// If a heartbeat and timeout are given, the child is killed once it did not beat for longer than the timeout.
+/// Returns the exit status and the reason the child is gone.
+#[cfg(all(feature = "std", feature = "fork", unix))]
+fn watch_child<SHM>(
+    handle: &libafl_bolts::os::ChildHandle,
+    watchdog: Option<(&SHM, Duration)>,
+) -> (i32, ClientRestartReason)
+where
+    SHM: ShMem,
+{
+    let Some((heartbeat, client_timeout)) = watchdog else {
+        let status = handle.status();
+        return (status, ClientRestartReason::Exited { status });
+    };
+
+    loop {
+        if let Some(status) = handle.try_status() {
+            return (status, ClientRestartReason::Exited { status });
+        }
+        let silent_for = Duration::from_millis(
+            libafl_bolts::current_milliseconds().saturating_sub(read_heartbeat(heartbeat)),
+        );
+        if silent_for > client_timeout {
+            log::warn!(
+                "Client {} did not report for {}s, killing it",
+                handle.pid,
+                silent_for.as_secs()
+            );
+            unsafe {
+                libc::kill(handle.pid, libc::SIGKILL);
+            }
+            let status = handle.status();
+            return (status, ClientRestartReason::Unresponsive { silent_for });
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "std")]
 mod tests {
     use core::sync::atomic::{compiler_fence, Ordering};
+    #[cfg(all(unix, feature = "fork"))]
+    use core::{mem::size_of, time::Duration};
+    #[cfg(all(unix, feature = "fork"))]
+    use std::thread;
+
+    #[cfg(all(unix, feature = "fork"))]
+    use libafl_bolts::os::{fork, ForkResult};
 
     use libafl_bolts::{
         llmp::{LlmpClient, LlmpSharedMap},
@@ -713,6 +926,11 @@ mod tests {
     };
     use serial_test::serial;
 
+    #[cfg(all(unix, feature = "fork"))]
+    use crate::events::{
+        llmp::restarting::{watch_child, write_heartbeat},
+        ClientRestartReason,
+    };
     use crate::{
         corpus::{Corpus, InMemoryCorpus, Testcase},
         events::llmp::{restarting::_ENV_FUZZER_SENDER, LlmpEventManager},
@@ -825,4 +1043,45 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(all(unix, feature = "fork"))]
+    fn test_watch_child() {
+        let timeout = Duration::from_millis(200);
+        let mut shmem_provider = StdShMemProvider::new().unwrap();
+        let mut heartbeat = shmem_provider.new_shmem(size_of::<u64>()).unwrap();
+
+        // A client that keeps beating for longer than the timeout, then exits on its own
+        write_heartbeat(&mut heartbeat);
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Parent(handle) => {
+                let (status, reason) = watch_child(&handle, Some((&heartbeat, timeout)));
+                assert_eq!(status, 3);
+                assert_eq!(reason, ClientRestartReason::Exited { status: 3 });
+            }
+            ForkResult::Child => {
+                for _ in 0..5 {
+                    write_heartbeat(&mut heartbeat);
+                    thread::sleep(Duration::from_millis(100));
+                }
+                unsafe { libc::_exit(3) };
+            }
+        }
+
+        // A client that got stuck
+        write_heartbeat(&mut heartbeat);
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Parent(handle) => {
+                let (_, reason) = watch_child(&handle, Some((&heartbeat, timeout)));
+                assert!(
+                    matches!(reason, ClientRestartReason::Unresponsive { silent_for } if silent_for > timeout)
+                );
+            }
+            ForkResult::Child => loop {
+                thread::sleep(Duration::from_secs(1));
+            },
+        }
+    }
 }
@@ -6,6 +6,8 @@ pub use events_hooks::*;
 
 pub mod simple;
 pub use simple::*;
+pub mod filter;
+pub use filter::*;
 #[cfg(all(unix, feature = "std"))]
 pub mod centralized;
 #[cfg(all(unix, feature = "std"))]
@@ -16,9 +18,13 @@ pub mod launcher;
 #[allow(clippy::ignored_unit_patterns)]
 pub mod llmp;
 pub use llmp::*;
+#[cfg(feature = "std")]
+pub mod record;
 #[cfg(feature = "tcp_manager")]
 #[allow(clippy::ignored_unit_patterns)]
 pub mod tcp;
+#[cfg(feature = "std")]
+pub use record::*;
 
 pub mod broker_hooks;
 use alloc::{
@@ -52,6 +58,7 @@ use uuid::Uuid;
 #[cfg(feature = "introspection")]
 use crate::state::HasClientPerfMonitor;
 use crate::{
+    common::serialization::{Postcard, SerdeFormat},
     executors::ExitKind,
     inputs::Input,
     monitors::UserStats,
@@ -125,7 +132,7 @@ use crate::{
 };
 
 /// The log event severity
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogSeverity {
     /// Debug severity
     Debug,
@@ -159,6 +166,34 @@ impl fmt::Display for LogSeverity {
     }
 }
 
+/// The reason a client got respawned by its restarter
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientRestartReason {
+    /// The client exited, or crashed, with the given status
+    Exited {
+        /// The exit status of the client
+        status: i32,
+    },
+    /// The client stopped sending heartbeats and got killed by the restarter's watchdog
+    Unresponsive {
+        /// How long the client was silent before it got killed
+        silent_for: Duration,
+    },
+}
+
+impl fmt::Display for ClientRestartReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientRestartReason::Exited { status } => write!(f, "exited with status {status}"),
+            ClientRestartReason::Unresponsive { silent_for } => write!(
+                f,
+                "unresponsive for {}s, killed by the watchdog",
+                silent_for.as_secs()
+            ),
+        }
+    }
+}
+
 /// The result of a custom buf handler added using [`HasCustomBufHandlers::add_custom_buf_handler`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CustomBufEventResult {
@@ -297,6 +332,25 @@ where
         #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
         node_id: Option<NodeId>,
     },
+    /// A fuzzer found a new testcase, but only announces its hashes.
+    /// Peers that consider it novel fetch the full [`Event::NewTestcase`] on demand,
+    /// see [`LlmpEventManagerBuilder::announce_testcases`].
+    NewTestcaseAnnounced {
+        /// The hash of the serialized input
+        input_hash: u64,
+        /// The hash of the serialized observers, summarizing the coverage of this testcase, if they got serialized
+        observers_hash: Option<u64>,
+        /// The new corpus size of this client
+        corpus_size: usize,
+        /// The client config for this observers/testcase combination
+        client_config: EventConfig,
+        /// The time of generation of the event
+        time: Duration,
+        /// The executions of this client
+        executions: u64,
+        /// [`PhantomData`]
+        phantom: PhantomData<I>,
+    },
     /// New stats event to monitor.
     UpdateExecStats {
         /// The time of generation of the [`Event`]
@@ -353,6 +407,13 @@ where
         /// Tag of this buffer
         tag: String,
     },
+    /// A client got respawned by its restarter
+    ClientRestarted {
+        /// Why the client got respawned
+        reason: ClientRestartReason,
+        /// How often this client got respawned so far
+        restarts: u64,
+    },
     /*/// A custom type
     Custom {
         // TODO: Allow custom events
@@ -366,7 +427,7 @@ where
 {
     fn name(&self) -> &str {
         match self {
-            Event::NewTestcase { .. } => "Testcase",
+            Event::NewTestcase { .. } | Event::NewTestcaseAnnounced { .. } => "Testcase",
             Event::UpdateExecStats { .. } => "Client Heartbeat",
             Event::UpdateUserStats { .. } => "UserStats",
             #[cfg(feature = "introspection")]
@@ -374,6 +435,7 @@ where
             Event::Objective { .. } => "Objective",
             Event::Log { .. } => "Log",
             Event::CustomBuf { .. } => "CustomBuf",
+            Event::ClientRestarted { .. } => "ClientRestarted",
             /*Event::Custom {
                 sender_id: _, /*custom_event} => custom_event.name()*/
             } => "todo",*/
@@ -385,6 +447,9 @@ where
             Event::NewTestcase { input, .. } => {
                 format!("Testcase {}", input.generate_name(None))
             }
+            Event::NewTestcaseAnnounced { input_hash, .. } => {
+                format!("Testcase announcement {input_hash:016x}")
+            }
             Event::UpdateExecStats { .. } => "Client Heartbeat".to_string(),
             Event::UpdateUserStats { .. } => "UserStats".to_string(),
             #[cfg(feature = "introspection")]
@@ -392,6 +457,7 @@ where
             Event::Objective { .. } => "Objective".to_string(),
             Event::Log { .. } => "Log".to_string(),
             Event::CustomBuf { .. } => "CustomBuf".to_string(),
+            Event::ClientRestarted { reason, .. } => format!("ClientRestarted ({reason})"),
             /*Event::Custom {
                 sender_id: _, /*custom_event} => custom_event.name()*/
             } => "todo",*/
@@ -438,7 +504,7 @@ pub trait EventFirer: UsesState {
     where
         OT: ObserversTuple<Self::State> + Serialize,
     {
-        Ok(Some(postcard::to_allocvec(observers)?))
+        Ok(Some(Postcard::serialize(observers)?))
     }
 
     /// Get the configuration
@@ -908,7 +974,7 @@ pub trait AdaptiveSerializer {
                     || self.serializations_cnt().trailing_zeros() >= 8
                 {
                     let start = current_time();
-                    let ser = postcard::to_allocvec(observers)?;
+                    let ser = Postcard::serialize(observers)?;
                     *self.serialization_time_mut() = current_time() - start;
 
                     *self.serializations_cnt_mut() += 1;
@@ -928,10 +994,14 @@ mod tests {
 
     use core::ptr::addr_of_mut;
 
-    use libafl_bolts::{current_time, tuples::tuple_list, Named};
+    use libafl_bolts::{current_time, llmp::LLMP_FLAG_COMPRESSED, tuples::tuple_list, Named};
     use tuple_list::tuple_list_type;
 
     use crate::{
+        common::serialization::{
+            deserialize_with_header, hash_serialized, serialize_with_header, Postcard, SerdeFormat,
+            SerializationFormat,
+        },
         events::{Event, EventConfig},
         executors::ExitKind,
         inputs::bytes::BytesInput,
@@ -974,4 +1044,62 @@ mod tests {
             _ => panic!("mistmatch"),
         };
     }
+
+    #[test]
+    fn test_event_serde_mixed_formats() {
+        let map = tuple_list!(StdMapObserver::owned("test", vec![0_u32; 4]));
+        let input = BytesInput::new(vec![1, 2, 3]);
+        let event = Event::NewTestcase {
+            input: input.clone(),
+            observers_buf: Some(Postcard::serialize(&map).unwrap()),
+            exit_kind: ExitKind::Ok,
+            corpus_size: 1,
+            client_config: EventConfig::AlwaysUnique,
+            time: current_time(),
+            executions: 0,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        };
+
+        let mut formats = vec![SerializationFormat::Postcard];
+        #[cfg(feature = "std")]
+        formats.extend([SerializationFormat::Bincode, SerializationFormat::Json]);
+
+        // Nodes read the events of other nodes, whatever format each of them uses,
+        // over LLMP, where the format is in the message flags, and over TCP, where it is in a header.
+        let mut tcp_stream = vec![];
+        for &sender in &formats {
+            let flags = sender.llmp_flags() | LLMP_FLAG_COMPRESSED;
+            let serialized = sender.serialize(&event).unwrap();
+            tcp_stream.push(serialize_with_header(sender, &event).unwrap());
+            let received: Event<BytesInput> = SerializationFormat::from_llmp_flags(flags)
+                .unwrap()
+                .deserialize(&serialized)
+                .unwrap();
+            let Event::NewTestcase {
+                input: received_input,
+                observers_buf,
+                ..
+            } = received
+            else {
+                panic!("mismatch");
+            };
+            // Hashes and observers do not depend on the format of the event
+            assert_eq!(
+                hash_serialized(&received_input).unwrap(),
+                hash_serialized(&input).unwrap()
+            );
+            let observers: tuple_list_type!(StdMapObserver::<u32, false>) =
+                Postcard::deserialize(observers_buf.as_ref().unwrap()).unwrap();
+            assert_eq!("test", observers.0.name());
+        }
+        for message in tcp_stream {
+            let received: Event<BytesInput> = deserialize_with_header(&message).unwrap();
+            assert!(matches!(
+                received,
+                Event::NewTestcase { corpus_size: 1, .. }
+            ));
+        }
+    }
 }
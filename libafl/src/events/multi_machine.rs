@@ -1,7 +1,12 @@
-use core::fmt::Display;
+use core::{
+    fmt::{self, Display},
+    hash::Hasher,
+};
 use std::{
+    borrow::Cow,
     boxed::Box,
     collections::HashMap,
+    format,
     io::ErrorKind,
     process,
     sync::{
@@ -15,7 +20,13 @@ use std::{
 use enumflags2::{bitflags, BitFlags};
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::compress::GzipCompressor;
-use libafl_bolts::{current_time, ownedref::OwnedRef, Error};
+use libafl_bolts::{
+    current_time, hash_std, hasher_std,
+    ownedref::OwnedRef,
+    rands::{Rand, StdRand},
+    Error,
+};
+use postcard::ser_flavors::Flavor;
 use serde::{Deserialize, Serialize};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -28,7 +39,9 @@ use tokio::{
 use typed_builder::TypedBuilder;
 
 use crate::{
-    events::{Event, TcpMultiMachineLlmpReceiverHook, TcpMultiMachineLlmpSenderHook},
+    events::{
+        filter::KnownHashes, Event, TcpMultiMachineLlmpReceiverHook, TcpMultiMachineLlmpSenderHook,
+    },
     inputs::{Input, NopInput},
 };
 
@@ -43,10 +56,44 @@ pub enum NodePolicy {
     SendToParent,
     /// Send current node's interesting inputs to children.
     SendToChildren,
+    /// Send the user stats of the current node (and the ones received from its children) to parent.
+    SendStatsToParent,
 }
 
 const DUMMY_BYTE: u8 = 0x14;
 
+/// A postcard flavor feeding the serialized bytes straight into a hasher
+struct HashFlavor<H>(H);
+
+impl<H> Flavor for HashFlavor<H>
+where
+    H: Hasher,
+{
+    type Output = u64;
+
+    fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+        self.0.write(&[data]);
+        Ok(())
+    }
+
+    fn try_extend(&mut self, data: &[u8]) -> postcard::Result<()> {
+        self.0.write(data);
+        Ok(())
+    }
+
+    fn finalize(self) -> postcard::Result<u64> {
+        Ok(self.0.finish())
+    }
+}
+
+/// Hash an input, without serializing it into a buffer first
+fn hash_input<I: Input>(input: &I) -> Result<u64, Error> {
+    Ok(postcard::serialize_with_flavor(
+        input,
+        HashFlavor(hasher_std()),
+    )?)
+}
+
 /// Use `OwnedRef` as much as possible here to avoid useless copies.
 /// An owned TCP message for multi machine
 #[derive(Clone, Debug)]
@@ -108,16 +155,35 @@ where
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
-/// A `NodeId` (unused for now)
+/// A `NodeId`, identifying a node, or a child connected to the current node
 pub struct NodeId(pub u64);
 
 impl NodeId {
-    /// Generate a unique [`NodeId`].
+    /// Generate a unique [`NodeId`], unique in the current process.
     pub fn new() -> Self {
         static CTR: OnceLock<AtomicU64> = OnceLock::new();
         let ctr = CTR.get_or_init(|| AtomicU64::new(0));
         NodeId(ctr.fetch_add(1, Ordering::Relaxed))
     }
+
+    /// Generate a random [`NodeId`], to identify the current node among all machines.
+    #[must_use]
+    pub fn random() -> Self {
+        NodeId(StdRand::new().next())
+    }
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node#{}", self.0)
+    }
+}
+
+/// The neighbour a message was received from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Neighbour {
+    Parent,
+    Child(NodeId),
 }
 
 /// The state of the hook shared between the background threads and the main thread.
@@ -130,6 +196,12 @@ pub struct TcpMultiMachineState<A> {
     /// The children who connected during the fuzzing session.
     children: HashMap<NodeId, TcpStream>, // The children who connected during the fuzzing session.
     old_msgs: Vec<Vec<u8>>,
+    /// The hashes of the inputs already forwarded to other nodes
+    forwarded_inputs: KnownHashes,
+    /// The neighbours the inputs received from other nodes came from, by input hash
+    input_origins: KnownHashes<Neighbour>,
+    /// The hashes of the names of the user stats received from children, already prefixed by their node
+    relayed_stats: KnownHashes,
     #[cfg(feature = "llmp_compression")]
     compressor: GzipCompressor,
 }
@@ -140,6 +212,10 @@ pub struct NodeDescriptor<A> {
     /// The parent address, if there is one.
     pub parent_addr: Option<A>,
 
+    /// The id of this node. Should be unique among all machines, defaults to a random id.
+    #[builder(default_code = "NodeId::random()")]
+    pub node_id: NodeId,
+
     /// The node listening port. Defaults to 50000
     #[builder(default = Some(50000))]
     pub node_listening_port: Option<u16>,
@@ -239,6 +315,9 @@ where
             parent: None,
             children: HashMap::default(),
             old_msgs: Vec::new(),
+            forwarded_inputs: KnownHashes::default(),
+            input_origins: KnownHashes::default(),
+            relayed_stats: KnownHashes::default(),
             #[cfg(feature = "llmp_compression")]
             compressor: GzipCompressor::new(),
         }));
@@ -453,9 +532,70 @@ where
         Ok(())
     }
 
-    pub(crate) async fn send_interesting_event_to_nodes<'a, I: Input>(
+    /// Forward an event fired on this node to the other nodes, following the node policy.
+    ///
+    /// Each input is forwarded only once, and never back to the neighbour it came from.
+    /// Inputs found on this node get tagged with our [`NodeId`], so they won't be accepted once they come back to us.
+    /// User stats are only sent to the parent, prefixed with the [`NodeId`] of the node they were computed on.
+    pub(crate) async fn forward_event<I: Input>(
+        &mut self,
+        mut event: Event<I>,
+    ) -> Result<(), Error> {
+        let flags = self.node_descriptor.flags;
+        let own_id = self.node_descriptor.node_id;
+
+        match &mut event {
+            Event::NewTestcase { input, node_id, .. } => {
+                let input_hash = hash_input(input)?;
+                if !self.forwarded_inputs.insert(input_hash) {
+                    log::debug!("Input already forwarded, skipping it.");
+                    return Ok(());
+                }
+                node_id.get_or_insert(own_id);
+
+                let origin = self.input_origins.get(input_hash).copied();
+                let msg = postcard::to_allocvec(&event)?;
+                let mm_msg: MultiMachineMsg<I> = MultiMachineMsg::llmp_msg(OwnedRef::Ref(&msg));
+
+                self.send_interesting_event_to_nodes(&mm_msg, origin)
+                    .await?;
+
+                // TODO: do not copy here
+                self.add_past_msg(&msg);
+            }
+            Event::UpdateUserStats { name, .. }
+                if flags.intersects(NodePolicy::SendStatsToParent) =>
+            {
+                if !self.relayed_stats.contains(hash_std(name.as_bytes())) {
+                    *name = Cow::Owned(format!("{own_id}/{name}"));
+                }
+
+                let msg = postcard::to_allocvec(&event)?;
+                let mm_msg: MultiMachineMsg<I> = MultiMachineMsg::llmp_msg(OwnedRef::Ref(&msg));
+                self.send_to_parent(&mm_msg).await;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    async fn send_to_parent<'a, I: Input>(&mut self, msg: &MultiMachineMsg<'a, I>) {
+        if let Some(parent) = &mut self.parent {
+            log::debug!("Sending to parent...");
+            if let Err(e) = Self::write_msg(parent, msg).await {
+                log::error!("The parent disconnected. We won't try to communicate with it again.");
+                log::error!("Error: {e:?}");
+                self.parent.take();
+            }
+        }
+    }
+
+    /// Send a message to all neighbours, except the one it came from, if any.
+    async fn send_interesting_event_to_nodes<'a, I: Input>(
         &mut self,
         msg: &MultiMachineMsg<'a, I>,
+        origin: Option<Neighbour>,
     ) -> Result<(), Error> {
         log::debug!("Sending interesting events to nodes...");
 
@@ -463,17 +603,9 @@ where
             .node_descriptor
             .flags
             .intersects(NodePolicy::SendToParent)
+            && origin != Some(Neighbour::Parent)
         {
-            if let Some(parent) = &mut self.parent {
-                log::debug!("Sending to parent...");
-                if let Err(e) = Self::write_msg(parent, msg).await {
-                    log::error!(
-                        "The parent disconnected. We won't try to communicate with it again."
-                    );
-                    log::error!("Error: {e:?}");
-                    self.parent.take();
-                }
-            }
+            self.send_to_parent(msg).await;
         }
 
         if self
@@ -483,6 +615,9 @@ where
         {
             let mut ids_to_remove: Vec<NodeId> = Vec::new();
             for (child_id, child_stream) in &mut self.children {
+                if origin == Some(Neighbour::Child(*child_id)) {
+                    continue;
+                }
                 log::debug!("Sending to child...");
                 if (Self::write_msg(child_stream, msg).await).is_err() {
                     // most likely the child disconnected. drop the connection later on and continue.
@@ -503,11 +638,68 @@ where
         Ok(())
     }
 
+    /// Check whether a message received from `from` should be passed on to the local broker.
+    ///
+    /// Drops inputs that were found on this node, or already seen before,
+    /// and user stats sent by our parent, since they only flow upwards.
+    fn accept_received_msg<I: Input>(
+        &mut self,
+        msg: &[u8],
+        from: Neighbour,
+    ) -> Result<bool, Error> {
+        let event: Event<I> = postcard::from_bytes(msg)?;
+
+        match event {
+            Event::NewTestcase { input, node_id, .. } => {
+                if node_id == Some(self.node_descriptor.node_id) {
+                    log::debug!("Received our own input back, dropping it.");
+                    return Ok(false);
+                }
+
+                let input_hash = hash_input(&input)?;
+                if self.forwarded_inputs.contains(input_hash)
+                    || self.input_origins.contains(input_hash)
+                {
+                    log::debug!("Received an already known input, dropping it.");
+                    return Ok(false);
+                }
+
+                self.input_origins.insert_with(input_hash, from);
+                Ok(true)
+            }
+            Event::UpdateUserStats { name, .. } if matches!(from, Neighbour::Child(_)) => {
+                self.relayed_stats.insert(hash_std(name.as_bytes()));
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     /// Flush the message queue from other nodes and add incoming events to the
     /// centralized event manager queue.
+    ///
+    /// Only new inputs from other nodes, and user stats from children, are added.
     pub(crate) async fn receive_new_messages_from_nodes<'a, I: Input>(
         &mut self,
         msgs: &mut Vec<MultiMachineMsg<'a, I>>,
+    ) -> Result<(), Error> {
+        let mut received = Vec::new();
+        self.read_new_messages_from_nodes::<I>(&mut received)
+            .await?;
+
+        for (from, msg) in received {
+            if self.accept_received_msg::<I>(msg.serialize_as_ref(), from)? {
+                msgs.push(msg);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the pending messages of all neighbours, alongside the neighbour they came from.
+    async fn read_new_messages_from_nodes<'a, I: Input>(
+        &mut self,
+        msgs: &mut Vec<(Neighbour, MultiMachineMsg<'a, I>)>,
     ) -> Result<(), Error> {
         log::debug!("Checking for new events from other nodes...");
         let mut nb_received = 0usize;
@@ -525,7 +717,7 @@ where
                     Ok(Some(msg)) => {
                         log::debug!("Received event from parent");
                         // The parent has something for us, we store it
-                        msgs.push(msg);
+                        msgs.push((Neighbour::Parent, msg));
                         nb_received += 1;
                     }
 
@@ -571,7 +763,7 @@ where
                     Ok(Some(msg)) => {
                         // The parent has something for us, we store it
                         log::debug!("Received event from child!");
-                        msgs.push(msg);
+                        msgs.push((Neighbour::Child(*child_id), msg));
                         nb_received += 1;
                     }
 
@@ -609,3 +801,207 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use std::{borrow::Cow, collections::HashMap, string::String, time::Duration, vec::Vec};
+
+    use enumflags2::BitFlags;
+    #[cfg(feature = "llmp_compression")]
+    use libafl_bolts::compress::GzipCompressor;
+    use libafl_bolts::hash_std;
+    use tokio::{
+        net::{TcpListener, TcpStream},
+        runtime::Runtime,
+        time,
+    };
+
+    use super::{hash_input, Neighbour, NodeDescriptor, NodeId, NodePolicy, TcpMultiMachineState};
+    use crate::{
+        events::{filter::KnownHashes, Event, EventConfig},
+        executors::ExitKind,
+        inputs::BytesInput,
+        monitors::{AggregatorOps, UserStats, UserStatsValue},
+    };
+
+    const OWN_ID: NodeId = NodeId(0);
+    const CHILD_ID: NodeId = NodeId(1);
+    const REMOTE_ID: NodeId = NodeId(2);
+
+    fn node_state(flags: BitFlags<NodePolicy>) -> TcpMultiMachineState<String> {
+        TcpMultiMachineState {
+            node_descriptor: NodeDescriptor::builder()
+                .parent_addr(None)
+                .node_id(OWN_ID)
+                .flags(flags)
+                .build(),
+            parent: None,
+            children: HashMap::new(),
+            old_msgs: Vec::new(),
+            forwarded_inputs: KnownHashes::default(),
+            input_origins: KnownHashes::default(),
+            relayed_stats: KnownHashes::default(),
+            #[cfg(feature = "llmp_compression")]
+            compressor: GzipCompressor::new(),
+        }
+    }
+
+    fn testcase(input: &[u8], node_id: Option<NodeId>) -> Event<BytesInput> {
+        Event::NewTestcase {
+            input: BytesInput::new(input.to_vec()),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 1,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            executions: 0,
+            forward_id: None,
+            node_id,
+        }
+    }
+
+    fn user_stats(name: &'static str) -> Event<BytesInput> {
+        Event::UpdateUserStats {
+            name: Cow::Borrowed(name),
+            value: UserStats::new(UserStatsValue::Number(1), AggregatorOps::None),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Connect a stream of the node to a neighbour, returning both ends.
+    async fn connect() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let node_end = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (neighbour_end, _) = listener.accept().await.unwrap();
+        (node_end, neighbour_end)
+    }
+
+    /// Read the events a neighbour received, waiting a bit for them to arrive.
+    async fn received(stream: &mut TcpStream) -> Vec<Event<BytesInput>> {
+        time::sleep(Duration::from_millis(50)).await;
+        let mut events = Vec::new();
+        while let Some(msg) = TcpMultiMachineState::<String>::read_msg::<BytesInput>(stream)
+            .await
+            .unwrap()
+        {
+            events.push(postcard::from_bytes(msg.serialize_as_ref()).unwrap());
+        }
+        events
+    }
+
+    #[test]
+    fn test_accept_received_msg() {
+        let mut state = node_state(BitFlags::default());
+        let accept = |state: &mut TcpMultiMachineState<String>, event, from| {
+            let msg = postcard::to_allocvec(&event).unwrap();
+            state.accept_received_msg::<BytesInput>(&msg, from).unwrap()
+        };
+
+        // our own inputs coming back are dropped
+        assert!(!accept(
+            &mut state,
+            testcase(b"a", Some(OWN_ID)),
+            Neighbour::Parent
+        ));
+
+        assert!(accept(
+            &mut state,
+            testcase(b"b", Some(REMOTE_ID)),
+            Neighbour::Parent
+        ));
+        // the same input over another path is dropped
+        assert!(!accept(
+            &mut state,
+            testcase(b"b", Some(REMOTE_ID)),
+            Neighbour::Child(CHILD_ID)
+        ));
+        assert_eq!(
+            state
+                .input_origins
+                .get(hash_input(&BytesInput::new(b"b".to_vec())).unwrap()),
+            Some(&Neighbour::Parent)
+        );
+
+        // user stats only flow upwards
+        assert!(!accept(&mut state, user_stats("cov"), Neighbour::Parent));
+        assert!(accept(
+            &mut state,
+            user_stats("node#1/cov"),
+            Neighbour::Child(CHILD_ID)
+        ));
+        assert!(state.relayed_stats.contains(hash_std(b"node#1/cov")));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_forward_event() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut state = node_state(
+                NodePolicy::SendToParent
+                    | NodePolicy::SendToChildren
+                    | NodePolicy::SendStatsToParent,
+            );
+            let (node_end, mut parent) = connect().await;
+            state.parent = Some(node_end);
+            let (node_end, mut child) = connect().await;
+            state.children.insert(CHILD_ID, node_end);
+
+            // local inputs go everywhere once, tagged with our id
+            state.forward_event(testcase(b"a", None)).await.unwrap();
+            state.forward_event(testcase(b"a", None)).await.unwrap();
+            for events in [received(&mut parent).await, received(&mut child).await] {
+                assert_eq!(events.len(), 1);
+                assert!(matches!(
+                    events[0],
+                    Event::NewTestcase {
+                        node_id: Some(OWN_ID),
+                        ..
+                    }
+                ));
+            }
+            assert_eq!(state.old_msgs.len(), 1);
+
+            // inputs are never sent back where they came from
+            let msg = postcard::to_allocvec(&testcase(b"b", Some(REMOTE_ID))).unwrap();
+            assert!(state
+                .accept_received_msg::<BytesInput>(&msg, Neighbour::Child(CHILD_ID))
+                .unwrap());
+            state
+                .forward_event(testcase(b"b", Some(REMOTE_ID)))
+                .await
+                .unwrap();
+            let events = received(&mut parent).await;
+            assert_eq!(events.len(), 1);
+            assert!(matches!(
+                events[0],
+                Event::NewTestcase {
+                    node_id: Some(REMOTE_ID),
+                    ..
+                }
+            ));
+            assert!(received(&mut child).await.is_empty());
+
+            // local stats get prefixed by our id, relayed ones are kept as they are
+            let msg = postcard::to_allocvec(&user_stats("node#1/cov")).unwrap();
+            assert!(state
+                .accept_received_msg::<BytesInput>(&msg, Neighbour::Child(CHILD_ID))
+                .unwrap());
+            state.forward_event(user_stats("cov")).await.unwrap();
+            state.forward_event(user_stats("node#1/cov")).await.unwrap();
+            let names: Vec<_> = received(&mut parent)
+                .await
+                .into_iter()
+                .map(|event| match event {
+                    Event::UpdateUserStats { name, .. } => name,
+                    _ => panic!("Expected user stats"),
+                })
+                .collect();
+            assert_eq!(names, ["node#0/cov", "node#1/cov"]);
+            assert!(received(&mut child).await.is_empty());
+        });
+    }
+}
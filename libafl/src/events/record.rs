@@ -0,0 +1,556 @@
+//! Record all events a client receives to a log, and replay them later.
+//!
+//! The [`EventRecorder`] is an [`EventManagerHook`] writing each received [`Event`] to a file,
+//! together with the time, the executions of the client at that point, and a fresh RNG seed.
+//! The [`ReplayEventManager`] wraps another event manager and feeds the recorded events back into the fuzzer,
+//! at the same executions and with the same RNG seed, so the corpus growth of a campaign can be reproduced offline.
+//!
+//! Replays are only deterministic if the target, the initial seed, and the initial corpus are, too.
+
+use alloc::{boxed::Box, collections::VecDeque, string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use libafl_bolts::{current_time, rands::Rand, tuples::Handle, ClientId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{
+        AdaptiveSerializer, CustomBufEventResult, CustomBufHandlerFn, Event, EventConfig,
+        EventFirer, EventManager, EventManagerHook, EventManagerId, EventProcessor, EventRestarter,
+        HasCustomBufHandlers, HasEventManagerId, LogSeverity, ProgressReporter,
+    },
+    executors::{Executor, HasObservers},
+    fuzzer::{EvaluatorObservers, ExecutionProcessor},
+    inputs::{Input, UsesInput},
+    observers::{ObserversTuple, TimeObserver},
+    state::{HasExecutions, HasLastReportTime, HasRand, State, UsesState},
+    Error, HasMetadata,
+};
+
+/// An [`Event`] received by a client, as written to the log by the [`EventRecorder`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "I: serde::de::DeserializeOwned")]
+pub struct RecordedEvent<I>
+where
+    I: Input,
+{
+    /// The client that sent the event
+    pub client_id: ClientId,
+    /// The time the event got received
+    pub time: Duration,
+    /// The executions of the receiving client when the event got received
+    pub executions: u64,
+    /// The seed the RNG of the receiving client got reset to, before handling the event
+    pub seed: u64,
+    /// The event itself
+    pub event: Event<I>,
+}
+
+/// An [`EventManagerHook`] recording each received event to a log file, for a later replay using the [`ReplayEventManager`].
+///
+/// Before each event, the RNG of the state is reseeded with a fresh seed drawn from itself, which is written to the log.
+/// Add it as the last hook, after any hooks that may cancel the event handling.
+#[derive(Debug)]
+pub struct EventRecorder {
+    writer: BufWriter<File>,
+}
+
+impl EventRecorder {
+    /// Record to the log at `path`. If the log already exists, the events get appended,
+    /// so that restarted clients keep writing to the same log.
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Write a single event to the log.
+    /// Each record is prefixed with its length, as little endian `u32`.
+    pub fn record<I>(
+        &mut self,
+        client_id: ClientId,
+        time: Duration,
+        executions: u64,
+        seed: u64,
+        event: &Event<I>,
+    ) -> Result<(), Error>
+    where
+        I: Input,
+    {
+        // serializes to the same bytes as a `RecordedEvent`, without cloning the event
+        let record = postcard::to_allocvec(&(client_id, time, executions, seed, event))?;
+        let len = u32::try_from(record.len())
+            .map_err(|_| Error::illegal_argument("Event too large to record"))?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&record)?;
+        // the client may crash any moment, make sure the event is on disk
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<S> EventManagerHook<S> for EventRecorder
+where
+    S: State + HasExecutions + HasRand,
+{
+    fn pre_exec(
+        &mut self,
+        state: &mut S,
+        client_id: ClientId,
+        event: &Event<S::Input>,
+    ) -> Result<bool, Error> {
+        let seed = state.rand_mut().next();
+        state.rand_mut().set_seed(seed);
+        self.record(client_id, current_time(), *state.executions(), seed, event)?;
+        Ok(true)
+    }
+}
+
+/// Reads the events written by an [`EventRecorder`], in order
+#[derive(Debug)]
+pub struct EventLogReader<I> {
+    reader: BufReader<File>,
+    /// The bytes left in the log, records claiming to be longer are cut off
+    remaining: u64,
+    phantom: PhantomData<I>,
+}
+
+impl<I> EventLogReader<I>
+where
+    I: Input,
+{
+    /// Open the log at `path`
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)?;
+        let remaining = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            remaining,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Read the next event, or `None` at the end of the log.
+    ///
+    /// A record cut off at the end of the log, as left behind by a client that crashed while writing it, also ends the log.
+    pub fn next_event(&mut self) -> Result<Option<RecordedEvent<I>>, Error> {
+        let mut len = [0; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => (),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        self.remaining = self.remaining.saturating_sub(len.len() as u64);
+
+        let len = u64::from(u32::from_le_bytes(len));
+        if len > self.remaining {
+            log::warn!(
+                "The last record of the event log is cut off ({} of {len} bytes), ignoring it",
+                self.remaining
+            );
+            self.remaining = 0;
+            return Ok(None);
+        }
+        self.remaining -= len;
+
+        let mut record = vec![0; len as usize];
+        match self.reader.read_exact(&mut record) {
+            Ok(()) => Ok(Some(postcard::from_bytes(&record)?)),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl<I> Iterator for EventLogReader<I>
+where
+    I: Input,
+{
+    type Item = Result<RecordedEvent<I>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+/// An event manager feeding the events recorded by an [`EventRecorder`] back into the fuzzer.
+///
+/// Each event is handled in [`EventProcessor::process`], once the state reached the executions it was recorded at,
+/// after resetting the RNG to the recorded seed.
+/// Everything else, such as firing events and reporting progress, is done by the wrapped event manager.
+pub struct ReplayEventManager<EM>
+where
+    EM: UsesState,
+{
+    inner: EM,
+    /// The events that were not replayed yet
+    events: VecDeque<RecordedEvent<<EM::State as UsesInput>::Input>>,
+    /// Our configuration, to decide if the recorded observers can be reused
+    configuration: EventConfig,
+    /// The custom buf handler
+    custom_buf_handlers: Vec<Box<CustomBufHandlerFn<EM::State>>>,
+}
+
+impl<EM> core::fmt::Debug for ReplayEventManager<EM>
+where
+    EM: UsesState + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ReplayEventManager")
+            .field("inner", &self.inner)
+            .field("events", &self.events.len())
+            .field("configuration", &self.configuration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<EM> ReplayEventManager<EM>
+where
+    EM: UsesState + EventFirer,
+{
+    /// Replay the log at `path`, written by an [`EventRecorder`], wrapping the `inner` event manager
+    pub fn new<P>(inner: EM, path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let events = EventLogReader::new(path)?.collect::<Result<VecDeque<_>, _>>()?;
+        Ok(Self::with_events(inner, events))
+    }
+
+    /// Replay the given recorded events, wrapping the `inner` event manager
+    pub fn with_events(
+        inner: EM,
+        events: VecDeque<RecordedEvent<<EM::State as UsesInput>::Input>>,
+    ) -> Self {
+        let configuration = inner.configuration();
+        Self {
+            inner,
+            events,
+            configuration,
+            custom_buf_handlers: vec![],
+        }
+    }
+
+    /// The amount of events that were not replayed yet
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    /// The wrapped event manager
+    pub fn inner(&self) -> &EM {
+        &self.inner
+    }
+
+    /// The wrapped event manager (mutable)
+    pub fn inner_mut(&mut self) -> &mut EM {
+        &mut self.inner
+    }
+
+    // Handle a recorded event, like the llmp event manager would have handled it in the client
+    fn handle_recorded<E, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut EM::State,
+        recorded: RecordedEvent<<EM::State as UsesInput>::Input>,
+    ) -> Result<(), Error>
+    where
+        E: Executor<Self, Z> + HasObservers<State = EM::State>,
+        for<'a> E::Observers: Deserialize<'a>,
+        EM::State: HasExecutions + HasMetadata + HasRand,
+        Z: ExecutionProcessor<E::Observers, State = EM::State> + EvaluatorObservers<E::Observers>,
+    {
+        state.rand_mut().set_seed(recorded.seed);
+
+        match recorded.event {
+            Event::NewTestcase {
+                input,
+                client_config,
+                exit_kind,
+                observers_buf,
+                ..
+            } => {
+                let res = match observers_buf {
+                    Some(observers_buf) if client_config.match_with(&self.configuration) => {
+                        let observers: E::Observers = postcard::from_bytes(&observers_buf)?;
+                        fuzzer.execute_and_process(
+                            state, self, input, &observers, &exit_kind, false,
+                        )?
+                    }
+                    _ => fuzzer.evaluate_input_with_observers::<E, Self>(
+                        state, executor, self, input, false,
+                    )?,
+                };
+                if let Some(item) = res.1 {
+                    log::debug!(
+                        "Replayed Testcase from {:?} as item #{item}",
+                        recorded.client_id
+                    );
+                }
+            }
+            Event::CustomBuf { tag, buf } => {
+                for handler in &mut self.custom_buf_handlers {
+                    if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
+                        break;
+                    }
+                }
+            }
+            event => log::debug!("Skipping replay of {}", event.name_detailed()),
+        }
+        Ok(())
+    }
+}
+
+impl<EM> UsesState for ReplayEventManager<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for ReplayEventManager<EM>
+where
+    EM: EventFirer,
+{
+    fn should_send(&self) -> bool {
+        self.inner.should_send()
+    }
+
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.inner.fire(state, event)
+    }
+
+    fn log(
+        &mut self,
+        state: &mut Self::State,
+        severity_level: LogSeverity,
+        message: String,
+    ) -> Result<(), Error> {
+        self.inner.log(state, severity_level, message)
+    }
+
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for ReplayEventManager<EM>
+where
+    EM: EventRestarter,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.on_restart(state)
+    }
+
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for ReplayEventManager<EM>
+where
+    EM: EventProcessor<E, Z> + EventFirer,
+    E: HasObservers<State = Self::State> + Executor<Self, Z>,
+    for<'a> E::Observers: Deserialize<'a>,
+    Self::State: HasExecutions + HasMetadata + HasRand,
+    Z: ExecutionProcessor<E::Observers, State = Self::State> + EvaluatorObservers<E::Observers>,
+{
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        let mut count = self.inner.process(fuzzer, state, executor)?;
+        while self
+            .events
+            .front()
+            .is_some_and(|recorded| recorded.executions <= *state.executions())
+        {
+            let recorded = self.events.pop_front().unwrap();
+            self.handle_recorded(fuzzer, executor, state, recorded)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for ReplayEventManager<EM>
+where
+    EM: EventManager<E, Z>,
+    EM::State: HasExecutions + HasMetadata + HasLastReportTime + HasRand,
+    E: HasObservers<State = Self::State> + Executor<Self, Z>,
+    for<'a> E::Observers: Deserialize<'a>,
+    Z: ExecutionProcessor<E::Observers, State = Self::State> + EvaluatorObservers<E::Observers>,
+{
+}
+
+impl<EM> HasCustomBufHandlers for ReplayEventManager<EM>
+where
+    EM: UsesState,
+{
+    /// Adds a custom buffer handler that will run for each replayed `CustomBuf` event.
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.custom_buf_handlers.push(handler);
+    }
+}
+
+impl<EM> ProgressReporter for ReplayEventManager<EM>
+where
+    EM: ProgressReporter,
+    EM::State: HasMetadata + HasExecutions + HasLastReportTime,
+{
+}
+
+impl<EM> HasEventManagerId for ReplayEventManager<EM>
+where
+    EM: HasEventManagerId + UsesState,
+{
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+impl<EM> AdaptiveSerializer for ReplayEventManager<EM>
+where
+    EM: AdaptiveSerializer + UsesState,
+{
+    fn serialization_time(&self) -> Duration {
+        self.inner.serialization_time()
+    }
+    fn deserialization_time(&self) -> Duration {
+        self.inner.deserialization_time()
+    }
+    fn serializations_cnt(&self) -> usize {
+        self.inner.serializations_cnt()
+    }
+    fn should_serialize_cnt(&self) -> usize {
+        self.inner.should_serialize_cnt()
+    }
+
+    fn serialization_time_mut(&mut self) -> &mut Duration {
+        self.inner.serialization_time_mut()
+    }
+    fn deserialization_time_mut(&mut self) -> &mut Duration {
+        self.inner.deserialization_time_mut()
+    }
+    fn serializations_cnt_mut(&mut self) -> &mut usize {
+        self.inner.serializations_cnt_mut()
+    }
+    fn should_serialize_cnt_mut(&mut self) -> &mut usize {
+        self.inner.should_serialize_cnt_mut()
+    }
+
+    fn time_ref(&self) -> &Option<Handle<TimeObserver>> {
+        self.inner.time_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::{marker::PhantomData, time::Duration};
+    use std::{fs::OpenOptions, io::Write};
+
+    use libafl_bolts::ClientId;
+
+    use crate::{
+        events::{record::EventLogReader, Event, EventRecorder},
+        inputs::BytesInput,
+    };
+
+    #[test]
+    fn test_event_log_roundtrip() {
+        let path = std::env::temp_dir().join(format!("libafl-event-log-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut recorder = EventRecorder::new(&path).unwrap();
+        for i in 0..3_u64 {
+            let event = Event::<BytesInput>::UpdateExecStats {
+                time: Duration::from_secs(i),
+                executions: i * 100,
+                phantom: PhantomData,
+            };
+            recorder
+                .record(
+                    ClientId(i as u32),
+                    Duration::from_secs(i),
+                    i * 10,
+                    i + 1337,
+                    &event,
+                )
+                .unwrap();
+        }
+        drop(recorder);
+
+        let recorded = EventLogReader::<BytesInput>::new(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(recorded.len(), 3);
+        for (i, recorded) in recorded.iter().enumerate() {
+            assert_eq!(recorded.client_id, ClientId(i as u32));
+            assert_eq!(recorded.executions, i as u64 * 10);
+            assert_eq!(recorded.seed, i as u64 + 1337);
+            assert!(matches!(
+                recorded.event,
+                Event::UpdateExecStats { executions, .. } if executions == i as u64 * 100
+            ));
+        }
+
+        // A record with a corrupted length, or cut off by a crash while writing it, ends the log
+        let full_len = std::fs::metadata(&path).unwrap().len();
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        log.write_all(&u32::MAX.to_le_bytes()).unwrap();
+        let recorded = EventLogReader::<BytesInput>::new(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(recorded.len(), 3);
+
+        log.set_len(full_len - 1).unwrap();
+        let recorded = EventLogReader::<BytesInput>::new(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(recorded.len(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
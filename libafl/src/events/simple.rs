@@ -209,6 +209,12 @@ where
                 time,
                 executions,
                 ..
+            }
+            | Event::NewTestcaseAnnounced {
+                corpus_size,
+                time,
+                executions,
+                ..
             } => {
                 monitor.client_stats_insert(ClientId(0));
                 monitor
@@ -280,6 +286,10 @@ where
                 log::log!((*severity_level).into(), "{message}");
                 Ok(BrokerEventResult::Handled)
             }
+            Event::ClientRestarted { reason, .. } => {
+                log::warn!("Client got restarted: {reason}");
+                Ok(BrokerEventResult::Handled)
+            }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
             //_ => Ok(BrokerEventResult::Forward),
         }
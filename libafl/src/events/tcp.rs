@@ -1,6 +1,6 @@
 //! TCP-backed event manager for scalable multi-processed fuzzing
 
-use alloc::{boxed::Box, vec::Vec};
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
 #[cfg(all(unix, feature = "std", not(miri)))]
 use core::ptr::addr_of_mut;
 use core::{
@@ -44,6 +44,9 @@ use super::{CustomBufEventResult, CustomBufHandlerFn};
 #[cfg(all(unix, feature = "std", not(miri)))]
 use crate::events::EVENTMGR_SIGHANDLER_STATE;
 use crate::{
+    common::serialization::{
+        deserialize_with_header, serialize_with_header, Postcard, SerdeFormat, SerializationFormat,
+    },
     events::{
         BrokerEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerHooksTuple,
         EventManagerId, EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId,
@@ -52,7 +55,7 @@ use crate::{
     executors::{Executor, HasObservers},
     fuzzer::{EvaluatorObservers, ExecutionProcessor},
     inputs::{Input, UsesInput},
-    monitors::Monitor,
+    monitors::{AggregatorOps, Monitor, UserStats, UserStatsValue},
     state::{HasExecutions, HasLastReportTime, State, UsesState},
     Error, HasMetadata,
 };
@@ -290,8 +293,9 @@ where
             #[cfg(feature = "tcp_compression")]
             let event_bytes = GzipCompressor::new().decompress(event_bytes)?;
 
+            // Each client may use another serialization format
             #[allow(clippy::needless_borrow)] // make decompressed vec and slice compatible
-            let event: Event<I> = postcard::from_bytes(&event_bytes)?;
+            let event: Event<I> = deserialize_with_header(&event_bytes)?;
             match Self::handle_in_broker(&mut self.monitor, client_id, &event)? {
                 BrokerEventResult::Forward => {
                     tx_bc.send(buf).expect("Could not send");
@@ -336,6 +340,19 @@ where
                 monitor.display(event.name(), id);
                 Ok(BrokerEventResult::Forward)
             }
+            Event::NewTestcaseAnnounced {
+                corpus_size,
+                time,
+                executions,
+                ..
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_corpus_size(*corpus_size as u64);
+                client.update_executions(*executions, *time);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Forward)
+            }
             Event::UpdateExecStats {
                 time,
                 executions,
@@ -407,6 +424,18 @@ where
                 log::log!((*severity_level).into(), "{message}");
                 Ok(BrokerEventResult::Handled)
             }
+            Event::ClientRestarted { reason, restarts } => {
+                log::warn!("Client {client_id:?} got restarted: {reason}");
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_user_stats(
+                    Cow::Borrowed("restarts"),
+                    UserStats::new(UserStatsValue::Number(*restarts), AggregatorOps::Sum),
+                );
+                monitor.aggregate("restarts");
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
             //_ => Ok(BrokerEventResult::Forward),
         }
@@ -423,6 +452,8 @@ where
     throttle: Option<Duration>,
     /// When we sent the last message
     last_sent: Duration,
+    /// The format of the events we send, recorded in front of each event
+    serialization: SerializationFormat,
     hooks: EMH,
     /// The TCP stream for inter process communication
     tcp: TcpStream,
@@ -454,6 +485,7 @@ where
 #[derive(Debug, Copy, Clone)]
 pub struct TcpEventManagerBuilder<EMH, S> {
     throttle: Option<Duration>,
+    serialization: SerializationFormat,
     hooks: EMH,
     phantom: PhantomData<S>,
 }
@@ -470,6 +502,7 @@ impl<S> TcpEventManagerBuilder<(), S> {
     pub fn new() -> Self {
         Self {
             throttle: None,
+            serialization: SerializationFormat::Postcard,
             hooks: (),
             phantom: PhantomData,
         }
@@ -480,6 +513,7 @@ impl<S> TcpEventManagerBuilder<(), S> {
     pub fn hooks<EMH>(self, hooks: EMH) -> TcpEventManagerBuilder<EMH, S> {
         TcpEventManagerBuilder {
             throttle: self.throttle,
+            serialization: self.serialization,
             hooks,
             phantom: PhantomData,
         }
@@ -498,6 +532,14 @@ where
         self
     }
 
+    /// Serialize the events this manager sends in `serialization`, for example as JSON to debug them.
+    /// Received events are deserialized in whatever format their sender used.
+    #[must_use]
+    pub fn serialization_format(mut self, serialization: SerializationFormat) -> Self {
+        self.serialization = serialization;
+        self
+    }
+
     /// Create a manager from a raw TCP client with hooks
     pub fn build_from_client<A: ToSocketAddrs>(
         self,
@@ -520,6 +562,7 @@ where
         Ok(TcpEventManager {
             throttle: self.throttle,
             last_sent: Duration::from_secs(0),
+            serialization: self.serialization,
             hooks: self.hooks,
             tcp,
             client_id,
@@ -631,7 +674,7 @@ where
                     && observers_buf.is_some()
                 {
                     let observers: E::Observers =
-                        postcard::from_bytes(observers_buf.as_ref().unwrap())?;
+                        Postcard::deserialize(observers_buf.as_ref().unwrap())?;
                     #[cfg(feature = "scalability_introspection")]
                     {
                         state.scalability_monitor_mut().testcase_with_observers += 1;
@@ -710,7 +753,7 @@ where
         _state: &mut Self::State,
         event: Event<<Self::State as UsesInput>::Input>,
     ) -> Result<(), Error> {
-        let serialized = postcard::to_allocvec(&event)?;
+        let serialized = serialize_with_header(self.serialization, &event)?;
 
         #[cfg(feature = "tcp_compression")]
         let serialized = self.compressor.compress(&serialized);
@@ -789,7 +832,7 @@ where
 
                         // make decompressed vec and slice compatible
                         #[allow(clippy::needless_borrow)]
-                        let event = postcard::from_bytes(&buf)?;
+                        let event = deserialize_with_header(&buf)?;
 
                         self.handle_in_client(fuzzer, executor, state, other_client_id, event)?;
                         count += 1;
@@ -11,23 +11,24 @@ use std::os::unix::ffi::OsStrExt;
 use std::process::Child;
 use std::{
     ffi::{OsStr, OsString},
-    io::{Read, Write},
+    io::Write,
     path::{Path, PathBuf},
     process::{Command, Stdio},
     time::Duration,
 };
 
+#[cfg(feature = "std")]
+use libafl_bolts::os::supervisor::Supervisor;
 use libafl_bolts::{
     fs::{get_unique_std_input_file, InputFile},
     tuples::{Handle, MatchName, RefIndexable},
-    AsSlice,
 };
 
 #[cfg(all(feature = "std", unix))]
 use crate::executors::{Executor, ExitKind};
 use crate::{
     executors::HasObservers,
-    inputs::{HasTargetBytes, UsesInput},
+    inputs::{HasTargetBytes, StreamingInput, UsesInput},
     observers::{ObserversTuple, StdErrObserver, StdOutObserver, UsesObservers},
     state::{HasExecutions, State, UsesState},
     std::borrow::ToOwned,
@@ -67,10 +68,14 @@ pub struct StdCommandConfigurator {
     stdout_observer: Option<Handle<StdOutObserver>>,
     stderr_observer: Option<Handle<StdErrObserver>>,
     timeout: Duration,
+    /// The memory limit of the child, in bytes
+    memory_limit: Option<usize>,
     /// true: input gets delivered via stdink
     input_location: InputLocation,
     /// The Command to execute
     command: Command,
+    /// The buffer the target bytes are rendered into, reused between executions
+    buffer: Vec<u8>,
 }
 
 impl<I> CommandConfigurator<I> for StdCommandConfigurator
@@ -90,6 +95,7 @@ where
             InputLocation::Arg { argnum } => {
                 let args = self.command.get_args();
                 let mut cmd = Command::new(self.command.get_program());
+                cmd.stdin(Stdio::null());
 
                 if !self.debug_child {
                     cmd.stdout(Stdio::null());
@@ -107,7 +113,7 @@ where
                     if i == *argnum {
                         debug_assert_eq!(arg, "DUMMY");
                         #[cfg(unix)]
-                        cmd.arg(OsStr::from_bytes(input.target_bytes().as_slice()));
+                        cmd.arg(OsStr::from_bytes(input.target_bytes_into(&mut self.buffer)));
                         // There is an issue here that the chars on Windows are 16 bit wide.
                         // I can't really test it. Please open a PR if this goes wrong.
                         #[cfg(not(unix))]
@@ -124,12 +130,13 @@ where
                 if let Some(cwd) = self.command.get_current_dir() {
                     cmd.current_dir(cwd);
                 }
+                self.std_supervisor().configure(&mut cmd);
                 Ok(cmd.spawn()?)
             }
             InputLocation::StdIn => {
                 let mut handle = self.command.stdin(Stdio::piped()).spawn()?;
                 let mut stdin = handle.stdin.take().unwrap();
-                if let Err(err) = stdin.write_all(input.target_bytes().as_slice()) {
+                if let Err(err) = stdin.write_all(input.target_bytes_into(&mut self.buffer)) {
                     if err.kind() != std::io::ErrorKind::BrokenPipe {
                         return Err(err.into());
                     }
@@ -142,7 +149,7 @@ where
                 Ok(handle)
             }
             InputLocation::File { out_file } => {
-                out_file.write_buf(input.target_bytes().as_slice())?;
+                out_file.write_buf(input.target_bytes_into(&mut self.buffer))?;
                 Ok(self.command.spawn()?)
             }
         }
@@ -151,6 +158,68 @@ where
     fn exec_timeout(&self) -> Duration {
         self.timeout
     }
+
+    fn supervisor(&self) -> Supervisor {
+        self.std_supervisor()
+    }
+}
+
+/// Streams the [`StreamingInput`] to the target, without holding its content in memory.
+/// It can not be passed as an argument.
+impl CommandConfigurator<StreamingInput> for StdCommandConfigurator {
+    fn stdout_observer(&self) -> Option<Handle<StdOutObserver>> {
+        self.stdout_observer.clone()
+    }
+
+    fn stderr_observer(&self) -> Option<Handle<StdErrObserver>> {
+        self.stderr_observer.clone()
+    }
+
+    fn spawn_child(&mut self, input: &StreamingInput) -> Result<Child, Error> {
+        match &mut self.input_location {
+            InputLocation::Arg { .. } => Err(Error::illegal_argument(
+                "A StreamingInput can not be passed as an argument, use stdin or a file",
+            )),
+            InputLocation::StdIn => {
+                let mut handle = self.command.stdin(Stdio::piped()).spawn()?;
+                let mut stdin = handle.stdin.take().unwrap();
+                match input.write_to(&mut stdin).and_then(|()| Ok(stdin.flush()?)) {
+                    Err(Error::OsError(err, ..))
+                        if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+                    result => result?,
+                }
+                drop(stdin);
+                Ok(handle)
+            }
+            InputLocation::File { out_file } => {
+                input.write_to_file(out_file)?;
+                Ok(self.command.spawn()?)
+            }
+        }
+    }
+
+    fn exec_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn supervisor(&self) -> Supervisor {
+        self.std_supervisor()
+    }
+}
+
+impl StdCommandConfigurator {
+    /// The [`Supervisor`] for the children, which also cleans up everything the target spawned itself
+    fn std_supervisor(&self) -> Supervisor {
+        let supervisor = Supervisor::new()
+            .timeout(self.timeout)
+            .capture_stdout(self.stdout_observer.is_some())
+            .capture_stderr(self.stderr_observer.is_some())
+            .kill_tree(true);
+        match self.memory_limit {
+            Some(limit) => supervisor.memory_limit(limit),
+            None => supervisor,
+        }
+    }
 }
 
 /// A `CommandExecutor` is a wrapper around [`std::process::Command`] to execute a target as a child process.
@@ -206,7 +275,7 @@ where
     }
 }
 
-// this only works on unix because of the reliance on checking the process signal for detecting OOM
+// the `StdCommandConfigurator` only builds commandlines on unix, for now
 #[cfg(all(feature = "std", unix))]
 impl<EM, OT, S, T, Z> Executor<EM, Z> for CommandExecutor<OT, S, T>
 where
@@ -223,62 +292,44 @@ where
         _mgr: &mut EM,
         input: &Self::Input,
     ) -> Result<ExitKind, Error> {
-        use std::os::unix::prelude::ExitStatusExt;
-
-        use wait_timeout::ChildExt;
+        use libafl_bolts::os::supervisor::ChildExit;
 
         *state.executions_mut() += 1;
         self.observers.pre_exec_child_all(state, input)?;
 
-        let mut child = self.configurer.spawn_child(input)?;
-
-        let res = match child
-            .wait_timeout(self.configurer.exec_timeout())
-            .expect("waiting on child failed")
-            .map(|status| status.signal())
-        {
-            // for reference: https://www.man7.org/linux/man-pages/man7/signal.7.html
-            Some(Some(9)) => Ok(ExitKind::Oom),
-            Some(Some(_)) => Ok(ExitKind::Crash),
-            Some(None) => Ok(ExitKind::Ok),
-            None => {
-                // if this fails, there is not much we can do. let's hope it failed because the process finished
-                // in the meantime.
-                drop(child.kill());
-                // finally, try to wait to properly clean up system resources.
-                drop(child.wait());
-                Ok(ExitKind::Timeout)
-            }
+        let child = self.configurer.spawn_child(input)?;
+        let outcome = self.configurer.supervisor().supervise(child)?.wait()?;
+        let exit_kind = match outcome.exit {
+            ChildExit::Ok | ChildExit::Failed(_) => ExitKind::Ok,
+            ChildExit::Crashed(_) => ExitKind::Crash,
+            ChildExit::OutOfMemory => ExitKind::Oom,
+            ChildExit::Timeout => ExitKind::Timeout,
         };
 
-        if let Ok(exit_kind) = res {
-            self.observers
-                .post_exec_child_all(state, input, &exit_kind)?;
-        }
+        self.observers
+            .post_exec_child_all(state, input, &exit_kind)?;
 
         if let Some(h) = &mut self.configurer.stdout_observer() {
-            let mut stdout = Vec::new();
-            child.stdout.as_mut().ok_or_else(|| {
-                 Error::illegal_state(
-                     "Observer tries to read stderr, but stderr was not `Stdio::pipe` in CommandExecutor",
-                 )
-             })?.read_to_end(&mut stdout)?;
+            let stdout = outcome.stdout.ok_or_else(|| {
+                Error::illegal_state(
+                    "Observer tries to read stdout, but stdout was not `Stdio::pipe` in CommandExecutor",
+                )
+            })?;
             let mut observers = self.observers_mut();
             let obs = observers.index_mut(h);
             obs.observe_stdout(&stdout);
         }
         if let Some(h) = &mut self.configurer.stderr_observer() {
-            let mut stderr = Vec::new();
-            child.stderr.as_mut().ok_or_else(|| {
-                 Error::illegal_state(
-                     "Observer tries to read stderr, but stderr was not `Stdio::pipe` in CommandExecutor",
-                 )
-             })?.read_to_end(&mut stderr)?;
+            let stderr = outcome.stderr.ok_or_else(|| {
+                Error::illegal_state(
+                    "Observer tries to read stderr, but stderr was not `Stdio::pipe` in CommandExecutor",
+                )
+            })?;
             let mut observers = self.observers_mut();
             let obs = observers.index_mut(h);
             obs.observe_stderr(&stderr);
         }
-        res
+        Ok(exit_kind)
     }
 }
 
@@ -324,6 +375,7 @@ pub struct CommandExecutorBuilder {
     cwd: Option<PathBuf>,
     envs: Vec<(OsString, OsString)>,
     timeout: Duration,
+    memory_limit: Option<usize>,
 }
 
 impl Default for CommandExecutorBuilder {
@@ -345,6 +397,7 @@ impl CommandExecutorBuilder {
             cwd: None,
             envs: vec![],
             timeout: Duration::from_secs(5),
+            memory_limit: None,
             debug_child: false,
         }
     }
@@ -475,6 +528,13 @@ impl CommandExecutorBuilder {
         self
     }
 
+    /// Limits the memory of the target to `bytes`, see [`Supervisor::memory_limit`].
+    /// Targets running out of memory are reported as [`crate::executors::ExitKind::Oom`] if they get killed, and usually as crashes if they abort.
+    pub fn memory_limit(&mut self, bytes: usize) -> &mut CommandExecutorBuilder {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
     /// Builds the `CommandExecutor`
     pub fn build<OT, S>(
         &self,
@@ -483,7 +543,8 @@ impl CommandExecutorBuilder {
     where
         OT: MatchName + ObserversTuple<S>,
         S: UsesInput,
-        S::Input: Input + HasTargetBytes,
+        S::Input: Input,
+        StdCommandConfigurator: CommandConfigurator<S::Input>,
     {
         let Some(program) = &self.program else {
             return Err(Error::illegal_argument(
@@ -522,14 +583,19 @@ impl CommandExecutorBuilder {
             command.stderr(Stdio::piped());
         }
 
-        let configurator = StdCommandConfigurator {
+        let mut configurator = StdCommandConfigurator {
             debug_child: self.debug_child,
             stdout_observer: self.stdout.clone(),
             stderr_observer: self.stderr.clone(),
             input_location: self.input_location.clone(),
             timeout: self.timeout,
+            memory_limit: self.memory_limit,
             command,
+            buffer: Vec::new(),
         };
+        configurator
+            .std_supervisor()
+            .configure(&mut configurator.command);
         Ok(
             <StdCommandConfigurator as CommandConfigurator<S::Input>>::into_executor::<OT, S>(
                 configurator,
@@ -598,6 +664,17 @@ pub trait CommandConfigurator<I>: Sized {
     /// Provides timeout duration for execution of the child process.
     fn exec_timeout(&self) -> Duration;
 
+    /// The [`Supervisor`] waiting for the child, enforcing the [`Self::exec_timeout`] and capturing the output for the observers.
+    ///
+    /// Override it for further limits. Those set up before the spawn, like [`Supervisor::memory_limit`],
+    /// need [`Supervisor::configure`] to be called on the [`Command`] in [`Self::spawn_child`].
+    fn supervisor(&self) -> Supervisor {
+        Supervisor::new()
+            .timeout(self.exec_timeout())
+            .capture_stdout(self.stdout_observer().is_some())
+            .capture_stderr(self.stderr_observer().is_some())
+    }
+
     /// Create an `Executor` from this `CommandConfigurator`.
     fn into_executor<OT, S>(self, observers: OT) -> CommandExecutor<OT, S, Self>
     where
@@ -617,10 +694,10 @@ mod tests {
         events::SimpleEventManager,
         executors::{
             command::{CommandExecutor, InputLocation},
-            Executor,
+            Executor, ExitKind,
         },
         fuzzer::test::NopFuzzer,
-        inputs::BytesInput,
+        inputs::{BytesInput, StreamingInput},
         monitors::SimpleMonitor,
         state::NopState,
     };
@@ -649,4 +726,53 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_streaming_input() {
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|status| {
+            log::info!("{status}");
+        }));
+        let path = std::env::temp_dir().join(format!(
+            "libafl_command_streaming_test_{}",
+            std::process::id()
+        ));
+        std::fs::write(&path, b"hello").unwrap();
+        let mut input = StreamingInput::open(&path).unwrap();
+        input.splice(5..5, b" world");
+
+        let mut executor = CommandExecutor::builder();
+        executor
+            .program("sh")
+            .args(["-c", r#"[ "$(cat)" = "hello world" ] || kill -SEGV $$"#])
+            .input(InputLocation::StdIn);
+        let mut executor = executor.build(()).unwrap();
+        assert_eq!(
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut mgr,
+                    &input
+                )
+                .unwrap(),
+            ExitKind::Ok
+        );
+
+        input.splice(0..1, b"j");
+        assert_eq!(
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut mgr,
+                    &input
+                )
+                .unwrap(),
+            ExitKind::Crash
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
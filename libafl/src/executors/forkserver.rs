@@ -1,6 +1,10 @@
 //! Expose an `Executor` based on a `Forkserver` in order to execute AFL/AFL++ binaries
 
-use alloc::{borrow::ToOwned, string::ToString, vec::Vec};
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+    vec::Vec,
+};
 use core::{
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
@@ -21,10 +25,9 @@ use std::{
 use libafl_bolts::{
     fs::{get_unique_std_input_file, InputFile},
     os::{dup2, pipes::Pipe},
-    ownedref::OwnedSlice,
     shmem::{ShMem, ShMemProvider, UnixShMemProvider},
     tuples::{Handle, Handled, MatchNameRef, Prepend, RefIndexable},
-    AsSlice, AsSliceMut, Truncate,
+    AsSliceMut, Truncate,
 };
 use nix::{
     sys::{
@@ -533,6 +536,7 @@ where
     map_size: Option<usize>,
     min_input_size: usize,
     max_input_size: usize,
+    input_buffer: Vec<u8>,
     #[cfg(feature = "regex")]
     asan_obs: Handle<AsanBacktraceObserver>,
     timeout: TimeSpec,
@@ -683,6 +687,7 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             map_size: self.map_size,
             min_input_size: self.min_input_size,
             max_input_size: self.max_input_size,
+            input_buffer: Vec::new(),
             timeout,
             asan_obs: self
                 .asan_obs
@@ -747,6 +752,7 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             map_size: self.map_size,
             min_input_size: self.min_input_size,
             max_input_size: self.max_input_size,
+            input_buffer: Vec::new(),
             timeout,
             asan_obs: self
                 .asan_obs
@@ -910,7 +916,7 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
                 return Err(Error::unknown("Failed to load autodictionary".to_string()));
             }
             if let Some(t) = &mut self.autotokens {
-                t.parse_autodict(&buf, dict_size as usize);
+                t.parse_autodict(&buf, dict_size as usize)?;
             }
         }
 
@@ -1228,19 +1234,16 @@ where
 
         let last_run_timed_out = self.forkserver.last_run_timed_out_raw();
 
-        let mut input_bytes = input.target_bytes();
-        let mut input_size = input_bytes.as_slice().len();
+        // Reuse the buffer between executions, inputs holding their bytes don't even copy into it
+        let mut input_bytes = Cow::Borrowed(input.target_bytes_into(&mut self.input_buffer));
+        let mut input_size = input_bytes.len();
         if input_size > self.max_input_size {
             // Truncate like AFL++ does
             input_size = self.max_input_size;
         } else if input_size < self.min_input_size {
             // Extend like AFL++ does
             input_size = self.min_input_size;
-            let mut input_bytes_copy = Vec::with_capacity(input_size);
-            input_bytes_copy
-                .as_slice_mut()
-                .copy_from_slice(input_bytes.as_slice());
-            input_bytes = OwnedSlice::from(input_bytes_copy);
+            input_bytes.to_mut().resize(input_size, 0);
         }
         let input_size_in_bytes = input_size.to_ne_bytes();
         if self.uses_shmem_testcase {
@@ -1255,10 +1258,9 @@ where
             map.as_slice_mut()[..SHMEM_FUZZ_HDR_SIZE]
                 .copy_from_slice(&input_size_in_bytes[..SHMEM_FUZZ_HDR_SIZE]);
             map.as_slice_mut()[SHMEM_FUZZ_HDR_SIZE..(SHMEM_FUZZ_HDR_SIZE + input_size)]
-                .copy_from_slice(&input_bytes.as_slice()[..input_size]);
+                .copy_from_slice(&input_bytes[..input_size]);
         } else {
-            self.input_file
-                .write_buf(&input_bytes.as_slice()[..input_size])?;
+            self.input_file.write_buf(&input_bytes[..input_size])?;
         }
 
         let send_len = self.forkserver.write_ctl(last_run_timed_out)?;
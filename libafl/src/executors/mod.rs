@@ -16,6 +16,7 @@ pub use inprocess_fork::InProcessForkExecutor;
 #[cfg(unix)]
 use libafl_bolts::os::unix_signals::Signal;
 use libafl_bolts::tuples::RefIndexable;
+pub use post_process::{PostProcessExecutor, TargetBytesPostProcessor};
 use serde::{Deserialize, Serialize};
 pub use shadow::ShadowExecutor;
 pub use with_observers::WithObservers;
@@ -38,6 +39,9 @@ pub mod inprocess;
 #[cfg(all(feature = "std", unix))]
 pub mod inprocess_fork;
 
+/// The module for the [`PostProcessExecutor`], fixing up the bytes of each input before the execution
+pub mod post_process;
+
 pub mod shadow;
 
 pub mod with_observers;
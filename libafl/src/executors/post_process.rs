@@ -0,0 +1,210 @@
+//! The [`PostProcessExecutor`] applies [`TargetBytesPostProcessor`]s to the bytes of each input
+//! right before handing them to the target, to fix checksums, update length fields, or re-sign blobs.
+//!
+//! The corpus, the solutions, and the events keep the input before post-processing,
+//! so that mutations keep working on the unfixed form, and reproducers run through the same fixups.
+
+use alloc::vec::Vec;
+
+use libafl_bolts::tuples::RefIndexable;
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::{HasMutatorBytes, UsesInput},
+    observers::UsesObservers,
+    state::UsesState,
+    Error,
+};
+
+/// Fixes up the bytes of an input, right before they get delivered to the target
+pub trait TargetBytesPostProcessor {
+    /// Post-process the `bytes` in place
+    fn post_process(&mut self, bytes: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+impl TargetBytesPostProcessor for () {
+    #[inline]
+    fn post_process(&mut self, _bytes: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<Head, Tail> TargetBytesPostProcessor for (Head, Tail)
+where
+    Head: TargetBytesPostProcessor,
+    Tail: TargetBytesPostProcessor,
+{
+    /// Post-process with all processors, in order
+    fn post_process(&mut self, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        self.0.post_process(bytes)?;
+        self.1.post_process(bytes)
+    }
+}
+
+impl<F> TargetBytesPostProcessor for F
+where
+    F: FnMut(&mut Vec<u8>) -> Result<(), Error>,
+{
+    #[inline]
+    fn post_process(&mut self, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        self(bytes)
+    }
+}
+
+/// A wrapper for any [`Executor`], running the target with the post-processed bytes of each input.
+///
+/// The inner executor gets a copy of the input with the post-processed bytes,
+/// while the caller, and thereby the corpus, keeps the original one.
+#[derive(Debug)]
+pub struct PostProcessExecutor<E, P> {
+    executor: E,
+    post_processor: P,
+}
+
+impl<E, P> PostProcessExecutor<E, P> {
+    /// Wraps the given [`Executor`], applying `post_processor` before each execution.
+    /// To apply several processors in order, pass a `tuple_list` of them.
+    pub fn new(executor: E, post_processor: P) -> Self {
+        Self {
+            executor,
+            post_processor,
+        }
+    }
+
+    /// The wrapped executor
+    pub fn executor(&self) -> &E {
+        &self.executor
+    }
+
+    /// The wrapped executor (mutable)
+    pub fn executor_mut(&mut self) -> &mut E {
+        &mut self.executor
+    }
+
+    /// The post-processor
+    pub fn post_processor_mut(&mut self) -> &mut P {
+        &mut self.post_processor
+    }
+}
+
+impl<E, EM, P, Z> Executor<EM, Z> for PostProcessExecutor<E, P>
+where
+    E: Executor<EM, Z>,
+    EM: UsesState<State = Self::State>,
+    P: TargetBytesPostProcessor,
+    Z: UsesState<State = Self::State>,
+    <Self::State as UsesInput>::Input: HasMutatorBytes + Clone,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        let mut bytes = input.bytes().to_vec();
+        self.post_processor.post_process(&mut bytes)?;
+
+        let mut processed = input.clone();
+        processed.resize(bytes.len(), 0);
+        processed.bytes_mut().copy_from_slice(&bytes);
+        self.executor.run_target(fuzzer, state, mgr, &processed)
+    }
+}
+
+impl<E, P> UsesState for PostProcessExecutor<E, P>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, P> UsesObservers for PostProcessExecutor<E, P>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E, P> HasObservers for PostProcessExecutor<E, P>
+where
+    E: HasObservers,
+{
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        self.executor.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use libafl_bolts::{tuples::tuple_list, Error};
+
+    use crate::{
+        events::NopEventManager,
+        executors::{
+            post_process::{PostProcessExecutor, TargetBytesPostProcessor},
+            test::NopExecutor,
+            Executor, ExitKind,
+        },
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        state::NopState,
+    };
+
+    #[test]
+    fn test_post_process_executor() {
+        // A length prefix, and an additive checksum at the end
+        let mut processors = tuple_list!(
+            |bytes: &mut Vec<u8>| {
+                bytes.insert(0, u8::try_from(bytes.len()).unwrap());
+                Ok(())
+            },
+            |bytes: &mut Vec<u8>| {
+                let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+                bytes.push(sum);
+                Ok::<(), Error>(())
+            }
+        );
+        let mut bytes = vec![1, 2, 3];
+        processors.post_process(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![3, 1, 2, 3, 9]);
+
+        // The inner executor fails on empty inputs, so it has to see the processed bytes
+        let mut executor = PostProcessExecutor::new(NopExecutor::new(), |bytes: &mut Vec<u8>| {
+            bytes.clear();
+            Ok(())
+        });
+        let input = BytesInput::new(vec![1]);
+        let mut state = NopState::new();
+        executor
+            .run_target(
+                &mut NopFuzzer::new(),
+                &mut state,
+                &mut NopEventManager::new(),
+                &input,
+            )
+            .unwrap_err();
+        assert_eq!(input, BytesInput::new(vec![1]));
+
+        let mut executor = PostProcessExecutor::new(NopExecutor::new(), ());
+        assert_eq!(
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut state,
+                    &mut NopEventManager::new(),
+                    &input,
+                )
+                .unwrap(),
+            ExitKind::Ok
+        );
+    }
+}
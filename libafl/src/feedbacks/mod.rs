@@ -111,6 +111,9 @@ where
         state
             .introspection_monitor_mut()
             .update_feedback(self.name(), elapsed);
+        if matches!(ret, Ok(true)) {
+            state.introspection_monitor_mut().hit_feedback(self.name());
+        }
 
         ret
     }
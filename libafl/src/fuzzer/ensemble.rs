@@ -0,0 +1,440 @@
+//! Ensemble fuzzing: several differently configured fuzzers, taking turns on one shared state.
+//!
+//! Each member of an [`EnsembleFuzzer`] brings its own feedbacks, objectives, scheduler and stages,
+//! while the corpus, the solutions and all metadata are shared through the state.
+//! Compared to running the configurations in separate processes, nothing has to be synced,
+//! and each member immediately builds on the testcases found by the others.
+
+use alloc::{borrow::Cow, format, vec::Vec};
+
+use libafl_bolts::{impl_serdeany, tuples::HasConstLen, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    events::ProgressReporter,
+    feedbacks::Feedback,
+    fuzzer::{Fuzzer, HasFeedback, HasObjective, STATS_TIMEOUT_DEFAULT},
+    stages::{HasCurrentStage, StagesTuple},
+    state::{
+        HasCorpus, HasExecutions, HasImported, HasLastReportTime, HasSolutions, State, UsesState,
+    },
+    Error, HasMetadata,
+};
+
+/// The statistics of a member of an [`EnsembleFuzzer`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnsembleMemberStats {
+    /// The iterations this member ran
+    pub iterations: u64,
+    /// The testcases this member added to the corpus
+    pub corpus_entries: u64,
+    /// The solutions this member found
+    pub solutions: u64,
+}
+
+/// The metadata of an [`EnsembleFuzzer`]: which member runs next, and what each member found so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct EnsembleMetadata {
+    /// The statistics of each member, in the order of the members
+    pub members: Vec<EnsembleMemberStats>,
+    /// The member that currently runs, to resume it after a restart
+    current: Option<usize>,
+    /// The member to run next
+    next: usize,
+}
+
+impl_serdeany!(EnsembleMetadata);
+
+/// A member of an [`EnsembleFuzzer`]: a fuzzer, with its own feedbacks and scheduler, and its stages
+#[derive(Debug)]
+pub struct EnsembleMember<Z, ST> {
+    name: Cow<'static, str>,
+    fuzzer: Z,
+    stages: ST,
+}
+
+impl<Z, ST> EnsembleMember<Z, ST> {
+    /// Create a new [`EnsembleMember`]
+    pub fn new<N>(name: N, fuzzer: Z, stages: ST) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        Self {
+            name: name.into(),
+            fuzzer,
+            stages,
+        }
+    }
+
+    /// The fuzzer of this member
+    pub fn fuzzer(&self) -> &Z {
+        &self.fuzzer
+    }
+
+    /// The fuzzer of this member (mutable)
+    pub fn fuzzer_mut(&mut self) -> &mut Z {
+        &mut self.fuzzer
+    }
+
+    /// The stages of this member (mutable)
+    pub fn stages_mut(&mut self) -> &mut ST {
+        &mut self.stages
+    }
+}
+
+impl<Z, ST> Named for EnsembleMember<Z, ST> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+/// The members of an [`EnsembleFuzzer`], as a tuple list of [`EnsembleMember`]s
+pub trait EnsembleMembersTuple<S>: HasConstLen {
+    /// The names of all members, each with the name of its feedback
+    fn feedback_names(&self) -> Vec<(&str, &str)>;
+
+    /// Initialize the feedbacks and objectives of all members on a new state
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error>;
+}
+
+impl<S> EnsembleMembersTuple<S> for () {
+    fn feedback_names(&self) -> Vec<(&str, &str)> {
+        Vec::new()
+    }
+
+    fn init_state(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<S, Z, ST, Tail> EnsembleMembersTuple<S> for (EnsembleMember<Z, ST>, Tail)
+where
+    S: State,
+    Z: HasFeedback<State = S> + HasObjective<State = S>,
+    Tail: EnsembleMembersTuple<S>,
+{
+    fn feedback_names(&self) -> Vec<(&str, &str)> {
+        let mut names = self.1.feedback_names();
+        names.insert(0, (&self.0.name, self.0.fuzzer.feedback().name()));
+        names
+    }
+
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        self.0.fuzzer.feedback_mut().init_state(state)?;
+        self.0.fuzzer.objective_mut().init_state(state)?;
+        self.1.init_state(state)
+    }
+}
+
+/// Runs a single iteration of a member of an [`EnsembleFuzzer`]
+pub trait FuzzEnsembleMembersTuple<E, EM, S>: EnsembleMembersTuple<S> {
+    /// Run an iteration of the member at `index`
+    fn fuzz_one_member(
+        &mut self,
+        index: usize,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<CorpusId, Error>;
+}
+
+impl<E, EM, S> FuzzEnsembleMembersTuple<E, EM, S> for () {
+    fn fuzz_one_member(
+        &mut self,
+        _index: usize,
+        _executor: &mut E,
+        _state: &mut S,
+        _manager: &mut EM,
+    ) -> Result<CorpusId, Error> {
+        Err(Error::illegal_argument("No such ensemble member"))
+    }
+}
+
+impl<E, EM, S, Z, ST, Tail> FuzzEnsembleMembersTuple<E, EM, S> for (EnsembleMember<Z, ST>, Tail)
+where
+    S: State + HasMetadata + HasExecutions + HasLastReportTime + HasCurrentStage,
+    E: UsesState<State = S>,
+    EM: ProgressReporter<State = S>,
+    Z: Fuzzer<E, EM, ST> + HasFeedback<State = S> + HasObjective<State = S>,
+    ST: StagesTuple<E, EM, S, Z>,
+    Tail: FuzzEnsembleMembersTuple<E, EM, S>,
+{
+    fn fuzz_one_member(
+        &mut self,
+        index: usize,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<CorpusId, Error> {
+        if index == 0 {
+            self.0
+                .fuzzer
+                .fuzz_one(&mut self.0.stages, executor, state, manager)
+        } else {
+            self.1.fuzz_one_member(index - 1, executor, state, manager)
+        }
+    }
+}
+
+/// Runs several fuzzers, each with its own feedbacks, objectives, scheduler and stages,
+/// round robin on one shared state, and so on one shared corpus.
+///
+/// All members share the metadata of the state. Feedbacks that keep metadata, such as the history of a
+/// [`crate::feedbacks::MapFeedback`], store it under their name, so [`EnsembleFuzzer::init_state`] rejects
+/// members whose feedbacks have the same name. Use, for example, [`crate::feedbacks::MapFeedback::with_name`]
+/// to give each member its own history for the same observer.
+///
+/// New testcases are only reported to the scheduler of the member that found them.
+/// As schedulers keep their state in the metadata, the members should use the same kind of scheduler.
+#[derive(Debug)]
+pub struct EnsembleFuzzer<MT> {
+    members: MT,
+}
+
+impl<MT> EnsembleFuzzer<MT> {
+    /// Create a new [`EnsembleFuzzer`] from a tuple list of [`EnsembleMember`]s
+    pub fn new(members: MT) -> Self {
+        Self { members }
+    }
+
+    /// The members
+    pub fn members(&self) -> &MT {
+        &self.members
+    }
+
+    /// The members (mutable)
+    pub fn members_mut(&mut self) -> &mut MT {
+        &mut self.members
+    }
+
+    /// Initialize the feedbacks and objectives of all members on a new state.
+    /// Call it once after creating the state, but not on a state restored after a restart.
+    pub fn init_state<S>(&mut self, state: &mut S) -> Result<(), Error>
+    where
+        MT: EnsembleMembersTuple<S>,
+        S: HasMetadata,
+    {
+        let names = self.members.feedback_names();
+        for (i, (member, feedback)) in names.iter().enumerate() {
+            if let Some((other, _)) = names[..i].iter().find(|(_, other)| other == feedback) {
+                return Err(Error::illegal_argument(format!(
+                    "The ensemble members {other} and {member} both use a feedback named {feedback}, \
+                     rename one of them to keep their metadata apart"
+                )));
+            }
+        }
+
+        self.members.init_state(state)?;
+        state.add_metadata(EnsembleMetadata {
+            members: vec![EnsembleMemberStats::default(); MT::LEN],
+            current: None,
+            next: 0,
+        });
+        Ok(())
+    }
+
+    /// Run a single iteration of the next member.
+    /// Returns the index of the fuzzed corpus item.
+    pub fn fuzz_one<E, EM, S>(
+        &mut self,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<CorpusId, Error>
+    where
+        MT: FuzzEnsembleMembersTuple<E, EM, S>,
+        S: HasMetadata + HasCorpus + HasSolutions + HasImported,
+    {
+        if MT::LEN == 0 {
+            return Err(Error::illegal_state("The ensemble has no members"));
+        }
+
+        let metadata = state.metadata_or_insert_with(EnsembleMetadata::default);
+        metadata
+            .members
+            .resize(MT::LEN, EnsembleMemberStats::default());
+        // Resume the member that was interrupted by a restart, if any
+        let index = if let Some(index) = metadata.current {
+            index
+        } else {
+            let index = metadata.next % MT::LEN;
+            metadata.next = (index + 1) % MT::LEN;
+            metadata.current = Some(index);
+            index
+        };
+
+        let corpus_count = state.corpus().count();
+        let solutions_count = state.solutions().count();
+        let imported = *state.imported();
+
+        let id = self
+            .members
+            .fuzz_one_member(index, executor, state, manager)?;
+
+        // Testcases imported from other nodes do not count as finds of this member
+        let corpus_entries = state
+            .corpus()
+            .count()
+            .saturating_sub(corpus_count)
+            .saturating_sub(state.imported().saturating_sub(imported))
+            as u64;
+        let solutions = state.solutions().count().saturating_sub(solutions_count) as u64;
+        let metadata = state.metadata_mut::<EnsembleMetadata>()?;
+        let stats = &mut metadata.members[index];
+        stats.iterations += 1;
+        stats.corpus_entries += corpus_entries;
+        stats.solutions += solutions;
+        metadata.current = None;
+
+        Ok(id)
+    }
+
+    /// Fuzz forever (or until stopped)
+    pub fn fuzz_loop<E, EM, S>(
+        &mut self,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error>
+    where
+        MT: FuzzEnsembleMembersTuple<E, EM, S>,
+        EM: ProgressReporter<State = S>,
+        S: State
+            + HasMetadata
+            + HasCorpus
+            + HasSolutions
+            + HasImported
+            + HasExecutions
+            + HasLastReportTime,
+    {
+        loop {
+            manager.maybe_report_progress(state, STATS_TIMEOUT_DEFAULT)?;
+            self.fuzz_one(executor, state, manager)?;
+        }
+    }
+
+    /// Fuzz for `iters` iterations, spread over all members.
+    /// Returns the index of the last fuzzed corpus item.
+    pub fn fuzz_loop_for<E, EM, S>(
+        &mut self,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+        iters: u64,
+    ) -> Result<CorpusId, Error>
+    where
+        MT: FuzzEnsembleMembersTuple<E, EM, S>,
+        EM: ProgressReporter<State = S>,
+        S: State
+            + HasMetadata
+            + HasCorpus
+            + HasSolutions
+            + HasImported
+            + HasExecutions
+            + HasLastReportTime,
+    {
+        if iters == 0 {
+            return Err(Error::illegal_argument("Cannot fuzz for 0 iterations!"));
+        }
+
+        let mut ret = None;
+        for _ in 0..iters {
+            manager.maybe_report_progress(state, STATS_TIMEOUT_DEFAULT)?;
+            ret = Some(self.fuzz_one(executor, state, manager)?);
+        }
+        manager.report_progress(state)?;
+
+        Ok(ret.unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list};
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::test::NopExecutor,
+        feedbacks::{ConstFeedback, CrashFeedback},
+        fuzzer::{
+            ensemble::{EnsembleFuzzer, EnsembleMember, EnsembleMetadata},
+            StdFuzzer,
+        },
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{HasCorpus, StdState},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_ensemble_round_robin() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![1])))
+            .unwrap();
+
+        let first = StdFuzzer::<_, _, _, ()>::new(QueueScheduler::new(), feedback, objective);
+        let second = StdFuzzer::<_, _, _, ()>::new(
+            QueueScheduler::new(),
+            CrashFeedback::new(),
+            ConstFeedback::new(false),
+        );
+        let mut ensemble = EnsembleFuzzer::new(tuple_list!(
+            EnsembleMember::new("first", first, ()),
+            EnsembleMember::new("second", second, ())
+        ));
+        ensemble.init_state(&mut state).unwrap();
+
+        let mut executor = NopExecutor::new();
+        let mut manager = NopEventManager::new();
+        ensemble
+            .fuzz_loop_for(&mut executor, &mut state, &mut manager, 3)
+            .unwrap();
+
+        let metadata = state.metadata::<EnsembleMetadata>().unwrap();
+        assert_eq!(metadata.members[0].iterations, 2);
+        assert_eq!(metadata.members[1].iterations, 1);
+    }
+
+    #[test]
+    fn test_ensemble_rejects_duplicate_feedbacks() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let first = StdFuzzer::<_, _, _, ()>::new(QueueScheduler::new(), feedback, objective);
+        let second = StdFuzzer::<_, _, _, ()>::new(
+            QueueScheduler::new(),
+            ConstFeedback::new(true),
+            ConstFeedback::new(false),
+        );
+        let mut ensemble = EnsembleFuzzer::new(tuple_list!(
+            EnsembleMember::new("first", first, ()),
+            EnsembleMember::new("second", second, ())
+        ));
+        assert!(ensemble.init_state(&mut state).is_err());
+    }
+}
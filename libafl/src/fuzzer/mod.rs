@@ -3,7 +3,7 @@
 use alloc::string::ToString;
 use core::{fmt::Debug, marker::PhantomData, time::Duration};
 
-use libafl_bolts::current_time;
+use libafl_bolts::{current_time, rands::Rand};
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
@@ -18,16 +18,37 @@ use crate::{
     stages::{HasCurrentStage, StagesTuple},
     start_timer,
     state::{
-        HasCorpus, HasCurrentTestcase, HasExecutions, HasImported, HasLastReportTime, HasSolutions,
-        UsesState,
+        HasCorpus, HasCurrentTestcase, HasExecutions, HasImported, HasLastReportTime, HasRand,
+        HasSolutions, UsesState,
     },
     Error, HasMetadata,
 };
 #[cfg(feature = "introspection")]
 use crate::{monitors::PerfFeature, state::HasClientPerfMonitor};
 
+pub mod ensemble;
+pub use ensemble::{EnsembleFuzzer, EnsembleMember, EnsembleMetadata};
+pub mod replay_log;
+pub use replay_log::{ReplayLogEntry, ReplayLogMetadata, ReplayLogStages};
+#[cfg(feature = "std")]
+pub mod reproduce;
+#[cfg(feature = "std")]
+pub use reproduce::{Reproducer, ReproductionReport};
+#[cfg(feature = "std")]
+pub mod quickstart;
+#[cfg(feature = "std")]
+pub use quickstart::{FuzzerBuilder, QuickstartConfig, SchedulerKind};
+#[cfg(feature = "std")]
+pub mod validate;
+#[cfg(feature = "std")]
+pub use validate::{HarnessValidator, ValidationIssue, ValidationReport};
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+#[cfg(feature = "telemetry")]
+pub use telemetry::{Telemetry, TelemetryDecision, TelemetryReader, TelemetryRecord};
+
 /// Send a monitor update all 15 (or more) seconds
-const STATS_TIMEOUT_DEFAULT: Duration = Duration::from_secs(15);
+pub(crate) const STATS_TIMEOUT_DEFAULT: Duration = Duration::from_secs(15);
 
 /// Holds a scheduler
 pub trait HasScheduler: UsesState
@@ -279,6 +300,8 @@ pub struct StdFuzzer<CS, F, OF, OT> {
     scheduler: CS,
     feedback: F,
     objective: OF,
+    #[cfg(feature = "telemetry")]
+    telemetry: Option<Telemetry>,
     phantom: PhantomData<OT>,
 }
 
@@ -421,6 +444,11 @@ where
             exit_kind,
             send_events,
         )?;
+        #[cfg(feature = "telemetry")]
+        if let Some(telemetry) = &self.telemetry {
+            let stage = state.current_stage_idx()?.map(|id| id.0);
+            telemetry.record_execution(stage, *exit_kind, &exec_res, corpus_id);
+        }
         Ok((exec_res, corpus_id))
     }
 
@@ -726,13 +754,19 @@ where
         #[cfg(feature = "introspection")]
         state.introspection_monitor_mut().mark_scheduler_time();
 
-        // Mark the elapsed time for the scheduler
-        #[cfg(feature = "introspection")]
-        state.introspection_monitor_mut().reset_stage_index();
+        #[cfg(feature = "telemetry")]
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.begin_iteration(*state.executions(), id);
+        }
 
         // Execute all stages
         stages.perform_all(self, executor, state, manager)?;
 
+        #[cfg(feature = "telemetry")]
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.end_iteration();
+        }
+
         // Init timer for manager
         #[cfg(feature = "introspection")]
         state.introspection_monitor_mut().start_timer();
@@ -771,10 +805,56 @@ where
             scheduler,
             feedback,
             objective,
+            #[cfg(feature = "telemetry")]
+            telemetry: None,
             phantom: PhantomData,
         }
     }
 
+    /// Log the scheduling decisions of this fuzzer to the given [`Telemetry`] log
+    #[cfg(feature = "telemetry")]
+    #[must_use]
+    pub fn with_telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// Rerun the stages of a fuzzing iteration logged by the [`ReplayLogMetadata`] of the state,
+    /// with the same corpus entry and the same random seed.
+    ///
+    /// The replay is exact as long as the stages see the same state as back then,
+    /// for example by restoring a state snapshot of that time, or by running a single stage on a fixed corpus.
+    /// Pass the stages without their [`ReplayLogStages`] wrapper.
+    /// Returns `true` if the replay drew the same random numbers as the original iteration.
+    pub fn replay_one<E, EM, ST>(
+        &mut self,
+        stages: &mut ST,
+        executor: &mut E,
+        state: &mut <Self as UsesState>::State,
+        manager: &mut EM,
+        iteration: u64,
+    ) -> Result<bool, Error>
+    where
+        E: UsesState<State = <Self as UsesState>::State>,
+        EM: UsesState<State = <Self as UsesState>::State>,
+        ST: StagesTuple<E, EM, <Self as UsesState>::State, Self>,
+        CS::State: HasMetadata + HasRand + HasCurrentCorpusId + HasCurrentStage,
+    {
+        let entry = *state
+            .metadata::<ReplayLogMetadata>()?
+            .entry(iteration)
+            .ok_or_else(|| {
+                Error::key_not_found(format!("Iteration {iteration} is not in the replay log"))
+            })?;
+
+        state.rand_mut().set_seed(entry.seed);
+        state.set_corpus_id(entry.corpus_id)?;
+        stages.perform_all(self, executor, state, manager)?;
+        state.clear_corpus_id()?;
+
+        Ok(state.rand_mut().next() == entry.check)
+    }
+
     /// Runs the input and triggers observers
     pub fn execute_input<E, EM>(
         &mut self,
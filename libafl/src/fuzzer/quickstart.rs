@@ -0,0 +1,548 @@
+//! A quick start into fuzzing: the standard pipeline for [`BytesInput`]s, assembled by the [`FuzzerBuilder`].
+//!
+//! The pipeline is made of a map feedback over the coverage map and a time feedback, a crash and timeout objective,
+//! a queue or weighted scheduler, a havoc stage, and a [`SimpleEventManager`].
+//! The target runs either in-process, see [`FuzzerBuilder::run_in_process`],
+//! or in an AFL-style forkserver, see [`FuzzerBuilder::run_forkserver`].
+//! To triage a crash, [`FuzzerBuilder::reproduce_forkserver`] runs a single input, and reports what happened.
+//! With the `cli` feature, the configuration can be taken from the [`FuzzerOptions`],
+//! so `--reproduce <FILE>` turns the fuzzer into such a triage tool.
+//!
+//! ```rust,ignore
+//! FuzzerBuilder::new(QuickstartConfig {
+//!     input_dirs: vec![PathBuf::from("./seeds")],
+//!     ..QuickstartConfig::default()
+//! })
+//! .run_in_process(harness, coverage_map)?;
+//! ```
+//!
+//! Once you need more than the overrides offered here, copy the pipeline from this file, and go from there.
+
+#[cfg(all(feature = "fork", unix))]
+use std::ffi::OsString;
+#[cfg(all(feature = "fork", feature = "regex", unix))]
+use std::path::Path;
+use std::{path::PathBuf, time::Duration, vec::Vec};
+
+#[cfg(feature = "cli")]
+use libafl_bolts::cli::FuzzerOptions;
+use libafl_bolts::{ownedref::OwnedMutSlice, rands::StdRand, tuples::tuple_list};
+#[cfg(all(feature = "fork", unix))]
+use libafl_bolts::{
+    shmem::{ShMem, ShMemProvider, UnixShMemProvider},
+    AsSliceMut,
+};
+
+#[cfg(all(feature = "fork", unix))]
+use crate::executors::ForkserverExecutor;
+#[cfg(all(feature = "fork", feature = "regex", unix))]
+use crate::{
+    corpus::InMemoryCorpus,
+    fuzzer::{Reproducer, ReproductionReport},
+    observers::AsanBacktraceObserver,
+};
+use crate::{
+    corpus::{Corpus, InMemoryOnDiskCorpus, OnDiskCorpus},
+    events::{EventFirer, ProgressReporter, SimpleEventManager},
+    executors::{inprocess::InProcessExecutor, ExitKind},
+    feedback_or, feedback_or_fast,
+    feedbacks::{CrashFeedback, MaxMapFeedback, TimeFeedback, TimeoutFeedback},
+    fuzzer::{Evaluator, Fuzzer, StdFuzzer},
+    generators::RandBytesGenerator,
+    inputs::BytesInput,
+    monitors::{Monitor, SimpleMonitor},
+    mutators::{
+        scheduled::{havoc_mutations, HavocMutationsType, StdScheduledMutator},
+        Mutator,
+    },
+    observers::{CanTrack, HitcountsMapObserver, StdMapObserver, TimeObserver},
+    schedulers::{
+        powersched::PowerSchedule, IndexesLenTimeMinimizerScheduler, QueueScheduler,
+        StdWeightedScheduler,
+    },
+    stages::{CalibrationStage, StagesTuple, StdMutationalStage, StdPowerMutationalStage},
+    state::{HasCorpus, StdState, UsesState},
+    Error,
+};
+
+/// The state of the fuzzers assembled by the [`FuzzerBuilder`]
+pub type QuickstartState =
+    StdState<BytesInput, InMemoryOnDiskCorpus<BytesInput>, StdRand, OnDiskCorpus<BytesInput>>;
+
+/// The default mutator of the [`FuzzerBuilder`]
+pub type HavocMutator =
+    StdScheduledMutator<BytesInput, HavocMutationsType<BytesInput>, QuickstartState>;
+
+/// How the [`FuzzerBuilder`] picks the next testcase to fuzz
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchedulerKind {
+    /// Go through the corpus in order, see [`QueueScheduler`]
+    Queue,
+    /// Favor testcases with rarely seen coverage, with a calibration stage and the `explore` power schedule of AFL++,
+    /// see [`StdWeightedScheduler`]
+    #[default]
+    Weighted,
+}
+
+/// The configuration of a [`FuzzerBuilder`]
+#[derive(Debug, Clone)]
+pub struct QuickstartConfig {
+    /// The directories to load the initial inputs from
+    pub input_dirs: Vec<PathBuf>,
+    /// The directory to store the corpus in. It is also kept in memory.
+    pub corpus_dir: PathBuf,
+    /// The directory to store the crashes and timeouts in
+    pub solutions_dir: PathBuf,
+    /// How to pick the next testcase to fuzz
+    pub scheduler: SchedulerKind,
+    /// The timeout of a single run of the target
+    pub timeout: Duration,
+    /// The size of the coverage map the forkserver target writes to
+    pub map_size: usize,
+    /// How many random inputs to start with, if none could be loaded from the `input_dirs`
+    pub initial_inputs: usize,
+    /// The maximum size of these random inputs
+    pub max_initial_input_size: usize,
+    /// The seed of the rand, a random one if `None`
+    pub seed: Option<u64>,
+    /// Stop after this many iterations, instead of fuzzing forever
+    pub iterations: Option<u64>,
+    /// Instead of fuzzing, run the forkserver target on this single input and print a report,
+    /// see [`FuzzerBuilder::run_forkserver`]
+    pub reproduce: Option<PathBuf>,
+}
+
+impl Default for QuickstartConfig {
+    fn default() -> Self {
+        Self {
+            input_dirs: vec![],
+            corpus_dir: PathBuf::from("./corpus"),
+            solutions_dir: PathBuf::from("./solutions"),
+            scheduler: SchedulerKind::default(),
+            timeout: Duration::from_secs(1),
+            map_size: 65536,
+            initial_inputs: 8,
+            max_initial_input_size: 32,
+            seed: None,
+            iterations: None,
+            reproduce: None,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl From<&FuzzerOptions> for QuickstartConfig {
+    fn from(options: &FuzzerOptions) -> Self {
+        Self {
+            input_dirs: options.input.clone(),
+            solutions_dir: options.output.clone(),
+            timeout: options.timeout,
+            iterations: (options.iterations > 0).then_some(options.iterations as u64),
+            reproduce: options.reproduce.clone(),
+            ..Self::default()
+        }
+    }
+}
+
+impl QuickstartConfig {
+    /// Create a new [`QuickstartState`] according to this configuration
+    fn state<F, OF>(&self, feedback: &mut F, objective: &mut OF) -> Result<QuickstartState, Error>
+    where
+        F: crate::feedbacks::Feedback<QuickstartState>,
+        OF: crate::feedbacks::Feedback<QuickstartState>,
+    {
+        StdState::new(
+            self.seed.map_or_else(StdRand::new, StdRand::with_seed),
+            InMemoryOnDiskCorpus::new(&self.corpus_dir)?,
+            OnDiskCorpus::new(&self.solutions_dir)?,
+            feedback,
+            objective,
+        )
+    }
+
+    /// Load the initial inputs, or generate some if there are none, then fuzz
+    fn fuzz<E, EM, Z, ST>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut QuickstartState,
+        mgr: &mut EM,
+        stages: &mut ST,
+    ) -> Result<(), Error>
+    where
+        E: UsesState<State = QuickstartState>,
+        EM: EventFirer<State = QuickstartState> + ProgressReporter<State = QuickstartState>,
+        Z: Evaluator<E, EM, State = QuickstartState> + Fuzzer<E, EM, ST>,
+        ST: StagesTuple<E, EM, QuickstartState, Z>,
+    {
+        if !self.input_dirs.is_empty() {
+            state.load_initial_inputs(fuzzer, executor, mgr, &self.input_dirs)?;
+        }
+        if state.corpus().count() == 0 {
+            log::info!(
+                "No initial inputs loaded, starting with {} random ones",
+                self.initial_inputs
+            );
+            let mut generator = RandBytesGenerator::new(self.max_initial_input_size);
+            state.generate_initial_inputs_forced(
+                fuzzer,
+                executor,
+                &mut generator,
+                mgr,
+                self.initial_inputs,
+            )?;
+        }
+
+        match self.iterations {
+            Some(iterations) => {
+                fuzzer.fuzz_loop_for(stages, executor, state, mgr, iterations)?;
+                Ok(())
+            }
+            None => fuzzer.fuzz_loop(stages, executor, state, mgr),
+        }
+    }
+}
+
+/// Assemble the feedbacks, the state, the scheduler and the stages for the configured [`SchedulerKind`],
+/// create the executor with `$executor`, and fuzz.
+macro_rules! fuzz_pipeline {
+    ($self:ident, $edges:ident, $time:ident, |$fuzzer:ident, $state:ident, $mgr:ident| $executor:expr) => {{
+        let map_feedback = MaxMapFeedback::new(&$edges);
+        let mut objective = feedback_or_fast!(CrashFeedback::new(), TimeoutFeedback::new());
+        let mut $mgr = SimpleEventManager::new($self.monitor);
+
+        match $self.config.scheduler {
+            SchedulerKind::Queue => {
+                let mut feedback = feedback_or!(map_feedback, TimeFeedback::new(&$time));
+                let mut $state = $self.config.state(&mut feedback, &mut objective)?;
+
+                let scheduler =
+                    IndexesLenTimeMinimizerScheduler::new(&$edges, QueueScheduler::new());
+                let mut $fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+                let mut stages = tuple_list!(StdMutationalStage::new($self.mutator));
+
+                let mut executor = $executor;
+                $self.config.fuzz(
+                    &mut $fuzzer,
+                    &mut executor,
+                    &mut $state,
+                    &mut $mgr,
+                    &mut stages,
+                )
+            }
+            SchedulerKind::Weighted => {
+                let calibration = CalibrationStage::new(&map_feedback);
+                let mut feedback = feedback_or!(map_feedback, TimeFeedback::new(&$time));
+                let mut $state = $self.config.state(&mut feedback, &mut objective)?;
+
+                let scheduler = IndexesLenTimeMinimizerScheduler::new(
+                    &$edges,
+                    StdWeightedScheduler::with_schedule(
+                        &mut $state,
+                        &$edges,
+                        Some(PowerSchedule::EXPLORE),
+                    ),
+                );
+                let mut $fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+                let mut stages =
+                    tuple_list!(calibration, StdPowerMutationalStage::new($self.mutator));
+
+                let mut executor = $executor;
+                $self.config.fuzz(
+                    &mut $fuzzer,
+                    &mut executor,
+                    &mut $state,
+                    &mut $mgr,
+                    &mut stages,
+                )
+            }
+        }
+    }};
+}
+
+/// Prints the monitor output to `stdout`
+fn print_line(line: &str) {
+    println!("{line}");
+}
+
+/// Assembles and runs the standard fuzzing pipeline for [`BytesInput`]s from a [`QuickstartConfig`].
+///
+/// By default, the stats are printed to `stdout` with a [`SimpleMonitor`], and inputs are mutated with the
+/// [`havoc_mutations`]. Both can be replaced, see [`FuzzerBuilder::monitor`] and [`FuzzerBuilder::mutator`].
+#[derive(Debug)]
+pub struct FuzzerBuilder<MT, M> {
+    config: QuickstartConfig,
+    monitor: MT,
+    mutator: M,
+}
+
+impl FuzzerBuilder<SimpleMonitor<fn(&str)>, HavocMutator> {
+    /// Create a new [`FuzzerBuilder`] with the given configuration
+    #[must_use]
+    pub fn new(config: QuickstartConfig) -> Self {
+        Self {
+            config,
+            monitor: SimpleMonitor::new(print_line),
+            mutator: StdScheduledMutator::new(havoc_mutations()),
+        }
+    }
+}
+
+impl<MT, M> FuzzerBuilder<MT, M>
+where
+    MT: Monitor,
+    M: Mutator<BytesInput, QuickstartState>,
+{
+    /// The configuration
+    #[must_use]
+    pub fn config(&self) -> &QuickstartConfig {
+        &self.config
+    }
+
+    /// Report the stats to the given [`Monitor`], instead of printing them to `stdout`
+    pub fn monitor<MT2>(self, monitor: MT2) -> FuzzerBuilder<MT2, M>
+    where
+        MT2: Monitor,
+    {
+        FuzzerBuilder {
+            config: self.config,
+            monitor,
+            mutator: self.mutator,
+        }
+    }
+
+    /// Mutate the inputs with the given [`Mutator`], instead of the [`havoc_mutations`]
+    pub fn mutator<M2>(self, mutator: M2) -> FuzzerBuilder<MT, M2>
+    where
+        M2: Mutator<BytesInput, QuickstartState>,
+    {
+        FuzzerBuilder {
+            config: self.config,
+            monitor: self.monitor,
+            mutator,
+        }
+    }
+
+    /// Fuzz the `harness`, running in this process.
+    ///
+    /// The `coverage` map is the one the target writes its coverage to,
+    /// such as the edges map of `libafl_targets` for targets built with `libafl_cc`.
+    pub fn run_in_process<H>(
+        self,
+        mut harness: H,
+        coverage: OwnedMutSlice<'static, u8>,
+    ) -> Result<(), Error>
+    where
+        H: FnMut(&BytesInput) -> ExitKind,
+    {
+        let edges = HitcountsMapObserver::new(StdMapObserver::from_mut_slice("edges", coverage))
+            .track_indices();
+        let time = TimeObserver::new("time");
+        let timeout = self.config.timeout;
+
+        fuzz_pipeline!(self, edges, time, |fuzzer, state, mgr| {
+            InProcessExecutor::with_timeout(
+                &mut harness,
+                tuple_list!(edges, time),
+                &mut fuzzer,
+                &mut state,
+                &mut mgr,
+                timeout,
+            )?
+        })
+    }
+
+    /// Fuzz the `program`, instrumented for AFL++, running in a forkserver.
+    ///
+    /// The `args` are parsed like the ones of `afl-fuzz`, so `@@` is replaced by the path of the input file.
+    /// Without `@@`, the inputs are passed on `stdin`.
+    ///
+    /// If [`QuickstartConfig::reproduce`] is set, the target only runs on that input, and the report of
+    /// [`FuzzerBuilder::reproduce_forkserver`] is printed.
+    #[cfg(all(feature = "fork", unix))]
+    pub fn run_forkserver<P, A>(self, program: P, args: Vec<A>) -> Result<(), Error>
+    where
+        P: Into<OsString>,
+        A: Into<OsString> + AsRef<std::ffi::OsStr>,
+    {
+        if let Some(input_path) = self.config.reproduce.clone() {
+            #[cfg(feature = "regex")]
+            {
+                let report = self.reproduce_forkserver(program, args, &input_path)?;
+                println!("{report}");
+                return Ok(());
+            }
+            #[cfg(not(feature = "regex"))]
+            return Err(Error::unsupported(format!(
+                "Reproducing {} needs the regex feature",
+                input_path.display()
+            )));
+        }
+
+        let mut shmem_provider = UnixShMemProvider::new()?;
+        let mut shmem = shmem_provider.new_shmem(self.config.map_size)?;
+        // let the forkserver know the shmid
+        shmem.write_to_env("__AFL_SHM_ID")?;
+
+        let edges = unsafe {
+            HitcountsMapObserver::new(StdMapObserver::new("edges", shmem.as_slice_mut()))
+                .track_indices()
+        };
+        let time = TimeObserver::new("time");
+        let program = program.into();
+        let timeout = self.config.timeout;
+        let map_size = self.config.map_size;
+
+        fuzz_pipeline!(self, edges, time, |fuzzer, state, mgr| {
+            ForkserverExecutor::builder()
+                .program(program)
+                .parse_afl_cmdline(args)
+                .shmem_provider(&mut shmem_provider)
+                .coverage_map_size(map_size)
+                .timeout(timeout)
+                .build_dynamic_map(edges, tuple_list!(time))?
+        })
+    }
+
+    /// Run the `program` on the single input at `input_path` in a forkserver, like [`FuzzerBuilder::run_forkserver`]
+    /// would, and report what happened.
+    ///
+    /// The inputs in the `corpus_dir` are run first, so the report lists the coverage they did not reach.
+    #[cfg(all(feature = "fork", feature = "regex", unix))]
+    pub fn reproduce_forkserver<P, A>(
+        self,
+        program: P,
+        args: Vec<A>,
+        input_path: &Path,
+    ) -> Result<ReproductionReport, Error>
+    where
+        P: Into<OsString>,
+        A: Into<OsString> + AsRef<std::ffi::OsStr>,
+    {
+        let mut shmem_provider = UnixShMemProvider::new()?;
+        let mut shmem = shmem_provider.new_shmem(self.config.map_size)?;
+        // let the forkserver know the shmid
+        shmem.write_to_env("__AFL_SHM_ID")?;
+
+        let edges = unsafe {
+            HitcountsMapObserver::new(StdMapObserver::new("edges", shmem.as_slice_mut()))
+                .track_indices()
+        };
+        // the forkserver parses the ASan log into the observer with the default name
+        let asan = AsanBacktraceObserver::default();
+        let reproducer = Reproducer::new(&edges).with_hash_observer(&asan);
+
+        let mut feedback = MaxMapFeedback::new(&edges);
+        let mut objective = ();
+        let mut state = StdState::new(
+            self.config
+                .seed
+                .map_or_else(StdRand::new, StdRand::with_seed),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )?;
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = SimpleEventManager::new(self.monitor);
+
+        let mut executor = ForkserverExecutor::builder()
+            .program(program.into())
+            .parse_afl_cmdline(args)
+            .shmem_provider(&mut shmem_provider)
+            .coverage_map_size(self.config.map_size)
+            .timeout(self.config.timeout)
+            .build_dynamic_map(edges, tuple_list!(asan))?;
+
+        if self.config.corpus_dir.is_dir() {
+            state.load_initial_inputs(
+                &mut fuzzer,
+                &mut executor,
+                &mut mgr,
+                core::slice::from_ref(&self.config.corpus_dir),
+            )?;
+        }
+        reproducer.reproduce(&mut fuzzer, &mut executor, &mut state, &mut mgr, input_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::addr_of_mut;
+    use std::fs;
+
+    use libafl_bolts::AsSlice;
+    use serial_test::serial;
+
+    use crate::{
+        executors::ExitKind,
+        fuzzer::quickstart::{FuzzerBuilder, QuickstartConfig, SchedulerKind},
+        inputs::{BytesInput, HasTargetBytes},
+        monitors::NopMonitor,
+    };
+
+    static mut MAP: [u8; 16] = [0; 16];
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_quickstart_in_process() {
+        for scheduler in [SchedulerKind::Queue, SchedulerKind::Weighted] {
+            let dir = std::env::temp_dir().join(format!("libafl_quickstart_{scheduler:?}"));
+            let config = QuickstartConfig {
+                corpus_dir: dir.join("corpus"),
+                solutions_dir: dir.join("solutions"),
+                scheduler,
+                seed: Some(1337),
+                iterations: Some(16),
+                ..QuickstartConfig::default()
+            };
+
+            let harness = |input: &BytesInput| {
+                let target = input.target_bytes();
+                let map = unsafe { &mut *addr_of_mut!(MAP) };
+                map[0] = 1;
+                map[1 + target.as_slice().len() % 15] = 1;
+                ExitKind::Ok
+            };
+            FuzzerBuilder::new(config)
+                .monitor(NopMonitor::new())
+                .run_in_process(harness, unsafe {
+                    (*addr_of_mut!(MAP)).as_mut_slice().into()
+                })
+                .unwrap();
+
+            assert!(fs::read_dir(dir.join("corpus")).unwrap().count() > 0);
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_quickstart_config_from_cli() {
+        use std::path::PathBuf;
+
+        use libafl_bolts::cli::FuzzerOptions;
+
+        let options = FuzzerOptions::try_parse_with_config_from([
+            "fuzzer",
+            "--input",
+            "seeds",
+            "--iterations",
+            "100",
+            "--reproduce",
+            "crash-1234",
+        ])
+        .unwrap();
+        let config = QuickstartConfig::from(&options);
+        assert_eq!(config.input_dirs, [PathBuf::from("seeds")]);
+        assert_eq!(config.iterations, Some(100));
+        assert_eq!(config.reproduce, Some(PathBuf::from("crash-1234")));
+
+        let options =
+            FuzzerOptions::try_parse_with_config_from(["fuzzer", "--input", "seeds"]).unwrap();
+        let config = QuickstartConfig::from(&options);
+        assert_eq!(config.iterations, None);
+        assert_eq!(config.reproduce, None);
+    }
+}
@@ -0,0 +1,346 @@
+//! Deterministic replay of fuzzing iterations.
+//!
+//! When the stages are wrapped in [`ReplayLogStages`] and a [`ReplayLogMetadata`] is added to the state,
+//! the random number generator is reseeded at the start of each iteration, with a seed derived from the
+//! base seed and the iteration number, and the iteration is logged. Since every random decision of the stages
+//! and mutators is drawn from this seed, [`crate::StdFuzzer::replay_one`] can later rerun an iteration exactly,
+//! for example to debug a mutator or to see how a crasher was derived from its parent.
+
+use alloc::{collections::VecDeque, vec::Vec};
+
+use libafl_bolts::{impl_serdeany, rands::Rand};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasCurrentCorpusId},
+    stages::{HasCurrentStage, StagesTuple},
+    state::{HasCorpus, HasExecutions, HasRand, HasSolutions, UsesState},
+    Error, HasMetadata,
+};
+
+/// The default amount of iterations kept in the log, if they found nothing
+pub const DEFAULT_REPLAY_LOG_RECENT_ITERATIONS: usize = 1024;
+
+/// The log entry of a single fuzzing iteration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayLogEntry {
+    /// The number of the iteration, counted from when the log was enabled
+    pub iteration: u64,
+    /// The corpus entry the scheduler picked for this iteration
+    pub corpus_id: CorpusId,
+    /// The seed of the random number generator at the start of the iteration
+    pub seed: u64,
+    /// The executions of the state when the iteration started
+    pub executions_start: u64,
+    /// The executions of the state when the iteration ended
+    pub executions_end: u64,
+    /// The amount of testcases this iteration added to the corpus
+    pub new_corpus_entries: usize,
+    /// The amount of testcases this iteration added to the solutions
+    pub new_solutions: usize,
+    /// The next value of the random number generator after the iteration.
+    /// If a replay draws a different one, it took a different path.
+    pub check: u64,
+}
+
+impl ReplayLogEntry {
+    /// If this iteration found new corpus entries or solutions
+    #[must_use]
+    pub fn found_something(&self) -> bool {
+        self.new_corpus_entries > 0 || self.new_solutions > 0
+    }
+}
+
+/// The corpus and solutions count when the current iteration started
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct PendingIteration {
+    entry: ReplayLogEntry,
+    corpus_count: usize,
+    solutions_count: usize,
+}
+
+/// Enables the replay log, when added to the state, and holds the logged iterations.
+///
+/// Iterations that found new corpus entries or solutions are kept forever,
+/// of the others only the most recent ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct ReplayLogMetadata {
+    seed: u64,
+    iterations: u64,
+    max_recent: usize,
+    found: Vec<ReplayLogEntry>,
+    recent: VecDeque<ReplayLogEntry>,
+    /// The iteration that is currently running, kept across restarts
+    pending: Option<PendingIteration>,
+}
+
+impl_serdeany!(ReplayLogMetadata);
+
+impl ReplayLogMetadata {
+    /// Create a new [`ReplayLogMetadata`], deriving the seed of each iteration from `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            iterations: 0,
+            max_recent: DEFAULT_REPLAY_LOG_RECENT_ITERATIONS,
+            found: vec![],
+            recent: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    /// Keep up to `max_recent` of the last iterations that found nothing
+    #[must_use]
+    pub fn with_max_recent(mut self, max_recent: usize) -> Self {
+        self.max_recent = max_recent;
+        self
+    }
+
+    /// The base seed
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The amount of finished iterations
+    #[must_use]
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// The logged iterations that found new corpus entries or solutions
+    #[must_use]
+    pub fn found(&self) -> &[ReplayLogEntry] {
+        &self.found
+    }
+
+    /// The most recent logged iterations that found nothing
+    pub fn recent(&self) -> impl Iterator<Item = &ReplayLogEntry> {
+        self.recent.iter()
+    }
+
+    /// The log entry of `iteration`, if it is still kept.
+    /// This includes the current iteration, so the iteration that crashed the fuzzer can be replayed.
+    #[must_use]
+    pub fn entry(&self, iteration: u64) -> Option<&ReplayLogEntry> {
+        self.entries().find(|entry| entry.iteration == iteration)
+    }
+
+    /// The iteration during which the state reached `executions`.
+    /// Use it with [`crate::corpus::Testcase::executions`] to find the iteration that found a testcase.
+    #[must_use]
+    pub fn entry_for_executions(&self, executions: u64) -> Option<&ReplayLogEntry> {
+        self.entries()
+            .find(|entry| entry.executions_start < executions && executions <= entry.executions_end)
+    }
+
+    /// The iteration that is currently running, or was interrupted by a crash
+    #[must_use]
+    pub fn pending(&self) -> Option<&ReplayLogEntry> {
+        self.pending.as_ref().map(|pending| &pending.entry)
+    }
+
+    /// All kept entries, including the pending one
+    fn entries(&self) -> impl Iterator<Item = &ReplayLogEntry> {
+        self.found
+            .iter()
+            .chain(self.recent.iter())
+            .chain(self.pending())
+    }
+
+    /// The seed of `iteration`
+    #[must_use]
+    pub fn seed_for(&self, iteration: u64) -> u64 {
+        // `Rand::set_seed` mixes the seed, so neighbouring seeds still give unrelated streams
+        self.seed
+            .wrapping_add(iteration.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    /// Start a new iteration on `corpus_id`, returning its seed.
+    /// The entry is kept as pending in the metadata, so it is part of the state the crash handlers serialize,
+    /// before the first execution of the iteration.
+    /// Returns `None` if the fuzzer resumes an iteration that was interrupted by a restart.
+    fn begin(
+        &mut self,
+        corpus_id: CorpusId,
+        executions: u64,
+        corpus_count: usize,
+        solutions_count: usize,
+    ) -> Option<u64> {
+        if self.pending.is_some() {
+            return None;
+        }
+        let seed = self.seed_for(self.iterations);
+        self.pending = Some(PendingIteration {
+            entry: ReplayLogEntry {
+                iteration: self.iterations,
+                corpus_id,
+                seed,
+                executions_start: executions,
+                executions_end: executions,
+                new_corpus_entries: 0,
+                new_solutions: 0,
+                check: 0,
+            },
+            corpus_count,
+            solutions_count,
+        });
+        Some(seed)
+    }
+
+    /// Finish the current iteration and log it
+    fn end(&mut self, executions: u64, corpus_count: usize, solutions_count: usize, check: u64) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let entry = ReplayLogEntry {
+            executions_end: executions,
+            new_corpus_entries: corpus_count.saturating_sub(pending.corpus_count),
+            new_solutions: solutions_count.saturating_sub(pending.solutions_count),
+            check,
+            ..pending.entry
+        };
+        self.iterations += 1;
+
+        if entry.found_something() {
+            self.found.push(entry);
+        } else if self.max_recent > 0 {
+            if self.recent.len() >= self.max_recent {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(entry);
+        }
+    }
+}
+
+/// Wraps the stages of the fuzzer, to reseed and log each iteration when the state has a [`ReplayLogMetadata`].
+///
+/// To replay an iteration with [`crate::StdFuzzer::replay_one`], pass the [`ReplayLogStages::inner_mut`] stages,
+/// as the wrapper would log the replay as a new iteration.
+#[derive(Debug, Clone)]
+pub struct ReplayLogStages<ST> {
+    inner: ST,
+}
+
+impl<ST> ReplayLogStages<ST> {
+    /// Wrap the `inner` stages
+    #[must_use]
+    pub fn new(inner: ST) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped stages
+    pub fn inner_mut(&mut self) -> &mut ST {
+        &mut self.inner
+    }
+}
+
+impl<E, EM, S, ST, Z> StagesTuple<E, EM, S, Z> for ReplayLogStages<ST>
+where
+    E: UsesState<State = S>,
+    EM: UsesState<State = S>,
+    Z: UsesState<State = S>,
+    S: HasCurrentStage
+        + HasCurrentCorpusId
+        + HasMetadata
+        + HasRand
+        + HasCorpus
+        + HasSolutions
+        + HasExecutions,
+    ST: StagesTuple<E, EM, S, Z>,
+{
+    fn perform_all(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let corpus_id = state.current_corpus_id()?.ok_or_else(|| {
+            Error::illegal_state("state is not currently processing a corpus index")
+        })?;
+        begin_iteration(state, corpus_id);
+        self.inner.perform_all(fuzzer, executor, state, manager)?;
+        end_iteration(state);
+        Ok(())
+    }
+}
+
+/// Reseed the random number generator for a new iteration, if the replay log is enabled
+fn begin_iteration<S>(state: &mut S, corpus_id: CorpusId)
+where
+    S: HasMetadata + HasRand + HasCorpus + HasSolutions + HasExecutions,
+{
+    if !state.has_metadata::<ReplayLogMetadata>() {
+        return;
+    }
+    let executions = *state.executions();
+    let corpus_count = state.corpus().count();
+    let solutions_count = state.solutions().count();
+    let seed = state.metadata_mut::<ReplayLogMetadata>().unwrap().begin(
+        corpus_id,
+        executions,
+        corpus_count,
+        solutions_count,
+    );
+    if let Some(seed) = seed {
+        state.rand_mut().set_seed(seed);
+    }
+}
+
+/// Log the iteration that just finished, if the replay log is enabled
+fn end_iteration<S>(state: &mut S)
+where
+    S: HasMetadata + HasRand + HasCorpus + HasSolutions + HasExecutions,
+{
+    if !state.has_metadata::<ReplayLogMetadata>() {
+        return;
+    }
+    // The next iteration reseeds anyway, so this draw does not change anything
+    let check = state.rand_mut().next();
+    let executions = *state.executions();
+    let corpus_count = state.corpus().count();
+    let solutions_count = state.solutions().count();
+    state.metadata_mut::<ReplayLogMetadata>().unwrap().end(
+        executions,
+        corpus_count,
+        solutions_count,
+        check,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{corpus::CorpusId, fuzzer::replay_log::ReplayLogMetadata};
+
+    #[test]
+    fn test_replay_log() {
+        let mut replay = ReplayLogMetadata::new(1337).with_max_recent(2);
+
+        for i in 0..4 {
+            let seed = replay.begin(CorpusId(0), i * 10, 1, 0).unwrap();
+            assert_eq!(seed, replay.seed_for(i));
+            // The running iteration can be replayed already, in case it crashes
+            assert_eq!(replay.entry(i).unwrap().seed, seed);
+            // A restart in the middle of the iteration keeps the seed
+            assert!(replay.begin(CorpusId(0), i * 10 + 5, 1, 0).is_none());
+            // The second iteration finds a new corpus entry
+            let corpus_count = if i == 1 { 2 } else { 1 };
+            replay.end(i * 10 + 10, corpus_count, 0, i);
+        }
+
+        assert_eq!(replay.iterations(), 4);
+        assert_eq!(replay.found().len(), 1);
+        assert_eq!(replay.found()[0].iteration, 1);
+        // Only the two most recent boring iterations are kept
+        assert!(replay.entry(0).is_none());
+        assert_eq!(replay.recent().count(), 2);
+        assert_eq!(replay.entry(3).unwrap().check, 3);
+        assert_eq!(replay.entry_for_executions(15).unwrap().iteration, 1);
+    }
+}
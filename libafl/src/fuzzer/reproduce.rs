@@ -0,0 +1,286 @@
+//! Reproduce a single input, such as a crash found by the fuzzer, and report on what happened.
+//!
+//! The [`Reproducer`] runs the input through the executor of the fuzzer, with all of its observers,
+//! so the same harness binary can be used both to fuzz and to triage.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Debug, Display, Formatter},
+    marker::PhantomData,
+};
+use std::path::{Path, PathBuf};
+
+use libafl_bolts::{
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    executors::{ExitKind, HasObservers},
+    feedbacks::MapFeedbackMetadata,
+    fuzzer::ExecutesInput,
+    inputs::{Input, UsesInput},
+    observers::{CanTrack, MapObserver, ObserverWithHashField, StdErrObserver},
+    state::UsesState,
+    Error, HasNamedMetadata,
+};
+
+/// The outcome of reproducing a single input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproductionReport {
+    /// The file the input was loaded from
+    pub input: PathBuf,
+    /// How the target exited
+    pub exit_kind: ExitKind,
+    /// The number of map entries this run has hit
+    pub covered: usize,
+    /// The map entries this run has hit, but which are not in the history of the map feedback yet
+    pub new_coverage: Vec<usize>,
+    /// The hash of the backtrace of a crash, if a hash observer was configured and the target crashed
+    pub backtrace_hash: Option<u64>,
+    /// What the target wrote to `stderr`, where sanitizers report, if a [`StdErrObserver`] was configured
+    pub sanitizer_output: Option<String>,
+}
+
+impl Display for ReproductionReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "input: {}", self.input.display())?;
+        writeln!(f, "exit kind: {:?}", self.exit_kind)?;
+        writeln!(
+            f,
+            "coverage: {} entries, {} new",
+            self.covered,
+            self.new_coverage.len()
+        )?;
+        if let Some(hash) = self.backtrace_hash {
+            writeln!(f, "backtrace hash: {hash:#018x}")?;
+        }
+        if let Some(output) = &self.sanitizer_output {
+            writeln!(f, "sanitizer output:\n{output}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The hash observer of a [`Reproducer`] which does not report a backtrace hash
+#[derive(Debug, Clone, Copy)]
+pub struct NoHashObserver;
+
+impl ObserverWithHashField for NoHashObserver {
+    fn hash(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Runs a single input through the executor of a fuzzer, and reports the exit kind,
+/// the coverage compared to the history of the map feedback, and, if configured,
+/// the backtrace hash and the sanitizer output.
+#[derive(Debug)]
+pub struct Reproducer<C, O, H = NoHashObserver> {
+    map_ref: Handle<C>,
+    hash_ref: Option<Handle<H>>,
+    stderr_ref: Option<Handle<StdErrObserver>>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> Reproducer<C, O>
+where
+    C: CanTrack + AsRef<O> + Named,
+    O: MapObserver,
+{
+    /// Create a new [`Reproducer`], reporting on the coverage of the given map observer
+    #[must_use]
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_ref: map_observer.handle(),
+            hash_ref: None,
+            stderr_ref: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O, H> Reproducer<C, O, H>
+where
+    C: CanTrack + AsRef<O> + Named,
+    O: MapObserver,
+    O::Entry: Serialize + DeserializeOwned,
+    H: ObserverWithHashField,
+{
+    /// Report the backtrace hash of the given observer, such as a `BacktraceObserver`
+    /// or an `AsanBacktraceObserver`
+    #[must_use]
+    pub fn with_hash_observer<H2>(self, hash_observer: &H2) -> Reproducer<C, O, H2>
+    where
+        H2: ObserverWithHashField + Named,
+    {
+        Reproducer {
+            map_ref: self.map_ref,
+            hash_ref: Some(hash_observer.handle()),
+            stderr_ref: self.stderr_ref,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Report what the target wrote to `stderr`, as captured by the given observer
+    #[must_use]
+    pub fn with_stderr_observer(mut self, stderr_observer: &StdErrObserver) -> Self {
+        self.stderr_ref = Some(stderr_observer.handle());
+        self
+    }
+
+    /// Load the input at `input_path` and run it once
+    pub fn reproduce<E, EM, S, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        mgr: &mut EM,
+        input_path: &Path,
+    ) -> Result<ReproductionReport, Error>
+    where
+        E: HasObservers + UsesState<State = S>,
+        EM: UsesState<State = S>,
+        S: HasNamedMetadata + UsesInput,
+        Z: ExecutesInput<E, EM, State = S>,
+    {
+        let input = S::Input::from_file(input_path)?;
+        let exit_kind = fuzzer.execute_input(state, executor, mgr, &input)?;
+
+        let observers = executor.observers();
+        let map = observers
+            .get(&self.map_ref)
+            .ok_or_else(|| Error::key_not_found("Map observer not found".to_string()))?
+            .as_ref();
+        // the history of the map feedback is stored under the name of its map observer
+        let history = state
+            .named_metadata_map()
+            .get::<MapFeedbackMetadata<O::Entry>>(self.map_ref.name())
+            .map_or(&[][..], |meta| meta.history_map.as_slice());
+
+        let initial = map.initial();
+        let mut covered = 0;
+        let mut new_coverage = vec![];
+        for idx in 0..map.usable_count() {
+            if map.get(idx) == initial {
+                continue;
+            }
+            covered += 1;
+            if !matches!(history.get(idx), Some(&seen) if seen != initial) {
+                new_coverage.push(idx);
+            }
+        }
+
+        let backtrace_hash = self
+            .hash_ref
+            .as_ref()
+            .and_then(|hash_ref| observers.get(hash_ref))
+            .and_then(ObserverWithHashField::hash);
+        let sanitizer_output = self
+            .stderr_ref
+            .as_ref()
+            .and_then(|stderr_ref| observers.get(stderr_ref))
+            .and_then(|observer| observer.stderr.as_deref())
+            .map(|stderr| String::from_utf8_lossy(stderr).into_owned());
+
+        Ok(ReproductionReport {
+            input: input_path.to_path_buf(),
+            exit_kind,
+            covered,
+            new_coverage,
+            backtrace_hash,
+            sanitizer_output,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::addr_of_mut;
+    use std::fs;
+
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list, AsSlice};
+    use serial_test::serial;
+
+    use crate::{
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::{CrashFeedback, MaxMapFeedback},
+        fuzzer::{reproduce::Reproducer, Evaluator, StdFuzzer},
+        inputs::{BytesInput, HasTargetBytes},
+        observers::{CanTrack, StdMapObserver},
+        schedulers::QueueScheduler,
+        state::StdState,
+    };
+
+    static mut MAP: [u8; 4] = [0; 4];
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_reproduce() {
+        let mut harness = |input: &BytesInput| {
+            let map = unsafe { &mut *addr_of_mut!(MAP) };
+            map[0] = 1;
+            if input.target_bytes().as_slice().first() == Some(&b'a') {
+                map[1] = 1;
+            }
+            ExitKind::Ok
+        };
+        let edges = unsafe {
+            StdMapObserver::from_mut_slice("edges", (*addr_of_mut!(MAP)).as_mut_slice().into())
+        }
+        .track_indices();
+        let reproducer = Reproducer::new(&edges);
+
+        let mut feedback = MaxMapFeedback::new(&edges);
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(edges),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+
+        // the history of the map feedback only has the first entry
+        fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(b"b".to_vec()),
+            )
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("libafl_reproduce_{}", std::process::id()));
+        fs::write(&path, b"a").unwrap();
+        let report = reproducer
+            .reproduce(&mut fuzzer, &mut executor, &mut state, &mut mgr, &path)
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(report.exit_kind, ExitKind::Ok);
+        assert_eq!(report.covered, 2);
+        assert_eq!(report.new_coverage, [1]);
+        assert!(report.backtrace_hash.is_none());
+        assert!(report.sanitizer_output.is_none());
+    }
+}
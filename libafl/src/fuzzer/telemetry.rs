@@ -0,0 +1,501 @@
+//! Telemetry of the scheduling decisions of the fuzzer, for research on schedulers and mutators.
+//!
+//! A [`Telemetry`] log given to [`StdFuzzer::with_telemetry`] records, for each iteration,
+//! the corpus entry the scheduler picked and, for each execution of the iteration,
+//! the stage that ran it, the [`ExitKind`] and whether the input was added to the corpus or the solutions.
+//! Given to a [`LoggerScheduledMutator`] too, see [`LoggerScheduledMutator::with_telemetry`],
+//! the log also records the mutations applied to each input.
+//! The log is a compact binary file which can be decoded with a [`TelemetryReader`].
+//!
+//! Failing to write the log does not stop the fuzzer, the error is logged and the telemetry disabled.
+//! Executions that crash an in-process executor are not logged, since the process dies
+//! before the feedbacks decide on them.
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{corpus::CorpusId, executors::ExitKind, fuzzer::ExecuteInputResult, Error};
+#[cfg(doc)]
+use crate::{fuzzer::StdFuzzer, mutators::LoggerScheduledMutator};
+
+/// The magic bytes at the start of a telemetry log
+const TELEMETRY_MAGIC: &[u8; 8] = b"LAFLTEL1";
+
+/// The maximum size of a single event of the log, anything larger means the log is corrupted
+const MAX_EVENT_LEN: usize = 1 << 20;
+
+/// What the feedbacks decided on an execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TelemetryDecision {
+    /// The input was not interesting
+    Discarded,
+    /// The input was added to the corpus, with this id
+    Corpus(Option<CorpusId>),
+    /// The input is a solution
+    Solution,
+}
+
+/// An entry of the binary log, as written to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum TelemetryEvent {
+    /// Define the name of a mutation, for the ids of the following executions
+    Name { id: u32, name: String },
+    /// A new iteration started on a corpus entry, after the given amount of executions
+    Iteration {
+        executions: u64,
+        corpus_id: CorpusId,
+    },
+    /// An input was executed during the last iteration
+    Execution {
+        stage: Option<usize>,
+        mutations: Vec<u32>,
+        exit_kind: ExitKind,
+        decision: TelemetryDecision,
+    },
+}
+
+/// The open log file, and the ids of the mutation names already defined in it
+#[derive(Debug)]
+struct TelemetryLog {
+    /// `None` once writing failed
+    writer: Option<BufWriter<File>>,
+    names: HashMap<String, u32>,
+    in_iteration: bool,
+    /// The mutations applied to the input of the next execution
+    mutations: Vec<u32>,
+}
+
+impl TelemetryLog {
+    /// Write an event, disabling the telemetry if that fails
+    fn write(&mut self, event: &TelemetryEvent) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        let written = postcard::to_allocvec(event)
+            .map_err(Error::from)
+            .and_then(|buf| {
+                if buf.len() > MAX_EVENT_LEN {
+                    return Err(Error::illegal_argument(format!(
+                        "Telemetry event of {} bytes is too large",
+                        buf.len()
+                    )));
+                }
+                writer.write_all(&(buf.len() as u32).to_le_bytes())?;
+                writer.write_all(&buf)?;
+                Ok(())
+            });
+        if let Err(err) = written {
+            self.fail(&err);
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(Err(err)) = self.writer.as_mut().map(BufWriter::flush) {
+            self.fail(&err.into());
+        }
+    }
+
+    fn fail(&mut self, err: &Error) {
+        log::error!("Failed to write the telemetry log, disabling it: {err}");
+        self.writer = None;
+    }
+
+    /// The id of a mutation name, defining it in the log the first time it is used
+    fn name_id(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.names.get(name) {
+            return *id;
+        }
+        let id = self.names.len() as u32;
+        self.write(&TelemetryEvent::Name {
+            id,
+            name: name.to_string(),
+        });
+        self.names.insert(name.to_string(), id);
+        id
+    }
+}
+
+/// A telemetry log, shared by the [`StdFuzzer`] and the [`LoggerScheduledMutator`] that write to it.
+///
+/// The log is appended to, so the iterations of restarted fuzzers end up in the same file.
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    log: Rc<RefCell<TelemetryLog>>,
+}
+
+impl Telemetry {
+    /// Open the telemetry log at `path`, appending to it if it exists
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(TELEMETRY_MAGIC)?;
+        }
+        Ok(Self {
+            log: Rc::new(RefCell::new(TelemetryLog {
+                writer: Some(BufWriter::new(file)),
+                names: HashMap::new(),
+                in_iteration: false,
+                mutations: vec![],
+            })),
+        })
+    }
+
+    /// Log the mutations applied to the input of the next execution
+    pub fn log_mutations<'a, I>(&self, names: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut log = self.log.borrow_mut();
+        let mutations = names.into_iter().map(|name| log.name_id(name)).collect();
+        log.mutations = mutations;
+    }
+
+    /// Log the start of an iteration on `corpus_id`, after the given amount of `executions`
+    pub(crate) fn begin_iteration(&self, executions: u64, corpus_id: CorpusId) {
+        let mut log = self.log.borrow_mut();
+        log.in_iteration = true;
+        log.mutations.clear();
+        log.write(&TelemetryEvent::Iteration {
+            executions,
+            corpus_id,
+        });
+    }
+
+    /// Log an execution in the given stage, and what the feedbacks decided
+    pub(crate) fn record_execution(
+        &self,
+        stage: Option<usize>,
+        exit_kind: ExitKind,
+        exec_res: &ExecuteInputResult,
+        corpus_id: Option<CorpusId>,
+    ) {
+        let mut log = self.log.borrow_mut();
+        let mutations = core::mem::take(&mut log.mutations);
+        if !log.in_iteration {
+            return;
+        }
+        let decision = match exec_res {
+            ExecuteInputResult::None => TelemetryDecision::Discarded,
+            ExecuteInputResult::Corpus => TelemetryDecision::Corpus(corpus_id),
+            ExecuteInputResult::Solution => TelemetryDecision::Solution,
+        };
+        log.write(&TelemetryEvent::Execution {
+            stage,
+            mutations,
+            exit_kind,
+            decision,
+        });
+        if decision == TelemetryDecision::Solution {
+            // the fuzzer may not survive the next execution
+            log.flush();
+        }
+    }
+
+    /// Log the end of the running iteration
+    pub(crate) fn end_iteration(&self) {
+        let mut log = self.log.borrow_mut();
+        log.in_iteration = false;
+        log.mutations.clear();
+        log.flush();
+    }
+}
+
+/// A decoded execution of the telemetry log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelemetryRecord {
+    /// The executions of the fuzzer before the iteration started, which identifies the iteration across restarts
+    pub executions: u64,
+    /// The corpus entry the scheduler picked for the iteration
+    pub corpus_id: CorpusId,
+    /// The [`crate::stages::StageId`] of the stage that ran the execution, if known.
+    /// The [`crate::stages::StagesTuple`] counts them from its end, so the last stage is `1`.
+    pub stage: Option<usize>,
+    /// The names of the mutations applied to the input, in order
+    pub mutations: Vec<String>,
+    /// How the target exited
+    pub exit_kind: ExitKind,
+    /// What the feedbacks decided
+    pub decision: TelemetryDecision,
+}
+
+impl Display for TelemetryRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t", self.executions, self.corpus_id)?;
+        match self.stage {
+            Some(stage) => write!(f, "{stage}")?,
+            None => write!(f, "-")?,
+        }
+        write!(f, "\t{:?}\t", self.exit_kind)?;
+        match self.decision {
+            TelemetryDecision::Discarded => write!(f, "discarded")?,
+            TelemetryDecision::Corpus(Some(id)) => write!(f, "corpus:{id}")?,
+            TelemetryDecision::Corpus(None) => write!(f, "corpus")?,
+            TelemetryDecision::Solution => write!(f, "solution")?,
+        }
+        write!(f, "\t{}", self.mutations.join(","))
+    }
+}
+
+/// Decodes a telemetry log into [`TelemetryRecord`]s, one for each execution.
+///
+/// A record truncated by a crash of the fuzzer ends the log.
+/// To dump a log as tab separated text:
+///
+/// ```rust,no_run
+/// # use libafl::fuzzer::telemetry::TelemetryReader;
+/// for record in TelemetryReader::open("telemetry.bin")? {
+///     println!("{}", record?);
+/// }
+/// # Ok::<(), libafl::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TelemetryReader<R> {
+    reader: R,
+    names: HashMap<u32, String>,
+    iteration: Option<(u64, CorpusId)>,
+}
+
+impl TelemetryReader<BufReader<File>> {
+    /// Open the telemetry log at `path`
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R> TelemetryReader<R>
+where
+    R: Read,
+{
+    /// Decode the telemetry log read from `reader`
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0; TELEMETRY_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != TELEMETRY_MAGIC {
+            return Err(Error::illegal_argument("Not a telemetry log"));
+        }
+        Ok(Self {
+            reader,
+            names: HashMap::new(),
+            iteration: None,
+        })
+    }
+
+    /// The next event, or `None` at the end of the log
+    fn next_event(&mut self) -> Result<Option<TelemetryEvent>, Error> {
+        let mut len = [0; 4];
+        let mut buf = vec![];
+        let read = self.reader.read_exact(&mut len).and_then(|()| {
+            let len = u32::from_le_bytes(len) as usize;
+            if len > MAX_EVENT_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Telemetry event of {len} bytes, the log is corrupted"),
+                ));
+            }
+            buf.resize(len, 0);
+            self.reader.read_exact(&mut buf)
+        });
+        match read {
+            Ok(()) => Ok(Some(postcard::from_bytes(&buf)?)),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl<R> Iterator for TelemetryReader<R>
+where
+    R: Read,
+{
+    type Item = Result<TelemetryRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = match self.next_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            match event {
+                TelemetryEvent::Name { id, name } => {
+                    // a restarted fuzzer defines its names again
+                    self.names.insert(id, name);
+                }
+                TelemetryEvent::Iteration {
+                    executions,
+                    corpus_id,
+                } => self.iteration = Some((executions, corpus_id)),
+                TelemetryEvent::Execution {
+                    stage,
+                    mutations,
+                    exit_kind,
+                    decision,
+                } => {
+                    let Some((executions, corpus_id)) = self.iteration else {
+                        return Some(Err(Error::illegal_state(
+                            "Telemetry execution outside of an iteration",
+                        )));
+                    };
+                    let mutations = mutations
+                        .iter()
+                        .map(|id| {
+                            self.names.get(id).cloned().ok_or_else(|| {
+                                Error::illegal_state(format!("Unknown telemetry name {id}"))
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>();
+                    return Some(mutations.map(|mutations| TelemetryRecord {
+                        executions,
+                        corpus_id,
+                        stage,
+                        mutations,
+                        exit_kind,
+                        decision,
+                    }));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::fs;
+
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list, AsSlice};
+
+    use crate::{
+        corpus::{CorpusId, InMemoryCorpus},
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::CrashFeedback,
+        fuzzer::{
+            telemetry::{Telemetry, TelemetryDecision, TelemetryReader},
+            Evaluator, Fuzzer, StdFuzzer,
+        },
+        inputs::{BytesInput, HasTargetBytes},
+        mutators::{BitFlipMutator, LoggerScheduledMutator, StdScheduledMutator},
+        schedulers::QueueScheduler,
+        stages::StdMutationalStage,
+        state::{HasExecutions, StdState},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_telemetry() {
+        let path = std::env::temp_dir().join(format!("libafl_telemetry_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // "crash" if the first bit is set, without actually crashing the test
+        let mut harness = |input: &BytesInput| {
+            if input.target_bytes().as_slice()[0] & 1 == 1 {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+
+        let mut feedback = ();
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let telemetry = Telemetry::open(&path).unwrap();
+        let mut fuzzer = StdFuzzer::<_, _, _, ()>::new(QueueScheduler::new(), feedback, objective)
+            .with_telemetry(telemetry.clone());
+        let mut mgr = NopEventManager::new();
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+        let mut stages = tuple_list!(StdMutationalStage::new(
+            LoggerScheduledMutator::new(StdScheduledMutator::new(tuple_list!(
+                BitFlipMutator::new()
+            )))
+            .with_telemetry(telemetry)
+        ));
+
+        // executions outside of an iteration are not logged
+        fuzzer
+            .add_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![0; 4]),
+            )
+            .unwrap();
+        let executions_before = *state.executions();
+        for _ in 0..2 {
+            fuzzer
+                .fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)
+                .unwrap();
+        }
+
+        let records = TelemetryReader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            records.len() as u64,
+            *state.executions() - executions_before
+        );
+        assert_eq!(records[0].executions, executions_before);
+        // the only stage is the last one
+        assert!(records
+            .iter()
+            .all(|record| record.corpus_id == CorpusId(0) && record.stage == Some(1)));
+        assert!(records.iter().all(|record| !record.mutations.is_empty()
+            && record.mutations.iter().all(|name| name == "BitFlipMutator")));
+        assert!(records
+            .iter()
+            .all(|record| (record.exit_kind == ExitKind::Crash)
+                == (record.decision == TelemetryDecision::Solution)));
+        assert!(records
+            .iter()
+            .any(|record| record.decision == TelemetryDecision::Solution));
+        assert!(records
+            .iter()
+            .any(|record| record.executions > executions_before));
+    }
+
+    #[test]
+    fn test_telemetry_reader_corrupted() {
+        let mut log = b"LAFLTEL1".to_vec();
+        log.extend_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = TelemetryReader::new(log.as_slice()).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+}
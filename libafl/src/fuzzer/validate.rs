@@ -0,0 +1,471 @@
+//! A dry run of the harness, to catch problems before a campaign starts.
+//!
+//! The [`HarnessValidator`] runs each seed several times, and reports coverage that differs between runs,
+//! seeds that do not exit cleanly, coverage that looks like the target is not instrumented
+//! or the map observer is not wired to the map the target writes, and how long executions take.
+//! Most campaigns that find nothing suffer from one of these.
+
+use alloc::{string::ToString, vec, vec::Vec};
+use core::{
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use libafl_bolts::{
+    current_time,
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    executors::{ExitKind, HasObservers},
+    fuzzer::ExecutesInput,
+    inputs::UsesInput,
+    observers::MapObserver,
+    state::UsesState,
+    Error,
+};
+
+/// The default amount of times each seed is run
+pub const DEFAULT_VALIDATION_RUNS: usize = 4;
+
+/// Executions slower than this are reported as [`ValidationIssue::SlowExecutions`]
+const SLOW_EXECUTION: Duration = Duration::from_millis(100);
+
+/// A problem with the harness found by a [`HarnessValidator`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ValidationIssue {
+    /// No seed hit a single map entry
+    NoCoverage,
+    /// All seeds hit only a handful of map entries
+    FewEntries(usize),
+    /// All seeds hit exactly the same map entries
+    ConstantCoverage,
+    /// Map entries that are hit in some runs of a seed, but not in others
+    UnstableEntries {
+        /// The amount of unstable entries
+        unstable: usize,
+        /// The amount of entries hit by any run
+        covered: usize,
+    },
+    /// Most entries of the map are hit by the seeds alone
+    MapAlmostFull {
+        /// The amount of entries hit by any run
+        covered: usize,
+        /// The usable size of the map
+        map_size: usize,
+    },
+    /// A seed exits differently between runs
+    NondeterministicExit {
+        /// The index of the seed
+        seed: usize,
+    },
+    /// A seed does not exit with [`ExitKind::Ok`]
+    SeedNotOk {
+        /// The index of the seed
+        seed: usize,
+        /// How the seed exited in its first run
+        exit_kind: ExitKind,
+    },
+    /// Executions take long on average
+    SlowExecutions(Duration),
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCoverage => write!(
+                f,
+                "no map entry was hit: the target is not instrumented, or the map observer does not observe the map the target writes"
+            ),
+            Self::FewEntries(covered) => write!(
+                f,
+                "only {covered} map entries were hit: the instrumentation is probably missing for most of the target"
+            ),
+            Self::ConstantCoverage => write!(
+                f,
+                "all seeds hit the same map entries: the harness may not pass the input to the target"
+            ),
+            Self::UnstableEntries { unstable, covered } => write!(
+                f,
+                "{unstable} of {covered} hit map entries ({:.2}%) are unstable: look for randomness, time, threads or global state in the target",
+                percent(*unstable, *covered)
+            ),
+            Self::MapAlmostFull { covered, map_size } => write!(
+                f,
+                "the seeds hit {covered} of {map_size} map entries ({:.2}%): the map is probably too small, increase its size",
+                percent(*covered, *map_size)
+            ),
+            Self::NondeterministicExit { seed } => write!(
+                f,
+                "seed {seed} exits differently between runs: the target is not deterministic"
+            ),
+            Self::SeedNotOk { seed, exit_kind } => write!(
+                f,
+                "seed {seed} exits with {exit_kind:?}: remove it from the seeds, or fix the target"
+            ),
+            Self::SlowExecutions(time) => write!(
+                f,
+                "executions take {time:?} on average: look for initialization that runs on every execution, or use a persistent harness"
+            ),
+        }
+    }
+}
+
+/// Extend `out` with the entries that are in only one of the sorted slices `a` and `b`
+fn extend_symmetric_difference(out: &mut Vec<usize>, a: &[usize], b: &[usize]) {
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+    loop {
+        match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) if x < y => out.extend(a.next()),
+            (Some(x), Some(y)) if x > y => out.extend(b.next()),
+            (Some(_), Some(_)) => {
+                a.next();
+                b.next();
+            }
+            (Some(_), None) => out.extend(a.by_ref()),
+            (None, Some(_)) => out.extend(b.by_ref()),
+            (None, None) => return,
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// The outcome of a [`HarnessValidator`] dry run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// The amount of seeds
+    pub seeds: usize,
+    /// The amount of times each seed was run
+    pub runs: usize,
+    /// The usable size of the map
+    pub map_size: usize,
+    /// The map entries hit by any run, sorted
+    pub covered: Vec<usize>,
+    /// The map entries hit by some runs of a seed, but not by others, sorted
+    pub unstable: Vec<usize>,
+    /// The time of the first execution, which includes any initialization of the target
+    pub startup_time: Duration,
+    /// The average time of the other executions
+    pub exec_time: Duration,
+    /// The problems found
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// If no problems were found
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The share of hit map entries that are unstable, in percent
+    #[must_use]
+    pub fn unstable_percent(&self) -> f64 {
+        percent(self.unstable.len(), self.covered.len())
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "seeds: {}, {} runs each", self.seeds, self.runs)?;
+        writeln!(
+            f,
+            "coverage: {} of {} map entries, {} unstable ({:.2}%)",
+            self.covered.len(),
+            self.map_size,
+            self.unstable.len(),
+            self.unstable_percent()
+        )?;
+        writeln!(
+            f,
+            "startup: {:?}, executions: {:?} on average",
+            self.startup_time, self.exec_time
+        )?;
+        if self.issues.is_empty() {
+            writeln!(f, "no issues found")?;
+        }
+        for issue in &self.issues {
+            writeln!(f, "issue: {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs each seed several times through the executor of a fuzzer, before the campaign starts,
+/// and reports problems with the harness.
+#[derive(Debug)]
+pub struct HarnessValidator<C, O> {
+    map_ref: Handle<C>,
+    runs: usize,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> HarnessValidator<C, O>
+where
+    C: AsRef<O> + Named,
+    O: MapObserver,
+{
+    /// Create a new [`HarnessValidator`], checking the coverage of the given map observer
+    #[must_use]
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_ref: map_observer.handle(),
+            runs: DEFAULT_VALIDATION_RUNS,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Run each seed `runs` times, at least twice
+    #[must_use]
+    pub fn with_runs(mut self, runs: usize) -> Self {
+        self.runs = runs.max(2);
+        self
+    }
+
+    /// The map entries hit by the last execution
+    fn hit_entries<E>(&self, executor: &E) -> Result<Vec<usize>, Error>
+    where
+        E: HasObservers,
+    {
+        let observers = executor.observers();
+        let map = observers
+            .get(&self.map_ref)
+            .ok_or_else(|| {
+                Error::key_not_found(
+                    "Map observer not found, it is not among the observers of the executor"
+                        .to_string(),
+                )
+            })?
+            .as_ref();
+        let initial = map.initial();
+        Ok((0..map.usable_count())
+            .filter(|&idx| map.get(idx) != initial)
+            .collect())
+    }
+
+    /// Run each of the `seeds` several times, and report on the harness
+    pub fn validate<E, EM, S, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        mgr: &mut EM,
+        seeds: &[S::Input],
+    ) -> Result<ValidationReport, Error>
+    where
+        E: HasObservers + UsesState<State = S>,
+        EM: UsesState<State = S>,
+        S: UsesInput,
+        Z: ExecutesInput<E, EM, State = S>,
+    {
+        if seeds.is_empty() {
+            return Err(Error::empty("No seeds to validate the harness with"));
+        }
+
+        let mut issues = vec![];
+        let mut covered = vec![];
+        let mut unstable = vec![];
+        let mut seed_coverage = vec![];
+        let mut startup_time = Duration::ZERO;
+        let mut exec_time = Duration::ZERO;
+        let mut executions = 0_u32;
+
+        for (seed, input) in seeds.iter().enumerate() {
+            let mut first: Option<(ExitKind, Vec<usize>)> = None;
+            let mut nondeterministic = false;
+            for _ in 0..self.runs {
+                let start = current_time();
+                let exit_kind = fuzzer.execute_input(state, executor, mgr, input)?;
+                let time = current_time().saturating_sub(start);
+                if executions == 0 {
+                    startup_time = time;
+                } else {
+                    exec_time += time;
+                }
+                executions += 1;
+
+                let hit = self.hit_entries(executor)?;
+                covered.extend_from_slice(&hit);
+                match &first {
+                    None => first = Some((exit_kind, hit)),
+                    Some((first_exit_kind, first_hit)) => {
+                        nondeterministic |= exit_kind != *first_exit_kind;
+                        // entries hit by only one of the runs
+                        extend_symmetric_difference(&mut unstable, first_hit, &hit);
+                    }
+                }
+            }
+
+            let (exit_kind, hit) = first.unwrap();
+            if nondeterministic {
+                issues.push(ValidationIssue::NondeterministicExit { seed });
+            }
+            if exit_kind != ExitKind::Ok {
+                issues.push(ValidationIssue::SeedNotOk { seed, exit_kind });
+            }
+            seed_coverage.push(hit);
+        }
+
+        covered.sort_unstable();
+        covered.dedup();
+        unstable.sort_unstable();
+        unstable.dedup();
+        if executions > 1 {
+            exec_time /= executions - 1;
+        }
+        let map_size = {
+            let observers = executor.observers();
+            observers
+                .get(&self.map_ref)
+                .map_or(0, |map| map.as_ref().usable_count())
+        };
+
+        if covered.is_empty() {
+            issues.push(ValidationIssue::NoCoverage);
+        } else if covered.len() < 3 {
+            issues.push(ValidationIssue::FewEntries(covered.len()));
+        } else if seed_coverage.len() > 1 && seed_coverage.windows(2).all(|w| w[0] == w[1]) {
+            issues.push(ValidationIssue::ConstantCoverage);
+        }
+        if !unstable.is_empty() {
+            issues.push(ValidationIssue::UnstableEntries {
+                unstable: unstable.len(),
+                covered: covered.len(),
+            });
+        }
+        if covered.len() * 10 > map_size * 9 {
+            issues.push(ValidationIssue::MapAlmostFull {
+                covered: covered.len(),
+                map_size,
+            });
+        }
+        if exec_time > SLOW_EXECUTION {
+            issues.push(ValidationIssue::SlowExecutions(exec_time));
+        }
+
+        Ok(ValidationReport {
+            seeds: seeds.len(),
+            runs: self.runs,
+            map_size,
+            covered,
+            unstable,
+            startup_time,
+            exec_time,
+            issues,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::addr_of_mut;
+
+    use libafl_bolts::{
+        rands::StdRand,
+        tuples::{tuple_list, tuple_list_type},
+        AsSlice,
+    };
+    use serial_test::serial;
+
+    use crate::{
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::{CrashFeedback, MaxMapFeedback},
+        fuzzer::{
+            validate::{extend_symmetric_difference, HarnessValidator, ValidationIssue},
+            StdFuzzer,
+        },
+        inputs::{BytesInput, HasTargetBytes},
+        observers::StdMapObserver,
+        schedulers::QueueScheduler,
+        state::StdState,
+    };
+
+    static mut MAP: [u8; 16] = [0; 16];
+    static mut COUNTER: usize = 0;
+
+    #[test]
+    fn test_symmetric_difference() {
+        let mut out = vec![];
+        extend_symmetric_difference(&mut out, &[1, 3, 4, 7], &[0, 3, 5, 7, 8, 9]);
+        assert_eq!(out, [0, 1, 4, 5, 8, 9]);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_harness_validator() {
+        let mut harness = |input: &BytesInput| {
+            let map = unsafe { &mut *addr_of_mut!(MAP) };
+            let counter = unsafe { &mut *addr_of_mut!(COUNTER) };
+            map[0] = 1;
+            map[1] = 1;
+            if input.target_bytes().as_slice().first() == Some(&b'a') {
+                map[2] = 1;
+            }
+            // an unstable entry
+            *counter += 1;
+            if *counter % 2 == 0 {
+                map[3] = 1;
+            }
+            ExitKind::Ok
+        };
+        let edges = unsafe {
+            StdMapObserver::from_mut_slice("edges", (*addr_of_mut!(MAP)).as_mut_slice().into())
+        };
+        let validator = HarnessValidator::new(&edges);
+
+        let mut feedback = MaxMapFeedback::new(&edges);
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer: StdFuzzer<_, _, _, tuple_list_type!(StdMapObserver<u8, false>)> =
+            StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(edges),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+
+        let seeds = [
+            BytesInput::new(b"a".to_vec()),
+            BytesInput::new(b"b".to_vec()),
+        ];
+        let report = validator
+            .validate(&mut fuzzer, &mut executor, &mut state, &mut mgr, &seeds)
+            .unwrap();
+
+        assert_eq!(report.map_size, 16);
+        assert_eq!(report.covered, [0, 1, 2, 3]);
+        assert_eq!(report.unstable, [3]);
+        assert_eq!(
+            report.issues,
+            [ValidationIssue::UnstableEntries {
+                unstable: 1,
+                covered: 4
+            }]
+        );
+    }
+}
@@ -5,6 +5,7 @@ use alloc::{borrow::ToOwned, rc::Rc, string::String, vec::Vec};
 use core::{
     cell::RefCell,
     hash::{BuildHasher, Hasher},
+    ops::Range,
 };
 #[cfg(feature = "std")]
 use std::{fs::File, io::Read, path::Path};
@@ -17,7 +18,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     corpus::CorpusId,
-    inputs::{HasMutatorBytes, HasTargetBytes, Input},
+    inputs::{HasMutatorBytes, HasTargetBytes, Input, Trimmable},
 };
 
 /// A bytes input is the basic input
@@ -104,6 +105,11 @@ impl HasTargetBytes for BytesInput {
     fn target_bytes(&self) -> OwnedSlice<u8> {
         OwnedSlice::from(&self.bytes)
     }
+
+    #[inline]
+    fn target_bytes_into<'a>(&'a self, _buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        &self.bytes
+    }
 }
 
 impl HasLen for BytesInput {
@@ -113,6 +119,18 @@ impl HasLen for BytesInput {
     }
 }
 
+impl Trimmable for BytesInput {
+    #[inline]
+    fn remove_range(&mut self, range: Range<usize>) {
+        self.bytes.drain(range);
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.bytes.truncate(len);
+    }
+}
+
 impl From<Vec<u8>> for BytesInput {
     fn from(bytes: Vec<u8>) -> Self {
         Self::new(bytes)
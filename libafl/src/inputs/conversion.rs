@@ -0,0 +1,308 @@
+//! A registry of [`InputConverter`]s, to convert between input types chosen at runtime.
+//!
+//! Conversions are looked up by the source and destination type, and chained if there is no direct converter,
+//! so registering `BytesInput -> EncodedInput` and `EncodedInput -> MultipartInput` also converts
+//! `BytesInput -> MultipartInput`. Use a [`crate::mutators::ConvertingMutator`] to mutate in another representation
+//! than the one in the corpus, without a dedicated [`crate::stages::mutational::MutatedTransform`].
+
+#[cfg(feature = "multipart_inputs")]
+use alloc::string::String;
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc, vec::Vec};
+use core::{
+    any::{type_name, Any, TypeId},
+    cell::RefCell,
+    fmt::{self, Debug, Formatter},
+};
+
+use hashbrown::HashMap;
+use libafl_bolts::Error;
+
+use crate::inputs::{
+    BytesInput, EncodedInput, HasMutatorBytes, Input, InputConverter, InputDecoder, InputEncoder,
+    TokenInputEncoderDecoder, Tokenizer,
+};
+#[cfg(feature = "multipart_inputs")]
+use crate::inputs::{HasTargetBytes, MultipartInput, PartsJoin};
+
+/// An [`InputConverter`], with the types erased
+trait ErasedInputConverter: Debug {
+    fn convert_any(&mut self, input: Box<dyn Any>) -> Result<Box<dyn Any>, Error>;
+}
+
+impl<IC> ErasedInputConverter for IC
+where
+    IC: InputConverter,
+    IC::From: 'static,
+    IC::To: 'static,
+{
+    fn convert_any(&mut self, input: Box<dyn Any>) -> Result<Box<dyn Any>, Error> {
+        let input = input
+            .downcast::<IC::From>()
+            .map_err(|_| Error::illegal_state("Converter got an input of the wrong type"))?;
+        Ok(Box::new(self.convert(*input)?))
+    }
+}
+
+#[derive(Debug)]
+struct RegisteredConverter {
+    from: &'static str,
+    to: &'static str,
+    converter: Box<dyn ErasedInputConverter>,
+}
+
+/// A registry of [`InputConverter`]s between input types, see the [module documentation](self)
+#[derive(Default)]
+pub struct InputConverterRegistry {
+    converters: HashMap<(TypeId, TypeId), RegisteredConverter>,
+}
+
+impl Debug for InputConverterRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.converters
+                    .values()
+                    .map(|converter| (converter.from, converter.to)),
+            )
+            .finish()
+    }
+}
+
+impl InputConverterRegistry {
+    /// Creates a new, empty [`InputConverterRegistry`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `converter`, replacing the previous converter between the same types
+    pub fn register<IC>(&mut self, converter: IC)
+    where
+        IC: InputConverter + 'static,
+        IC::From: 'static,
+        IC::To: 'static,
+    {
+        self.converters.insert(
+            (TypeId::of::<IC::From>(), TypeId::of::<IC::To>()),
+            RegisteredConverter {
+                from: type_name::<IC::From>(),
+                to: type_name::<IC::To>(),
+                converter: Box::new(converter),
+            },
+        );
+    }
+
+    /// Registers `converter`, replacing the previous converter between the same types
+    #[must_use]
+    pub fn with_converter<IC>(mut self, converter: IC) -> Self
+    where
+        IC: InputConverter + 'static,
+        IC::From: 'static,
+        IC::To: 'static,
+    {
+        self.register(converter);
+        self
+    }
+
+    /// Registers the conversions between [`BytesInput`]s and [`EncodedInput`]s, tokenized with `tokenizer`
+    #[must_use]
+    pub fn with_token_encoding<T>(mut self, tokenizer: T) -> Self
+    where
+        T: Tokenizer + Debug + 'static,
+    {
+        let table = Rc::new(RefCell::new(TokenInputEncoderDecoder::new()));
+        self.register(BytesToEncodedConverter {
+            tokenizer,
+            table: table.clone(),
+        });
+        self.register(EncodedToBytesConverter { table });
+        self
+    }
+
+    /// Registers the conversions between [`BytesInput`]s and [`MultipartInput`]s of a single part called `name`.
+    /// Multipart inputs are converted back to their target bytes, joined with `join`.
+    #[cfg(feature = "multipart_inputs")]
+    #[must_use]
+    pub fn with_multipart(mut self, name: &str, join: PartsJoin) -> Self {
+        self.register(BytesToMultipartConverter {
+            name: name.into(),
+            join,
+        });
+        self.register(MultipartToBytesConverter);
+        self
+    }
+
+    /// The shortest chain of conversions from `from` to `to`, empty if they are the same type
+    fn path(&self, from: TypeId, to: TypeId) -> Option<Vec<(TypeId, TypeId)>> {
+        let mut previous: HashMap<TypeId, TypeId> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![];
+                let mut node = to;
+                while node != from {
+                    let prev = previous[&node];
+                    path.push((prev, node));
+                    node = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (edge_from, edge_to) in self.converters.keys() {
+                if *edge_from == current && *edge_to != from && !previous.contains_key(edge_to) {
+                    previous.insert(*edge_to, current);
+                    queue.push_back(*edge_to);
+                }
+            }
+        }
+        None
+    }
+
+    /// If inputs of type `F` can be converted to `T`, directly or through other types
+    #[must_use]
+    pub fn can_convert<F, T>(&self) -> bool
+    where
+        F: 'static,
+        T: 'static,
+    {
+        self.path(TypeId::of::<F>(), TypeId::of::<T>()).is_some()
+    }
+
+    /// Converts `input` to `T`, through the shortest chain of registered converters
+    pub fn convert<F, T>(&mut self, input: F) -> Result<T, Error>
+    where
+        F: Input + 'static,
+        T: Input + 'static,
+    {
+        let path = self
+            .path(TypeId::of::<F>(), TypeId::of::<T>())
+            .ok_or_else(|| {
+                Error::illegal_argument(format!(
+                    "No registered conversion from {} to {}",
+                    type_name::<F>(),
+                    type_name::<T>()
+                ))
+            })?;
+        let mut input: Box<dyn Any> = Box::new(input);
+        for edge in path {
+            input = self
+                .converters
+                .get_mut(&edge)
+                .unwrap()
+                .converter
+                .convert_any(input)?;
+        }
+        Ok(*input.downcast::<T>().unwrap())
+    }
+}
+
+/// Converts [`BytesInput`]s to [`EncodedInput`]s, see [`InputConverterRegistry::with_token_encoding`]
+#[derive(Debug)]
+pub struct BytesToEncodedConverter<T> {
+    tokenizer: T,
+    table: Rc<RefCell<TokenInputEncoderDecoder>>,
+}
+
+impl<T> InputConverter for BytesToEncodedConverter<T>
+where
+    T: Tokenizer + Debug,
+{
+    type From = BytesInput;
+    type To = EncodedInput;
+
+    fn convert(&mut self, input: BytesInput) -> Result<EncodedInput, Error> {
+        self.table
+            .borrow_mut()
+            .encode(input.bytes(), &mut self.tokenizer)
+    }
+}
+
+/// Converts [`EncodedInput`]s back to [`BytesInput`]s, see [`InputConverterRegistry::with_token_encoding`]
+#[derive(Debug)]
+pub struct EncodedToBytesConverter {
+    table: Rc<RefCell<TokenInputEncoderDecoder>>,
+}
+
+impl InputConverter for EncodedToBytesConverter {
+    type From = EncodedInput;
+    type To = BytesInput;
+
+    fn convert(&mut self, input: EncodedInput) -> Result<BytesInput, Error> {
+        let mut bytes = vec![];
+        self.table.borrow().decode(&input, &mut bytes)?;
+        Ok(BytesInput::new(bytes))
+    }
+}
+
+/// Converts [`BytesInput`]s to [`MultipartInput`]s of a single part, see [`InputConverterRegistry::with_multipart`]
+#[cfg(feature = "multipart_inputs")]
+#[derive(Debug)]
+pub struct BytesToMultipartConverter {
+    name: String,
+    join: PartsJoin,
+}
+
+#[cfg(feature = "multipart_inputs")]
+impl InputConverter for BytesToMultipartConverter {
+    type From = BytesInput;
+    type To = MultipartInput<BytesInput>;
+
+    fn convert(&mut self, input: BytesInput) -> Result<MultipartInput<BytesInput>, Error> {
+        let mut multipart = MultipartInput::new().with_join(self.join.clone());
+        multipart.add_part(self.name.clone(), input);
+        Ok(multipart)
+    }
+}
+
+/// Converts [`MultipartInput`]s to [`BytesInput`]s of their joined parts, see [`InputConverterRegistry::with_multipart`]
+#[cfg(feature = "multipart_inputs")]
+#[derive(Debug)]
+pub struct MultipartToBytesConverter;
+
+#[cfg(feature = "multipart_inputs")]
+impl InputConverter for MultipartToBytesConverter {
+    type From = MultipartInput<BytesInput>;
+    type To = BytesInput;
+
+    fn convert(&mut self, input: MultipartInput<BytesInput>) -> Result<BytesInput, Error> {
+        Ok(BytesInput::new(input.target_bytes().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::inputs::{
+        conversion::InputConverterRegistry, BytesInput, ClosureInputConverter, EncodedInput,
+        HasMutatorBytes, NopInput,
+    };
+
+    #[test]
+    fn test_input_converter_registry() {
+        let mut registry = InputConverterRegistry::new()
+            .with_converter(ClosureInputConverter::<BytesInput, EncodedInput>::new(
+                Box::new(|input| {
+                    Ok(EncodedInput::new(
+                        input.bytes().iter().map(|b| (*b).into()).collect(),
+                    ))
+                }),
+            ))
+            .with_converter(ClosureInputConverter::<EncodedInput, NopInput>::new(
+                Box::new(|_| Ok(NopInput {})),
+            ));
+
+        let encoded: EncodedInput = registry.convert(BytesInput::new(vec![1, 2])).unwrap();
+        assert_eq!(encoded.codes(), &[1, 2]);
+        // Chained through the EncodedInput
+        assert!(registry.can_convert::<BytesInput, NopInput>());
+        registry
+            .convert::<_, NopInput>(BytesInput::new(vec![1]))
+            .unwrap();
+        assert!(!registry.can_convert::<NopInput, BytesInput>());
+        assert!(registry.convert::<_, BytesInput>(NopInput {}).is_err());
+        // Converting to the same type is free
+        let same: BytesInput = registry.convert(BytesInput::new(vec![3])).unwrap();
+        assert_eq!(same.bytes(), &[3]);
+    }
+}
@@ -10,6 +10,7 @@ use core::str::from_utf8;
 use core::{
     cell::RefCell,
     hash::{BuildHasher, Hasher},
+    ops::Range,
 };
 
 use ahash::RandomState;
@@ -19,7 +20,11 @@ use libafl_bolts::{Error, HasLen};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::{corpus::CorpusId, inputs::Input};
+use crate::{
+    corpus::CorpusId,
+    inputs::{Input, Trimmable},
+    mutators::EncodedBracketsMetadata,
+};
 
 /// Trait to encode bytes to an [`EncodedInput`] using the given [`Tokenizer`]
 pub trait InputEncoder<T>
@@ -99,6 +104,12 @@ impl TokenInputEncoderDecoder {
             next_id: 0,
         }
     }
+
+    /// The code of the given token, if it was encoded before
+    #[must_use]
+    pub fn code_of(&self, token: &str) -> Option<u32> {
+        self.token_table.get(token).copied()
+    }
 }
 
 impl Default for TokenInputEncoderDecoder {
@@ -225,6 +236,18 @@ impl HasLen for EncodedInput {
     }
 }
 
+impl Trimmable for EncodedInput {
+    #[inline]
+    fn remove_range(&mut self, range: Range<usize>) {
+        self.codes.drain(range);
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        self.codes.truncate(len);
+    }
+}
+
 impl From<Vec<u32>> for EncodedInput {
     #[must_use]
     fn from(codes: Vec<u32>) -> Self {
@@ -257,6 +280,17 @@ impl EncodedInput {
     pub fn codes_mut(&mut self) -> &mut Vec<u32> {
         &mut self.codes
     }
+
+    /// The balanced region whose opening or closing bracket is at `idx`, including its brackets,
+    /// see [`EncodedBracketsMetadata::matched_region_at`]
+    #[must_use]
+    pub fn matched_region_at(
+        &self,
+        brackets: &EncodedBracketsMetadata,
+        idx: usize,
+    ) -> Option<Range<usize>> {
+        brackets.matched_region_at(&self.codes, idx)
+    }
 }
 
 #[cfg(feature = "regex")]
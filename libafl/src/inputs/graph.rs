@@ -0,0 +1,274 @@
+//! An input shaped as a directed graph, for state machines, scene graphs, and other targets
+//! whose inputs are nodes with payloads and the relations between them.
+//!
+//! The mutators in [`crate::mutators::graph`] change the structure, while the payloads
+//! can be mutated by any mutator for their type. A [`GraphLinearizer`] renders the graph to the bytes of the target.
+
+use alloc::{collections::VecDeque, string::String, vec::Vec};
+use core::hash::{BuildHasher, Hasher};
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasTargetBytes, Input},
+};
+
+/// A directed graph of nodes with payloads of type `P`, possibly with cycles and parallel edges
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GraphInput<P> {
+    nodes: Vec<P>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl<P> Input for GraphInput<P>
+where
+    P: Input,
+{
+    /// Generate a name for this input
+    fn generate_name(&self, id: Option<CorpusId>) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        for node in &self.nodes {
+            hasher.write(node.generate_name(id).as_bytes());
+        }
+        for (from, to) in &self.edges {
+            hasher.write_usize(*from);
+            hasher.write_usize(*to);
+        }
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl<P> HasLen for GraphInput<P> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl<P> HasTargetBytes for GraphInput<P>
+where
+    P: HasTargetBytes,
+{
+    /// The graph, linearized by the [`EdgeListLinearizer`].
+    /// To use another format, render the input with [`GraphInput::linearize_with`] in the harness.
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(self.linearize_with(&mut EdgeListLinearizer))
+    }
+
+    fn target_bytes_into<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        buffer.clear();
+        EdgeListLinearizer.linearize(self, buffer);
+        buffer
+    }
+}
+
+impl<P> GraphInput<P> {
+    /// Creates a new [`GraphInput`]. All edges must connect existing nodes.
+    #[must_use]
+    pub fn new(nodes: Vec<P>, edges: Vec<(usize, usize)>) -> Self {
+        debug_assert!(edges
+            .iter()
+            .all(|(from, to)| *from < nodes.len() && *to < nodes.len()));
+        Self { nodes, edges }
+    }
+
+    /// The payloads of the nodes
+    #[must_use]
+    pub fn nodes(&self) -> &[P] {
+        &self.nodes
+    }
+
+    /// The payloads of the nodes, mutable
+    #[must_use]
+    pub fn nodes_mut(&mut self) -> &mut [P] {
+        &mut self.nodes
+    }
+
+    /// The edges, as pairs of the indices of the source and the target node
+    #[must_use]
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// The edges, mutable. Keep all edges connecting existing nodes.
+    #[must_use]
+    pub fn edges_mut(&mut self) -> &mut Vec<(usize, usize)> {
+        &mut self.edges
+    }
+
+    /// Adds a node, returns its index
+    pub fn add_node(&mut self, payload: P) -> usize {
+        self.nodes.push(payload);
+        self.nodes.len() - 1
+    }
+
+    /// Removes the node at `idx` and all its edges, the nodes after it move down by one
+    pub fn remove_node(&mut self, idx: usize) -> Option<P> {
+        if idx >= self.nodes.len() {
+            return None;
+        }
+        let payload = self.nodes.remove(idx);
+        self.edges.retain(|(from, to)| *from != idx && *to != idx);
+        for (from, to) in &mut self.edges {
+            if *from > idx {
+                *from -= 1;
+            }
+            if *to > idx {
+                *to -= 1;
+            }
+        }
+        Some(payload)
+    }
+
+    /// Adds an edge from `from` to `to`
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        debug_assert!(from < self.nodes.len() && to < self.nodes.len());
+        self.edges.push((from, to));
+    }
+
+    /// The nodes that can be reached from `start` along the edges, `start` first, in breadth-first order
+    #[must_use]
+    pub fn reachable_from(&self, start: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut reachable = vec![];
+        let mut queue = VecDeque::from([start]);
+        seen[start] = true;
+        while let Some(node) = queue.pop_front() {
+            reachable.push(node);
+            for (_, to) in self.edges.iter().filter(|(from, _)| *from == node) {
+                if !seen[*to] {
+                    seen[*to] = true;
+                    queue.push_back(*to);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// If there is a path from `from` to `to`
+    #[must_use]
+    pub fn has_path(&self, from: usize, to: usize) -> bool {
+        self.reachable_from(from).contains(&to)
+    }
+
+    /// If the graph has no cycles, that is, if it is a DAG
+    #[must_use]
+    pub fn is_acyclic(&self) -> bool {
+        // Kahn's algorithm: all nodes get removed, if and only if there is no cycle
+        let mut in_degree = vec![0_usize; self.nodes.len()];
+        for (_, to) in &self.edges {
+            in_degree[*to] += 1;
+        }
+        let mut queue: VecDeque<usize> = (0..self.nodes.len())
+            .filter(|node| in_degree[*node] == 0)
+            .collect();
+        let mut removed = 0;
+        while let Some(node) = queue.pop_front() {
+            removed += 1;
+            for (_, to) in self.edges.iter().filter(|(from, _)| *from == node) {
+                in_degree[*to] -= 1;
+                if in_degree[*to] == 0 {
+                    queue.push_back(*to);
+                }
+            }
+        }
+        removed == self.nodes.len()
+    }
+
+    /// Linearize the graph with the given `linearizer`
+    pub fn linearize_with<L>(&self, linearizer: &mut L) -> Vec<u8>
+    where
+        L: GraphLinearizer<P>,
+    {
+        let mut bytes = vec![];
+        linearizer.linearize(self, &mut bytes);
+        bytes
+    }
+}
+
+/// Renders a [`GraphInput`] to the bytes the target receives
+pub trait GraphLinearizer<P> {
+    /// Appends the linearized `graph` to `bytes`
+    fn linearize(&mut self, graph: &GraphInput<P>, bytes: &mut Vec<u8>);
+}
+
+impl<F, P> GraphLinearizer<P> for F
+where
+    F: FnMut(&GraphInput<P>, &mut Vec<u8>),
+{
+    fn linearize(&mut self, graph: &GraphInput<P>, bytes: &mut Vec<u8>) {
+        self(graph, bytes);
+    }
+}
+
+/// The default [`GraphLinearizer`], used for the target bytes of a [`GraphInput`].
+///
+/// All integers are little-endian `u32`s. The graph is written as the number of nodes,
+/// followed by the length and the bytes of each payload, then the number of edges,
+/// followed by the source and the target index of each edge.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EdgeListLinearizer;
+
+impl<P> GraphLinearizer<P> for EdgeListLinearizer
+where
+    P: HasTargetBytes,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn linearize(&mut self, graph: &GraphInput<P>, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&(graph.nodes.len() as u32).to_le_bytes());
+        for node in &graph.nodes {
+            let payload = node.target_bytes();
+            bytes.extend_from_slice(&(payload.as_slice().len() as u32).to_le_bytes());
+            bytes.extend_from_slice(payload.as_slice());
+        }
+        bytes.extend_from_slice(&(graph.edges.len() as u32).to_le_bytes());
+        for (from, to) in &graph.edges {
+            bytes.extend_from_slice(&(*from as u32).to_le_bytes());
+            bytes.extend_from_slice(&(*to as u32).to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::inputs::{graph::GraphInput, BytesInput, HasTargetBytes};
+
+    #[test]
+    fn test_graph_input() {
+        let mut graph = GraphInput::new(
+            vec![
+                BytesInput::new(vec![0]),
+                BytesInput::new(vec![1]),
+                BytesInput::new(vec![2]),
+            ],
+            vec![(0, 1), (1, 2)],
+        );
+        assert!(graph.is_acyclic());
+        assert_eq!(graph.reachable_from(0), vec![0, 1, 2]);
+        assert!(!graph.has_path(2, 0));
+
+        graph.add_edge(2, 0);
+        assert!(!graph.is_acyclic());
+
+        assert_eq!(graph.remove_node(1), Some(BytesInput::new(vec![1])));
+        assert_eq!(graph.edges(), &[(1, 0)]);
+        assert!(graph.is_acyclic());
+
+        let bytes = graph.target_bytes();
+        assert_eq!(
+            &*bytes,
+            &[2, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 2, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+        );
+        let mut dot = |graph: &GraphInput<BytesInput>, bytes: &mut Vec<u8>| {
+            for (from, to) in graph.edges() {
+                bytes.extend(format!("{from}->{to};").bytes());
+            }
+        };
+        assert_eq!(graph.linearize_with(&mut dot), b"1->0;");
+    }
+}
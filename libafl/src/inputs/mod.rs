@@ -15,6 +15,23 @@ pub use generalized::*;
 pub mod bytessub;
 pub use bytessub::BytesSubInput;
 
+pub mod syscalls;
+pub use syscalls::*;
+
+pub mod graph;
+pub use graph::*;
+
+pub mod value;
+pub use value::*;
+
+pub mod conversion;
+pub use conversion::*;
+
+#[cfg(feature = "std")]
+pub mod streaming;
+#[cfg(feature = "std")]
+pub use streaming::StreamingInput;
+
 #[cfg(feature = "multipart_inputs")]
 pub mod multi;
 #[cfg(feature = "multipart_inputs")]
@@ -28,13 +45,18 @@ use alloc::{
     string::{String, ToString},
     vec::{Drain, Splice, Vec},
 };
-use core::{clone::Clone, fmt::Debug, marker::PhantomData, ops::RangeBounds};
+use core::{
+    clone::Clone,
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Range, RangeBounds},
+};
 #[cfg(feature = "std")]
 use std::{fs::File, hash::Hash, io::Read, path::Path};
 
 #[cfg(feature = "std")]
 use libafl_bolts::fs::write_file_atomic;
-use libafl_bolts::{ownedref::OwnedSlice, Error, HasLen};
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice, Error, HasLen};
 #[cfg(feature = "nautilus")]
 pub use nautilus::*;
 use serde::{Deserialize, Serialize};
@@ -123,13 +145,23 @@ impl HasTargetBytes for NopInput {
     }
 }
 
-// TODO change this to fn target_bytes(&self, buffer: &mut Vec<u8>) -> &[u8];
 /// Can be represented with a vector of bytes.
 /// This representation is not necessarily deserializable.
 /// Instead, it can be used as bytes input for a target
 pub trait HasTargetBytes {
     /// Target bytes, that can be written to a target
     fn target_bytes(&self) -> OwnedSlice<u8>;
+
+    /// Target bytes, rendered into `buffer` if needed, to reuse its allocation between executions.
+    ///
+    /// Inputs holding their bytes return them directly, and leave `buffer` as is.
+    /// Inputs that render their bytes, such as [`GraphInput`], should override this method
+    /// to render straight into `buffer`, as the default renders with [`HasTargetBytes::target_bytes`] and copies.
+    fn target_bytes_into<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        buffer.clear();
+        buffer.extend_from_slice(self.target_bytes().as_slice());
+        buffer
+    }
 }
 
 /// Contains mutateable and resizable bytes
@@ -168,6 +200,21 @@ pub trait HasMutatorBytes: HasLen {
     }
 }
 
+/// An input that can shrink by removing contiguous ranges of its elements (bytes, codes, tokens, ...).
+/// Used to trim corpus entries independently of their concrete representation.
+pub trait Trimmable: HasLen {
+    /// Removes the elements in the given `range`
+    fn remove_range(&mut self, range: Range<usize>);
+
+    /// Shortens this input to `len` elements, does nothing if it is already shorter
+    fn truncate(&mut self, len: usize) {
+        let cur_len = self.len();
+        if len < cur_len {
+            self.remove_range(len..cur_len);
+        }
+    }
+}
+
 /// A wrapper type that allows us to use mutators for Mutators for `&mut `[`Vec`].
 #[derive(Debug)]
 pub struct MutVecInput<'a>(&'a mut Vec<u8>);
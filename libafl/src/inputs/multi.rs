@@ -10,9 +10,66 @@ use alloc::{
 };
 
 use arrayvec::ArrayVec;
+use libafl_bolts::ownedref::OwnedSlice;
 use serde::{Deserialize, Serialize};
 
-use crate::{corpus::CorpusId, inputs::Input};
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasTargetBytes, Input},
+};
+
+/// How the parts of a [`MultipartInput`] are joined into the bytes the target receives,
+/// see [`MultipartInput::with_join`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PartsJoin {
+    /// The parts are concatenated, in order
+    #[default]
+    Concat,
+    /// The parts are concatenated, with the given separator between each two parts
+    Separator(Vec<u8>),
+    /// Each part is prefixed with its length, as a little-endian `u32`
+    LengthPrefixedLe32,
+    /// Each part is prefixed with its length, as a big-endian `u32`
+    LengthPrefixedBe32,
+}
+
+impl PartsJoin {
+    /// Join `parts` according to this rule
+    pub fn join<'a, It>(&self, parts: It) -> Vec<u8>
+    where
+        It: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut joined = vec![];
+        self.join_into(parts, &mut joined);
+        joined
+    }
+
+    /// Join `parts` according to this rule, appending them to `joined`
+    pub fn join_into<'a, It>(&self, parts: It, joined: &mut Vec<u8>)
+    where
+        It: IntoIterator<Item = &'a [u8]>,
+    {
+        for (i, part) in parts.into_iter().enumerate() {
+            match self {
+                Self::Concat => {}
+                Self::Separator(separator) => {
+                    if i > 0 {
+                        joined.extend_from_slice(separator);
+                    }
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                Self::LengthPrefixedLe32 => {
+                    joined.extend_from_slice(&(part.len() as u32).to_le_bytes());
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                Self::LengthPrefixedBe32 => {
+                    joined.extend_from_slice(&(part.len() as u32).to_be_bytes());
+                }
+            }
+            joined.extend_from_slice(part);
+        }
+    }
+}
 
 /// An input composed of multiple parts. Use in situations where subcomponents are not necessarily
 /// related, or represent distinct parts of the input.
@@ -20,6 +77,8 @@ use crate::{corpus::CorpusId, inputs::Input};
 pub struct MultipartInput<I> {
     parts: Vec<I>,
     names: Vec<String>,
+    #[serde(default)]
+    join: PartsJoin,
 }
 
 impl<I> Default for MultipartInput<I> {
@@ -35,9 +94,23 @@ impl<I> MultipartInput<I> {
         Self {
             parts: Vec::new(),
             names: Vec::new(),
+            join: PartsJoin::Concat,
         }
     }
 
+    /// Join the parts according to `join` for [`HasTargetBytes`], instead of concatenating them
+    #[must_use]
+    pub fn with_join(mut self, join: PartsJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// How the parts are joined for [`HasTargetBytes`]
+    #[must_use]
+    pub fn join(&self) -> &PartsJoin {
+        &self.join
+    }
+
     fn idxs_to_skips(idxs: &mut [usize]) {
         for following in (1..idxs.len()).rev() {
             let first = idxs[following - 1];
@@ -129,6 +202,15 @@ impl<I> MultipartInput<I> {
         self.names.push(name);
     }
 
+    /// Removes the part at `idx`, returning its name and the part.
+    pub fn remove_part(&mut self, idx: usize) -> Option<(String, I)> {
+        if idx < self.parts.len() {
+            Some((self.names.remove(idx), self.parts.remove(idx)))
+        } else {
+            None
+        }
+    }
+
     /// Iterate over the parts of this input; no order is specified.
     pub fn iter(&self) -> impl Iterator<Item = (&str, &I)> {
         self.names.iter().map(String::as_ref).zip(self.parts())
@@ -163,3 +245,81 @@ where
             .join(",")
     }
 }
+
+impl<I> HasTargetBytes for MultipartInput<I>
+where
+    I: HasTargetBytes,
+{
+    /// The parts, joined according to [`MultipartInput::join`]
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        if let ([part], PartsJoin::Concat | PartsJoin::Separator(_)) =
+            (self.parts.as_slice(), &self.join)
+        {
+            // Nothing to join, hand out the part's bytes without copying them
+            return part.target_bytes();
+        }
+        let parts: Vec<OwnedSlice<u8>> = self
+            .parts
+            .iter()
+            .map(HasTargetBytes::target_bytes)
+            .collect();
+        OwnedSlice::from(self.join.join(parts.iter().map(|part| &**part)))
+    }
+
+    fn target_bytes_into<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        let parts: Vec<OwnedSlice<u8>> = self
+            .parts
+            .iter()
+            .map(HasTargetBytes::target_bytes)
+            .collect();
+        buffer.clear();
+        self.join
+            .join_into(parts.iter().map(|part| &**part), buffer);
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inputs::{BytesInput, HasTargetBytes, MultipartInput, PartsJoin};
+
+    #[test]
+    fn test_multipart_target_bytes() {
+        let input = MultipartInput::from([
+            ("header", BytesInput::new(b"GET".to_vec())),
+            ("body", BytesInput::new(b"hi".to_vec())),
+        ]);
+        assert_eq!(&*input.target_bytes(), b"GEThi");
+
+        let input = input.with_join(PartsJoin::Separator(b"\r\n".to_vec()));
+        assert_eq!(&*input.target_bytes(), b"GET\r\nhi");
+
+        let mut input = input.with_join(PartsJoin::LengthPrefixedBe32);
+        assert_eq!(&*input.target_bytes(), b"\0\0\0\x03GET\0\0\0\x02hi");
+
+        assert_eq!(input.remove_part(0).unwrap().0, "header");
+        assert_eq!(input.names(), ["body"]);
+        assert!(input.remove_part(1).is_none());
+        assert_eq!(&*input.target_bytes(), b"\0\0\0\x02hi");
+
+        let input = input.with_join(PartsJoin::Concat);
+        assert_eq!(&*input.target_bytes(), b"hi");
+    }
+
+    #[test]
+    fn test_multipart_target_bytes_into() {
+        let input = MultipartInput::from([
+            ("header", BytesInput::new(b"GET".to_vec())),
+            ("body", BytesInput::new(b"hi".to_vec())),
+        ])
+        .with_join(PartsJoin::Separator(b"\r\n".to_vec()));
+        let mut buffer = b"stale".to_vec();
+        assert_eq!(input.target_bytes_into(&mut buffer), b"GET\r\nhi");
+        assert_eq!(buffer, b"GET\r\nhi");
+
+        // Bytes inputs are returned as they are, without touching the buffer
+        let bytes = BytesInput::new(b"direct".to_vec());
+        assert_eq!(bytes.target_bytes_into(&mut buffer), b"direct");
+        assert_eq!(buffer, b"GET\r\nhi");
+    }
+}
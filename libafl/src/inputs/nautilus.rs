@@ -1,22 +1,29 @@
 //! Input for the [`Nautilus`](https://github.com/RUB-SysSec/nautilus) grammar fuzzer methods
 //!
-//!
+//! Next to the binary serialization of the tree, inputs can be stored as their unparsed source,
+//! with [`NautilusInput::to_source_file`], to be read, or edited, by hand.
+//! [`NautilusInput::from_source_file`] parses them back into a tree of the grammar.
 use alloc::{rc::Rc, string::String, vec::Vec};
 use core::cell::RefCell;
-use std::hash::{Hash, Hasher};
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
 
-use libafl_bolts::HasLen;
+use libafl_bolts::{fs::write_file_atomic, HasLen};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     common::nautilus::grammartec::{
         newtypes::NodeId,
+        parser::parse_from_nt,
         rule::RuleIdOrCustom,
         tree::{Tree, TreeLike},
     },
     corpus::CorpusId,
     generators::nautilus::NautilusContext,
-    inputs::{BytesInput, Input, InputConverter},
+    inputs::{BytesInput, HasMutatorBytes, Input, InputConverter},
     Error,
 };
 
@@ -84,6 +91,32 @@ impl NautilusInput {
         self.tree.unparse(NodeId::from(0), &context.ctx, bytes);
     }
 
+    /// Parse a `Nautilus` input from the bytes it unparses to, see [`NautilusInput::unparse`].
+    /// Fails if the grammar does not derive `bytes`, or only with script rules.
+    pub fn parse(context: &NautilusContext, bytes: &[u8]) -> Result<Self, Error> {
+        parse_from_nt(&context.ctx, context.ctx.nt_id("START"), bytes)
+            .map(Self::new)
+            .ok_or_else(|| Error::illegal_argument("The bytes are not derived by the grammar"))
+    }
+
+    /// Write the unparsed input to the file at `path`, as a human-readable alternative to [`Input::to_file`]
+    pub fn to_source_file<P>(&self, context: &NautilusContext, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut bytes = vec![];
+        self.unparse(context, &mut bytes);
+        write_file_atomic(path, &bytes)
+    }
+
+    /// Read an input from the source file at `path`, written by [`NautilusInput::to_source_file`] or by hand
+    pub fn from_source_file<P>(context: &NautilusContext, path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse(context, &fs::read(path)?)
+    }
+
     /// Get the tree representation of this input
     #[must_use]
     pub fn tree(&self) -> &Tree {
@@ -137,3 +170,80 @@ impl<'a> InputConverter for NautilusToBytesInputConverter<'a> {
         Ok(BytesInput::new(bytes))
     }
 }
+
+/// `InputConverter` to convert from `BytesInput` to `NautilusInput`, parsing the bytes with the grammar
+#[derive(Debug)]
+pub struct BytesToNautilusInputConverter<'a> {
+    ctx: &'a NautilusContext,
+}
+
+impl<'a> BytesToNautilusInputConverter<'a> {
+    #[must_use]
+    /// Create a new `BytesToNautilusInputConverter` from a context
+    pub fn new(ctx: &'a NautilusContext) -> Self {
+        Self { ctx }
+    }
+}
+
+impl InputConverter for BytesToNautilusInputConverter<'_> {
+    type From = BytesInput;
+    type To = NautilusInput;
+
+    fn convert(&mut self, input: Self::From) -> Result<Self::To, Error> {
+        NautilusInput::parse(self.ctx, input.bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use libafl_bolts::{rands::StdRand, HasLen};
+
+    use crate::{
+        generators::{NautilusContext, NautilusGenerator},
+        inputs::NautilusInput,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_nautilus_source_file() {
+        let context = NautilusContext::new(
+            15,
+            &[
+                vec!["EXPR".into(), "{EXPR}+{EXPR}".into()],
+                vec!["EXPR".into(), "({EXPR})".into()],
+                vec!["EXPR".into(), "{NUM}".into()],
+                vec!["NUM".into(), "1".into()],
+                vec!["NUM".into(), "42".into()],
+            ],
+        );
+        let generator = NautilusGenerator::new(&context);
+        let mut rand = StdRand::with_seed(0);
+        let path = env::temp_dir().join(format!("libafl_nautilus_source_{}", process::id()));
+
+        for _ in 0..16 {
+            let mut input = NautilusInput::empty();
+            let start = generator.nonterminal("START");
+            generator.generate_from_nonterminal(&mut rand, &mut input, start, 15);
+            input.to_source_file(&context, &path).unwrap();
+            let mut source = vec![];
+            input.unparse(&context, &mut source);
+            assert_eq!(fs::read(&path).unwrap(), source);
+
+            let parsed = NautilusInput::from_source_file(&context, &path).unwrap();
+            let mut reparsed = vec![];
+            parsed.unparse(&context, &mut reparsed);
+            assert_eq!(reparsed, source);
+        }
+
+        // Edited by hand
+        fs::write(&path, b"(1+42)+1").unwrap();
+        let edited = NautilusInput::from_source_file(&context, &path).unwrap();
+        assert!(edited.len() > 1);
+        fs::write(&path, b"1+").unwrap();
+        assert!(NautilusInput::from_source_file(&context, &path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
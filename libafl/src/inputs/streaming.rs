@@ -0,0 +1,290 @@
+//! The [`StreamingInput`] keeps huge payloads, such as firmware images or videos, on disk,
+//! and only holds the mutated regions in memory.
+//!
+//! The input is a piece table over its backing file: each piece is either a range of the file,
+//! or bytes written by a mutation. Serializing the input, for example to the corpus,
+//! only stores the path and the pieces, and the executor receives the content streamed,
+//! with [`StreamingInput::write_to`], or as a file, with [`StreamingInput::materialize`].
+//!
+//! On purpose, the input does not implement [`crate::inputs::HasTargetBytes`], as that would read the whole content
+//! into memory. The [`crate::executors::CommandExecutor`] streams it to the target's stdin, or to its input file.
+
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use ahash::RandomState;
+use libafl_bolts::{fs::InputFile, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{corpus::CorpusId, inputs::Input, Error};
+
+/// The size of the chunks the content is streamed in
+const STREAMING_CHUNK_SIZE: usize = 1 << 20;
+
+/// A piece of a [`StreamingInput`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum Piece {
+    /// `len` bytes of the backing file, starting at `offset`
+    File { offset: u64, len: u64 },
+    /// Bytes in memory
+    Data(Vec<u8>),
+}
+
+impl Piece {
+    fn len(&self) -> u64 {
+        match self {
+            Self::File { len, .. } => *len,
+            Self::Data(data) => data.len() as u64,
+        }
+    }
+
+    /// The part of this piece from `start` to `end`, relative to the start of the piece
+    fn slice(&self, start: u64, end: u64) -> Self {
+        match self {
+            Self::File { offset, .. } => Self::File {
+                offset: offset + start,
+                len: end - start,
+            },
+            #[allow(clippy::cast_possible_truncation)]
+            Self::Data(data) => Self::Data(data[start as usize..end as usize].to_vec()),
+        }
+    }
+}
+
+/// An input backed by a file on disk, with the mutated regions in memory, see the [module documentation](self)
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StreamingInput {
+    path: PathBuf,
+    pieces: Vec<Piece>,
+}
+
+impl Input for StreamingInput {
+    /// Generate a name for this input, from the backing file and the mutated regions
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        format!(
+            "{:016x}",
+            RandomState::with_seeds(0, 0, 0, 0).hash_one(self)
+        )
+    }
+}
+
+impl HasLen for StreamingInput {
+    #[allow(clippy::cast_possible_truncation)]
+    fn len(&self) -> usize {
+        self.stream_len() as usize
+    }
+}
+
+impl StreamingInput {
+    /// Creates a new [`StreamingInput`] backed by the file at `path`.
+    /// The file must not change while inputs refer to it.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let len = path.metadata()?.len();
+        let pieces = if len == 0 {
+            vec![]
+        } else {
+            vec![Piece::File { offset: 0, len }]
+        };
+        Ok(Self { path, pieces })
+    }
+
+    /// The backing file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The length of the content, in bytes
+    #[must_use]
+    pub fn stream_len(&self) -> u64 {
+        self.pieces.iter().map(Piece::len).sum()
+    }
+
+    /// The amount of mutated bytes held in memory
+    #[must_use]
+    pub fn patched_len(&self) -> usize {
+        self.pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::File { .. } => 0,
+                Piece::Data(data) => data.len(),
+            })
+            .sum()
+    }
+
+    /// Streams the content to `writer`, in chunks
+    pub fn write_to<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        self.write_range_to(0..self.stream_len(), writer)
+    }
+
+    /// Writes the content to a file at `path`, for targets reading their input from a file
+    pub fn materialize<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Streams the content into `file`, truncating it to the length of the content,
+    /// and rewinds it for the target to read
+    pub fn write_to_file(&self, file: &mut InputFile) -> Result<(), Error> {
+        file.rewind()?;
+        let mut writer = BufWriter::new(&mut file.file);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        file.file.set_len(self.stream_len())?;
+        file.rewind()
+    }
+
+    /// Reads the bytes in `range` of the content, clamped to its length
+    pub fn read_range(&self, range: Range<u64>) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![];
+        self.write_range_to(range, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Replaces the bytes in `range` of the content with `data`, which may have another length.
+    /// The backing file stays untouched.
+    pub fn splice(&mut self, range: Range<u64>, data: &[u8]) {
+        let len = self.stream_len();
+        let (start, end) = (
+            range.start.min(len),
+            range.end.clamp(range.start.min(len), len),
+        );
+
+        let mut pieces = Vec::with_capacity(self.pieces.len() + 2);
+        let mut inserted = false;
+        let mut pos = 0;
+        for piece in self.pieces.drain(..) {
+            let piece_len = piece.len();
+            let (piece_start, piece_end) = (pos, pos + piece_len);
+            pos = piece_end;
+            // Keep what comes before the range, and after it, with `data` in between
+            if piece_start < start {
+                pieces.push(piece.slice(0, piece_end.min(start) - piece_start));
+            }
+            if piece_end > end {
+                if !inserted {
+                    Self::push_data(&mut pieces, data);
+                    inserted = true;
+                }
+                pieces.push(piece.slice(piece_start.max(end) - piece_start, piece_len));
+            }
+        }
+        if !inserted {
+            Self::push_data(&mut pieces, data);
+        }
+        self.pieces = pieces;
+    }
+
+    /// Pushes `data`, merged into the previous piece if that is in memory too
+    fn push_data(pieces: &mut Vec<Piece>, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        if let Some(Piece::Data(previous)) = pieces.last_mut() {
+            previous.extend_from_slice(data);
+        } else {
+            pieces.push(Piece::Data(data.to_vec()));
+        }
+    }
+
+    fn write_range_to<W>(&self, range: Range<u64>, writer: &mut W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut file = None;
+        let mut buf = vec![];
+        let mut pos = 0;
+        for piece in &self.pieces {
+            let (piece_start, piece_end) = (pos, pos + piece.len());
+            pos = piece_end;
+            let (start, end) = (range.start.max(piece_start), range.end.min(piece_end));
+            if start >= end {
+                continue;
+            }
+            match piece.slice(start - piece_start, end - piece_start) {
+                Piece::Data(data) => writer.write_all(&data)?,
+                Piece::File { offset, len } => {
+                    let file = match &mut file {
+                        Some(file) => file,
+                        None => file.insert(File::open(&self.path)?),
+                    };
+                    file.seek(SeekFrom::Start(offset))?;
+                    let mut remaining = len;
+                    while remaining > 0 {
+                        #[allow(clippy::cast_possible_truncation)]
+                        let chunk = remaining.min(STREAMING_CHUNK_SIZE as u64) as usize;
+                        buf.resize(chunk, 0);
+                        file.read_exact(&mut buf)?;
+                        writer.write_all(&buf)?;
+                        remaining -= chunk as u64;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use libafl_bolts::fs::InputFile;
+
+    use crate::inputs::streaming::StreamingInput;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_streaming_input() {
+        let path = env::temp_dir().join(format!("libafl_streaming_test_{}", process::id()));
+        fs::write(&path, b"0123456789").unwrap();
+
+        let mut input = StreamingInput::open(&path).unwrap();
+        assert_eq!(input.stream_len(), 10);
+        assert_eq!(input.read_range(2..5).unwrap(), b"234");
+
+        input.splice(2..5, b"abcdef");
+        input.splice(0..0, b"<");
+        input.splice(14..14, b">");
+        input.splice(9..12, &[]);
+        let mut bytes = vec![];
+        input.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, b"<01abcdef89>");
+        assert_eq!(input.read_range(3..7).unwrap(), b"abcd");
+        assert_eq!(input.patched_len(), 8);
+
+        let out = path.with_extension("out");
+        input.materialize(&out).unwrap();
+        assert_eq!(fs::read(&out).unwrap(), b"<01abcdef89>");
+        // The backing file is untouched
+        assert_eq!(fs::read(&path).unwrap(), b"0123456789");
+
+        // Shorter content truncates the input file
+        let mut file = InputFile::create(path.with_extension("cur")).unwrap();
+        file.write_buf(&[0; 32]).unwrap();
+        input.write_to_file(&mut file).unwrap();
+        assert_eq!(fs::read(&file.path).unwrap(), b"<01abcdef89>");
+        drop(file);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&out).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+//! An input modeling a sequence of typed calls, for syzkaller-style fuzzing of kernels and APIs.
+//!
+//! Each [`Call`] has an id and typed arguments, and an argument may refer to the result of an earlier call,
+//! such as a file descriptor returned by `open`. The calls are described by a [`CallTable`],
+//! which the mutators in [`crate::mutators::syscalls`] use to generate and mutate calls,
+//! and a [`CallSerializer`] renders the sequence to the bytes the harness decodes and executes.
+
+use alloc::{string::String, vec::Vec};
+use core::hash::{BuildHasher, Hasher};
+
+use ahash::RandomState;
+use hashbrown::HashMap;
+use libafl_bolts::{ownedref::OwnedSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasTargetBytes, Input},
+};
+
+/// The type of an argument of a call, see [`CallDescription`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArgKind {
+    /// An integer of `bits` bits, taken from `pool` (flags, magic numbers, sizes) or at random
+    Int {
+        /// The width of the integer, up to 64 bits
+        bits: u32,
+        /// Values worth trying, may be empty
+        pool: Vec<u64>,
+    },
+    /// A buffer of up to `max_len` bytes
+    Bytes {
+        /// The maximum length of the buffer
+        max_len: usize,
+    },
+    /// The result of an earlier call producing the resource `kind`, see [`CallDescription::produces`]
+    Resource {
+        /// The kind of the resource, such as `fd`
+        kind: String,
+    },
+}
+
+/// The description of a call, its arguments, and the resource it returns, if any
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallDescription {
+    /// The id of the call, as serialized for the harness
+    pub id: u32,
+    /// The name of the call, for humans
+    pub name: String,
+    /// The arguments of the call
+    pub args: Vec<ArgKind>,
+    /// The kind of resource the call returns, if later calls may use its result
+    pub produces: Option<String>,
+}
+
+impl CallDescription {
+    /// Creates a new [`CallDescription`], without arguments
+    #[must_use]
+    pub fn new(id: u32, name: &str) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            args: vec![],
+            produces: None,
+        }
+    }
+
+    /// Adds an argument
+    #[must_use]
+    pub fn arg(mut self, arg: ArgKind) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    /// Sets the kind of resource the call returns
+    #[must_use]
+    pub fn produces(mut self, kind: &str) -> Self {
+        self.produces = Some(kind.into());
+        self
+    }
+}
+
+/// All calls the fuzzer may generate, by id
+#[derive(Clone, Debug, Default)]
+pub struct CallTable {
+    calls: Vec<CallDescription>,
+    ids: HashMap<u32, usize>,
+}
+
+impl CallTable {
+    /// Creates a new, empty [`CallTable`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a call, replacing the previous call with the same id
+    #[must_use]
+    pub fn with_call(mut self, call: CallDescription) -> Self {
+        if let Some(&idx) = self.ids.get(&call.id) {
+            self.calls[idx] = call;
+        } else {
+            self.ids.insert(call.id, self.calls.len());
+            self.calls.push(call);
+        }
+        self
+    }
+
+    /// All calls
+    #[must_use]
+    pub fn calls(&self) -> &[CallDescription] {
+        &self.calls
+    }
+
+    /// The call with the given `id`
+    #[must_use]
+    pub fn get(&self, id: u32) -> Option<&CallDescription> {
+        self.ids.get(&id).map(|&idx| &self.calls[idx])
+    }
+}
+
+/// The value of an argument of a [`Call`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CallArg {
+    /// An integer
+    Int(u64),
+    /// A buffer
+    Bytes(Vec<u8>),
+    /// The result of the call at this index of the sequence, which always comes before the call using it
+    Result(usize),
+}
+
+/// A single call of a [`SyscallSequenceInput`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Call {
+    /// The id of the call, see [`CallDescription::id`]
+    pub id: u32,
+    /// The arguments of the call
+    pub args: Vec<CallArg>,
+}
+
+impl Call {
+    /// Creates a new [`Call`]
+    #[must_use]
+    pub fn new(id: u32, args: Vec<CallArg>) -> Self {
+        Self { id, args }
+    }
+
+    /// The indices of the calls whose results this call uses
+    pub fn dependencies(&self) -> impl Iterator<Item = usize> + '_ {
+        self.args.iter().filter_map(|arg| match arg {
+            CallArg::Result(idx) => Some(*idx),
+            _ => None,
+        })
+    }
+}
+
+/// Renders the calls of a [`SyscallSequenceInput`] to the bytes the harness receives
+pub trait CallSerializer {
+    /// Appends the serialized `calls` to `bytes`
+    fn serialize(&mut self, calls: &[Call], bytes: &mut Vec<u8>);
+}
+
+impl<F> CallSerializer for F
+where
+    F: FnMut(&[Call], &mut Vec<u8>),
+{
+    fn serialize(&mut self, calls: &[Call], bytes: &mut Vec<u8>) {
+        self(calls, bytes);
+    }
+}
+
+/// The default [`CallSerializer`], used for the target bytes of a [`SyscallSequenceInput`].
+///
+/// All integers are little-endian. Each call is written as its `u32` id, followed by its `u32` number of arguments.
+/// Each argument is a tag byte, followed by its value: `0` for an `u64` integer, `1` for a `u32` length and
+/// that many bytes, and `2` for the `u32` index of the call whose result to pass.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BinaryCallSerializer;
+
+impl CallSerializer for BinaryCallSerializer {
+    #[allow(clippy::cast_possible_truncation)]
+    fn serialize(&mut self, calls: &[Call], bytes: &mut Vec<u8>) {
+        for call in calls {
+            bytes.extend_from_slice(&call.id.to_le_bytes());
+            bytes.extend_from_slice(&(call.args.len() as u32).to_le_bytes());
+            for arg in &call.args {
+                match arg {
+                    CallArg::Int(value) => {
+                        bytes.push(0);
+                        bytes.extend_from_slice(&value.to_le_bytes());
+                    }
+                    CallArg::Bytes(buf) => {
+                        bytes.push(1);
+                        bytes.extend_from_slice(&(buf.len() as u32).to_le_bytes());
+                        bytes.extend_from_slice(buf);
+                    }
+                    CallArg::Result(idx) => {
+                        bytes.push(2);
+                        bytes.extend_from_slice(&(*idx as u32).to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An input made of a sequence of calls, where calls may use the results of earlier calls
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SyscallSequenceInput {
+    calls: Vec<Call>,
+}
+
+impl Input for SyscallSequenceInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(&self.serialize_with(&mut BinaryCallSerializer));
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for SyscallSequenceInput {
+    #[inline]
+    fn len(&self) -> usize {
+        self.calls.len()
+    }
+}
+
+impl HasTargetBytes for SyscallSequenceInput {
+    /// The calls, serialized by the [`BinaryCallSerializer`].
+    /// To use another format, render the input with [`SyscallSequenceInput::serialize_with`] in the harness.
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(self.serialize_with(&mut BinaryCallSerializer))
+    }
+
+    fn target_bytes_into<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        buffer.clear();
+        BinaryCallSerializer.serialize(&self.calls, buffer);
+        buffer
+    }
+}
+
+impl SyscallSequenceInput {
+    /// Creates a new [`SyscallSequenceInput`] from the given calls
+    #[must_use]
+    pub fn new(calls: Vec<Call>) -> Self {
+        Self { calls }
+    }
+
+    /// The calls of this input
+    #[must_use]
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// The calls of this input, mutable.
+    /// Keep each [`CallArg::Result`] pointing to an earlier call, see [`SyscallSequenceInput::is_valid`].
+    #[must_use]
+    pub fn calls_mut(&mut self) -> &mut Vec<Call> {
+        &mut self.calls
+    }
+
+    /// Serialize the calls with the given `serializer`
+    pub fn serialize_with<S>(&self, serializer: &mut S) -> Vec<u8>
+    where
+        S: CallSerializer,
+    {
+        let mut bytes = vec![];
+        serializer.serialize(&self.calls, &mut bytes);
+        bytes
+    }
+
+    /// If each call only uses the results of earlier calls
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.calls
+            .iter()
+            .enumerate()
+            .all(|(idx, call)| call.dependencies().all(|dep| dep < idx))
+    }
+
+    /// If a later call uses the result of the call at `idx`
+    #[must_use]
+    pub fn has_dependents(&self, idx: usize) -> bool {
+        self.calls[idx + 1..]
+            .iter()
+            .any(|call| call.dependencies().any(|dep| dep == idx))
+    }
+
+    /// Inserts `call` at `idx`, updating the references to the calls after it.
+    /// The arguments of `call` itself are not updated, and must only refer to calls before `idx`.
+    pub fn insert_call(&mut self, idx: usize, call: Call) {
+        self.remap_results(|dep| if dep >= idx { dep + 1 } else { dep });
+        self.calls.insert(idx, call);
+    }
+
+    /// Removes the call at `idx`, updating the references to the calls after it.
+    /// Returns `None` if a later call uses its result.
+    pub fn remove_call(&mut self, idx: usize) -> Option<Call> {
+        if idx >= self.calls.len() || self.has_dependents(idx) {
+            return None;
+        }
+        let call = self.calls.remove(idx);
+        self.remap_results(|dep| if dep > idx { dep - 1 } else { dep });
+        Some(call)
+    }
+
+    /// Moves the call at `from` to `to`, updating all references.
+    /// Returns `false`, and leaves the input as is, if a call would end up before a call whose result it uses.
+    pub fn move_call(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.calls.len() || to >= self.calls.len() {
+            return false;
+        }
+        let remap = |dep: usize| {
+            if dep == from {
+                to
+            } else if from < to && dep > from && dep <= to {
+                dep - 1
+            } else if to < from && dep >= to && dep < from {
+                dep + 1
+            } else {
+                dep
+            }
+        };
+        let call = &self.calls[from];
+        // The moved call must stay after its dependencies, and before its dependents
+        if call.dependencies().any(|dep| remap(dep) >= to)
+            || self
+                .calls
+                .iter()
+                .enumerate()
+                .any(|(idx, other)| other.dependencies().any(|dep| dep == from) && remap(idx) <= to)
+        {
+            return false;
+        }
+        let call = self.calls.remove(from);
+        self.calls.insert(to, call);
+        self.remap_results(remap);
+        true
+    }
+
+    fn remap_results<F>(&mut self, mut remap: F)
+    where
+        F: FnMut(usize) -> usize,
+    {
+        for call in &mut self.calls {
+            for arg in &mut call.args {
+                if let CallArg::Result(dep) = arg {
+                    *dep = remap(*dep);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::inputs::{
+        syscalls::{BinaryCallSerializer, Call, CallArg, SyscallSequenceInput},
+        HasTargetBytes,
+    };
+
+    #[test]
+    fn test_syscall_sequence_input() {
+        // open, write(fd), close(fd)
+        let mut input = SyscallSequenceInput::new(vec![
+            Call::new(0, vec![CallArg::Bytes(b"/tmp".to_vec())]),
+            Call::new(1, vec![CallArg::Result(0), CallArg::Int(4)]),
+            Call::new(2, vec![CallArg::Result(0)]),
+        ]);
+        assert!(input.is_valid());
+        assert!(input.remove_call(0).is_none());
+        assert!(!input.move_call(0, 2));
+        assert!(!input.move_call(2, 0));
+
+        assert!(input.move_call(2, 1));
+        assert_eq!(input.calls()[1].id, 2);
+        assert!(input.is_valid());
+
+        input.insert_call(0, Call::new(3, vec![]));
+        assert_eq!(input.calls()[3].args[0], CallArg::Result(1));
+        assert_eq!(input.remove_call(0).unwrap().id, 3);
+        assert_eq!(input.calls()[2].args[0], CallArg::Result(0));
+        assert!(input.is_valid());
+
+        let bytes = input.serialize_with(&mut BinaryCallSerializer);
+        assert_eq!(&*input.target_bytes(), &bytes[..]);
+        assert_eq!(&bytes[..9], &[0, 0, 0, 0, 1, 0, 0, 0, 1]);
+        let mut ids = |calls: &[Call], bytes: &mut Vec<u8>| {
+            bytes.extend(calls.iter().map(|call| call.id as u8));
+        };
+        assert_eq!(input.serialize_with(&mut ids), vec![0, 2, 1]);
+    }
+}
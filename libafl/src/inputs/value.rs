@@ -0,0 +1,256 @@
+//! An input made of typed primitives, for harnesses taking structured parameters instead of a byte buffer.
+//!
+//! Each [`Value`] knows its type and its valid range, so the mutators in [`crate::mutators::value`]
+//! produce boundary values, bit patterns, and adjacent floats of the right type, and never leave the range.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    hash::{BuildHasher, Hasher},
+    ops::RangeInclusive,
+};
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasTargetBytes, Input},
+};
+
+/// A typed primitive of a [`ValueInput`]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    /// A boolean
+    Bool(bool),
+    /// A signed integer, within `min..=max`
+    Int {
+        /// The value
+        value: i64,
+        /// The smallest valid value
+        min: i64,
+        /// The largest valid value
+        max: i64,
+    },
+    /// An unsigned integer, within `min..=max`
+    UInt {
+        /// The value
+        value: u64,
+        /// The smallest valid value
+        min: u64,
+        /// The largest valid value
+        max: u64,
+    },
+    /// A single precision float
+    F32(f32),
+    /// A double precision float
+    F64(f64),
+    /// The variant of an enum with `variants` variants
+    Enum {
+        /// The index of the variant
+        variant: u32,
+        /// The number of variants
+        variants: u32,
+    },
+}
+
+impl Value {
+    /// A signed integer, which will stay within `range`
+    #[must_use]
+    pub fn int(value: i64, range: RangeInclusive<i64>) -> Self {
+        let (min, max) = range.into_inner();
+        Self::Int {
+            value: value.clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    /// An unsigned integer, which will stay within `range`
+    #[must_use]
+    pub fn uint(value: u64, range: RangeInclusive<u64>) -> Self {
+        let (min, max) = range.into_inner();
+        Self::UInt {
+            value: value.clamp(min, max),
+            min,
+            max,
+        }
+    }
+
+    /// The variant `variant` of an enum with `variants` variants
+    #[must_use]
+    pub fn enumeration(variant: u32, variants: u32) -> Self {
+        debug_assert!(variant < variants);
+        Self::Enum { variant, variants }
+    }
+
+    /// The value, if it is a [`Value::Bool`]
+    #[must_use]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a [`Value::Int`]
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a [`Value::UInt`]
+    #[must_use]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::UInt { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a [`Value::F32`]
+    #[must_use]
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::F32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a [`Value::F64`]
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::F64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The index of the variant, if it is a [`Value::Enum`]
+    #[must_use]
+    pub fn as_variant(&self) -> Option<u32> {
+        match self {
+            Self::Enum { variant, .. } => Some(*variant),
+            _ => None,
+        }
+    }
+
+    /// Appends the little-endian bytes of the value to `bytes`: a single byte for booleans,
+    /// eight bytes for integers and doubles, and four bytes for singles and enum variants.
+    pub fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Self::Bool(value) => bytes.push((*value).into()),
+            Self::Int { value, .. } => bytes.extend_from_slice(&value.to_le_bytes()),
+            Self::UInt { value, .. } => bytes.extend_from_slice(&value.to_le_bytes()),
+            Self::F32(value) => bytes.extend_from_slice(&value.to_le_bytes()),
+            Self::F64(value) => bytes.extend_from_slice(&value.to_le_bytes()),
+            Self::Enum { variant, .. } => bytes.extend_from_slice(&variant.to_le_bytes()),
+        }
+    }
+}
+
+/// An input holding a list of typed primitives
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValueInput {
+    values: Vec<Value>,
+}
+
+impl Input for ValueInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(&self.to_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for ValueInput {
+    #[inline]
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+}
+
+impl HasTargetBytes for ValueInput {
+    /// The values, one after the other, see [`Value::write_bytes`]
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(self.to_bytes())
+    }
+
+    fn target_bytes_into<'a>(&'a self, buffer: &'a mut Vec<u8>) -> &'a [u8] {
+        buffer.clear();
+        for value in &self.values {
+            value.write_bytes(buffer);
+        }
+        buffer
+    }
+}
+
+impl From<Vec<Value>> for ValueInput {
+    fn from(values: Vec<Value>) -> Self {
+        Self::new(values)
+    }
+}
+
+impl ValueInput {
+    /// Creates a new [`ValueInput`] from the given values
+    #[must_use]
+    pub fn new(values: Vec<Value>) -> Self {
+        Self { values }
+    }
+
+    /// The values of this input
+    #[must_use]
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// The values of this input, mutable
+    #[must_use]
+    pub fn values_mut(&mut self) -> &mut [Value] {
+        &mut self.values
+    }
+
+    /// The value at `idx`
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<&Value> {
+        self.values.get(idx)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for value in &self.values {
+            value.write_bytes(&mut bytes);
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inputs::{
+        value::{Value, ValueInput},
+        HasTargetBytes,
+    };
+
+    #[test]
+    fn test_value_input() {
+        let input = ValueInput::new(vec![
+            Value::Bool(true),
+            Value::int(-200, -100..=100),
+            Value::F32(1.5),
+            Value::enumeration(2, 3),
+        ]);
+        assert_eq!(input.get(1).unwrap().as_i64(), Some(-100));
+        assert_eq!(input.get(3).unwrap().as_variant(), Some(2));
+        assert!(input.get(0).unwrap().as_u64().is_none());
+
+        let bytes = input.target_bytes();
+        assert_eq!(bytes.len(), 1 + 8 + 4 + 4);
+        assert_eq!(&bytes[1..9], &(-100_i64).to_le_bytes());
+        assert_eq!(&bytes[9..13], &1.5_f32.to_le_bytes());
+    }
+}
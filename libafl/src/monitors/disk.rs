@@ -1,15 +1,19 @@
 //! Monitors that wrap a base one and log on disk
 
-use alloc::{string::String, vec::Vec};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 use core::time::Duration;
 use std::{
-    fs::{File, OpenOptions},
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
     io::Write,
     path::PathBuf,
 };
 
 use libafl_bolts::{current_time, format_duration_hms, ClientId};
-use serde_json::json;
+use serde_json::{json, Map, Value};
 
 use crate::monitors::{ClientStats, Monitor, NopMonitor};
 
@@ -230,3 +234,316 @@ where
         self.base.display(event_msg, sender_id);
     }
 }
+
+/// The version of the records written by the [`OnDiskRotatingJSONMonitor`].
+/// It is bumped whenever the layout of a record changes incompatibly.
+pub const ROTATING_JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Wraps a base monitor and appends one JSON object per update interval to a JSON lines file,
+/// including a breakdown of the stats per client.
+///
+/// Once the file grows larger than the maximum size, or it has been written to for longer than the maximum age,
+/// it is rotated: `stats.jsonl` is renamed to `stats.jsonl.1`, `stats.jsonl.1` to `stats.jsonl.2`, and so on.
+/// Only the newest rotated files are kept.
+/// Each record carries a `schema_version` field, see [`ROTATING_JSON_SCHEMA_VERSION`].
+#[derive(Debug, Clone)]
+pub struct OnDiskRotatingJSONMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    path: PathBuf,
+    /// The size of the current file, once we wrote to it
+    file_size: u64,
+    /// When we first wrote to the current file, if we did
+    file_opened: Option<Duration>,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    max_files: usize,
+    last_update: Duration,
+    update_interval: Duration,
+}
+
+impl<M> OnDiskRotatingJSONMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`OnDiskRotatingJSONMonitor`], writing a record every 60 seconds,
+    /// rotating the file once it grows beyond 64 MiB and keeping 5 rotated files.
+    #[must_use]
+    pub fn new<P>(filename: P, base: M) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let update_interval = Duration::from_secs(60);
+        Self {
+            base,
+            path: filename.into(),
+            file_size: 0,
+            file_opened: None,
+            max_size: Some(64 * 1024 * 1024),
+            max_age: None,
+            max_files: 5,
+            last_update: current_time() - update_interval,
+            update_interval,
+        }
+    }
+
+    /// Write a record at most once per `update_interval`
+    #[must_use]
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.last_update = current_time() - update_interval;
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Rotate the file once it grows larger than `max_size` bytes, or never, if `None`
+    #[must_use]
+    pub fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Rotate the file once it has been written to for longer than `max_age`, or never, if `None`
+    #[must_use]
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Keep at most `max_files` rotated files, deleting older ones.
+    /// With `0`, the file is truncated on rotation.
+    #[must_use]
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// The path of the rotated file with the given index
+    fn rotated_path(&self, idx: usize) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{idx}"));
+        path.into()
+    }
+
+    /// Whether the current file needs to be rotated before writing to it again
+    fn needs_rotation(&self, cur_time: Duration) -> bool {
+        self.file_opened.is_some_and(|file_opened| {
+            self.max_size
+                .is_some_and(|max_size| self.file_size >= max_size)
+                || self
+                    .max_age
+                    .is_some_and(|max_age| cur_time - file_opened >= max_age)
+        })
+    }
+
+    /// Shift all rotated files by one, and move the current file to the first rotated slot
+    fn rotate(&mut self) {
+        self.file_opened = None;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path).expect("Failed to remove the JSON lines file");
+            return;
+        }
+
+        let oldest = self.rotated_path(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(oldest).expect("Failed to remove the oldest rotated JSON lines file");
+        }
+        for idx in (1..self.max_files).rev() {
+            let from = self.rotated_path(idx);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(idx + 1))
+                    .expect("Failed to rotate the JSON lines file");
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1)).expect("Failed to rotate the JSON lines file");
+    }
+
+    /// Append a line to the current file, creating it if needed
+    fn append(&mut self, line: &str, cur_time: Duration) {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .expect("Failed to open the JSON lines file");
+        if self.file_opened.is_none() {
+            self.file_size = file
+                .metadata()
+                .expect("Failed to read the size of the JSON lines file")
+                .len();
+            self.file_opened = Some(cur_time);
+        }
+        file.write_all(line.as_bytes())
+            .expect("Unable to write JSON to file");
+        self.file_size += line.len() as u64;
+    }
+
+    /// The record for the current stats
+    fn record(&mut self, cur_time: Duration) -> Value {
+        let clients: Vec<Value> = self
+            .client_stats_mut()
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, client)| client.enabled)
+            .map(|(id, client)| {
+                let user_stats: Map<String, Value> = client
+                    .user_monitor
+                    .iter()
+                    .map(|(key, val)| (key.to_string(), json!(val.value())))
+                    .collect();
+                json!({
+                    "id": id,
+                    "corpus": client.corpus_size,
+                    "objectives": client.objective_size,
+                    "executions": client.executions,
+                    "exec_sec": client.execs_per_sec(cur_time),
+                    "user_stats": user_stats,
+                })
+            })
+            .collect();
+
+        json!({
+            "schema_version": ROTATING_JSON_SCHEMA_VERSION,
+            "timestamp": cur_time.as_secs(),
+            "run_time": (cur_time - self.start_time()).as_secs(),
+            "clients": self.client_stats_count(),
+            "corpus": self.corpus_size(),
+            "objectives": self.objective_size(),
+            "executions": self.total_execs(),
+            "exec_sec": self.execs_per_sec(),
+            "client_stats": clients,
+        })
+    }
+}
+
+impl<M> Monitor for OnDiskRotatingJSONMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        let cur_time = current_time();
+
+        if cur_time - self.last_update >= self.update_interval {
+            self.last_update = cur_time;
+
+            if self.needs_rotation(cur_time) {
+                self.rotate();
+            }
+
+            let line = format!("{}\n", self.record(cur_time));
+            self.append(&line, cur_time);
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+}
+
+impl OnDiskRotatingJSONMonitor<NopMonitor> {
+    /// Create new [`OnDiskRotatingJSONMonitor`] without a base
+    #[must_use]
+    pub fn nop<P>(filename: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::new(filename, NopMonitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, string::ToString};
+    use core::time::Duration;
+    use std::{env, fs, process};
+
+    use libafl_bolts::ClientId;
+    use serde_json::{json, Value};
+
+    use crate::monitors::{
+        disk::ROTATING_JSON_SCHEMA_VERSION, AggregatorOps, Monitor, OnDiskRotatingJSONMonitor,
+        UserStats, UserStatsValue,
+    };
+
+    #[test]
+    fn test_rotating_json_monitor() {
+        let path = env::temp_dir().join(format!("libafl_rotating_json_{}", process::id()));
+        let rotated = |idx: usize| path.with_extension(idx.to_string());
+        let _ = fs::remove_file(&path);
+        for idx in 1..=3 {
+            let _ = fs::remove_file(rotated(idx));
+        }
+
+        // every record fills the file, so each following one rotates it
+        let mut monitor = OnDiskRotatingJSONMonitor::nop(&path)
+            .with_update_interval(Duration::ZERO)
+            .with_max_size(Some(1))
+            .with_max_files(2);
+        monitor.client_stats_insert(ClientId(1));
+        let client = monitor.client_stats_mut_for(ClientId(1));
+        client.update_corpus_size(3);
+        client.update_user_stats(
+            Cow::Borrowed("cov"),
+            UserStats::new(UserStatsValue::Number(7), AggregatorOps::None),
+        );
+        for _ in 0..4 {
+            monitor.display("test", ClientId(1));
+        }
+
+        assert!(rotated(1).exists());
+        assert!(rotated(2).exists());
+        assert!(!rotated(3).exists());
+
+        let record: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            record["schema_version"],
+            json!(ROTATING_JSON_SCHEMA_VERSION)
+        );
+        assert_eq!(record["corpus"], json!(3));
+        // only the enabled client is reported
+        let clients = record["client_stats"].as_array().unwrap();
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0]["id"], json!(1));
+        assert_eq!(clients[0]["corpus"], json!(3));
+        assert_eq!(
+            clients[0]["user_stats"]["cov"],
+            json!(UserStatsValue::Number(7))
+        );
+
+        // without rotated files, the file gets truncated instead
+        let mut monitor = monitor.with_max_files(0);
+        monitor.display("test", ClientId(1));
+        monitor.display("test", ClientId(1));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+        // clones, as handed out by the launcher, keep appending to the same file
+        let mut monitor = monitor.with_max_size(None);
+        let mut clone = monitor.clone();
+        monitor.display("test", ClientId(1));
+        clone.display("test", ClientId(1));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(rotated(1)).unwrap();
+        fs::remove_file(rotated(2)).unwrap();
+    }
+}
@@ -20,10 +20,25 @@ use alloc::{borrow::Cow, fmt::Debug, string::String, vec::Vec};
 use core::{fmt, fmt::Write, time::Duration};
 
 #[cfg(feature = "std")]
-pub use disk::{OnDiskJSONMonitor, OnDiskTOMLMonitor};
+pub use disk::{OnDiskJSONMonitor, OnDiskRotatingJSONMonitor, OnDiskTOMLMonitor};
+#[cfg(feature = "std")]
+pub mod plot;
 use hashbrown::HashMap;
 use libafl_bolts::{current_time, format_duration_hms, ClientId};
+#[cfg(feature = "std")]
+pub use plot::OnDiskPlotMonitor;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+pub use statsd::{StatsdFlavor, StatsdMonitor};
+#[cfg(feature = "std")]
+pub use web::WebMonitor;
+
+use crate::stages::StageId;
+
+#[cfg(feature = "std")]
+pub mod statsd;
+#[cfg(feature = "std")]
+pub mod web;
 
 #[cfg(feature = "afl_exec_sec")]
 const CLIENT_STATS_TIME_WINDOW_SECS: u64 = 5; // 5 seconds
@@ -886,7 +901,7 @@ pub struct ClientPerfMonitor {
     manager: u64,
 
     /// Current stage index to write the next stage benchmark time
-    curr_stage: u8,
+    curr_stage: usize,
 
     /// Flag to dictate this stage is in use. Used during printing to not print the empty
     /// stages if they are not in use.
@@ -895,9 +910,15 @@ pub struct ClientPerfMonitor {
     /// Clock cycles spent in the the various features of each stage
     stages: Vec<[u64; PerfFeature::Count as usize]>,
 
+    /// The id in its [`crate::stages::StagesTuple`] and the name of each stage, see [`Self::enter_stage`]
+    stage_names: Vec<(StageId, Cow<'static, str>)>,
+
     /// Clock cycles spent in each feedback mechanism of the fuzzer.
     feedbacks: HashMap<String, u64>,
 
+    /// How often each feedback mechanism of the fuzzer found an input interesting.
+    feedback_hits: HashMap<String, u64>,
+
     /// Current time set by `start_timer`
     timer_start: Option<u64>,
 }
@@ -1024,7 +1045,9 @@ impl ClientPerfMonitor {
             curr_stage: 0,
             stages: vec![],
             stages_used: vec![],
+            stage_names: vec![],
             feedbacks: HashMap::new(),
+            feedback_hits: HashMap::new(),
             timer_start: None,
         }
     }
@@ -1046,8 +1069,23 @@ impl ClientPerfMonitor {
         self.set_current_time(monitor.current_time);
         self.update_scheduler(monitor.scheduler);
         self.update_manager(monitor.manager);
-        self.update_stages(&monitor.stages);
+        let curr_stage = self.curr_stage;
+        for (stage_index, features) in monitor.stages.iter().enumerate() {
+            // Stages are matched by their id and name, in case the other monitor saw them in a different order
+            if let Some((id, name)) = monitor.stage_names.get(stage_index) {
+                self.enter_stage(*id, name.clone());
+            } else {
+                self.curr_stage = stage_index;
+            }
+            for (feature_index, time) in features.iter().enumerate() {
+                self.update_feature(feature_index.into(), *time);
+            }
+        }
+        self.curr_stage = curr_stage;
         self.update_feedbacks(&monitor.feedbacks);
+        for (name, hits) in &monitor.feedback_hits {
+            *self.feedback_hits.entry_ref(name.as_str()).or_default() += hits;
+        }
     }
 
     /// Gets the elapsed time since the internal timer started. Resets the timer when
@@ -1123,18 +1161,29 @@ impl ClientPerfMonitor {
             .expect("update_manager overflow");
     }
 
-    /// Update the total stage counter and increment the stage counter for the next stage
-    #[inline]
-    pub fn finish_stage(&mut self) {
-        // Increment the stage to the next index. The check is only done if this were to
-        // be used past the length of the `self.stages` buffer
-        self.curr_stage += 1;
+    /// Attribute the following times to the stage with the given `id` in its [`crate::stages::StagesTuple`],
+    /// named `name`, until the next stage is entered.
+    ///
+    /// This is called by the [`crate::stages::StagesTuple`] for each stage, using [`crate::stages::Stage::introspection_name`].
+    /// Each stage keeps the index it got when it was first entered, and stages with the same id and name,
+    /// such as a stage that runs in a loop, add up.
+    pub fn enter_stage(&mut self, id: StageId, name: Cow<'static, str>) {
+        self.curr_stage = self
+            .stage_names
+            .iter()
+            .position(|(stage_id, stage_name)| *stage_id == id && *stage_name == name)
+            .unwrap_or_else(|| {
+                self.stage_names.push((id, name));
+                self.stage_names.len() - 1
+            });
     }
 
-    /// Reset the stage index counter to zero
-    #[inline]
-    pub fn reset_stage_index(&mut self) {
-        self.curr_stage = 0;
+    /// The name of the stage at the given index, as given to [`Self::enter_stage`]
+    #[must_use]
+    pub fn stage_name(&self, stage_index: usize) -> Option<&str> {
+        self.stage_names
+            .get(stage_index)
+            .map(|(_, name)| name.as_ref())
     }
 
     /// Update the time spent in the feedback
@@ -1149,6 +1198,11 @@ impl ClientPerfMonitor {
         );
     }
 
+    /// Count that the feedback found an input interesting
+    pub fn hit_feedback(&mut self, name: &str) {
+        *self.feedback_hits.entry_ref(name).or_default() += 1;
+    }
+
     /// Update the time spent in all the feedbacks
     pub fn update_feedbacks(&mut self, feedbacks: &HashMap<String, u64>) {
         for (key, value) in feedbacks {
@@ -1174,8 +1228,8 @@ impl ClientPerfMonitor {
 
     /// Update the given [`PerfFeature`] with the given `time`
     pub fn update_feature(&mut self, feature: PerfFeature, time: u64) {
-        // Get the current stage index as `usize`
-        let stage_index: usize = self.curr_stage.into();
+        // Get the current stage index
+        let stage_index = self.curr_stage;
 
         // Get the index of the given feature
         let feature_index: usize = feature.into();
@@ -1229,6 +1283,21 @@ impl ClientPerfMonitor {
     pub fn feedbacks(&self) -> &HashMap<String, u64> {
         &self.feedbacks
     }
+
+    /// How often each feedback found an input interesting
+    #[must_use]
+    pub fn feedback_hits(&self) -> &HashMap<String, u64> {
+        &self.feedback_hits
+    }
+
+    /// The label of the stage at the given index for printing, such as `Stage 0 (StdMutationalStage)`
+    #[must_use]
+    pub fn stage_label(&self, stage_index: usize) -> String {
+        match self.stage_name(stage_index) {
+            Some(name) => format!("Stage {stage_index} ({name})"),
+            None => format!("Stage {stage_index}"),
+        }
+    }
 }
 
 #[cfg(feature = "introspection")]
@@ -1257,7 +1326,7 @@ impl fmt::Display for ClientPerfMonitor {
         // Make sure we only iterate over used stages
         for (stage_index, features) in self.used_stages() {
             // Write the stage header
-            writeln!(f, "  Stage {stage_index}:")?;
+            writeln!(f, "  {}:", self.stage_label(stage_index))?;
 
             for (feature_index, feature) in features.iter().enumerate() {
                 // Calculate this current stage's percentage
@@ -1293,8 +1362,12 @@ impl fmt::Display for ClientPerfMonitor {
             // Update the other percent by removing this current percent
             other_percent -= feedback_percent;
 
-            // Write the percentage for this feedback
-            writeln!(f, "    {feedback_percent:6.4}: {feedback_name}")?;
+            // Write the percentage and hits for this feedback
+            let hits = self.feedback_hits.get(feedback_name).unwrap_or(&0);
+            writeln!(
+                f,
+                "    {feedback_percent:6.4}: {feedback_name} ({hits} hits)"
+            )?;
         }
 
         write!(f, "  {other_percent:6.4}: Not Measured")?;
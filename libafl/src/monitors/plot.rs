@@ -0,0 +1,382 @@
+//! A monitor that wraps a base one and writes the stats in the `plot_data` format of AFL++,
+//! so that `afl-plot` and other tools of the AFL ecosystem can be used on `LibAFL` campaigns.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Write as _, time::Duration};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use libafl_bolts::{current_time, format_duration_hms, ClientId};
+use serde_json::Value;
+
+use crate::monitors::{ClientStats, Monitor, NopMonitor, UserStats, UserStatsValue};
+
+/// The header of the `plot_data` file, the same as the one written by AFL++
+pub const PLOT_DATA_HEADER: &str = "# relative_time, cycles_done, cur_item, corpus_count, pending_total, pending_favs, map_size, saved_crashes, saved_hangs, max_depth, execs_per_sec, total_execs, edges_found";
+
+/// The maximum number of points kept for the SVG chart, older points get thinned out
+const PLOT_MAX_HISTORY: usize = 1024;
+
+const SVG_WIDTH: u32 = 1000;
+const SVG_PANEL_HEIGHT: u32 = 200;
+/// The space left for the axis labels, around each chart
+const SVG_MARGIN: u32 = 60;
+const SVG_COLORS: [&str; 3] = ["#1f77b4", "#d62728", "#2ca02c"];
+
+/// A single row of the `plot_data` file
+#[derive(Debug, Clone, Copy, Default)]
+struct PlotPoint {
+    run_time: u64,
+    corpus: u64,
+    pending: u64,
+    pending_favs: u64,
+    /// The map coverage, in percent
+    coverage: f64,
+    edges: u64,
+    objectives: u64,
+    exec_sec: f64,
+    executions: u64,
+}
+
+/// A series in a chart of the SVG: its name, and how to get its value from a [`PlotPoint`]
+type SvgSeries = (&'static str, fn(&PlotPoint) -> f64);
+
+/// Wraps a base monitor and appends the global stats to a `plot_data` file, in the CSV format of AFL++.
+///
+/// The coverage is taken from the `edges` user stats, the pending entries from the `AflStats` of the
+/// [`crate::stages::AflStatsStage`], if present. Columns `LibAFL` does not track, such as the cycles
+/// or the hangs, are written as `0`.
+///
+/// Optionally, a simple SVG chart of the campaign is rendered, next to the `plot_data`.
+#[derive(Debug, Clone)]
+pub struct OnDiskPlotMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    path: PathBuf,
+    svg_path: Option<PathBuf>,
+    history: Vec<PlotPoint>,
+    last_update: Duration,
+    update_interval: Duration,
+}
+
+impl<M> Monitor for OnDiskPlotMonitor<M>
+where
+    M: Monitor,
+{
+    /// The client monitor, mutable
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    /// The client monitor
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    /// Time this fuzzing run stated
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    /// Set creation time
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        let cur_time = current_time();
+
+        if cur_time - self.last_update >= self.update_interval {
+            self.last_update = cur_time;
+
+            let point = self.point(cur_time);
+            let line = format!(
+                "{}, 0, 0, {}, {}, {}, {:.2}%, {}, 0, 0, {:.2}, {}, {}\n",
+                point.run_time,
+                point.corpus,
+                point.pending,
+                point.pending_favs,
+                point.coverage,
+                point.objectives,
+                point.exec_sec,
+                point.executions,
+                point.edges
+            );
+            self.append(&line);
+
+            if let Some(svg_path) = &self.svg_path {
+                if self.history.len() >= PLOT_MAX_HISTORY {
+                    let mut i = 0;
+                    self.history.retain(|_| {
+                        i += 1;
+                        i % 2 == 0
+                    });
+                }
+                self.history.push(point);
+                fs::write(svg_path, render_svg(&self.history))
+                    .expect("Failed to write the SVG chart");
+            }
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+}
+
+impl<M> OnDiskPlotMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`OnDiskPlotMonitor`], appending to the `plot_data` file at `filename` every 5 seconds.
+    /// The header is written if the file is new, so resumed campaigns keep appending to the same file.
+    #[must_use]
+    pub fn new<P>(filename: P, base: M) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let update_interval = Duration::from_secs(5);
+        Self {
+            base,
+            path: filename.into(),
+            svg_path: None,
+            history: vec![],
+            last_update: current_time() - update_interval,
+            update_interval,
+        }
+    }
+
+    /// Write a row at most once per `update_interval`
+    #[must_use]
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.last_update = current_time() - update_interval;
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Also render an SVG chart of corpus, objectives, coverage and speed to `svg_path` on each update.
+    /// The chart only covers the points seen by this monitor, not the ones of previous runs.
+    #[must_use]
+    pub fn with_svg<P>(mut self, svg_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.svg_path = Some(svg_path.into());
+        self
+    }
+
+    /// Append a row to the `plot_data` file, creating it with the header if needed
+    fn append(&self, line: &str) {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .expect("Failed to open the plot_data file");
+        let len = file
+            .metadata()
+            .expect("Failed to read the size of the plot_data file")
+            .len();
+        if len == 0 {
+            writeln!(file, "{PLOT_DATA_HEADER}").expect("Failed to write to the plot_data file");
+        }
+        file.write_all(line.as_bytes())
+            .expect("Failed to write to the plot_data file");
+    }
+
+    /// The row for the current stats
+    #[allow(clippy::cast_precision_loss)]
+    fn point(&mut self, cur_time: Duration) -> PlotPoint {
+        let mut point = PlotPoint {
+            run_time: (cur_time - self.start_time()).as_secs(),
+            corpus: self.corpus_size(),
+            objectives: self.objective_size(),
+            executions: self.total_execs(),
+            exec_sec: self.execs_per_sec(),
+            ..PlotPoint::default()
+        };
+
+        for client in self.client_stats().iter().filter(|client| client.enabled) {
+            // Like AFL++ with multiple instances, report the best coverage of all clients
+            if let Some(UserStatsValue::Ratio(edges, size)) =
+                client.get_user_stats("edges").map(UserStats::value)
+            {
+                if *size != 0 && *edges >= point.edges {
+                    point.edges = *edges;
+                    point.coverage = *edges as f64 * 100.0 / *size as f64;
+                }
+            }
+
+            if let Some(UserStatsValue::String(afl_stats)) =
+                client.get_user_stats("AflStats").map(UserStats::value)
+            {
+                if let Ok(afl_stats) = serde_json::from_str::<Value>(afl_stats) {
+                    point.pending += afl_stats["pending"].as_u64().unwrap_or_default();
+                    point.pending_favs += afl_stats["pend_fav"].as_u64().unwrap_or_default();
+                }
+            }
+        }
+
+        point
+    }
+}
+
+impl OnDiskPlotMonitor<NopMonitor> {
+    /// Create new [`OnDiskPlotMonitor`] without a base
+    #[must_use]
+    pub fn nop<P>(filename: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::new(filename, NopMonitor::new())
+    }
+}
+
+/// Render the history as an SVG image with one chart per metric, similar to the ones of `afl-plot`
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn render_svg(history: &[PlotPoint]) -> String {
+    let panels: [(&str, &[SvgSeries]); 4] = [
+        (
+            "corpus",
+            &[
+                ("corpus count", |p| p.corpus as f64),
+                ("pending", |p| p.pending as f64),
+                ("pending favs", |p| p.pending_favs as f64),
+            ],
+        ),
+        ("objectives", &[("objectives", |p| p.objectives as f64)]),
+        ("map coverage (%)", &[("coverage", |p| p.coverage)]),
+        ("exec speed", &[("execs/sec", |p| p.exec_sec)]),
+    ];
+
+    let height = panels.len() as u32 * (SVG_PANEL_HEIGHT + SVG_MARGIN) + SVG_MARGIN;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_WIDTH}\" height=\"{height}\" font-family=\"sans-serif\" font-size=\"12\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n"
+    );
+    for (i, (title, series)) in panels.iter().enumerate() {
+        let top = SVG_MARGIN + i as u32 * (SVG_PANEL_HEIGHT + SVG_MARGIN);
+        render_svg_panel(&mut svg, top, title, series, history);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Render a single chart, with its top edge at `top`
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn render_svg_panel(
+    svg: &mut String,
+    top: u32,
+    title: &str,
+    series: &[SvgSeries],
+    history: &[PlotPoint],
+) {
+    let left = SVG_MARGIN;
+    let width = SVG_WIDTH - 2 * SVG_MARGIN;
+    let bottom = top + SVG_PANEL_HEIGHT;
+
+    let max_x = history.last().map_or(0, |p| p.run_time).max(1) as f64;
+    let max_y = history
+        .iter()
+        .flat_map(|p| series.iter().map(move |(_, value)| value(p)))
+        .fold(0.0, f64::max);
+    // Avoid a flat line at the top if everything is 0
+    let scale_y = if max_y > 0.0 { max_y } else { 1.0 };
+
+    writeln!(
+        svg,
+        "<text x=\"{left}\" y=\"{}\" font-weight=\"bold\">{title}</text>\n\
+         <rect x=\"{left}\" y=\"{top}\" width=\"{width}\" height=\"{SVG_PANEL_HEIGHT}\" fill=\"none\" stroke=\"#999\"/>\n\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n\
+         <text x=\"{}\" y=\"{bottom}\" text-anchor=\"end\">0</text>\n\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        top - 8,
+        left - 4,
+        top + 10,
+        format_svg_number(max_y),
+        left - 4,
+        left + width,
+        bottom + 16,
+        format_duration_hms(&Duration::from_secs(max_x as u64)),
+    )
+    .unwrap();
+
+    for (i, (name, value)) in series.iter().enumerate() {
+        let color = SVG_COLORS[i % SVG_COLORS.len()];
+        let points: Vec<String> = history
+            .iter()
+            .map(|p| {
+                let x = f64::from(left) + p.run_time as f64 / max_x * f64::from(width);
+                let y = f64::from(bottom) - value(p) / scale_y * f64::from(SVG_PANEL_HEIGHT);
+                format!("{x:.1},{y:.1}")
+            })
+            .collect();
+        writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\"/>\n\
+             <text x=\"{}\" y=\"{}\" fill=\"{color}\">{name}</text>",
+            points.join(" "),
+            left + width - 150,
+            top + 16 + i as u32 * 14,
+        )
+        .unwrap();
+    }
+}
+
+/// Format the label of an axis, keeping it short
+fn format_svg_number(value: f64) -> String {
+    if value >= 1_000_000.0 {
+        format!("{:.1}M", value / 1_000_000.0)
+    } else if value >= 1_000.0 {
+        format!("{:.1}k", value / 1_000.0)
+    } else {
+        format!("{value:.0}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::{env, fs, process};
+
+    use libafl_bolts::ClientId;
+
+    use crate::monitors::{plot::PLOT_DATA_HEADER, Monitor, OnDiskPlotMonitor};
+
+    #[test]
+    fn test_plot_monitor() {
+        let path = env::temp_dir().join(format!("libafl_plot_data_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut monitor = OnDiskPlotMonitor::nop(&path).with_update_interval(Duration::ZERO);
+        monitor.display("test", ClientId(0));
+        // clones, as handed out by the launcher, keep appending to the same file
+        let mut clone = monitor.clone();
+        clone.display("test", ClientId(0));
+
+        let plot_data = fs::read_to_string(&path).unwrap();
+        let mut lines = plot_data.lines();
+        assert_eq!(lines.next(), Some(PLOT_DATA_HEADER));
+        let rows: Vec<_> = lines.collect();
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            assert_eq!(
+                row.split(", ").count(),
+                PLOT_DATA_HEADER.split(", ").count()
+            );
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+}
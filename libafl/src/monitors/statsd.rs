@@ -0,0 +1,323 @@
+//! A monitor that wraps a base one and exports the stats to a `StatsD` (or `DogStatsD`) daemon over UDP
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Write, time::Duration};
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::Arc,
+};
+
+use libafl_bolts::{current_time, ClientId, Error};
+
+use crate::monitors::{ClientStats, Monitor, NopMonitor, UserStatsValue};
+
+/// The maximum size of a single UDP datagram we send, safe for the usual MTU of 1500 bytes
+const STATSD_MAX_PACKET_SIZE: usize = 1432;
+
+/// The dialect of the `StatsD` protocol to speak
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatsdFlavor {
+    /// Plain `StatsD`. Tags are not supported, per-client stats are put into the metric name.
+    Statsd,
+    /// `DogStatsD`, as understood by the Datadog agent. Tags, including the client id, are appended to each metric.
+    DogStatsd,
+}
+
+/// Wraps a base monitor and sends the standard fuzzing stats, as well as all user stats,
+/// to a `StatsD` daemon, as gauges and counters.
+///
+/// All global stats are sent as gauges, except for the executions, which are sent as a counter.
+/// User stats are sent per client, non-numeric user stats are skipped.
+/// Clones share the same socket.
+#[derive(Debug, Clone)]
+pub struct StatsdMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    socket: Arc<UdpSocket>,
+    target: SocketAddr,
+    flavor: StatsdFlavor,
+    prefix: String,
+    tags: Vec<(String, String)>,
+    last_update: Duration,
+    update_interval: Duration,
+    last_executions: u64,
+}
+
+impl<M> Monitor for StatsdMonitor<M>
+where
+    M: Monitor,
+{
+    /// The client monitor, mutable
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    /// The client monitor
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    /// Time this fuzzing run stated
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    /// Set creation time
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        let cur_time = current_time();
+
+        if cur_time - self.last_update >= self.update_interval {
+            self.last_update = cur_time;
+            self.report(cur_time);
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+}
+
+impl<M> StatsdMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`StatsdMonitor`], sending plain `StatsD` metrics prefixed with `libafl` to `addr` every 10 seconds
+    pub fn new<A>(addr: A, base: M) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let target = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            Error::illegal_argument("The StatsD address did not resolve to any socket address")
+        })?;
+        let socket = if target.is_ipv4() {
+            UdpSocket::bind(("0.0.0.0", 0))?
+        } else {
+            UdpSocket::bind(("::", 0))?
+        };
+        let update_interval = Duration::from_secs(10);
+
+        Ok(Self {
+            base,
+            socket: Arc::new(socket),
+            target,
+            flavor: StatsdFlavor::Statsd,
+            prefix: "libafl".into(),
+            tags: vec![],
+            last_update: current_time() - update_interval,
+            update_interval,
+            last_executions: 0,
+        })
+    }
+
+    /// Speak the given [`StatsdFlavor`]
+    #[must_use]
+    pub fn with_flavor(mut self, flavor: StatsdFlavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Prefix all metric names with `prefix`, followed by a `.`
+    #[must_use]
+    pub fn with_prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Add the tag `key:value` to all metrics.
+    /// Tags are only sent for [`StatsdFlavor::DogStatsd`].
+    #[must_use]
+    pub fn with_tag<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// Send the stats at most once per `update_interval`
+    #[must_use]
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.last_update = current_time() - update_interval;
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Sends all stats to the `StatsD` daemon
+    #[allow(clippy::cast_precision_loss)]
+    fn report(&mut self, cur_time: Duration) {
+        let mut lines = vec![];
+
+        let executions = self.total_execs();
+        // The executions are a counter, so we only send the increment since the last report
+        let new_executions = executions.saturating_sub(self.last_executions);
+        self.last_executions = executions;
+
+        let run_time = (cur_time - self.start_time()).as_secs();
+        let clients = self.client_stats_count() as u64;
+        let corpus = self.corpus_size();
+        let objectives = self.objective_size();
+        let exec_sec = self.execs_per_sec();
+
+        lines.push(self.metric("executions", new_executions, "c", None));
+        lines.push(self.metric("run_time", run_time, "g", None));
+        lines.push(self.metric("clients", clients, "g", None));
+        lines.push(self.metric("corpus", corpus, "g", None));
+        lines.push(self.metric("objectives", objectives, "g", None));
+        lines.push(self.metric("exec_sec", exec_sec, "g", None));
+
+        for (i, client) in self.client_stats().iter().enumerate() {
+            if !client.enabled {
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            let client_id = ClientId(i as u32);
+            for (key, val) in &client.user_monitor {
+                let value = match val.value() {
+                    UserStatsValue::Number(n) => *n as f64,
+                    UserStatsValue::Float(f) => *f,
+                    UserStatsValue::String(_) => continue,
+                    UserStatsValue::Ratio(a, b) => {
+                        if *b == 0 {
+                            0.0
+                        } else {
+                            (*a as f64 / *b as f64) * 100.0
+                        }
+                    }
+                    UserStatsValue::Percent(p) => *p * 100.0,
+                };
+                lines.push(self.metric(
+                    &format!("user.{}", sanitize(key)),
+                    value,
+                    "g",
+                    Some(client_id),
+                ));
+            }
+        }
+
+        self.send(&lines);
+    }
+
+    /// Formats a single metric line, like `prefix.name:value|kind|#tags`
+    fn metric<V>(&self, name: &str, value: V, kind: &str, client: Option<ClientId>) -> String
+    where
+        V: core::fmt::Display,
+    {
+        let mut line = String::new();
+        if !self.prefix.is_empty() {
+            write!(line, "{}.", self.prefix).unwrap();
+        }
+        match (self.flavor, client) {
+            (StatsdFlavor::Statsd, Some(client)) => {
+                write!(line, "client_{}.{name}:{value}|{kind}", client.0).unwrap();
+            }
+            (StatsdFlavor::Statsd, None) => write!(line, "{name}:{value}|{kind}").unwrap(),
+            (StatsdFlavor::DogStatsd, client) => {
+                write!(line, "{name}:{value}|{kind}").unwrap();
+                let mut tags = self
+                    .tags
+                    .iter()
+                    .map(|(key, value)| format!("{key}:{value}"))
+                    .chain(client.map(|client| format!("client:{}", client.0)));
+                if let Some(first) = tags.next() {
+                    write!(line, "|#{first}").unwrap();
+                    for tag in tags {
+                        write!(line, ",{tag}").unwrap();
+                    }
+                }
+            }
+        }
+        line
+    }
+
+    /// Sends the lines, packing as many of them as possible into each datagram
+    fn send(&self, lines: &[String]) {
+        let mut packet = String::new();
+        for line in lines {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > STATSD_MAX_PACKET_SIZE {
+                self.send_packet(&packet);
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(line);
+        }
+        if !packet.is_empty() {
+            self.send_packet(&packet);
+        }
+    }
+
+    fn send_packet(&self, packet: &str) {
+        if let Err(err) = self.socket.send_to(packet.as_bytes(), self.target) {
+            log::warn!("Failed to send stats to StatsD at {}: {err}", self.target);
+        }
+    }
+}
+
+impl StatsdMonitor<NopMonitor> {
+    /// Create a new [`StatsdMonitor`] without a base
+    pub fn nop<A>(addr: A) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::new(addr, NopMonitor::new())
+    }
+}
+
+/// `StatsD` metric names may not contain `:`, `|`, `@`, `#` or whitespace, replace them with `_`
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::net::UdpSocket;
+
+    use libafl_bolts::ClientId;
+
+    use crate::monitors::{Monitor, StatsdMonitor};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_statsd_monitor_clone() {
+        let daemon = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
+        daemon
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let monitor = StatsdMonitor::nop(daemon.local_addr().unwrap())
+            .unwrap()
+            .with_prefix("fuzz")
+            .with_update_interval(Duration::ZERO);
+
+        // a clone, as handed to each client by the launcher, reports to the same daemon
+        let mut clone = monitor.clone();
+        clone.display("test", ClientId(0));
+
+        let mut buf = [0; 1500];
+        let len = daemon.recv(&mut buf).unwrap();
+        let packet = core::str::from_utf8(&buf[..len]).unwrap();
+        assert!(packet.lines().any(|line| line == "fuzz.executions:0|c"));
+        assert!(packet.lines().any(|line| line == "fuzz.corpus:0|g"));
+    }
+}
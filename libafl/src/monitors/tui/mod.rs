@@ -34,6 +34,7 @@ use ui::TuiUI;
 
 const DEFAULT_TIME_WINDOW: u64 = 60 * 10; // 10 min
 const DEFAULT_LOGS_NUMBER: usize = 128;
+const DEFAULT_CLIENT_LOGS_NUMBER: usize = 64;
 
 #[derive(Debug, Copy, Clone)]
 pub struct TimedStat {
@@ -95,7 +96,7 @@ pub struct PerfTuiContext {
     pub scheduler: f64,
     pub manager: f64,
     pub unmeasured: f64,
-    pub stages: Vec<Vec<(String, f64)>>,
+    pub stages: Vec<(String, Vec<(String, f64)>)>,
     pub feedbacks: Vec<(String, f64)>,
 }
 
@@ -119,7 +120,7 @@ impl PerfTuiContext {
 
         // Calculate each stage
         // Make sure we only iterate over used stages
-        for (_stage_index, features) in m.used_stages() {
+        for (stage_index, features) in m.used_stages() {
             let mut features_percentages = vec![];
 
             for (feature_index, feature) in features.iter().enumerate() {
@@ -139,7 +140,8 @@ impl PerfTuiContext {
                 features_percentages.push((format!("{feature:?}"), feature_percent));
             }
 
-            self.stages.push(features_percentages);
+            self.stages
+                .push((m.stage_label(stage_index), features_percentages));
         }
 
         self.feedbacks.clear();
@@ -156,8 +158,9 @@ impl PerfTuiContext {
             // Update the other percent by removing this current percent
             other_percent -= feedback_percent;
 
+            let hits = m.feedback_hits().get(feedback_name).unwrap_or(&0);
             self.feedbacks
-                .push((feedback_name.clone(), feedback_percent));
+                .push((format!("{feedback_name} ({hits} hits)"), feedback_percent));
         }
 
         self.unmeasured = other_percent;
@@ -212,6 +215,12 @@ pub struct ClientTuiContext {
     pub process_timing: ProcessTiming,
     pub item_geometry: ItemGeometry,
     pub user_stats: HashMap<Cow<'static, str>, UserStats>,
+
+    /// The last events received from this client
+    pub logs: VecDeque<String>,
+    /// How often each kind of event was received from this client.
+    /// `Testcase` and `Objective` count the hits of the feedback and the objective, respectively.
+    pub event_counts: HashMap<String, u64>,
 }
 
 impl ClientTuiContext {
@@ -265,6 +274,15 @@ impl ClientTuiContext {
             self.user_stats.insert(key.clone(), val.clone());
         }
     }
+
+    /// Record an event received from this client, for the event log and the counters
+    pub fn add_event(&mut self, event_msg: &str, log: String) {
+        *self.event_counts.entry(event_msg.to_string()).or_default() += 1;
+        while self.logs.len() >= DEFAULT_CLIENT_LOGS_NUMBER {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(log);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -411,11 +429,11 @@ impl Monitor for TuiMonitor {
 
         {
             let client = &self.client_stats()[sender_id.0 as usize];
+            let run_time = format_duration_hms(&(cur_time - self.start_time));
             let mut ctx = self.context.write().unwrap();
-            ctx.clients
-                .entry(sender_id.0 as usize)
-                .or_default()
-                .grab_data(client, exec_sec);
+            let client_ctx = ctx.clients.entry(sender_id.0 as usize).or_default();
+            client_ctx.grab_data(client, exec_sec);
+            client_ctx.add_event(event_msg, format!("{run_time} {fmt}"));
             while ctx.client_logs.len() >= DEFAULT_LOGS_NUMBER {
                 ctx.client_logs.pop_front();
             }
@@ -613,9 +631,11 @@ fn run_tui_thread<W: Write + Send + Sync + 'static>(
                     match key.code {
                         KeyCode::Char(c) => ui.on_key(c),
                         KeyCode::Left => ui.on_left(),
-                        //KeyCode::Up => ui.on_up(),
+                        KeyCode::Up => ui.on_up(),
                         KeyCode::Right => ui.on_right(),
-                        //KeyCode::Down => ui.on_down(),
+                        KeyCode::Down => ui.on_down(),
+                        KeyCode::Tab => ui.on_tab(),
+                        KeyCode::BackTab => ui.on_back_tab(),
                         _ => {}
                     }
                 }
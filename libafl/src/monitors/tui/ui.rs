@@ -20,12 +20,16 @@ use super::{
     TuiContext,
 };
 
+/// The tabs of the UI, selected with `tab` or the number keys
+const TABS: [&str; 5] = ["overview", "clients", "stages", "feedbacks", "events"];
+
 #[derive(Default, Debug)]
 pub struct TuiUI {
     title: String,
     version: String,
     enhanced_graphics: bool,
     show_logs: bool,
+    tab_idx: usize,
     clients_idx: usize,
     clients: usize,
     charts_tab_idx: usize,
@@ -63,13 +67,31 @@ impl TuiUI {
             't' => {
                 self.show_logs = !self.show_logs;
             }
+            '1'..='9' => {
+                let idx = c as usize - '1' as usize;
+                if idx < TABS.len() {
+                    self.tab_idx = idx;
+                }
+            }
             _ => {}
         }
     }
 
-    //pub fn on_up(&mut self) {}
+    pub fn on_tab(&mut self) {
+        self.tab_idx = (self.tab_idx + 1) % TABS.len();
+    }
+
+    pub fn on_back_tab(&mut self) {
+        self.tab_idx = (self.tab_idx + TABS.len() - 1) % TABS.len();
+    }
 
-    //pub fn on_down(&mut self) {}
+    pub fn on_up(&mut self) {
+        self.on_left();
+    }
+
+    pub fn on_down(&mut self) {
+        self.on_right();
+    }
 
     pub fn on_right(&mut self) {
         if self.clients != 0 {
@@ -96,6 +118,48 @@ impl TuiUI {
     pub fn draw(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>) {
         self.clients = app.read().unwrap().clients_num;
 
+        let layout = Layout::default()
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(f.size());
+        self.draw_tabs(f, layout[0]);
+
+        match self.tab_idx {
+            0 => self.draw_overview(f, app, layout[1]),
+            1 => self.draw_clients_tab(f, app, layout[1]),
+            2 => self.draw_stages_tab(f, app, layout[1]),
+            3 => self.draw_feedbacks_tab(f, app, layout[1]),
+            _ => self.draw_events_tab(f, app, layout[1]),
+        }
+    }
+
+    fn draw_tabs(&mut self, f: &mut Frame, area: Rect) {
+        let titles: Vec<Line> = TABS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                Line::from(Span::styled(
+                    format!("{} {name}", i + 1),
+                    Style::default().fg(Color::LightGreen),
+                ))
+            })
+            .collect();
+        let tabs = Tabs::new(titles)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "tabs (`tab` or 1-5 to switch, arrows to select the client)",
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .highlight_style(Style::default().fg(Color::LightYellow))
+            .select(self.tab_idx);
+        f.render_widget(tabs, area);
+    }
+
+    fn draw_overview(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
         let body = Layout::default()
             .constraints(if self.show_logs {
                 if cfg!(feature = "introspection") {
@@ -116,7 +180,7 @@ impl TuiUI {
             } else {
                 [Constraint::Percentage(50), Constraint::Percentage(50)].as_ref()
             })
-            .split(f.size());
+            .split(area);
         let top_body = body[0];
         let mid_body = body[1];
 
@@ -741,13 +805,13 @@ impl TuiUI {
                     Cell::from(Span::raw("manager")),
                     Cell::from(Span::raw(format!("{:.2}%", client.manager * 100.0))),
                 ]));
-                for i in 0..client.stages.len() {
+                for (label, stage) in &client.stages {
                     items.push(Row::new(vec![
-                        Cell::from(Span::raw(format!("stage {i}"))),
+                        Cell::from(Span::raw(label.clone())),
                         Cell::from(Span::raw("")),
                     ]));
 
-                    for (key, val) in &client.stages[i] {
+                    for (key, val) in stage {
                         items.push(Row::new(vec![
                             Cell::from(Span::raw(key.clone())),
                             Cell::from(Span::raw(format!("{:.2}%", val * 100.0))),
@@ -800,4 +864,280 @@ impl TuiUI {
         );
         f.render_widget(logs, area);
     }
+
+    fn draw_clients_tab(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+            .split(area);
+
+        let header = Row::new(vec![
+            Cell::from("client"),
+            Cell::from("corpus"),
+            Cell::from("objectives"),
+            Cell::from("execs"),
+            Cell::from("exec/sec"),
+            Cell::from("last new entry"),
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let items: Vec<Row> = {
+            let ctx = app.read().unwrap();
+            (1..self.clients)
+                .filter_map(|idx| ctx.clients.get(&idx).map(|client| (idx, client)))
+                .map(|(idx, client)| {
+                    Row::new(vec![
+                        Cell::from(format!("#{idx}")),
+                        Cell::from(format!("{}", client.corpus)),
+                        Cell::from(format!("{}", client.objectives)),
+                        Cell::from(format!("{}", client.executions)),
+                        Cell::from(client.process_timing.exec_speed.clone()),
+                        Cell::from(format_duration_hms(&client.process_timing.last_new_entry)),
+                    ])
+                    .style(self.client_row_style(idx))
+                })
+                .collect()
+        };
+
+        let table = Table::default()
+            .header(header)
+            .rows(items)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "clients (up/down arrows to select)",
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .widths([
+                Constraint::Percentage(12),
+                Constraint::Percentage(14),
+                Constraint::Percentage(16),
+                Constraint::Percentage(18),
+                Constraint::Percentage(16),
+                Constraint::Percentage(24),
+            ]);
+        f.render_widget(table, layout[0]);
+
+        self.draw_client_ui(f, app, layout[1]);
+    }
+
+    #[cfg(feature = "introspection")]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn draw_stages_tab(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        // Leave some space for the borders and the first two columns
+        let bar_width = f64::from(area.width.saturating_sub(40));
+        let row = |name: String, val: f64| {
+            Row::new(vec![
+                Cell::from(name),
+                Cell::from(format!("{:.2}%", val * 100.0)),
+                Cell::from("█".repeat((val.clamp(0.0, 1.0) * bar_width) as usize))
+                    .style(Style::default().fg(Color::LightYellow)),
+            ])
+        };
+
+        let mut items = vec![];
+        {
+            let ctx = app.read().unwrap();
+            if let Some(client) = ctx.introspection.get(&self.clients_idx) {
+                items.push(row("scheduler".into(), client.scheduler));
+                items.push(row("manager".into(), client.manager));
+                for (label, stage) in &client.stages {
+                    let total = stage.iter().map(|(_, val)| val).sum();
+                    items.push(
+                        row(label.clone(), total)
+                            .style(Style::default().add_modifier(Modifier::BOLD)),
+                    );
+                    for (key, val) in stage {
+                        items.push(row(format!("  {key}"), *val));
+                    }
+                }
+                let feedbacks = client.feedbacks.iter().map(|(_, val)| val).sum();
+                items.push(
+                    row("feedbacks".into(), feedbacks)
+                        .style(Style::default().add_modifier(Modifier::BOLD)),
+                );
+                items.push(row("not measured".into(), client.unmeasured));
+            }
+        }
+
+        let table = Table::default()
+            .rows(items)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        format!("stage timings of client #{}", self.clients_idx),
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .widths([
+                Constraint::Length(26),
+                Constraint::Length(10),
+                Constraint::Min(0),
+            ]);
+        f.render_widget(table, area);
+    }
+
+    #[cfg(not(feature = "introspection"))]
+    #[allow(clippy::unused_self)]
+    fn draw_stages_tab(&mut self, f: &mut Frame, _app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        let paragraph = Paragraph::new(Line::from(
+            "stage timings need LibAFL to be built with the `introspection` feature",
+        ))
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+        f.render_widget(paragraph, area);
+    }
+
+    fn draw_feedbacks_tab(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        #[cfg(feature = "introspection")]
+        let counters_area = {
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(area);
+            self.draw_feedback_timings(f, app, layout[1]);
+            layout[0]
+        };
+        #[cfg(not(feature = "introspection"))]
+        let counters_area = area;
+
+        let header = Row::new(vec![
+            Cell::from("client"),
+            Cell::from("feedback hits"),
+            Cell::from("objective hits"),
+            Cell::from("own finds"),
+            Cell::from("imported"),
+            Cell::from("events"),
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let items: Vec<Row> = {
+            let ctx = app.read().unwrap();
+            (1..self.clients)
+                .filter_map(|idx| ctx.clients.get(&idx).map(|client| (idx, client)))
+                .map(|(idx, client)| {
+                    let count = |name: &str| client.event_counts.get(name).copied().unwrap_or(0);
+                    let mut events: Vec<_> = client.event_counts.iter().collect();
+                    events.sort();
+                    let events = events
+                        .iter()
+                        .map(|(name, count)| format!("{name}: {count}"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    Row::new(vec![
+                        Cell::from(format!("#{idx}")),
+                        Cell::from(format!("{}", count("Testcase"))),
+                        Cell::from(format!("{}", count("Objective"))),
+                        Cell::from(format!("{}", client.item_geometry.own_finds)),
+                        Cell::from(format!("{}", client.item_geometry.imported)),
+                        Cell::from(events),
+                    ])
+                    .style(self.client_row_style(idx))
+                })
+                .collect()
+        };
+
+        let table = Table::default()
+            .header(header)
+            .rows(items)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "feedback hits per client",
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .widths([
+                Constraint::Length(8),
+                Constraint::Length(15),
+                Constraint::Length(15),
+                Constraint::Length(11),
+                Constraint::Length(10),
+                Constraint::Min(0),
+            ]);
+        f.render_widget(table, counters_area);
+    }
+
+    #[cfg(feature = "introspection")]
+    fn draw_feedback_timings(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        let items: Vec<Row> = {
+            let ctx = app.read().unwrap();
+            ctx.introspection
+                .get(&self.clients_idx)
+                .map(|client| {
+                    client
+                        .feedbacks
+                        .iter()
+                        .map(|(key, val)| {
+                            Row::new(vec![
+                                Cell::from(key.clone()),
+                                Cell::from(format!("{:.2}%", val * 100.0)),
+                            ])
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let table = Table::default()
+            .rows(items)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        format!("feedback timings of client #{}", self.clients_idx),
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .widths([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]);
+        f.render_widget(table, area);
+    }
+
+    fn draw_events_tab(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        let ctx = app.read().unwrap();
+        // Only show the newest events that fit
+        let shown = usize::from(area.height.saturating_sub(2));
+        let logs: Vec<ListItem> = ctx
+            .clients
+            .get(&self.clients_idx)
+            .map(|client| {
+                client
+                    .logs
+                    .iter()
+                    .skip(client.logs.len().saturating_sub(shown))
+                    .map(|msg| ListItem::new(Span::raw(msg)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let logs = List::new(logs).block(
+            Block::default().borders(Borders::ALL).title(Span::styled(
+                format!("events of client #{} (arrows to switch)", self.clients_idx),
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            )),
+        );
+        f.render_widget(logs, area);
+    }
+
+    /// Highlight the row of the selected client
+    fn client_row_style(&self, idx: usize) -> Style {
+        if idx == self.clients_idx {
+            Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        }
+    }
 }
@@ -0,0 +1,640 @@
+//! A monitor that wraps a base one and serves a small live dashboard over HTTP.
+//!
+//! The dashboard itself is a single embedded page, which receives the stats as `JSON`
+//! through server-sent events from `/events`. The latest stats are also available at `/stats`.
+//!
+//! The dashboard is only served on localhost by default. Before serving it on other addresses,
+//! protect it with a token, see [`WebMonitor::with_token`], as it shows the stats to anyone who can reach it.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+use libafl_bolts::{current_time, ClientId, Error};
+use serde::Serialize;
+
+#[cfg(feature = "introspection")]
+use crate::monitors::PerfFeature;
+use crate::monitors::{ClientStats, Monitor, NopMonitor, UserStatsValue};
+
+/// The embedded dashboard page
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// The maximum amount of data points kept for the charts.
+/// Once reached, every other point is dropped, so the charts always span the whole campaign.
+const WEB_MAX_HISTORY: usize = 720;
+
+/// The amount of recent objectives shown on the dashboard
+const WEB_MAX_OBJECTIVES: usize = 32;
+
+/// Send a keep-alive comment to idle event streams this often
+const WEB_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The maximum amount of dashboard connections served at once, further ones are turned away
+const WEB_MAX_CONNECTIONS: usize = 16;
+
+/// Give up on connections that do not send their request, or do not read our response, for this long
+const WEB_SOCKET_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum length of a request line or a header we read
+const WEB_MAX_LINE_LEN: u64 = 8192;
+
+/// A point in the charts of the dashboard
+#[derive(Debug, Clone, Serialize)]
+struct HistoryPoint {
+    /// Seconds since the start of the campaign
+    run_time: u64,
+    corpus: u64,
+    objectives: u64,
+    executions: u64,
+    exec_sec: f64,
+    /// The highest coverage, in percent, of each ratio user stat over all clients
+    coverage: BTreeMap<String, f64>,
+}
+
+/// An objective found by a client
+#[derive(Debug, Clone, Serialize)]
+struct ObjectiveEntry {
+    /// Seconds since the start of the campaign
+    run_time: u64,
+    client: u32,
+    /// The objective count of this client, after this objective
+    objective_size: u64,
+}
+
+/// The share of the time a client spent in one part of the fuzzing loop
+#[cfg(feature = "introspection")]
+#[derive(Debug, Clone, Serialize)]
+struct Timing {
+    name: String,
+    fraction: f64,
+}
+
+/// The stats of a single client on the dashboard
+#[derive(Debug, Clone, Serialize)]
+struct ClientSnapshot {
+    id: u32,
+    executions: u64,
+    exec_sec: f64,
+    corpus: u64,
+    objectives: u64,
+    /// Seconds since this client last found a new testcase
+    last_corpus: u64,
+    user_stats: BTreeMap<String, String>,
+    #[cfg(feature = "introspection")]
+    timings: Vec<Timing>,
+}
+
+/// Everything the dashboard shows, sent on each update
+#[derive(Debug, Serialize)]
+struct Snapshot<'a> {
+    run_time: u64,
+    clients: Vec<ClientSnapshot>,
+    history: &'a VecDeque<HistoryPoint>,
+    objectives: &'a VecDeque<ObjectiveEntry>,
+}
+
+/// The latest stats, shared with the server threads
+#[derive(Debug, Default)]
+struct SharedStats {
+    /// Increased on each update, so event streams know when to send
+    version: u64,
+    json: String,
+    /// The token requests need to pass in the `token` query parameter, if any
+    token: Option<String>,
+    /// Set once the server should shut down
+    stopped: bool,
+}
+
+type Shared = Arc<(Mutex<SharedStats>, Condvar)>;
+
+/// The dashboard server, shared by all clones of a [`WebMonitor`].
+/// It stops once the last clone is dropped.
+#[derive(Debug)]
+struct WebServer {
+    addr: SocketAddr,
+    shared: Shared,
+}
+
+impl Drop for WebServer {
+    fn drop(&mut self) {
+        let (stats, updated) = &*self.shared;
+        stats.lock().unwrap().stopped = true;
+        updated.notify_all();
+
+        // Wake up the server, waiting for the next connection
+        let mut addr = self.addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(if addr.is_ipv4() {
+                Ipv4Addr::LOCALHOST.into()
+            } else {
+                Ipv6Addr::LOCALHOST.into()
+            });
+        }
+        let _ = TcpStream::connect(addr);
+    }
+}
+
+/// Wraps a base monitor and serves a live dashboard on a configurable address.
+///
+/// The dashboard shows the throughput and the corpus of each client, charts of the coverage
+/// and the corpus over time, the most recent objectives, and, with the `introspection` feature,
+/// where each client spends its time.
+///
+/// Clones share the server, so the monitor can be used with the `Launcher`.
+#[derive(Debug, Clone)]
+pub struct WebMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    server: Arc<WebServer>,
+    history: VecDeque<HistoryPoint>,
+    objectives: VecDeque<ObjectiveEntry>,
+    last_objective_sizes: Vec<u64>,
+    last_update: Duration,
+    update_interval: Duration,
+}
+
+impl<M> Monitor for WebMonitor<M>
+where
+    M: Monitor,
+{
+    /// The client monitor, mutable
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    /// The client monitor
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    /// Time this fuzzing run stated
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    /// Set creation time
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        let cur_time = current_time();
+
+        self.track_objectives(cur_time);
+        if cur_time.saturating_sub(self.last_update) >= self.update_interval {
+            self.last_update = cur_time;
+            self.update(cur_time);
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+}
+
+impl<M> WebMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`WebMonitor`], serving the dashboard on `localhost:port` and updating it every 5 seconds
+    pub fn new(port: u16, base: M) -> Result<Self, Error> {
+        Self::with_addr((Ipv4Addr::LOCALHOST, port), base)
+    }
+
+    /// Create a new [`WebMonitor`], serving the dashboard on `addr` and updating it every 5 seconds.
+    /// Anyone who can reach `addr` can see the stats, unless the dashboard is protected with [`WebMonitor::with_token`].
+    pub fn with_addr<A>(addr: A, base: M) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared: Shared = Arc::default();
+
+        let server_shared = shared.clone();
+        thread::spawn(move || serve(&listener, &server_shared));
+        log::info!("Serving the fuzzing dashboard at http://{addr}/");
+
+        let update_interval = Duration::from_secs(5);
+        Ok(Self {
+            base,
+            server: Arc::new(WebServer { addr, shared }),
+            history: VecDeque::new(),
+            objectives: VecDeque::new(),
+            last_objective_sizes: vec![],
+            last_update: current_time().saturating_sub(update_interval),
+            update_interval,
+        })
+    }
+
+    /// Update the dashboard at most once per `update_interval`
+    #[must_use]
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.last_update = current_time().saturating_sub(update_interval);
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Only serve requests that pass the given `token`, as in `http://host:port/?token=<token>`
+    #[must_use]
+    pub fn with_token<T>(self, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.server.shared.0.lock().unwrap().token = Some(token.into());
+        self
+    }
+
+    /// The address the dashboard is served on
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.addr
+    }
+
+    /// Remembers each new objective, since clients only report their objective count
+    fn track_objectives(&mut self, cur_time: Duration) {
+        let run_time = cur_time.saturating_sub(self.start_time()).as_secs();
+        let client_stats = self.base.client_stats();
+        self.last_objective_sizes.resize(client_stats.len(), 0);
+        for (i, client) in client_stats.iter().enumerate() {
+            if client.objective_size > self.last_objective_sizes[i] {
+                self.last_objective_sizes[i] = client.objective_size;
+                #[allow(clippy::cast_possible_truncation)]
+                self.objectives.push_back(ObjectiveEntry {
+                    run_time,
+                    client: i as u32,
+                    objective_size: client.objective_size,
+                });
+                if self.objectives.len() > WEB_MAX_OBJECTIVES {
+                    self.objectives.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Records a new history point and publishes the current stats to all dashboards
+    fn update(&mut self, cur_time: Duration) {
+        let run_time = cur_time.saturating_sub(self.start_time()).as_secs();
+
+        let mut coverage = BTreeMap::new();
+        let mut clients = vec![];
+        for (i, client) in self.base.client_stats_mut().iter_mut().enumerate() {
+            if !client.enabled {
+                continue;
+            }
+            let mut user_stats = BTreeMap::new();
+            for (key, val) in &client.user_monitor {
+                if let Some(percent) = coverage_percent(val.value()) {
+                    let max: &mut f64 = coverage.entry(key.to_string()).or_default();
+                    *max = max.max(percent);
+                }
+                user_stats.insert(key.to_string(), val.to_string());
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            clients.push(ClientSnapshot {
+                id: i as u32,
+                executions: client.executions,
+                exec_sec: client.execs_per_sec(cur_time),
+                corpus: client.corpus_size,
+                objectives: client.objective_size,
+                last_corpus: cur_time.saturating_sub(client.last_corpus_time).as_secs(),
+                user_stats,
+                #[cfg(feature = "introspection")]
+                timings: timings(client),
+            });
+        }
+
+        if self.history.len() >= WEB_MAX_HISTORY {
+            let mut i = 0;
+            self.history.retain(|_| {
+                i += 1;
+                i % 2 == 0
+            });
+        }
+        let point = HistoryPoint {
+            run_time,
+            corpus: self.corpus_size(),
+            objectives: self.objective_size(),
+            executions: self.total_execs(),
+            exec_sec: self.execs_per_sec(),
+            coverage,
+        };
+        self.history.push_back(point);
+
+        let snapshot = Snapshot {
+            run_time,
+            clients,
+            history: &self.history,
+            objectives: &self.objectives,
+        };
+        let json = match serde_json::to_string(&snapshot) {
+            Ok(json) => json,
+            Err(err) => {
+                log::warn!("Failed to serialize the dashboard stats: {err}");
+                return;
+            }
+        };
+
+        let (stats, updated) = &*self.server.shared;
+        let mut stats = stats.lock().unwrap();
+        stats.version += 1;
+        stats.json = json;
+        updated.notify_all();
+    }
+}
+
+impl WebMonitor<NopMonitor> {
+    /// Create a new [`WebMonitor`] without a base, serving the dashboard on `localhost:port`
+    pub fn nop(port: u16) -> Result<Self, Error> {
+        Self::new(port, NopMonitor::new())
+    }
+}
+
+/// The value of a user stat in percent, if it is a ratio or a percentage
+#[allow(clippy::cast_precision_loss)]
+fn coverage_percent(value: &UserStatsValue) -> Option<f64> {
+    match value {
+        UserStatsValue::Ratio(a, b) => Some(if *b == 0 {
+            0.0
+        } else {
+            (*a as f64 / *b as f64) * 100.0
+        }),
+        UserStatsValue::Percent(p) => Some(*p * 100.0),
+        UserStatsValue::Number(_) | UserStatsValue::Float(_) | UserStatsValue::String(_) => None,
+    }
+}
+
+/// Where this client spends its time, as measured by the `introspection` feature
+#[cfg(feature = "introspection")]
+#[allow(clippy::cast_precision_loss)]
+fn timings(client: &ClientStats) -> Vec<Timing> {
+    let perf = &client.introspection_monitor;
+    let elapsed = perf.elapsed_cycles() as f64;
+    if elapsed == 0.0 {
+        return vec![];
+    }
+
+    let mut timings = vec![
+        Timing {
+            name: "Scheduler".into(),
+            fraction: perf.scheduler_cycles() as f64 / elapsed,
+        },
+        Timing {
+            name: "Manager".into(),
+            fraction: perf.manager_cycles() as f64 / elapsed,
+        },
+    ];
+    for (stage_index, features) in perf.used_stages() {
+        for (feature_index, cycles) in features.iter().enumerate() {
+            if *cycles == 0 {
+                continue;
+            }
+            let feature: PerfFeature = feature_index.into();
+            timings.push(Timing {
+                name: format!("{}: {feature:?}", perf.stage_label(stage_index)),
+                fraction: *cycles as f64 / elapsed,
+            });
+        }
+    }
+    for (feedback_name, feedback_time) in perf.feedbacks() {
+        if *feedback_time == 0 {
+            continue;
+        }
+        let hits = perf.feedback_hits().get(feedback_name).unwrap_or(&0);
+        timings.push(Timing {
+            name: format!("Feedback: {feedback_name} ({hits} hits)"),
+            fraction: *feedback_time as f64 / elapsed,
+        });
+    }
+    timings
+}
+
+/// Accepts dashboard connections, each one is handled in its own thread, until the server is stopped
+fn serve(listener: &TcpListener, shared: &Shared) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if shared.0.lock().unwrap().stopped {
+            break;
+        }
+        match stream {
+            Ok(mut stream) => {
+                if let Err(err) = stream
+                    .set_read_timeout(Some(WEB_SOCKET_TIMEOUT))
+                    .and_then(|()| stream.set_write_timeout(Some(WEB_SOCKET_TIMEOUT)))
+                {
+                    log::warn!("Failed to set the timeouts of a dashboard connection: {err}");
+                    continue;
+                }
+                if connections.fetch_add(1, Ordering::SeqCst) >= WEB_MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    log::debug!("Too many dashboard connections, turning one away");
+                    let _ = respond(
+                        &mut stream,
+                        "503 Service Unavailable",
+                        "text/plain",
+                        "Too many connections",
+                    );
+                    continue;
+                }
+
+                let shared = shared.clone();
+                let connections = connections.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream, &shared) {
+                        log::debug!("Dashboard connection closed: {err}");
+                    }
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(err) => log::warn!("Failed to accept a dashboard connection: {err}"),
+        }
+    }
+}
+
+/// Answers a single HTTP request
+fn handle_connection(mut stream: TcpStream, shared: &Shared) -> Result<(), Error> {
+    let mut reader = BufReader::new(stream.try_clone()?.take(WEB_MAX_LINE_LEN));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, we do not need any of them
+    let mut header = String::new();
+    loop {
+        reader.get_mut().set_limit(WEB_MAX_LINE_LEN);
+        if reader.read_line(&mut header)? <= 2 {
+            break;
+        }
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next());
+    if method != Some("GET") {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", "");
+    }
+    let (path, query) = match target.map(|target| target.split_once('?')) {
+        Some(Some((path, query))) => (Some(path), query),
+        Some(None) => (target, ""),
+        None => (None, ""),
+    };
+
+    let authorized = match &shared.0.lock().unwrap().token {
+        Some(token) => query
+            .split('&')
+            .any(|param| param.strip_prefix("token=") == Some(token.as_str())),
+        None => true,
+    };
+    if !authorized {
+        return respond(
+            &mut stream,
+            "401 Unauthorized",
+            "text/plain",
+            "Pass the dashboard token as ?token=<token>",
+        );
+    }
+
+    match path {
+        Some("/") => respond(&mut stream, "200 OK", "text/html", DASHBOARD_HTML),
+        Some("/stats") => {
+            let json = shared.0.lock().unwrap().json.clone();
+            respond(&mut stream, "200 OK", "application/json", &json)
+        }
+        Some("/events") => stream_events(&mut stream, shared),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "Not found"),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// Sends the stats as server-sent events, whenever they change, until the dashboard disconnects or the server stops
+fn stream_events(stream: &mut TcpStream, shared: &Shared) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+
+    let (stats, updated) = &**shared;
+    let mut sent_version = 0;
+    loop {
+        let message: String = {
+            let stats = stats.lock().unwrap();
+            let (stats, _) = updated
+                .wait_timeout_while(stats, WEB_KEEPALIVE_INTERVAL, |stats| {
+                    stats.version == sent_version && !stats.stopped
+                })
+                .unwrap();
+            if stats.stopped {
+                return Ok(());
+            }
+            if stats.version == sent_version {
+                // Keep proxies from closing idle connections
+                ": keep-alive\n\n".into()
+            } else {
+                sent_version = stats.version;
+                format!("data: {}\n\n", stats.json)
+            }
+        };
+        stream.write_all(message.as_bytes())?;
+        stream.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use core::time::Duration;
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        thread,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use crate::monitors::{Monitor, NopMonitor, WebMonitor};
+
+    fn get(monitor: &WebMonitor<NopMonitor>, target: &str) -> String {
+        let mut stream = TcpStream::connect(monitor.local_addr()).unwrap();
+        write!(stream, "GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_web_monitor_requests() {
+        let mut monitor = WebMonitor::with_addr("127.0.0.1:0", NopMonitor::new())
+            .unwrap()
+            .with_update_interval(Duration::ZERO);
+        monitor.display("Testcase", ClientId(0));
+
+        let stats = get(&monitor, "/stats");
+        assert!(stats.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(stats.contains("application/json"));
+        assert!(stats.contains("\"executions\":0"));
+
+        assert!(get(&monitor, "/nope").starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let mut stream = TcpStream::connect(monitor.local_addr()).unwrap();
+        write!(stream, "POST /stats HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+        let monitor = monitor.with_token("secret");
+        assert!(get(&monitor, "/stats").starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(get(&monitor, "/stats?token=wrong").starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(get(&monitor, "/stats?token=secret").starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_web_monitor_clone() {
+        let monitor = WebMonitor::with_addr("127.0.0.1:0", NopMonitor::new())
+            .unwrap()
+            .with_update_interval(Duration::ZERO);
+        let addr = monitor.local_addr();
+
+        // clones, as handed out by the launcher, publish to the same dashboard
+        let mut clone = monitor.clone();
+        drop(monitor);
+        clone.display("Testcase", ClientId(0));
+        assert!(get(&clone, "/stats").contains("\"executions\":0"));
+
+        // the server stops with the last clone
+        drop(clone);
+        let stopped = (0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            TcpStream::connect(addr).is_err()
+        });
+        assert!(stopped);
+    }
+}
@@ -0,0 +1,576 @@
+//! A bridge to [AFL++ custom mutators](https://aflplus.plus/docs/custom_mutators/),
+//! loading their shared object and calling it from a [`Mutator`].
+//!
+//! The [`AflCustomMutator`] calls `afl_custom_fuzz`, or `afl_custom_havoc_mutation` if the library
+//! only exports that, and reports new corpus entries with `afl_custom_queue_new_entry`.
+//! Its [`AflCustomMutator::post_processor`] runs `afl_custom_post_process` in a
+//! [`crate::executors::PostProcessExecutor`], and [`AflCustomMutator::trim`] drives the trimming callbacks.
+//!
+//! The libraries get a null pointer instead of the AFL++ state in `afl_custom_init`,
+//! so mutators that read from it are not supported.
+
+use alloc::{borrow::Cow, rc::Rc, vec::Vec};
+use core::{
+    cell::RefCell,
+    ffi::{c_uint, c_void, CStr},
+    fmt::{self, Debug, Formatter},
+    mem, ptr, slice,
+};
+use std::{
+    ffi::CString,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::TargetBytesPostProcessor,
+    inputs::HasMutatorBytes,
+    mutators::{MutationResult, Mutator},
+    random_corpus_id_with_disabled,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+type InitFn = unsafe extern "C" fn(*mut c_void, c_uint) -> *mut c_void;
+type FuzzFn =
+    unsafe extern "C" fn(*mut c_void, *mut u8, usize, *mut *mut u8, *mut u8, usize, usize) -> usize;
+type HavocMutationFn =
+    unsafe extern "C" fn(*mut c_void, *mut u8, usize, *mut *mut u8, usize) -> usize;
+type HavocMutationProbabilityFn = unsafe extern "C" fn(*mut c_void) -> u8;
+type PostProcessFn = unsafe extern "C" fn(*mut c_void, *mut u8, usize, *mut *mut u8) -> usize;
+type InitTrimFn = unsafe extern "C" fn(*mut c_void, *mut u8, usize) -> i32;
+type TrimFn = unsafe extern "C" fn(*mut c_void, *mut *mut u8) -> usize;
+type PostTrimFn = unsafe extern "C" fn(*mut c_void, u8) -> i32;
+type QueueGetFn = unsafe extern "C" fn(*mut c_void, *const u8) -> u8;
+type QueueNewEntryFn = unsafe extern "C" fn(*mut c_void, *const u8, *const u8) -> u8;
+type DeinitFn = unsafe extern "C" fn(*mut c_void);
+
+/// A loaded custom mutator library, and the data it returned from `afl_custom_init`
+struct AflCustomLibrary {
+    path: PathBuf,
+    handle: *mut c_void,
+    data: *mut c_void,
+    fuzz: Option<FuzzFn>,
+    havoc_mutation: Option<HavocMutationFn>,
+    havoc_mutation_probability: Option<HavocMutationProbabilityFn>,
+    post_process: Option<PostProcessFn>,
+    init_trim: Option<InitTrimFn>,
+    trim: Option<TrimFn>,
+    post_trim: Option<PostTrimFn>,
+    queue_get: Option<QueueGetFn>,
+    queue_new_entry: Option<QueueNewEntryFn>,
+    deinit: Option<DeinitFn>,
+    /// The output of the last call, copied out of the library, which may reuse `buf` for its output
+    scratch: Vec<u8>,
+}
+
+impl Debug for AflCustomLibrary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AflCustomLibrary")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AflCustomLibrary {
+    /// Looks up the function `name`, given with a trailing nul byte, if the library exports it.
+    /// The names are byte strings, not `c"..."` literals, which would need Rust 1.77.
+    ///
+    /// # Safety
+    /// `F` must be the function pointer type of the symbol.
+    unsafe fn symbol<F>(handle: *mut c_void, name: &[u8]) -> Option<F> {
+        debug_assert_eq!(size_of::<F>(), size_of::<*mut c_void>());
+        let name = CStr::from_bytes_with_nul(name).unwrap();
+        let symbol = libc::dlsym(handle, name.as_ptr());
+        (!symbol.is_null()).then(|| mem::transmute_copy(&symbol))
+    }
+
+    unsafe fn load(path: &Path, seed: u32) -> Result<Self, Error> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| Error::illegal_argument("The library path contains a nul byte"))?;
+        let handle = libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW);
+        if handle.is_null() {
+            let reason = CStr::from_ptr(libc::dlerror()).to_string_lossy();
+            return Err(Error::illegal_argument(format!(
+                "Could not load the custom mutator {}: {reason}",
+                path.display()
+            )));
+        }
+
+        let mut library = Self {
+            path: path.to_path_buf(),
+            handle,
+            data: ptr::null_mut(),
+            fuzz: Self::symbol(handle, b"afl_custom_fuzz\0"),
+            havoc_mutation: Self::symbol(handle, b"afl_custom_havoc_mutation\0"),
+            havoc_mutation_probability: Self::symbol(
+                handle,
+                b"afl_custom_havoc_mutation_probability\0",
+            ),
+            post_process: Self::symbol(handle, b"afl_custom_post_process\0"),
+            init_trim: Self::symbol(handle, b"afl_custom_init_trim\0"),
+            trim: Self::symbol(handle, b"afl_custom_trim\0"),
+            post_trim: Self::symbol(handle, b"afl_custom_post_trim\0"),
+            queue_get: Self::symbol(handle, b"afl_custom_queue_get\0"),
+            queue_new_entry: Self::symbol(handle, b"afl_custom_queue_new_entry\0"),
+            deinit: Self::symbol(handle, b"afl_custom_deinit\0"),
+            scratch: vec![],
+        };
+        // Dropping the library from here on closes it again
+        let Some(init) = Self::symbol::<InitFn>(handle, b"afl_custom_init\0") else {
+            return Err(Error::illegal_argument(format!(
+                "The custom mutator {} does not export afl_custom_init",
+                path.display()
+            )));
+        };
+        if library.fuzz.is_none() && library.havoc_mutation.is_none() {
+            return Err(Error::illegal_argument(format!(
+                "The custom mutator {} exports neither afl_custom_fuzz nor afl_custom_havoc_mutation",
+                path.display()
+            )));
+        }
+        library.data = init(ptr::null_mut(), seed);
+        if library.data.is_null() {
+            return Err(Error::illegal_state(format!(
+                "afl_custom_init of {} failed",
+                path.display()
+            )));
+        }
+        Ok(library)
+    }
+
+    /// Copies the `len` bytes at `out` to the scratch buffer, unless the library returned nothing
+    unsafe fn take_output(&mut self, out: *const u8, len: usize) -> Option<&[u8]> {
+        if out.is_null() || len == 0 {
+            return None;
+        }
+        self.scratch.clear();
+        self.scratch
+            .extend_from_slice(slice::from_raw_parts(out, len));
+        Some(&self.scratch)
+    }
+}
+
+impl Drop for AflCustomLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            // Only deinit what `afl_custom_init` returned
+            if let Some(deinit) = self.deinit.filter(|_| !self.data.is_null()) {
+                deinit(self.data);
+            }
+            // Libraries injected in tests were never opened
+            if !self.handle.is_null() {
+                libc::dlclose(self.handle);
+            }
+        }
+    }
+}
+
+/// The path of a corpus entry, as a C string, if it is stored on disk
+fn corpus_entry_path<S>(state: &S, id: CorpusId) -> Result<Option<CString>, Error>
+where
+    S: HasCorpus,
+{
+    let testcase = state.corpus().get_from_all(id)?.borrow();
+    Ok(testcase
+        .file_path()
+        .as_ref()
+        .and_then(|path| CString::new(path.as_os_str().as_bytes()).ok()))
+}
+
+/// A [`Mutator`] calling an AFL++ custom mutator library, see the [module documentation](self)
+#[derive(Debug)]
+pub struct AflCustomMutator {
+    library: Rc<RefCell<AflCustomLibrary>>,
+    name: Cow<'static, str>,
+}
+
+impl AflCustomMutator {
+    /// Loads the custom mutator at `path`, and initializes it with `seed`.
+    ///
+    /// # Safety
+    /// Loading the library runs its initializers, and all calls run its code, which has to be sound.
+    pub unsafe fn load<P>(path: P, seed: u32) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let library = AflCustomLibrary::load(path, seed)?;
+        let name = Cow::Owned(format!("AflCustomMutator({})", path.display()));
+        Ok(Self {
+            library: Rc::new(RefCell::new(library)),
+            name,
+        })
+    }
+
+    /// The path of the loaded library
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        self.library.borrow().path.clone()
+    }
+
+    /// The post processor of this custom mutator, for a [`crate::executors::PostProcessExecutor`].
+    /// Returns `None` if the library does not export `afl_custom_post_process`.
+    #[must_use]
+    pub fn post_processor(&self) -> Option<AflCustomPostProcessor> {
+        self.library
+            .borrow()
+            .post_process
+            .map(|_| AflCustomPostProcessor {
+                library: self.library.clone(),
+            })
+    }
+
+    /// The probability, in percent, to use `afl_custom_havoc_mutation` in a havoc round, if the library exports it
+    #[must_use]
+    pub fn havoc_mutation_probability(&self) -> Option<u8> {
+        let library = self.library.borrow();
+        library
+            .havoc_mutation_probability
+            .map(|probability| unsafe { probability(library.data) })
+    }
+
+    /// If the library wants the corpus entry at `path` to be fuzzed, as decided by `afl_custom_queue_get`.
+    /// Without that callback, every entry is fuzzed.
+    pub fn queue_get(&mut self, path: &Path) -> Result<bool, Error> {
+        let library = self.library.borrow();
+        let Some(queue_get) = library.queue_get else {
+            return Ok(true);
+        };
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| Error::illegal_argument("The path contains a nul byte"))?;
+        Ok(unsafe { queue_get(library.data, path.as_ptr().cast()) } != 0)
+    }
+
+    /// Trims `input` with the trimming callbacks of the library.
+    ///
+    /// Each step proposes a smaller input, which is kept if `is_interesting` returns `true` for it,
+    /// usually if it still yields the same coverage. Returns if `input` got smaller,
+    /// or `false` if the library does not export `afl_custom_init_trim`, `afl_custom_trim`, and `afl_custom_post_trim`.
+    pub fn trim<I, F>(&mut self, input: &mut I, mut is_interesting: F) -> Result<bool, Error>
+    where
+        I: HasMutatorBytes,
+        F: FnMut(&[u8]) -> Result<bool, Error>,
+    {
+        let mut library = self.library.borrow_mut();
+        let (Some(init_trim), Some(trim), Some(post_trim)) =
+            (library.init_trim, library.trim, library.post_trim)
+        else {
+            return Ok(false);
+        };
+        let original_len = input.bytes().len();
+        let mut bytes = input.bytes().to_vec();
+        let (mut step, steps) =
+            unsafe { (0, init_trim(library.data, bytes.as_mut_ptr(), bytes.len())) };
+        while step >= 0 && step < steps {
+            let mut out = ptr::null_mut();
+            let len = unsafe { trim(library.data, &mut out) };
+            let interesting = match unsafe { library.take_output(out, len) } {
+                Some(candidate) if is_interesting(candidate)? => {
+                    bytes.clear();
+                    bytes.extend_from_slice(candidate);
+                    true
+                }
+                _ => false,
+            };
+            step = unsafe { post_trim(library.data, u8::from(interesting)) };
+        }
+        if bytes.len() >= original_len {
+            return Ok(false);
+        }
+        input.resize(bytes.len(), 0);
+        input.bytes_mut().copy_from_slice(&bytes);
+        Ok(true)
+    }
+}
+
+impl Named for AflCustomMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Mutator<I, S> for AflCustomMutator
+where
+    I: HasMutatorBytes,
+    S: HasCorpus + HasRand + HasMaxSize,
+    S::Input: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let mut library = self.library.borrow_mut();
+        let mut out = ptr::null_mut();
+        let len = if let Some(fuzz) = library.fuzz {
+            // The library may splice with another corpus entry
+            let mut add_buf = if state.corpus().count_all() == 0 {
+                vec![]
+            } else {
+                let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+                let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+                other_testcase.load_input(state.corpus())?.bytes().to_vec()
+            };
+            let buf = input.bytes_mut();
+            unsafe {
+                fuzz(
+                    library.data,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut out,
+                    if add_buf.is_empty() {
+                        ptr::null_mut()
+                    } else {
+                        add_buf.as_mut_ptr()
+                    },
+                    add_buf.len(),
+                    max_size,
+                )
+            }
+        } else {
+            let havoc_mutation = library.havoc_mutation.unwrap();
+            let buf = input.bytes_mut();
+            unsafe {
+                havoc_mutation(
+                    library.data,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut out,
+                    max_size,
+                )
+            }
+        };
+
+        let Some(mutated) = (unsafe { library.take_output(out, len.min(max_size)) }) else {
+            return Ok(MutationResult::Skipped);
+        };
+        input.resize(mutated.len(), 0);
+        input.bytes_mut().copy_from_slice(mutated);
+        Ok(MutationResult::Mutated)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        let Some(new_corpus_id) = new_corpus_id else {
+            return Ok(());
+        };
+        let library = self.library.borrow();
+        let Some(queue_new_entry) = library.queue_new_entry else {
+            return Ok(());
+        };
+        // The library only learns about entries stored on disk
+        let Some(new_path) = corpus_entry_path(state, new_corpus_id)? else {
+            return Ok(());
+        };
+        let orig_path = match *state.corpus().current() {
+            Some(id) => corpus_entry_path(state, id)?,
+            None => None,
+        };
+        unsafe {
+            queue_new_entry(
+                library.data,
+                new_path.as_ptr().cast(),
+                orig_path
+                    .as_ref()
+                    .map_or(ptr::null(), |path| path.as_ptr().cast()),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Runs `afl_custom_post_process` of an [`AflCustomMutator`] on the bytes of each input,
+/// see [`AflCustomMutator::post_processor`].
+///
+/// If the library drops an input, by returning no bytes, the target gets an empty input.
+#[derive(Debug)]
+pub struct AflCustomPostProcessor {
+    library: Rc<RefCell<AflCustomLibrary>>,
+}
+
+impl TargetBytesPostProcessor for AflCustomPostProcessor {
+    fn post_process(&mut self, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        let mut library = self.library.borrow_mut();
+        let post_process = library.post_process.unwrap();
+        let mut out = ptr::null_mut();
+        let len = unsafe { post_process(library.data, bytes.as_mut_ptr(), bytes.len(), &mut out) };
+        let processed = unsafe { library.take_output(out, len) }.unwrap_or_default();
+        bytes.clear();
+        bytes.extend_from_slice(processed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, boxed::Box, rc::Rc, vec::Vec};
+    use core::{cell::RefCell, ffi::c_void, ptr, slice};
+    use std::path::PathBuf;
+
+    use crate::{
+        corpus::{Corpus, Testcase},
+        executors::TargetBytesPostProcessor,
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{
+            afl_custom::{AflCustomLibrary, AflCustomMutator},
+            MutationResult, Mutator,
+        },
+        state::{test::test_std_state, HasCorpus, HasMaxSize},
+        Error,
+    };
+
+    /// The data of the test library, as returned by `afl_custom_init`
+    #[derive(Default)]
+    struct TestData {
+        out: Vec<u8>,
+        trim_step: i32,
+    }
+
+    unsafe fn test_data<'a>(data: *mut c_void) -> &'a mut TestData {
+        &mut *data.cast::<TestData>()
+    }
+
+    /// Appends the splice buffer to the input
+    unsafe extern "C" fn test_fuzz(
+        data: *mut c_void,
+        buf: *mut u8,
+        buf_size: usize,
+        out_buf: *mut *mut u8,
+        add_buf: *mut u8,
+        add_buf_size: usize,
+        _max_size: usize,
+    ) -> usize {
+        let data = test_data(data);
+        data.out.clear();
+        data.out
+            .extend_from_slice(slice::from_raw_parts(buf, buf_size));
+        if !add_buf.is_null() {
+            data.out
+                .extend_from_slice(slice::from_raw_parts(add_buf, add_buf_size));
+        }
+        *out_buf = data.out.as_mut_ptr();
+        data.out.len()
+    }
+
+    /// Upper-cases the input, and drops empty inputs
+    unsafe extern "C" fn test_post_process(
+        data: *mut c_void,
+        buf: *mut u8,
+        buf_size: usize,
+        out_buf: *mut *mut u8,
+    ) -> usize {
+        let data = test_data(data);
+        data.out = slice::from_raw_parts(buf, buf_size).to_ascii_uppercase();
+        *out_buf = data.out.as_mut_ptr();
+        data.out.len()
+    }
+
+    /// Trims one byte off the end in each step, for as many steps as the input is long
+    unsafe extern "C" fn test_init_trim(data: *mut c_void, buf: *mut u8, buf_size: usize) -> i32 {
+        let data = test_data(data);
+        data.out = slice::from_raw_parts(buf, buf_size).to_vec();
+        data.trim_step = 0;
+        i32::try_from(buf_size).unwrap()
+    }
+
+    unsafe extern "C" fn test_trim(data: *mut c_void, out_buf: *mut *mut u8) -> usize {
+        let data = test_data(data);
+        *out_buf = data.out.as_mut_ptr();
+        data.out.len().saturating_sub(1)
+    }
+
+    unsafe extern "C" fn test_post_trim(data: *mut c_void, success: u8) -> i32 {
+        let data = test_data(data);
+        if success != 0 {
+            data.out.pop();
+        }
+        data.trim_step += 1;
+        data.trim_step
+    }
+
+    unsafe extern "C" fn test_deinit(data: *mut c_void) {
+        drop(Box::from_raw(data.cast::<TestData>()));
+    }
+
+    /// A custom mutator calling the test functions above, instead of a loaded library
+    fn test_mutator() -> AflCustomMutator {
+        let library = AflCustomLibrary {
+            path: PathBuf::from("test"),
+            handle: ptr::null_mut(),
+            data: Box::into_raw(Box::<TestData>::default()).cast(),
+            fuzz: Some(test_fuzz),
+            havoc_mutation: None,
+            havoc_mutation_probability: None,
+            post_process: Some(test_post_process),
+            init_trim: Some(test_init_trim),
+            trim: Some(test_trim),
+            post_trim: Some(test_post_trim),
+            queue_get: None,
+            queue_new_entry: None,
+            deinit: Some(test_deinit),
+            scratch: vec![],
+        };
+        AflCustomMutator {
+            library: Rc::new(RefCell::new(library)),
+            name: Cow::Borrowed("test"),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_afl_custom_mutator_missing() {
+        let err = unsafe { AflCustomMutator::load("/nonexistent/libcustom.so", 0) }.unwrap_err();
+        assert!(matches!(err, Error::IllegalArgument(..)));
+    }
+
+    #[test]
+    fn test_afl_custom_fuzz() {
+        let mut state = test_std_state::<BytesInput>();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"xy".to_vec())))
+            .unwrap();
+        let mut mutator = test_mutator();
+
+        let mut input = BytesInput::new(b"ab".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"abxy");
+
+        // The output is cut off at the max size
+        state.set_max_size(5);
+        mutator.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(input.bytes(), b"abxyx");
+    }
+
+    #[test]
+    fn test_afl_custom_post_process() {
+        let mut post_processor = test_mutator().post_processor().unwrap();
+
+        let mut bytes = b"abc".to_vec();
+        post_processor.post_process(&mut bytes).unwrap();
+        assert_eq!(bytes, b"ABC");
+
+        let mut bytes = vec![];
+        post_processor.post_process(&mut bytes).unwrap();
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_afl_custom_trim() {
+        let mut mutator = test_mutator();
+
+        // Everything still starting with `a` is as good as the original
+        let mut input = BytesInput::new(b"abcd".to_vec());
+        assert!(mutator
+            .trim(&mut input, |candidate| Ok(candidate.starts_with(b"a")))
+            .unwrap());
+        assert_eq!(input.bytes(), b"a");
+
+        let mut input = BytesInput::new(b"abcd".to_vec());
+        assert!(!mutator.trim(&mut input, |_| Ok(false)).unwrap());
+        assert_eq!(input.bytes(), b"abcd");
+    }
+}
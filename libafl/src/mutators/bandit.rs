@@ -0,0 +1,410 @@
+//! A [`ScheduledMutator`] that learns which mutations pay off, and schedules them more often.
+//!
+//! Each mutation is an arm of a multi-armed bandit. A mutation is rewarded if a mutant it contributed to
+//! is added to the corpus. The statistics live in the [`BanditScheduledMutatorMetadata`] of the state,
+//! so the learned weights are serialized, and restored, with it.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{
+    f64::consts::PI,
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+use libafl_bolts::{impl_serdeany, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    mutators::{
+        ComposedByMutations, MutationId, MutationResult, Mutator, MutatorsTuple, ScheduledMutator,
+    },
+    state::HasRand,
+    Error, HasMetadata,
+};
+
+/// The default probability of the [`BanditScheduledMutator`] to pick a uniformly random mutation
+pub const BANDIT_DEFAULT_EXPLORATION_FLOOR: f64 = 0.05;
+
+/// How the [`BanditScheduledMutator`] picks the next mutation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BanditPolicy {
+    /// Picks the mutation with the highest upper confidence bound of its success rate (UCB1)
+    #[default]
+    Ucb1,
+    /// Samples a success rate for each mutation from its beta posterior, and picks the highest
+    ThompsonSampling,
+}
+
+/// The success statistics of each mutation of a [`BanditScheduledMutator`]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct BanditScheduledMutatorMetadata {
+    /// The number of mutants each mutation contributed to
+    pub produced: Vec<u64>,
+    /// The number of those mutants that were added to the corpus
+    pub kept: Vec<u64>,
+}
+
+impl_serdeany!(BanditScheduledMutatorMetadata);
+
+impl BanditScheduledMutatorMetadata {
+    /// Creates new, empty statistics for `len` mutations
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self {
+            produced: vec![0; len],
+            kept: vec![0; len],
+        }
+    }
+
+    /// The share of mutants of the mutation `id` that were kept, `0` for untried or unknown mutations
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn success_rate(&self, id: MutationId) -> f64 {
+        match (self.produced.get(id.0), self.kept.get(id.0)) {
+            (Some(&produced), Some(&kept)) if produced > 0 => kept as f64 / produced as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Checks the statistics cover the `len` mutations of the mutator
+    fn check_len(&self, len: usize) -> Result<(), Error> {
+        if self.produced.len() == len && self.kept.len() == len {
+            Ok(())
+        } else {
+            Err(Error::illegal_state(format!(
+                "The BanditScheduledMutatorMetadata has statistics for {} mutations, but the mutator has {len}",
+                self.produced.len()
+            )))
+        }
+    }
+}
+
+/// A standard normal sample, with the Box-Muller transform
+fn sample_normal<R>(rand: &mut R) -> f64
+where
+    R: Rand,
+{
+    let u1 = 1.0 - rand.next_float();
+    let u2 = rand.next_float();
+    libm::sqrt(-2.0 * libm::log(u1)) * libm::cos(2.0 * PI * u2)
+}
+
+/// A sample of the gamma distribution with the given `shape`, at least 1, and a scale of 1,
+/// with the method of Marsaglia and Tsang
+fn sample_gamma<R>(rand: &mut R, shape: f64) -> f64
+where
+    R: Rand,
+{
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / libm::sqrt(9.0 * d);
+    loop {
+        let x = sample_normal(rand);
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let uniform = 1.0 - rand.next_float();
+        if libm::log(uniform) < 0.5 * x * x + d - d * v + d * libm::log(v) {
+            return d * v;
+        }
+    }
+}
+
+/// A sample of the beta distribution with the shapes `alpha` and `beta`, both at least 1
+fn sample_beta<R>(rand: &mut R, alpha: f64, beta: f64) -> f64
+where
+    R: Rand,
+{
+    let x = sample_gamma(rand, alpha);
+    let y = sample_gamma(rand, beta);
+    x / (x + y)
+}
+
+/// A [`Mutator`] that schedules the embedded mutations with a multi-armed bandit,
+/// see the [module documentation](self)
+pub struct BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    name: Cow<'static, str>,
+    mutations: MT,
+    policy: BanditPolicy,
+    exploration_floor: f64,
+    max_stack_pow: usize,
+    mutation_log: Vec<MutationId>,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, MT, S> Debug for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BanditScheduledMutator with {} mutations for Input type {}, using {:?}",
+            self.mutations.len(),
+            core::any::type_name::<I>(),
+            self.policy
+        )
+    }
+}
+
+impl<I, MT, S> Named for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, MT, S> Mutator<I, S> for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasMetadata,
+{
+    #[inline]
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        self.scheduled_mutate(state, input)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        let metadata = state.metadata_mut::<BanditScheduledMutatorMetadata>()?;
+        metadata.check_len(self.mutations.len())?;
+        // Each mutation counts once per mutant, no matter how often it was stacked
+        self.mutation_log.sort_unstable();
+        self.mutation_log.dedup();
+        for id in self.mutation_log.drain(..) {
+            metadata.produced[id.0] += 1;
+            if new_corpus_id.is_some() {
+                metadata.kept[id.0] += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<I, MT, S> ComposedByMutations<I, MT, S> for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    /// Get the mutations
+    #[inline]
+    fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    // Get the mutations (mutable)
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+impl<I, MT, S> ScheduledMutator<I, MT, S> for BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasMetadata,
+{
+    /// Compute the number of iterations used to apply stacked mutations
+    fn iterations(&self, state: &mut S, _: &I) -> u64 {
+        1 << (1 + state.rand_mut().below(self.max_stack_pow))
+    }
+
+    /// Get the next mutation to apply.
+    /// Picks uniformly at random if the statistics are missing, which [`Self::scheduled_mutate`] reports as an error.
+    fn schedule(&self, state: &mut S, _: &I) -> MutationId {
+        match self.pick(state) {
+            Ok(id) => id,
+            Err(_) => state.rand_mut().below(self.mutations.len()).into(),
+        }
+    }
+
+    fn scheduled_mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let mut r = MutationResult::Skipped;
+        let num = self.iterations(state, input);
+        self.mutation_log.clear();
+        for _ in 0..num {
+            let idx = self.pick(state)?;
+            let outcome = self.mutations_mut().get_and_mutate(idx, state, input)?;
+            if outcome == MutationResult::Mutated {
+                self.mutation_log.push(idx);
+                r = MutationResult::Mutated;
+            }
+        }
+        Ok(r)
+    }
+}
+
+impl<I, MT, S> BanditScheduledMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasMetadata,
+{
+    /// Create a new [`BanditScheduledMutator`] instance picking mutations with `policy`.
+    ///
+    /// The statistics are kept if the state already has them, for example after a restart,
+    /// and reset if they were learned for another number of mutations.
+    /// Fails if there are no `mutations`.
+    pub fn new(state: &mut S, mutations: MT, policy: BanditPolicy) -> Result<Self, Error> {
+        let len = mutations.len();
+        if len == 0 {
+            return Err(Error::illegal_argument(
+                "The BanditScheduledMutator needs at least one mutation",
+            ));
+        }
+        let metadata = state.metadata_or_insert_with(|| BanditScheduledMutatorMetadata::new(len));
+        if metadata.check_len(len).is_err() {
+            *metadata = BanditScheduledMutatorMetadata::new(len);
+        }
+        Ok(Self {
+            name: Cow::from(format!(
+                "BanditScheduledMutator[{}]",
+                mutations.names().join(", ")
+            )),
+            mutations,
+            policy,
+            exploration_floor: BANDIT_DEFAULT_EXPLORATION_FLOOR,
+            max_stack_pow: 7,
+            mutation_log: vec![],
+            phantom: PhantomData,
+        })
+    }
+
+    /// Sets the probability to pick a uniformly random mutation instead of asking the bandit,
+    /// so mutations that were unlucky early on still get a chance
+    #[must_use]
+    pub fn with_exploration_floor(mut self, exploration_floor: f64) -> Self {
+        self.exploration_floor = exploration_floor;
+        self
+    }
+
+    /// Sets the maximum number of stacked mutations to `2^max_stack_pow`, which must be at least `1`
+    pub fn with_max_stack_pow(mut self, max_stack_pow: usize) -> Result<Self, Error> {
+        if max_stack_pow == 0 {
+            return Err(Error::illegal_argument(
+                "The max_stack_pow of the BanditScheduledMutator must be at least 1",
+            ));
+        }
+        self.max_stack_pow = max_stack_pow;
+        Ok(self)
+    }
+
+    /// Picks the next mutation with the bandit, or uniformly at random with the probability of the exploration floor
+    #[allow(clippy::cast_precision_loss)]
+    fn pick(&self, state: &mut S) -> Result<MutationId, Error> {
+        if state.rand_mut().coinflip(self.exploration_floor) {
+            return Ok(state.rand_mut().below(self.mutations.len()).into());
+        }
+
+        let metadata = state.metadata_mut::<BanditScheduledMutatorMetadata>()?;
+        metadata.check_len(self.mutations.len())?;
+        // Only moved out of the state for the duration of the sampling, to borrow the rand along with it
+        let metadata = core::mem::take(metadata);
+        let total: u64 = metadata.produced.iter().sum();
+        let mut best = (0, f64::NEG_INFINITY);
+        for (idx, (produced, kept)) in metadata.produced.iter().zip(&metadata.kept).enumerate() {
+            let score = match self.policy {
+                // Untried mutations come first
+                BanditPolicy::Ucb1 if *produced == 0 => f64::INFINITY,
+                BanditPolicy::Ucb1 => {
+                    metadata.success_rate(idx.into())
+                        + libm::sqrt(2.0 * libm::log(total as f64) / *produced as f64)
+                }
+                BanditPolicy::ThompsonSampling => sample_beta(
+                    state.rand_mut(),
+                    (kept + 1) as f64,
+                    (produced - kept + 1) as f64,
+                ),
+            };
+            if score > best.1 {
+                best = (idx, score);
+            }
+        }
+        state.add_metadata(metadata);
+        Ok(best.0.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::CorpusId,
+        inputs::BytesInput,
+        mutators::{
+            bandit::{BanditPolicy, BanditScheduledMutator, BanditScheduledMutatorMetadata},
+            BitFlipMutator, ByteIncMutator, Mutator, ScheduledMutator,
+        },
+        state::test::test_std_state,
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_bandit_scheduled_mutator() {
+        for policy in [BanditPolicy::Ucb1, BanditPolicy::ThompsonSampling] {
+            let mut state = test_std_state::<BytesInput>();
+            let mut mutator = BanditScheduledMutator::new(
+                &mut state,
+                tuple_list!(BitFlipMutator::new(), ByteIncMutator::new()),
+                policy,
+            )
+            .unwrap()
+            .with_max_stack_pow(1)
+            .unwrap();
+
+            let mut input = BytesInput::new(vec![0; 16]);
+            mutator.mutate(&mut state, &mut input).unwrap();
+            mutator.post_exec(&mut state, Some(CorpusId(0))).unwrap();
+            let metadata = state.metadata::<BanditScheduledMutatorMetadata>().unwrap();
+            assert!(metadata.produced.iter().sum::<u64>() >= 1);
+            assert_eq!(metadata.produced, metadata.kept);
+
+            // The second mutation keeps paying off, the first never does
+            *state
+                .metadata_mut::<BanditScheduledMutatorMetadata>()
+                .unwrap() = BanditScheduledMutatorMetadata {
+                produced: vec![1000, 1000],
+                kept: vec![0, 300],
+            };
+            let picked = (0..1000)
+                .filter(|_| mutator.schedule(&mut state, &input).0 == 1)
+                .count();
+            assert!(picked > 900);
+
+            // Statistics for another number of mutations are an error, not an out of bounds access
+            *state
+                .metadata_mut::<BanditScheduledMutatorMetadata>()
+                .unwrap() = BanditScheduledMutatorMetadata::new(1);
+            mutator.mutate(&mut state, &mut input).unwrap_err();
+            mutator.post_exec(&mut state, None).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_bandit_scheduled_mutator_arguments() {
+        let mut state = test_std_state::<BytesInput>();
+        BanditScheduledMutator::<BytesInput, _, _>::new(&mut state, (), BanditPolicy::Ucb1)
+            .unwrap_err();
+        BanditScheduledMutator::<BytesInput, _, _>::new(
+            &mut state,
+            tuple_list!(BitFlipMutator::new()),
+            BanditPolicy::Ucb1,
+        )
+        .unwrap()
+        .with_max_stack_pow(0)
+        .unwrap_err();
+    }
+}
@@ -0,0 +1,120 @@
+//! The [`ConvertingMutator`] applies a mutator for another input type, converting through an [`InputConverterRegistry`].
+
+use alloc::{borrow::Cow, rc::Rc, string::String};
+use core::{cell::RefCell, fmt, marker::PhantomData};
+
+use libafl_bolts::{Error, Named};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{conversion::InputConverterRegistry, Input},
+    mutators::{MutationResult, Mutator},
+};
+
+/// Converts the input to `I2`, applies `mutator`, and converts the result back.
+///
+/// Both conversions are looked up in the shared `registry` at runtime, so the same stage can mix mutators
+/// for several representations. If the mutator skips, the input stays as it was, without converting it back.
+pub struct ConvertingMutator<I2, M> {
+    mutator: M,
+    registry: Rc<RefCell<InputConverterRegistry>>,
+    name: Cow<'static, str>,
+    phantom: PhantomData<I2>,
+}
+
+impl<I2, M> fmt::Debug for ConvertingMutator<I2, M>
+where
+    M: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConvertingMutator")
+            .field("mutator", &self.mutator)
+            .field("registry", &self.registry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I2, M> ConvertingMutator<I2, M>
+where
+    M: Named,
+{
+    /// Creates a new [`ConvertingMutator`], applying `mutator` to inputs converted with `registry`
+    pub fn new(mutator: M, registry: Rc<RefCell<InputConverterRegistry>>) -> Self {
+        let name = Cow::Owned(String::from("Converting") + mutator.name());
+        Self {
+            mutator,
+            registry,
+            name,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I2, M> Named for ConvertingMutator<I2, M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, I2, M, S> Mutator<I, S> for ConvertingMutator<I2, M>
+where
+    I: Input + 'static,
+    I2: Input + 'static,
+    M: Mutator<I2, S>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let mut converted: I2 = self.registry.borrow_mut().convert(input.clone())?;
+        if self.mutator.mutate(state, &mut converted)? == MutationResult::Skipped {
+            return Ok(MutationResult::Skipped);
+        }
+        *input = self.registry.borrow_mut().convert(converted)?;
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.mutator.post_exec(state, new_corpus_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, rc::Rc};
+    use core::cell::RefCell;
+
+    use crate::{
+        inputs::{
+            conversion::InputConverterRegistry, BytesInput, ClosureInputConverter, EncodedInput,
+            HasMutatorBytes,
+        },
+        mutators::{conversion::ConvertingMutator, EncodedRandMutator, MutationResult, Mutator},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_converting_mutator() {
+        let registry = InputConverterRegistry::new()
+            .with_converter(ClosureInputConverter::<BytesInput, EncodedInput>::new(
+                Box::new(|input| {
+                    Ok(EncodedInput::new(
+                        input.bytes().iter().map(|b| u32::from(*b)).collect(),
+                    ))
+                }),
+            ))
+            .with_converter(ClosureInputConverter::<EncodedInput, BytesInput>::new(
+                Box::new(|input| {
+                    Ok(BytesInput::new(
+                        input.codes().iter().map(|code| *code as u8).collect(),
+                    ))
+                }),
+            ));
+        let registry = Rc::new(RefCell::new(registry));
+
+        let mut state = test_std_state::<BytesInput>();
+        let mut mutator = ConvertingMutator::<EncodedInput, _>::new(EncodedRandMutator::new(), registry);
+        let mut input = BytesInput::new(vec![1, 2, 3, 4]);
+        while mutator.mutate(&mut state, &mut input).unwrap() == MutationResult::Skipped {}
+        assert_eq!(input.bytes().len(), 4);
+        assert_ne!(input.bytes(), &[1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,343 @@
+//! Crossover and splice mutators that pick their donor by coverage, instead of at random.
+//!
+//! The coverage of each testcase is summarized in a [`CoverageMinHashMetadata`], a minhash over the
+//! indexes of its [`MapIndexesMetadata`], so the map feedback has to track indexes.
+//! Donors are drawn from a handful of random candidates, and the one whose coverage is the most
+//! different from (or the most similar to) the current testcase wins.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::marker::PhantomData;
+
+use libafl_bolts::{impl_serdeany, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    feedbacks::MapIndexesMetadata,
+    inputs::HasMutatorBytes,
+    mutators::{
+        CrossoverInsertMutator, CrossoverReplaceMutator, MutationResult, Mutator, SpliceMutator,
+    },
+    random_corpus_id_with_disabled,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error, HasMetadata,
+};
+
+/// The number of hashes in a [`CoverageMinHashMetadata`]
+pub const COVERAGE_MINHASH_SIZE: usize = 32;
+
+/// The default number of random candidates a [`CoverageDonorSelector`] compares
+pub const DONOR_DEFAULT_CANDIDATES: usize = 8;
+
+/// A minhash signature of the map indexes covered by a testcase
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct CoverageMinHashMetadata {
+    /// The minimum hash of all indexes, for each of the hash functions
+    pub signature: Vec<u64>,
+}
+
+impl_serdeany!(CoverageMinHashMetadata);
+
+/// Murmur3's 64 bit finalizer
+#[inline]
+fn fmix64(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
+
+impl CoverageMinHashMetadata {
+    /// Computes the signature of the given covered map indexes
+    #[must_use]
+    pub fn from_indexes(indexes: &[usize]) -> Self {
+        let signature = (0..COVERAGE_MINHASH_SIZE as u64)
+            .map(|i| {
+                let seed = (i + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                indexes
+                    .iter()
+                    .map(|idx| fmix64(*idx as u64 ^ seed))
+                    .min()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+        Self { signature }
+    }
+
+    /// An estimate of the jaccard similarity of the coverage, from 0 (disjoint) to 1 (identical)
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn similarity(&self, other: &Self) -> f64 {
+        let equal = self
+            .signature
+            .iter()
+            .zip(&other.signature)
+            .filter(|(a, b)| a == b)
+            .count();
+        equal as f64 / self.signature.len().max(1) as f64
+    }
+}
+
+/// Which donor a [`CoverageDonorSelector`] prefers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DonorSimilarity {
+    /// The donor covering the most different edges, to combine unrelated behaviour
+    #[default]
+    MostDifferent,
+    /// The donor covering the most similar edges, to recombine closely related inputs
+    MostSimilar,
+}
+
+/// Picks donor corpus entries for crossover by the similarity of their coverage to the current testcase
+#[derive(Debug, Clone, Copy)]
+pub struct CoverageDonorSelector {
+    similarity: DonorSimilarity,
+    candidates: usize,
+}
+
+impl Default for CoverageDonorSelector {
+    fn default() -> Self {
+        Self::new(DonorSimilarity::default())
+    }
+}
+
+impl CoverageDonorSelector {
+    /// Creates a new [`CoverageDonorSelector`] preferring donors by `similarity`
+    #[must_use]
+    pub fn new(similarity: DonorSimilarity) -> Self {
+        Self {
+            similarity,
+            candidates: DONOR_DEFAULT_CANDIDATES,
+        }
+    }
+
+    /// Sets the number of random candidates compared for each donor
+    #[must_use]
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+
+    /// Gets the [`CoverageMinHashMetadata`] of the corpus entry `id`, computing and storing it on first use
+    pub fn signature<S>(state: &S, id: CorpusId) -> Result<Option<CoverageMinHashMetadata>, Error>
+    where
+        S: HasCorpus,
+    {
+        let mut testcase = state.corpus().get_from_all(id)?.borrow_mut();
+        if let Some(meta) = testcase.metadata_map().get::<CoverageMinHashMetadata>() {
+            return Ok(Some(meta.clone()));
+        }
+        let Some(indexes) = testcase.metadata_map().get::<MapIndexesMetadata>() else {
+            return Ok(None);
+        };
+        let meta = CoverageMinHashMetadata::from_indexes(indexes);
+        testcase.add_metadata(meta.clone());
+        Ok(Some(meta))
+    }
+
+    /// Selects a donor other than the current testcase, if there is any.
+    ///
+    /// Falls back to a random donor if the current testcase, or all candidates, lack coverage indexes.
+    pub fn select<S>(&self, state: &mut S) -> Result<Option<CorpusId>, Error>
+    where
+        S: HasCorpus + HasRand,
+    {
+        let current = *state.corpus().current();
+        let current_signature = match current {
+            Some(cur) => Self::signature(state, cur)?,
+            None => None,
+        };
+
+        let mut fallback = None;
+        let mut best: Option<(CorpusId, f64)> = None;
+        for _ in 0..self.candidates {
+            let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+            // We don't want to use the testcase we're already using for splicing
+            if Some(id) == current {
+                continue;
+            }
+            fallback.get_or_insert(id);
+
+            let Some(current_signature) = &current_signature else {
+                break;
+            };
+            let Some(signature) = Self::signature(state, id)? else {
+                continue;
+            };
+            let score = match self.similarity {
+                DonorSimilarity::MostDifferent => -current_signature.similarity(&signature),
+                DonorSimilarity::MostSimilar => current_signature.similarity(&signature),
+            };
+            if best.map_or(true, |(_, best_score)| score > best_score) {
+                best = Some((id, score));
+            }
+        }
+        Ok(best.map(|(id, _)| id).or(fallback))
+    }
+}
+
+/// Splice mutation for inputs with a bytes vector, with a donor picked by a [`CoverageDonorSelector`]
+#[derive(Debug, Default)]
+pub struct CoverageSpliceMutator {
+    selector: CoverageDonorSelector,
+}
+
+impl<S> Mutator<S::Input, S> for CoverageSpliceMutator
+where
+    S: HasCorpus + HasRand,
+    S::Input: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut S::Input) -> Result<MutationResult, Error> {
+        match self.selector.select(state)? {
+            Some(id) => SpliceMutator::splice_from(state, input, id),
+            None => Ok(MutationResult::Skipped),
+        }
+    }
+}
+
+impl Named for CoverageSpliceMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CoverageSpliceMutator");
+        &NAME
+    }
+}
+
+impl CoverageSpliceMutator {
+    /// Creates a new [`CoverageSpliceMutator`].
+    #[must_use]
+    pub fn new(selector: CoverageDonorSelector) -> Self {
+        Self { selector }
+    }
+}
+
+/// Crossover insert mutation for inputs with a bytes vector, with a donor picked by a [`CoverageDonorSelector`]
+#[derive(Debug, Default)]
+pub struct CoverageCrossoverInsertMutator<I> {
+    selector: CoverageDonorSelector,
+    phantom: PhantomData<I>,
+}
+
+impl<I, S> Mutator<I, S> for CoverageCrossoverInsertMutator<I>
+where
+    S: HasCorpus + HasRand + HasMaxSize,
+    S::Input: HasMutatorBytes,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        if input.bytes().len() >= state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        match self.selector.select(state)? {
+            Some(id) => CrossoverInsertMutator::crossover_insert_from(state, input, id),
+            None => Ok(MutationResult::Skipped),
+        }
+    }
+}
+
+impl<I> Named for CoverageCrossoverInsertMutator<I> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CoverageCrossoverInsertMutator");
+        &NAME
+    }
+}
+
+impl<I> CoverageCrossoverInsertMutator<I> {
+    /// Creates a new [`CoverageCrossoverInsertMutator`].
+    #[must_use]
+    pub fn new(selector: CoverageDonorSelector) -> Self {
+        Self {
+            selector,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Crossover replace mutation for inputs with a bytes vector, with a donor picked by a [`CoverageDonorSelector`]
+#[derive(Debug, Default)]
+pub struct CoverageCrossoverReplaceMutator<I> {
+    selector: CoverageDonorSelector,
+    phantom: PhantomData<I>,
+}
+
+impl<I, S> Mutator<I, S> for CoverageCrossoverReplaceMutator<I>
+where
+    S: HasCorpus + HasRand,
+    S::Input: HasMutatorBytes,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        if input.bytes().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        match self.selector.select(state)? {
+            Some(id) => CrossoverReplaceMutator::crossover_replace_from(state, input, id),
+            None => Ok(MutationResult::Skipped),
+        }
+    }
+}
+
+impl<I> Named for CoverageCrossoverReplaceMutator<I> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CoverageCrossoverReplaceMutator");
+        &NAME
+    }
+}
+
+impl<I> CoverageCrossoverReplaceMutator<I> {
+    /// Creates a new [`CoverageCrossoverReplaceMutator`].
+    #[must_use]
+    pub fn new(selector: CoverageDonorSelector) -> Self {
+        Self {
+            selector,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        corpus::{Corpus, Testcase},
+        feedbacks::MapIndexesMetadata,
+        inputs::BytesInput,
+        mutators::donor::{CoverageDonorSelector, CoverageMinHashMetadata, DonorSimilarity},
+        state::{test::test_std_state, HasCorpus},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_coverage_donor_selector() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut ids = vec![];
+        for indexes in [0..100, 0..90, 200..300] {
+            let mut testcase = Testcase::new(BytesInput::new(vec![0; 16]));
+            testcase.add_metadata(MapIndexesMetadata::new(indexes.collect()));
+            ids.push(state.corpus_mut().add(testcase).unwrap());
+        }
+        *state.corpus_mut().current_mut() = Some(ids[0]);
+
+        let close = CoverageDonorSelector::signature(&state, ids[1])
+            .unwrap()
+            .unwrap();
+        let far = CoverageMinHashMetadata::from_indexes(&(200..300).collect::<Vec<_>>());
+        let own = CoverageDonorSelector::signature(&state, ids[0])
+            .unwrap()
+            .unwrap();
+        assert!(own.similarity(&close) > own.similarity(&far));
+
+        let different =
+            CoverageDonorSelector::new(DonorSimilarity::MostDifferent).with_candidates(32);
+        let similar = CoverageDonorSelector::new(DonorSimilarity::MostSimilar).with_candidates(32);
+        for _ in 0..16 {
+            assert_eq!(different.select(&mut state).unwrap(), Some(ids[2]));
+            assert_eq!(similar.select(&mut state).unwrap(), Some(ids[1]));
+        }
+    }
+}
@@ -1,37 +1,150 @@
 //! Mutations for [`EncodedInput`]s
 //!
+//! The mutations that grow an input respect [`HasMaxSize::max_size`], counted in codes by default,
+//! or in the bytes of the serialized input, see [`MaxSizeMode`].
+//! The decoded input is not bounded: the mutators never see the [`InputDecoder`](crate::inputs::InputDecoder).
 use alloc::{borrow::Cow, vec::Vec};
-use core::cmp::{max, min};
+use core::{
+    cmp::{max, min, Ordering},
+    iter,
+    ops::Range,
+};
 
 use libafl_bolts::{
+    impl_serdeany,
     rands::Rand,
-    tuples::{tuple_list, tuple_list_type},
+    tuples::{tuple_list, tuple_list_type, Merge},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     corpus::Corpus,
-    inputs::{EncodedInput, UsesInput},
+    inputs::{EncodedInput, TokenInputEncoderDecoder, UsesInput},
     mutators::{
         mutations::{buffer_copy, buffer_self_copy, ARITH_MAX},
         MutationResult, Mutator, Named,
     },
     random_corpus_id_with_disabled,
     state::{HasCorpus, HasMaxSize, HasRand},
-    Error,
+    Error, HasMetadata,
 };
 
+/// How the mutations that can grow an [`EncodedInput`] measure it against [`HasMaxSize::max_size`].
+///
+/// In [`MaxSizeMode::SerializedBytes`], mutations changing the value of a code grow the input as well,
+/// since bigger codes take more bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MaxSizeMode {
+    /// The number of codes
+    #[default]
+    Codes,
+    /// The bytes of the input serialized with [`postcard`], as it is stored on disk and sent to other clients
+    SerializedBytes,
+}
+
+/// The bytes of a [`postcard`] varint holding `value`
+fn varint_len(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()).div_ceil(7).max(1) as usize
+}
+
+/// The bytes of the serialized `codes`, without the length prefix
+fn codes_bytes(codes: &[u32]) -> usize {
+    codes.iter().map(|code| varint_len(*code as usize)).sum()
+}
+
+impl MaxSizeMode {
+    /// The size of `codes`, in this mode
+    #[must_use]
+    pub fn size_of(self, codes: &[u32]) -> usize {
+        match self {
+            Self::Codes => codes.len(),
+            Self::SerializedBytes => varint_len(codes.len()) + codes_bytes(codes),
+        }
+    }
+
+    /// If `codes`, with the `removed` range replaced by `inserted`, is at most `max_size` in this mode.
+    /// Replacements that do not grow the input always fit, even if it is already too big.
+    #[must_use]
+    pub fn fits(
+        self,
+        codes: &[u32],
+        removed: Range<usize>,
+        inserted: &[u32],
+        max_size: usize,
+    ) -> bool {
+        let len = codes.len() - removed.len() + inserted.len();
+        let (old_size, new_size) = match self {
+            Self::Codes => (codes.len(), len),
+            Self::SerializedBytes => {
+                let bytes = codes_bytes(codes);
+                (
+                    varint_len(codes.len()) + bytes,
+                    varint_len(len) + bytes - codes_bytes(&codes[removed]) + codes_bytes(inserted),
+                )
+            }
+        };
+        new_size <= max(max_size, old_size)
+    }
+
+    /// How many of the first codes of `inserted` can be inserted into `codes`, staying at most `max_size` in this mode
+    fn fitting_len<It>(self, codes: &[u32], inserted: It, max_size: usize) -> usize
+    where
+        It: ExactSizeIterator<Item = u32>,
+    {
+        match self {
+            Self::Codes => min(inserted.len(), max_size.saturating_sub(codes.len())),
+            Self::SerializedBytes => {
+                // Reserve the longest length prefix, inserting fewer codes never makes it longer
+                let used = varint_len(codes.len() + inserted.len()) + codes_bytes(codes);
+                let mut budget = max_size.saturating_sub(used);
+                inserted
+                    .take_while(|code| {
+                        let len = varint_len(*code as usize);
+                        let fits = len <= budget;
+                        budget = budget.saturating_sub(len);
+                        fits
+                    })
+                    .count()
+            }
+        }
+    }
+}
+
+/// Sets the code at `idx` to `code`, unless that makes the input bigger than `max_size` in the given [`MaxSizeMode`]
+fn replace_code(
+    max_size_mode: MaxSizeMode,
+    input: &mut EncodedInput,
+    idx: usize,
+    code: u32,
+    max_size: usize,
+) -> MutationResult {
+    if !max_size_mode.fits(input.codes(), idx..idx + 1, &[code], max_size) {
+        return MutationResult::Skipped;
+    }
+    input.codes_mut()[idx] = code;
+    MutationResult::Mutated
+}
+
 /// Set a code in the input as a random value
 #[derive(Debug, Default)]
-pub struct EncodedRandMutator;
+pub struct EncodedRandMutator {
+    max_size_mode: MaxSizeMode,
+}
 
-impl<S: HasRand> Mutator<EncodedInput, S> for EncodedRandMutator {
+impl<S: HasRand + HasMaxSize> Mutator<EncodedInput, S> for EncodedRandMutator {
     fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
         if input.codes().is_empty() {
             Ok(MutationResult::Skipped)
         } else {
-            let val = state.rand_mut().choose(input.codes_mut()).unwrap();
-            *val = state.rand_mut().next() as u32;
-            Ok(MutationResult::Mutated)
+            let idx = state.rand_mut().below(input.codes().len());
+            let code = state.rand_mut().next() as u32;
+            Ok(replace_code(
+                self.max_size_mode,
+                input,
+                idx,
+                code,
+                state.max_size(),
+            ))
         }
     }
 }
@@ -47,22 +160,37 @@ impl EncodedRandMutator {
     /// Creates a new [`EncodedRandMutator`].
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
     }
 }
 
 /// Increment a random code in the input
 #[derive(Debug, Default)]
-pub struct EncodedIncMutator;
+pub struct EncodedIncMutator {
+    max_size_mode: MaxSizeMode,
+}
 
-impl<S: HasRand> Mutator<EncodedInput, S> for EncodedIncMutator {
+impl<S: HasRand + HasMaxSize> Mutator<EncodedInput, S> for EncodedIncMutator {
     fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
         if input.codes().is_empty() {
             Ok(MutationResult::Skipped)
         } else {
-            let val = state.rand_mut().choose(input.codes_mut()).unwrap();
-            *val = val.wrapping_add(1);
-            Ok(MutationResult::Mutated)
+            let idx = state.rand_mut().below(input.codes().len());
+            let code = input.codes()[idx].wrapping_add(1);
+            Ok(replace_code(
+                self.max_size_mode,
+                input,
+                idx,
+                code,
+                state.max_size(),
+            ))
         }
     }
 }
@@ -78,22 +206,37 @@ impl EncodedIncMutator {
     /// Creates a new [`EncodedIncMutator`].
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
     }
 }
 
 /// Decrement a random code in the input
 #[derive(Debug, Default)]
-pub struct EncodedDecMutator;
+pub struct EncodedDecMutator {
+    max_size_mode: MaxSizeMode,
+}
 
-impl<S: HasRand> Mutator<EncodedInput, S> for EncodedDecMutator {
+impl<S: HasRand + HasMaxSize> Mutator<EncodedInput, S> for EncodedDecMutator {
     fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
         if input.codes().is_empty() {
             Ok(MutationResult::Skipped)
         } else {
-            let val = state.rand_mut().choose(input.codes_mut()).unwrap();
-            *val = val.wrapping_sub(1);
-            Ok(MutationResult::Mutated)
+            let idx = state.rand_mut().below(input.codes().len());
+            let code = input.codes()[idx].wrapping_sub(1);
+            Ok(replace_code(
+                self.max_size_mode,
+                input,
+                idx,
+                code,
+                state.max_size(),
+            ))
         }
     }
 }
@@ -109,26 +252,42 @@ impl EncodedDecMutator {
     /// Creates a new [`EncodedDecMutator`].
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
     }
 }
 
 /// Adds or subtracts a random value up to `ARITH_MAX` to a random place in the codes [`Vec`].
 #[derive(Debug, Default)]
-pub struct EncodedAddMutator;
+pub struct EncodedAddMutator {
+    max_size_mode: MaxSizeMode,
+}
 
-impl<S: HasRand> Mutator<EncodedInput, S> for EncodedAddMutator {
+impl<S: HasRand + HasMaxSize> Mutator<EncodedInput, S> for EncodedAddMutator {
     fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
         if input.codes().is_empty() {
             Ok(MutationResult::Skipped)
         } else {
-            let val = state.rand_mut().choose(input.codes_mut()).unwrap();
+            let idx = state.rand_mut().below(input.codes().len());
+            let val = input.codes()[idx];
             let num = 1 + state.rand_mut().below(ARITH_MAX) as u32;
-            *val = match state.rand_mut().below(2) {
+            let code = match state.rand_mut().below(2) {
                 0 => val.wrapping_add(num),
                 _ => val.wrapping_sub(num),
             };
-            Ok(MutationResult::Mutated)
+            Ok(replace_code(
+                self.max_size_mode,
+                input,
+                idx,
+                code,
+                state.max_size(),
+            ))
         }
     }
 }
@@ -144,7 +303,14 @@ impl EncodedAddMutator {
     /// Creates a new [`EncodedAddMutator`].
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
     }
 }
 
@@ -186,6 +352,7 @@ impl EncodedDeleteMutator {
 #[derive(Debug, Default)]
 pub struct EncodedInsertCopyMutator {
     tmp_buf: Vec<u32>,
+    max_size_mode: MaxSizeMode,
 }
 
 impl<S> Mutator<EncodedInput, S> for EncodedInsertCopyMutator
@@ -199,30 +366,28 @@ where
             return Ok(MutationResult::Skipped);
         }
         let off = state.rand_mut().below(size + 1);
-        let mut len = 1 + state.rand_mut().below(min(16, size));
-
-        if size + len > max_size {
-            if max_size > size {
-                len = max_size - size;
-            } else {
-                return Ok(MutationResult::Skipped);
-            }
-        }
-
+        let len = 1 + state.rand_mut().below(min(16, size));
         let from = if size == len {
             0
         } else {
             state.rand_mut().below(size - len)
         };
 
+        let len = self.max_size_mode.fitting_len(
+            input.codes(),
+            input.codes()[from..from + len].iter().copied(),
+            max_size,
+        );
+        if len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
         input.codes_mut().resize(size + len, 0);
         self.tmp_buf.resize(len, 0);
-        unsafe {
-            buffer_copy(&mut self.tmp_buf, input.codes(), from, 0, len);
+        buffer_copy(&mut self.tmp_buf, input.codes(), from, 0, len);
 
-            buffer_self_copy(input.codes_mut(), off, off + len, size - off);
-            buffer_copy(input.codes_mut(), &self.tmp_buf, 0, off, len);
-        };
+        buffer_self_copy(input.codes_mut(), off, off + len, size - off);
+        buffer_copy(input.codes_mut(), &self.tmp_buf, 0, off, len);
 
         Ok(MutationResult::Mutated)
     }
@@ -241,26 +406,48 @@ impl EncodedInsertCopyMutator {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
 }
 
-/// Codes copy mutation for encoded inputs
-#[derive(Debug, Default)]
-pub struct EncodedCopyMutator;
+/// Codes copy mutation for encoded inputs, overwriting a range of codes with another range of the same input
+#[derive(Debug)]
+pub struct EncodedCopyMutator {
+    max_len: usize,
+    max_size_mode: MaxSizeMode,
+}
 
-impl<S: HasRand> Mutator<EncodedInput, S> for EncodedCopyMutator {
+impl<S: HasRand + HasMaxSize> Mutator<EncodedInput, S> for EncodedCopyMutator {
     fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
         let size = input.codes().len();
-        if size <= 1 {
+        if size <= 1 || self.max_len == 0 {
             return Ok(MutationResult::Skipped);
         }
 
         let from = state.rand_mut().below(size);
         let to = state.rand_mut().below(size);
-        let len = 1 + state.rand_mut().below(size - max(from, to));
-
-        unsafe {
-            buffer_self_copy(input.codes_mut(), from, to, len);
+        if from == to {
+            return Ok(MutationResult::Skipped);
+        }
+        let len = 1 + state
+            .rand_mut()
+            .below(min(self.max_len, size - max(from, to)));
+
+        let codes = input.codes();
+        if !self.max_size_mode.fits(
+            codes,
+            to..to + len,
+            &codes[from..from + len],
+            state.max_size(),
+        ) {
+            return Ok(MutationResult::Skipped);
         }
+        buffer_self_copy(input.codes_mut(), from, to, len);
 
         Ok(MutationResult::Mutated)
     }
@@ -273,17 +460,43 @@ impl Named for EncodedCopyMutator {
     }
 }
 
+impl Default for EncodedCopyMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EncodedCopyMutator {
-    /// Creates a new [`EncodedCopyMutator`].
+    /// Creates a new [`EncodedCopyMutator`], which may overwrite up to the rest of the input.
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::with_max_len(usize::MAX)
+    }
+
+    /// Creates a new [`EncodedCopyMutator`], overwriting at most `max_len` codes at once.
+    #[must_use]
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            max_len,
+            max_size_mode: MaxSizeMode::Codes,
+        }
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
     }
 }
 
-/// Crossover insert mutation for encoded inputs
+/// Crossover insert mutation for encoded inputs, copying a run of codes from another corpus entry
+/// and inserting it at a random position, the token analogue of [`crate::mutators::CrossoverInsertMutator`].
+/// It does not need any brackets in the codes.
 #[derive(Debug, Default)]
-pub struct EncodedCrossoverInsertMutator;
+pub struct EncodedCrossoverInsertMutator {
+    max_size_mode: MaxSizeMode,
+}
 
 impl<S> Mutator<S::Input, S> for EncodedCrossoverInsertMutator
 where
@@ -311,27 +524,27 @@ where
 
         let max_size = state.max_size();
         let from = state.rand_mut().below(other_size);
-        let to = state.rand_mut().below(size);
-        let mut len = 1 + state.rand_mut().below(other_size - from);
-
-        if size + len > max_size {
-            if max_size > size {
-                len = max_size - size;
-            } else {
-                return Ok(MutationResult::Skipped);
-            }
-        }
+        // Inserting after the last code is fine, and the only option for empty inputs
+        let to = state.rand_mut().below(size + 1);
+        let len = 1 + state.rand_mut().below(other_size - from);
 
         let other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
         // no need to `load_input` again -  we did that above already.
         let other = other_testcase.input().as_ref().unwrap();
 
-        input.codes_mut().resize(size + len, 0);
-        unsafe {
-            buffer_self_copy(input.codes_mut(), to, to + len, size - to);
-            buffer_copy(input.codes_mut(), other.codes(), from, to, len);
+        let len = self.max_size_mode.fitting_len(
+            input.codes(),
+            other.codes()[from..from + len].iter().copied(),
+            max_size,
+        );
+        if len == 0 {
+            return Ok(MutationResult::Skipped);
         }
 
+        input.codes_mut().resize(size + len, 0);
+        buffer_self_copy(input.codes_mut(), to, to + len, size - to);
+        buffer_copy(input.codes_mut(), other.codes(), from, to, len);
+
         Ok(MutationResult::Mutated)
     }
 }
@@ -347,17 +560,26 @@ impl EncodedCrossoverInsertMutator {
     /// Creates a new [`EncodedCrossoverInsertMutator`].
     #[must_use]
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
     }
 }
 
 /// Crossover replace mutation for encoded inputs
 #[derive(Debug, Default)]
-pub struct EncodedCrossoverReplaceMutator;
+pub struct EncodedCrossoverReplaceMutator {
+    max_size_mode: MaxSizeMode,
+}
 
 impl<S> Mutator<S::Input, S> for EncodedCrossoverReplaceMutator
 where
-    S: UsesInput<Input = EncodedInput> + HasRand + HasCorpus,
+    S: UsesInput<Input = EncodedInput> + HasRand + HasCorpus + HasMaxSize,
 {
     fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
         let size = input.codes().len();
@@ -391,9 +613,15 @@ where
         // no need to load the input again, it'll already be present at this point.
         let other = other_testcase.input().as_ref().unwrap();
 
-        unsafe {
-            buffer_copy(input.codes_mut(), other.codes(), from, to, len);
+        if !self.max_size_mode.fits(
+            input.codes(),
+            to..to + len,
+            &other.codes()[from..from + len],
+            state.max_size(),
+        ) {
+            return Ok(MutationResult::Skipped);
         }
+        buffer_copy(input.codes_mut(), other.codes(), from, to, len);
 
         Ok(MutationResult::Mutated)
     }
@@ -409,33 +637,1319 @@ impl Named for EncodedCrossoverReplaceMutator {
 impl EncodedCrossoverReplaceMutator {
     /// Creates a new [`EncodedCrossoverReplaceMutator`].
     #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Two-point crossover mutation for encoded inputs, replacing a range of codes
+/// with a range of another corpus entry, of a possibly different length.
+/// Unlike [`EncodedRegionCrossoverMutator`], it does not need any brackets in the codes.
+#[derive(Debug, Default)]
+pub struct EncodedTwoPointCrossoverMutator {
+    max_size_mode: MaxSizeMode,
+}
+
+impl<S> Mutator<S::Input, S> for EncodedTwoPointCrossoverMutator
+where
+    S: UsesInput<Input = EncodedInput> + HasRand + HasCorpus + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+        // We don't want to use the testcase we're already using for splicing
+        if let Some(cur) = state.corpus().current() {
+            if id == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let other_size = {
+            let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+            other_testcase.load_input(state.corpus())?.codes().len()
+        };
+        if other_size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let to = state.rand_mut().below(size);
+        let to_len = 1 + state.rand_mut().below(size - to);
+        let from = state.rand_mut().below(other_size);
+        let from_len = 1 + state.rand_mut().below(other_size - from);
+
+        let other_testcase = state.corpus().get_from_all(id)?.borrow();
+        // no need to load the input again, it'll already be present at this point.
+        let other_codes = other_testcase.input().as_ref().unwrap().codes();
+
+        if !self.max_size_mode.fits(
+            input.codes(),
+            to..to + to_len,
+            &other_codes[from..from + from_len],
+            state.max_size(),
+        ) || input.codes()[to..to + to_len] == other_codes[from..from + from_len]
+        {
+            return Ok(MutationResult::Skipped);
+        }
+
+        input.codes_mut().splice(
+            to..to + to_len,
+            other_codes[from..from + from_len].iter().copied(),
+        );
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedTwoPointCrossoverMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedTwoPointCrossoverMutator");
+        &NAME
+    }
+}
+
+impl EncodedTwoPointCrossoverMutator {
+    /// Creates a new [`EncodedTwoPointCrossoverMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Duplicate mutation for encoded inputs, repeating a range of codes right after itself
+#[derive(Debug, Default)]
+pub struct EncodedDuplicateMutator {
+    max_size_mode: MaxSizeMode,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedDuplicateMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let off = state.rand_mut().below(size);
+        let len = 1 + state.rand_mut().below(min(16, size - off));
+        let codes = input.codes();
+        if !self
+            .max_size_mode
+            .fits(codes, 0..0, &codes[off..off + len], state.max_size())
+        {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let codes = input.codes_mut();
+        codes.extend_from_within(off..off + len);
+        codes[off + len..].rotate_right(len);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedDuplicateMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedDuplicateMutator");
+        &NAME
+    }
+}
+
+impl EncodedDuplicateMutator {
+    /// Creates a new [`EncodedDuplicateMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Move mutation for encoded inputs, cutting a range of codes and inserting it somewhere else
+#[derive(Debug, Default)]
+pub struct EncodedMoveMutator;
+
+impl<S: HasRand> Mutator<EncodedInput, S> for EncodedMoveMutator {
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let from = state.rand_mut().below(size);
+        let len = 1 + state.rand_mut().below(min(16, size - from));
+        // The new position, in the codes without the moved range
+        let to = state.rand_mut().below(size - len + 1);
+
+        let codes = input.codes_mut();
+        match to.cmp(&from) {
+            Ordering::Less => codes[to..from + len].rotate_right(len),
+            Ordering::Greater => codes[from..to + len].rotate_left(len),
+            Ordering::Equal => return Ok(MutationResult::Skipped),
+        }
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedMoveMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedMoveMutator");
+        &NAME
+    }
+}
+
+impl EncodedMoveMutator {
+    /// Creates a new [`EncodedMoveMutator`].
+    #[must_use]
     pub fn new() -> Self {
         Self
     }
 }
 
-/// Get the mutations that compose the encoded mutator
-#[must_use]
-pub fn encoded_mutations() -> tuple_list_type!(
-    EncodedRandMutator,
-    EncodedIncMutator,
-    EncodedDecMutator,
-    EncodedAddMutator,
-    EncodedDeleteMutator,
-    EncodedInsertCopyMutator,
-    EncodedCopyMutator,
-    EncodedCrossoverInsertMutator,
-    EncodedCrossoverReplaceMutator,
-) {
-    tuple_list!(
-        EncodedRandMutator::new(),
-        EncodedIncMutator::new(),
-        EncodedDecMutator::new(),
-        EncodedAddMutator::new(),
-        EncodedDeleteMutator::new(),
-        EncodedInsertCopyMutator::new(),
-        EncodedCopyMutator::new(),
-        EncodedCrossoverInsertMutator::new(),
-        EncodedCrossoverReplaceMutator::new(),
-    )
+/// The default maximum amount of copies inserted by an [`EncodedRepeatMutator`]
+pub const DEFAULT_ENCODED_MAX_REPEAT: usize = 64;
+
+/// Repeat mutation for encoded inputs, inserting a random amount of copies of a single code right after it
+#[derive(Debug)]
+pub struct EncodedRepeatMutator {
+    max_repeat: usize,
+    max_size_mode: MaxSizeMode,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedRepeatMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let off = state.rand_mut().below(size);
+        let max_count = self.max_size_mode.fitting_len(
+            input.codes(),
+            iter::repeat_n(input.codes()[off], self.max_repeat),
+            state.max_size(),
+        );
+        if max_count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let count = 1 + state.rand_mut().below(max_count);
+
+        let codes = input.codes_mut();
+        codes.resize(size + count, codes[off]);
+        codes[off + 1..].rotate_right(count);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedRepeatMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedRepeatMutator");
+        &NAME
+    }
+}
+
+impl Default for EncodedRepeatMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EncodedRepeatMutator {
+    /// Creates a new [`EncodedRepeatMutator`], inserting up to [`DEFAULT_ENCODED_MAX_REPEAT`] copies.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_repeat(DEFAULT_ENCODED_MAX_REPEAT)
+    }
+
+    /// Creates a new [`EncodedRepeatMutator`], inserting up to `max_repeat` copies.
+    #[must_use]
+    pub fn with_max_repeat(max_repeat: usize) -> Self {
+        Self {
+            max_repeat,
+            max_size_mode: MaxSizeMode::Codes,
+        }
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Rotate mutation for encoded inputs, rotating a range of codes left or right by a random amount
+#[derive(Debug, Default)]
+pub struct EncodedRotateMutator;
+
+impl<S: HasRand> Mutator<EncodedInput, S> for EncodedRotateMutator {
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        // A range of at least two codes
+        let off = state.rand_mut().below(size - 1);
+        let len = 2 + state.rand_mut().below(size - off - 1);
+        let amount = 1 + state.rand_mut().below(len - 1);
+
+        let range = &mut input.codes_mut()[off..off + len];
+        if state.rand_mut().coinflip(0.5) {
+            range.rotate_left(amount);
+        } else {
+            range.rotate_right(amount);
+        }
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedRotateMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedRotateMutator");
+        &NAME
+    }
+}
+
+impl EncodedRotateMutator {
+    /// Creates a new [`EncodedRotateMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Swap mutation for encoded inputs, exchanging two codes at distinct positions
+#[derive(Debug, Default)]
+pub struct EncodedSwapMutator;
+
+impl<S: HasRand> Mutator<EncodedInput, S> for EncodedSwapMutator {
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let first = state.rand_mut().below(size);
+        let second = state.rand_mut().below(size);
+        let codes = input.codes_mut();
+        if codes[first] == codes[second] {
+            return Ok(MutationResult::Skipped);
+        }
+        codes.swap(first, second);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedSwapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedSwapMutator");
+        &NAME
+    }
+}
+
+impl EncodedSwapMutator {
+    /// Creates a new [`EncodedSwapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A state metadata holding the codes of matching brackets, like `(` and `)`,
+/// which delimit the regions used by the region mutators for [`EncodedInput`]s
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct EncodedBracketsMetadata {
+    /// The codes of the opening and the closing bracket of each pair
+    pub pairs: Vec<(u32, u32)>,
+}
+
+impl_serdeany!(EncodedBracketsMetadata);
+
+impl EncodedBracketsMetadata {
+    /// Creates a new [`EncodedBracketsMetadata`] from pairs of codes
+    #[must_use]
+    pub fn new(pairs: Vec<(u32, u32)>) -> Self {
+        Self { pairs }
+    }
+
+    /// Creates a new [`EncodedBracketsMetadata`] from pairs of tokens, like `[("(", ")"), ("{", "}")]`.
+    ///
+    /// Pairs with a token the `encoder` did not encode yet are left out, so encode the initial inputs first.
+    #[must_use]
+    pub fn from_tokens(encoder: &TokenInputEncoderDecoder, pairs: &[(&str, &str)]) -> Self {
+        Self {
+            pairs: pairs
+                .iter()
+                .filter_map(|(open, close)| Some((encoder.code_of(open)?, encoder.code_of(close)?)))
+                .collect(),
+        }
+    }
+
+    /// The balanced regions of `codes`, each including its brackets.
+    /// Brackets without a matching partner are ignored.
+    #[must_use]
+    pub fn regions(&self, codes: &[u32]) -> Vec<Range<usize>> {
+        let mut regions = vec![];
+        self.visit_regions(codes, &mut vec![], |region| {
+            regions.push(region);
+            true
+        });
+        regions
+    }
+
+    /// Call `f` with each balanced region of `codes`, in the order their closing brackets appear, until it returns `false`.
+    /// `open` is a scratch buffer for the open brackets, so repeated calls do not need to allocate.
+    pub fn visit_regions<F>(&self, codes: &[u32], open: &mut Vec<(usize, usize)>, mut f: F)
+    where
+        F: FnMut(Range<usize>) -> bool,
+    {
+        // The pair index and the position of each open bracket
+        open.clear();
+        for (pos, code) in codes.iter().enumerate() {
+            // Closing first, for pairs that use the same code twice, like quotes
+            if let Some(&(pair, start)) = open.last() {
+                if self.pairs[pair].1 == *code {
+                    open.pop();
+                    if !f(start..pos + 1) {
+                        return;
+                    }
+                    continue;
+                }
+            }
+            if let Some(pair) = self.pairs.iter().position(|(o, _)| o == code) {
+                open.push((pair, pos));
+            }
+        }
+    }
+
+    /// The balanced region of `codes` whose opening or closing bracket is at `idx`, including its brackets.
+    /// Nested pairs are skipped, so in `{ { } }` the first `{` matches the last `}`.
+    ///
+    /// Only the brackets of the same pair are counted, walking away from `idx` until the depth drops to zero,
+    /// so unlike [`Self::regions`], a region may contain mismatched brackets of other pairs.
+    /// For pairs using the same code twice, like quotes, every other occurrence opens a region.
+    #[must_use]
+    pub fn matched_region_at(&self, codes: &[u32], idx: usize) -> Option<Range<usize>> {
+        let code = *codes.get(idx)?;
+        let &(open, close) = self
+            .pairs
+            .iter()
+            .find(|(open, close)| *open == code || *close == code)?;
+
+        if open == close {
+            let opening = codes[..idx].iter().filter(|c| **c == code).count() % 2 == 0;
+            return if opening {
+                let len = codes[idx + 1..].iter().position(|c| *c == code)?;
+                Some(idx..idx + len + 2)
+            } else {
+                let start = codes[..idx].iter().rposition(|c| *c == code)?;
+                Some(start..idx + 1)
+            };
+        }
+
+        let mut depth = 0_usize;
+        if code == open {
+            for (pos, c) in codes.iter().enumerate().skip(idx) {
+                if *c == open {
+                    depth += 1;
+                } else if *c == close {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(idx..pos + 1);
+                    }
+                }
+            }
+        } else {
+            for (pos, c) in codes[..=idx].iter().enumerate().rev() {
+                if *c == close {
+                    depth += 1;
+                } else if *c == open {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos..idx + 1);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl EncodedBracketsMetadata {
+    /// The number of balanced regions of `codes` for which `filter` holds
+    fn count_regions_with<F>(
+        &self,
+        codes: &[u32],
+        open: &mut Vec<(usize, usize)>,
+        filter: F,
+    ) -> usize
+    where
+        F: Fn(&Range<usize>) -> bool,
+    {
+        let mut count = 0;
+        self.visit_regions(codes, open, |region| {
+            count += usize::from(filter(&region));
+            true
+        });
+        count
+    }
+
+    /// The `nth` balanced region of `codes` for which `filter` holds, in the order of [`Self::visit_regions`]
+    fn nth_region_with<F>(
+        &self,
+        codes: &[u32],
+        open: &mut Vec<(usize, usize)>,
+        mut nth: usize,
+        filter: F,
+    ) -> Option<Range<usize>>
+    where
+        F: Fn(&Range<usize>) -> bool,
+    {
+        let mut chosen = None;
+        self.visit_regions(codes, open, |region| {
+            if !filter(&region) {
+                return true;
+            }
+            if nth == 0 {
+                chosen = Some(region);
+                return false;
+            }
+            nth -= 1;
+            true
+        });
+        chosen
+    }
+}
+
+/// Picks a random bracketed region of `codes` for which `filter` holds, using the [`EncodedBracketsMetadata`] of the state.
+/// The regions are counted first and the chosen one is looked up again, so nothing but `open` is allocated.
+fn rand_region_with<S, F>(
+    state: &mut S,
+    codes: &[u32],
+    open: &mut Vec<(usize, usize)>,
+    filter: F,
+) -> Option<Range<usize>>
+where
+    S: HasRand + HasMetadata,
+    F: Fn(&Range<usize>) -> bool,
+{
+    let count = state
+        .metadata_map()
+        .get::<EncodedBracketsMetadata>()?
+        .count_regions_with(codes, open, &filter);
+    if count == 0 {
+        return None;
+    }
+
+    let nth = state.rand_mut().below(count);
+    state
+        .metadata_map()
+        .get::<EncodedBracketsMetadata>()?
+        .nth_region_with(codes, open, nth, filter)
+}
+
+/// Picks a random bracketed region of `codes`, using the [`EncodedBracketsMetadata`] of the state
+fn rand_region<S>(
+    state: &mut S,
+    codes: &[u32],
+    open: &mut Vec<(usize, usize)>,
+) -> Option<Range<usize>>
+where
+    S: HasRand + HasMetadata,
+{
+    rand_region_with(state, codes, open, |_| true)
+}
+
+/// Region delete mutation for encoded inputs, removing a bracketed region, see [`EncodedBracketsMetadata`]
+#[derive(Debug, Default)]
+pub struct EncodedRegionDeleteMutator {
+    /// Scratch buffer for the open brackets
+    open: Vec<(usize, usize)>,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedRegionDeleteMutator
+where
+    S: HasRand + HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let Some(region) = rand_region(state, input.codes(), &mut self.open) else {
+            return Ok(MutationResult::Skipped);
+        };
+        input.codes_mut().drain(region);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedRegionDeleteMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedRegionDeleteMutator");
+        &NAME
+    }
+}
+
+impl EncodedRegionDeleteMutator {
+    /// Creates a new [`EncodedRegionDeleteMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Region crossover mutation for encoded inputs, replacing a bracketed region with a bracketed region
+/// of another corpus entry, see [`EncodedBracketsMetadata`]
+#[derive(Debug, Default)]
+pub struct EncodedRegionCrossoverMutator {
+    /// Scratch buffer for the open brackets
+    open: Vec<(usize, usize)>,
+    max_size_mode: MaxSizeMode,
+}
+
+impl<S> Mutator<S::Input, S> for EncodedRegionCrossoverMutator
+where
+    S: UsesInput<Input = EncodedInput> + HasRand + HasCorpus + HasMaxSize + HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let Some(region) = rand_region(state, input.codes(), &mut self.open) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+        // We don't want to use the testcase we're already using for splicing
+        if let Some(cur) = state.corpus().current() {
+            if id == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let other_count = {
+            let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+            let other_codes = other_testcase.load_input(state.corpus())?.codes();
+            state
+                .metadata::<EncodedBracketsMetadata>()?
+                .count_regions_with(other_codes, &mut self.open, |_| true)
+        };
+        if other_count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let nth = state.rand_mut().below(other_count);
+
+        let other_testcase = state.corpus().get_from_all(id)?.borrow();
+        // no need to load the input again, it'll already be present at this point.
+        let other_codes = other_testcase.input().as_ref().unwrap().codes();
+        let Some(other_region) = state
+            .metadata::<EncodedBracketsMetadata>()?
+            .nth_region_with(other_codes, &mut self.open, nth, |_| true)
+        else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        if !self.max_size_mode.fits(
+            input.codes(),
+            region.clone(),
+            &other_codes[other_region.clone()],
+            state.max_size(),
+        ) || other_codes[other_region.clone()] == input.codes()[region.clone()]
+        {
+            return Ok(MutationResult::Skipped);
+        }
+
+        input
+            .codes_mut()
+            .splice(region, other_codes[other_region].iter().copied());
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedRegionCrossoverMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedRegionCrossoverMutator");
+        &NAME
+    }
+}
+
+impl EncodedRegionCrossoverMutator {
+    /// Creates a new [`EncodedRegionCrossoverMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Region swap mutation for encoded inputs, exchanging two bracketed regions which do not overlap,
+/// see [`EncodedBracketsMetadata`]
+#[derive(Debug, Default)]
+pub struct EncodedRegionSwapMutator {
+    /// Scratch buffer for the open brackets
+    open: Vec<(usize, usize)>,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedRegionSwapMutator
+where
+    S: HasRand + HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let Some(first) = rand_region(state, input.codes(), &mut self.open) else {
+            return Ok(MutationResult::Skipped);
+        };
+        // Regions nest, so only the ones entirely before or after the first can be swapped with it
+        let Some(second) = rand_region_with(state, input.codes(), &mut self.open, |region| {
+            region.end <= first.start || first.end <= region.start
+        }) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let (before, after) = if first.start < second.start {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let codes = input.codes_mut();
+        if codes[before.clone()] == codes[after.clone()] {
+            return Ok(MutationResult::Skipped);
+        }
+        let swapped: Vec<u32> = codes[after.clone()]
+            .iter()
+            .chain(&codes[before.end..after.start])
+            .chain(&codes[before.clone()])
+            .copied()
+            .collect();
+        codes.splice(before.start..after.end, swapped);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedRegionSwapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedRegionSwapMutator");
+        &NAME
+    }
+}
+
+impl EncodedRegionSwapMutator {
+    /// Creates a new [`EncodedRegionSwapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Region duplicate mutation for encoded inputs, inserting a copy of a bracketed region right after it,
+/// see [`EncodedBracketsMetadata`]
+#[derive(Debug, Default)]
+pub struct EncodedRegionDuplicateMutator {
+    /// Scratch buffer for the open brackets
+    open: Vec<(usize, usize)>,
+    max_size_mode: MaxSizeMode,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedRegionDuplicateMutator
+where
+    S: HasRand + HasMaxSize + HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let Some(region) = rand_region(state, input.codes(), &mut self.open) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let codes = input.codes();
+        if !self
+            .max_size_mode
+            .fits(codes, 0..0, &codes[region.clone()], state.max_size())
+        {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let codes = input.codes_mut();
+        let len = region.len();
+        codes.extend_from_within(region.clone());
+        codes[region.end..].rotate_right(len);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedRegionDuplicateMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedRegionDuplicateMutator");
+        &NAME
+    }
+}
+
+impl EncodedRegionDuplicateMutator {
+    /// Creates a new [`EncodedRegionDuplicateMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Bracket wrap mutation for encoded inputs, enclosing a random range of codes in a bracket pair
+/// of the [`EncodedBracketsMetadata`], to nest it deeper
+#[derive(Debug, Default)]
+pub struct EncodedBracketWrapMutator {
+    max_size_mode: MaxSizeMode,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedBracketWrapMutator
+where
+    S: HasRand + HasMaxSize + HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let size = input.codes().len();
+        if size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let Some(count) = state
+            .metadata_map()
+            .get::<EncodedBracketsMetadata>()
+            .map(|meta| meta.pairs.len())
+        else {
+            return Ok(MutationResult::Skipped);
+        };
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(count);
+        let (open, close) = state.metadata::<EncodedBracketsMetadata>()?.pairs[idx];
+        if !self
+            .max_size_mode
+            .fits(input.codes(), 0..0, &[open, close], state.max_size())
+        {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let off = state.rand_mut().below(size);
+        let len = 1 + state.rand_mut().below(size - off);
+        let codes = input.codes_mut();
+        codes.insert(off + len, close);
+        codes.insert(off, open);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedBracketWrapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedBracketWrapMutator");
+        &NAME
+    }
+}
+
+impl EncodedBracketWrapMutator {
+    /// Creates a new [`EncodedBracketWrapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the input in the given [`MaxSizeMode`]
+    #[must_use]
+    pub fn with_max_size_mode(mut self, max_size_mode: MaxSizeMode) -> Self {
+        self.max_size_mode = max_size_mode;
+        self
+    }
+}
+
+/// Bracket unwrap mutation for encoded inputs, removing the brackets of a bracketed region but keeping its contents,
+/// see [`EncodedBracketsMetadata`]
+#[derive(Debug, Default)]
+pub struct EncodedBracketUnwrapMutator {
+    /// Scratch buffer for the open brackets
+    open: Vec<(usize, usize)>,
+}
+
+impl<S> Mutator<EncodedInput, S> for EncodedBracketUnwrapMutator
+where
+    S: HasRand + HasMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut EncodedInput) -> Result<MutationResult, Error> {
+        let Some(region) = rand_region(state, input.codes(), &mut self.open) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let codes = input.codes_mut();
+        codes.remove(region.end - 1);
+        codes.remove(region.start);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for EncodedBracketUnwrapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("EncodedBracketUnwrapMutator");
+        &NAME
+    }
+}
+
+impl EncodedBracketUnwrapMutator {
+    /// Creates a new [`EncodedBracketUnwrapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Tuple type of the mutations that compose the encoded mutator
+pub type EncodedMutationsType = tuple_list_type!(
+    EncodedRandMutator,
+    EncodedIncMutator,
+    EncodedDecMutator,
+    EncodedAddMutator,
+    EncodedDeleteMutator,
+    EncodedInsertCopyMutator,
+    EncodedCopyMutator,
+    EncodedCrossoverInsertMutator,
+    EncodedCrossoverReplaceMutator,
+);
+
+/// Tuple type of the mutations moving, repeating, and exchanging whole runs of codes of encoded inputs
+pub type EncodedStructureMutationsType = tuple_list_type!(
+    EncodedTwoPointCrossoverMutator,
+    EncodedDuplicateMutator,
+    EncodedMoveMutator,
+    EncodedSwapMutator,
+    EncodedRotateMutator,
+    EncodedRepeatMutator,
+);
+
+/// Tuple type of the bracket-aware region mutations for encoded inputs
+pub type EncodedRegionMutationsType = tuple_list_type!(
+    EncodedRegionDeleteMutator,
+    EncodedRegionCrossoverMutator,
+    EncodedRegionSwapMutator,
+    EncodedRegionDuplicateMutator,
+    EncodedBracketWrapMutator,
+    EncodedBracketUnwrapMutator,
+);
+
+/// Tuple type of all the mutations for encoded inputs
+pub type EncodedHavocMutationsType = <<EncodedMutationsType as Merge<
+    EncodedStructureMutationsType,
+>>::MergeResult as Merge<EncodedRegionMutationsType>>::MergeResult;
+
+/// Get the mutations that compose the encoded mutator
+#[must_use]
+pub fn encoded_mutations() -> EncodedMutationsType {
+    tuple_list!(
+        EncodedRandMutator::new(),
+        EncodedIncMutator::new(),
+        EncodedDecMutator::new(),
+        EncodedAddMutator::new(),
+        EncodedDeleteMutator::new(),
+        EncodedInsertCopyMutator::new(),
+        EncodedCopyMutator::new(),
+        EncodedCrossoverInsertMutator::new(),
+        EncodedCrossoverReplaceMutator::new(),
+    )
+}
+
+/// Get the mutations moving, repeating, and exchanging whole runs of codes of encoded inputs
+#[must_use]
+pub fn encoded_structure_mutations() -> EncodedStructureMutationsType {
+    tuple_list!(
+        EncodedTwoPointCrossoverMutator::new(),
+        EncodedDuplicateMutator::new(),
+        EncodedMoveMutator::new(),
+        EncodedSwapMutator::new(),
+        EncodedRotateMutator::new(),
+        EncodedRepeatMutator::new(),
+    )
+}
+
+/// Get the bracket-aware region mutations for encoded inputs, which need an [`EncodedBracketsMetadata`] in the state
+#[must_use]
+pub fn encoded_region_mutations() -> EncodedRegionMutationsType {
+    tuple_list!(
+        EncodedRegionDeleteMutator::new(),
+        EncodedRegionCrossoverMutator::new(),
+        EncodedRegionSwapMutator::new(),
+        EncodedRegionDuplicateMutator::new(),
+        EncodedBracketWrapMutator::new(),
+        EncodedBracketUnwrapMutator::new(),
+    )
+}
+
+/// Get all the mutations for encoded inputs, ready for a [`crate::mutators::StdScheduledMutator`],
+/// or a [`crate::mutators::StdMOptMutator`] to adapt how often each is picked to how often it finds something.
+///
+/// The region mutations skip until an [`EncodedBracketsMetadata`] is added to the state.
+#[must_use]
+pub fn encoded_havoc_mutations() -> EncodedHavocMutationsType {
+    encoded_mutations()
+        .merge(encoded_structure_mutations())
+        .merge(encoded_region_mutations())
+}
+
+/// Get all the mutations for encoded inputs, like [`encoded_havoc_mutations`],
+/// with the mutations that can grow the input measuring it in the given [`MaxSizeMode`]
+#[must_use]
+pub fn encoded_havoc_mutations_with_max_size_mode(
+    max_size_mode: MaxSizeMode,
+) -> EncodedHavocMutationsType {
+    tuple_list!(
+        EncodedRandMutator::new().with_max_size_mode(max_size_mode),
+        EncodedIncMutator::new().with_max_size_mode(max_size_mode),
+        EncodedDecMutator::new().with_max_size_mode(max_size_mode),
+        EncodedAddMutator::new().with_max_size_mode(max_size_mode),
+        EncodedDeleteMutator::new(),
+        EncodedInsertCopyMutator::new().with_max_size_mode(max_size_mode),
+        EncodedCopyMutator::new().with_max_size_mode(max_size_mode),
+        EncodedCrossoverInsertMutator::new().with_max_size_mode(max_size_mode),
+        EncodedCrossoverReplaceMutator::new().with_max_size_mode(max_size_mode),
+        EncodedTwoPointCrossoverMutator::new().with_max_size_mode(max_size_mode),
+        EncodedDuplicateMutator::new().with_max_size_mode(max_size_mode),
+        EncodedMoveMutator::new(),
+        EncodedSwapMutator::new(),
+        EncodedRotateMutator::new(),
+        EncodedRepeatMutator::new().with_max_size_mode(max_size_mode),
+        EncodedRegionDeleteMutator::new(),
+        EncodedRegionCrossoverMutator::new().with_max_size_mode(max_size_mode),
+        EncodedRegionSwapMutator::new(),
+        EncodedRegionDuplicateMutator::new().with_max_size_mode(max_size_mode),
+        EncodedBracketWrapMutator::new().with_max_size_mode(max_size_mode),
+        EncodedBracketUnwrapMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use libafl_bolts::rands::StdRand;
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus},
+        inputs::{EncodedInput, InputEncoder, TokenInputEncoderDecoder, Tokenizer},
+        mutators::{
+            encoded_havoc_mutations, encoded_havoc_mutations_with_max_size_mode,
+            EncodedBracketUnwrapMutator, EncodedBracketWrapMutator, EncodedBracketsMetadata,
+            EncodedRegionDuplicateMutator, EncodedRegionSwapMutator, MOpt, MaxSizeMode,
+            MutationResult, Mutator, StdMOptMutator, StdScheduledMutator,
+        },
+        state::{HasCorpus, HasMaxSize, StdState},
+        Error, HasMetadata,
+    };
+
+    #[test]
+    fn test_encoded_bracket_regions() {
+        // ( a [ b ) ] " c " ( )
+        let meta = EncodedBracketsMetadata::new(vec![(0, 1), (2, 3), (4, 4)]);
+        let codes = [0, 10, 2, 11, 1, 3, 4, 12, 4, 0, 1];
+        assert_eq!(meta.regions(&codes), vec![2..6, 6..9, 9..11]);
+
+        let codes = [0, 2, 4, 4, 3, 1];
+        assert_eq!(meta.regions(&codes), vec![2..4, 1..5, 0..6]);
+
+        // ( ( ) a )
+        let codes = [0, 0, 1, 10, 1];
+        assert_eq!(meta.regions(&codes), vec![1..3, 0..5]);
+        assert_eq!(meta.matched_region_at(&codes, 0), Some(0..5));
+        assert_eq!(meta.matched_region_at(&codes, 2), Some(1..3));
+        assert_eq!(meta.matched_region_at(&codes, 4), Some(0..5));
+        assert_eq!(meta.matched_region_at(&codes, 3), None);
+        assert_eq!(meta.matched_region_at(&codes, 5), None);
+
+        // " ( " " )
+        let codes = [4, 0, 4, 4, 1];
+        assert_eq!(meta.matched_region_at(&codes, 0), Some(0..3));
+        assert_eq!(meta.matched_region_at(&codes, 2), Some(0..3));
+        assert_eq!(meta.matched_region_at(&codes, 3), None);
+        assert_eq!(meta.matched_region_at(&codes, 4), Some(1..5));
+        let input = EncodedInput::new(codes.to_vec());
+        assert_eq!(input.matched_region_at(&meta, 1), Some(1..5));
+    }
+
+    #[test]
+    fn test_encoded_brackets_metadata() {
+        /// Splits at spaces
+        struct SpaceTokenizer;
+
+        impl Tokenizer for SpaceTokenizer {
+            fn tokenize(&self, bytes: &[u8]) -> Result<Vec<String>, Error> {
+                Ok(core::str::from_utf8(bytes)?
+                    .split(' ')
+                    .map(String::from)
+                    .collect())
+            }
+        }
+
+        let mut encoder = TokenInputEncoderDecoder::new();
+        let input = encoder
+            .encode(b"f ( x ) { [ y ] } ) (", &mut SpaceTokenizer)
+            .unwrap();
+        // `<` and `>` were never encoded, so their pair is left out
+        let meta = EncodedBracketsMetadata::from_tokens(
+            &encoder,
+            &[("(", ")"), ("{", "}"), ("[", "]"), ("<", ">")],
+        );
+        assert_eq!(meta.pairs.len(), 3);
+
+        // The stray `)` and the unclosed `(` are ignored
+        let codes = input.codes();
+        assert_eq!(meta.regions(codes), vec![1..4, 5..8, 4..9]);
+        assert_eq!(meta.matched_region_at(codes, 4), Some(4..9));
+        assert_eq!(meta.matched_region_at(codes, 9), None);
+        assert_eq!(meta.matched_region_at(codes, 10), None);
+
+        // Mismatched brackets do not close each other
+        let (open_paren, close_square) = (codes[1], codes[7]);
+        assert!(meta.regions(&[open_paren, close_square]).is_empty());
+
+        // The visit stops once the callback returns false
+        let mut visited = vec![];
+        meta.visit_regions(codes, &mut vec![], |region| {
+            visited.push(region);
+            visited.len() < 2
+        });
+        assert_eq!(visited, vec![1..4, 5..8]);
+    }
+
+    #[test]
+    fn test_encoded_havoc_mutations() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<EncodedInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        state.add_metadata(EncodedBracketsMetadata::new(vec![(0, 1)]));
+        state
+            .corpus_mut()
+            .add(EncodedInput::new(vec![0, 10, 1]).into())
+            .unwrap();
+
+        state.set_max_size(16);
+
+        let mut mutator = StdScheduledMutator::new(encoded_havoc_mutations());
+        let mut input = EncodedInput::new(vec![0, 10, 11, 1, 12]);
+        for _ in 0..1024 {
+            mutator.mutate(&mut state, &mut input).unwrap();
+            assert!(input.codes().len() <= 16);
+        }
+    }
+
+    #[test]
+    fn test_encoded_max_size_mode() {
+        for codes in [vec![], vec![0, 127, 128], vec![16_383, 16_384, u32::MAX]] {
+            let serialized = postcard::to_allocvec(&EncodedInput::new(codes.clone())).unwrap();
+            assert_eq!(
+                MaxSizeMode::SerializedBytes.size_of(&codes),
+                serialized.len()
+            );
+            assert_eq!(MaxSizeMode::Codes.size_of(&codes), codes.len());
+        }
+
+        // [127] takes 2 bytes, [128] takes 3
+        assert!(MaxSizeMode::SerializedBytes.fits(&[127], 0..1, &[1], 2));
+        assert!(!MaxSizeMode::SerializedBytes.fits(&[127], 0..1, &[128], 2));
+        assert!(MaxSizeMode::Codes.fits(&[127], 0..1, &[128], 1));
+        // shrinking an input that is too big is fine
+        assert!(MaxSizeMode::SerializedBytes.fits(&[u32::MAX], 0..1, &[128], 2));
+    }
+
+    #[test]
+    fn test_encoded_havoc_mutations_serialized_bytes() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<EncodedInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        state.add_metadata(EncodedBracketsMetadata::new(vec![(0, 1)]));
+        state
+            .corpus_mut()
+            .add(EncodedInput::new(vec![0, 70_000, 1, u32::MAX]).into())
+            .unwrap();
+
+        state.set_max_size(24);
+
+        let mut mutator = StdScheduledMutator::new(encoded_havoc_mutations_with_max_size_mode(
+            MaxSizeMode::SerializedBytes,
+        ));
+        let mut input = EncodedInput::new(vec![0, 10, 300, 1, 12]);
+        for _ in 0..4096 {
+            mutator.mutate(&mut state, &mut input).unwrap();
+            assert!(postcard::to_allocvec(&input).unwrap().len() <= 24);
+        }
+    }
+
+    #[test]
+    fn test_encoded_mopt() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<EncodedInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        state
+            .corpus_mut()
+            .add(EncodedInput::new(vec![0, 10, 1]).into())
+            .unwrap();
+
+        let mut mutator = StdMOptMutator::new(&mut state, encoded_havoc_mutations(), 7, 5).unwrap();
+        let mut input = EncodedInput::new(vec![0, 10, 11, 1, 12]);
+        mutator.mutate(&mut state, &mut input).unwrap();
+        // a new corpus entry is attributed to the operators that were used
+        state.corpus_mut().add(input.into()).unwrap();
+        mutator.post_exec(&mut state, None).unwrap();
+
+        let mopt = state.metadata::<MOpt>().unwrap();
+        let finds = mopt.operator_finds();
+        assert_eq!(finds.len(), 21);
+        assert!(finds.iter().any(|&finds| finds > 0));
+        assert!(mopt.operator_cycles().iter().sum::<u64>() > 0);
+    }
+
+    #[test]
+    fn test_encoded_region_swap() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<EncodedInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        state.add_metadata(EncodedBracketsMetadata::new(vec![(0, 1)]));
+
+        // ( a ) b ( c d )
+        let mut input = EncodedInput::new(vec![0, 10, 1, 11, 0, 12, 13, 1]);
+        let mut mutator = EncodedRegionSwapMutator::new();
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.codes(), [0, 12, 13, 1, 11, 0, 10, 1]);
+
+        // a single region can not be swapped
+        let mut input = EncodedInput::new(vec![0, 10, 1]);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+
+    #[test]
+    fn test_encoded_region_duplicate() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<EncodedInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        state.add_metadata(EncodedBracketsMetadata::new(vec![(0, 1)]));
+
+        // a ( b ) c
+        let mut input = EncodedInput::new(vec![10, 0, 11, 1, 12]);
+        let mut mutator = EncodedRegionDuplicateMutator::new();
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.codes(), [10, 0, 11, 1, 0, 11, 1, 12]);
+
+        state.set_max_size(10);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+
+    #[test]
+    fn test_encoded_bracket_wrap_unwrap() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<EncodedInput>::new(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        let meta = EncodedBracketsMetadata::new(vec![(0, 1)]);
+        state.add_metadata(meta.clone());
+
+        // a ( b ) c
+        let mut input = EncodedInput::new(vec![10, 0, 11, 1, 12]);
+        let mut mutator = EncodedBracketWrapMutator::new();
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.codes().len(), 7);
+        // only the brackets are new
+        assert!(input
+            .codes()
+            .iter()
+            .copied()
+            .filter(|&code| code >= 10)
+            .eq([10, 11, 12]));
+        assert_eq!(meta.regions(input.codes()).len(), 2);
+
+        // a ( b ) c
+        let mut input = EncodedInput::new(vec![10, 0, 11, 1, 12]);
+        let mut mutator = EncodedBracketUnwrapMutator::new();
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.codes(), [10, 11, 12]);
+    }
 }
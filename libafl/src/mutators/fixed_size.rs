@@ -0,0 +1,83 @@
+//! A [`Mutator`] wrapper keeping the size of the input, for targets with fixed-size input frames.
+
+use alloc::borrow::Cow;
+
+use libafl_bolts::Named;
+
+use crate::{
+    corpus::CorpusId,
+    inputs::HasMutatorBytes,
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+
+/// A [`Mutator`] that restores the original size of the input after the inner mutator ran.
+///
+/// Grown inputs are truncated at the end, shrunk inputs are padded with zeros at the end.
+/// To not waste executions on padded inputs, prefer inner mutators that keep the size to begin with,
+/// like the [`crate::mutators::havoc_mutations_fixed_size`], and use this as a safety net for the rest.
+#[derive(Debug)]
+pub struct FixedSizeMutator<M> {
+    name: Cow<'static, str>,
+    inner: M,
+}
+
+impl<M> Named for FixedSizeMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for FixedSizeMutator<M>
+where
+    I: HasMutatorBytes,
+    M: Mutator<I, S>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let size = input.bytes().len();
+        let result = self.inner.mutate(state, input)?;
+        if input.bytes().len() != size {
+            input.resize(size, 0);
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
+
+impl<M> FixedSizeMutator<M> {
+    /// Creates a new [`FixedSizeMutator`], clamping the size changes of the `inner` mutator
+    pub fn new(inner: M) -> Self
+    where
+        M: Named,
+    {
+        Self {
+            name: Cow::from(format!("FixedSizeMutator[{}]", inner.name())),
+            inner,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{havoc_mutations_no_crossover, FixedSizeMutator, Mutator, StdScheduledMutator},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_fixed_size_mutator() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mutator =
+            FixedSizeMutator::new(StdScheduledMutator::new(havoc_mutations_no_crossover()));
+        let mut input = BytesInput::new(vec![0x41; 16]);
+        for _ in 0..1000 {
+            mutator.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(input.bytes().len(), 16);
+        }
+    }
+}
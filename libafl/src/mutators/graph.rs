@@ -0,0 +1,378 @@
+//! Structural mutators for [`GraphInput`]s: node insertion and removal, edge rewiring, and subgraph crossover.
+//! See [`crate::inputs::graph`] for details.
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use libafl_bolts::{rands::Rand, Error, Named};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::{graph::GraphInput, Input},
+    mutators::{MutationResult, Mutator},
+    random_corpus_id_with_disabled,
+    state::{HasCorpus, HasRand},
+};
+
+/// The default maximum amount of nodes the mutators grow a [`GraphInput`] to
+pub const GRAPH_DEFAULT_MAX_NODES: usize = 32;
+
+/// Adds a copy of a random node, with an edge from a random node to it, and sometimes an edge from it to a random node
+#[derive(Debug)]
+pub struct GraphInsertNodeMutator {
+    max_nodes: usize,
+    acyclic: bool,
+}
+
+impl<P, S> Mutator<GraphInput<P>, S> for GraphInsertNodeMutator
+where
+    P: Clone,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GraphInput<P>,
+    ) -> Result<MutationResult, Error> {
+        let len = input.nodes().len();
+        if len == 0 || len >= self.max_nodes {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let payload = input.nodes()[rand.below(len)].clone();
+        let src = rand.below(len);
+        let node = input.add_node(payload);
+        input.add_edge(src, node);
+
+        if rand.coinflip(0.5) {
+            let dst = rand.below(len);
+            // An edge back into the graph closes a cycle if `dst` leads to the new node
+            if !self.acyclic || !input.has_path(dst, node) {
+                input.add_edge(node, dst);
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphInsertNodeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphInsertNodeMutator");
+        &NAME
+    }
+}
+
+impl Default for GraphInsertNodeMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GraphInsertNodeMutator {
+    /// Creates a new [`GraphInsertNodeMutator`], growing inputs to at most [`GRAPH_DEFAULT_MAX_NODES`] nodes
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_nodes: GRAPH_DEFAULT_MAX_NODES,
+            acyclic: false,
+        }
+    }
+
+    /// Grow inputs to at most `max_nodes` nodes
+    #[must_use]
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Never add a cycle, for targets expecting a DAG
+    #[must_use]
+    pub fn acyclic(mut self, acyclic: bool) -> Self {
+        self.acyclic = acyclic;
+        self
+    }
+}
+
+/// Removes a random node and all its edges, the last node is kept
+#[derive(Debug, Default)]
+pub struct GraphRemoveNodeMutator;
+
+impl<P, S> Mutator<GraphInput<P>, S> for GraphRemoveNodeMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GraphInput<P>,
+    ) -> Result<MutationResult, Error> {
+        if input.nodes().len() <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(input.nodes().len());
+        input.remove_node(idx);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphRemoveNodeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphRemoveNodeMutator");
+        &NAME
+    }
+}
+
+impl GraphRemoveNodeMutator {
+    /// Creates a new [`GraphRemoveNodeMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Moves the source or the target of a random edge to a random node, or adds an edge if there is none
+#[derive(Debug, Default)]
+pub struct GraphRewireEdgeMutator {
+    acyclic: bool,
+}
+
+impl<P, S> Mutator<GraphInput<P>, S> for GraphRewireEdgeMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GraphInput<P>,
+    ) -> Result<MutationResult, Error> {
+        let len = input.nodes().len();
+        if len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let node = rand.below(len);
+
+        if input.edges().is_empty() {
+            let other = rand.below(len);
+            if self.acyclic && input.has_path(other, node) {
+                return Ok(MutationResult::Skipped);
+            }
+            input.add_edge(node, other);
+            return Ok(MutationResult::Mutated);
+        }
+
+        let idx = rand.below(input.edges().len());
+        let old = input.edges()[idx];
+        let new = if rand.coinflip(0.5) {
+            (node, old.1)
+        } else {
+            (old.0, node)
+        };
+        if new == old {
+            return Ok(MutationResult::Skipped);
+        }
+        input.edges_mut()[idx] = new;
+        if self.acyclic && !input.is_acyclic() {
+            input.edges_mut()[idx] = old;
+            return Ok(MutationResult::Skipped);
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphRewireEdgeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphRewireEdgeMutator");
+        &NAME
+    }
+}
+
+impl GraphRewireEdgeMutator {
+    /// Creates a new [`GraphRewireEdgeMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self { acyclic: false }
+    }
+
+    /// Never add a cycle, for targets expecting a DAG
+    #[must_use]
+    pub fn acyclic(mut self, acyclic: bool) -> Self {
+        self.acyclic = acyclic;
+        self
+    }
+}
+
+/// Copies the nodes reachable from a random node of another testcase in the corpus, with the edges between them,
+/// and adds an edge from a random node of the input to the copied subgraph.
+/// As no edge leads back out of the copy, this never adds a cycle.
+#[derive(Debug)]
+pub struct GraphCrossoverMutator {
+    max_nodes: usize,
+}
+
+impl<P, S> Mutator<GraphInput<P>, S> for GraphCrossoverMutator
+where
+    P: Input,
+    S: HasCorpus<Input = GraphInput<P>> + HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GraphInput<P>,
+    ) -> Result<MutationResult, Error> {
+        let room = self.max_nodes.saturating_sub(input.nodes().len());
+        if room == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+        let donor = if state.corpus().current().is_some_and(|cur| cur == id) {
+            input.clone()
+        } else {
+            let mut testcase = state.corpus().get_from_all(id)?.borrow_mut();
+            testcase.load_input(state.corpus())?.clone()
+        };
+        if donor.nodes().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let root = rand.below(donor.nodes().len());
+        let mut subgraph = donor.reachable_from(root);
+        subgraph.truncate(rand.between(1, room.min(subgraph.len())));
+
+        let mut mapping: Vec<Option<usize>> = vec![None; donor.nodes().len()];
+        let previous = input.nodes().len();
+        for node in &subgraph {
+            mapping[*node] = Some(input.add_node(donor.nodes()[*node].clone()));
+        }
+        for (from, to) in donor.edges() {
+            if let (Some(from), Some(to)) = (mapping[*from], mapping[*to]) {
+                input.add_edge(from, to);
+            }
+        }
+        if previous > 0 {
+            input.add_edge(rand.below(previous), previous);
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphCrossoverMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphCrossoverMutator");
+        &NAME
+    }
+}
+
+impl Default for GraphCrossoverMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GraphCrossoverMutator {
+    /// Creates a new [`GraphCrossoverMutator`], growing inputs to at most [`GRAPH_DEFAULT_MAX_NODES`] nodes
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_nodes(GRAPH_DEFAULT_MAX_NODES)
+    }
+
+    /// Creates a new [`GraphCrossoverMutator`], growing inputs to at most `max_nodes` nodes
+    #[must_use]
+    pub fn with_max_nodes(max_nodes: usize) -> Self {
+        Self { max_nodes }
+    }
+}
+
+/// Applies `mutator` to the payload of a random node
+#[derive(Debug)]
+pub struct GraphPayloadMutator<M> {
+    mutator: M,
+    name: Cow<'static, str>,
+}
+
+impl<M, P, S> Mutator<GraphInput<P>, S> for GraphPayloadMutator<M>
+where
+    M: Mutator<P, S>,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GraphInput<P>,
+    ) -> Result<MutationResult, Error> {
+        if input.nodes().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(input.nodes().len());
+        self.mutator.mutate(state, &mut input.nodes_mut()[idx])
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.mutator.post_exec(state, new_corpus_id)
+    }
+}
+
+impl<M> Named for GraphPayloadMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<M> GraphPayloadMutator<M>
+where
+    M: Named,
+{
+    /// Creates a new [`GraphPayloadMutator`], mutating the payloads with `mutator`
+    pub fn new(mutator: M) -> Self {
+        let name = Cow::Owned(String::from("GraphPayload") + mutator.name());
+        Self { mutator, name }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::{Corpus, Testcase},
+        inputs::{graph::GraphInput, BytesInput},
+        mutators::{
+            graph::{
+                GraphCrossoverMutator, GraphInsertNodeMutator, GraphPayloadMutator,
+                GraphRemoveNodeMutator, GraphRewireEdgeMutator,
+            },
+            ByteFlipMutator, MutatorsTuple,
+        },
+        state::{test::test_std_state, HasCorpus},
+    };
+
+    #[test]
+    fn test_graph_mutators() {
+        let mut state = test_std_state::<GraphInput<BytesInput>>();
+        let donor = GraphInput::new(
+            vec![BytesInput::new(vec![1]), BytesInput::new(vec![2])],
+            vec![(0, 1)],
+        );
+        state.corpus_mut().add(Testcase::new(donor)).unwrap();
+
+        let mut mutations = tuple_list!(
+            GraphInsertNodeMutator::new().max_nodes(16).acyclic(true),
+            GraphRewireEdgeMutator::new().acyclic(true),
+            GraphCrossoverMutator::with_max_nodes(16),
+            GraphRemoveNodeMutator::new(),
+            GraphPayloadMutator::new(ByteFlipMutator::new()),
+        );
+        let mut input = GraphInput::new(vec![BytesInput::new(vec![0])], vec![]);
+        for _ in 0..256 {
+            mutations.mutate_all(&mut state, &mut input).unwrap();
+            assert!(input.is_acyclic());
+            assert!(!input.nodes().is_empty() && input.nodes().len() <= 16);
+            assert!(input
+                .edges()
+                .iter()
+                .all(|(from, to)| *from < input.nodes().len() && *to < input.nodes().len()));
+        }
+    }
+}
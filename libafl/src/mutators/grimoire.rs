@@ -6,6 +6,7 @@ use core::cmp::{max, min};
 
 use libafl_bolts::{
     rands::{choose, fast_bound, Rand},
+    tuples::{tuple_list, tuple_list_type},
     Named,
 };
 
@@ -397,3 +398,76 @@ impl GrimoireRandomDeleteMutator {
         }
     }
 }
+
+/// Tuple type of the mutations that compose the Grimoire mutator
+pub type GrimoireMutationsType = tuple_list_type!(
+    GrimoireExtensionMutator,
+    GrimoireRecursiveReplacementMutator,
+    GrimoireStringReplacementMutator,
+    GrimoireRandomDeleteMutator,
+    GrimoireRandomDeleteMutator
+);
+
+/// Get the mutations that compose the Grimoire mutator, working on the [`GeneralizedInputMetadata`]
+/// the [`crate::stages::GeneralizationStage`] stores for each corpus entry.
+///
+/// The [`GrimoireRandomDeleteMutator`] is contained twice, giving it more probability to avoid large inputs.
+#[must_use]
+pub fn grimoire_mutations() -> GrimoireMutationsType {
+    tuple_list!(
+        GrimoireExtensionMutator::new(),
+        GrimoireRecursiveReplacementMutator::new(),
+        GrimoireStringReplacementMutator::new(),
+        GrimoireRandomDeleteMutator::new(),
+        GrimoireRandomDeleteMutator::new()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::{Corpus, Testcase},
+        inputs::{BytesInput, GeneralizedInputMetadata, GeneralizedItem},
+        mutators::{grimoire::grimoire_mutations, MutationResult, Mutator, StdScheduledMutator},
+        state::{test::test_std_state, HasCorpus},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_grimoire_mutations() {
+        // # Safety
+        // No concurrency per testcase
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            GeneralizedInputMetadata::register();
+        }
+
+        let mut state = test_std_state::<BytesInput>();
+        let generalized = GeneralizedInputMetadata::generalized_from_options(&[
+            None,
+            Some(b'f'),
+            Some(b'o'),
+            Some(b'o'),
+            None,
+            Some(b'('),
+            None,
+            Some(b')'),
+        ]);
+        let mut testcase = Testcase::new(BytesInput::new(b"foo()".to_vec()));
+        testcase.add_metadata(generalized.clone());
+        state.corpus_mut().add(testcase).unwrap();
+
+        let mut mutator = StdScheduledMutator::new(grimoire_mutations());
+        let mut mutated = 0;
+        for _ in 0..64 {
+            let mut input = generalized.clone();
+            if mutator.mutate(&mut state, &mut input).unwrap() == MutationResult::Mutated {
+                mutated += 1;
+            }
+            // the mutations keep the gaps around the generalized input
+            assert_eq!(input.generalized().first(), Some(&GeneralizedItem::Gap));
+            assert_eq!(input.generalized().last(), Some(&GeneralizedItem::Gap));
+        }
+        assert!(mutated > 0);
+    }
+}
@@ -0,0 +1,333 @@
+//! Undo journals for stacked mutations.
+//!
+//! The [`JournalScheduledMutator`] records the byte edit of every mutation it stacks, and attaches the
+//! journal to new corpus entries as [`MutationJournalMetadata`]. The
+//! [`crate::stages::MutationMinimizationStage`] later replays the journal with individual edits left out,
+//! to find the few mutations that actually made the entry interesting.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+use libafl_bolts::{impl_serdeany, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::HasMutatorBytes,
+    mutators::{
+        ComposedByMutations, MutationId, MutationResult, Mutator, MutatorsTuple, ScheduledMutator,
+    },
+    state::HasCorpus,
+    Error, HasMetadata,
+};
+
+/// A single edit of a byte input: `removed` at `offset` was replaced by `inserted`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteEdit {
+    /// The offset of the edit, in the input as it was right before this edit
+    pub offset: usize,
+    /// The bytes the edit replaced, to undo it
+    pub removed: Vec<u8>,
+    /// The bytes the edit wrote
+    pub inserted: Vec<u8>,
+}
+
+impl ByteEdit {
+    /// Computes the edit turning `before` into `after`, as the range between their common prefix and suffix.
+    ///
+    /// Returns `None` if both are equal.
+    #[must_use]
+    pub fn diff(before: &[u8], after: &[u8]) -> Option<Self> {
+        let prefix = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+        if prefix == before.len() && prefix == after.len() {
+            return None;
+        }
+        let suffix = before[prefix..]
+            .iter()
+            .rev()
+            .zip(after[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        Some(Self {
+            offset: prefix,
+            removed: before[prefix..before.len() - suffix].to_vec(),
+            inserted: after[prefix..after.len() - suffix].to_vec(),
+        })
+    }
+}
+
+/// A run of bytes present in both the fully mutated and the partially mutated input of a replay
+#[derive(Debug, Clone, Copy)]
+struct SharedRun {
+    full: usize,
+    partial: usize,
+    len: usize,
+}
+
+/// The journal of all mutations that turned the `parent` corpus entry into this testcase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct MutationJournalMetadata {
+    /// The corpus entry the mutations were applied to
+    pub parent: CorpusId,
+    /// The edits of the mutations, in the order they were applied
+    pub edits: Vec<ByteEdit>,
+}
+
+impl_serdeany!(MutationJournalMetadata);
+
+impl MutationJournalMetadata {
+    /// Creates a new [`MutationJournalMetadata`]
+    #[must_use]
+    pub fn new(parent: CorpusId, edits: Vec<ByteEdit>) -> Self {
+        Self { parent, edits }
+    }
+
+    /// Replays the edits for which `keep` is `true` on the `parent` bytes.
+    ///
+    /// Later edits are moved to make up for the size changes of the dropped ones.
+    /// Returns `None` if a kept edit touches bytes that only a dropped edit wrote, so it cannot be applied without it.
+    #[must_use]
+    pub fn replay(&self, parent: &[u8], keep: &[bool]) -> Option<Vec<u8>> {
+        let mut partial = parent.to_vec();
+        let mut runs = vec![SharedRun {
+            full: 0,
+            partial: 0,
+            len: parent.len(),
+        }];
+
+        for (idx, edit) in self.edits.iter().enumerate() {
+            let (start, end) = (edit.offset, edit.offset + edit.removed.len());
+            if keep.get(idx).copied().unwrap_or(true) {
+                // The edited bytes must exist in the partial input, too
+                let run_idx = runs
+                    .iter()
+                    .position(|run| run.full <= start && end <= run.full + run.len)?;
+                let run = &mut runs[run_idx];
+                let at = run.partial + start - run.full;
+                partial.splice(at..at + edit.removed.len(), edit.inserted.iter().copied());
+                run.len = run.len - edit.removed.len() + edit.inserted.len();
+                for run in &mut runs[run_idx + 1..] {
+                    run.full = run.full - edit.removed.len() + edit.inserted.len();
+                    run.partial = run.partial - edit.removed.len() + edit.inserted.len();
+                }
+            } else {
+                // Only the full input changes, cut the edited bytes out of the shared runs
+                let mut next = Vec::with_capacity(runs.len() + 1);
+                for run in &runs {
+                    if run.full < start {
+                        next.push(SharedRun {
+                            len: run.len.min(start - run.full),
+                            ..*run
+                        });
+                    }
+                    let run_end = run.full + run.len;
+                    if run_end > end {
+                        let skip = end.saturating_sub(run.full);
+                        next.push(SharedRun {
+                            full: run.full + skip - edit.removed.len() + edit.inserted.len(),
+                            partial: run.partial + skip,
+                            len: run.len - skip,
+                        });
+                    }
+                }
+                runs = next;
+            }
+        }
+        Some(partial)
+    }
+}
+
+/// A [`Mutator`] that wraps around a [`ScheduledMutator`] and journals the byte edit of each stacked mutation,
+/// see the [module documentation](self).
+///
+/// Journaling copies the input before every mutation, so this is best used on small to medium inputs.
+pub struct JournalScheduledMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    name: Cow<'static, str>,
+    scheduled: SM,
+    parent: Option<CorpusId>,
+    journal: Vec<ByteEdit>,
+    phantom: PhantomData<(I, MT, S)>,
+}
+
+impl<I, MT, S, SM> Debug for JournalScheduledMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "JournalScheduledMutator with {} mutations for Input type {}",
+            self.scheduled.mutations().len(),
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S, SM> Named for JournalScheduledMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, MT, S, SM> Mutator<I, S> for JournalScheduledMutator<I, MT, S, SM>
+where
+    I: HasMutatorBytes,
+    MT: MutatorsTuple<I, S>,
+    S: HasCorpus,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        self.scheduled_mutate(state, input)
+    }
+
+    fn post_exec(&mut self, state: &mut S, corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        if let (Some(id), Some(parent)) = (corpus_id, self.parent) {
+            if !self.journal.is_empty() {
+                let meta = MutationJournalMetadata::new(parent, core::mem::take(&mut self.journal));
+                state.corpus().get(id)?.borrow_mut().add_metadata(meta);
+            }
+        }
+        // Always reset the journal for each run
+        self.journal.clear();
+        self.scheduled.post_exec(state, corpus_id)
+    }
+}
+
+impl<I, MT, S, SM> ComposedByMutations<I, MT, S> for JournalScheduledMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    #[inline]
+    fn mutations(&self) -> &MT {
+        self.scheduled.mutations()
+    }
+
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        self.scheduled.mutations_mut()
+    }
+}
+
+impl<I, MT, S, SM> ScheduledMutator<I, MT, S> for JournalScheduledMutator<I, MT, S, SM>
+where
+    I: HasMutatorBytes,
+    MT: MutatorsTuple<I, S>,
+    S: HasCorpus,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    /// Compute the number of iterations used to apply stacked mutations
+    fn iterations(&self, state: &mut S, input: &I) -> u64 {
+        self.scheduled.iterations(state, input)
+    }
+
+    /// Get the next mutation to apply
+    fn schedule(&self, state: &mut S, input: &I) -> MutationId {
+        self.scheduled.schedule(state, input)
+    }
+
+    fn scheduled_mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let mut r = MutationResult::Skipped;
+        let num = self.iterations(state, input);
+        self.parent = *state.corpus().current();
+        self.journal.clear();
+        let mut before = input.bytes().to_vec();
+        for _ in 0..num {
+            let idx = self.schedule(state, input);
+            let outcome = self.mutations_mut().get_and_mutate(idx, state, input)?;
+            if outcome == MutationResult::Mutated {
+                r = MutationResult::Mutated;
+                if let Some(edit) = ByteEdit::diff(&before, input.bytes()) {
+                    self.journal.push(edit);
+                    before.clear();
+                    before.extend_from_slice(input.bytes());
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+impl<I, MT, S, SM> JournalScheduledMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    /// Create a new [`JournalScheduledMutator`], journaling the mutations of the `scheduled` mutator
+    pub fn new(scheduled: SM) -> Self {
+        Self {
+            name: Cow::from(format!("JournalScheduledMutator[{}]", scheduled.name())),
+            scheduled,
+            parent: None,
+            journal: vec![],
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::CorpusId,
+        mutators::journal::{ByteEdit, MutationJournalMetadata},
+    };
+
+    #[test]
+    fn test_journal_replay() {
+        let parent = b"hello world";
+        let mut bytes = parent.to_vec();
+        let mut edits = vec![];
+        for (range, with) in [
+            (0..5, &b"HELLO!"[..]),
+            (12..12, &b"!!"[..]),
+            (7..7, &b"big "[..]),
+        ] {
+            let before = bytes.clone();
+            bytes.splice(range, with.iter().copied());
+            edits.push(ByteEdit::diff(&before, &bytes).unwrap());
+        }
+        assert_eq!(bytes, b"HELLO! big world!!");
+        let journal = MutationJournalMetadata::new(CorpusId(0), edits);
+
+        assert_eq!(
+            journal.replay(parent, &[true, true, true]).unwrap(),
+            b"HELLO! big world!!"
+        );
+        assert_eq!(
+            journal.replay(parent, &[false, true, true]).unwrap(),
+            b"hello big world!!"
+        );
+        assert_eq!(
+            journal.replay(parent, &[true, false, true]).unwrap(),
+            b"HELLO! big world"
+        );
+        assert_eq!(journal.replay(parent, &[false; 3]).unwrap(), parent);
+
+        // Changing bytes only a dropped edit inserted is impossible
+        let mut after = b"HELLO! big world!!".to_vec();
+        after[5] = b'?';
+        let mut dependent = journal.clone();
+        dependent
+            .edits
+            .push(ByteEdit::diff(b"HELLO! big world!!", &after).unwrap());
+        assert!(dependent
+            .replay(parent, &[false, true, true, true])
+            .is_none());
+    }
+}
@@ -0,0 +1,232 @@
+//! Byte-range masks, protecting parts of an input, like file headers or session tokens, from mutation.
+//!
+//! The [`MaskedMutator`] wraps any byte mutator, usually a whole scheduled havoc mutator, and lets it
+//! mutate only the unmasked bytes. The masked ranges are taken from the [`MutationMaskMetadata`] of the
+//! current testcase, or of the state for a global mask.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::ops::Range;
+
+use libafl_bolts::{impl_serdeany, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::{BytesInput, HasMutatorBytes},
+    mutators::{MutationResult, Mutator},
+    state::{HasCorpus, HasMaxSize},
+    Error, HasMetadata,
+};
+
+/// The byte ranges of an input that must not be mutated, as testcase or as state metadata
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct MutationMaskMetadata {
+    immutable: Vec<Range<usize>>,
+}
+
+impl_serdeany!(MutationMaskMetadata);
+
+impl MutationMaskMetadata {
+    /// Creates a new mask protecting the given byte ranges, which may overlap
+    #[must_use]
+    pub fn new(mut immutable: Vec<Range<usize>>) -> Self {
+        immutable.retain(|range| !range.is_empty());
+        immutable.sort_unstable_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(immutable.len());
+        for range in immutable {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        Self { immutable: merged }
+    }
+
+    /// The protected byte ranges, sorted and without overlaps
+    #[must_use]
+    pub fn immutable(&self) -> &[Range<usize>] {
+        &self.immutable
+    }
+
+    /// The unprotected byte ranges of an input of `len` bytes, including empty ones between adjacent protected ranges
+    fn mutable(&self, len: usize) -> Vec<Range<usize>> {
+        let mut mutable = Vec::with_capacity(self.immutable.len() + 1);
+        let mut start = 0;
+        for range in &self.immutable {
+            let range = range.start.min(len)..range.end.min(len);
+            mutable.push(start..range.start);
+            start = range.end;
+        }
+        mutable.push(start..len);
+        mutable
+    }
+}
+
+/// A [`Mutator`] that applies the inner mutator to the unmasked bytes of the input only,
+/// see the [module documentation](self).
+///
+/// The unmasked bytes are handed to the inner mutator as one contiguous [`BytesInput`],
+/// so its offsets are remapped into the unmasked regions. Bytes the inner mutator inserts or
+/// deletes are attributed to the region where the change happened, moving the masked ranges behind it.
+/// New testcases derived from a testcase with a mask inherit the moved mask.
+#[derive(Debug)]
+pub struct MaskedMutator<M> {
+    name: Cow<'static, str>,
+    inner: M,
+    inherited_mask: Option<MutationMaskMetadata>,
+}
+
+impl<M> Named for MaskedMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for MaskedMutator<M>
+where
+    I: HasMutatorBytes,
+    M: Mutator<BytesInput, S>,
+    S: HasCorpus + HasMetadata + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        self.inherited_mask = None;
+        let testcase_mask = match *state.corpus().current() {
+            Some(id) => state
+                .corpus()
+                .get(id)?
+                .borrow()
+                .metadata_map()
+                .get::<MutationMaskMetadata>()
+                .cloned(),
+            None => None,
+        };
+        let from_testcase = testcase_mask.is_some();
+        let mask = testcase_mask
+            .or_else(|| state.metadata_map().get::<MutationMaskMetadata>().cloned())
+            .unwrap_or_default();
+
+        let bytes = input.bytes();
+        let mutable = mask.mutable(bytes.len());
+        let mut original = Vec::with_capacity(bytes.len());
+        for range in &mutable {
+            original.extend_from_slice(&bytes[range.clone()]);
+        }
+        let mut stream = BytesInput::new(original.clone());
+        if self.inner.mutate(state, &mut stream)? == MutationResult::Skipped {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let (old, new) = (original.len(), stream.bytes().len());
+        let masked = bytes.len() - old;
+        if masked + new > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        // Only the bytes between `prefix` and `old - suffix` of the stream were changed
+        let prefix = common_prefix(&original, stream.bytes());
+        let suffix = common_suffix(&original[prefix..], &stream.bytes()[prefix..]);
+        let remap = |pos: usize| {
+            if pos <= prefix {
+                pos
+            } else if pos >= old - suffix {
+                pos + new - old
+            } else {
+                pos.min(new - suffix)
+            }
+        };
+
+        let mut out = Vec::with_capacity(masked + new);
+        let mut immutable = Vec::with_capacity(mutable.len() - 1);
+        let mut stream_pos = 0;
+        for (idx, range) in mutable.iter().enumerate() {
+            let from = remap(stream_pos);
+            stream_pos += range.len();
+            match mutable.get(idx + 1) {
+                Some(next) => {
+                    out.extend_from_slice(&stream.bytes()[from..remap(stream_pos)]);
+                    let start = out.len();
+                    out.extend_from_slice(&bytes[range.end..next.start]);
+                    immutable.push(start..out.len());
+                }
+                // The last region takes everything that is left, e.g., if all unmasked bytes were empty before
+                None => out.extend_from_slice(&stream.bytes()[from..]),
+            }
+        }
+
+        input.resize(out.len(), 0);
+        input.bytes_mut().copy_from_slice(&out);
+        if from_testcase {
+            self.inherited_mask = Some(MutationMaskMetadata::new(immutable));
+        }
+        Ok(MutationResult::Mutated)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        if let (Some(id), Some(mask)) = (new_corpus_id, self.inherited_mask.take()) {
+            state.corpus().get(id)?.borrow_mut().add_metadata(mask);
+        }
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn common_suffix(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+impl<M> MaskedMutator<M> {
+    /// Creates a new [`MaskedMutator`], protecting the masked bytes from the `inner` mutator
+    pub fn new(inner: M) -> Self
+    where
+        M: Named,
+    {
+        Self {
+            name: Cow::from(format!("MaskedMutator[{}]", inner.name())),
+            inner,
+            inherited_mask: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{
+            havoc_mutations_no_crossover, MaskedMutator, MutationMaskMetadata, Mutator,
+            StdScheduledMutator,
+        },
+        state::test::test_std_state,
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_masked_mutator() {
+        let mask = MutationMaskMetadata::new(vec![4..6, 0..4, 10..12]);
+        assert_eq!(mask.immutable(), [0..6, 10..12]);
+
+        let mut state = test_std_state::<BytesInput>();
+        state.add_metadata(mask);
+        let mut mutator =
+            MaskedMutator::new(StdScheduledMutator::new(havoc_mutations_no_crossover()));
+        for _ in 0..1000 {
+            let mut input = BytesInput::new(b"HEADER....ENDtail".to_vec());
+            mutator.mutate(&mut state, &mut input).unwrap();
+            let bytes = input.bytes();
+            assert!(bytes.starts_with(b"HEADER"));
+            // The second masked range moves with insertions and deletions before it
+            assert!(bytes.windows(2).any(|w| w == b"EN"));
+        }
+    }
+}
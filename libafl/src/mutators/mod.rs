@@ -6,6 +6,8 @@ use core::fmt;
 pub use scheduled::*;
 pub mod mutations;
 pub use mutations::*;
+pub mod donor;
+pub use donor::*;
 pub mod token_mutations;
 use serde::{Deserialize, Serialize};
 pub use token_mutations::*;
@@ -19,6 +21,36 @@ pub mod grimoire;
 pub use grimoire::*;
 pub mod tuneable;
 pub use tuneable::*;
+pub mod bandit;
+pub use bandit::*;
+pub mod pipeline;
+pub use pipeline::*;
+pub mod mask;
+pub use mask::*;
+pub mod journal;
+pub use journal::*;
+pub mod fixed_size;
+pub use fixed_size::*;
+pub mod radamsa;
+pub use radamsa::*;
+pub mod ranged;
+pub use ranged::*;
+pub mod syscalls;
+pub use syscalls::*;
+pub mod graph;
+pub use graph::*;
+pub mod value;
+pub use value::*;
+pub mod conversion;
+pub use conversion::*;
+#[cfg(feature = "std")]
+pub mod streaming;
+#[cfg(feature = "std")]
+pub use streaming::*;
+#[cfg(all(unix, feature = "std"))]
+pub mod afl_custom;
+#[cfg(all(unix, feature = "std"))]
+pub use afl_custom::*;
 
 #[cfg(feature = "unicode")]
 pub mod unicode;
@@ -33,6 +65,11 @@ pub use multi::*;
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "python")]
+pub use python::*;
+
 use alloc::{borrow::Cow, boxed::Box, vec::Vec};
 
 use libafl_bolts::{tuples::IntoVec, HasLen, Named};
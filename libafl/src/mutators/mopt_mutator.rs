@@ -186,6 +186,37 @@ impl MOpt {
         Ok(mopt)
     }
 
+    /// The findings attributed to each mutation operator so far, over the core and all pilot swarms.
+    /// The operators are in the order of the mutations tuple of the [`StdMOptMutator`].
+    #[must_use]
+    pub fn operator_finds(&self) -> Vec<u64> {
+        (0..self.operator_num)
+            .map(|i| {
+                self.core_operator_finds_v2[i]
+                    + self
+                        .pilot_operator_finds_v2
+                        .iter()
+                        .map(|swarm| swarm[i])
+                        .sum::<u64>()
+            })
+            .collect()
+    }
+
+    /// How often each mutation operator was used so far, over the core and all pilot swarms.
+    #[must_use]
+    pub fn operator_cycles(&self) -> Vec<u64> {
+        (0..self.operator_num)
+            .map(|i| {
+                self.core_operator_cycles_v2[i]
+                    + self
+                        .pilot_operator_cycles_v2
+                        .iter()
+                        .map(|swarm| swarm[i])
+                        .sum::<u64>()
+            })
+            .collect()
+    }
+
     /// initialize pso
     #[allow(clippy::cast_precision_loss)]
     pub fn pso_initialize(&mut self) -> Result<(), Error> {
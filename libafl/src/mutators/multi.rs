@@ -1,8 +1,17 @@
 //! Mutator definitions for [`MultipartInput`]s. See [`crate::inputs::multi`] for details.
 
-use core::cmp::{min, Ordering};
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cmp::{min, Ordering},
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
 
-use libafl_bolts::{rands::Rand, Error};
+use libafl_bolts::{rands::Rand, Error, Named};
 
 use crate::{
     corpus::{Corpus, CorpusId},
@@ -10,17 +19,18 @@ use crate::{
     inputs::{multi::MultipartInput, HasMutatorBytes, Input},
     mutators::{
         mutations::{
-            rand_range, BitFlipMutator, ByteAddMutator, ByteDecMutator, ByteFlipMutator,
-            ByteIncMutator, ByteInterestingMutator, ByteNegMutator, ByteRandMutator,
-            BytesCopyMutator, BytesDeleteMutator, BytesExpandMutator, BytesInsertCopyMutator,
-            BytesInsertMutator, BytesRandInsertMutator, BytesRandSetMutator, BytesSetMutator,
-            BytesSwapMutator, CrossoverInsertMutator, CrossoverReplaceMutator, DwordAddMutator,
-            DwordInterestingMutator, QwordAddMutator, WordAddMutator, WordInterestingMutator,
+            locate_diffs, rand_range, BitFlipMutator, ByteAddMutator, ByteDecMutator,
+            ByteFlipMutator, ByteIncMutator, ByteInterestingMutator, ByteNegMutator,
+            ByteRandMutator, BytesCopyMutator, BytesDeleteMutator, BytesExpandMutator,
+            BytesInsertCopyMutator, BytesInsertMutator, BytesRandInsertMutator,
+            BytesRandSetMutator, BytesSetMutator, BytesSwapMutator, CrossoverInsertMutator,
+            CrossoverReplaceMutator, DwordAddMutator, DwordInterestingMutator, QwordAddMutator,
+            WordAddMutator, WordInterestingMutator,
         },
         token_mutations::{I2SRandReplace, TokenInsert, TokenReplace},
-        MutationResult, Mutator,
+        ComposedByMutations, MutationResult, Mutator, MutatorsTuple,
     },
-    random_corpus_id,
+    random_corpus_id, random_corpus_id_with_disabled,
     state::{HasCorpus, HasMaxSize, HasRand},
 };
 
@@ -320,3 +330,579 @@ where
         }
     }
 }
+
+/// The default maximum amount of parts [`MultipartAddPartMutator`] grows an input to
+pub const MULTIPART_DEFAULT_MAX_PARTS: usize = 32;
+
+/// Picks a random part of a random testcase in the corpus, or of `input` if that is the current testcase
+fn random_donor_part<I, S>(
+    state: &mut S,
+    input: &MultipartInput<I>,
+) -> Result<Option<(String, I)>, Error>
+where
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand,
+    I: Input,
+{
+    if state.corpus().count_all() == 0 {
+        return Err(Error::empty(
+            "No testcase in the corpus to take a part from",
+        ));
+    }
+    let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+    let part_choice = state.rand_mut().next() as usize;
+
+    if state.corpus().current().is_some_and(|cur| cur == id) {
+        return Ok(input
+            .iter()
+            .nth(part_choice % input.parts().len().max(1))
+            .map(|(name, part)| (name.to_string(), part.clone())));
+    }
+
+    let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+    let other = other_testcase.load_input(state.corpus())?;
+    let donor = other
+        .iter()
+        .nth(part_choice % other.parts().len().max(1))
+        .map(|(name, part)| (name.to_string(), part.clone()));
+    Ok(donor)
+}
+
+/// Replaces a part of the input with a part of the same name from another testcase in the corpus
+#[derive(Debug, Default)]
+pub struct MultipartReplacePartMutator;
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartReplacePartMutator
+where
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand,
+    I: Input,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+    ) -> Result<MutationResult, Error> {
+        if input.parts().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let Some((name, donor)) = random_donor_part(state, input)? else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let parts = input.parts_by_name(&name).count();
+        if parts == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let choice = state.rand_mut().below(parts);
+        let (_, part) = input.parts_by_name_mut(&name).nth(choice).unwrap();
+        *part = donor;
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for MultipartReplacePartMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("MultipartReplacePartMutator");
+        &NAME
+    }
+}
+
+impl MultipartReplacePartMutator {
+    /// Creates a new [`MultipartReplacePartMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Adds a part of another testcase in the corpus to the input, for example an optional header
+#[derive(Debug)]
+pub struct MultipartAddPartMutator {
+    max_parts: usize,
+}
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartAddPartMutator
+where
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand,
+    I: Input,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+    ) -> Result<MutationResult, Error> {
+        if input.parts().len() >= self.max_parts {
+            return Ok(MutationResult::Skipped);
+        }
+        let Some((name, donor)) = random_donor_part(state, input)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        input.add_part(name, donor);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for MultipartAddPartMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("MultipartAddPartMutator");
+        &NAME
+    }
+}
+
+impl Default for MultipartAddPartMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartAddPartMutator {
+    /// Creates a new [`MultipartAddPartMutator`], growing inputs to at most [`MULTIPART_DEFAULT_MAX_PARTS`] parts.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_parts(MULTIPART_DEFAULT_MAX_PARTS)
+    }
+
+    /// Creates a new [`MultipartAddPartMutator`], growing inputs to at most `max_parts` parts.
+    #[must_use]
+    pub fn with_max_parts(max_parts: usize) -> Self {
+        Self { max_parts }
+    }
+}
+
+/// Removes a part of the input. The last part of each required name, and the last part overall, are kept.
+#[derive(Debug, Default)]
+pub struct MultipartRemovePartMutator {
+    required: Vec<String>,
+}
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartRemovePartMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+    ) -> Result<MutationResult, Error> {
+        if input.parts().len() <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let names = input.names();
+        let removable: Vec<usize> = (0..names.len())
+            .filter(|&i| {
+                !self.required.contains(&names[i])
+                    || names.iter().filter(|name| **name == names[i]).count() > 1
+            })
+            .collect();
+        if removable.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = removable[state.rand_mut().below(removable.len())];
+        input.remove_part(idx);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for MultipartRemovePartMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("MultipartRemovePartMutator");
+        &NAME
+    }
+}
+
+impl MultipartRemovePartMutator {
+    /// Creates a new [`MultipartRemovePartMutator`], which may remove any part but the last one.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never removes the last part called `name`
+    #[must_use]
+    pub fn required(mut self, name: &str) -> Self {
+        self.required.push(name.into());
+        self
+    }
+}
+
+/// Splices the tail of a part with the same name of another testcase in the corpus into the part `part_idx`
+#[allow(clippy::cast_sign_loss)]
+fn splice_part<I, S>(
+    state: &mut S,
+    input: &mut MultipartInput<I>,
+    part_idx: usize,
+) -> Result<MutationResult, Error>
+where
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand,
+    I: Input + HasMutatorBytes,
+{
+    if state.corpus().count_all() == 0 {
+        return Err(Error::empty("No testcase in the corpus to splice with"));
+    }
+    let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
+    // We don't want to use the testcase we're already using for splicing
+    if state.corpus().current().is_some_and(|cur| cur == id) {
+        return Ok(MutationResult::Skipped);
+    }
+    let part_choice = state.rand_mut().next() as usize;
+    let name = &input.names()[part_idx];
+
+    let (donor_choice, first_diff, last_diff) = {
+        let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+        let other = other_testcase.load_input(state.corpus())?;
+
+        let donors = other.parts_by_name(name).count();
+        if donors == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let donor_choice = part_choice % donors;
+        let (_, donor) = other.parts_by_name(name).nth(donor_choice).unwrap();
+
+        let (f, l) = locate_diffs(input.parts()[part_idx].bytes(), donor.bytes());
+        if f != l && f >= 0 && l >= 2 {
+            (donor_choice, f as usize, l as usize)
+        } else {
+            return Ok(MutationResult::Skipped);
+        }
+    };
+
+    let split_at = state.rand_mut().between(first_diff, last_diff);
+
+    let other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
+    // Input will already be loaded.
+    let other = other_testcase.input().as_ref().unwrap();
+    let (_, donor) = other.parts_by_name(name).nth(donor_choice).unwrap();
+    let tail = donor.bytes()[split_at..].to_vec();
+
+    input
+        .part_mut(part_idx)
+        .unwrap()
+        .splice(split_at.., tail);
+
+    Ok(MutationResult::Mutated)
+}
+
+/// Splices a random part of the input with a part of the same name from another testcase in the corpus
+#[derive(Debug, Default)]
+pub struct MultipartSpliceMutator;
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartSpliceMutator
+where
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand,
+    I: Input + HasMutatorBytes,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+    ) -> Result<MutationResult, Error> {
+        if input.parts().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let part_idx = state.rand_mut().below(input.parts().len());
+        splice_part(state, input, part_idx)
+    }
+}
+
+impl Named for MultipartSpliceMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("MultipartSpliceMutator");
+        &NAME
+    }
+}
+
+impl MultipartSpliceMutator {
+    /// Creates a new [`MultipartSpliceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A structure-aware havoc [`Mutator`] for [`MultipartInput`]s.
+///
+/// On each call, it picks one part, weighted by the part names, and applies a stack of the embedded
+/// byte mutations to it. Splicing with a part of the same name from another testcase in the corpus
+/// is scheduled like one more mutation. Parts without a configured weight have a weight of `1.0`;
+/// a weight of `0.0` protects a part, like a fixed header, from mutation.
+///
+/// The embedded mutations work on single parts, so use crossover-free mutations, like
+/// [`crate::mutators::havoc_mutations_no_crossover`].
+pub struct MultipartHavocMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    name: Cow<'static, str>,
+    mutations: MT,
+    weights: Vec<(String, f64)>,
+    max_stack_pow: usize,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, MT, S> Debug for MultipartHavocMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MultipartHavocMutator with {} mutations and part weights {:?} for Input type {}",
+            self.mutations.len(),
+            self.weights,
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S> Named for MultipartHavocMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, MT, S> Mutator<MultipartInput<I>, S> for MultipartHavocMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand,
+    I: Input + HasMutatorBytes,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+    ) -> Result<MutationResult, Error> {
+        let Some(part_idx) = self.choose_part(state, input) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let mut r = MutationResult::Skipped;
+        let num = 1_u64 << (1 + state.rand_mut().below(self.max_stack_pow));
+        for _ in 0..num {
+            // The last slot is the splice with a donor part
+            let idx = state.rand_mut().below(self.mutations.len() + 1);
+            let outcome = if idx == self.mutations.len() {
+                splice_part(state, input, part_idx)?
+            } else {
+                let part = input.part_mut(part_idx).unwrap();
+                self.mutations.get_and_mutate(idx.into(), state, part)?
+            };
+            if outcome == MutationResult::Mutated {
+                r = MutationResult::Mutated;
+            }
+        }
+        Ok(r)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.mutations.post_exec_all(state, new_corpus_id)
+    }
+}
+
+impl<I, MT, S> ComposedByMutations<I, MT, S> for MultipartHavocMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    /// Get the mutations
+    #[inline]
+    fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    // Get the mutations (mutable)
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+impl<I, MT, S> MultipartHavocMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    /// Create a new [`MultipartHavocMutator`] applying the byte `mutations` to the parts, all weighted equally
+    pub fn new(mutations: MT) -> Self {
+        Self {
+            name: Cow::from(format!(
+                "MultipartHavocMutator[{}]",
+                mutations.names().join(", ")
+            )),
+            mutations,
+            weights: vec![],
+            max_stack_pow: 7,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the maximum number of stacked mutations to `2^max_stack_pow`
+    #[must_use]
+    pub fn with_max_stack_pow(mut self, max_stack_pow: usize) -> Self {
+        self.max_stack_pow = max_stack_pow.max(1);
+        self
+    }
+
+    /// Sets the relative weight of the parts called `name`, which is `1.0` by default
+    #[must_use]
+    pub fn with_part_weight(mut self, name: &str, weight: f64) -> Self {
+        let weight = weight.max(0.0);
+        match self.weights.iter_mut().find(|(n, _)| n == name) {
+            Some((_, w)) => *w = weight,
+            None => self.weights.push((name.into(), weight)),
+        }
+        self
+    }
+
+    /// The relative weight of the parts called `name`
+    #[must_use]
+    pub fn part_weight(&self, name: &str) -> f64 {
+        self.weights
+            .iter()
+            .find(|(n, _)| n == name)
+            .map_or(1.0, |(_, w)| *w)
+    }
+
+    /// Picks a part by weight, `None` if there is no part with a positive weight
+    fn choose_part(&self, state: &mut S, input: &MultipartInput<I>) -> Option<usize>
+    where
+        S: HasRand,
+    {
+        let total: f64 = input.names().iter().map(|n| self.part_weight(n)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = state.rand_mut().next_float() * total;
+        for (idx, name) in input.names().iter().enumerate() {
+            let weight = self.part_weight(name);
+            if pick < weight {
+                return Some(idx);
+            }
+            pick -= weight;
+        }
+        // Rounding errors may leave a tiny rest
+        input
+            .names()
+            .iter()
+            .rposition(|n| self.part_weight(n) > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasMutatorBytes, MultipartInput},
+        mutators::{
+            havoc_mutations_no_crossover, MultipartAddPartMutator, MultipartHavocMutator,
+            MultipartRemovePartMutator, MultipartReplacePartMutator, MultipartSpliceMutator,
+            MutationResult, Mutator,
+        },
+        state::{HasCorpus, StdState},
+        Error,
+    };
+
+    #[test]
+    fn test_multipart_part_mutators() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1),
+            InMemoryCorpus::<MultipartInput<BytesInput>>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut input = MultipartInput::from([
+            ("header", BytesInput::new(b"GET".to_vec())),
+            ("body", BytesInput::new(b"mine".to_vec())),
+        ]);
+
+        // Without any donors, the mutators can't do anything
+        let mut replace = MultipartReplacePartMutator::new();
+        assert!(matches!(
+            replace.mutate(&mut state, &mut input),
+            Err(Error::Empty(..))
+        ));
+
+        let donor = MultipartInput::from([("body", BytesInput::new(b"donor".to_vec()))]);
+        state.corpus_mut().add(Testcase::new(donor)).unwrap();
+
+        assert_eq!(
+            replace.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.parts()[1].bytes(), b"donor");
+
+        let mut add = MultipartAddPartMutator::with_max_parts(3);
+        add.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(input.names(), ["header", "body", "body"]);
+        assert_eq!(
+            add.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+
+        let mut remove = MultipartRemovePartMutator::new()
+            .required("header")
+            .required("body");
+        remove.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(input.names(), ["header", "body"]);
+        assert_eq!(
+            remove.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+
+    #[test]
+    fn test_multipart_havoc_mutator() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1),
+            InMemoryCorpus::<MultipartInput<BytesInput>>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let donor = MultipartInput::from([
+            ("header", BytesInput::new(b"PUT".to_vec())),
+            ("body", BytesInput::new(b"a body from the donor".to_vec())),
+        ]);
+        state.corpus_mut().add(Testcase::new(donor)).unwrap();
+
+        let mut havoc = MultipartHavocMutator::new(havoc_mutations_no_crossover())
+            .with_part_weight("header", 0.0);
+        assert!((havoc.part_weight("body") - 1.0).abs() < f64::EPSILON);
+        for _ in 0..100 {
+            let mut input = MultipartInput::from([
+                ("header", BytesInput::new(b"GET".to_vec())),
+                ("body", BytesInput::new(b"a body of mine".to_vec())),
+            ]);
+            havoc.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(input.parts()[0].bytes(), b"GET");
+        }
+
+        let mut input = MultipartInput::from([("header", BytesInput::new(b"GET".to_vec()))]);
+        assert_eq!(
+            havoc.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+
+        // Splicing keeps the common prefix, and only takes the tail of the donor part with the same name
+        let mut splice = MultipartSpliceMutator::new();
+        let mut input =
+            MultipartInput::from([("body", BytesInput::new(b"a body of mine".to_vec()))]);
+        for _ in 0..100 {
+            splice.mutate(&mut state, &mut input).unwrap();
+            assert!(input.parts()[0].bytes().starts_with(b"a body "));
+        }
+    }
+}
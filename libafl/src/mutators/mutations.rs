@@ -5,45 +5,42 @@ use alloc::{
     vec::Vec,
 };
 use core::{cmp::min, marker::PhantomData, mem::size_of, ops::Range};
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
 
 use libafl_bolts::{rands::Rand, Named};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    corpus::Corpus,
+    corpus::{Corpus, CorpusId},
     inputs::HasMutatorBytes,
     mutators::{MutationResult, Mutator},
     random_corpus_id_with_disabled,
     state::{HasCorpus, HasMaxSize, HasRand},
-    Error,
+    Error, HasMetadata,
 };
 
-/// Mem move in the own vec
+/// Mem move in the own vec, the source and destination may overlap.
+///
+/// Uses [`slice::copy_within`], a single `memmove`.
 #[inline]
-pub(crate) unsafe fn buffer_self_copy<T>(data: &mut [T], from: usize, to: usize, len: usize) {
-    debug_assert!(!data.is_empty());
+pub fn buffer_self_copy<T: Copy>(data: &mut [T], from: usize, to: usize, len: usize) {
     debug_assert!(from + len <= data.len());
     debug_assert!(to + len <= data.len());
     if len != 0 && from != to {
-        let ptr = data.as_mut_ptr();
-        unsafe {
-            core::ptr::copy(ptr.add(from), ptr.add(to), len);
-        }
+        data.copy_within(from..from + len, to);
     }
 }
 
-/// Mem move between vecs
+/// Mem copy between vecs.
+///
+/// Uses [`slice::copy_from_slice`], a single `memcpy`.
 #[inline]
-pub(crate) unsafe fn buffer_copy<T>(dst: &mut [T], src: &[T], from: usize, to: usize, len: usize) {
-    debug_assert!(!dst.is_empty());
-    debug_assert!(!src.is_empty());
+pub fn buffer_copy<T: Copy>(dst: &mut [T], src: &[T], from: usize, to: usize, len: usize) {
     debug_assert!(from + len <= src.len());
     debug_assert!(to + len <= dst.len());
-    let dst_ptr = dst.as_mut_ptr();
-    let src_ptr = src.as_ptr();
     if len != 0 {
-        unsafe {
-            core::ptr::copy(src_ptr.add(from), dst_ptr.add(to), len);
-        }
+        dst[to..to + len].copy_from_slice(&src[from..from + len]);
     }
 }
 
@@ -56,6 +53,16 @@ pub fn buffer_set<T: Clone>(data: &mut [T], from: usize, len: usize, val: T) {
     data[from..(from + len)].fill(val);
 }
 
+/// Inserts `amount` copies of `val` at `offset`, moving the tail of the input only once,
+/// instead of resizing with zeros, moving the tail, and overwriting the zeros.
+#[inline]
+pub fn buffer_insert_fill<I>(input: &mut I, offset: usize, amount: usize, val: u8)
+where
+    I: HasMutatorBytes,
+{
+    input.splice(offset..offset, core::iter::repeat_n(val, amount));
+}
+
 /// Generate a range of values where (upon repeated calls) each index is likely to appear in the
 /// provided range as likely as any other value
 ///
@@ -116,6 +123,131 @@ pub const INTERESTING_32: [i32; 27] = [
     2147483647,
 ];
 
+/// A state metadata holding the interesting values used by the [`ByteInterestingValuesMutator`],
+/// [`WordInterestingValuesMutator`], and [`DwordInterestingValuesMutator`], and by the
+/// [`crate::stages::DeterministicStage`].
+///
+/// Without it, the AFL tables ([`INTERESTING_8`], [`INTERESTING_16`], [`INTERESTING_32`]) are used.
+/// Start from [`InterestingValuesMetadata::default`] to extend them, or from
+/// [`InterestingValuesMetadata::empty`] to replace them, for example with the magic values of a protocol.
+#[allow(clippy::unsafe_derive_deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterestingValuesMetadata {
+    /// The interesting 8-bit values
+    pub bytes: Vec<u8>,
+    /// The interesting 16-bit values
+    pub words: Vec<u16>,
+    /// The interesting 32-bit values
+    pub dwords: Vec<u32>,
+}
+
+libafl_bolts::impl_serdeany!(InterestingValuesMetadata);
+
+impl Default for InterestingValuesMetadata {
+    /// The interesting values from AFL
+    #[allow(clippy::cast_sign_loss)]
+    fn default() -> Self {
+        Self {
+            bytes: INTERESTING_8.map(|v| v as u8).to_vec(),
+            words: INTERESTING_16.map(|v| v as u16).to_vec(),
+            dwords: INTERESTING_32.map(|v| v as u32).to_vec(),
+        }
+    }
+}
+
+impl InterestingValuesMetadata {
+    /// Creates new metadata without any interesting values
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            bytes: vec![],
+            words: vec![],
+            dwords: vec![],
+        }
+    }
+
+    /// Adds `value` to every table it fits in, as a signed or as an unsigned integer.
+    /// Returns `false` if it did not fit in any table.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn add_value(&mut self, value: i64) -> bool {
+        fn push<T: PartialEq>(table: &mut Vec<T>, value: T) {
+            if !table.contains(&value) {
+                table.push(value);
+            }
+        }
+
+        if (i64::from(i8::MIN)..=i64::from(u8::MAX)).contains(&value) {
+            push(&mut self.bytes, value as u8);
+        }
+        if (i64::from(i16::MIN)..=i64::from(u16::MAX)).contains(&value) {
+            push(&mut self.words, value as u16);
+        }
+        if (i64::from(i32::MIN)..=i64::from(u32::MAX)).contains(&value) {
+            push(&mut self.dwords, value as u32);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Adds the given values, see [`InterestingValuesMetadata::add_value`]
+    pub fn add_values<IT>(&mut self, values: IT) -> &mut Self
+    where
+        IT: IntoIterator<Item = i64>,
+    {
+        for value in values {
+            self.add_value(value);
+        }
+        self
+    }
+
+    /// Reads a file with one decimal, or `0x`-prefixed hexadecimal, value per line, optionally negative.
+    /// Empty lines and lines starting with `#` are ignored.
+    #[cfg(feature = "std")]
+    pub fn add_from_file<P>(&mut self, file: P) -> Result<&mut Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        for line in fs::read_to_string(file)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negative, digits) = match line.strip_prefix('-') {
+                Some(digits) => (true, digits),
+                None => (false, line),
+            };
+            let parsed = match digits
+                .strip_prefix("0x")
+                .or_else(|| digits.strip_prefix("0X"))
+            {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => digits.parse::<i64>(),
+            };
+            let Ok(value) = parsed else {
+                return Err(Error::illegal_argument(format!("Illegal line: {line}")));
+            };
+            if !self.add_value(if negative { -value } else { value }) {
+                return Err(Error::illegal_argument(format!(
+                    "Value out of the 32-bit range: {line}"
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Creates the AFL tables extended by the values in the given file, see [`InterestingValuesMetadata::add_from_file`]
+    #[cfg(feature = "std")]
+    pub fn from_file<P>(file: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut ret = Self::default();
+        ret.add_from_file(file)?;
+        Ok(ret)
+    }
+}
+
 /// Bitflip mutation for inputs with a bytes vector
 #[derive(Default, Debug)]
 pub struct BitFlipMutator;
@@ -447,6 +579,68 @@ interesting_mutator_impl!(ByteInterestingMutator, u8, INTERESTING_8);
 interesting_mutator_impl!(WordInterestingMutator, u16, INTERESTING_16);
 interesting_mutator_impl!(DwordInterestingMutator, u32, INTERESTING_32);
 
+macro_rules! interesting_values_mutator_impl {
+    ($name: ident, $size: ty, $interesting: ident, $table: ident) => {
+        /// Inserts an interesting value from the [`InterestingValuesMetadata`] of the state at a random place in the input vector.
+        ///
+        /// Without the metadata, it falls back to the AFL tables, like the mutator without `Values` in its name.
+        #[derive(Default, Debug)]
+        pub struct $name;
+
+        impl<I, S> Mutator<I, S> for $name
+        where
+            S: HasRand + HasMetadata,
+            I: HasMutatorBytes,
+        {
+            #[allow(clippy::cast_sign_loss)]
+            fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+                let table_len = state
+                    .metadata_map()
+                    .get::<InterestingValuesMetadata>()
+                    .map(|meta| meta.$table.len());
+                if input.bytes().len() < size_of::<$size>() || table_len == Some(0) {
+                    return Ok(MutationResult::Skipped);
+                }
+                let bytes = input.bytes_mut();
+                let upper_bound = (bytes.len() + 1 - size_of::<$size>());
+                let idx = state.rand_mut().below(upper_bound);
+                let val = match table_len {
+                    Some(len) => {
+                        let choice = state.rand_mut().below(len);
+                        state.metadata::<InterestingValuesMetadata>()?.$table[choice]
+                    }
+                    None => *state.rand_mut().choose(&$interesting).unwrap() as $size,
+                };
+                let new_bytes = match state.rand_mut().choose(&[0, 1]).unwrap() {
+                    0 => val.to_be_bytes(),
+                    _ => val.to_le_bytes(),
+                };
+                bytes[idx..idx + size_of::<$size>()].copy_from_slice(&new_bytes);
+                Ok(MutationResult::Mutated)
+            }
+        }
+
+        impl Named for $name {
+            fn name(&self) -> &Cow<'static, str> {
+                static NAME: Cow<'static, str> = Cow::Borrowed(stringify!($name));
+                &NAME
+            }
+        }
+
+        impl $name {
+            #[doc = concat!("Creates a new [`", stringify!($name), "`].")]
+            #[must_use]
+            pub fn new() -> Self {
+                Self
+            }
+        }
+    };
+}
+
+interesting_values_mutator_impl!(ByteInterestingValuesMutator, u8, INTERESTING_8, bytes);
+interesting_values_mutator_impl!(WordInterestingValuesMutator, u16, INTERESTING_16, words);
+interesting_values_mutator_impl!(DwordInterestingValuesMutator, u32, INTERESTING_32, dwords);
+
 /// Bytes delete mutation for inputs with a bytes vector
 #[derive(Default, Debug)]
 pub struct BytesDeleteMutator;
@@ -504,14 +698,12 @@ where
         let range = rand_range(state, size, min(16, max_size - size));
 
         input.resize(size + range.len(), 0);
-        unsafe {
-            buffer_self_copy(
-                input.bytes_mut(),
-                range.start,
-                range.start + range.len(),
-                size - range.start,
-            );
-        }
+        buffer_self_copy(
+            input.bytes_mut(),
+            range.start,
+            range.start + range.len(),
+            size - range.start,
+        );
 
         Ok(MutationResult::Mutated)
     }
@@ -561,11 +753,7 @@ where
 
         let val = input.bytes()[state.rand_mut().below(size)];
 
-        input.resize(size + amount, 0);
-        unsafe {
-            buffer_self_copy(input.bytes_mut(), offset, offset + amount, size - offset);
-        }
-        buffer_set(input.bytes_mut(), offset, amount, val);
+        buffer_insert_fill(input, offset, amount, val);
 
         Ok(MutationResult::Mutated)
     }
@@ -615,11 +803,7 @@ where
 
         let val = state.rand_mut().next() as u8;
 
-        input.resize(size + amount, 0);
-        unsafe {
-            buffer_self_copy(input.bytes_mut(), offset, offset + amount, size - offset);
-        }
-        buffer_set(input.bytes_mut(), offset, amount, val);
+        buffer_insert_fill(input, offset, amount, val);
 
         Ok(MutationResult::Mutated)
     }
@@ -736,9 +920,7 @@ where
         let target = state.rand_mut().below(size);
         let range = rand_range(state, size, size - target);
 
-        unsafe {
-            buffer_self_copy(input.bytes_mut(), range.start, target, range.len());
-        }
+        buffer_self_copy(input.bytes_mut(), range.start, target, range.len());
 
         Ok(MutationResult::Mutated)
     }
@@ -783,23 +965,21 @@ where
 
         input.resize(size + range.len(), 0);
         self.tmp_buf.resize(range.len(), 0);
-        unsafe {
-            buffer_copy(
-                &mut self.tmp_buf,
-                input.bytes(),
-                range.start,
-                0,
-                range.len(),
-            );
-
-            buffer_self_copy(
-                input.bytes_mut(),
-                target,
-                target + range.len(),
-                size - target,
-            );
-            buffer_copy(input.bytes_mut(), &self.tmp_buf, 0, target, range.len());
-        }
+        buffer_copy(
+            &mut self.tmp_buf,
+            input.bytes(),
+            range.start,
+            0,
+            range.len(),
+        );
+
+        buffer_self_copy(
+            input.bytes_mut(),
+            target,
+            target + range.len(),
+            size - target,
+        );
+        buffer_copy(input.bytes_mut(), &self.tmp_buf, 0, target, range.len());
         Ok(MutationResult::Mutated)
     }
 }
@@ -843,81 +1023,79 @@ where
 
             let second = rand_range(state, first.start, first.start);
             self.tmp_buf.resize(first.len(), 0);
-            unsafe {
-                // If range first is larger
-                if first.len() >= second.len() {
-                    let diff_in_size = first.len() - second.len();
-
-                    // copy first range to tmp
-                    buffer_copy(
-                        &mut self.tmp_buf,
-                        input.bytes(),
-                        first.start,
-                        0,
-                        first.len(),
-                    );
-
-                    // adjust second.end..first.start, move them by diff_in_size to the right
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        second.end,
-                        second.end + diff_in_size,
-                        first.start - second.end,
-                    );
-
-                    // copy second to where first was
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        second.start,
-                        first.start + diff_in_size,
-                        second.len(),
-                    );
-
-                    // copy first back
-                    buffer_copy(
-                        input.bytes_mut(),
-                        &self.tmp_buf,
-                        0,
-                        second.start,
-                        first.len(),
-                    );
-                } else {
-                    let diff_in_size = second.len() - first.len();
-
-                    // copy first range to tmp
-                    buffer_copy(
-                        &mut self.tmp_buf,
-                        input.bytes(),
-                        first.start,
-                        0,
-                        first.len(),
-                    );
-
-                    // adjust second.end..first.start, move them by diff_in_size to the left
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        second.end,
-                        second.end - diff_in_size,
-                        first.start - second.end,
-                    );
-
-                    // copy second to where first was
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        second.start,
-                        first.start - diff_in_size,
-                        second.len(),
-                    );
-
-                    // copy first back
-                    buffer_copy(
-                        input.bytes_mut(),
-                        &self.tmp_buf,
-                        0,
-                        second.start,
-                        first.len(),
-                    );
-                }
+            // If range first is larger
+            if first.len() >= second.len() {
+                let diff_in_size = first.len() - second.len();
+
+                // copy first range to tmp
+                buffer_copy(
+                    &mut self.tmp_buf,
+                    input.bytes(),
+                    first.start,
+                    0,
+                    first.len(),
+                );
+
+                // adjust second.end..first.start, move them by diff_in_size to the right
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    second.end,
+                    second.end + diff_in_size,
+                    first.start - second.end,
+                );
+
+                // copy second to where first was
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    second.start,
+                    first.start + diff_in_size,
+                    second.len(),
+                );
+
+                // copy first back
+                buffer_copy(
+                    input.bytes_mut(),
+                    &self.tmp_buf,
+                    0,
+                    second.start,
+                    first.len(),
+                );
+            } else {
+                let diff_in_size = second.len() - first.len();
+
+                // copy first range to tmp
+                buffer_copy(
+                    &mut self.tmp_buf,
+                    input.bytes(),
+                    first.start,
+                    0,
+                    first.len(),
+                );
+
+                // adjust second.end..first.start, move them by diff_in_size to the left
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    second.end,
+                    second.end - diff_in_size,
+                    first.start - second.end,
+                );
+
+                // copy second to where first was
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    second.start,
+                    first.start - diff_in_size,
+                    second.len(),
+                );
+
+                // copy first back
+                buffer_copy(
+                    input.bytes_mut(),
+                    &self.tmp_buf,
+                    0,
+                    second.start,
+                    first.len(),
+                );
             }
             Ok(MutationResult::Mutated)
         } else if first.end != size {
@@ -927,78 +1105,76 @@ where
             second.end += first.end;
 
             self.tmp_buf.resize(second.len(), 0);
-            unsafe {
-                if second.len() >= first.len() {
-                    let diff_in_size = second.len() - first.len();
-                    // copy second range to tmp
-                    buffer_copy(
-                        &mut self.tmp_buf,
-                        input.bytes(),
-                        second.start,
-                        0,
-                        second.len(),
-                    );
-
-                    // adjust first.end..second.start, move them by diff_in_size to the right
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        first.end,
-                        first.end + diff_in_size,
-                        second.start - first.end,
-                    );
-
-                    // copy first to where second was
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        first.start,
-                        second.start + diff_in_size,
-                        first.len(),
-                    );
-
-                    // copy second back
-                    buffer_copy(
-                        input.bytes_mut(),
-                        &self.tmp_buf,
-                        0,
-                        first.start,
-                        second.len(),
-                    );
-                } else {
-                    let diff_in_size = first.len() - second.len();
-                    // copy second range to tmp
-                    buffer_copy(
-                        &mut self.tmp_buf,
-                        input.bytes(),
-                        second.start,
-                        0,
-                        second.len(),
-                    );
-
-                    // adjust first.end..second.start, move them by diff_in_size to the left
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        first.end,
-                        first.end - diff_in_size,
-                        second.start - first.end,
-                    );
-
-                    // copy first to where second was
-                    buffer_self_copy(
-                        input.bytes_mut(),
-                        first.start,
-                        second.start - diff_in_size,
-                        first.len(),
-                    );
-
-                    // copy second back
-                    buffer_copy(
-                        input.bytes_mut(),
-                        &self.tmp_buf,
-                        0,
-                        first.start,
-                        second.len(),
-                    );
-                }
+            if second.len() >= first.len() {
+                let diff_in_size = second.len() - first.len();
+                // copy second range to tmp
+                buffer_copy(
+                    &mut self.tmp_buf,
+                    input.bytes(),
+                    second.start,
+                    0,
+                    second.len(),
+                );
+
+                // adjust first.end..second.start, move them by diff_in_size to the right
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    first.end,
+                    first.end + diff_in_size,
+                    second.start - first.end,
+                );
+
+                // copy first to where second was
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    first.start,
+                    second.start + diff_in_size,
+                    first.len(),
+                );
+
+                // copy second back
+                buffer_copy(
+                    input.bytes_mut(),
+                    &self.tmp_buf,
+                    0,
+                    first.start,
+                    second.len(),
+                );
+            } else {
+                let diff_in_size = first.len() - second.len();
+                // copy second range to tmp
+                buffer_copy(
+                    &mut self.tmp_buf,
+                    input.bytes(),
+                    second.start,
+                    0,
+                    second.len(),
+                );
+
+                // adjust first.end..second.start, move them by diff_in_size to the left
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    first.end,
+                    first.end - diff_in_size,
+                    second.start - first.end,
+                );
+
+                // copy first to where second was
+                buffer_self_copy(
+                    input.bytes_mut(),
+                    first.start,
+                    second.start - diff_in_size,
+                    first.len(),
+                );
+
+                // copy second back
+                buffer_copy(
+                    input.bytes_mut(),
+                    &self.tmp_buf,
+                    0,
+                    first.start,
+                    second.len(),
+                );
             }
 
             Ok(MutationResult::Mutated)
@@ -1038,24 +1214,20 @@ impl<I: HasMutatorBytes> CrossoverInsertMutator<I> {
         other: &I2,
     ) -> MutationResult {
         input.resize(size + range.len(), 0);
-        unsafe {
-            buffer_self_copy(
-                input.bytes_mut(),
-                target,
-                target + range.len(),
-                size - target,
-            );
-        }
-
-        unsafe {
-            buffer_copy(
-                input.bytes_mut(),
-                other.bytes(),
-                range.start,
-                target,
-                range.len(),
-            );
-        }
+        buffer_self_copy(
+            input.bytes_mut(),
+            target,
+            target + range.len(),
+            size - target,
+        );
+
+        buffer_copy(
+            input.bytes_mut(),
+            other.bytes(),
+            range.start,
+            target,
+            range.len(),
+        );
         MutationResult::Mutated
     }
 }
@@ -1081,6 +1253,27 @@ where
             }
         }
 
+        Self::crossover_insert_from(state, input, id)
+    }
+}
+
+impl<I: HasMutatorBytes> CrossoverInsertMutator<I> {
+    /// Inserts a random part of the corpus entry `id` into the `input`
+    pub(crate) fn crossover_insert_from<S>(
+        state: &mut S,
+        input: &mut I,
+        id: CorpusId,
+    ) -> Result<MutationResult, Error>
+    where
+        S: HasCorpus + HasRand + HasMaxSize,
+        S::Input: HasMutatorBytes,
+    {
+        let size = input.bytes().len();
+        let max_size = state.max_size();
+        if size >= max_size {
+            return Ok(MutationResult::Skipped);
+        }
+
         let other_size = {
             let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
             other_testcase.load_input(state.corpus())?.bytes().len()
@@ -1131,15 +1324,13 @@ impl<I: HasMutatorBytes> CrossoverReplaceMutator<I> {
         range: Range<usize>,
         other: &I2,
     ) -> MutationResult {
-        unsafe {
-            buffer_copy(
-                input.bytes_mut(),
-                other.bytes(),
-                range.start,
-                target,
-                range.len(),
-            );
-        }
+        buffer_copy(
+            input.bytes_mut(),
+            other.bytes(),
+            range.start,
+            target,
+            range.len(),
+        );
         MutationResult::Mutated
     }
 }
@@ -1164,6 +1355,26 @@ where
             }
         }
 
+        Self::crossover_replace_from(state, input, id)
+    }
+}
+
+impl<I: HasMutatorBytes> CrossoverReplaceMutator<I> {
+    /// Overwrites a random part of the `input` with a random part of the corpus entry `id`
+    pub(crate) fn crossover_replace_from<S>(
+        state: &mut S,
+        input: &mut I,
+        id: CorpusId,
+    ) -> Result<MutationResult, Error>
+    where
+        S: HasCorpus + HasRand,
+        S::Input: HasMutatorBytes,
+    {
+        let size = input.bytes().len();
+        if size == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
         let other_size = {
             let mut testcase = state.corpus().get_from_all(id)?.borrow_mut();
             testcase.load_input(state.corpus())?.bytes().len()
@@ -1202,7 +1413,7 @@ impl<I> CrossoverReplaceMutator<I> {
 }
 
 /// Returns the first and last diff position between the given vectors, stopping at the min len
-fn locate_diffs(this: &[u8], other: &[u8]) -> (i64, i64) {
+pub(crate) fn locate_diffs(this: &[u8], other: &[u8]) -> (i64, i64) {
     let mut first_diff: i64 = -1;
     let mut last_diff: i64 = -1;
     for (i, (this_el, other_el)) in this.iter().zip(other.iter()).enumerate() {
@@ -1227,7 +1438,6 @@ where
     S: HasCorpus + HasRand,
     S::Input: HasMutatorBytes,
 {
-    #[allow(clippy::cast_sign_loss)]
     fn mutate(&mut self, state: &mut S, input: &mut S::Input) -> Result<MutationResult, Error> {
         let id = random_corpus_id_with_disabled!(state.corpus(), state.rand_mut());
         // We don't want to use the testcase we're already using for splicing
@@ -1237,6 +1447,22 @@ where
             }
         }
 
+        Self::splice_from(state, input, id)
+    }
+}
+
+impl SpliceMutator {
+    /// Splices the tail of the corpus entry `id` into the `input`, somewhere between their first and last difference
+    #[allow(clippy::cast_sign_loss)]
+    pub(crate) fn splice_from<S>(
+        state: &mut S,
+        input: &mut S::Input,
+        id: CorpusId,
+    ) -> Result<MutationResult, Error>
+    where
+        S: HasCorpus + HasRand,
+        S::Input: HasMutatorBytes,
+    {
         let (first_diff, last_diff) = {
             let mut other_testcase = state.corpus().get_from_all(id)?.borrow_mut();
             let other = other_testcase.load_input(state.corpus())?;
@@ -1647,4 +1873,39 @@ mod tests {
             < 500));
         Ok(())
     }
+
+    #[test]
+    fn test_interesting_values_metadata() {
+        let mut meta = InterestingValuesMetadata::empty();
+        meta.add_values([0x1337, -1, 1 << 40]);
+        assert_eq!(meta.bytes, vec![0xff]);
+        assert_eq!(meta.words, vec![0x1337, 0xffff]);
+        assert_eq!(meta.dwords, vec![0x1337, 0xffff_ffff]);
+
+        let mut state = test_state();
+        let mut meta = InterestingValuesMetadata::empty();
+        meta.add_value(0x1337);
+        state.add_metadata(meta);
+        for _ in 0..16 {
+            let mut input = BytesInput::new(vec![0; 2]);
+            WordInterestingValuesMutator::new()
+                .mutate(&mut state, &mut input)
+                .unwrap();
+            assert!(input.bytes == [0x13, 0x37] || input.bytes == [0x37, 0x13]);
+        }
+        let mut input = BytesInput::new(vec![0; 1]);
+        assert_eq!(
+            ByteInterestingValuesMutator::new()
+                .mutate(&mut state, &mut input)
+                .unwrap(),
+            MutationResult::Skipped
+        );
+        // The AFL tables stay in use by the mutators not reading the metadata
+        assert_eq!(
+            ByteInterestingMutator::new()
+                .mutate(&mut state, &mut input)
+                .unwrap(),
+            MutationResult::Mutated
+        );
+    }
 }
@@ -0,0 +1,149 @@
+//! A [`Mutator`] combinator applying a fixed sequence of mutators, to build compound operators
+//! like "delete a region, then repair the brackets" without writing a new mutator type.
+
+use alloc::borrow::Cow;
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+use libafl_bolts::Named;
+
+use crate::{
+    corpus::CorpusId,
+    mutators::{ComposedByMutations, MutationResult, Mutator, MutatorsTuple},
+    Error,
+};
+
+/// A [`Mutator`] that applies all embedded mutations in order to the same input,
+/// each one seeing the result of the previous one.
+///
+/// The pipeline stops at the first mutation that reports [`MutationResult::Skipped`], and then reports
+/// [`MutationResult::Skipped`] itself, since the compound operation did not happen as a whole.
+/// The input may already be changed by the earlier mutations in this case, and should be discarded.
+pub struct PipelineMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    name: Cow<'static, str>,
+    mutations: MT,
+    phantom: PhantomData<(I, S)>,
+}
+
+impl<I, MT, S> Debug for PipelineMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PipelineMutator with {} mutations for Input type {}",
+            self.mutations.len(),
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S> Named for PipelineMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, MT, S> Mutator<I, S> for PipelineMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        if self.mutations.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        for idx in 0..self.mutations.len() {
+            if self.mutations.get_and_mutate(idx.into(), state, input)? == MutationResult::Skipped {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.mutations.post_exec_all(state, new_corpus_id)
+    }
+}
+
+impl<I, MT, S> ComposedByMutations<I, MT, S> for PipelineMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    /// Get the mutations
+    #[inline]
+    fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    // Get the mutations (mutable)
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+impl<I, MT, S> PipelineMutator<I, MT, S>
+where
+    MT: MutatorsTuple<I, S>,
+{
+    /// Create a new [`PipelineMutator`] applying the `mutations` in order.
+    ///
+    /// Its name joins the names of the mutations, like `Pipeline[BytesDeleteMutator -> BracketRepair]`,
+    /// so the compound operator shows up as one in the stats.
+    pub fn new(mutations: MT) -> Self {
+        Self {
+            name: Cow::from(format!("Pipeline[{}]", mutations.names().join(" -> "))),
+            mutations,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{tuples::tuple_list, Named};
+
+    use crate::{
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{ByteIncMutator, BytesDeleteMutator, MutationResult, Mutator, PipelineMutator},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_pipeline_mutator() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut pipeline =
+            PipelineMutator::new(tuple_list!(ByteIncMutator::new(), ByteIncMutator::new()));
+        assert_eq!(
+            pipeline.name(),
+            "Pipeline[ByteIncMutator -> ByteIncMutator]"
+        );
+
+        let mut input = BytesInput::new(vec![0]);
+        assert_eq!(
+            pipeline.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), [2]);
+
+        // Deleting from a single byte is skipped, so the whole pipeline is
+        let mut pipeline = PipelineMutator::new(tuple_list!(
+            ByteIncMutator::new(),
+            BytesDeleteMutator::new()
+        ));
+        assert_eq!(
+            pipeline.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! The [`PyMutator`] calls a Python function as a [`Mutator`], to prototype mutations without recompiling the fuzzer.
+//!
+//! The function is called as `mutate(data: bytes, max_size: int, seed: int)` and returns the mutated
+//! `bytes` or `bytearray`, or `None` to skip. Results longer than `max_size` are truncated.
+//!
+//! ```python
+//! import random
+//!
+//! def mutate(data, max_size, seed):
+//!     rand = random.Random(seed)
+//!     if not data:
+//!         return None
+//!     data = bytearray(data)
+//!     data[rand.randrange(len(data))] = rand.randrange(256)
+//!     return data
+//! ```
+
+use alloc::{borrow::Cow, string::String};
+use std::{fs, path::Path};
+
+use libafl_bolts::{rands::Rand, Named};
+use pyo3::{
+    types::{PyByteArray, PyBytes, PyModule},
+    PyAny, PyErr, PyObject, Python,
+};
+
+use crate::{
+    inputs::HasMutatorBytes,
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+fn py_error(py: Python, err: &PyErr) -> Error {
+    let traceback = err
+        .traceback(py)
+        .and_then(|traceback| traceback.format().ok())
+        .unwrap_or_default();
+    Error::illegal_state(format!("The Python mutator failed: {traceback}{err}"))
+}
+
+/// A [`Mutator`] calling a Python function, see the [module documentation](self)
+#[derive(Debug)]
+pub struct PyMutator {
+    function: PyObject,
+    name: Cow<'static, str>,
+}
+
+impl PyMutator {
+    /// Creates a new [`PyMutator`], calling `function`
+    #[must_use]
+    pub fn new(function: PyObject) -> Self {
+        pyo3::prepare_freethreaded_python();
+        let name = Python::with_gil(|py| {
+            function
+                .getattr(py, "__name__")
+                .and_then(|name| name.extract::<String>(py))
+                .unwrap_or_else(|_| "<function>".into())
+        });
+        Self {
+            function,
+            name: Cow::Owned(format!("PyMutator({name})")),
+        }
+    }
+
+    /// Creates a new [`PyMutator`], calling the function `function_name` of the Python script at `path`
+    pub fn from_file<P>(path: P, function_name: &str) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let code = fs::read_to_string(path)?;
+        let file_name = path.to_string_lossy();
+        let module_name = path
+            .file_stem()
+            .map_or(Cow::Borrowed("mutator"), |stem| stem.to_string_lossy());
+        pyo3::prepare_freethreaded_python();
+        let function = Python::with_gil(|py| {
+            PyModule::from_code(py, &code, &file_name, &module_name)
+                .and_then(|module| module.getattr(function_name))
+                .map(PyObject::from)
+                .map_err(|err| py_error(py, &err))
+        })?;
+        Ok(Self::new(function))
+    }
+}
+
+impl Named for PyMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Mutator<I, S> for PyMutator
+where
+    I: HasMutatorBytes,
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let seed = state.rand_mut().next();
+        Python::with_gil(|py| {
+            let result = self
+                .function
+                .call1(py, (PyBytes::new(py, input.bytes()), max_size, seed))
+                .map_err(|err| py_error(py, &err))?;
+            let result: &PyAny = result.as_ref(py);
+            if result.is_none() {
+                return Ok(MutationResult::Skipped);
+            }
+            // Copy straight from the Python object into the input, reusing its allocation
+            let write = |input: &mut I, bytes: &[u8]| {
+                let bytes = &bytes[..bytes.len().min(max_size)];
+                input.resize(bytes.len(), 0);
+                input.bytes_mut().copy_from_slice(bytes);
+            };
+            if let Ok(bytes) = result.downcast::<PyBytes>() {
+                write(input, bytes.as_bytes());
+            } else if let Ok(bytes) = result.downcast::<PyByteArray>() {
+                // Safe, as nothing else runs Python code while the bytes are borrowed
+                write(input, unsafe { bytes.as_bytes() });
+            } else {
+                return Err(Error::illegal_state(format!(
+                    "The Python mutator {} returned neither bytes, a bytearray, nor None",
+                    self.name
+                )));
+            }
+            Ok(MutationResult::Mutated)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{python::PyMutator, MutationResult, Mutator},
+        state::{test::test_std_state, HasMaxSize},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_py_mutator() {
+        let path = env::temp_dir().join(format!("libafl_py_mutator_{}.py", process::id()));
+        fs::write(
+            &path,
+            "def mutate(data, max_size, seed):\n    if not data:\n        return None\n    return bytearray(data[::-1]) + b'!' * max_size\n",
+        )
+        .unwrap();
+        let mut mutator = PyMutator::from_file(&path, "mutate").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut state = test_std_state::<BytesInput>();
+        state.set_max_size(6);
+        let mut input = BytesInput::new(b"abc".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"cba!!!");
+
+        let mut empty = BytesInput::new(vec![]);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut empty).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+}
@@ -0,0 +1,527 @@
+//! Mutators inspired by [radamsa](https://gitlab.com/akihe/radamsa), working on the textual structure of an input.
+//!
+//! In contrast to the AFL-style havoc mutations, these repeat chunks, shuffle lines, mangle the decimal numbers
+//! found in the input, and add format strings, quotes, and other silly values at the boundaries of text.
+//! Use [`radamsa_mutations`] in a [`crate::mutators::StdScheduledMutator`], or merge them with the havoc mutations.
+
+use alloc::{borrow::Cow, string::ToString, vec::Vec};
+use core::{cmp::min, ops::Range};
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    Named,
+};
+
+use crate::{
+    inputs::HasMutatorBytes,
+    mutators::{mutations::rand_range, MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// The maximum power of two of repetitions of the [`RadamsaRepeatMutator`] and [`RadamsaLineRepeatMutator`]
+pub const RADAMSA_MAX_REPEAT_POW: usize = 10;
+
+/// The maximum length of the chunk the [`RadamsaRepeatMutator`] repeats
+const RADAMSA_MAX_CHUNK: usize = 32;
+
+/// Values the [`RadamsaNumberMutator`] replaces numbers with, around the limits of common integer types
+const RADAMSA_INTERESTING_NUMBERS: [i128; 20] = [
+    -1,
+    127,
+    128,
+    255,
+    256,
+    -128,
+    -129,
+    32767,
+    32768,
+    65535,
+    65536,
+    -32769,
+    2_147_483_647,
+    2_147_483_648,
+    4_294_967_295,
+    4_294_967_296,
+    -2_147_483_649,
+    9_223_372_036_854_775_807,
+    9_223_372_036_854_775_808,
+    18_446_744_073_709_551_616,
+];
+
+/// The silly values the [`RadamsaAsciiBoundaryMutator`] inserts
+const RADAMSA_SILLY: [&[u8]; 26] = [
+    b"%n",
+    b"%s",
+    b"%p%p%p%p",
+    b"%x",
+    b"%d",
+    b"\0",
+    b"\r\n",
+    b"\n",
+    b"'",
+    b"\"",
+    b"\\",
+    b"`",
+    b"$(",
+    b"${",
+    b"{{",
+    b"}}",
+    b"../",
+    b"..\\",
+    b"%00",
+    b"\xff",
+    b"\xc0\xaf",
+    b"\xef\xbb\xbf",
+    b"\xf0\x9f\x92\xa9",
+    b"<script>",
+    b"&#",
+    b"NaN",
+];
+
+/// The ranges of the lines of `bytes`, without their `\n` terminators
+fn line_ranges(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'\n' {
+            lines.push(start..i);
+            start = i + 1;
+        }
+    }
+    if start < bytes.len() {
+        lines.push(start..bytes.len());
+    }
+    lines
+}
+
+/// The ranges of decimal numbers in `bytes`, including a leading `-`
+fn number_ranges(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut numbers = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_digit() {
+            let start = if i > 0 && bytes[i - 1] == b'-' {
+                i - 1
+            } else {
+                i
+            };
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            numbers.push(start..i);
+        } else {
+            i += 1;
+        }
+    }
+    numbers
+}
+
+/// The ranges of runs of at least two printable ascii chars in `bytes`
+fn text_ranges(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut texts = Vec::new();
+    let mut start = None;
+    for (i, b) in bytes.iter().enumerate() {
+        let printable = b.is_ascii_graphic() || *b == b' ';
+        match (printable, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                if i - s >= 2 {
+                    texts.push(s..i);
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        if bytes.len() - s >= 2 {
+            texts.push(s..bytes.len());
+        }
+    }
+    texts
+}
+
+/// Picks a number of repetitions, exponentially distributed up to `2^RADAMSA_MAX_REPEAT_POW`
+fn repetitions<S>(state: &mut S) -> usize
+where
+    S: HasRand,
+{
+    let pow = 1 + state.rand_mut().below(RADAMSA_MAX_REPEAT_POW);
+    1 + state.rand_mut().below(1 << pow)
+}
+
+/// Repeats a chunk of the input many times in a row, like radamsa's `sr` and `td`, to find length and loop bugs
+#[derive(Default, Debug)]
+pub struct RadamsaRepeatMutator;
+
+impl<I, S> Mutator<I, S> for RadamsaRepeatMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let size = input.bytes().len();
+        if size == 0 || size >= max_size {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let range = rand_range(state, size, min(size, RADAMSA_MAX_CHUNK));
+        let count = min(repetitions(state), (max_size - size) / range.len());
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let chunk = input.bytes()[range.clone()].to_vec();
+        input.splice(
+            range.end..range.end,
+            chunk.iter().cycle().take(chunk.len() * count).copied(),
+        );
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for RadamsaRepeatMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RadamsaRepeatMutator");
+        &NAME
+    }
+}
+
+impl RadamsaRepeatMutator {
+    /// Creates a new [`RadamsaRepeatMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Swaps two lines of the input, like radamsa's `ls`
+#[derive(Default, Debug)]
+pub struct RadamsaLineSwapMutator;
+
+impl<I, S> Mutator<I, S> for RadamsaLineSwapMutator
+where
+    S: HasRand,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let lines = line_ranges(input.bytes());
+        if lines.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let first = state.rand_mut().below(lines.len() - 1);
+        let second = first + 1 + state.rand_mut().below(lines.len() - first - 1);
+        let (first, second) = (lines[first].clone(), lines[second].clone());
+
+        let bytes = input.bytes();
+        let mut swapped = Vec::with_capacity(second.end - first.start);
+        swapped.extend_from_slice(&bytes[second.clone()]);
+        swapped.extend_from_slice(&bytes[first.end..second.start]);
+        swapped.extend_from_slice(&bytes[first.clone()]);
+        if swapped == bytes[first.start..second.end] {
+            return Ok(MutationResult::Skipped);
+        }
+        input.splice(first.start..second.end, swapped);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for RadamsaLineSwapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RadamsaLineSwapMutator");
+        &NAME
+    }
+}
+
+impl RadamsaLineSwapMutator {
+    /// Creates a new [`RadamsaLineSwapMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Deletes a line of the input, like radamsa's `ld`
+#[derive(Default, Debug)]
+pub struct RadamsaLineDeleteMutator;
+
+impl<I, S> Mutator<I, S> for RadamsaLineDeleteMutator
+where
+    S: HasRand,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let lines = line_ranges(input.bytes());
+        if lines.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let line = lines[state.rand_mut().below(lines.len())].clone();
+        // Take the terminator with it, if there is one
+        let end = min(line.end + 1, input.bytes().len());
+        input.drain(line.start..end);
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for RadamsaLineDeleteMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RadamsaLineDeleteMutator");
+        &NAME
+    }
+}
+
+impl RadamsaLineDeleteMutator {
+    /// Creates a new [`RadamsaLineDeleteMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Repeats a line of the input many times, like radamsa's `lr`
+#[derive(Default, Debug)]
+pub struct RadamsaLineRepeatMutator;
+
+impl<I, S> Mutator<I, S> for RadamsaLineRepeatMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let size = input.bytes().len();
+        if size >= max_size {
+            return Ok(MutationResult::Skipped);
+        }
+        let lines = line_ranges(input.bytes());
+        if lines.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let line = lines[state.rand_mut().below(lines.len())].clone();
+        let mut unit = input.bytes()[line.clone()].to_vec();
+        unit.push(b'\n');
+        let count = min(repetitions(state), (max_size - size) / unit.len());
+        if count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        // Insert in front of the line, so the last line does not need a terminator
+        input.splice(
+            line.start..line.start,
+            unit.iter().cycle().take(unit.len() * count).copied(),
+        );
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for RadamsaLineRepeatMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RadamsaLineRepeatMutator");
+        &NAME
+    }
+}
+
+impl RadamsaLineRepeatMutator {
+    /// Creates a new [`RadamsaLineRepeatMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Replaces a decimal number in the input with a close or an interesting one, like radamsa's `num`
+#[derive(Default, Debug)]
+pub struct RadamsaNumberMutator;
+
+impl<I, S> Mutator<I, S> for RadamsaNumberMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let numbers = number_ranges(input.bytes());
+        if numbers.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let range = numbers[state.rand_mut().below(numbers.len())].clone();
+        // The range only contains ascii, numbers too large for an i128 count as the maximum
+        let value = core::str::from_utf8(&input.bytes()[range.clone()])
+            .ok()
+            .and_then(|text| text.parse::<i128>().ok())
+            .unwrap_or(i128::MAX);
+
+        let mutated = match state.rand_mut().below(8) {
+            0 => value.saturating_add(1),
+            1 => value.saturating_sub(1),
+            2 => 0,
+            3 => value.saturating_neg(),
+            4 => value.saturating_mul(1 + state.rand_mut().below(16) as i128),
+            5 => value.saturating_add(state.rand_mut().below(1024) as i128 - 512),
+            6 => i128::from(state.rand_mut().next()),
+            _ => *state
+                .rand_mut()
+                .choose(&RADAMSA_INTERESTING_NUMBERS)
+                .unwrap(),
+        };
+        if mutated == value {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let text = mutated.to_string();
+        if input.bytes().len() - range.len() + text.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        input.splice(range, text.bytes());
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for RadamsaNumberMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RadamsaNumberMutator");
+        &NAME
+    }
+}
+
+impl RadamsaNumberMutator {
+    /// Creates a new [`RadamsaNumberMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Inserts silly values, like format strings, quotes, or a very long run of the same char,
+/// at the start, the end, or in the middle of a text in the input, like radamsa's `ab`
+#[derive(Default, Debug)]
+pub struct RadamsaAsciiBoundaryMutator;
+
+impl<I, S> Mutator<I, S> for RadamsaAsciiBoundaryMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasMutatorBytes,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let max_size = state.max_size();
+        let size = input.bytes().len();
+        if size >= max_size {
+            return Ok(MutationResult::Skipped);
+        }
+        let texts = text_ranges(input.bytes());
+        if texts.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let text = texts[state.rand_mut().below(texts.len())].clone();
+        let at = match state.rand_mut().below(3) {
+            0 => text.start,
+            1 => text.end,
+            _ => state.rand_mut().between(text.start, text.end),
+        };
+
+        if state.rand_mut().coinflip(0.75) {
+            let silly = *state.rand_mut().choose(&RADAMSA_SILLY).unwrap();
+            if size + silly.len() > max_size {
+                return Ok(MutationResult::Skipped);
+            }
+            input.splice(at..at, silly.iter().copied());
+        } else {
+            // A long run of a char of the text, to overflow fixed-size buffers
+            let val = input.bytes()[min(at, text.end - 1)];
+            let count = min(repetitions(state), max_size - size);
+            input.splice(at..at, core::iter::repeat_n(val, count));
+        }
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for RadamsaAsciiBoundaryMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("RadamsaAsciiBoundaryMutator");
+        &NAME
+    }
+}
+
+impl RadamsaAsciiBoundaryMutator {
+    /// Creates a new [`RadamsaAsciiBoundaryMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Tuple type of the radamsa-style mutations
+pub type RadamsaMutationsType = tuple_list_type!(
+    RadamsaRepeatMutator,
+    RadamsaLineSwapMutator,
+    RadamsaLineDeleteMutator,
+    RadamsaLineRepeatMutator,
+    RadamsaNumberMutator,
+    RadamsaAsciiBoundaryMutator,
+);
+
+/// Get the radamsa-style mutations
+#[must_use]
+pub fn radamsa_mutations() -> RadamsaMutationsType {
+    tuple_list!(
+        RadamsaRepeatMutator::new(),
+        RadamsaLineSwapMutator::new(),
+        RadamsaLineDeleteMutator::new(),
+        RadamsaLineRepeatMutator::new(),
+        RadamsaNumberMutator::new(),
+        RadamsaAsciiBoundaryMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{
+            radamsa_mutations, MutationResult, Mutator, RadamsaLineSwapMutator,
+            RadamsaNumberMutator, StdScheduledMutator,
+        },
+        state::{test::test_std_state, HasMaxSize},
+    };
+
+    #[test]
+    fn test_radamsa_mutations() {
+        let mut state = test_std_state::<BytesInput>();
+
+        let mut swap = RadamsaLineSwapMutator::new();
+        let mut input = BytesInput::new(b"first\nsecond\n".to_vec());
+        swap.mutate(&mut state, &mut input).unwrap();
+        assert_eq!(input.bytes(), b"second\nfirst\n");
+
+        let mut number = RadamsaNumberMutator::new();
+        for _ in 0..100 {
+            let mut input = BytesInput::new(b"len=41;".to_vec());
+            if number.mutate(&mut state, &mut input).unwrap() == MutationResult::Mutated {
+                assert!(input.bytes().starts_with(b"len="));
+                assert!(input.bytes().ends_with(b";"));
+                assert_ne!(input.bytes(), b"len=41;");
+            }
+        }
+
+        state.set_max_size(4096);
+        let mut radamsa = StdScheduledMutator::new(radamsa_mutations());
+        let mut input = BytesInput::new(b"GET /index.html HTTP/1.1\nHost: 127.0.0.1\n\n".to_vec());
+        for _ in 0..1000 {
+            radamsa.mutate(&mut state, &mut input).unwrap();
+            assert!(input.bytes().len() <= 4096);
+        }
+    }
+}
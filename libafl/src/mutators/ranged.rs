@@ -0,0 +1,209 @@
+//! The [`RangedMutator`] restricts any mutator for bytes to a region of the input, such as a header or a body,
+//! by handing it a [`BytesSubInput`] of that region.
+
+use alloc::{borrow::Cow, string::String};
+use core::{fmt, ops::Range};
+
+use libafl_bolts::{tuples::MappingFunctor, Named};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{BytesSubInput, HasMutatorBytes},
+    mutators::{MutationResult, Mutator},
+    state::HasMaxSize,
+    Error,
+};
+
+/// A region that always spans `range`, or as much of it as the input has, for [`RangedMutator`]
+pub fn fixed_region(range: Range<usize>) -> impl FnMut(&[u8]) -> Option<Range<usize>> + Clone {
+    move |bytes| {
+        let start = range.start.min(bytes.len());
+        Some(start..range.end.clamp(start, bytes.len()))
+    }
+}
+
+/// Applies `mutator` only to the region of the input returned by `region`.
+///
+/// The region is computed from the current bytes of the input before each mutation,
+/// so it may follow a length field, or the position of a separator.
+/// If `region` returns `None`, the mutation is skipped.
+/// If the mutator grows or shrinks the region, the bytes after it are moved accordingly.
+/// If the mutator grows the input beyond the maximum size of the state, the excess is cut from the end of the region,
+/// never from the bytes after it, and never below the length the input had before.
+///
+/// As the wrapped mutator sees a [`BytesSubInput`], it has to be generic over its input, as all havoc mutations are.
+/// To restrict a whole tuple of mutations, map it with a [`RangedMutatorMapper`].
+pub struct RangedMutator<M, F> {
+    mutator: M,
+    region: F,
+    name: Cow<'static, str>,
+}
+
+impl<M, F> fmt::Debug for RangedMutator<M, F>
+where
+    M: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangedMutator")
+            .field("mutator", &self.mutator)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, F> RangedMutator<M, F>
+where
+    M: Named,
+{
+    /// Creates a new [`RangedMutator`], applying `mutator` to the bytes in `region`
+    pub fn new(mutator: M, region: F) -> Self {
+        let name = Cow::Owned(String::from("Ranged") + mutator.name());
+        Self {
+            mutator,
+            region,
+            name,
+        }
+    }
+
+    /// The wrapped mutator
+    pub fn mutator(&self) -> &M {
+        &self.mutator
+    }
+}
+
+impl<M, F> Named for RangedMutator<M, F> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, M, F, S> Mutator<I, S> for RangedMutator<M, F>
+where
+    I: HasMutatorBytes,
+    M: for<'a> Mutator<BytesSubInput<'a, I>, S>,
+    F: FnMut(&[u8]) -> Option<Range<usize>>,
+    S: HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let Some(region) = (self.region)(input.bytes()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let len = input.bytes().len();
+        if region.start > region.end || region.end > len {
+            return Err(Error::illegal_argument(format!(
+                "The region {region:?} is out of the bounds of the input of length {len}"
+            )));
+        }
+
+        let result = self
+            .mutator
+            .mutate(state, &mut input.sub_input(region.clone()))?;
+        let new_len = input.bytes().len();
+        let limit = len.max(state.max_size());
+        if new_len > limit {
+            // Only take back some of what the mutator added to the region
+            let region_end = region.end + (new_len - len);
+            input.drain(region_end - (new_len - limit)..region_end);
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.mutator.post_exec(state, new_corpus_id)
+    }
+}
+
+/// Maps each mutator of a tuple to a [`RangedMutator`] with the same region,
+/// for example `havoc_mutations().map(RangedMutatorMapper::new(fixed_region(4..16)))`.
+#[derive(Debug, Clone)]
+pub struct RangedMutatorMapper<F> {
+    region: F,
+}
+
+impl<F> RangedMutatorMapper<F> {
+    /// Creates a new [`RangedMutatorMapper`] for `region`
+    pub fn new(region: F) -> Self {
+        Self { region }
+    }
+}
+
+impl<M, F> MappingFunctor<M> for RangedMutatorMapper<F>
+where
+    M: Named,
+    F: Clone,
+{
+    type Output = RangedMutator<M, F>;
+
+    fn apply(&mut self, from: M) -> Self::Output {
+        RangedMutator::new(from, self.region.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::{tuple_list, Map};
+
+    use crate::{
+        inputs::{BytesInput, HasMutatorBytes},
+        mutators::{
+            fixed_region, havoc_mutations_no_crossover, ranged::RangedMutator, BytesDeleteMutator,
+            MutationResult, Mutator, MutatorsTuple, RangedMutatorMapper, StdScheduledMutator,
+        },
+        state::{test::test_std_state, HasMaxSize},
+    };
+
+    #[test]
+    fn test_ranged_mutator() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut input = BytesInput::new(b"HEAD:body-body-body".to_vec());
+
+        // The header is everything up to and including the colon
+        let body = |bytes: &[u8]| {
+            let colon = bytes.iter().position(|&b| b == b':')?;
+            Some(colon + 1..bytes.len())
+        };
+        let mut mutator = RangedMutator::new(BytesDeleteMutator::new(), body);
+        for _ in 0..16 {
+            mutator.mutate(&mut state, &mut input).unwrap();
+            assert!(input.bytes().starts_with(b"HEAD:"));
+        }
+
+        let mut mutator = RangedMutator::new(BytesDeleteMutator::new(), |_: &[u8]| None);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Skipped
+        );
+
+        // Only the header, up to the first colon, gets mutated
+        let mut input = BytesInput::new(b"HEAD:body".to_vec());
+        let header = |bytes: &[u8]| Some(0..bytes.iter().position(|&b| b == b':')?);
+        let mut mutations = havoc_mutations_no_crossover().map(RangedMutatorMapper::new(header));
+        for _ in 0..64 {
+            mutations.mutate_all(&mut state, &mut input).unwrap();
+            assert!(input.bytes().ends_with(b":body"));
+        }
+
+        // Growing the header beyond the maximum size never cuts the body, nor the input below its length
+        state.set_max_size(4);
+        let mut input = BytesInput::new(b"HEAD:body".to_vec());
+        for _ in 0..64 {
+            mutations.mutate_all(&mut state, &mut input).unwrap();
+            assert!(input.bytes().ends_with(b":body"));
+            assert!(input.bytes().len() <= 9);
+        }
+
+        // The fixed region is clamped to the input
+        let mut input = BytesInput::new(b"HEAD:body".to_vec());
+        let mut scheduled = StdScheduledMutator::new(tuple_list!(RangedMutator::new(
+            BytesDeleteMutator::new(),
+            fixed_region(5..100)
+        )));
+        assert_eq!(
+            scheduled.mutate(&mut state, &mut input).unwrap(),
+            MutationResult::Mutated
+        );
+        assert!(input.bytes().starts_with(b"HEAD:"));
+        assert!(input.bytes().len() < 9);
+    }
+}
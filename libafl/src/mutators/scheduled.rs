@@ -15,6 +15,8 @@ use libafl_bolts::{
 use serde::{Deserialize, Serialize};
 
 use super::MutationId;
+#[cfg(feature = "telemetry")]
+use crate::fuzzer::Telemetry;
 use crate::{
     corpus::{Corpus, CorpusId},
     mutators::{
@@ -250,6 +252,28 @@ pub type HavocMutationsNoCrossoverType = tuple_list_type!(
     BytesSwapMutator,
 );
 
+/// Tuple type of the mutations of the Havoc mutator that keep the size of the input
+pub type HavocMutationsFixedSizeType<I> = tuple_list_type!(
+    BitFlipMutator,
+    ByteFlipMutator,
+    ByteIncMutator,
+    ByteDecMutator,
+    ByteNegMutator,
+    ByteRandMutator,
+    ByteAddMutator,
+    WordAddMutator,
+    DwordAddMutator,
+    QwordAddMutator,
+    ByteInterestingMutator,
+    WordInterestingMutator,
+    DwordInterestingMutator,
+    BytesSetMutator,
+    BytesRandSetMutator,
+    BytesCopyMutator,
+    BytesSwapMutator,
+    CrossoverReplaceMutator<I>,
+);
+
 /// Tuple type of the mutations that compose the Havoc mutator's crossover mutations
 pub type HavocCrossoverType<I> =
     tuple_list_type!(CrossoverInsertMutator<I>, CrossoverReplaceMutator<I>);
@@ -317,6 +341,34 @@ pub fn havoc_mutations_no_crossover() -> HavocMutationsNoCrossoverType {
     )
 }
 
+/// Get the mutations of the Havoc mutator that never change the size of the input,
+/// for targets with fixed-size inputs, like network packets or memory-mapped structs.
+///
+/// Wrap mutators that may still change the size in a [`crate::mutators::FixedSizeMutator`].
+#[must_use]
+pub fn havoc_mutations_fixed_size<I>() -> HavocMutationsFixedSizeType<I> {
+    tuple_list!(
+        BitFlipMutator::new(),
+        ByteFlipMutator::new(),
+        ByteIncMutator::new(),
+        ByteDecMutator::new(),
+        ByteNegMutator::new(),
+        ByteRandMutator::new(),
+        ByteAddMutator::new(),
+        WordAddMutator::new(),
+        DwordAddMutator::new(),
+        QwordAddMutator::new(),
+        ByteInterestingMutator::new(),
+        WordInterestingMutator::new(),
+        DwordInterestingMutator::new(),
+        BytesSetMutator::new(),
+        BytesRandSetMutator::new(),
+        BytesCopyMutator::new(),
+        BytesSwapMutator::new(),
+        CrossoverReplaceMutator::new(),
+    )
+}
+
 /// Get the mutations that compose the Havoc mutator's crossover strategy
 #[must_use]
 pub fn havoc_crossover<I>() -> HavocCrossoverType<I> {
@@ -348,6 +400,8 @@ where
     name: Cow<'static, str>,
     scheduled: SM,
     mutation_log: Vec<MutationId>,
+    #[cfg(feature = "telemetry")]
+    telemetry: Option<Telemetry>,
     phantom: PhantomData<(I, MT, S)>,
 }
 
@@ -451,6 +505,15 @@ where
                 r = MutationResult::Mutated;
             }
         }
+        #[cfg(feature = "telemetry")]
+        if let (Some(telemetry), MutationResult::Mutated) = (&self.telemetry, r) {
+            let mutations = self.scheduled.mutations();
+            telemetry.log_mutations(
+                self.mutation_log
+                    .iter()
+                    .filter_map(|idx| mutations.name(idx.0).map(AsRef::as_ref)),
+            );
+        }
         Ok(r)
     }
 }
@@ -468,9 +531,19 @@ where
             name: Cow::from(format!("LoggerScheduledMutator[{}]", scheduled.name())),
             scheduled,
             mutation_log: vec![],
+            #[cfg(feature = "telemetry")]
+            telemetry: None,
             phantom: PhantomData,
         }
     }
+
+    /// Also log the mutations applied to each input to the given [`Telemetry`] log
+    #[cfg(feature = "telemetry")]
+    #[must_use]
+    pub fn with_telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -483,7 +556,7 @@ mod tests {
         inputs::{BytesInput, HasMutatorBytes},
         mutators::{
             mutations::SpliceMutator,
-            scheduled::{havoc_mutations, StdScheduledMutator},
+            scheduled::{havoc_mutations, havoc_mutations_fixed_size, StdScheduledMutator},
             Mutator,
         },
         state::StdState,
@@ -567,4 +640,29 @@ mod tests {
             assert_ne!(equal_in_a_row, 5);
         }
     }
+
+    #[test]
+    fn test_havoc_fixed_size() {
+        let mut corpus: InMemoryCorpus<BytesInput> = InMemoryCorpus::new();
+        corpus.add(Testcase::new(vec![0; 5].into())).unwrap();
+        corpus.add(Testcase::new(vec![1; 40].into())).unwrap();
+        let mut input = corpus.cloned_input_for_id(corpus.first().unwrap()).unwrap();
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0x1337),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut havoc = StdScheduledMutator::new(havoc_mutations_fixed_size());
+        for _ in 0..1000 {
+            havoc.mutate(&mut state, &mut input).unwrap();
+            assert_eq!(input.bytes().len(), 5);
+        }
+    }
 }
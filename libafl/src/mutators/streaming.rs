@@ -0,0 +1,122 @@
+//! The [`StreamingWindowMutator`] mutates a [`StreamingInput`] one window at a time,
+//! so huge inputs never have to be loaded as a whole.
+
+use alloc::{borrow::Cow, string::String};
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{BytesInput, HasMutatorBytes, StreamingInput},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+
+/// The default size of the window a [`StreamingWindowMutator`] mutates
+pub const STREAMING_DEFAULT_WINDOW: usize = 4096;
+
+/// Reads a random window of the input into a [`BytesInput`], applies `mutator` to it,
+/// and splices the result back in, in memory. The backing file is never written.
+#[derive(Debug)]
+pub struct StreamingWindowMutator<M> {
+    mutator: M,
+    window: usize,
+    name: Cow<'static, str>,
+}
+
+impl<M> StreamingWindowMutator<M>
+where
+    M: Named,
+{
+    /// Creates a new [`StreamingWindowMutator`], mutating windows of [`STREAMING_DEFAULT_WINDOW`] bytes
+    pub fn new(mutator: M) -> Self {
+        Self::with_window(mutator, STREAMING_DEFAULT_WINDOW)
+    }
+
+    /// Creates a new [`StreamingWindowMutator`], mutating windows of `window` bytes
+    pub fn with_window(mutator: M, window: usize) -> Self {
+        let name = Cow::Owned(String::from("StreamingWindow") + mutator.name());
+        Self {
+            mutator,
+            window,
+            name,
+        }
+    }
+}
+
+impl<M> Named for StreamingWindowMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<M, S> Mutator<StreamingInput, S> for StreamingWindowMutator<M>
+where
+    M: Mutator<BytesInput, S>,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut StreamingInput,
+    ) -> Result<MutationResult, Error> {
+        let len = input.stream_len();
+        let window = self.window as u64;
+        let start = if len > window {
+            state.rand_mut().below((len - window + 1) as usize) as u64
+        } else {
+            0
+        };
+        let range = start..(start + window).min(len);
+
+        let original = input.read_range(range.clone())?;
+        let mut bytes = BytesInput::new(original.clone());
+        if self.mutator.mutate(state, &mut bytes)? == MutationResult::Skipped
+            || bytes.bytes() == original
+        {
+            return Ok(MutationResult::Skipped);
+        }
+        input.splice(range, bytes.bytes());
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.mutator.post_exec(state, new_corpus_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{
+        inputs::StreamingInput,
+        mutators::{
+            streaming::StreamingWindowMutator, BytesDeleteMutator, MutationResult, Mutator,
+        },
+        state::test::test_std_state,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_streaming_window_mutator() {
+        let path = env::temp_dir().join(format!("libafl_streaming_mutator_test_{}", process::id()));
+        fs::write(&path, vec![0x41; 1 << 16]).unwrap();
+
+        let mut state = test_std_state::<StreamingInput>();
+        let mut input = StreamingInput::open(&path).unwrap();
+        let mut mutator = StreamingWindowMutator::with_window(BytesDeleteMutator::new(), 256);
+        for _ in 0..16 {
+            assert_eq!(
+                mutator.mutate(&mut state, &mut input).unwrap(),
+                MutationResult::Mutated
+            );
+        }
+        assert!(input.stream_len() < 1 << 16);
+        assert!(input.patched_len() <= 16 * 256);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,371 @@
+//! Mutators for [`SyscallSequenceInput`]s, inserting, removing, and reordering calls, and mutating their arguments.
+//! All of them keep each result reference pointing to an earlier call of the right kind.
+//! See [`crate::inputs::syscalls`] for details.
+
+use alloc::{borrow::Cow, vec::Vec};
+
+use libafl_bolts::{rands::Rand, Error, Named};
+
+use crate::{
+    inputs::syscalls::{ArgKind, Call, CallArg, CallDescription, CallTable, SyscallSequenceInput},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+};
+
+/// The default maximum amount of calls [`SyscallInsertMutator`] grows an input to
+pub const SYSCALL_DEFAULT_MAX_CALLS: usize = 32;
+
+/// The mask of an integer argument of `bits` bits
+fn int_mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// The calls before `before` returning a resource of `kind`
+fn producers<'a>(
+    table: &'a CallTable,
+    calls: &'a [Call],
+    before: usize,
+    kind: &'a str,
+) -> impl Iterator<Item = usize> + 'a {
+    calls[..before]
+        .iter()
+        .enumerate()
+        .filter(move |(_, call)| {
+            table
+                .get(call.id)
+                .is_some_and(|desc| desc.produces.as_deref() == Some(kind))
+        })
+        .map(|(idx, _)| idx)
+}
+
+/// Generates a random argument of `kind` for a call at index `before`.
+/// Returns `None` if no earlier call produces a needed resource.
+fn generate_arg<R>(
+    rand: &mut R,
+    table: &CallTable,
+    calls: &[Call],
+    before: usize,
+    kind: &ArgKind,
+) -> Option<CallArg>
+where
+    R: Rand,
+{
+    Some(match kind {
+        ArgKind::Int { bits, pool } => {
+            let value = if pool.is_empty() || rand.coinflip(0.5) {
+                rand.next()
+            } else {
+                pool[rand.below(pool.len())]
+            };
+            CallArg::Int(value & int_mask(*bits))
+        }
+        ArgKind::Bytes { max_len } => {
+            let len = rand.below(max_len + 1);
+            CallArg::Bytes((0..len).map(|_| rand.next() as u8).collect())
+        }
+        ArgKind::Resource { kind } => {
+            let producers: Vec<usize> = producers(table, calls, before, kind).collect();
+            CallArg::Result(*rand.choose(&producers)?)
+        }
+    })
+}
+
+/// Generates a random call of `desc` at index `before`, or `None` if a needed resource is missing
+fn generate_call<R>(
+    rand: &mut R,
+    table: &CallTable,
+    calls: &[Call],
+    before: usize,
+    desc: &CallDescription,
+) -> Option<Call>
+where
+    R: Rand,
+{
+    let args = desc
+        .args
+        .iter()
+        .map(|kind| generate_arg(rand, table, calls, before, kind))
+        .collect::<Option<Vec<_>>>()?;
+    Some(Call::new(desc.id, args))
+}
+
+/// Inserts a new call at a random position, with random arguments
+#[derive(Debug)]
+pub struct SyscallInsertMutator<'a> {
+    table: &'a CallTable,
+    max_calls: usize,
+}
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallInsertMutator<'_>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        if input.calls().len() >= self.max_calls || self.table.calls().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let idx = rand.below(input.calls().len() + 1);
+        let desc = &self.table.calls()[rand.below(self.table.calls().len())];
+        let Some(call) = generate_call(rand, self.table, input.calls(), idx, desc) else {
+            return Ok(MutationResult::Skipped);
+        };
+        input.insert_call(idx, call);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for SyscallInsertMutator<'_> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallInsertMutator");
+        &NAME
+    }
+}
+
+impl<'a> SyscallInsertMutator<'a> {
+    /// Creates a new [`SyscallInsertMutator`] for the calls of `table`,
+    /// growing inputs to at most [`SYSCALL_DEFAULT_MAX_CALLS`] calls.
+    #[must_use]
+    pub fn new(table: &'a CallTable) -> Self {
+        Self::with_max_calls(table, SYSCALL_DEFAULT_MAX_CALLS)
+    }
+
+    /// Creates a new [`SyscallInsertMutator`] for the calls of `table`, growing inputs to at most `max_calls` calls.
+    #[must_use]
+    pub fn with_max_calls(table: &'a CallTable, max_calls: usize) -> Self {
+        Self { table, max_calls }
+    }
+}
+
+/// Removes a random call, if no later call uses its result
+#[derive(Debug, Default)]
+pub struct SyscallRemoveMutator;
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallRemoveMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        if input.calls().len() <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let idx = state.rand_mut().below(input.calls().len());
+        if input.remove_call(idx).is_some() {
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl Named for SyscallRemoveMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallRemoveMutator");
+        &NAME
+    }
+}
+
+impl SyscallRemoveMutator {
+    /// Creates a new [`SyscallRemoveMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Moves a random call to another position, as long as all calls stay after the calls whose results they use
+#[derive(Debug, Default)]
+pub struct SyscallReorderMutator;
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallReorderMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        let len = input.calls().len();
+        if len <= 1 {
+            return Ok(MutationResult::Skipped);
+        }
+        let from = state.rand_mut().below(len);
+        let to = state.rand_mut().below(len);
+        if from != to && input.move_call(from, to) {
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl Named for SyscallReorderMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallReorderMutator");
+        &NAME
+    }
+}
+
+impl SyscallReorderMutator {
+    /// Creates a new [`SyscallReorderMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Mutates a random argument of a random call, according to its [`ArgKind`]:
+/// integers get values of the pool, boundary values, bit flips and small increments,
+/// buffers get byte flips and resizes, and resources get another earlier call of the same kind.
+#[derive(Debug)]
+pub struct SyscallArgMutator<'a> {
+    table: &'a CallTable,
+}
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallArgMutator<'_>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        if input.calls().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let idx = rand.below(input.calls().len());
+        let call = &input.calls()[idx];
+        let Some(desc) = self.table.get(call.id) else {
+            return Ok(MutationResult::Skipped);
+        };
+        if call.args.is_empty() || call.args.len() != desc.args.len() {
+            return Ok(MutationResult::Skipped);
+        }
+        let arg_idx = rand.below(call.args.len());
+        let kind = &desc.args[arg_idx];
+
+        let new_arg = match (kind, &call.args[arg_idx]) {
+            (ArgKind::Int { bits, pool }, CallArg::Int(value)) => {
+                let mask = int_mask(*bits);
+                let value = match rand.below(5) {
+                    0 if !pool.is_empty() => pool[rand.below(pool.len())],
+                    0 | 1 => [0, 1, mask, mask >> 1, (mask >> 1) + 1][rand.below(5)],
+                    2 => value ^ (1 << rand.below((*bits).clamp(1, 64) as usize)),
+                    3 => value.wrapping_add(1 + rand.below(16) as u64),
+                    _ => value.wrapping_sub(1 + rand.below(16) as u64),
+                };
+                CallArg::Int(value & mask)
+            }
+            (ArgKind::Bytes { max_len }, CallArg::Bytes(buf)) if !buf.is_empty() => {
+                let mut buf = buf.clone();
+                if rand.coinflip(0.5) {
+                    let pos = rand.below(buf.len());
+                    buf[pos] ^= 1 + rand.below(255) as u8;
+                } else {
+                    let len = rand.below(max_len + 1);
+                    buf.resize(len, rand.next() as u8);
+                }
+                CallArg::Bytes(buf)
+            }
+            (kind, _) => {
+                let Some(arg) = generate_arg(rand, self.table, input.calls(), idx, kind) else {
+                    return Ok(MutationResult::Skipped);
+                };
+                arg
+            }
+        };
+
+        let arg = &mut input.calls_mut()[idx].args[arg_idx];
+        if *arg == new_arg {
+            return Ok(MutationResult::Skipped);
+        }
+        *arg = new_arg;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for SyscallArgMutator<'_> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallArgMutator");
+        &NAME
+    }
+}
+
+impl<'a> SyscallArgMutator<'a> {
+    /// Creates a new [`SyscallArgMutator`] for the calls of `table`
+    #[must_use]
+    pub fn new(table: &'a CallTable) -> Self {
+        Self { table }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        inputs::{
+            syscalls::{ArgKind, CallDescription, CallTable, SyscallSequenceInput},
+            NopInput,
+        },
+        mutators::{
+            syscalls::{
+                SyscallArgMutator, SyscallInsertMutator, SyscallRemoveMutator,
+                SyscallReorderMutator,
+            },
+            MutatorsTuple,
+        },
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_syscall_mutators() {
+        let table = CallTable::new()
+            .with_call(
+                CallDescription::new(0, "open")
+                    .arg(ArgKind::Bytes { max_len: 8 })
+                    .arg(ArgKind::Int {
+                        bits: 32,
+                        pool: vec![0, 0o100, 0o1000],
+                    })
+                    .produces("fd"),
+            )
+            .with_call(
+                CallDescription::new(1, "write")
+                    .arg(ArgKind::Resource { kind: "fd".into() })
+                    .arg(ArgKind::Bytes { max_len: 16 }),
+            )
+            .with_call(
+                CallDescription::new(2, "close").arg(ArgKind::Resource { kind: "fd".into() }),
+            );
+
+        let mut state = test_std_state::<NopInput>();
+        let mut mutations = tuple_list!(
+            SyscallInsertMutator::with_max_calls(&table, 8),
+            SyscallArgMutator::new(&table),
+            SyscallReorderMutator::new(),
+            SyscallRemoveMutator::new(),
+        );
+        let mut input = SyscallSequenceInput::default();
+        for _ in 0..256 {
+            mutations.mutate_all(&mut state, &mut input).unwrap();
+            assert!(input.is_valid());
+            assert!(input.calls().len() <= 8);
+        }
+    }
+}
@@ -78,27 +78,31 @@ impl Tokens {
         Ok(self)
     }
 
-    /// Parse autodict section
-    pub fn parse_autodict(&mut self, slice: &[u8], size: usize) {
+    /// Parse autodict section, a sequence of tokens, each prefixed by its length in a single byte.
+    /// Fails if the section is shorter than `size`, or a token reaches past its end.
+    pub fn parse_autodict(&mut self, slice: &[u8], size: usize) -> Result<(), Error> {
+        let Some(slice) = slice.get(..size) else {
+            return Err(Error::illegal_argument(format!(
+                "Autodict section of {size} bytes, but only {} bytes given",
+                slice.len()
+            )));
+        };
         let mut head = 0;
-        loop {
-            if head >= size {
-                // Sanity Check
-                assert!(head == size);
-                break;
-            }
-            let size = slice[head] as usize;
+        while head < size {
+            let len = slice[head] as usize;
             head += 1;
-            if size > 0 {
-                self.add_token(&slice[head..head + size].to_vec());
-                log::info!(
-                    "Token size: {} content: {:x?}",
-                    size,
-                    &slice[head..head + size].to_vec()
-                );
-                head += size;
+            let Some(token) = slice.get(head..head + len) else {
+                return Err(Error::illegal_argument(format!(
+                    "Autodict token of {len} bytes at offset {head} reaches past the end of the section of {size} bytes"
+                )));
+            };
+            if len > 0 {
+                self.add_token(&token.to_vec());
+                log::info!("Token size: {len} content: {token:x?}");
             }
+            head += len;
         }
+        Ok(())
     }
 
     /// Create a token section from a start and an end pointer
@@ -126,7 +130,7 @@ impl Tokens {
         let slice = from_raw_parts(token_start, section_size);
 
         // Now we know the beginning and the end of the token section.. let's parse them into tokens
-        ret.parse_autodict(slice, section_size);
+        ret.parse_autodict(slice, section_size)?;
 
         Ok(ret)
     }
@@ -336,10 +340,8 @@ where
         }
 
         input.resize(size + len, 0);
-        unsafe {
-            buffer_self_copy(input.bytes_mut(), off, off + len, size - off);
-            buffer_copy(input.bytes_mut(), token, 0, off, len);
-        }
+        buffer_self_copy(input.bytes_mut(), off, off + len, size - off);
+        buffer_copy(input.bytes_mut(), token, 0, off, len);
 
         Ok(MutationResult::Mutated)
     }
@@ -396,9 +398,7 @@ where
             len = size - off;
         }
 
-        unsafe {
-            buffer_copy(input.bytes_mut(), token, 0, off, len);
-        }
+        buffer_copy(input.bytes_mut(), token, 0, off, len);
 
         Ok(MutationResult::Mutated)
     }
@@ -562,9 +562,7 @@ where
                     let mut size = core::cmp::min(v.0.len(), len - i);
                     while size != 0 {
                         if v.0[0..size] == input.bytes()[i..i + size] {
-                            unsafe {
-                                buffer_copy(input.bytes_mut(), &v.1, 0, i, size);
-                            }
+                            buffer_copy(input.bytes_mut(), &v.1, 0, i, size);
                             result = MutationResult::Mutated;
                             break 'outer;
                         }
@@ -573,9 +571,7 @@ where
                     size = core::cmp::min(v.1.len(), len - i);
                     while size != 0 {
                         if v.1[0..size] == input.bytes()[i..i + size] {
-                            unsafe {
-                                buffer_copy(input.bytes_mut(), &v.0, 0, i, size);
-                            }
+                            buffer_copy(input.bytes_mut(), &v.0, 0, i, size);
                             result = MutationResult::Mutated;
                             break 'outer;
                         }
@@ -1063,14 +1059,12 @@ impl AFLppRedQueen {
         }
 
         if copy_len > 0 {
-            unsafe {
-                for l in 1..=copy_len {
-                    let mut cloned = buf.to_vec();
-                    buffer_copy(&mut cloned, repl, 0, buf_idx, l);
-                    vec.push(cloned);
-                }
-                // vec.push(cloned);
+            for l in 1..=copy_len {
+                let mut cloned = buf.to_vec();
+                buffer_copy(&mut cloned, repl, 0, buf_idx, l);
+                vec.push(cloned);
             }
+            // vec.push(cloned);
             true
         } else {
             false
@@ -1866,7 +1860,8 @@ mod tests {
     use std::fs;
 
     #[cfg(feature = "std")]
-    use super::{AFLppRedQueen, Tokens};
+    use super::AFLppRedQueen;
+    use super::Tokens;
 
     #[cfg(feature = "std")]
     #[test]
@@ -1918,4 +1913,20 @@ token2="B"
             &mut vec,
         );
     }
+
+    #[test]
+    fn test_parse_autodict() {
+        let section = b"\x03GET\x00\x04POST";
+        let mut tokens = Tokens::new();
+        tokens.parse_autodict(section, section.len()).unwrap();
+        assert_eq!(tokens.tokens(), &[b"GET".to_vec(), b"POST".to_vec()]);
+
+        // The last token is cut off
+        let mut tokens = Tokens::new();
+        tokens.parse_autodict(&section[..8], 8).unwrap_err();
+        tokens
+            .parse_autodict(section, section.len() + 1)
+            .unwrap_err();
+        tokens.parse_autodict(b"\x05abc", 4).unwrap_err();
+    }
 }
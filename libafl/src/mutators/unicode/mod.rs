@@ -1,6 +1,7 @@
 //! Mutators for preserving unicode string categories,
-//! which may be useful for certain targets which are primarily string-oriented.
-use alloc::{borrow::Cow, vec::Vec};
+//! which may be useful for certain targets which are primarily string-oriented,
+//! and for codepoint-level edits, like swaps, confusables, case changes, and overlong encodings.
+use alloc::{borrow::Cow, string::String, vec::Vec};
 use core::{
     cmp::{Ordering, Reverse},
     ops::Range,
@@ -15,7 +16,7 @@ use crate::{
     stages::{
         extract_metadata,
         mutational::{MutatedTransform, MutatedTransformPost},
-        UnicodeIdentificationMetadata,
+        UnicodeIdentificationMetadata, UnicodeSpanClass,
     },
     state::{HasCorpus, HasMaxSize, HasRand},
     HasMetadata,
@@ -493,18 +494,593 @@ where
     }
 }
 
+/// Picks a random char of the given [`UnicodeSpanClass`], for non-ASCII chars from the same 128-codepoint block as `c`,
+/// which usually is the same script
+fn rand_char_of_class<R: Rand>(rand: &mut R, class: UnicodeSpanClass, c: char) -> char {
+    match class {
+        UnicodeSpanClass::AsciiLetter => {
+            let idx = rand.below(52) as u8;
+            if idx < 26 {
+                (b'a' + idx) as char
+            } else {
+                (b'A' + idx - 26) as char
+            }
+        }
+        UnicodeSpanClass::AsciiDigit => (b'0' + rand.below(10) as u8) as char,
+        UnicodeSpanClass::AsciiOther => loop {
+            let new_c = rand.below(0x80) as u8 as char;
+            if !new_c.is_ascii_alphanumeric() {
+                return new_c;
+            }
+        },
+        UnicodeSpanClass::NonAscii => {
+            let block = c as u32 & !0x7f;
+            loop {
+                if let Some(new_c) = char::from_u32(block + rand.below(0x80) as u32) {
+                    if !new_c.is_ascii() {
+                        return new_c;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Replaces `range` of the input with `replacement`, and recomputes the unicode metadata
+fn replace_range(
+    max_size: usize,
+    input: &mut UnicodeInput,
+    range: Range<usize>,
+    replacement: &[u8],
+) -> MutationResult {
+    if input.0.len() - (range.end - range.start) + replacement.len() > max_size {
+        return MutationResult::Skipped;
+    }
+
+    input.0.splice(range, replacement.iter().copied());
+    input.1 = extract_metadata(input.0.bytes());
+
+    MutationResult::Mutated
+}
+
+/// Mutator which replaces a single char of a randomly selected [`crate::stages::UnicodeSpan`]
+/// with another char of the same [`UnicodeSpanClass`]
+#[derive(Debug, Default)]
+pub struct UnicodeSpanClassReplaceMutator;
+
+impl Named for UnicodeSpanClassReplaceMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("string-span-class-replace");
+        &NAME
+    }
+}
+
+impl<S> Mutator<UnicodeInput, S> for UnicodeSpanClassReplaceMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut UnicodeInput) -> Result<MutationResult, Error> {
+        let spans = input.1.spans();
+        if spans.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let span = spans[state.rand_mut().below(spans.len())];
+
+        let substring = core::str::from_utf8(&input.0.bytes()[span.start..span.end])?;
+        let chars = substring.char_indices().collect::<Vec<_>>();
+        let (c_idx, c) = chars[state.rand_mut().below(chars.len())];
+
+        let new_c = rand_char_of_class(state.rand_mut(), span.class, c);
+        if new_c == c {
+            return Ok(MutationResult::Skipped);
+        }
+        let mut dest = [0u8; 4];
+        let replacement = new_c.encode_utf8(&mut dest).as_bytes();
+
+        let start = span.start + c_idx;
+        Ok(replace_range(
+            state.max_size(),
+            input,
+            start..(start + c.len_utf8()),
+            replacement,
+        ))
+    }
+}
+
+/// Latin-1 uppercase letters with their canonical decomposition into a base letter and a combining mark.
+/// The lowercase letters are offset by `0x20`, both in the composed and in the base letter.
+const LATIN1_DECOMPOSITIONS: [(char, char, char); 26] = [
+    ('\u{c0}', 'A', '\u{300}'),
+    ('\u{c1}', 'A', '\u{301}'),
+    ('\u{c2}', 'A', '\u{302}'),
+    ('\u{c3}', 'A', '\u{303}'),
+    ('\u{c4}', 'A', '\u{308}'),
+    ('\u{c5}', 'A', '\u{30a}'),
+    ('\u{c7}', 'C', '\u{327}'),
+    ('\u{c8}', 'E', '\u{300}'),
+    ('\u{c9}', 'E', '\u{301}'),
+    ('\u{ca}', 'E', '\u{302}'),
+    ('\u{cb}', 'E', '\u{308}'),
+    ('\u{cc}', 'I', '\u{300}'),
+    ('\u{cd}', 'I', '\u{301}'),
+    ('\u{ce}', 'I', '\u{302}'),
+    ('\u{cf}', 'I', '\u{308}'),
+    ('\u{d1}', 'N', '\u{303}'),
+    ('\u{d2}', 'O', '\u{300}'),
+    ('\u{d3}', 'O', '\u{301}'),
+    ('\u{d4}', 'O', '\u{302}'),
+    ('\u{d5}', 'O', '\u{303}'),
+    ('\u{d6}', 'O', '\u{308}'),
+    ('\u{d9}', 'U', '\u{300}'),
+    ('\u{da}', 'U', '\u{301}'),
+    ('\u{db}', 'U', '\u{302}'),
+    ('\u{dc}', 'U', '\u{308}'),
+    ('\u{dd}', 'Y', '\u{301}'),
+];
+
+/// The offset of the fullwidth forms (`U+FF01` to `U+FF5E`) to the printable ASCII chars
+const FULLWIDTH_OFFSET: u32 = 0xfee0;
+
+fn decompose(c: char) -> Option<(char, char)> {
+    let (upper, lower) = match c {
+        '\u{e0}'..='\u{fe}' => (char::from_u32(c as u32 - 0x20)?, true),
+        _ => (c, false),
+    };
+    LATIN1_DECOMPOSITIONS
+        .iter()
+        .find(|&&(composed, _, _)| composed == upper)
+        .map(|&(_, base, mark)| {
+            if lower {
+                (base.to_ascii_lowercase(), mark)
+            } else {
+                (base, mark)
+            }
+        })
+}
+
+fn compose(base: char, mark: char) -> Option<char> {
+    let lower = base.is_ascii_lowercase();
+    let upper_base = base.to_ascii_uppercase();
+    LATIN1_DECOMPOSITIONS
+        .iter()
+        .find(|&&(_, b, m)| b == upper_base && m == mark)
+        .and_then(|&(composed, _, _)| {
+            if lower {
+                char::from_u32(composed as u32 + 0x20)
+            } else {
+                Some(composed)
+            }
+        })
+}
+
+/// The normalization forms the [`UnicodeNormalizationMutator`] converts between
+#[derive(Debug, Clone, Copy)]
+enum NormalizationForm {
+    /// Precomposed letters, like NFC
+    Composed,
+    /// Base letters followed by combining marks, like NFD
+    Decomposed,
+    /// Fullwidth compatibility forms, which NFKC would fold to ASCII
+    Fullwidth,
+    /// Compatibility forms folded to ASCII, like NFKC
+    Folded,
+}
+
+impl NormalizationForm {
+    fn apply(self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match self {
+                Self::Composed => {
+                    if let Some(composed) = chars.peek().and_then(|&mark| compose(c, mark)) {
+                        chars.next();
+                        out.push(composed);
+                    } else {
+                        out.push(c);
+                    }
+                }
+                Self::Decomposed => {
+                    if let Some((base, mark)) = decompose(c) {
+                        out.push(base);
+                        out.push(mark);
+                    } else {
+                        out.push(c);
+                    }
+                }
+                Self::Fullwidth => out.push(if ('!'..='~').contains(&c) {
+                    char::from_u32(c as u32 + FULLWIDTH_OFFSET).unwrap_or(c)
+                } else {
+                    c
+                }),
+                Self::Folded => out.push(if ('\u{ff01}'..='\u{ff5e}').contains(&c) {
+                    char::from_u32(c as u32 - FULLWIDTH_OFFSET).unwrap_or(c)
+                } else {
+                    c
+                }),
+            }
+        }
+        out
+    }
+}
+
+/// Mutator which converts a randomly selected string-like range between normalization forms,
+/// i.e., composes or decomposes accented letters, or converts ASCII from or to its fullwidth forms.
+///
+/// This is a lightweight approximation of Unicode normalization, covering the Latin-1 letters
+/// and the fullwidth forms only, which are the most common sources of normalization bugs.
+#[derive(Debug, Default)]
+pub struct UnicodeNormalizationMutator;
+
+impl Named for UnicodeNormalizationMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("string-normalization");
+        &NAME
+    }
+}
+
+impl<S> Mutator<UnicodeInput, S> for UnicodeNormalizationMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut UnicodeInput) -> Result<MutationResult, Error> {
+        if input.0.bytes().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let bytes = input.0.bytes();
+        let meta = &input.1;
+        if let Some((base, len)) = choose_start(state.rand_mut(), bytes, meta) {
+            let substring = core::str::from_utf8(&bytes[base..][..len])?;
+            let form = match state.rand_mut().below(4) {
+                0 => NormalizationForm::Composed,
+                1 => NormalizationForm::Decomposed,
+                2 => NormalizationForm::Fullwidth,
+                _ => NormalizationForm::Folded,
+            };
+            let normalized = form.apply(substring);
+            if normalized == substring {
+                return Ok(MutationResult::Skipped);
+            }
+
+            return Ok(replace_range(
+                state.max_size(),
+                input,
+                base..(base + len),
+                normalized.as_bytes(),
+            ));
+        }
+
+        Ok(MutationResult::Skipped)
+    }
+}
+
+/// The start of a string-like range, and its chars with their offsets
+type CharRange = (usize, Vec<(usize, char)>);
+
+/// Picks a random string-like range of the input
+fn choose_chars<R: Rand>(rand: &mut R, input: &UnicodeInput) -> Result<Option<CharRange>, Error> {
+    let bytes = input.0.bytes();
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    let Some((base, len)) = choose_start(rand, bytes, &input.1) else {
+        return Ok(None);
+    };
+    let substring = core::str::from_utf8(&bytes[base..][..len])?;
+    if substring.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((base, substring.char_indices().collect())))
+}
+
+/// Mutator which swaps two randomly selected codepoints of a randomly selected string-like range
+#[derive(Debug, Default)]
+pub struct UnicodeCodepointSwapMutator;
+
+impl Named for UnicodeCodepointSwapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("string-codepoint-swap");
+        &NAME
+    }
+}
+
+impl<S> Mutator<UnicodeInput, S> for UnicodeCodepointSwapMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut UnicodeInput) -> Result<MutationResult, Error> {
+        let Some((base, chars)) = choose_chars(state.rand_mut(), input)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        if chars.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let first = state.rand_mut().below(chars.len());
+        let second = state.rand_mut().below(chars.len());
+        let ((first_idx, first_c), (second_idx, second_c)) =
+            (chars[first.min(second)], chars[first.max(second)]);
+        if first_c == second_c {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let end = base + second_idx + second_c.len_utf8();
+        let mut replacement = String::with_capacity(end - base - first_idx);
+        replacement.push(second_c);
+        replacement.push_str(core::str::from_utf8(
+            &input.0.bytes()[(base + first_idx + first_c.len_utf8())..(base + second_idx)],
+        )?);
+        replacement.push(first_c);
+
+        Ok(replace_range(
+            state.max_size(),
+            input,
+            (base + first_idx)..end,
+            replacement.as_bytes(),
+        ))
+    }
+}
+
+/// ASCII chars with a lookalike from another script, mostly Cyrillic and Greek
+const CONFUSABLES: [(char, char); 24] = [
+    ('A', '\u{391}'),
+    ('B', '\u{392}'),
+    ('C', '\u{421}'),
+    ('E', '\u{415}'),
+    ('H', '\u{41d}'),
+    ('K', '\u{41a}'),
+    ('M', '\u{41c}'),
+    ('O', '\u{41e}'),
+    ('P', '\u{420}'),
+    ('T', '\u{422}'),
+    ('X', '\u{425}'),
+    ('a', '\u{430}'),
+    ('c', '\u{441}'),
+    ('e', '\u{435}'),
+    ('i', '\u{456}'),
+    ('j', '\u{458}'),
+    ('o', '\u{43e}'),
+    ('p', '\u{440}'),
+    ('s', '\u{455}'),
+    ('x', '\u{445}'),
+    ('y', '\u{443}'),
+    ('0', '\u{39f}'),
+    ('1', '\u{6f1}'),
+    ('/', '\u{2215}'),
+];
+
+/// Chars which are invisible, or change the rendering of their neighbours, when inserted into text
+const INVISIBLES: [char; 7] = [
+    '\u{ad}',   // soft hyphen
+    '\u{200b}', // zero width space
+    '\u{200c}', // zero width non-joiner
+    '\u{200d}', // zero width joiner
+    '\u{202e}', // right-to-left override
+    '\u{2060}', // word joiner
+    '\u{feff}', // zero width no-break space
+];
+
+/// Mutator which either replaces a char of a randomly selected string-like range with a
+/// confusable lookalike, or inserts an invisible char into it
+#[derive(Debug, Default)]
+pub struct UnicodeConfusableMutator;
+
+impl Named for UnicodeConfusableMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("string-confusable");
+        &NAME
+    }
+}
+
+impl<S> Mutator<UnicodeInput, S> for UnicodeConfusableMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut UnicodeInput) -> Result<MutationResult, Error> {
+        let Some((base, chars)) = choose_chars(state.rand_mut(), input)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        let mut dest = [0u8; 4];
+
+        if state.rand_mut().coinflip(0.5) {
+            let confusables = chars
+                .iter()
+                .filter_map(|&(c_idx, c)| {
+                    CONFUSABLES
+                        .iter()
+                        .find(|&&(ascii, _)| ascii == c)
+                        .map(|&(_, confusable)| (c_idx, confusable))
+                })
+                .collect::<Vec<_>>();
+            if confusables.is_empty() {
+                return Ok(MutationResult::Skipped);
+            }
+            let (c_idx, confusable) = confusables[state.rand_mut().below(confusables.len())];
+            let start = base + c_idx;
+            // All chars with a confusable are ASCII, i.e., a single byte
+            Ok(replace_range(
+                state.max_size(),
+                input,
+                start..(start + 1),
+                confusable.encode_utf8(&mut dest).as_bytes(),
+            ))
+        } else {
+            // Any char boundary, including the end of the range
+            let pos = state.rand_mut().below(chars.len() + 1);
+            let at = chars.get(pos).map_or_else(
+                || {
+                    let (c_idx, c) = chars[chars.len() - 1];
+                    c_idx + c.len_utf8()
+                },
+                |&(c_idx, _)| c_idx,
+            );
+            let invisible = *state.rand_mut().choose(&INVISIBLES).unwrap();
+            Ok(replace_range(
+                state.max_size(),
+                input,
+                (base + at)..(base + at),
+                invisible.encode_utf8(&mut dest).as_bytes(),
+            ))
+        }
+    }
+}
+
+/// Mutator which converts a randomly selected string-like range to upper, lower, or toggled case,
+/// using the full Unicode case mappings, which may change the length of the string
+#[derive(Debug, Default)]
+pub struct UnicodeCaseMutator;
+
+impl Named for UnicodeCaseMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("string-case");
+        &NAME
+    }
+}
+
+impl<S> Mutator<UnicodeInput, S> for UnicodeCaseMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut UnicodeInput) -> Result<MutationResult, Error> {
+        let Some((base, chars)) = choose_chars(state.rand_mut(), input)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        let (last_idx, last_c) = chars[chars.len() - 1];
+        let end = base + last_idx + last_c.len_utf8();
+
+        let mode = state.rand_mut().below(3);
+        let mut converted = String::with_capacity(end - base);
+        for &(_, c) in &chars {
+            match mode {
+                0 => converted.extend(c.to_uppercase()),
+                1 => converted.extend(c.to_lowercase()),
+                _ if c.is_uppercase() => converted.extend(c.to_lowercase()),
+                _ => converted.extend(c.to_uppercase()),
+            }
+        }
+        if input.0.bytes()[base..end] == *converted.as_bytes() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        Ok(replace_range(
+            state.max_size(),
+            input,
+            base..end,
+            converted.as_bytes(),
+        ))
+    }
+}
+
+/// Encodes `c` in `len` bytes, longer than its shortest UTF-8 encoding, into `dest`
+fn encode_overlong(c: char, len: usize, dest: &mut [u8; 4]) -> &[u8] {
+    debug_assert!(len > c.len_utf8() && len <= 4);
+    let cp = c as u32;
+    for (i, byte) in dest[1..len].iter_mut().rev().enumerate() {
+        *byte = 0x80 | ((cp >> (6 * i)) & 0x3f) as u8;
+    }
+    // The lead byte has `len` leading ones, and the remaining bits of the codepoint, which are zero-padded
+    let lead_marker = !(0xffu8 >> len);
+    dest[0] = lead_marker | (cp >> (6 * (len - 1))) as u8;
+    &dest[..len]
+}
+
+/// Mutator which replaces a char of a randomly selected string-like range with an overlong,
+/// i.e., invalid but naively decodable, UTF-8 encoding of it, like `0xc0 0xaf` for `/`.
+///
+/// The result is no longer valid UTF-8, so this mutator is not part of any default set,
+/// and should only be added for targets which are expected to reject, or normalize, such inputs.
+#[derive(Debug, Default)]
+pub struct UnicodeOverlongMutator;
+
+impl Named for UnicodeOverlongMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("string-overlong");
+        &NAME
+    }
+}
+
+impl<S> Mutator<UnicodeInput, S> for UnicodeOverlongMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut UnicodeInput) -> Result<MutationResult, Error> {
+        let Some((base, chars)) = choose_chars(state.rand_mut(), input)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        let (c_idx, c) = chars[state.rand_mut().below(chars.len())];
+        if c.len_utf8() == 4 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let len = state.rand_mut().between(c.len_utf8() + 1, 4);
+        let mut dest = [0u8; 4];
+        let start = base + c_idx;
+        Ok(replace_range(
+            state.max_size(),
+            input,
+            start..(start + c.len_utf8()),
+            encode_overlong(c, len, &mut dest),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use alloc::{string::String, vec::Vec};
+
     use libafl_bolts::{rands::StdRand, Error};
 
     use crate::{
         corpus::NopCorpus,
         inputs::{BytesInput, HasMutatorBytes},
-        mutators::{Mutator, UnicodeCategoryRandMutator, UnicodeSubcategoryRandMutator},
-        stages::extract_metadata,
+        mutators::{
+            MutationResult, Mutator, UnicodeCaseMutator, UnicodeCategoryRandMutator,
+            UnicodeCodepointSwapMutator, UnicodeConfusableMutator, UnicodeNormalizationMutator,
+            UnicodeOverlongMutator, UnicodeSpanClassReplaceMutator, UnicodeSubcategoryRandMutator,
+        },
+        stages::{extract_metadata, UnicodeSpanClass},
         state::StdState,
     };
 
+    use super::{encode_overlong, NormalizationForm, UnicodeInput, CONFUSABLES, INVISIBLES};
+
+    type TestState = StdState<BytesInput, NopCorpus<BytesInput>, StdRand, NopCorpus<BytesInput>>;
+
+    /// Mutates `original` many times with `mutator`, and passes each mutant to `check`
+    fn check_mutants<M, F>(mutator: &mut M, original: &str, check: F)
+    where
+        M: Mutator<UnicodeInput, TestState>,
+        F: Fn(&[u8]),
+    {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            NopCorpus::<BytesInput>::new(),
+            NopCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        let mut mutated = 0;
+        for _ in 0..(1 << 10) {
+            let bytes = BytesInput::from(original.as_bytes());
+            let metadata = extract_metadata(bytes.bytes());
+            let mut input = (bytes, metadata);
+            if mutator.mutate(&mut state, &mut input).unwrap() == MutationResult::Mutated {
+                check(input.0.bytes());
+                mutated += 1;
+            }
+        }
+        assert!(mutated > 0);
+    }
+
+    /// The chars of `s`, sorted
+    fn sorted_chars(s: &str) -> Vec<char> {
+        let mut chars = s.chars().collect::<Vec<_>>();
+        chars.sort_unstable();
+        chars
+    }
+
     // a not-so-useful test for this
     #[test]
     fn mutate_hex() {
@@ -569,4 +1145,116 @@ mod test {
             panic!("failed with error: {e}");
         }
     }
+
+    #[test]
+    fn normalization_forms() {
+        let decomposed = NormalizationForm::Decomposed.apply("Ça été");
+        assert_eq!(decomposed, "C\u{327}a e\u{301}te\u{301}");
+        assert_eq!(NormalizationForm::Composed.apply(&decomposed), "Ça été");
+
+        let fullwidth = NormalizationForm::Fullwidth.apply("a1 !");
+        assert_eq!(fullwidth, "\u{ff41}\u{ff11} \u{ff01}");
+        assert_eq!(NormalizationForm::Folded.apply(&fullwidth), "a1 !");
+    }
+
+    #[test]
+    fn mutate_normalization() {
+        let original = "Ça été";
+        check_mutants(&mut UnicodeNormalizationMutator, original, |bytes| {
+            let mutant = core::str::from_utf8(bytes).unwrap();
+            assert_ne!(mutant, original);
+            let restored = NormalizationForm::Folded.apply(mutant);
+            assert_eq!(NormalizationForm::Composed.apply(&restored), original);
+        });
+    }
+
+    #[test]
+    fn mutate_span_class_replace() {
+        let original = "abc123 +-";
+        check_mutants(&mut UnicodeSpanClassReplaceMutator, original, |bytes| {
+            let mutant = core::str::from_utf8(bytes).unwrap();
+            // exactly a single char is replaced by one of the same class
+            assert_eq!(mutant.chars().count(), original.chars().count());
+            assert_eq!(
+                mutant
+                    .chars()
+                    .zip(original.chars())
+                    .filter(|(a, b)| a != b)
+                    .count(),
+                1
+            );
+            assert!(mutant
+                .chars()
+                .zip(original.chars())
+                .all(|(a, b)| UnicodeSpanClass::of(a) == UnicodeSpanClass::of(b)));
+        });
+    }
+
+    #[test]
+    fn overlong_encodings() {
+        let mut dest = [0u8; 4];
+        assert_eq!(encode_overlong('/', 2, &mut dest), [0xc0, 0xaf]);
+        assert_eq!(encode_overlong('/', 3, &mut dest), [0xe0, 0x80, 0xaf]);
+        assert_eq!(encode_overlong('/', 4, &mut dest), [0xf0, 0x80, 0x80, 0xaf]);
+        assert_eq!(encode_overlong('\u{e9}', 3, &mut dest), [0xe0, 0x83, 0xa9]);
+    }
+
+    #[test]
+    fn mutate_codepoint_swap() {
+        let original = "Ça été, 日本";
+        check_mutants(&mut UnicodeCodepointSwapMutator, original, |bytes| {
+            let mutant = core::str::from_utf8(bytes).unwrap();
+            assert_ne!(mutant, original);
+            assert_eq!(sorted_chars(mutant), sorted_chars(original));
+        });
+    }
+
+    #[test]
+    fn mutate_confusable() {
+        let original = "Password/Admin";
+        check_mutants(&mut UnicodeConfusableMutator, original, |bytes| {
+            let mutant = core::str::from_utf8(bytes).unwrap();
+            // Removing the invisible chars, and mapping the lookalikes back, restores the original
+            let restored = mutant
+                .chars()
+                .filter(|c| !INVISIBLES.contains(c))
+                .map(|c| {
+                    CONFUSABLES
+                        .iter()
+                        .find(|&&(_, confusable)| confusable == c)
+                        .map_or(c, |&(ascii, _)| ascii)
+                })
+                .collect::<String>();
+            assert_eq!(restored, original);
+            assert_eq!(mutant.chars().filter(|c| !c.is_ascii()).count(), 1);
+        });
+    }
+
+    #[test]
+    fn mutate_case() {
+        let original = "Hello World straße";
+        check_mutants(&mut UnicodeCaseMutator, original, |bytes| {
+            let mutant = core::str::from_utf8(bytes).unwrap();
+            assert_ne!(mutant, original);
+            // Uppercasing may expand the ß, which does not come back
+            let lowercase = mutant.to_lowercase();
+            assert!(
+                lowercase == original.to_lowercase()
+                    || lowercase == original.to_lowercase().replace('ß', "ss")
+            );
+        });
+    }
+
+    #[test]
+    fn mutate_overlong() {
+        let original = "../etc/passwd";
+        check_mutants(&mut UnicodeOverlongMutator, original, |bytes| {
+            assert!(core::str::from_utf8(bytes).is_err());
+            // Each ASCII char takes at most 4 bytes
+            assert!((original.len() + 1..=original.len() + 3).contains(&bytes.len()));
+            let overlong = bytes.iter().position(|&b| b >= 0x80).unwrap();
+            assert!(matches!(bytes[overlong], 0xc0 | 0xc1 | 0xe0 | 0xf0));
+            assert!(bytes[..overlong].iter().all(u8::is_ascii));
+        });
+    }
 }
@@ -0,0 +1,393 @@
+//! Type-aware mutators for [`ValueInput`]s. Each mutator changes a random [`Value`] according to its type,
+//! and integers never leave their range. See [`crate::inputs::value`] for details.
+
+use alloc::borrow::Cow;
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    Error, Named,
+};
+
+use crate::{
+    inputs::value::{Value, ValueInput},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+};
+
+/// Integer values worth trying, clamped to the range of each value
+const INTERESTING_INTS: [i128; 16] = [
+    0,
+    1,
+    -1,
+    i8::MIN as i128,
+    i8::MAX as i128,
+    u8::MAX as i128,
+    i16::MIN as i128,
+    i16::MAX as i128,
+    u16::MAX as i128,
+    i32::MIN as i128,
+    i32::MAX as i128,
+    u32::MAX as i128,
+    i64::MIN as i128,
+    i64::MAX as i128,
+    u64::MAX as i128,
+    1024,
+];
+
+/// Float values worth trying, for singles and doubles alike
+const INTERESTING_FLOATS: [f64; 14] = [
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    0.5,
+    f64::MIN,
+    f64::MAX,
+    f64::MIN_POSITIVE,
+    f64::EPSILON,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    f64::NAN,
+    // The smallest subnormal
+    5e-324,
+    1e100,
+];
+
+macro_rules! ulp_step_impl {
+    ($name:ident, $float:ty, $bits:ty) => {
+        /// The float `steps` units in the last place above, or below, `value`
+        fn $name(value: $float, steps: $bits, up: bool) -> $float {
+            if value.is_nan() || value.is_infinite() {
+                return value;
+            }
+            let mut value = value;
+            for _ in 0..steps {
+                if value == 0.0 {
+                    let tiny = <$float>::from_bits(1);
+                    value = if up { tiny } else { -tiny };
+                    continue;
+                }
+                let bits = value.to_bits();
+                // The bits of positive floats grow with the value, the ones of negative floats shrink
+                value = <$float>::from_bits(if (value > 0.0) == up {
+                    bits + 1
+                } else {
+                    bits - 1
+                });
+                if value.is_infinite() {
+                    break;
+                }
+            }
+            value
+        }
+    };
+}
+
+ulp_step_impl!(ulp_step_f32, f32, u32);
+ulp_step_impl!(ulp_step_f64, f64, u64);
+
+/// Applies `f` to an integer value, as `(value, min, max)`, and clamps the result into the range.
+/// Returns `false` if `value` is not an integer.
+fn map_int<F>(value: &mut Value, f: F) -> bool
+where
+    F: FnOnce(i128, i128, i128) -> i128,
+{
+    match value {
+        Value::Int { value, min, max } => {
+            let new = f((*value).into(), (*min).into(), (*max).into());
+            *value = i64::try_from(new.clamp((*min).into(), (*max).into())).unwrap();
+            true
+        }
+        Value::UInt { value, min, max } => {
+            let new = f((*value).into(), (*min).into(), (*max).into());
+            *value = u64::try_from(new.clamp((*min).into(), (*max).into())).unwrap();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Mutates a random value of `input` with `mutate`, and reports whether it changed
+fn mutate_random_value<S, F>(state: &mut S, input: &mut ValueInput, mutate: F) -> MutationResult
+where
+    S: HasRand,
+    F: FnOnce(&mut S::Rand, &mut Value),
+{
+    if input.values().is_empty() {
+        return MutationResult::Skipped;
+    }
+    let rand = state.rand_mut();
+    let idx = rand.below(input.values().len());
+    let value = &mut input.values_mut()[idx];
+    let before = *value;
+    mutate(rand, value);
+    if before == *value {
+        MutationResult::Skipped
+    } else {
+        MutationResult::Mutated
+    }
+}
+
+/// Sets a random value to a boundary of its range, or to an interesting value of its type
+#[derive(Debug, Default)]
+pub struct ValueBoundaryMutator;
+
+impl<S> Mutator<ValueInput, S> for ValueBoundaryMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut ValueInput) -> Result<MutationResult, Error> {
+        Ok(mutate_random_value(
+            state,
+            input,
+            |rand, value| match value {
+                Value::Bool(value) => *value = !*value,
+                #[allow(clippy::cast_possible_truncation)]
+                Value::F32(value) => {
+                    *value = INTERESTING_FLOATS[rand.below(INTERESTING_FLOATS.len())] as f32;
+                }
+                Value::F64(value) => {
+                    *value = INTERESTING_FLOATS[rand.below(INTERESTING_FLOATS.len())];
+                }
+                Value::Enum { variant, variants } => {
+                    *variant = if rand.coinflip(0.5) { 0 } else { *variants - 1 };
+                }
+                Value::Int { .. } | Value::UInt { .. } => {
+                    let choice = rand.below(INTERESTING_INTS.len() + 4);
+                    map_int(value, |_, min, max| match choice {
+                        0 => min,
+                        1 => min + 1,
+                        2 => max,
+                        3 => max - 1,
+                        _ => INTERESTING_INTS[choice - 4],
+                    });
+                }
+            },
+        ))
+    }
+}
+
+impl Named for ValueBoundaryMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ValueBoundaryMutator");
+        &NAME
+    }
+}
+
+impl ValueBoundaryMutator {
+    /// Creates a new [`ValueBoundaryMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Flips a random bit of a random value
+#[derive(Debug, Default)]
+pub struct ValueBitFlipMutator;
+
+impl<S> Mutator<ValueInput, S> for ValueBitFlipMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut ValueInput) -> Result<MutationResult, Error> {
+        Ok(mutate_random_value(state, input, |rand, value| {
+            let bit = rand.below(64);
+            match value {
+                Value::Bool(value) => *value = !*value,
+                Value::F32(value) => *value = f32::from_bits(value.to_bits() ^ (1 << (bit % 32))),
+                Value::F64(value) => *value = f64::from_bits(value.to_bits() ^ (1 << bit)),
+                Value::Enum { variant, variants } => {
+                    *variant = (*variant ^ (1 << (bit % 32))) % *variants;
+                }
+                Value::Int { .. } | Value::UInt { .. } => {
+                    map_int(value, |value, _, _| value ^ (1 << bit));
+                }
+            }
+        }))
+    }
+}
+
+impl Named for ValueBitFlipMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ValueBitFlipMutator");
+        &NAME
+    }
+}
+
+impl ValueBitFlipMutator {
+    /// Creates a new [`ValueBitFlipMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Adds or subtracts a small amount from a random value.
+/// Floats move by a few units in the last place, to the adjacent representable values.
+#[derive(Debug, Default)]
+pub struct ValueArithMutator;
+
+impl<S> Mutator<ValueInput, S> for ValueArithMutator
+where
+    S: HasRand,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate(&mut self, state: &mut S, input: &mut ValueInput) -> Result<MutationResult, Error> {
+        Ok(mutate_random_value(state, input, |rand, value| {
+            let up = rand.coinflip(0.5);
+            let amount = 1 + rand.below(16);
+            match value {
+                Value::Bool(value) => *value = !*value,
+                Value::F32(value) => *value = ulp_step_f32(*value, amount as u32, up),
+                Value::F64(value) => *value = ulp_step_f64(*value, amount as u64, up),
+                Value::Enum { variant, variants } => {
+                    let step = amount as u32 % *variants;
+                    *variant = if up {
+                        (*variant + step) % *variants
+                    } else {
+                        (*variant + *variants - step) % *variants
+                    };
+                }
+                Value::Int { .. } | Value::UInt { .. } => {
+                    map_int(value, |value, _, _| {
+                        if up {
+                            value + amount as i128
+                        } else {
+                            value - amount as i128
+                        }
+                    });
+                }
+            }
+        }))
+    }
+}
+
+impl Named for ValueArithMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ValueArithMutator");
+        &NAME
+    }
+}
+
+impl ValueArithMutator {
+    /// Creates a new [`ValueArithMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Sets a random value to a random value of its type and range
+#[derive(Debug, Default)]
+pub struct ValueRandMutator;
+
+impl<S> Mutator<ValueInput, S> for ValueRandMutator
+where
+    S: HasRand,
+{
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_wrap
+    )]
+    fn mutate(&mut self, state: &mut S, input: &mut ValueInput) -> Result<MutationResult, Error> {
+        Ok(mutate_random_value(state, input, |rand, value| {
+            let random = rand.next();
+            match value {
+                Value::Bool(value) => *value = random & 1 == 1,
+                Value::F32(value) => *value = f32::from_bits(random as u32),
+                Value::F64(value) => *value = f64::from_bits(random),
+                Value::Enum { variant, variants } => {
+                    *variant = (random % u64::from(*variants)) as u32;
+                }
+                Value::Int { .. } | Value::UInt { .. } => {
+                    map_int(value, |_, min, max| {
+                        let span = (max - min + 1) as u128;
+                        min + (u128::from(random) % span) as i128
+                    });
+                }
+            }
+        }))
+    }
+}
+
+impl Named for ValueRandMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ValueRandMutator");
+        &NAME
+    }
+}
+
+impl ValueRandMutator {
+    /// Creates a new [`ValueRandMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Tuple type of the mutations for [`ValueInput`]s
+pub type ValueMutationsType = tuple_list_type!(
+    ValueBoundaryMutator,
+    ValueBitFlipMutator,
+    ValueArithMutator,
+    ValueRandMutator,
+);
+
+/// Get the mutations for [`ValueInput`]s
+#[must_use]
+pub fn value_mutations() -> ValueMutationsType {
+    tuple_list!(
+        ValueBoundaryMutator::new(),
+        ValueBitFlipMutator::new(),
+        ValueArithMutator::new(),
+        ValueRandMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inputs::value::{Value, ValueInput},
+        mutators::{
+            value::{ulp_step_f32, ulp_step_f64, value_mutations},
+            MutatorsTuple,
+        },
+        state::test::test_std_state,
+    };
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_ulp_step() {
+        assert_eq!(ulp_step_f64(1.0, 1, true), 1.0 + f64::EPSILON);
+        assert_eq!(ulp_step_f64(-1.0, 1, false), -1.0 - f64::EPSILON);
+        assert_eq!(ulp_step_f32(0.0, 1, true), f32::from_bits(1));
+        assert_eq!(
+            ulp_step_f32(f32::from_bits(1), 2, false),
+            -f32::from_bits(1)
+        );
+        assert_eq!(ulp_step_f64(f64::MAX, 1, true), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_value_mutations() {
+        let mut state = test_std_state::<ValueInput>();
+        let mut input = ValueInput::new(vec![
+            Value::Bool(false),
+            Value::int(0, -10..=10),
+            Value::uint(5, 1..=u64::MAX),
+            Value::F32(0.0),
+            Value::F64(1.0),
+            Value::enumeration(0, 3),
+        ]);
+        let mut mutations = value_mutations();
+        for _ in 0..512 {
+            mutations.mutate_all(&mut state, &mut input).unwrap();
+            assert!((-10..=10).contains(&input.values()[1].as_i64().unwrap()));
+            assert_ne!(input.values()[2].as_u64().unwrap(), 0);
+            assert!(input.values()[5].as_variant().unwrap() < 3);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! The [`AutoDictStage`] mines the comparison operands logged by a cmplog executor into the [`Tokens`] dictionary,
+//! similar to AFL++'s autodict, but computed entirely at runtime.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::{
+    mutators::Tokens,
+    observers::cmp::{CmpValues, CmpValuesMetadata},
+    stages::Stage,
+    state::UsesState,
+    Error, HasMetadata,
+};
+
+/// The default minimum length of a dictionary token mined by the [`AutoDictStage`]
+pub const AUTODICT_DEFAULT_MIN_LEN: usize = 2;
+/// The default maximum length of a dictionary token mined by the [`AutoDictStage`]
+pub const AUTODICT_DEFAULT_MAX_LEN: usize = 32;
+/// The default maximum amount of tokens in the dictionary, after which the [`AutoDictStage`] stops adding tokens
+pub const AUTODICT_DEFAULT_MAX_TOKENS: usize = 4096;
+
+/// A stage adding the operands of the comparisons logged for the current input to the [`Tokens`] state metadata,
+/// where the token mutators, like [`crate::mutators::TokenInsert`] and [`crate::mutators::TokenReplace`], will pick them up.
+///
+/// It needs a [`CmpValuesMetadata`] in the state, usually filled by a [`crate::stages::TracingStage`]
+/// with a cmplog executor right before this stage.
+/// Byte operands are added with their trailing `NUL`s stripped, numeric operands of at least two bytes are added
+/// in little endian, if they are not trivial (all bytes the same, like `0` or `-1`).
+/// The [`Tokens`] deduplicate all entries, and are stored in the state, so the dictionary survives restarts.
+#[derive(Clone, Debug)]
+pub struct AutoDictStage<E, EM, Z> {
+    min_len: usize,
+    max_len: usize,
+    max_tokens: usize,
+    numeric: bool,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for AutoDictStage<E, EM, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for AutoDictStage<E, EM, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: UsesState<State = Self::State>,
+    Z: UsesState,
+    Self::State: HasMetadata,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(cmps) = state.metadata_map().get::<CmpValuesMetadata>() else {
+            return Ok(());
+        };
+
+        let mut candidates = Vec::new();
+        for cmp in cmps.iter() {
+            if cmp.is_numeric() && !self.numeric {
+                continue;
+            }
+            match cmp {
+                CmpValues::U8(_) => (),
+                CmpValues::U16((v0, v1)) => {
+                    candidates.push(v0.to_le_bytes().to_vec());
+                    candidates.push(v1.to_le_bytes().to_vec());
+                }
+                CmpValues::U32((v0, v1)) => {
+                    candidates.push(v0.to_le_bytes().to_vec());
+                    candidates.push(v1.to_le_bytes().to_vec());
+                }
+                CmpValues::U64((v0, v1)) => {
+                    candidates.push(v0.to_le_bytes().to_vec());
+                    candidates.push(v1.to_le_bytes().to_vec());
+                }
+                CmpValues::Bytes((v0, v1)) => {
+                    for v in [v0, v1] {
+                        let len = v.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+                        if len >= self.min_len {
+                            candidates.push(v[..len.min(self.max_len)].to_vec());
+                        }
+                    }
+                }
+            }
+        }
+        candidates
+            .retain(|token| token.len() >= self.min_len && token.iter().any(|&b| b != token[0]));
+
+        if candidates.is_empty() {
+            return Ok(());
+        }
+
+        let tokens = state.metadata_or_insert_with(Tokens::new);
+        for token in &candidates {
+            if tokens.len() >= self.max_tokens {
+                break;
+            }
+            tokens.add_token(token);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Stage does not run the target. No reset helper needed.
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Stage does not run the target. No reset helper needed.
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> AutoDictStage<E, EM, Z> {
+    /// Creates a new [`AutoDictStage`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            min_len: AUTODICT_DEFAULT_MIN_LEN,
+            max_len: AUTODICT_DEFAULT_MAX_LEN,
+            max_tokens: AUTODICT_DEFAULT_MAX_TOKENS,
+            numeric: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Only adds tokens of at least `min_len` and at most `max_len` bytes, longer byte operands get truncated
+    #[must_use]
+    pub fn with_len_range(mut self, min_len: usize, max_len: usize) -> Self {
+        self.min_len = min_len.max(1);
+        self.max_len = max_len;
+        self
+    }
+
+    /// Stops adding tokens once the dictionary holds `max_tokens` entries
+    #[must_use]
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Sets whether numeric comparison operands are added to the dictionary, too (the default)
+    #[must_use]
+    pub fn with_numeric(mut self, numeric: bool) -> Self {
+        self.numeric = numeric;
+        self
+    }
+}
+
+impl<E, EM, Z> Default for AutoDictStage<E, EM, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::{
+        events::NopEventManager,
+        executors::test::NopExecutor,
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        mutators::Tokens,
+        observers::cmp::{CmpValues, CmpValuesMetadata},
+        stages::{test, AutoDictStage, Stage},
+        state::test::test_std_state,
+        HasMetadata,
+    };
+
+    type TestState = test::TestState<BytesInput>;
+    type TestStage =
+        AutoDictStage<NopExecutor<TestState>, NopEventManager<TestState>, NopFuzzer<TestState>>;
+
+    /// Runs an [`AutoDictStage`] over some logged comparisons, returns the mined tokens
+    fn mine(stage: &mut TestStage) -> Vec<Vec<u8>> {
+        let mut state = test_std_state::<BytesInput>();
+        let mut cmps = CmpValuesMetadata::new();
+        cmps.list.extend([
+            CmpValues::Bytes((b"magic\0\0".to_vec(), b"ab".to_vec())),
+            CmpValues::Bytes((b"a".to_vec(), b"a_long_operand".to_vec())),
+            CmpValues::U32((0, 0x1122_3344)),
+            CmpValues::U8((1, 2)),
+        ]);
+        state.add_metadata(cmps);
+
+        // running the stage twice doesn't add duplicates
+        for _ in 0..2 {
+            stage
+                .perform(
+                    &mut NopFuzzer::new(),
+                    &mut NopExecutor::new(),
+                    &mut state,
+                    &mut NopEventManager::new(),
+                )
+                .unwrap();
+        }
+        state.metadata::<Tokens>().unwrap().tokens().to_vec()
+    }
+
+    #[test]
+    fn test_autodict_stage() {
+        // # Safety
+        // No concurrency per testcase
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            CmpValuesMetadata::register();
+            Tokens::register();
+        }
+
+        assert_eq!(
+            mine(&mut AutoDictStage::new().with_len_range(2, 8)),
+            [
+                b"magic".to_vec(),
+                b"ab".to_vec(),
+                b"a_long_o".to_vec(),
+                vec![0x44, 0x33, 0x22, 0x11],
+            ]
+        );
+        assert_eq!(
+            mine(&mut AutoDictStage::new().with_numeric(false).with_max_tokens(2)),
+            [b"magic".to_vec(), b"ab".to_vec()]
+        );
+    }
+}
@@ -1,9 +1,6 @@
 //! The calibration stage. The fuzzer measures the average exec time and the bitmap size.
 
-use alloc::{
-    borrow::{Cow, ToOwned},
-    vec::Vec,
-};
+use alloc::borrow::{Cow, ToOwned};
 use core::{fmt::Debug, marker::PhantomData, time::Duration};
 
 use hashbrown::HashSet;
@@ -59,6 +56,12 @@ impl UnstableEntriesMetadata {
     pub fn filled_entries_count(&self) -> usize {
         self.filled_entries_count
     }
+
+    /// Returns `true` if the map entry at `idx` was observed to behave non-deterministically
+    #[must_use]
+    pub fn is_unstable(&self, idx: usize) -> bool {
+        self.unstable_entries.contains(&idx)
+    }
 }
 
 impl Default for UnstableEntriesMetadata {
@@ -78,6 +81,8 @@ pub struct CalibrationStage<C, E, O, OT> {
     stage_max: usize,
     /// If we should track stability
     track_stability: bool,
+    /// If unstable entries should be masked in the map feedback's history, so that they are never considered novel again
+    mask_unstable: bool,
     phantom: PhantomData<(E, O, OT)>,
 }
 
@@ -171,7 +176,7 @@ where
         };
         let map_first_entries = map_first.to_vec();
         let map_first_len = map_first.to_vec().len();
-        let mut unstable_entries: Vec<usize> = vec![];
+        let mut unstable_entries: HashSet<usize> = HashSet::new();
         // Run CAL_STAGE_START - 1 times, increase by 2 for every time a new
         // run is found to be unstable or to crash with CAL_STAGE_MAX total runs.
         let mut i = 1;
@@ -211,25 +216,38 @@ where
                     .as_ref()
                     .to_vec();
 
-                let history_map = &mut state
-                    .named_metadata_map_mut()
-                    .get_mut::<MapFeedbackMetadata<O::Entry>>(&self.map_name)
-                    .unwrap()
-                    .history_map;
+                if self.mask_unstable {
+                    let history_map = &mut state
+                        .named_metadata_map_mut()
+                        .get_mut::<MapFeedbackMetadata<O::Entry>>(&self.map_name)
+                        .unwrap()
+                        .history_map;
 
-                if history_map.len() < map_first_len {
-                    history_map.resize(map_first_len, O::Entry::default());
-                }
+                    if history_map.len() < map_first_len {
+                        history_map.resize(map_first_len, O::Entry::default());
+                    }
 
-                for (idx, (first, (cur, history))) in map_first_entries
-                    .iter()
-                    .zip(map.iter().zip(history_map.iter_mut()))
-                    .enumerate()
-                {
-                    if *first != *cur && *history != O::Entry::max_value() {
-                        *history = O::Entry::max_value();
-                        unstable_entries.push(idx);
-                    };
+                    for (idx, (first, (cur, history))) in map_first_entries
+                        .iter()
+                        .zip(map.iter().zip(history_map.iter_mut()))
+                        .enumerate()
+                    {
+                        if *first != *cur && *history != O::Entry::max_value() {
+                            // Saturate the history, so the feedback never considers this entry novel again
+                            *history = O::Entry::max_value();
+                            unstable_entries.insert(idx);
+                        };
+                    }
+                } else {
+                    let known_unstable = state.metadata_map().get::<UnstableEntriesMetadata>();
+                    for (idx, (first, cur)) in map_first_entries.iter().zip(map.iter()).enumerate()
+                    {
+                        if *first != *cur
+                            && !known_unstable.is_some_and(|meta| meta.is_unstable(idx))
+                        {
+                            unstable_entries.insert(idx);
+                        };
+                    }
                 }
 
                 if !unstable_entries.is_empty() && iter < CAL_STAGE_MAX {
@@ -245,9 +263,7 @@ where
             let metadata = state.metadata_or_insert_with(UnstableEntriesMetadata::new);
 
             // If we see new unstable entries executing this new corpus entries, then merge with the existing one
-            for item in unstable_entries {
-                metadata.unstable_entries.insert(item); // Insert newly found items
-            }
+            metadata.unstable_entries.extend(unstable_entries); // Insert newly found items
             metadata.filled_entries_count = map_first_filled_count;
         } else if !state.has_metadata::<UnstableEntriesMetadata>() {
             send_default_stability = true;
@@ -385,6 +401,7 @@ where
             map_name: map_name.clone(),
             stage_max: CAL_STAGE_START,
             track_stability: true,
+            mask_unstable: true,
             phantom: PhantomData,
             name: Cow::Owned(
                 CALIBRATION_STAGE_NAME.to_owned() + ":" + map_name.into_owned().as_str(),
@@ -402,6 +419,17 @@ where
         ret.track_stability = false;
         ret
     }
+
+    /// Only record unstable entries in the [`struct@UnstableEntriesMetadata`], but don't mask them in the map feedback.
+    ///
+    /// By default, entries found to be unstable are saturated in the feedback's history map,
+    /// so that noise in a non-deterministic target never makes an input look novel.
+    /// Disabling this keeps the history untouched, for example to use the metadata for analysis only.
+    #[must_use]
+    pub fn without_unstable_masking(mut self) -> Self {
+        self.mask_unstable = false;
+        self
+    }
 }
 
 impl<C, E, O, OT> Named for CalibrationStage<C, E, O, OT> {
@@ -409,3 +437,103 @@ impl<C, E, O, OT> Named for CalibrationStage<C, E, O, OT> {
         &self.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::ptr::addr_of_mut;
+
+    use hashbrown::HashSet;
+    use libafl_bolts::tuples::tuple_list;
+    use serial_test::serial;
+
+    use crate::{
+        corpus::HasCurrentCorpusId,
+        executors::ExitKind,
+        feedbacks::{map::MapFeedbackMetadata, MaxMapFeedback},
+        fuzzer::Evaluator,
+        inputs::BytesInput,
+        observers::StdMapObserver,
+        stages::{calibrate::UnstableEntriesMetadata, test::test_fuzzer, CalibrationStage, Stage},
+        HasMetadata, HasNamedMetadata,
+    };
+
+    static mut MAP: [u8; 16] = [0; 16];
+    static mut COUNTER: usize = 0;
+
+    /// Calibrates a single entry of a target with an unstable map entry at index 3,
+    /// returns the unstable entries and the feedback's history map
+    fn calibrate(mask_unstable: bool) -> (HashSet<usize>, Vec<u8>) {
+        unsafe {
+            COUNTER = 0;
+        }
+        let mut harness = |_input: &BytesInput| {
+            let map = unsafe { &mut *addr_of_mut!(MAP) };
+            let counter = unsafe { &mut *addr_of_mut!(COUNTER) };
+            map[0] = 1;
+            *counter += 1;
+            if *counter % 2 == 0 {
+                map[3] = 1;
+            }
+            ExitKind::Ok
+        };
+        let edges = unsafe {
+            StdMapObserver::from_mut_slice("edges", (*addr_of_mut!(MAP)).as_mut_slice().into())
+        };
+
+        let feedback = MaxMapFeedback::new(&edges);
+        let mut stage = CalibrationStage::new(&feedback);
+        if !mask_unstable {
+            stage = stage.without_unstable_masking();
+        }
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(edges), feedback);
+
+        // the first execution doesn't hit the unstable entry
+        let corpus_id = fuzzer
+            .add_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(b"a".to_vec()),
+            )
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+
+        let unstable = state
+            .metadata::<UnstableEntriesMetadata>()
+            .unwrap()
+            .unstable_entries()
+            .clone();
+        let history = state
+            .named_metadata::<MapFeedbackMetadata<u8>>("edges")
+            .unwrap()
+            .history_map
+            .clone();
+        (unstable, history)
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_calibration_masks_unstable_entries() {
+        let (unstable, history) = calibrate(true);
+        assert_eq!(unstable, HashSet::from([3]));
+        // the unstable entry is never novel again, the stable ones are untouched
+        assert_eq!(history[3], u8::MAX);
+        assert_eq!(history[0], 1);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_calibration_without_unstable_masking() {
+        let (unstable, history) = calibrate(false);
+        assert_eq!(unstable, HashSet::from([3]));
+        assert_eq!(history[3], 0);
+        assert_eq!(history[0], 1);
+    }
+}
@@ -0,0 +1,288 @@
+//! The [`CheckpointStage`] periodically writes the state to disk from a background thread,
+//! so that a campaign can resume even after the whole machine went down, and not only after a crash of the target.
+
+use alloc::vec::Vec;
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+};
+
+use libafl_bolts::current_time;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    common::serialization::{deserialize_with_header, serialize_with_header, SerializationFormat},
+    stages::Stage,
+    state::{State, UsesState},
+    Error,
+};
+
+/// The default interval between two checkpoints
+pub const CHECKPOINT_DEFAULT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Writes serialized states to a file, from a background thread.
+///
+/// The state is serialized on the calling thread, which is fast compared to writing and syncing it to disk,
+/// and only the bytes are handed over. At most one checkpoint waits while another one is being written,
+/// further ones are skipped, so a slow disk never stalls the fuzzer or piles up memory.
+#[derive(Debug)]
+pub struct Checkpointer {
+    path: PathBuf,
+    format: SerializationFormat,
+    sender: Option<SyncSender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+    skipped: u64,
+}
+
+impl Checkpointer {
+    /// Create a new [`Checkpointer`], writing to `path`
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_format(path, SerializationFormat::Postcard)
+    }
+
+    /// Create a new [`Checkpointer`], writing to `path` in the given `format`
+    pub fn with_format<P>(path: P, format: SerializationFormat) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        // Double buffering: one checkpoint is written, one more may wait
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(1);
+        let writer_path = path.clone();
+        let writer = thread::Builder::new()
+            .name("checkpoint".into())
+            .spawn(move || {
+                for bytes in receiver {
+                    if let Err(err) = write_checkpoint(&writer_path, &bytes) {
+                        log::error!(
+                            "Failed to write the checkpoint {}: {err}",
+                            writer_path.display()
+                        );
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            path,
+            format,
+            sender: Some(sender),
+            writer: Some(writer),
+            skipped: 0,
+        })
+    }
+
+    /// The file the checkpoints are written to
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The format the checkpoints are written in
+    #[must_use]
+    pub fn format(&self) -> SerializationFormat {
+        self.format
+    }
+
+    /// The amount of checkpoints skipped so far, because the previous ones were still being written
+    #[must_use]
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Serialize `state` and write it in the background.
+    /// Returns `false` if the checkpoint was skipped, because the previous ones are still being written.
+    pub fn checkpoint<S>(&mut self, state: &S) -> Result<bool, Error>
+    where
+        S: Serialize,
+    {
+        let bytes = serialize_with_header(self.format, state)?;
+        match self.sender.as_ref().unwrap().try_send(bytes) {
+            Ok(()) => Ok(true),
+            Err(TrySendError::Full(_)) => {
+                self.skipped += 1;
+                Ok(false)
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                Err(Error::illegal_state("The checkpoint writer thread is gone"))
+            }
+        }
+    }
+
+    /// Wait until the last checkpoint has been written
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), Error> {
+        drop(self.sender.take());
+        if let Some(writer) = self.writer.take() {
+            writer
+                .join()
+                .map_err(|_| Error::illegal_state("The checkpoint writer thread panicked"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Checkpointer {
+    fn drop(&mut self) {
+        drop(self.join());
+    }
+}
+
+/// Write a checkpoint next to `path` and move it in place, so a crash while writing keeps the previous one
+fn write_checkpoint(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let mut tmp = path.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Load the state from a checkpoint written by a [`Checkpointer`] or a [`CheckpointStage`], in any format.
+/// Returns `None` if there is no checkpoint at `path`.
+pub fn load_checkpoint<S, P>(path: P) -> Result<Option<S>, Error>
+where
+    S: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(deserialize_with_header(&bytes)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// A stage that checkpoints the state to disk every `interval`, see [`Checkpointer`].
+/// Restore the state with [`load_checkpoint`], if the restarting event manager did not provide one.
+#[derive(Debug)]
+pub struct CheckpointStage<E, EM, Z> {
+    checkpointer: Checkpointer,
+    interval: Duration,
+    last_checkpoint: Duration,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for CheckpointStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for CheckpointStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    Self::State: State,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_checkpoint) >= self.interval {
+            // If the disk is too slow, skip this checkpoint instead of retrying on every iteration
+            self.last_checkpoint = now;
+            self.checkpointer.checkpoint(state)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> CheckpointStage<E, EM, Z> {
+    /// Create a new [`CheckpointStage`], writing to `path` every [`CHECKPOINT_DEFAULT_INTERVAL`]
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_interval(path, CHECKPOINT_DEFAULT_INTERVAL)
+    }
+
+    /// Create a new [`CheckpointStage`], writing to `path` every `interval`
+    pub fn with_interval<P>(path: P, interval: Duration) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        Ok(Self::with_checkpointer(Checkpointer::new(path)?, interval))
+    }
+
+    /// Create a new [`CheckpointStage`], checkpointing with `checkpointer` every `interval`,
+    /// for example to write the checkpoints in another format
+    #[must_use]
+    pub fn with_checkpointer(checkpointer: Checkpointer, interval: Duration) -> Self {
+        Self {
+            checkpointer,
+            interval,
+            // The first checkpoint is only due after a full interval
+            last_checkpoint: current_time(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The underlying [`Checkpointer`]
+    #[must_use]
+    pub fn checkpointer(&self) -> &Checkpointer {
+        &self.checkpointer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use libafl_bolts::rands::StdRand;
+
+    use crate::{
+        corpus::InMemoryCorpus,
+        inputs::BytesInput,
+        stages::checkpoint::{load_checkpoint, Checkpointer},
+        state::{test::test_std_state, HasExecutions, StdState},
+    };
+
+    type TestState =
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_checkpoint_roundtrip() {
+        let path = env::temp_dir().join(format!("libafl_checkpoint_test_{}", process::id()));
+        assert!(load_checkpoint::<TestState, _>(&path).unwrap().is_none());
+
+        let mut state = test_std_state::<BytesInput>();
+        *state.executions_mut() = 1234;
+        let mut checkpointer = Checkpointer::new(&path).unwrap();
+        assert!(checkpointer.checkpoint(&state).unwrap());
+        checkpointer.finish().unwrap();
+
+        let restored: TestState = load_checkpoint(&path).unwrap().unwrap();
+        assert_eq!(*restored.executions(), 1234);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+//! The [`CorpusMinimizationStage`] periodically runs a [`CorpusMinimizer`] over the live corpus,
+//! so long-running campaigns don't need to be stopped for an external corpus minimization.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    format,
+};
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::{current_time, impl_serdeany, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusMinimizer},
+    events::{Event, EventFirer, LogSeverity},
+    executors::{Executor, HasObservers},
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
+    schedulers::RemovableScheduler,
+    stages::{Stage, StdRestartHelper},
+    state::{HasCorpus, HasExecutions, UsesState},
+    Error, HasMetadata, HasNamedMetadata, HasScheduler,
+};
+
+/// The default interval between two corpus minimizations
+pub const CMIN_DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default name for [`CorpusMinimizationStage`]
+pub const CMIN_STAGE_NAME: &str = "cmin";
+
+/// Metadata keeping track of the corpus minimizations
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CorpusMinimizationMetadata {
+    /// The last time the corpus was minimized, or the stage first ran
+    pub last_time: Duration,
+    /// The executions at the last minimization, or when the stage first ran
+    pub last_executions: u64,
+    /// The amount of minimizations so far
+    pub runs: u64,
+    /// The amount of corpus entries removed by all minimizations so far
+    pub removed: u64,
+}
+
+impl_serdeany!(CorpusMinimizationMetadata);
+
+/// A stage running a [`CorpusMinimizer`], like the [`crate::corpus::StdCorpusMinimizer`], over the live corpus
+/// every `interval`, or every `exec_interval` executions, whatever comes first.
+///
+/// The minimizer removes (or disables) all redundant corpus entries, the reduction is logged
+/// and reported as `cmin_corpus` user stats (the remaining entries of the ones before the minimization).
+/// The first minimization only runs after the first interval passed, not right after the initial corpus got loaded.
+///
+/// The current corpus entry may get removed, too, so place this stage last.
+#[derive(Debug)]
+pub struct CorpusMinimizationStage<CM, E, EM, Z> {
+    minimizer: CM,
+    interval: Duration,
+    exec_interval: Option<u64>,
+    name: Cow<'static, str>,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<CM, E, EM, Z> UsesState for CorpusMinimizationStage<CM, E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<CM, E, EM, Z> Named for CorpusMinimizationStage<CM, E, EM, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<CM, E, EM, Z> Stage<E, EM, Z> for CorpusMinimizationStage<CM, E, EM, Z>
+where
+    CM: CorpusMinimizer<E>,
+    E: Executor<EM, Z> + HasObservers,
+    EM: EventFirer<State = Self::State>,
+    Z: HasScheduler<State = Self::State>,
+    Z::Scheduler: RemovableScheduler,
+    Self::State: HasCorpus + HasExecutions + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        let executions = *state.executions();
+        let Some(meta) = state
+            .metadata_map()
+            .get::<CorpusMinimizationMetadata>()
+            .copied()
+        else {
+            state.add_metadata(CorpusMinimizationMetadata {
+                last_time: now,
+                last_executions: executions,
+                ..CorpusMinimizationMetadata::default()
+            });
+            return Ok(());
+        };
+
+        let time_passed = now.saturating_sub(meta.last_time) >= self.interval;
+        let execs_passed = self.exec_interval.is_some_and(|exec_interval| {
+            executions.saturating_sub(meta.last_executions) >= exec_interval
+        });
+        if !time_passed && !execs_passed {
+            return Ok(());
+        }
+
+        // Store the time first, so we won't minimize over and over if an entry takes us down
+        state.add_metadata(CorpusMinimizationMetadata {
+            last_time: now,
+            last_executions: executions,
+            ..meta
+        });
+
+        let before = state.corpus().count() as u64;
+        self.minimizer
+            .minimize::<Z::Scheduler, EM, Z>(fuzzer, executor, manager, state)?;
+        let after = state.corpus().count() as u64;
+
+        // The minimizer executed each entry, start counting from here
+        state.add_metadata(CorpusMinimizationMetadata {
+            last_time: current_time(),
+            last_executions: *state.executions(),
+            runs: meta.runs + 1,
+            removed: meta.removed + before.saturating_sub(after),
+        });
+
+        manager.log(
+            state,
+            LogSeverity::Info,
+            format!("Corpus minimization reduced the corpus from {before} to {after} entries"),
+        )?;
+        manager.fire(
+            state,
+            Event::UpdateUserStats {
+                name: Cow::from("cmin_corpus"),
+                value: UserStats::new(UserStatsValue::Ratio(after, before), AggregatorOps::Sum),
+                phantom: PhantomData,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // The minimizer executes every entry, don't crash on the same one again
+        StdRestartHelper::no_retry(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        StdRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<CM, E, EM, Z> CorpusMinimizationStage<CM, E, EM, Z> {
+    /// Creates a new [`CorpusMinimizationStage`], running the `minimizer` every hour
+    #[must_use]
+    pub fn new(minimizer: CM) -> Self {
+        Self {
+            minimizer,
+            interval: CMIN_DEFAULT_INTERVAL,
+            exec_interval: None,
+            name: Cow::Owned(CMIN_STAGE_NAME.to_owned()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the interval between two corpus minimizations
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Additionally minimizes the corpus every `exec_interval` executions
+    #[must_use]
+    pub fn with_exec_interval(mut self, exec_interval: u64) -> Self {
+        self.exec_interval = Some(exec_interval);
+        self
+    }
+
+    /// The underlying [`CorpusMinimizer`]
+    pub fn minimizer(&self) -> &CM {
+        &self.minimizer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::{Corpus, CorpusMinimizer, Testcase},
+        events::EventFirer,
+        executors::{Executor, ExitKind, HasObservers},
+        inputs::BytesInput,
+        schedulers::{RemovableScheduler, Scheduler},
+        stages::{
+            cmin::CorpusMinimizationMetadata, test::test_fuzzer, CorpusMinimizationStage, Stage,
+        },
+        state::{HasCorpus, HasExecutions, UsesState},
+        Error, HasMetadata, HasScheduler,
+    };
+
+    /// A minimizer keeping only the first corpus entry
+    struct KeepFirstMinimizer;
+
+    impl<E> CorpusMinimizer<E> for KeepFirstMinimizer
+    where
+        E: UsesState,
+        E::State: HasCorpus,
+    {
+        fn minimize<CS, EM, Z>(
+            &self,
+            fuzzer: &mut Z,
+            _executor: &mut E,
+            _manager: &mut EM,
+            state: &mut E::State,
+        ) -> Result<(), Error>
+        where
+            E: Executor<EM, Z> + HasObservers,
+            CS: Scheduler<State = E::State> + RemovableScheduler,
+            EM: EventFirer<State = E::State>,
+            Z: HasScheduler<Scheduler = CS, State = E::State>,
+        {
+            while let Some(id) = state.corpus().last() {
+                if Some(id) == state.corpus().first() {
+                    break;
+                }
+                let removed = state.corpus_mut().remove(id)?;
+                fuzzer
+                    .scheduler_mut()
+                    .on_remove(state, id, &Some(removed))?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_corpus_minimization_stage() {
+        let mut harness = |_input: &BytesInput| ExitKind::Ok;
+
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(), ());
+        let mut stage = CorpusMinimizationStage::new(KeepFirstMinimizer).with_exec_interval(10);
+
+        for input in [b"a", b"b", b"c"] {
+            state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(input.to_vec())))
+                .unwrap();
+        }
+
+        // the first run only starts counting, so does a run before the interval passed
+        for _ in 0..2 {
+            stage
+                .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+                .unwrap();
+            assert_eq!(state.corpus().count(), 3);
+        }
+
+        *state.executions_mut() += 10;
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        assert_eq!(state.corpus().count(), 1);
+        let meta = state.metadata::<CorpusMinimizationMetadata>().unwrap();
+        assert_eq!(meta.runs, 1);
+        assert_eq!(meta.removed, 2);
+        assert_eq!(meta.last_executions, 10);
+    }
+}
@@ -58,7 +58,10 @@ impl Ord for Earlier {
 
 /// Default name for `ColorizationStage`; derived from ALF++
 pub const COLORIZATION_STAGE_NAME: &str = "colorization";
-/// The mutational stage using power schedules
+/// The colorization stage, finding the regions of the current input that can be randomized without changing its coverage.
+///
+/// The result is stored as [`TaintMetadata`] on the current [`crate::corpus::Testcase`],
+/// so each corpus entry is only colorized once, and mirrored to the state for the input-to-state stages to consume.
 #[derive(Clone, Debug)]
 pub struct ColorizationStage<C, E, EM, O, Z> {
     map_observer_handle: Handle<C>,
@@ -102,6 +105,17 @@ where
         state: &mut Self::State,
         manager: &mut EM,
     ) -> Result<(), Error> {
+        // If we colorized this testcase before, reuse the previous result
+        let cached = state
+            .current_testcase()?
+            .metadata_map()
+            .get::<TaintMetadata>()
+            .cloned();
+        if let Some(meta) = cached {
+            state.add_metadata(meta);
+            return Ok(());
+        }
+
         // Run with the mutated input
         Self::colorize(fuzzer, executor, state, manager, &self.map_observer_handle)?;
 
@@ -121,7 +135,10 @@ where
 }
 
 /// Store the taint and the input
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// The `ranges` are the "entropic" regions of the input: replacing their bytes with random values keeps the coverage,
+/// so they are the only candidates for input-to-state replacements.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(
     any(not(feature = "serdeany_autoreg"), miri),
     allow(clippy::unsafe_derive_deserialize)
@@ -155,6 +172,23 @@ impl TaintMetadata {
     pub fn ranges(&self) -> &Vec<Range<usize>> {
         &self.ranges
     }
+
+    #[must_use]
+    /// Returns `true` if the byte at `idx` lies in one of the colorized `ranges`
+    pub fn is_tainted(&self, idx: usize) -> bool {
+        // ranges are sorted and merged, see `ColorizationStage::colorize`
+        self.ranges
+            .binary_search_by(|range| {
+                if range.end <= idx {
+                    Ordering::Less
+                } else if range.start > idx {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
 }
 
 libafl_bolts::impl_serdeany!(TaintMetadata);
@@ -225,15 +259,13 @@ where
                 let range_start = r.start;
                 let range_end = r.end;
                 let copy_len = r.len();
-                unsafe {
-                    buffer_copy(
-                        input.bytes_mut(),
-                        changed.bytes(),
-                        range_start,
-                        range_start,
-                        copy_len,
-                    );
-                }
+                buffer_copy(
+                    input.bytes_mut(),
+                    changed.bytes(),
+                    range_start,
+                    range_start,
+                    copy_len,
+                );
 
                 let consumed_input = input.clone();
                 let changed_hash = Self::get_raw_map_hash_run(
@@ -254,15 +286,13 @@ where
                     // Seems like this range is too big that we can't keep the original hash anymore
 
                     // Revert the changes
-                    unsafe {
-                        buffer_copy(
-                            input.bytes_mut(),
-                            backup.bytes(),
-                            range_start,
-                            range_start,
-                            copy_len,
-                        );
-                    }
+                    buffer_copy(
+                        input.bytes_mut(),
+                        backup.bytes(),
+                        range_start,
+                        range_start,
+                        copy_len,
+                    );
 
                     // Add smaller range
                     if copy_len > 1 {
@@ -299,12 +329,13 @@ where
             }
         }
 
-        if let Some(meta) = state.metadata_map_mut().get_mut::<TaintMetadata>() {
-            meta.update(input.bytes().to_vec(), res);
+        let meta = TaintMetadata::new(input.bytes().to_vec(), res);
+        // Remember the result for this testcase, so we don't need to colorize it again
+        state.current_testcase_mut()?.add_metadata(meta.clone());
 
-            // println!("meta: {:#?}", meta);
+        if let Some(state_meta) = state.metadata_map_mut().get_mut::<TaintMetadata>() {
+            state_meta.update(meta.input_vec, meta.ranges);
         } else {
-            let meta = TaintMetadata::new(input.bytes().to_vec(), res);
             state.add_metadata::<TaintMetadata>(meta);
         }
 
@@ -443,3 +474,81 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::addr_of_mut;
+
+    use libafl_bolts::{tuples::tuple_list, AsSlice};
+    use serial_test::serial;
+
+    use crate::{
+        corpus::{Corpus, HasCurrentCorpusId, Testcase},
+        executors::ExitKind,
+        inputs::{BytesInput, HasTargetBytes},
+        observers::StdMapObserver,
+        stages::{colorization::TaintMetadata, test::test_fuzzer, ColorizationStage, Stage},
+        state::{HasCorpus, HasCurrentTestcase, HasExecutions},
+        HasMetadata,
+    };
+
+    static mut MAP: [u8; 16] = [0; 16];
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_colorization() {
+        // only the first byte has an influence on the coverage, colorization always replaces `+` with `/`
+        let mut harness = |input: &BytesInput| {
+            let map = unsafe { &mut *addr_of_mut!(MAP) };
+            map[0] = 1;
+            if input.target_bytes().as_slice()[0] == b'+' {
+                map[1] = 1;
+            }
+            ExitKind::Ok
+        };
+        let edges = unsafe {
+            StdMapObserver::from_mut_slice("edges", (*addr_of_mut!(MAP)).as_mut_slice().into())
+        };
+        let mut stage = ColorizationStage::new(&edges);
+
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(edges), ());
+
+        let corpus_id = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"+abc0123".to_vec())))
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+
+        let meta = state.metadata::<TaintMetadata>().unwrap().clone();
+        assert!(!meta.is_tainted(0));
+        assert!((1..8).all(|idx| meta.is_tainted(idx)));
+        assert_eq!(meta.ranges().len(), 1);
+        assert_eq!(meta.ranges()[0], 1..8);
+        assert_eq!(
+            state
+                .current_testcase()
+                .unwrap()
+                .metadata::<TaintMetadata>()
+                .unwrap()
+                .ranges(),
+            meta.ranges()
+        );
+
+        // the result is reused for the same testcase, without running the target again
+        state.remove_metadata::<TaintMetadata>();
+        let executions = *state.executions();
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        assert_eq!(*state.executions(), executions);
+        assert_eq!(
+            state.metadata::<TaintMetadata>().unwrap().ranges(),
+            meta.ranges()
+        );
+    }
+}
@@ -1,39 +1,39 @@
 //! This module contains the `concolic` stages, which can trace a target using symbolic execution
 //! and use the results for fuzzer input and mutations.
 //!
-use alloc::borrow::{Cow, ToOwned};
-#[cfg(feature = "concolic_mutation")]
-use alloc::{string::ToString, vec::Vec};
-#[cfg(feature = "concolic_mutation")]
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+    vec::Vec,
+};
 use core::marker::PhantomData;
 
 use libafl_bolts::{
     tuples::{Handle, MatchNameRef},
     Named,
 };
+use serde::{Deserialize, Serialize};
 
-#[cfg(all(feature = "concolic_mutation", feature = "introspection"))]
+#[cfg(feature = "introspection")]
 use crate::monitors::PerfFeature;
-#[cfg(all(feature = "introspection", feature = "concolic_mutation"))]
+#[cfg(feature = "concolic_mutation")]
+use crate::observers::concolic::{SymExpr, SymExprRef};
+#[cfg(feature = "introspection")]
 use crate::state::HasClientPerfMonitor;
 use crate::{
     executors::{Executor, HasObservers},
-    observers::concolic::ConcolicObserver,
-    stages::{Stage, StdRestartHelper, TracingStage},
-    state::{HasCorpus, HasCurrentTestcase, HasExecutions, UsesState},
-    Error, HasMetadata, HasNamedMetadata,
-};
-#[cfg(feature = "concolic_mutation")]
-use crate::{
     inputs::HasMutatorBytes,
     mark_feature_time,
-    observers::concolic::{ConcolicMetadata, SymExpr, SymExprRef},
+    observers::concolic::{ConcolicMetadata, ConcolicObserver},
+    stages::{Stage, StdRestartHelper, TracingStage},
     start_timer,
-    state::State,
-    Evaluator,
+    state::{HasCorpus, HasCurrentTestcase, HasExecutions, State, UsesState},
+    Error, Evaluator, HasMetadata, HasNamedMetadata,
 };
 
 /// Wraps a [`TracingStage`] to add concolic observing.
+///
+/// Each corpus entry is only traced once, entries that already carry a [`ConcolicMetadata`] are skipped.
 #[derive(Clone, Debug)]
 pub struct ConcolicTracingStage<'a, EM, TE, Z> {
     name: Cow<'static, str>,
@@ -73,6 +73,11 @@ where
         state: &mut Self::State,
         manager: &mut EM,
     ) -> Result<(), Error> {
+        if state.current_testcase()?.has_metadata::<ConcolicMetadata>() {
+            // Tracing is expensive, and the trace of an input doesn't change
+            return Ok(());
+        }
+
         self.inner.trace(fuzzer, state, manager)?;
         if let Some(observer) = self.inner.executor().observers().get(&self.observer_handle) {
             let metadata = observer.create_metadata_from_current_map();
@@ -357,42 +362,85 @@ fn generate_mutations(iter: impl Iterator<Item = (SymExprRef, SymExpr)>) -> Vec<
     res
 }
 
-/// A mutational stage that uses Z3 to solve concolic constraints attached to the [`crate::corpus::Testcase`] by the [`ConcolicTracingStage`].
+/// A solver for the path constraints collected by the [`ConcolicTracingStage`].
+///
+/// Closures taking the [`ConcolicMetadata`] can be used as solvers, for example to call out to an external tool.
+pub trait ConcolicSolver {
+    /// Solves the constraints of the trace in `metadata`.
+    /// Returns one list of `(offset, new byte)` replacements for each new input to try.
+    fn solve(&mut self, metadata: &ConcolicMetadata) -> Result<Vec<Vec<(usize, u8)>>, Error>;
+}
+
+impl<F> ConcolicSolver for F
+where
+    F: FnMut(&ConcolicMetadata) -> Result<Vec<Vec<(usize, u8)>>, Error>,
+{
+    fn solve(&mut self, metadata: &ConcolicMetadata) -> Result<Vec<Vec<(usize, u8)>>, Error> {
+        self(metadata)
+    }
+}
+
+/// The built-in [`ConcolicSolver`], negating each branch condition along the trace using Z3
 #[cfg(feature = "concolic_mutation")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Z3ConcolicSolver;
+
+#[cfg(feature = "concolic_mutation")]
+impl ConcolicSolver for Z3ConcolicSolver {
+    fn solve(&mut self, metadata: &ConcolicMetadata) -> Result<Vec<Vec<(usize, u8)>>, Error> {
+        Ok(generate_mutations(metadata.iter_messages()))
+    }
+}
+
+/// Marks a [`crate::corpus::Testcase`] whose concolic trace was already solved
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct ConcolicSolvedMetadata;
+
+libafl_bolts::impl_serdeany!(ConcolicSolvedMetadata);
+
+/// A mutational stage that solves concolic constraints attached to the [`crate::corpus::Testcase`] by the [`ConcolicTracingStage`],
+/// using the given [`ConcolicSolver`], and evaluates each resulting input.
+///
+/// The constraints of each corpus entry are only solved once.
 #[derive(Clone, Debug, Default)]
-pub struct SimpleConcolicMutationalStage<Z> {
+pub struct ConcolicMutationalStage<SV, Z> {
     name: Cow<'static, str>,
+    solver: SV,
     phantom: PhantomData<Z>,
 }
 
+/// A mutational stage that uses Z3 to solve concolic constraints attached to the [`crate::corpus::Testcase`] by the [`ConcolicTracingStage`].
 #[cfg(feature = "concolic_mutation")]
-impl<Z> UsesState for SimpleConcolicMutationalStage<Z>
+pub type SimpleConcolicMutationalStage<Z> = ConcolicMutationalStage<Z3ConcolicSolver, Z>;
+
+impl<SV, Z> UsesState for ConcolicMutationalStage<SV, Z>
 where
     Z: UsesState,
 {
     type State = Z::State;
 }
 
-#[cfg(feature = "concolic_mutation")]
 /// The unique id for this stage
 static mut SIMPLE_CONCOLIC_MUTATIONAL_ID: usize = 0;
 
-#[cfg(feature = "concolic_mutation")]
 /// The name for concolic mutation stage
 pub const SIMPLE_CONCOLIC_MUTATIONAL_NAME: &str = "concolicmutation";
 
-#[cfg(feature = "concolic_mutation")]
-impl<Z> Named for SimpleConcolicMutationalStage<Z> {
+impl<SV, Z> Named for ConcolicMutationalStage<SV, Z> {
     fn name(&self) -> &Cow<'static, str> {
         &self.name
     }
 }
 
-#[cfg(feature = "concolic_mutation")]
-impl<E, EM, Z> Stage<E, EM, Z> for SimpleConcolicMutationalStage<Z>
+impl<E, EM, SV, Z> Stage<E, EM, Z> for ConcolicMutationalStage<SV, Z>
 where
     E: UsesState<State = Self::State>,
     EM: UsesState<State = Self::State>,
+    SV: ConcolicSolver,
     Z: Evaluator<E, EM>,
     Z::Input: HasMutatorBytes,
     Self::State: State + HasExecutions + HasCorpus + HasMetadata + HasNamedMetadata,
@@ -409,20 +457,34 @@ where
             start_timer!(state);
             mark_feature_time!(state, PerfFeature::GetInputFromCorpus);
         }
-        let testcase = state.current_testcase()?.clone();
 
-        let mutations = testcase.metadata::<ConcolicMetadata>().ok().map(|meta| {
-            start_timer!(state);
-            let mutations = { generate_mutations(meta.iter_messages()) };
-            mark_feature_time!(state, PerfFeature::Mutate);
-            mutations
-        });
+        // Each entry is only solved once, check that before the timer runs
+        if state
+            .current_testcase()?
+            .has_metadata::<ConcolicSolvedMetadata>()
+        {
+            return Ok(());
+        }
+
+        start_timer!(state);
+        let mutations = match state.current_testcase()?.metadata::<ConcolicMetadata>() {
+            Ok(meta) => Some(self.solver.solve(meta)?),
+            Err(_) => None,
+        };
+        mark_feature_time!(state, PerfFeature::Mutate);
 
         if let Some(mutations) = mutations {
+            state
+                .current_testcase_mut()?
+                .add_metadata(ConcolicSolvedMetadata);
+
             for mutation in mutations {
                 let mut input_copy = state.current_input_cloned()?;
+                let len = input_copy.bytes().len();
                 for (index, new_byte) in mutation {
-                    input_copy.bytes_mut()[index] = new_byte;
+                    if index < len {
+                        input_copy.bytes_mut()[index] = new_byte;
+                    }
                 }
                 // Time is measured directly the `evaluate_input` function
                 fuzzer.evaluate_input(state, executor, manager, input_copy)?;
@@ -445,11 +507,9 @@ where
     }
 }
 
-#[cfg(feature = "concolic_mutation")]
-impl<Z> SimpleConcolicMutationalStage<Z> {
-    #[must_use]
-    /// Construct this stage
-    pub fn new() -> Self {
+impl<SV, Z> ConcolicMutationalStage<SV, Z> {
+    /// Construct this stage, using the given [`ConcolicSolver`]
+    pub fn with_solver(solver: SV) -> Self {
         // unsafe but impossible that you create two threads both instantiating this instance
         let stage_id = unsafe {
             let ret = SIMPLE_CONCOLIC_MUTATIONAL_ID;
@@ -460,7 +520,17 @@ impl<Z> SimpleConcolicMutationalStage<Z> {
             name: Cow::Owned(
                 SIMPLE_CONCOLIC_MUTATIONAL_NAME.to_owned() + ":" + stage_id.to_string().as_str(),
             ),
+            solver,
             phantom: PhantomData,
         }
     }
 }
+
+#[cfg(feature = "concolic_mutation")]
+impl<Z> ConcolicMutationalStage<Z3ConcolicSolver, Z> {
+    #[must_use]
+    /// Construct this stage, using the built-in [`Z3ConcolicSolver`]
+    pub fn new() -> Self {
+        Self::with_solver(Z3ConcolicSolver)
+    }
+}
@@ -0,0 +1,346 @@
+//! The [`DeterministicStage`] performs AFL's deterministic mutations over each corpus entry exactly once:
+//! walking bitflips, byte flips, simple arithmetics, and interesting value substitutions.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::{impl_serdeany, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inputs::HasMutatorBytes,
+    mutators::mutations::{InterestingValuesMetadata, ARITH_MAX},
+    stages::{CheckpointRestartHelper, Stage},
+    state::{HasCorpus, HasCurrentTestcase, UsesState},
+    Error, Evaluator, HasMetadata, HasNamedMetadata,
+};
+
+/// Marks a [`crate::corpus::Testcase`] that already went through the [`DeterministicStage`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DeterministicDoneMetadata;
+
+impl_serdeany!(DeterministicDoneMetadata);
+
+/// The counter for giving this stage unique id
+static mut DETERMINISTIC_STAGE_ID: usize = 0;
+/// The name for deterministic stage
+pub static DETERMINISTIC_STAGE_NAME: &str = "deterministic";
+
+/// A stage running AFL's deterministic mutations once for each corpus entry, usually placed before the havoc stage.
+///
+/// Every corpus entry is marked with a [`DeterministicDoneMetadata`] afterwards, so it will be skipped the next time.
+/// If the target crashes, the stage resumes right after the crashing mutation, using the [`CheckpointRestartHelper`].
+/// The amount of executions grows linearly with the input length, so [`DeterministicStage::with_max_len`]
+/// can be used to skip large inputs.
+/// The interesting values are taken from the [`InterestingValuesMetadata`] of the state, if there is one.
+#[derive(Clone, Debug)]
+pub struct DeterministicStage<E, EM, Z> {
+    name: Cow<'static, str>,
+    max_len: usize,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for DeterministicStage<E, EM, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Named for DeterministicStage<E, EM, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for DeterministicStage<E, EM, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: UsesState<State = Self::State>,
+    Z: Evaluator<E, EM>,
+    Self::Input: HasMutatorBytes,
+    Self::State: HasCorpus + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        if state
+            .current_testcase()?
+            .has_metadata::<DeterministicDoneMetadata>()
+        {
+            return Ok(());
+        }
+
+        let input = state.current_input_cloned()?;
+        let len = input.bytes().len();
+
+        if len > 0 && len <= self.max_len {
+            // Each mutation is a step, resume right after the one we executed last
+            let resume_after: Option<u64> = CheckpointRestartHelper::load(state, &self.name)?;
+            let name = &self.name;
+            let mut step = 0_u64;
+            let interesting = state
+                .metadata_map()
+                .get::<InterestingValuesMetadata>()
+                .cloned()
+                .unwrap_or_default();
+
+            let mut run = |state: &mut Self::State, mutate: &dyn Fn(&mut [u8]) -> bool| {
+                step += 1;
+                if resume_after.is_some_and(|resume_after| step <= resume_after) {
+                    return Ok(());
+                }
+                let mut candidate = input.clone();
+                if mutate(candidate.bytes_mut()) {
+                    CheckpointRestartHelper::store(state, name, &step)?;
+                    fuzzer.evaluate_input(state, executor, manager, candidate)?;
+                }
+                Ok::<(), Error>(())
+            };
+
+            // Walking bitflips, flipping 1, 2, and 4 consecutive bits
+            for width in [1, 2, 4] {
+                for bit in 0..=(len * 8 - width) {
+                    run(state, &|bytes| {
+                        for i in bit..bit + width {
+                            bytes[i >> 3] ^= 128 >> (i & 7);
+                        }
+                        true
+                    })?;
+                }
+            }
+
+            // Walking byte flips, flipping 1, 2, and 4 consecutive bytes
+            for width in [1, 2, 4] {
+                for pos in 0..(len + 1).saturating_sub(width) {
+                    run(state, &|bytes| {
+                        bytes[pos..pos + width].iter_mut().for_each(|b| *b ^= 0xff);
+                        true
+                    })?;
+                }
+            }
+
+            // Simple arithmetics on bytes, words, and dwords, in both endiannesses
+            for width in [1, 2, 4] {
+                for pos in 0..(len + 1).saturating_sub(width) {
+                    for delta in 1..=(ARITH_MAX as u32) {
+                        for big_endian in [false, true] {
+                            if width == 1 && big_endian {
+                                continue;
+                            }
+                            for add in [true, false] {
+                                run(state, &|bytes| {
+                                    let val = read_int(&bytes[pos..pos + width], big_endian);
+                                    let new_val = if add {
+                                        val.wrapping_add(delta)
+                                    } else {
+                                        val.wrapping_sub(delta)
+                                    };
+                                    write_int(&mut bytes[pos..pos + width], new_val, big_endian)
+                                })?;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Interesting values, in both endiannesses
+            for pos in 0..len {
+                for val in &interesting.bytes {
+                    run(state, &|bytes| {
+                        write_int(&mut bytes[pos..=pos], u32::from(*val), false)
+                    })?;
+                }
+            }
+            for (width, values) in [
+                (2, interesting.words.iter().map(|v| u32::from(*v)).collect()),
+                (4, interesting.dwords.clone()),
+            ] {
+                for pos in 0..(len + 1).saturating_sub(width) {
+                    for val in &values {
+                        for big_endian in [false, true] {
+                            run(state, &|bytes| {
+                                write_int(&mut bytes[pos..pos + width], *val, big_endian)
+                            })?;
+                        }
+                    }
+                }
+            }
+        }
+
+        state
+            .current_testcase_mut()?
+            .add_metadata(DeterministicDoneMetadata);
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // Continue after the mutation that crashed
+        CheckpointRestartHelper::should_restart(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        CheckpointRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+/// Reads an integer of `bytes.len()` bytes, at most 4
+fn read_int(bytes: &[u8], big_endian: bool) -> u32 {
+    let mut buf = [0; 4];
+    if big_endian {
+        buf[4 - bytes.len()..].copy_from_slice(bytes);
+        u32::from_be_bytes(buf)
+    } else {
+        buf[..bytes.len()].copy_from_slice(bytes);
+        u32::from_le_bytes(buf)
+    }
+}
+
+/// Writes the lower `bytes.len()` bytes of `val`, returns `false` if this didn't change the bytes
+fn write_int(bytes: &mut [u8], val: u32, big_endian: bool) -> bool {
+    let width = bytes.len();
+    let new = if big_endian {
+        val.to_be_bytes()[4 - width..].to_vec()
+    } else {
+        val.to_le_bytes()[..width].to_vec()
+    };
+    if bytes == new.as_slice() {
+        return false;
+    }
+    bytes.copy_from_slice(&new);
+    true
+}
+
+impl<E, EM, Z> DeterministicStage<E, EM, Z> {
+    /// Creates a new [`DeterministicStage`]
+    #[must_use]
+    pub fn new() -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = DETERMINISTIC_STAGE_ID;
+            DETERMINISTIC_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(
+                DETERMINISTIC_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str(),
+            ),
+            max_len: usize::MAX,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Skips the deterministic mutations for inputs longer than `max_len` bytes
+    #[must_use]
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl<E, EM, Z> Default for DeterministicStage<E, EM, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{tuples::tuple_list, AsSlice};
+
+    use crate::{
+        corpus::{Corpus, HasCurrentCorpusId, Testcase},
+        executors::ExitKind,
+        inputs::{BytesInput, HasTargetBytes},
+        stages::{
+            deterministic::{read_int, write_int, DeterministicDoneMetadata, DeterministicStage},
+            test::test_fuzzer,
+            CheckpointRestartHelper, Stage,
+        },
+        state::{HasCorpus, HasCurrentTestcase, HasExecutions, HasSolutions},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_read_write_int() {
+        let mut bytes = [0x12, 0x34];
+        assert_eq!(read_int(&bytes, false), 0x3412);
+        assert_eq!(read_int(&bytes, true), 0x1234);
+        // only the lower bytes are written, unchanged bytes are reported
+        assert!(write_int(&mut bytes, 0xff_0001, true));
+        assert_eq!(bytes, [0x00, 0x01]);
+        assert!(!write_int(&mut bytes, 0x0100, false));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_deterministic_stage() {
+        // the target "crashes" for a single mutation: flipping the last bit
+        let mut harness = |input: &BytesInput| {
+            if input.target_bytes().as_slice() == [0x00, 0x01] {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(), ());
+        let mut stage = DeterministicStage::new();
+
+        let corpus_id = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0x00, 0x00])))
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+        assert!(stage.should_restart(&mut state).unwrap());
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        stage.clear_progress(&mut state).unwrap();
+        let executions = *state.executions();
+        assert!(executions > 0);
+        assert!(state
+            .current_testcase()
+            .unwrap()
+            .has_metadata::<DeterministicDoneMetadata>());
+        assert!(state.solutions().count() > 0);
+
+        // each entry only goes through the stage once
+        assert!(stage.should_restart(&mut state).unwrap());
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        stage.clear_progress(&mut state).unwrap();
+        assert_eq!(*state.executions(), executions);
+
+        // after a crash at the 10th mutation, the stage resumes right after it
+        let corpus_id = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0x00, 0x00])))
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+        assert!(stage.should_restart(&mut state).unwrap());
+        CheckpointRestartHelper::store(&mut state, &stage.name, &10_u64).unwrap();
+        assert!(stage.should_restart(&mut state).unwrap());
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        stage.clear_progress(&mut state).unwrap();
+        assert_eq!(*state.executions(), 2 * executions - 10);
+    }
+}
@@ -0,0 +1,366 @@
+//! The [`I2SReplaceStage`] is a deterministic, Redqueen-style input-to-state replacement stage.
+//!
+//! It takes the comparison operands logged by a [`crate::observers::StdCmpValuesObserver`] for the current input,
+//! searches the input for one operand (in several encodings), and replaces it with the other one.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inputs::HasMutatorBytes,
+    observers::cmp::{CmpValues, CmpValuesMetadata},
+    stages::{colorization::TaintMetadata, CheckpointRestartHelper, Stage},
+    state::{HasCorpus, HasCurrentTestcase, HasMaxSize, UsesState},
+    Error, Evaluator, HasMetadata, HasNamedMetadata,
+};
+
+/// Transformations applied to a comparison operand before searching for it in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum I2STransform {
+    /// Use the operand as-is, in native (little) endianness
+    Native,
+    /// Use the operand with swapped endianness
+    SwapEndian,
+    /// Use the decimal ASCII representation of numeric operands
+    Ascii,
+    /// Replace with the other operand plus one, in both endiannesses; helps with `<`/`>` comparisons
+    Increment,
+    /// Replace with the other operand minus one, in both endiannesses; helps with `<`/`>` comparisons
+    Decrement,
+}
+
+/// All [`I2STransform`]s
+pub const ALL_I2S_TRANSFORMS: &[I2STransform] = &[
+    I2STransform::Native,
+    I2STransform::SwapEndian,
+    I2STransform::Ascii,
+    I2STransform::Increment,
+    I2STransform::Decrement,
+];
+
+/// The default amount of executions this stage may take per corpus entry
+pub const I2S_DEFAULT_MAX_EXECS: usize = 4096;
+
+/// The counter for giving this stage unique id
+static mut I2S_STAGE_ID: usize = 0;
+/// The name for the input-to-state stage
+pub static I2S_STAGE_NAME: &str = "i2s";
+
+/// A Redqueen-style stage replacing comparison operands found in the current input.
+///
+/// It needs a [`CmpValuesMetadata`] for the current input in the state, usually filled by a
+/// [`crate::stages::TracingStage`] with a cmplog executor right before this stage.
+/// If the current testcase carries a [`TaintMetadata`] from the [`crate::stages::ColorizationStage`],
+/// only positions inside the colorized ranges are replaced.
+#[derive(Clone, Debug)]
+pub struct I2SReplaceStage<E, EM, Z> {
+    name: Cow<'static, str>,
+    transforms: Vec<I2STransform>,
+    max_execs: usize,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for I2SReplaceStage<E, EM, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Named for I2SReplaceStage<E, EM, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for I2SReplaceStage<E, EM, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: UsesState<State = Self::State>,
+    Z: Evaluator<E, EM>,
+    Self::Input: HasMutatorBytes,
+    Self::State: HasCorpus + HasMetadata + HasNamedMetadata + HasMaxSize,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let cmps = match state.metadata_map().get::<CmpValuesMetadata>() {
+            Some(meta) if !meta.list.is_empty() => meta.list.clone(),
+            _ => return Ok(()),
+        };
+        let taint = state
+            .current_testcase()?
+            .metadata_map()
+            .get::<TaintMetadata>()
+            .cloned();
+
+        let input = state.current_input_cloned()?;
+        let max_size = state.max_size();
+
+        // Each candidate is a step, resume right after the one we executed last
+        let resume_after: Option<usize> = CheckpointRestartHelper::load(state, &self.name)?;
+        let mut execs = 0;
+        'cmps: for cmp in &cmps {
+            for (pattern, replacement) in self.replacements(cmp) {
+                if pattern.is_empty() || pattern == replacement {
+                    continue;
+                }
+                let len = input.bytes().len();
+                if pattern.len() > len {
+                    continue;
+                }
+
+                for idx in 0..=(len - pattern.len()) {
+                    if &input.bytes()[idx..idx + pattern.len()] != pattern.as_slice() {
+                        continue;
+                    }
+                    if taint.as_ref().is_some_and(|taint| !taint.is_tainted(idx)) {
+                        continue;
+                    }
+                    if len - pattern.len() + replacement.len() > max_size {
+                        continue;
+                    }
+
+                    execs += 1;
+                    if resume_after.map_or(true, |resume_after| execs > resume_after) {
+                        let mut candidate = input.clone();
+                        candidate.splice(idx..(idx + pattern.len()), replacement.iter().copied());
+                        CheckpointRestartHelper::store(state, &self.name, &execs)?;
+                        fuzzer.evaluate_input(state, executor, manager, candidate)?;
+                    }
+
+                    if execs >= self.max_execs {
+                        break 'cmps;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // Continue after the candidate that crashed
+        CheckpointRestartHelper::should_restart(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        CheckpointRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+/// Encodes `val` in `width` bytes, little or big endian
+fn encode_width(val: u64, width: usize, big_endian: bool) -> Vec<u8> {
+    if big_endian {
+        val.to_be_bytes()[8 - width..].to_vec()
+    } else {
+        val.to_le_bytes()[..width].to_vec()
+    }
+}
+
+impl<E, EM, Z> I2SReplaceStage<E, EM, Z> {
+    /// Creates a new [`I2SReplaceStage`] using all [`I2STransform`]s
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_transforms(ALL_I2S_TRANSFORMS)
+    }
+
+    /// Creates a new [`I2SReplaceStage`] only using the given [`I2STransform`]s
+    #[must_use]
+    pub fn with_transforms(transforms: &[I2STransform]) -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = I2S_STAGE_ID;
+            I2S_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(I2S_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str()),
+            transforms: transforms.to_vec(),
+            max_execs: I2S_DEFAULT_MAX_EXECS,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Limits the amount of candidates this stage executes per corpus entry
+    #[must_use]
+    pub fn with_max_execs(mut self, max_execs: usize) -> Self {
+        self.max_execs = max_execs;
+        self
+    }
+
+    /// The `(pattern, replacement)` pairs to try for a logged comparison, in both directions
+    fn replacements(&self, cmp: &CmpValues) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut ret = Vec::new();
+
+        if let CmpValues::Bytes((v0, v1)) = cmp {
+            ret.push((v0.clone(), v1.clone()));
+            ret.push((v1.clone(), v0.clone()));
+            return ret;
+        }
+
+        let width = match cmp {
+            // single bytes are too noisy to be worth it, see AFL++
+            CmpValues::U8(_) | CmpValues::Bytes(_) => return ret,
+            CmpValues::U16(_) => 2,
+            CmpValues::U32(_) => 4,
+            CmpValues::U64(_) => 8,
+        };
+        let (v0, v1) = cmp.to_u64_tuple().unwrap();
+
+        for (pattern, replacement) in [(v0, v1), (v1, v0)] {
+            for transform in &self.transforms {
+                match transform {
+                    I2STransform::Native => ret.push((
+                        encode_width(pattern, width, false),
+                        encode_width(replacement, width, false),
+                    )),
+                    I2STransform::SwapEndian => ret.push((
+                        encode_width(pattern, width, true),
+                        encode_width(replacement, width, true),
+                    )),
+                    I2STransform::Ascii => ret.push((
+                        pattern.to_string().into_bytes(),
+                        replacement.to_string().into_bytes(),
+                    )),
+                    I2STransform::Increment | I2STransform::Decrement => {
+                        let replacement = if *transform == I2STransform::Increment {
+                            replacement.wrapping_add(1)
+                        } else {
+                            replacement.wrapping_sub(1)
+                        };
+                        for big_endian in [false, true] {
+                            ret.push((
+                                encode_width(pattern, width, big_endian),
+                                encode_width(replacement, width, big_endian),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+}
+
+impl<E, EM, Z> Default for I2SReplaceStage<E, EM, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::iter;
+
+    use libafl_bolts::{tuples::tuple_list, AsSlice};
+
+    use crate::{
+        corpus::{Corpus, HasCurrentCorpusId, Testcase},
+        executors::ExitKind,
+        inputs::{BytesInput, HasTargetBytes},
+        observers::cmp::{CmpValues, CmpValuesMetadata},
+        stages::{
+            colorization::TaintMetadata,
+            i2s::{I2SReplaceStage, I2STransform},
+            test::test_fuzzer,
+            Stage,
+        },
+        state::{HasCorpus, HasSolutions},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_i2s_replacements() {
+        let stage = I2SReplaceStage::<(), (), ()>::with_transforms(&[
+            I2STransform::SwapEndian,
+            I2STransform::Ascii,
+        ]);
+        let replacements = stage.replacements(&CmpValues::U16((0x1234, 10)));
+        assert_eq!(
+            replacements,
+            [
+                (vec![0x12, 0x34], vec![0x00, 0x0a]),
+                (b"4660".to_vec(), b"10".to_vec()),
+                (vec![0x00, 0x0a], vec![0x12, 0x34]),
+                (b"10".to_vec(), b"4660".to_vec()),
+            ]
+        );
+        // single bytes are skipped, byte operands are replaced in both directions
+        assert!(stage.replacements(&CmpValues::U8((1, 2))).is_empty());
+        assert_eq!(
+            stage
+                .replacements(&CmpValues::Bytes((b"ab".to_vec(), b"cd".to_vec())))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_i2s_replace_stage() {
+        // the target "crashes" once the input contains the magic value
+        let mut harness = |input: &BytesInput| {
+            if input
+                .target_bytes()
+                .as_slice()
+                .windows(4)
+                .any(|window| window == 0xdead_beef_u32.to_le_bytes())
+            {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(), ());
+        let mut stage = I2SReplaceStage::new();
+
+        // the target compared the bytes `AAAA` of the input to the magic value
+        let mut cmps = CmpValuesMetadata::new();
+        cmps.list.push(CmpValues::U32((0x4141_4141, 0xdead_beef)));
+        state.add_metadata(cmps);
+
+        let corpus_id = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"xxAAAAxx".to_vec())))
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+        assert!(stage.should_restart(&mut state).unwrap());
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        stage.clear_progress(&mut state).unwrap();
+        assert_eq!(state.solutions().count(), 1);
+
+        // the operand is outside of the colorized ranges, so it's left alone
+        let mut testcase = Testcase::new(BytesInput::new(b"xxAAAAxx".to_vec()));
+        testcase.add_metadata(TaintMetadata::new(
+            b"xxAAAAxx".to_vec(),
+            iter::once(4..8).collect(),
+        ));
+        let corpus_id = state.corpus_mut().add(testcase).unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+        assert!(stage.should_restart(&mut state).unwrap());
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        stage.clear_progress(&mut state).unwrap();
+        assert_eq!(state.solutions().count(), 1);
+    }
+}
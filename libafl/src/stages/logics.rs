@@ -1,11 +1,15 @@
 //! Stage wrappers that add logics to stage list
 
-use core::marker::PhantomData;
+use alloc::format;
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::{current_time, rands::Rand, serdeany::SerdeAny};
 
 use crate::{
+    corpus::{Corpus, HasCurrentCorpusId},
     stages::{HasCurrentStage, HasNestedStageStatus, Stage, StageId, StagesTuple},
-    state::UsesState,
-    Error,
+    state::{HasCorpus, HasCurrentTestcase, HasRand, UsesState},
+    Error, HasMetadata,
 };
 
 /// Progress for nested stages. This merely enters/exits the inner stage's scope.
@@ -317,3 +321,224 @@ impl<E, EM, ST, Z> OptionalStage<E, EM, ST, Z> {
         }
     }
 }
+
+/// A stage wrapper running the wrapped stages only with the given probability, for each selected corpus entry.
+#[derive(Debug)]
+pub struct ProbabilityStage<E, EM, ST, Z> {
+    probability: f64,
+    stages: ST,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, ST, Z> UsesState for ProbabilityStage<E, EM, ST, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, ST, Z> Stage<E, EM, Z> for ProbabilityStage<E, EM, ST, Z>
+where
+    E: UsesState,
+    EM: UsesState<State = Self::State>,
+    ST: StagesTuple<E, EM, Self::State, Z>,
+    Z: UsesState<State = Self::State>,
+    Self::State: HasNestedStageStatus + HasRand,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        if state.current_stage_idx()?.is_some() || state.rand_mut().coinflip(self.probability) {
+            self.stages.perform_all(fuzzer, executor, state, manager)?;
+        }
+        Ok(())
+    }
+
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        NestedStageStdRestartHelper::should_restart(state, self)
+    }
+
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        NestedStageStdRestartHelper::clear_progress(state, self)
+    }
+}
+
+impl<E, EM, ST, Z> ProbabilityStage<E, EM, ST, Z> {
+    /// Constructor for this randomly enabled stage.
+    /// The wrapped stages will be executed with the given `probability`, between `0.0` and `1.0`.
+    pub fn new(probability: f64, stages: ST) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(Error::illegal_argument(format!(
+                "Probability {probability} is not in 0.0..=1.0"
+            )));
+        }
+        Ok(Self {
+            probability,
+            stages,
+            phantom: PhantomData,
+        })
+    }
+}
+
+/// A condition for the [`IfStage`], [`IfElseStage`], and [`WhileStage`] that holds
+/// if the currently selected corpus entry has metadata of type `M`.
+///
+/// Negate it, to e.g. only run an expensive stage on fresh corpus entries that don't have its results yet.
+pub fn current_testcase_has_metadata<M, E, EM, S, Z>(
+) -> impl FnMut(&mut Z, &mut E, &mut S, &mut EM) -> Result<bool, Error>
+where
+    M: SerdeAny,
+    S: HasCorpus + HasCurrentCorpusId,
+{
+    |_fuzzer, _executor, state, _manager| Ok(state.current_testcase()?.has_metadata::<M>())
+}
+
+/// A condition for the [`IfStage`], [`IfElseStage`], and [`WhileStage`] that holds
+/// if no new corpus entries were found for the given `duration`.
+pub fn corpus_stalled_for<E, EM, S, Z>(
+    duration: Duration,
+) -> impl FnMut(&mut Z, &mut E, &mut S, &mut EM) -> Result<bool, Error>
+where
+    S: HasCorpus,
+{
+    let mut last_count = None;
+    let mut last_change = current_time();
+    move |_fuzzer, _executor, state, _manager| {
+        let count = state.corpus().count();
+        let now = current_time();
+        if last_count != Some(count) {
+            last_count = Some(count);
+            last_change = now;
+        }
+        Ok(now.saturating_sub(last_change) >= duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{marker::PhantomData, time::Duration};
+
+    use libafl_bolts::{tuples::tuple_list, Error};
+
+    use crate::{
+        corpus::{Corpus, HasCurrentCorpusId, Testcase},
+        events::NopEventManager,
+        executors::test::NopExecutor,
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        stages::{
+            colorization::TaintMetadata,
+            logics::{corpus_stalled_for, current_testcase_has_metadata, ProbabilityStage},
+            Stage, StagesTuple,
+        },
+        state::{test::test_std_state, HasCorpus, HasCurrentTestcase, State, UsesState},
+        HasMetadata,
+    };
+
+    /// A stage counting how often it ran
+    #[derive(Debug)]
+    struct CountingStage<S> {
+        count: usize,
+        phantom: PhantomData<S>,
+    }
+
+    impl<S> UsesState for CountingStage<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<E, EM, Z> Stage<E, EM, Z> for CountingStage<Z::State>
+    where
+        E: UsesState<State = Z::State>,
+        EM: UsesState<State = Z::State>,
+        Z: UsesState,
+    {
+        fn perform(
+            &mut self,
+            _fuzzer: &mut Z,
+            _executor: &mut E,
+            _state: &mut Self::State,
+            _manager: &mut EM,
+        ) -> Result<(), Error> {
+            self.count += 1;
+            Ok(())
+        }
+
+        fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Runs a [`ProbabilityStage`] wrapping a [`CountingStage`] 16 times, returns how often the inner stage ran
+    fn probability_stage_runs(probability: f64) -> usize {
+        let mut state = test_std_state::<BytesInput>();
+        let mut fuzzer = NopFuzzer::new();
+        let mut executor = NopExecutor::new();
+        let mut mgr = NopEventManager::new();
+        let mut stages = tuple_list!(ProbabilityStage::new(
+            probability,
+            tuple_list!(CountingStage {
+                count: 0,
+                phantom: PhantomData
+            })
+        )
+        .unwrap());
+
+        for _ in 0..16 {
+            stages
+                .perform_all(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+                .unwrap();
+        }
+        stages.0.stages.0.count
+    }
+
+    #[test]
+    fn test_probability_stage() {
+        assert_eq!(probability_stage_runs(0.0), 0);
+        assert_eq!(probability_stage_runs(1.0), 16);
+        let runs = probability_stage_runs(0.5);
+        assert!(runs > 0 && runs < 16);
+
+        assert!(ProbabilityStage::<(), (), (), ()>::new(1.5, ()).is_err());
+    }
+
+    #[test]
+    fn test_stage_conditions() {
+        // # Safety
+        // No concurrency per testcase
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            TaintMetadata::register();
+        }
+
+        let mut state = test_std_state::<BytesInput>();
+        let corpus_id = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0])))
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+
+        let mut has_taint = current_testcase_has_metadata::<TaintMetadata, (), (), _, ()>();
+        assert!(!has_taint(&mut (), &mut (), &mut state, &mut ()).unwrap());
+        state
+            .current_testcase_mut()
+            .unwrap()
+            .add_metadata(TaintMetadata::new(vec![0], vec![]));
+        assert!(has_taint(&mut (), &mut (), &mut state, &mut ()).unwrap());
+
+        let mut stalled = corpus_stalled_for::<(), (), _, ()>(Duration::ZERO);
+        assert!(stalled(&mut (), &mut (), &mut state, &mut ()).unwrap());
+        let mut stalled = corpus_stalled_for::<(), (), _, ()>(Duration::from_secs(10));
+        assert!(!stalled(&mut (), &mut (), &mut state, &mut ()).unwrap());
+    }
+}
@@ -12,37 +12,58 @@ use alloc::{
 };
 use core::{fmt, marker::PhantomData};
 
+pub use autodict::AutoDictStage;
 pub use calibrate::CalibrationStage;
+#[cfg(feature = "std")]
+pub use checkpoint::{load_checkpoint, CheckpointStage, Checkpointer};
+#[cfg(all(feature = "cmin", unix))]
+pub use cmin::{CorpusMinimizationMetadata, CorpusMinimizationStage};
 pub use colorization::*;
 #[cfg(all(feature = "std", unix))]
-pub use concolic::ConcolicTracingStage;
+pub use concolic::{ConcolicMutationalStage, ConcolicSolver, ConcolicTracingStage};
 #[cfg(all(feature = "std", feature = "concolic_mutation", unix))]
-pub use concolic::SimpleConcolicMutationalStage;
+pub use concolic::{SimpleConcolicMutationalStage, Z3ConcolicSolver};
+pub use deterministic::{DeterministicDoneMetadata, DeterministicStage};
 #[cfg(feature = "std")]
 pub use dump::*;
 pub use generalization::GeneralizationStage;
 use hashbrown::HashSet;
+pub use i2s::{I2SReplaceStage, I2STransform};
 use libafl_bolts::{
     impl_serdeany,
-    tuples::{HasConstLen, IntoVec},
+    tuples::{short_type_name, HasConstLen, IntoVec},
     Named,
 };
 pub use logics::*;
+pub use mutation_minimization::MutationMinimizationStage;
 pub use mutational::{MutationalStage, StdMutationalStage};
+#[cfg(feature = "nautilus")]
+pub use nautilus::NautilusSpliceStage;
 pub use power::{PowerMutationalStage, StdPowerMutationalStage};
+#[cfg(feature = "std")]
+pub use replay::{ReplayMetadata, ReplayStage};
+pub use sanitizer::{SanitizerVerificationMetadata, SanitizerVerificationStage};
 use serde::{Deserialize, Serialize};
-pub use stats::AflStatsStage;
+#[cfg(feature = "std")]
+pub use state_dump::{request_state_dump, StateDumpStage};
+pub use stats::{AflStatsStage, UserStatsStage};
 #[cfg(feature = "std")]
 pub use sync::*;
 pub use tmin::{
-    MapEqualityFactory, MapEqualityFeedback, StdTMinMutationalStage, TMinMutationalStage,
+    InputMinimizer, MapEqualityFactory, MapEqualityFeedback, StdTMinMutationalStage,
+    TMinMutationalStage,
 };
+#[cfg(feature = "std")]
+pub use trace_dump::{DumpAllFilter, TraceDumpStage, TraceLog, TraceRecord};
 pub use tracing::{ShadowTracingStage, TracingStage};
+pub use trim::{TrimStage, TrimmedMetadata};
 pub use tuneable::*;
 use tuple_list::NonEmptyTuple;
 #[cfg(feature = "unicode")]
 pub use unicode::*;
 
+#[cfg(feature = "introspection")]
+use crate::state::HasClientPerfMonitor;
 use crate::{
     corpus::{CorpusId, HasCurrentCorpusId},
     events::{EventFirer, EventRestarter, HasEventManagerId, ProgressReporter},
@@ -51,7 +72,10 @@ use crate::{
     observers::ObserversTuple,
     schedulers::Scheduler,
     stages::push::PushStage,
-    state::{HasCorpus, HasExecutions, HasLastReportTime, HasRand, State, UsesState},
+    state::{
+        HasCorpus, HasExecutions, HasLastReportTime, HasRand, MaybeHasClientPerfMonitor, State,
+        UsesState,
+    },
     Error, EvaluatorObservers, ExecutesInput, ExecutionProcessor, HasMetadata, HasNamedMetadata,
     HasScheduler,
 };
@@ -61,21 +85,39 @@ pub mod mutational;
 pub mod push;
 pub mod tmin;
 
+pub mod autodict;
 pub mod calibrate;
+#[cfg(feature = "std")]
+pub mod checkpoint;
+#[cfg(all(feature = "cmin", unix))]
+pub mod cmin;
 pub mod colorization;
 #[cfg(all(feature = "std", unix))]
 pub mod concolic;
+pub mod deterministic;
 #[cfg(feature = "std")]
 pub mod dump;
 pub mod generalization;
 /// The [`generation::GenStage`] generates a single input and evaluates it.
 pub mod generation;
+pub mod i2s;
 pub mod logics;
+pub mod mutation_minimization;
+#[cfg(feature = "nautilus")]
+pub mod nautilus;
 pub mod power;
+#[cfg(feature = "std")]
+pub mod replay;
+pub mod sanitizer;
+#[cfg(feature = "std")]
+pub mod state_dump;
 pub mod stats;
 #[cfg(feature = "std")]
 pub mod sync;
+#[cfg(feature = "std")]
+pub mod trace_dump;
 pub mod tracing;
+pub mod trim;
 pub mod tuneable;
 #[cfg(feature = "unicode")]
 pub mod unicode;
@@ -112,6 +154,12 @@ where
         manager: &mut EM,
     ) -> Result<(), Error>;
 
+    /// The name of this stage in the introspection output, such as the per-stage timings of the
+    /// [`crate::monitors::ClientPerfMonitor`]. Defaults to the name of the type, without generics.
+    fn introspection_name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(short_type_name::<Self>())
+    }
+
     /// Run the stage, calling [`Stage::should_restart`] and [`Stage::clear_progress`] appropriately
     fn perform_restartable(
         &mut self,
@@ -176,7 +224,7 @@ where
     E: UsesState<State = Head::State>,
     EM: UsesState<State = Head::State>,
     Z: UsesState<State = Head::State>,
-    Head::State: HasCurrentStage,
+    Head::State: HasCurrentStage + MaybeHasClientPerfMonitor,
 {
     fn perform_all(
         &mut self,
@@ -193,6 +241,11 @@ where
                 // perform the stage, but don't set it
                 let stage = &mut self.0;
 
+                #[cfg(feature = "introspection")]
+                state
+                    .introspection_monitor_mut()
+                    .enter_stage(idx, stage.introspection_name());
+
                 stage.perform_restartable(fuzzer, executor, state, manager)?;
 
                 state.clear_stage()?;
@@ -205,6 +258,12 @@ where
                 state.set_current_stage_idx(StageId(Self::LEN))?;
 
                 let stage = &mut self.0;
+
+                #[cfg(feature = "introspection")]
+                state
+                    .introspection_monitor_mut()
+                    .enter_stage(StageId(Self::LEN), stage.introspection_name());
+
                 stage.perform_restartable(fuzzer, executor, state, manager)?;
 
                 state.clear_stage()?;
@@ -284,8 +343,20 @@ where
         state: &mut S,
         manager: &mut EM,
     ) -> Result<(), Error> {
-        self.iter_mut()
-            .try_for_each(|x| x.perform_restartable(fuzzer, executor, state, manager))
+        // Same as for tuples, the last stage has id 1
+        #[cfg(feature = "introspection")]
+        let mut id = self.len();
+        self.iter_mut().try_for_each(|x| {
+            #[cfg(feature = "introspection")]
+            {
+                state
+                    .introspection_monitor_mut()
+                    .enter_stage(StageId(id), x.introspection_name());
+                id -= 1;
+            }
+
+            x.perform_restartable(fuzzer, executor, state, manager)
+        })
     }
 }
 
@@ -678,22 +749,192 @@ impl ExecutionCountRestartHelper {
     }
 }
 
+/// `SerdeAny` metadata holding the checkpoint of a stage using the [`CheckpointRestartHelper`].
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StageCheckpointMetadata {
+    /// The corpus entry the stage is currently working on
+    corpus_id: Option<CorpusId>,
+    /// The serialized checkpoint of the stage, if it stored one yet
+    checkpoint: Option<Vec<u8>>,
+    /// If the stage was resumed, and did not store a new checkpoint since
+    resumed: bool,
+    /// The corpus entries the stage gave up on
+    skipped: HashSet<CorpusId>,
+}
+
+impl_serdeany!(StageCheckpointMetadata);
+
+/// A tool shed of functions for long, deterministic stages, that checkpoint their inner progress
+/// (for example a phase and an offset) into the state before each execution.
+///
+/// After a restart, for example because the target crashed, the stage can resume right after the
+/// checkpoint it stored last, instead of starting the same corpus entry from scratch.
+/// If the stage crashes again, without storing a newer checkpoint, the corpus entry is skipped from then on,
+/// just like with [`StdRestartHelper::no_retry`].
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointRestartHelper;
+
+impl CheckpointRestartHelper {
+    /// Initializes the checkpoint for the current corpus entry, or prepares resuming from the last one.
+    ///
+    /// Returns `true` if the stage should run
+    pub fn should_restart<S>(state: &mut S, name: &str) -> Result<bool, Error>
+    where
+        S: HasNamedMetadata + HasCurrentCorpusId,
+    {
+        let corpus_id = state.current_corpus_id()?.ok_or_else(|| {
+            Error::illegal_state(
+                "No current_corpus_id set in State, but called CheckpointRestartHelper::should_restart",
+            )
+        })?;
+
+        let metadata = state.named_metadata_or_insert_with(name, StageCheckpointMetadata::default);
+        if metadata.skipped.contains(&corpus_id) {
+            return Ok(false);
+        }
+
+        if metadata.corpus_id == Some(corpus_id) {
+            if metadata.resumed {
+                // We crashed again without making any progress, give up on this entry
+                metadata.skipped.insert(corpus_id);
+                return Ok(false);
+            }
+            metadata.resumed = true;
+        } else {
+            metadata.corpus_id = Some(corpus_id);
+            metadata.checkpoint = None;
+            metadata.resumed = false;
+        }
+        Ok(true)
+    }
+
+    /// Loads the last checkpoint stored for the current corpus entry, if any.
+    /// Stages should continue right after the work described by it.
+    pub fn load<S, T>(state: &S, name: &str) -> Result<Option<T>, Error>
+    where
+        S: HasNamedMetadata + HasCurrentCorpusId,
+        T: for<'de> Deserialize<'de>,
+    {
+        let corpus_id = state.current_corpus_id()?;
+        match state
+            .named_metadata_map()
+            .get::<StageCheckpointMetadata>(name)
+        {
+            Some(StageCheckpointMetadata {
+                corpus_id: checkpoint_id,
+                checkpoint: Some(checkpoint),
+                ..
+            }) if *checkpoint_id == corpus_id => Ok(Some(postcard::from_bytes(checkpoint)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Stores a checkpoint for the current corpus entry, describing the work the stage is about to do.
+    ///
+    /// Stages call this before every execution, so the buffer of the previous checkpoint is reused.
+    pub fn store<S, T>(state: &mut S, name: &str, checkpoint: &T) -> Result<(), Error>
+    where
+        S: HasNamedMetadata,
+        T: Serialize,
+    {
+        let metadata = state.named_metadata_mut::<StageCheckpointMetadata>(name)?;
+        let mut buf = metadata.checkpoint.take().unwrap_or_default();
+        buf.clear();
+        metadata.checkpoint = Some(postcard::to_extend(checkpoint, buf)?);
+        metadata.resumed = false;
+        Ok(())
+    }
+
+    /// Clears the checkpoint, the stage finished the current corpus entry
+    pub fn clear_progress<S>(state: &mut S, name: &str) -> Result<(), Error>
+    where
+        S: HasNamedMetadata,
+    {
+        let metadata = state.named_metadata_mut::<StageCheckpointMetadata>(name)?;
+        metadata.corpus_id = None;
+        metadata.checkpoint = None;
+        metadata.resumed = false;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use alloc::borrow::Cow;
     use core::marker::PhantomData;
 
-    use libafl_bolts::{impl_serdeany, Error, Named};
+    use libafl_bolts::{impl_serdeany, rands::StdRand, Error, Named};
     use serde::{Deserialize, Serialize};
 
     use crate::{
-        corpus::{Corpus, HasCurrentCorpusId, Testcase},
-        inputs::NopInput,
-        stages::{Stage, StdRestartHelper},
-        state::{test::test_std_state, HasCorpus, State, UsesState},
+        corpus::{Corpus, HasCurrentCorpusId, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{Executor, ExitKind, HasObservers, InProcessExecutor},
+        feedbacks::{CrashFeedback, Feedback},
+        fuzzer::StdFuzzer,
+        inputs::{Input, NopInput},
+        observers::ObserversTuple,
+        schedulers::QueueScheduler,
+        stages::{CheckpointRestartHelper, Stage, StdRestartHelper},
+        state::{test::test_std_state, HasCorpus, State, StdState, UsesState},
         HasMetadata,
     };
 
+    /// The state stages are tested against
+    pub type TestState<I> = StdState<I, InMemoryCorpus<I>, StdRand, InMemoryCorpus<I>>;
+
+    /// The fuzzer stages are tested with, keeping crashes as solutions
+    pub type TestFuzzer<I, F, OT> = StdFuzzer<QueueScheduler<TestState<I>>, F, CrashFeedback, OT>;
+
+    /// The event manager stages are tested with
+    pub type TestEventManager<I> = NopEventManager<TestState<I>>;
+
+    /// The executor running the harness stages are tested on
+    pub type TestExecutor<'a, H, OT, I> = InProcessExecutor<'a, H, OT, TestState<I>>;
+
+    /// Set up a state, fuzzer, event manager and in-process executor to run stages against `harness`.
+    ///
+    /// The fuzzer uses `feedback` to judge inputs, and keeps the ones crashing the harness as solutions.
+    #[allow(clippy::type_complexity)]
+    pub fn test_fuzzer<'a, H, I, F, OT>(
+        harness: &'a mut H,
+        observers: OT,
+        mut feedback: F,
+    ) -> (
+        TestState<I>,
+        TestFuzzer<I, F, OT>,
+        TestEventManager<I>,
+        TestExecutor<'a, H, OT, I>,
+    )
+    where
+        H: FnMut(&I) -> ExitKind,
+        I: Input,
+        F: Feedback<TestState<I>>,
+        OT: ObserversTuple<TestState<I>>,
+        TestExecutor<'a, H, OT, I>: Executor<TestEventManager<I>, TestFuzzer<I, F, OT>, State = TestState<I>>
+            + HasObservers,
+    {
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .expect("couldn't instantiate the test state");
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+        let executor =
+            InProcessExecutor::new(harness, observers, &mut fuzzer, &mut state, &mut mgr)
+                .expect("couldn't instantiate the test executor");
+        (state, fuzzer, mgr, executor)
+    }
+
     #[derive(Debug)]
     pub struct ResumeSucceededStage<S> {
         phantom: PhantomData<S>,
@@ -852,4 +1093,47 @@ pub mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_checkpoint_progress() -> Result<(), Error> {
+        // # Safety
+        // No concurrency per testcase
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            crate::stages::StageCheckpointMetadata::register();
+        }
+
+        let name = "CheckpointStage";
+        let mut state = test_std_state();
+
+        let corpus_id = state.corpus_mut().add(Testcase::new(NopInput {}))?;
+        state.set_corpus_id(corpus_id)?;
+
+        // used normally, we start from scratch every time
+        for _ in 0..10 {
+            assert!(CheckpointRestartHelper::should_restart(&mut state, name)?);
+            assert_eq!(CheckpointRestartHelper::load::<_, u64>(&state, name)?, None);
+            CheckpointRestartHelper::store(&mut state, name, &42_u64)?;
+            CheckpointRestartHelper::clear_progress(&mut state, name)?;
+        }
+
+        assert!(CheckpointRestartHelper::should_restart(&mut state, name)?);
+        CheckpointRestartHelper::store(&mut state, name, &1_u64)?;
+        // crashed, let's resume after the checkpoint
+        assert!(CheckpointRestartHelper::should_restart(&mut state, name)?);
+        assert_eq!(CheckpointRestartHelper::load(&state, name)?, Some(1_u64));
+        CheckpointRestartHelper::store(&mut state, name, &2_u64)?;
+        // crashed again, but we made progress
+        assert!(CheckpointRestartHelper::should_restart(&mut state, name)?);
+        assert_eq!(CheckpointRestartHelper::load(&state, name)?, Some(2_u64));
+        // crashed without progress, skip this testcase
+        assert!(!CheckpointRestartHelper::should_restart(&mut state, name)?);
+        CheckpointRestartHelper::clear_progress(&mut state, name)?;
+
+        // we gave up on this testcase before, so we skip
+        assert!(!CheckpointRestartHelper::should_restart(&mut state, name)?);
+        CheckpointRestartHelper::clear_progress(&mut state, name)?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,252 @@
+//! The [`MutationMinimizationStage`] reverts the mutations that did not matter for a new corpus entry.
+//!
+//! Heavily stacked havoc produces entries where one or two of the stacked mutations found the new coverage,
+//! and all others are noise. The stage replays the [`MutationJournalMetadata`] recorded by the
+//! [`crate::mutators::JournalScheduledMutator`] on the parent, leaving out one mutation at a time,
+//! and keeps every reverted mutation that the feedback created by the given [`FeedbackFactory`] still accepts.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::{current_time, Named};
+
+#[cfg(feature = "unicode")]
+use crate::stages::UnicodeIdentificationMetadata;
+use crate::{
+    corpus::{Corpus, HasCurrentCorpusId, Testcase},
+    events::EventFirer,
+    executors::{ExitKind, HasObservers},
+    feedbacks::{Feedback, FeedbackFactory},
+    inputs::{GeneralizedInputMetadata, HasMutatorBytes, Input},
+    mutators::{MutationJournalMetadata, MutationMaskMetadata},
+    schedulers::RemovableScheduler,
+    stages::{Stage, StdRestartHelper, TaintMetadata},
+    state::{HasCorpus, HasCurrentTestcase, HasExecutions, UsesState},
+    Error, ExecutesInput, HasMetadata, HasNamedMetadata, HasScheduler,
+};
+
+/// The counter for giving this stage unique id
+static mut MUTATION_MINIMIZATION_STAGE_ID: usize = 0;
+/// The name for mutation minimization stage
+pub static MUTATION_MINIMIZATION_STAGE_NAME: &str = "mutation_minimization";
+
+/// A stage that minimizes the set of mutations that turned the parent into the current corpus entry,
+/// see the [module documentation](self).
+///
+/// Entries without a [`MutationJournalMetadata`], or whose parent changed since, are left alone.
+/// The journal is removed before the first execution, so each entry is minimized at most once.
+/// The factory usually is a [`crate::stages::MapEqualityFactory`], so the minimized input keeps the exact same coverage.
+/// The minimized input keeps the metadata of the entry, except for the entries referring to positions in the previous input.
+#[derive(Clone, Debug)]
+pub struct MutationMinimizationStage<E, EM, F, FF, Z> {
+    name: Cow<'static, str>,
+    factory: FF,
+    phantom: PhantomData<(E, EM, F, Z)>,
+}
+
+impl<E, EM, F, FF, Z> UsesState for MutationMinimizationStage<E, EM, F, FF, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, F, FF, Z> Named for MutationMinimizationStage<E, EM, F, FF, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, F, FF, Z> Stage<E, EM, Z> for MutationMinimizationStage<E, EM, F, FF, Z>
+where
+    E: HasObservers<State = Self::State>,
+    EM: EventFirer<State = Self::State>,
+    F: Feedback<Self::State>,
+    FF: FeedbackFactory<F, E::Observers>,
+    Z: ExecutesInput<E, EM> + HasScheduler,
+    Z::Scheduler: RemovableScheduler,
+    Self::Input: HasMutatorBytes + Clone,
+    Self::State: HasCorpus + HasExecutions + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(corpus_id) = state.current_corpus_id()? else {
+            return Err(Error::illegal_state(
+                "state is not currently processing a corpus index",
+            ));
+        };
+
+        let Some(journal) = state
+            .current_testcase_mut()?
+            .metadata_map_mut()
+            .remove::<MutationJournalMetadata>()
+        else {
+            return Ok(());
+        };
+        if journal.edits.len() < 2 {
+            return Ok(());
+        }
+
+        let orig = state.current_input_cloned()?;
+        let parent = {
+            let Ok(parent) = state.corpus().get_from_all(journal.parent) else {
+                // The parent was removed from the corpus, we can't replay the mutations
+                return Ok(());
+            };
+            let mut parent = parent.borrow_mut();
+            parent.load_input(state.corpus())?.clone()
+        };
+
+        let mut keep = vec![true; journal.edits.len()];
+        if journal.replay(parent.bytes(), &keep).as_deref() != Some(orig.bytes()) {
+            // The parent was replaced since, for example by trimming
+            return Ok(());
+        }
+
+        // Run the input once, so the feedback knows what to compare against
+        fuzzer.execute_input(state, executor, manager, &orig)?;
+        let mut feedback = self.factory.create_feedback(&*executor.observers());
+
+        let mut candidate = orig.clone();
+        let mut minimized = None;
+        for idx in 0..keep.len() {
+            keep[idx] = false;
+            let Some(bytes) = journal.replay(parent.bytes(), &keep) else {
+                // A later mutation builds on this one
+                keep[idx] = true;
+                continue;
+            };
+            candidate.resize(bytes.len(), 0);
+            candidate.bytes_mut().copy_from_slice(&bytes);
+
+            let exit_kind = fuzzer.execute_input(state, executor, manager, &candidate)?;
+            let observers = executor.observers();
+            if exit_kind == ExitKind::Ok
+                && feedback.is_interesting(state, manager, &candidate, &*observers, &exit_kind)?
+            {
+                // The mutation didn't matter, keep it reverted
+                minimized = Some(bytes);
+            } else {
+                keep[idx] = true;
+            }
+        }
+
+        let Some(bytes) = minimized else {
+            return Ok(());
+        };
+        candidate.resize(bytes.len(), 0);
+        candidate.bytes_mut().copy_from_slice(&bytes);
+
+        // Run the final input once more, to get its exec time
+        let start = current_time();
+        fuzzer.execute_input(state, executor, manager, &candidate)?;
+        let exec_time = current_time() - start;
+
+        let mut testcase = reduced_testcase(&mut *state.current_testcase_mut()?, candidate);
+        testcase.set_exec_time(exec_time);
+
+        let prev = state.corpus_mut().replace(corpus_id, testcase)?;
+        fuzzer.scheduler_mut().on_replace(state, corpus_id, &prev)?;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // The journal is already gone if we crashed while minimizing this entry
+        StdRestartHelper::no_retry(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        StdRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<E, EM, F, FF, Z> MutationMinimizationStage<E, EM, F, FF, Z> {
+    /// Creates a new [`MutationMinimizationStage`], keeping only reverts the feedback created by the `factory` considers interesting
+    #[must_use]
+    pub fn new(factory: FF) -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = MUTATION_MINIMIZATION_STAGE_ID;
+            MUTATION_MINIMIZATION_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(
+                MUTATION_MINIMIZATION_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str(),
+            ),
+            factory,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Creates the testcase replacing `prev` after a stage reduced its input to `input`, without changing its behavior,
+/// like the [`crate::stages::TrimStage`] and the [`MutationMinimizationStage`] do.
+///
+/// The coverage did not change, so the metadata about the coverage and the scheduling of the entry is moved over.
+/// Metadata referring to positions in the previous input is dropped instead, so the stages computing it
+/// run again on the reduced input: the taint of the [`crate::stages::ColorizationStage`], the generalized input of the
+/// [`crate::stages::GeneralizationStage`], the unicode spans, the protected ranges and the mutation journal.
+pub(crate) fn reduced_testcase<I>(prev: &mut Testcase<I>, input: I) -> Testcase<I>
+where
+    I: Input,
+{
+    let mut testcase = Testcase::with_executions(input, *prev.executions());
+    testcase.set_parent_id_optional(prev.parent_id());
+    testcase.set_scheduled_count(prev.scheduled_count());
+
+    let metadata = testcase.metadata_map_mut();
+    *metadata = core::mem::take(prev.metadata_map_mut());
+    drop(metadata.remove::<TaintMetadata>());
+    drop(metadata.remove::<GeneralizedInputMetadata>());
+    drop(metadata.remove::<MutationMaskMetadata>());
+    drop(metadata.remove::<MutationJournalMetadata>());
+    #[cfg(feature = "unicode")]
+    drop(metadata.remove::<UnicodeIdentificationMetadata>());
+    testcase
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::Testcase,
+        feedbacks::MapIndexesMetadata,
+        inputs::BytesInput,
+        stages::{mutation_minimization::reduced_testcase, TaintMetadata},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_reduced_testcase() {
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            MapIndexesMetadata::register();
+            TaintMetadata::register();
+        }
+
+        let mut prev = Testcase::new(BytesInput::new(vec![1, 2, 3, 4]));
+        prev.set_scheduled_count(3);
+        prev.add_metadata(MapIndexesMetadata::new(vec![1, 7]));
+        prev.add_metadata(TaintMetadata::new(vec![1, 2, 3, 4], vec![1..2, 3..4]));
+
+        let testcase = reduced_testcase(&mut prev, BytesInput::new(vec![1, 2]));
+        assert_eq!(testcase.scheduled_count(), 3);
+        // the coverage is still valid, the taint ranges refer to the removed bytes
+        assert_eq!(
+            testcase.metadata::<MapIndexesMetadata>().unwrap().list,
+            vec![1, 7]
+        );
+        assert!(!testcase.has_metadata::<TaintMetadata>());
+    }
+}
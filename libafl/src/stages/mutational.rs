@@ -15,6 +15,7 @@ use crate::{
     inputs::Input,
     mark_feature_time,
     mutators::{MultiMutator, MutationResult, Mutator},
+    schedulers::{testcase_score::CorpusPowerTestcaseScore, TestcaseScore},
     stages::{Stage, StdRestartHelper},
     start_timer,
     state::{HasCorpus, HasCurrentTestcase, HasExecutions, HasRand, UsesState},
@@ -161,6 +162,8 @@ pub struct StdMutationalStage<E, EM, I, M, Z> {
     mutator: M,
     /// The maximum amount of iterations we should do each round
     max_iterations: usize,
+    /// Take the amount of iterations from the power schedule
+    power_schedule: bool,
     #[allow(clippy::type_complexity)]
     phantom: PhantomData<(E, EM, I, Z)>,
 }
@@ -186,8 +189,20 @@ where
         &mut self.mutator
     }
 
-    /// Gets the number of iterations as a random number
+    /// Gets the number of iterations as a random number,
+    /// or from the power schedule, if enabled and the current testcase was calibrated.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     fn iterations(&self, state: &mut Self::State) -> Result<usize, Error> {
+        if self.power_schedule {
+            let energy = {
+                let mut testcase = state.current_testcase_mut()?;
+                // Entries that were not calibrated yet can't be scored
+                CorpusPowerTestcaseScore::compute(state, &mut testcase).ok()
+            };
+            if let Some(energy) = energy {
+                return Ok((energy as usize).max(1));
+            }
+        }
         Ok(1 + state.rand_mut().below(self.max_iterations))
     }
 }
@@ -228,12 +243,7 @@ where
         state: &mut Self::State,
         manager: &mut EM,
     ) -> Result<(), Error> {
-        let ret = self.perform_mutational(fuzzer, executor, state, manager);
-
-        #[cfg(feature = "introspection")]
-        state.introspection_monitor_mut().finish_stage();
-
-        ret
+        self.perform_mutational(fuzzer, executor, state, manager)
     }
 
     fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
@@ -291,9 +301,20 @@ where
             ),
             mutator,
             max_iterations,
+            power_schedule: false,
             phantom: PhantomData,
         }
     }
+
+    /// Perform as many mutations as the power schedule of the scheduler demands,
+    /// computed from its [`crate::schedulers::SchedulerMetadata`] like in the
+    /// [`crate::stages::PowerMutationalStage`].
+    /// Testcases that were not calibrated yet still get a random amount of mutations.
+    #[must_use]
+    pub fn with_power_schedule(mut self) -> Self {
+        self.power_schedule = true;
+        self
+    }
 }
 
 /// A mutational stage that operates on multiple inputs, as returned by [`MultiMutator::multi_mutate`].
@@ -402,3 +423,68 @@ impl<E, EM, I, M, Z> MultiMutationalStage<E, EM, I, M, Z> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::{Corpus, HasCurrentCorpusId, SchedulerTestcaseMetadata, Testcase},
+        executors::ExitKind,
+        inputs::BytesInput,
+        mutators::{MutationResult, NopMutator},
+        schedulers::{testcase_score::CorpusPowerTestcaseScore, SchedulerMetadata, TestcaseScore},
+        stages::{test::test_fuzzer, Stage, StdMutationalStage},
+        state::{HasCorpus, HasCurrentTestcase, HasExecutions},
+        HasMetadata,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn test_power_schedule_opt_in() {
+        let mut harness = |_input: &BytesInput| ExitKind::Ok;
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(), ());
+
+        let mut testcase = Testcase::new(BytesInput::new(vec![0; 4]));
+        testcase.add_metadata(SchedulerTestcaseMetadata::new(0));
+        let id = state.corpus_mut().add(testcase).unwrap();
+        state.set_corpus_id(id).unwrap();
+        state.add_metadata(SchedulerMetadata::new(None));
+
+        let mut stage =
+            StdMutationalStage::with_max_iterations(NopMutator::new(MutationResult::Mutated), 1);
+        let mut power_stage =
+            StdMutationalStage::with_max_iterations(NopMutator::new(MutationResult::Mutated), 1)
+                .with_power_schedule();
+
+        let mut executions = |stage: &mut StdMutationalStage<_, _, _, _, _>, state: &mut _| {
+            let before = *HasExecutions::executions(state);
+            stage
+                .perform(&mut fuzzer, &mut executor, state, &mut mgr)
+                .unwrap();
+            *HasExecutions::executions(state) - before
+        };
+
+        // Not calibrated yet, both stages choose a random amount of iterations
+        assert_eq!(executions(&mut stage, &mut state), 1);
+        assert_eq!(executions(&mut power_stage, &mut state), 1);
+
+        state
+            .current_testcase_mut()
+            .unwrap()
+            .set_exec_time(Duration::from_millis(1));
+        let energy = {
+            let mut testcase = state.current_testcase_mut().unwrap();
+            CorpusPowerTestcaseScore::compute(&state, &mut testcase).unwrap() as u64
+        };
+        assert!(energy > 1);
+
+        // Only the stage that opted in follows the power schedule
+        assert_eq!(executions(&mut stage, &mut state), 1);
+        assert_eq!(executions(&mut power_stage, &mut state), energy);
+    }
+}
@@ -0,0 +1,271 @@
+//! The [`NautilusSpliceStage`] exchanges subtrees of matching nonterminals between the current
+//! [`NautilusInput`] and other corpus entries.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    common::nautilus::grammartec::{
+        context::Context,
+        newtypes::NodeId,
+        tree::{Tree, TreeLike},
+    },
+    corpus::Corpus,
+    generators::nautilus::NautilusContext,
+    inputs::{nautilus::NautilusInput, UsesInput},
+    random_corpus_id,
+    stages::{Stage, StdRestartHelper},
+    state::{HasCorpus, HasCurrentTestcase, HasRand, UsesState},
+    Error, Evaluator, HasNamedMetadata,
+};
+
+/// The default amount of donors the [`NautilusSpliceStage`] splices with, per corpus entry
+pub const NAUTILUS_SPLICE_DEFAULT_DONORS: usize = 16;
+/// The default maximum amount of nodes of a spliced tree
+pub const NAUTILUS_SPLICE_DEFAULT_MAX_TREE_SIZE: usize = 1000;
+
+/// The counter for giving this stage unique id
+static mut NAUTILUS_SPLICE_STAGE_ID: usize = 0;
+/// The name for the nautilus splice stage
+pub static NAUTILUS_SPLICE_STAGE_NAME: &str = "nautilus_splice";
+
+/// A stage splicing the current [`NautilusInput`] with other corpus entries, the donors.
+///
+/// For each donor, a random node of the current tree is picked, and replaced by a random subtree of the donor
+/// that derives from the same nonterminal. Then, the other way around, the picked subtree of the current tree
+/// replaces the matching subtree in the donor. Splices resulting in more than `max_tree_size` nodes are skipped.
+///
+/// Unlike the [`crate::mutators::NautilusSpliceMutator`], which splices with the chunks collected by the
+/// [`crate::feedbacks::NautilusFeedback`], the donors are whole corpus entries, so this stage needs no metadata.
+#[derive(Debug)]
+pub struct NautilusSpliceStage<'a, E, EM, Z> {
+    name: Cow<'static, str>,
+    context: &'a NautilusContext,
+    donors: usize,
+    max_tree_size: usize,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for NautilusSpliceStage<'_, E, EM, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, Z> Named for NautilusSpliceStage<'_, E, EM, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for NautilusSpliceStage<'_, E, EM, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: UsesState<State = Self::State>,
+    Z: Evaluator<E, EM>,
+    Self::State: HasCorpus + HasRand + HasNamedMetadata + UsesInput<Input = NautilusInput>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let input = state.current_input_cloned()?;
+        if input.tree.size() == 0 {
+            return Ok(());
+        }
+        let ctx = &self.context.ctx;
+
+        for _ in 0..self.donors {
+            let donor_id = random_corpus_id!(state.corpus(), state.rand_mut());
+            let donor = state.corpus().cloned_input_for_id(donor_id)?;
+            if donor.tree.size() == 0 {
+                continue;
+            }
+
+            let node = NodeId::from(state.rand_mut().below(input.tree.size()));
+            let nonterm = input.tree.get_nonterm_id(node, ctx);
+            let candidates = (0..donor.tree.size())
+                .map(NodeId::from)
+                .filter(|&n| donor.tree.get_nonterm_id(n, ctx) == nonterm)
+                .collect::<Vec<_>>();
+            let Some(&donor_node) = state.rand_mut().choose(&candidates) else {
+                continue;
+            };
+
+            let spliced = [
+                Self::splice(
+                    ctx,
+                    &input.tree,
+                    node,
+                    &donor.tree,
+                    donor_node,
+                    self.max_tree_size,
+                ),
+                Self::splice(
+                    ctx,
+                    &donor.tree,
+                    donor_node,
+                    &input.tree,
+                    node,
+                    self.max_tree_size,
+                ),
+            ];
+            for tree in spliced.into_iter().flatten() {
+                fuzzer.evaluate_input(state, executor, manager, NautilusInput::new(tree))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // Make sure we don't get stuck crashing on a single testcase
+        StdRestartHelper::should_restart(state, &self.name, 3)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        StdRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<'a, E, EM, Z> NautilusSpliceStage<'a, E, EM, Z> {
+    /// Creates a new [`NautilusSpliceStage`] for trees of the given grammar
+    #[must_use]
+    pub fn new(context: &'a NautilusContext) -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = NAUTILUS_SPLICE_STAGE_ID;
+            NAUTILUS_SPLICE_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(
+                NAUTILUS_SPLICE_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str(),
+            ),
+            context,
+            donors: NAUTILUS_SPLICE_DEFAULT_DONORS,
+            max_tree_size: NAUTILUS_SPLICE_DEFAULT_MAX_TREE_SIZE,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the amount of donors to splice with, per corpus entry
+    #[must_use]
+    pub fn with_donors(mut self, donors: usize) -> Self {
+        self.donors = donors;
+        self
+    }
+
+    /// Skips splices resulting in trees of more than `max_tree_size` nodes
+    #[must_use]
+    pub fn with_max_tree_size(mut self, max_tree_size: usize) -> Self {
+        self.max_tree_size = max_tree_size;
+        self
+    }
+
+    /// Replaces the subtree at `node` of `tree` with the subtree at `other_node` of `other`,
+    /// returns `None` if the result would be too big
+    fn splice(
+        ctx: &Context,
+        tree: &Tree,
+        node: NodeId,
+        other: &Tree,
+        other_node: NodeId,
+        max_tree_size: usize,
+    ) -> Option<Tree> {
+        let new_size = tree.size() - tree.subtree_size(node) + other.subtree_size(other_node);
+        (new_size <= max_tree_size).then(|| {
+            tree.mutate_replace_from_tree(node, other, other_node)
+                .to_tree(ctx)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::{Corpus, HasCurrentCorpusId, Testcase},
+        executors::ExitKind,
+        generators::NautilusContext,
+        inputs::NautilusInput,
+        stages::{test::test_fuzzer, NautilusSpliceStage, Stage},
+        state::HasCorpus,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_nautilus_splice_stage() {
+        let context = NautilusContext::new(
+            15,
+            &[
+                vec!["EXPR".into(), "{EXPR}+{EXPR}".into()],
+                vec!["EXPR".into(), "({EXPR})".into()],
+                vec!["EXPR".into(), "{NUM}".into()],
+                vec!["NUM".into(), "1".into()],
+                vec!["NUM".into(), "42".into()],
+            ],
+        );
+        let executed = RefCell::new(Vec::new());
+        let mut harness = |input: &NautilusInput| {
+            let mut bytes = vec![];
+            input.unparse(&context, &mut bytes);
+            executed.borrow_mut().push(bytes);
+            ExitKind::Ok
+        };
+
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(), ());
+
+        let corpus_id = state
+            .corpus_mut()
+            .add(Testcase::new(
+                NautilusInput::parse(&context, b"1+1").unwrap(),
+            ))
+            .unwrap();
+        state
+            .corpus_mut()
+            .add(Testcase::new(
+                NautilusInput::parse(&context, b"(42)").unwrap(),
+            ))
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+
+        // no splice results in a single node
+        let mut stage = NautilusSpliceStage::new(&context).with_max_tree_size(1);
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        assert!(executed.borrow().is_empty());
+
+        let mut stage = NautilusSpliceStage::new(&context);
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        let executed = executed.borrow();
+        assert!(!executed.is_empty());
+        // the splices are derived by the grammar, and mix both entries
+        for bytes in executed.iter() {
+            NautilusInput::parse(&context, bytes).unwrap();
+        }
+        assert!(executed
+            .iter()
+            .any(|bytes| bytes.as_slice() == b"(42)+1" || bytes.as_slice() == b"1+(42)"));
+    }
+}
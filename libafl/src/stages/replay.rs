@@ -0,0 +1,343 @@
+//! The [`ReplayStage`] periodically re-executes a pinned set of known inputs from disk,
+//! reporting those that no longer behave the way they used to, for example after the target got rebuilt.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    format,
+    string::String,
+};
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    vec::Vec,
+};
+
+use hashbrown::HashMap;
+use libafl_bolts::{current_time, tuples::Handle, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::{Event, EventFirer, LogSeverity},
+    executors::{ExitKind, HasObservers},
+    inputs::UsesInput,
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
+    observers::{MapObserver, ObserversTuple},
+    stages::{Stage, StdRestartHelper},
+    state::{HasCorpus, HasExecutions, UsesState},
+    Error, ExecutesInput, HasMetadata, HasNamedMetadata,
+};
+
+/// The default interval between two replays of the pinned corpus
+pub const REPLAY_DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default name for [`ReplayStage`]
+pub const REPLAY_STAGE_NAME: &str = "replay";
+
+/// Metadata keeping track of the replays of the pinned corpus
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayMetadata {
+    /// The last time the pinned corpus was replayed
+    pub last_time: Duration,
+    /// The coverage map hash of each known-coverage input, recorded on its first replay
+    pub baselines: HashMap<PathBuf, u64>,
+    /// The inputs that did not reproduce in the last replay
+    pub regressions: Vec<PathBuf>,
+}
+
+libafl_bolts::impl_serdeany!(ReplayMetadata);
+
+/// A stage that re-executes a pinned corpus of known-crash and known-coverage inputs every `interval`,
+/// and reports every input that no longer reproduces, both as a [`LogSeverity::Warn`] log and as `replay_regressions` user stats.
+///
+/// * Known-crash inputs are expected to still exit with [`ExitKind::Crash`] (or [`ExitKind::Timeout`]).
+///   Replay them only with executors that survive crashes of the target, like the forkserver or a fork executor.
+/// * Known-coverage inputs are expected to still exit with [`ExitKind::Ok`], and to produce the same coverage map
+///   as the first time they were replayed. Unstable targets will report false positives, here.
+///
+/// The replayed inputs are never added to the corpus or the objectives.
+#[derive(Debug)]
+pub struct ReplayStage<C, CB, E, EM, O, Z> {
+    name: Cow<'static, str>,
+    crash_dirs: Vec<PathBuf>,
+    coverage_dirs: Vec<PathBuf>,
+    map_observer_handle: Handle<C>,
+    load_callback: CB,
+    interval: Duration,
+    phantom: PhantomData<(E, EM, O, Z)>,
+}
+
+impl<C, CB, E, EM, O, Z> UsesState for ReplayStage<C, CB, E, EM, O, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<C, CB, E, EM, O, Z> Named for ReplayStage<C, CB, E, EM, O, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<C, CB, E, EM, O, Z> Stage<E, EM, Z> for ReplayStage<C, CB, E, EM, O, Z>
+where
+    C: AsRef<O>,
+    CB: FnMut(&mut Z, &mut Self::State, &Path) -> Result<<Self::State as UsesInput>::Input, Error>,
+    E: HasObservers,
+    E::Observers: ObserversTuple<Self::State>,
+    EM: EventFirer<State = Self::State>,
+    O: MapObserver,
+    Z: ExecutesInput<E, EM, State = Self::State>,
+    Self::State: HasCorpus + HasExecutions + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        // The metadata is updated in place, so the baselines survive errors and restarts during the replay
+        let metadata = state.metadata_or_insert_with(ReplayMetadata::default);
+        if metadata.last_time != Duration::ZERO
+            && now.checked_sub(metadata.last_time).unwrap_or_default() < self.interval
+        {
+            return Ok(());
+        }
+        metadata.last_time = now;
+
+        let mut regressions = Vec::new();
+        let mut total = 0;
+
+        for path in Self::list_files(&self.crash_dirs)? {
+            let input = (self.load_callback)(fuzzer, state, &path)?;
+            let exit_kind = fuzzer.execute_input(state, executor, manager, &input)?;
+            total += 1;
+
+            if !matches!(exit_kind, ExitKind::Crash | ExitKind::Timeout) {
+                regressions.push((path, format!("known crash exited with {exit_kind:?}")));
+            }
+        }
+
+        for path in Self::list_files(&self.coverage_dirs)? {
+            let input = (self.load_callback)(fuzzer, state, &path)?;
+            let exit_kind = fuzzer.execute_input(state, executor, manager, &input)?;
+            total += 1;
+
+            if exit_kind != ExitKind::Ok {
+                regressions.push((path, format!("known input exited with {exit_kind:?}")));
+                continue;
+            }
+
+            let hash = executor.observers()[&self.map_observer_handle]
+                .as_ref()
+                .hash_simple();
+            let baselines = &mut state.metadata_mut::<ReplayMetadata>()?.baselines;
+            match baselines.get(&path) {
+                Some(baseline) if *baseline != hash => {
+                    regressions.push((path, String::from("known input changed its coverage")));
+                }
+                Some(_) => (),
+                None => {
+                    baselines.insert(path, hash);
+                }
+            }
+        }
+
+        for (path, reason) in &regressions {
+            manager.log(
+                state,
+                LogSeverity::Warn,
+                format!("Replay of {} did not reproduce: {reason}", path.display()),
+            )?;
+        }
+        manager.fire(
+            state,
+            Event::UpdateUserStats {
+                name: Cow::from("replay_regressions"),
+                value: UserStats::new(
+                    UserStatsValue::Ratio(regressions.len() as u64, total),
+                    AggregatorOps::Sum,
+                ),
+                phantom: PhantomData,
+            },
+        )?;
+
+        state.metadata_mut::<ReplayMetadata>()?.regressions =
+            regressions.into_iter().map(|(path, _)| path).collect();
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // A pinned input crashed the fuzzer, we don't want to crash again for the same input
+        StdRestartHelper::no_retry(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        StdRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<C, CB, E, EM, O, Z> ReplayStage<C, CB, E, EM, O, Z> {
+    /// Creates a new [`ReplayStage`], replaying the known-crash inputs in `crash_dirs` and the known-coverage inputs in `coverage_dirs`.
+    ///
+    /// The coverage of known-coverage inputs is compared using the map observer behind `map_observer_handle`,
+    /// files are turned into inputs by the `load_callback`, just like for the [`crate::stages::SyncFromDiskStage`].
+    #[must_use]
+    pub fn new(
+        crash_dirs: Vec<PathBuf>,
+        coverage_dirs: Vec<PathBuf>,
+        map_observer_handle: Handle<C>,
+        load_callback: CB,
+    ) -> Self {
+        let name = REPLAY_STAGE_NAME.to_owned() + ":" + map_observer_handle.name();
+        Self {
+            name: Cow::Owned(name),
+            crash_dirs,
+            coverage_dirs,
+            map_observer_handle,
+            load_callback,
+            interval: REPLAY_DEFAULT_INTERVAL,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, CB, E, EM, O, Z> ReplayStage<C, CB, E, EM, O, Z> {
+    /// Sets the interval between two replays of the pinned corpus
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Lists all non-empty files in the given directories, in a stable order
+    fn list_files(dirs: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        for dir in dirs {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if let Ok(attr) = fs::metadata(&path) {
+                    if attr.is_file() && attr.len() > 0 {
+                        files.push(path);
+                    }
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{cell::Cell, ptr::addr_of_mut, time::Duration};
+    use std::{env, fs, path::Path, process, vec::Vec};
+
+    use libafl_bolts::{
+        tuples::{tuple_list, Handled},
+        AsSlice, Error,
+    };
+
+    use crate::{
+        executors::ExitKind,
+        inputs::{BytesInput, HasTargetBytes},
+        observers::StdMapObserver,
+        stages::{replay::ReplayMetadata, test::test_fuzzer, ReplayStage, Stage},
+        HasMetadata,
+    };
+
+    static mut MAP: [u8; 16] = [0; 16];
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_replay_stage() {
+        // # Safety
+        // No concurrency per testcase
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            ReplayMetadata::register();
+        }
+
+        let dir = env::temp_dir().join(format!("libafl_replay_{}", process::id()));
+        let crash_dir = dir.join("crashes");
+        let coverage_dir = dir.join("coverage");
+        fs::create_dir_all(&crash_dir).unwrap();
+        fs::create_dir_all(&coverage_dir).unwrap();
+        fs::write(coverage_dir.join("a"), b"a").unwrap();
+        fs::write(coverage_dir.join("b"), b"b").unwrap();
+
+        // the coverage of `a` changes once the target got "rebuilt"
+        let rebuilt = Cell::new(false);
+        let mut harness = |input: &BytesInput| {
+            let map = unsafe { &mut *addr_of_mut!(MAP) };
+            map[0] = 1;
+            if rebuilt.get() && input.target_bytes().as_slice() == b"a" {
+                map[1] = 1;
+            }
+            ExitKind::Ok
+        };
+        let edges = unsafe {
+            StdMapObserver::from_mut_slice("edges", (*addr_of_mut!(MAP)).as_mut_slice().into())
+        };
+        let handle = edges.handle();
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(edges), ());
+
+        let fail_on_b = Cell::new(true);
+        let mut stage = ReplayStage::new(
+            vec![crash_dir],
+            vec![coverage_dir],
+            handle,
+            |_fuzzer: &mut _, _state: &mut _, path: &Path| {
+                let bytes = fs::read(path)?;
+                if fail_on_b.get() && bytes == b"b" {
+                    return Err(Error::illegal_state("unreadable input"));
+                }
+                Ok(BytesInput::new(bytes))
+            },
+        )
+        .with_interval(Duration::ZERO);
+
+        // the baselines recorded before the error are kept
+        assert!(stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .is_err());
+        assert_eq!(
+            state.metadata::<ReplayMetadata>().unwrap().baselines.len(),
+            1
+        );
+
+        fail_on_b.set(false);
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        let metadata = state.metadata::<ReplayMetadata>().unwrap();
+        assert_eq!(metadata.baselines.len(), 2);
+        assert!(metadata.regressions.is_empty());
+
+        rebuilt.set(true);
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        let regressions: Vec<_> = state
+            .metadata::<ReplayMetadata>()
+            .unwrap()
+            .regressions
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(regressions, ["a"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
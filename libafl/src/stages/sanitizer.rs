@@ -0,0 +1,290 @@
+//! The [`SanitizerVerificationStage`] periodically re-executes new corpus entries with a second,
+//! sanitizer-instrumented executor, so the main fuzzing loop can run on a faster, uninstrumented build.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    format,
+};
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::{current_time, impl_serdeany, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, Testcase},
+    events::{Event, EventFirer},
+    executors::{Executor, HasObservers},
+    feedbacks::Feedback,
+    observers::ObserversTuple,
+    stages::{Stage, StdRestartHelper},
+    state::{HasCorpus, HasExecutions, HasRand, HasSolutions, UsesState},
+    Error, HasMetadata, HasNamedMetadata, HasObjective,
+};
+
+/// The default interval between two verification runs
+pub const SANITIZER_DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default name for [`SanitizerVerificationStage`]
+pub const SANITIZER_VERIFICATION_STAGE_NAME: &str = "sanitizer";
+
+/// Metadata keeping track of the corpus entries already verified with the sanitizer executor
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SanitizerVerificationMetadata {
+    /// The last time new corpus entries were verified
+    pub last_time: Duration,
+    /// The last corpus entry that was considered for verification
+    pub last_id: Option<CorpusId>,
+}
+
+impl_serdeany!(SanitizerVerificationMetadata);
+
+/// A stage re-executing a sample of the corpus entries added since its last run with a second,
+/// sanitizer-instrumented executor, every `interval`.
+///
+/// Each sanitizer run is judged by the fuzzer's objective feedback, using the observers of the sanitizer executor;
+/// new objectives are added to the solutions, just like in the main loop.
+/// Entries already in the corpus did not trigger the objective on the main executor, so all of them are newly detected errors.
+#[derive(Debug)]
+pub struct SanitizerVerificationStage<EM, SE, Z> {
+    name: Cow<'static, str>,
+    sanitizer_executor: SE,
+    interval: Duration,
+    sample_rate: f64,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, SE, Z> UsesState for SanitizerVerificationStage<EM, SE, Z>
+where
+    SE: UsesState,
+{
+    type State = SE::State;
+}
+
+impl<EM, SE, Z> Named for SanitizerVerificationStage<EM, SE, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, SE, Z> Stage<E, EM, Z> for SanitizerVerificationStage<EM, SE, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: EventFirer<State = Self::State>,
+    SE: Executor<EM, Z> + HasObservers,
+    SE::Observers: ObserversTuple<Self::State>,
+    Z: HasObjective<State = Self::State>,
+    Self::State:
+        HasCorpus + HasSolutions + HasExecutions + HasRand + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        let meta = state
+            .metadata_map()
+            .get::<SanitizerVerificationMetadata>()
+            .copied()
+            .unwrap_or_default();
+
+        if meta.last_time != Duration::ZERO && now.saturating_sub(meta.last_time) < self.interval {
+            return Ok(());
+        }
+
+        let mut id = match meta.last_id {
+            Some(last_id) => state.corpus().next(last_id),
+            None => state.corpus().first(),
+        };
+        let mut last_id = meta.last_id;
+
+        while let Some(corpus_id) = id {
+            id = state.corpus().next(corpus_id);
+            last_id = Some(corpus_id);
+            // Store the progress first, so we won't get stuck if the sanitizer executor takes us down
+            state.add_metadata(SanitizerVerificationMetadata {
+                last_time: meta.last_time,
+                last_id,
+            });
+
+            if !state.rand_mut().coinflip(self.sample_rate) {
+                continue;
+            }
+            let input = state.corpus().cloned_input_for_id(corpus_id)?;
+
+            self.sanitizer_executor
+                .observers_mut()
+                .pre_exec_all(state, &input)?;
+            let exit_kind = self
+                .sanitizer_executor
+                .run_target(fuzzer, state, manager, &input)?;
+            self.sanitizer_executor
+                .observers_mut()
+                .post_exec_all(state, &input, &exit_kind)?;
+
+            let observers = self.sanitizer_executor.observers();
+            if fuzzer.objective_mut().is_interesting(
+                state,
+                manager,
+                &input,
+                &*observers,
+                &exit_kind,
+            )? {
+                let executions = *state.executions();
+                let mut testcase = Testcase::with_executions(input, executions);
+                testcase.set_parent_id(corpus_id);
+                fuzzer.objective_mut().append_metadata(
+                    state,
+                    manager,
+                    &*observers,
+                    &mut testcase,
+                )?;
+                state.solutions_mut().add(testcase)?;
+
+                manager.fire(
+                    state,
+                    Event::Objective {
+                        objective_size: state.solutions().count(),
+                        executions,
+                        time: current_time(),
+                    },
+                )?;
+            } else {
+                fuzzer.objective_mut().discard_metadata(state, &input)?;
+            }
+        }
+
+        state.add_metadata(SanitizerVerificationMetadata {
+            last_time: now,
+            last_id,
+        });
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // The progress is stored in the metadata, so the next run continues after the crashing entry
+        StdRestartHelper::no_retry(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        StdRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<EM, SE, Z> SanitizerVerificationStage<EM, SE, Z> {
+    /// Creates a new [`SanitizerVerificationStage`], verifying all new corpus entries with the `sanitizer_executor`
+    pub fn new(sanitizer_executor: SE) -> Self {
+        Self {
+            name: Cow::Owned(SANITIZER_VERIFICATION_STAGE_NAME.to_owned()),
+            sanitizer_executor,
+            interval: SANITIZER_DEFAULT_INTERVAL,
+            sample_rate: 1.0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the interval between two verification runs
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Only verifies each new corpus entry with the given probability, between `0.0` and `1.0`
+    pub fn with_sample_rate(mut self, sample_rate: f64) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&sample_rate) {
+            return Err(Error::illegal_argument(format!(
+                "Sample rate {sample_rate} is not in 0.0..=1.0"
+            )));
+        }
+        self.sample_rate = sample_rate;
+        Ok(self)
+    }
+
+    /// Gets the underlying sanitizer executor
+    pub fn executor(&self) -> &SE {
+        &self.sanitizer_executor
+    }
+
+    /// Gets the underlying sanitizer executor (mut)
+    pub fn executor_mut(&mut self) -> &mut SE {
+        &mut self.sanitizer_executor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use libafl_bolts::{tuples::tuple_list, AsSlice};
+
+    use crate::{
+        corpus::{Corpus, Testcase},
+        executors::{test::NopExecutor, ExitKind},
+        inputs::{BytesInput, HasTargetBytes},
+        stages::{test::test_fuzzer, SanitizerVerificationStage, Stage},
+        state::{HasCorpus, HasSolutions},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sanitizer_verification_stage() {
+        // only the sanitizer build detects the bug in inputs starting with `b`
+        let mut sanitizer_harness = |input: &BytesInput| {
+            if input.target_bytes().as_slice()[0] == b'b' {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+
+        let (mut state, mut fuzzer, mut mgr, sanitizer_executor) =
+            test_fuzzer(&mut sanitizer_harness, tuple_list!(), ());
+        let mut executor = NopExecutor::new();
+        let mut stage =
+            SanitizerVerificationStage::new(sanitizer_executor).with_interval(Duration::ZERO);
+
+        for input in [b"a", b"b"] {
+            state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(input.to_vec())))
+                .unwrap();
+        }
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        assert_eq!(state.solutions().count(), 1);
+
+        // only the new entries are verified the next time
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"bb".to_vec())))
+            .unwrap();
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        assert_eq!(state.solutions().count(), 2);
+
+        // nothing is verified before the interval passed
+        let mut stage = stage.with_interval(Duration::from_secs(10));
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"bbb".to_vec())))
+            .unwrap();
+        stage
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+            .unwrap();
+        assert_eq!(state.solutions().count(), 2);
+
+        assert!(stage.with_sample_rate(1.5).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! The [`StateDumpStage`] writes a [`StateSummary`] of the state as JSON, on request or periodically,
+//! to inspect a running fuzzer.
+
+#[cfg(unix)]
+use alloc::vec::Vec;
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use std::{fs, path::PathBuf};
+
+use libafl_bolts::current_time;
+#[cfg(unix)]
+use libafl_bolts::os::unix_signals::{
+    setup_signal_handler, siginfo_t, ucontext_t, Handler, Signal,
+};
+
+use crate::{
+    stages::Stage,
+    state::{HasStateSummary, StateSummary, UsesState},
+    Error,
+};
+
+/// Set by [`request_state_dump`], reset by the next [`StateDumpStage`] that dumps the state
+static STATE_DUMP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the [`StateDumpStage`] to dump the state the next time it runs.
+/// This is async-signal-safe, so it can be called from signal handlers, or from any event handler.
+pub fn request_state_dump() {
+    STATE_DUMP_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(unix)]
+#[derive(Debug)]
+struct StateDumpSignalHandler;
+
+#[cfg(unix)]
+impl Handler for StateDumpSignalHandler {
+    fn handle(
+        &mut self,
+        _signal: Signal,
+        _info: &mut siginfo_t,
+        _context: Option<&mut ucontext_t>,
+    ) {
+        request_state_dump();
+    }
+
+    fn signals(&self) -> Vec<Signal> {
+        vec![Signal::SigUser1]
+    }
+}
+
+#[cfg(unix)]
+static mut STATE_DUMP_SIGNAL_HANDLER: StateDumpSignalHandler = StateDumpSignalHandler;
+
+/// The [`StateDumpStage`] writes a [`StateSummary`] of the state to a JSON file,
+/// whenever [`request_state_dump`] was called, `SIGUSR1` was received (see [`StateDumpStage::with_signal`]),
+/// or the optional interval elapsed.
+///
+/// The file is replaced atomically, so it can be polled by other tools.
+#[derive(Debug)]
+pub struct StateDumpStage<E, EM, Z> {
+    path: PathBuf,
+    interval: Option<Duration>,
+    last_dump: Duration,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for StateDumpStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for StateDumpStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    Self::State: HasStateSummary,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        let interval_elapsed = self
+            .interval
+            .is_some_and(|interval| now.saturating_sub(self.last_dump) >= interval);
+        if STATE_DUMP_REQUESTED.swap(false, Ordering::Relaxed) || interval_elapsed {
+            self.last_dump = now;
+            self.dump(&state.summary()?)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> StateDumpStage<E, EM, Z> {
+    /// Create a new [`StateDumpStage`], writing to `path` whenever [`request_state_dump`] is called
+    #[must_use]
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into(),
+            interval: None,
+            last_dump: current_time(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Also dump the state every `interval`
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Also dump the state when the fuzzer receives `SIGUSR1`, for example by `kill -USR1 <pid>`.
+    /// This replaces any other handler for `SIGUSR1` of this process.
+    #[cfg(unix)]
+    pub fn with_signal(self) -> Result<Self, Error> {
+        // # Safety
+        // The handler is a static that never moves, and only touches an atomic.
+        unsafe {
+            setup_signal_handler(core::ptr::addr_of_mut!(STATE_DUMP_SIGNAL_HANDLER))?;
+        }
+        Ok(self)
+    }
+
+    /// Write the summary to a temporary file next to the target, then move it in place
+    fn dump(&self, summary: &StateSummary) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(summary)?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)?;
+        log::info!("Dumped the state summary to {}", self.path.display());
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
-//! Stage to compute/report AFL stats
+//! Stages to compute/report AFL stats, and user-defined stats
 
 #[cfg(feature = "std")]
-use alloc::{borrow::Cow, string::ToString};
-use core::{marker::PhantomData, time::Duration};
+use alloc::string::ToString;
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
+use core::{fmt, marker::PhantomData, time::Duration};
 
 use libafl_bolts::current_time;
 #[cfg(feature = "std")]
@@ -10,17 +11,13 @@ use serde_json::json;
 
 use crate::{
     corpus::{Corpus, HasCurrentCorpusId},
-    events::EventFirer,
+    events::{Event, EventFirer},
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
     schedulers::minimizer::IsFavoredMetadata,
     stages::Stage,
     state::{HasCorpus, HasImported, UsesState},
     Error, HasMetadata,
 };
-#[cfg(feature = "std")]
-use crate::{
-    events::Event,
-    monitors::{AggregatorOps, UserStats, UserStatsValue},
-};
 
 /// The [`AflStatsStage`] is a simple stage that computes and reports some stats.
 #[derive(Debug, Clone)]
@@ -164,3 +161,206 @@ impl<E, EM, Z> Default for AflStatsStage<E, EM, Z> {
         }
     }
 }
+
+/// Computes the value of a user-defined stat from the state
+type UserStatsFn<S> = Box<dyn FnMut(&S) -> Option<UserStatsValue>>;
+
+/// A user-defined stat of the [`UserStatsStage`], computed from the state
+struct UserStatsSource<S> {
+    name: Cow<'static, str>,
+    aggregator_op: AggregatorOps,
+    compute: UserStatsFn<S>,
+}
+
+/// The [`UserStatsStage`] forwards user-defined stats, computed by closures from the state (usually its metadata),
+/// to the monitors, at most once per interval.
+///
+/// ```rust,ignore
+/// let stats = UserStatsStage::new(Duration::from_secs(15)).with_stat(
+///     "tokens",
+///     AggregatorOps::Max,
+///     |state: &MyState| {
+///         let tokens = state.metadata_map().get::<Tokens>()?;
+///         Some(UserStatsValue::Number(tokens.len() as u64))
+///     },
+/// );
+/// ```
+pub struct UserStatsStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    stats: Vec<UserStatsSource<E::State>>,
+    last_report_time: Duration,
+    stats_report_interval: Duration,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> fmt::Debug for UserStatsStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserStatsStage")
+            .field(
+                "stats",
+                &self.stats.iter().map(|stat| &stat.name).collect::<Vec<_>>(),
+            )
+            .field("last_report_time", &self.last_report_time)
+            .field("stats_report_interval", &self.stats_report_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E, EM, Z> UsesState for UserStatsStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for UserStatsStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: EventFirer<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let cur = current_time();
+        if cur.checked_sub(self.last_report_time).unwrap_or_default() < self.stats_report_interval {
+            return Ok(());
+        }
+        self.last_report_time = cur;
+
+        for stat in &mut self.stats {
+            let Some(value) = (stat.compute)(state) else {
+                continue;
+            };
+            manager.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: stat.name.clone(),
+                    value: UserStats::new(value, stat.aggregator_op.clone()),
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not running the target so we wont't crash/timeout and, hence, don't need to restore anything
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not running the target so we wont't crash/timeout and, hence, don't need to restore anything
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> UserStatsStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    /// Creates a new [`UserStatsStage`], reporting all stats at most once per `interval`
+    #[must_use]
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            stats: Vec::new(),
+            last_report_time: current_time(),
+            stats_report_interval: interval,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Registers a new stat called `name`, computed by `compute` from the state.
+    /// If `compute` returns `None`, the stat is not reported this time.
+    #[must_use]
+    pub fn with_stat<N, F>(mut self, name: N, aggregator_op: AggregatorOps, compute: F) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        F: FnMut(&E::State) -> Option<UserStatsValue> + 'static,
+    {
+        self.stats.push(UserStatsSource {
+            name: name.into(),
+            aggregator_op,
+            compute: Box::new(compute),
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, string::String, vec::Vec};
+    use core::{cell::RefCell, time::Duration};
+
+    use crate::{
+        corpus::{Corpus, Testcase},
+        events::SimpleEventManager,
+        executors::test::NopExecutor,
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        monitors::{AggregatorOps, SimpleMonitor, UserStatsValue},
+        stages::{test, Stage, UserStatsStage},
+        state::{test::test_std_state, HasCorpus},
+    };
+
+    type TestState = test::TestState<BytesInput>;
+
+    #[test]
+    fn test_user_stats_stage() {
+        let messages: RefCell<Vec<String>> = RefCell::new(Vec::new());
+        let monitor = SimpleMonitor::with_user_monitor(|msg: &str| {
+            messages.borrow_mut().push(msg.to_owned());
+        });
+        let mut mgr = SimpleEventManager::new(monitor);
+        let mut state = test_std_state::<BytesInput>();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0])))
+            .unwrap();
+
+        let mut stage = UserStatsStage::new(Duration::ZERO)
+            .with_stat("corpus", AggregatorOps::Sum, |state: &TestState| {
+                Some(UserStatsValue::Number(state.corpus().count() as u64))
+            })
+            .with_stat("skipped", AggregatorOps::Sum, |_state| None);
+        stage
+            .perform(
+                &mut NopFuzzer::new(),
+                &mut NopExecutor::new(),
+                &mut state,
+                &mut mgr,
+            )
+            .unwrap();
+        // stats computing to `None` aren't reported
+        assert_eq!(messages.borrow().len(), 1);
+        assert!(messages.borrow()[0].ends_with(", corpus: 1"));
+
+        // nothing is reported before the interval passed
+        let mut stage = UserStatsStage::new(Duration::from_secs(10)).with_stat(
+            "corpus",
+            AggregatorOps::Sum,
+            |_state| Some(UserStatsValue::Number(0)),
+        );
+        stage
+            .perform(
+                &mut NopFuzzer::new(),
+                &mut NopExecutor::new(),
+                &mut state,
+                &mut mgr,
+            )
+            .unwrap();
+        assert_eq!(messages.borrow().len(), 1);
+    }
+}
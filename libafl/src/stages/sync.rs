@@ -12,8 +12,6 @@ use std::{
 use libafl_bolts::{current_time, shmem::ShMemProvider, Named};
 use serde::{Deserialize, Serialize};
 
-#[cfg(feature = "introspection")]
-use crate::state::HasClientPerfMonitor;
 use crate::{
     corpus::{Corpus, CorpusId, HasTestcase},
     events::{llmp::LlmpEventConverter, Event, EventConfig, EventFirer},
@@ -142,9 +140,6 @@ where
             }
         }
 
-        #[cfg(feature = "introspection")]
-        state.introspection_monitor_mut().finish_stage();
-
         Ok(())
     }
 
@@ -361,8 +356,6 @@ where
         }
 
         self.client.process(fuzzer, state, executor, manager)?;
-        #[cfg(feature = "introspection")]
-        state.introspection_monitor_mut().finish_stage();
         Ok(())
     }
 
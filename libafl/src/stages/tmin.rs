@@ -1,4 +1,5 @@
 //! The [`TMinMutationalStage`] is a stage which will attempt to minimize corpus entries.
+//! The [`InputMinimizer`] minimizes a single input, such as a crash, outside of the fuzzing loop.
 
 use alloc::{
     borrow::{Cow, ToOwned},
@@ -19,7 +20,7 @@ use crate::{
     events::EventFirer,
     executors::{ExitKind, HasObservers},
     feedbacks::{Feedback, FeedbackFactory, HasObserverHandle},
-    inputs::UsesInput,
+    inputs::{Trimmable, UsesInput},
     mark_feature_time,
     mutators::{MutationResult, Mutator},
     observers::{MapObserver, ObserversTuple},
@@ -265,9 +266,6 @@ where
     ) -> Result<(), Error> {
         self.perform_minification(fuzzer, executor, state, manager)?;
 
-        #[cfg(feature = "introspection")]
-        state.introspection_monitor_mut().finish_stage();
-
         Ok(())
     }
 }
@@ -352,6 +350,156 @@ impl<E, EM, F, FF, IP, M, Z> StdTMinMutationalStage<E, EM, F, FF, IP, M, Z> {
     }
 }
 
+/// Minimizes a single input, such as a crash, down to the smallest input which still triggers an objective;
+/// the `tmin` mode of AFL, as a library.
+///
+/// The input is trimmed first, removing chunks of decreasing size, then the mutator is run until `runs` mutations
+/// in a row did not produce a shorter input which still triggers the objective.
+/// You must provide at least one mutator that actually reduces size.
+/// Crossover mutators, such as the ones in [`crate::mutators::havoc_mutations`], need a corpus to splice from;
+/// without one, use [`crate::mutators::havoc_mutations_no_crossover`].
+///
+/// Every candidate input is run, so crashes must not take down the fuzzer,
+/// use an executor that runs the target in a separate process, such as the `ForkserverExecutor`.
+/// The objective should not depend on earlier runs, as the same crash is seen over and over again;
+/// a [`crate::feedbacks::CrashFeedback`] or [`crate::feedbacks::TimeoutFeedback`] works.
+#[derive(Clone, Debug)]
+pub struct InputMinimizer<M> {
+    mutator: M,
+    runs: usize,
+}
+
+impl<M> InputMinimizer<M> {
+    /// Creates a new [`InputMinimizer`], giving up after `runs` mutations in a row without a reduction
+    #[must_use]
+    pub fn new(mutator: M, runs: usize) -> Self {
+        Self { mutator, runs }
+    }
+
+    /// Minimizes the `input`, returning the smallest input found that still triggers the `objective`
+    pub fn minimize<E, EM, F, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        objective: &mut F,
+        input: <E::State as UsesInput>::Input,
+    ) -> Result<<E::State as UsesInput>::Input, Error>
+    where
+        E: HasObservers,
+        EM: EventFirer<State = E::State>,
+        F: Feedback<E::State>,
+        M: Mutator<<E::State as UsesInput>::Input, E::State>,
+        Z: ExecutesInput<E, EM, State = E::State>,
+        <E::State as UsesInput>::Input: Trimmable + Clone,
+        E::State: HasMaxSize,
+    {
+        if !triggers_objective(fuzzer, executor, state, manager, objective, &input)? {
+            return Err(Error::illegal_argument(
+                "The input to minimize does not trigger the objective",
+            ));
+        }
+        let orig_len = input.len();
+        let mut base = input;
+
+        // Remove chunks of halving size, retrying at the same position after each removal
+        let mut remove_len = (base.len().next_power_of_two() / 2).max(1);
+        while remove_len > 0 {
+            let mut remove_pos = 0;
+            while remove_pos < base.len() {
+                let remove_end = (remove_pos + remove_len).min(base.len());
+                if remove_end - remove_pos == base.len() {
+                    // never minimize an input down to nothing
+                    break;
+                }
+                let mut candidate = base.clone();
+                candidate.remove_range(remove_pos..remove_end);
+                if triggers_objective(fuzzer, executor, state, manager, objective, &candidate)? {
+                    base = candidate;
+                } else {
+                    remove_pos += remove_len;
+                }
+            }
+            remove_len /= 2;
+        }
+
+        // Then let the mutator try to find shorter inputs, restoring the max size even if it fails
+        let orig_max_size = state.max_size();
+        let shortened = self.mutate_shorter(fuzzer, executor, state, manager, objective, base);
+        state.set_max_size(orig_max_size);
+        let base = shortened?;
+
+        log::info!(
+            "Minimized the input from {orig_len} to {} elements",
+            base.len()
+        );
+        Ok(base)
+    }
+
+    /// Mutates the `base` until `runs` mutations in a row did not produce a shorter input
+    /// which still triggers the `objective`, capping the max size of the `state` to the current length
+    fn mutate_shorter<E, EM, F, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        objective: &mut F,
+        mut base: <E::State as UsesInput>::Input,
+    ) -> Result<<E::State as UsesInput>::Input, Error>
+    where
+        E: HasObservers,
+        EM: EventFirer<State = E::State>,
+        F: Feedback<E::State>,
+        M: Mutator<<E::State as UsesInput>::Input, E::State>,
+        Z: ExecutesInput<E, EM, State = E::State>,
+        <E::State as UsesInput>::Input: Trimmable + Clone,
+        E::State: HasMaxSize,
+    {
+        let mut i = 0;
+        while i < self.runs {
+            i += 1;
+            state.set_max_size(base.len());
+
+            let mut candidate = base.clone();
+            if self.mutator.mutate(state, &mut candidate)? == MutationResult::Skipped {
+                continue;
+            }
+            if candidate.len() < base.len()
+                && triggers_objective(fuzzer, executor, state, manager, objective, &candidate)?
+            {
+                base = candidate;
+                i = 0;
+            }
+            self.mutator.post_exec(state, None)?;
+        }
+        Ok(base)
+    }
+}
+
+/// Runs the `input` and returns if the `objective` considers it interesting
+fn triggers_objective<E, EM, F, Z>(
+    fuzzer: &mut Z,
+    executor: &mut E,
+    state: &mut E::State,
+    manager: &mut EM,
+    objective: &mut F,
+    input: &<E::State as UsesInput>::Input,
+) -> Result<bool, Error>
+where
+    E: HasObservers,
+    EM: EventFirer<State = E::State>,
+    F: Feedback<E::State>,
+    Z: ExecutesInput<E, EM, State = E::State>,
+{
+    let exit_kind = fuzzer.execute_input(state, executor, manager, input)?;
+    let observers = executor.observers();
+    let interesting = objective.is_interesting(state, manager, input, &*observers, &exit_kind)?;
+    objective.discard_metadata(state, input)?;
+    Ok(interesting)
+}
+
 /// A feedback which checks if the hash of the currently observed map is equal to the original hash
 /// provided
 #[derive(Clone, Debug)]
@@ -463,3 +611,114 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::borrow::Cow;
+
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list, AsSlice, Named};
+
+    use crate::{
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::{ExitKind, InProcessExecutor},
+        feedbacks::CrashFeedback,
+        inputs::{BytesInput, HasTargetBytes},
+        mutators::{havoc_mutations_no_crossover, MutationResult, Mutator, StdScheduledMutator},
+        schedulers::QueueScheduler,
+        stages::InputMinimizer,
+        state::{HasMaxSize, StdState},
+        Error, StdFuzzer,
+    };
+
+    /// A mutator which always fails
+    struct FailingMutator;
+
+    impl Named for FailingMutator {
+        fn name(&self) -> &Cow<'static, str> {
+            static NAME: Cow<'static, str> = Cow::Borrowed("FailingMutator");
+            &NAME
+        }
+    }
+
+    impl<I, S> Mutator<I, S> for FailingMutator {
+        fn mutate(&mut self, _state: &mut S, _input: &mut I) -> Result<MutationResult, Error> {
+            Err(Error::unknown("mutation failed"))
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_input_minimizer() {
+        // "crash" if the input contains an X, without actually crashing the test
+        let mut harness = |input: &BytesInput| {
+            if input.target_bytes().as_slice().contains(&b'X') {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+
+        let mut feedback = ();
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::<_, _, _, ()>::new(QueueScheduler::new(), feedback, ());
+        let mut mgr = NopEventManager::new();
+        let mut executor = InProcessExecutor::new(
+            &mut harness,
+            tuple_list!(),
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+        )
+        .unwrap();
+        let max_size = state.max_size();
+
+        let mut minimizer =
+            InputMinimizer::new(StdScheduledMutator::new(havoc_mutations_no_crossover()), 64);
+        let minimized = minimizer
+            .minimize(
+                &mut fuzzer,
+                &mut executor,
+                &mut state,
+                &mut mgr,
+                &mut objective,
+                BytesInput::new(b"some input with an X in the middle".to_vec()),
+            )
+            .unwrap();
+        assert_eq!(minimized.target_bytes().as_slice(), b"X");
+        assert_eq!(state.max_size(), max_size);
+
+        // inputs which don't trigger the objective are rejected
+        assert!(minimizer
+            .minimize(
+                &mut fuzzer,
+                &mut executor,
+                &mut state,
+                &mut mgr,
+                &mut objective,
+                BytesInput::new(b"no crash".to_vec()),
+            )
+            .is_err());
+
+        // the max size is restored when the mutator fails
+        assert!(InputMinimizer::new(FailingMutator, 64)
+            .minimize(
+                &mut fuzzer,
+                &mut executor,
+                &mut state,
+                &mut mgr,
+                &mut objective,
+                BytesInput::new(b"an X".to_vec()),
+            )
+            .is_err());
+        assert_eq!(state.max_size(), max_size);
+    }
+}
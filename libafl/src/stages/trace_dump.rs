@@ -0,0 +1,496 @@
+//! The [`TraceDumpStage`] mutates the current corpus entry like the mutational stage, and appends each executed input
+//! and the serialized observers to a [`TraceLog`], so that external analysis pipelines can consume the execution stream.
+//!
+//! Each record in the log is framed as a little-endian `u32` length, followed by that many bytes of
+//! [`postcard`]-serialized [`TraceRecord`]. Every file starts with [`TRACE_LOG_MAGIC`].
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::{current_time, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::{ExitKind, HasObservers},
+    fuzzer::ExecutionProcessor,
+    mutators::{MutationResult, Mutator},
+    observers::UsesObservers,
+    stages::{mutational::DEFAULT_MUTATIONAL_MAX_ITERATIONS, Stage, StdRestartHelper},
+    state::{HasCorpus, HasCurrentTestcase, HasExecutions, HasRand, UsesState},
+    Error, ExecutesInput, HasNamedMetadata,
+};
+
+/// Default name for [`TraceDumpStage`]
+pub const TRACE_DUMP_STAGE_NAME: &str = "trace_dump";
+
+/// The magic bytes each trace log file starts with
+pub const TRACE_LOG_MAGIC: &[u8; 8] = b"LAFLTRC1";
+
+/// A single execution, as written to the [`TraceLog`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TraceRecord<I, OT> {
+    /// The executions of this client, at the time of this execution
+    pub executions: u64,
+    /// The time this execution finished, since the epoch
+    pub time: Duration,
+    /// How the target exited
+    pub exit_kind: ExitKind,
+    /// The executed input
+    pub input: I,
+    /// The observers, right after the execution
+    pub observers: OT,
+}
+
+impl<I, OT> TraceRecord<I, OT>
+where
+    I: for<'de> Deserialize<'de>,
+    OT: for<'de> Deserialize<'de>,
+{
+    /// Reads the next record from a trace log, returns `None` at the end of the log.
+    /// The [`TRACE_LOG_MAGIC`] at the start of each file needs to be skipped before.
+    pub fn read_from<R: std::io::Read>(reader: &mut R) -> Result<Option<Self>, Error> {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => (),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let mut buf = vec![0; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut buf)?;
+        Ok(Some(postcard::from_bytes(&buf)?))
+    }
+}
+
+/// Where the [`TraceLog`] writes to
+enum TraceSink {
+    /// A file, rotated once it gets too big
+    File {
+        path: PathBuf,
+        writer: BufWriter<File>,
+    },
+    /// Any other stream, for example a socket
+    Stream(Box<dyn Write>),
+}
+
+/// An append-only log of [`TraceRecord`]s, either to a file with log rotation, or to any stream
+pub struct TraceLog {
+    sink: TraceSink,
+    written: u64,
+    max_size: Option<u64>,
+    max_files: usize,
+}
+
+impl Debug for TraceLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut dbg = f.debug_struct("TraceLog");
+        if let TraceSink::File { path, .. } = &self.sink {
+            dbg.field("path", path);
+        }
+        dbg.field("written", &self.written)
+            .field("max_size", &self.max_size)
+            .field("max_files", &self.max_files)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TraceLog {
+    /// Appends to the log file at `path`, creating it if it doesn't exist
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let (writer, written) = Self::open(&path)?;
+        Ok(Self {
+            sink: TraceSink::File { path, writer },
+            written,
+            max_size: None,
+            max_files: 0,
+        })
+    }
+
+    /// Writes the log to the given stream, for example a [`std::net::TcpStream`]. Streams are never rotated.
+    pub fn from_stream<W: Write + 'static>(mut stream: W) -> Result<Self, Error> {
+        stream.write_all(TRACE_LOG_MAGIC)?;
+        Ok(Self {
+            sink: TraceSink::Stream(Box::new(stream)),
+            written: TRACE_LOG_MAGIC.len() as u64,
+            max_size: None,
+            max_files: 0,
+        })
+    }
+
+    /// Rotates the log file once it exceeds `max_size` bytes, keeping `max_files` old logs
+    /// next to it, suffixed with `.1` (the newest) to `.<max_files>` (the oldest).
+    #[must_use]
+    pub fn with_rotation(mut self, max_size: u64, max_files: usize) -> Self {
+        self.max_size = Some(max_size);
+        self.max_files = max_files;
+        self
+    }
+
+    fn open(path: &Path) -> Result<(BufWriter<File>, u64), Error> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let mut written = file.metadata()?.len();
+        let mut writer = BufWriter::new(file);
+        if written == 0 {
+            writer.write_all(TRACE_LOG_MAGIC)?;
+            written = TRACE_LOG_MAGIC.len() as u64;
+        }
+        Ok((writer, written))
+    }
+
+    fn rotated_path(path: &Path, idx: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{idx}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        let TraceSink::File { path, writer } = &mut self.sink else {
+            return Ok(());
+        };
+        writer.flush()?;
+
+        if self.max_files == 0 {
+            fs::remove_file(&*path)?;
+        } else {
+            for idx in (1..self.max_files).rev() {
+                let from = Self::rotated_path(path, idx);
+                if from.exists() {
+                    fs::rename(from, Self::rotated_path(path, idx + 1))?;
+                }
+            }
+            fs::rename(&*path, Self::rotated_path(path, 1))?;
+        }
+
+        let (new_writer, written) = Self::open(path)?;
+        *writer = new_writer;
+        self.written = written;
+        Ok(())
+    }
+
+    /// Appends a single framed record to the log
+    pub fn append<I, OT>(&mut self, record: &TraceRecord<I, OT>) -> Result<(), Error>
+    where
+        I: Serialize,
+        OT: Serialize,
+    {
+        let buf = postcard::to_allocvec(record)?;
+        let len = u32::try_from(buf.len())
+            .map_err(|_| Error::illegal_argument("Trace record is too big to be framed"))?;
+
+        if self
+            .max_size
+            .is_some_and(|max_size| self.written + 4 + u64::from(len) > max_size)
+        {
+            self.rotate()?;
+        }
+
+        let writer: &mut dyn Write = match &mut self.sink {
+            TraceSink::File { writer, .. } => writer,
+            TraceSink::Stream(stream) => stream,
+        };
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&buf)?;
+        self.written += 4 + u64::from(len);
+        Ok(())
+    }
+
+    /// Flushes all buffered records
+    pub fn flush(&mut self) -> Result<(), Error> {
+        match &mut self.sink {
+            TraceSink::File { writer, .. } => writer.flush()?,
+            TraceSink::Stream(stream) => stream.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// The filter of a [`TraceDumpStage`] created with [`TraceDumpStage::new`], dumping all executions
+pub type DumpAllFilter<I, OT> = fn(&I, &OT, &ExitKind) -> bool;
+
+/// A mutational stage appending each of its executions, the input and the observers, to a [`TraceLog`].
+///
+/// Use it in place of a [`StdMutationalStage`](crate::stages::StdMutationalStage) to dump the execution stream.
+/// Dumping is expensive for large observers, use [`TraceDumpStage::with_filter`] to only dump some executions,
+/// and [`TraceDumpStage::with_min_interval`] to rate-limit it.
+#[derive(Debug)]
+pub struct TraceDumpStage<E, EM, F, M, Z> {
+    name: Cow<'static, str>,
+    mutator: M,
+    max_iterations: usize,
+    log: TraceLog,
+    filter: F,
+    min_interval: Duration,
+    last_dump: Option<Duration>,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, F, M, Z> UsesState for TraceDumpStage<E, EM, F, M, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, F, M, Z> Named for TraceDumpStage<E, EM, F, M, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, F, M, Z> Stage<E, EM, Z> for TraceDumpStage<E, EM, F, M, Z>
+where
+    E: HasObservers<State = Self::State>,
+    E::Observers: Serialize,
+    EM: EventFirer<State = Self::State>,
+    F: FnMut(&Self::Input, &E::Observers, &ExitKind) -> bool,
+    M: Mutator<Self::Input, Self::State>,
+    Z: ExecutesInput<E, EM> + ExecutionProcessor<E::Observers>,
+    Self::State: HasCorpus + HasRand + HasExecutions + HasNamedMetadata,
+    Self::Input: Serialize,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let num = 1 + state.rand_mut().below(self.max_iterations);
+        let base = state.current_input_cloned()?;
+
+        for _ in 0..num {
+            let mut input = base.clone();
+            if self.mutator.mutate(state, &mut input)? == MutationResult::Skipped {
+                continue;
+            }
+
+            let exit_kind = fuzzer.execute_input(state, executor, manager, &input)?;
+            let observers = executor.observers();
+
+            let time = current_time();
+            let rate_limited = self
+                .last_dump
+                .is_some_and(|last| time.saturating_sub(last) < self.min_interval);
+            if !rate_limited && (self.filter)(&input, &*observers, &exit_kind) {
+                self.last_dump = Some(time);
+                self.log.append(&TraceRecord {
+                    executions: *state.executions(),
+                    time,
+                    exit_kind,
+                    input: &input,
+                    observers: &*observers,
+                })?;
+            }
+
+            let (_, corpus_id) =
+                fuzzer.execute_and_process(state, manager, input, &*observers, &exit_kind, true)?;
+            self.mutator.post_exec(state, corpus_id)?;
+        }
+
+        self.log.flush()
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        StdRestartHelper::should_restart(state, &self.name, 3)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        StdRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<E, EM, M, Z> TraceDumpStage<E, EM, DumpAllFilter<Z::Input, E::Observers>, M, Z>
+where
+    E: UsesObservers,
+    Z: UsesState,
+{
+    /// Creates a new [`TraceDumpStage`], appending each execution to the given [`TraceLog`]
+    #[must_use]
+    pub fn new(mutator: M, log: TraceLog) -> Self {
+        Self {
+            name: Cow::Owned(TRACE_DUMP_STAGE_NAME.to_owned()),
+            mutator,
+            max_iterations: DEFAULT_MUTATIONAL_MAX_ITERATIONS,
+            log,
+            filter: |_, _, _| true,
+            min_interval: Duration::ZERO,
+            last_dump: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, F, M, Z> TraceDumpStage<E, EM, F, M, Z> {
+    /// Only dumps the executions for which `filter`, called with the input, the observers and the [`ExitKind`], returns `true`
+    #[must_use]
+    pub fn with_filter<F2>(self, filter: F2) -> TraceDumpStage<E, EM, F2, M, Z> {
+        TraceDumpStage {
+            name: self.name,
+            mutator: self.mutator,
+            max_iterations: self.max_iterations,
+            log: self.log,
+            filter,
+            min_interval: self.min_interval,
+            last_dump: self.last_dump,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Dumps at most a single execution per `min_interval`, skipping all others
+    #[must_use]
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// Runs at most `max_iterations` mutated inputs each time the stage is run
+    #[must_use]
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// The [`TraceLog`] written to
+    pub fn log_mut(&mut self) -> &mut TraceLog {
+        &mut self.log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::{env, fs, fs::File, io::Read, process};
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::HasCurrentCorpusId,
+        executors::ExitKind,
+        inputs::BytesInput,
+        mutators::BitFlipMutator,
+        stages::{
+            test::test_fuzzer,
+            trace_dump::{TraceLog, TraceRecord, TRACE_LOG_MAGIC},
+            Stage, TraceDumpStage,
+        },
+        Evaluator,
+    };
+
+    fn record(executions: u64) -> TraceRecord<Vec<u8>, u32> {
+        TraceRecord {
+            executions,
+            time: core::time::Duration::from_secs(executions),
+            exit_kind: ExitKind::Ok,
+            input: vec![1, 2, 3],
+            observers: 42,
+        }
+    }
+
+    fn read_log(path: &std::path::Path) -> Vec<u64> {
+        let mut file = File::open(path).unwrap();
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, TRACE_LOG_MAGIC);
+        let mut executions = vec![];
+        while let Some(record) = TraceRecord::<Vec<u8>, u32>::read_from(&mut file).unwrap() {
+            assert_eq!(record.input, vec![1, 2, 3]);
+            assert_eq!(record.observers, 42);
+            executions.push(record.executions);
+        }
+        executions
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_trace_log_rotation() {
+        let path = env::temp_dir().join(format!("libafl_trace_dump_{}", process::id()));
+        let rotated = TraceLog::rotated_path(&path, 1);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&rotated);
+
+        let record_len = postcard::to_allocvec(&record(0)).unwrap().len() as u64 + 4;
+        // room for the magic and two records per file
+        let mut log = TraceLog::new(&path)
+            .unwrap()
+            .with_rotation(TRACE_LOG_MAGIC.len() as u64 + 2 * record_len, 1);
+        for executions in 0..3 {
+            log.append(&record(executions)).unwrap();
+        }
+        log.flush().unwrap();
+
+        assert_eq!(read_log(&rotated), vec![0, 1]);
+        assert_eq!(read_log(&path), vec![2]);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_trace_dump_stage() {
+        let path = env::temp_dir().join(format!("libafl_trace_dump_stage_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        // every other execution times out
+        let mut counter = 0;
+        let mut harness = |_input: &BytesInput| {
+            counter += 1;
+            if counter % 2 == 0 {
+                ExitKind::Timeout
+            } else {
+                ExitKind::Ok
+            }
+        };
+        let (mut state, mut fuzzer, mut mgr, mut executor) =
+            test_fuzzer(&mut harness, tuple_list!(), ());
+
+        let corpus_id = fuzzer
+            .add_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(b"a".to_vec()),
+            )
+            .unwrap();
+        state.set_corpus_id(corpus_id).unwrap();
+
+        let mut stage = TraceDumpStage::new(BitFlipMutator, TraceLog::new(&path).unwrap())
+            .with_max_iterations(1)
+            .with_filter(|_: &BytesInput, (): &(), exit_kind: &ExitKind| {
+                *exit_kind == ExitKind::Ok
+            });
+        for _ in 0..4 {
+            stage
+                .perform(&mut fuzzer, &mut executor, &mut state, &mut mgr)
+                .unwrap();
+        }
+
+        let mut file = File::open(&path).unwrap();
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic).unwrap();
+        let mut executions = vec![];
+        while let Some(record) = TraceRecord::<BytesInput, ()>::read_from(&mut file).unwrap() {
+            assert_eq!(record.exit_kind, ExitKind::Ok);
+            executions.push(record.executions);
+        }
+        // the stage executed 2 to 5, the timeouts were filtered
+        assert_eq!(executions, vec![3, 5]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
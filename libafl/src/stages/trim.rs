@@ -0,0 +1,238 @@
+//! The [`TrimStage`] removes chunks of a corpus entry as long as its behavior stays the same, similar to AFL's trimming.
+//!
+//! In contrast to the [`crate::stages::StdTMinMutationalStage`], it does not rely on random mutations,
+//! but works on any [`Trimmable`] input, so structured inputs get size control, too.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::ToString,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::{current_time, impl_serdeany, HasLen, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, HasCurrentCorpusId},
+    events::EventFirer,
+    executors::{ExitKind, HasObservers},
+    feedbacks::{Feedback, FeedbackFactory},
+    inputs::Trimmable,
+    schedulers::RemovableScheduler,
+    stages::{mutation_minimization::reduced_testcase, CheckpointRestartHelper, Stage},
+    state::{HasCorpus, HasCurrentTestcase, HasExecutions, UsesState},
+    Error, ExecutesInput, HasMetadata, HasNamedMetadata, HasScheduler,
+};
+
+/// Start trimming with chunks of `len / TRIM_START_STEPS` elements; AFL++'s `TRIM_START_STEPS`
+const TRIM_START_STEPS: usize = 16;
+/// Stop trimming once chunks get smaller than `len / TRIM_END_STEPS` elements; AFL++'s `TRIM_END_STEPS`
+const TRIM_END_STEPS: usize = 1024;
+/// Never remove less than this amount of elements at once; AFL++'s `TRIM_MIN_BYTES`
+const TRIM_MIN_ELEMENTS: usize = 4;
+
+/// Marks a [`Testcase`](crate::corpus::Testcase) as already trimmed, so it is not trimmed again when scheduled the next time.
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TrimmedMetadata {
+    /// The length of the input before trimming
+    pub orig_len: usize,
+}
+
+impl_serdeany!(TrimmedMetadata);
+
+/// The progress of the [`TrimStage`] on the current corpus entry, stored using the [`CheckpointRestartHelper`]
+#[derive(Debug, Serialize, Deserialize)]
+struct TrimCheckpoint<I> {
+    /// The input trimmed so far
+    base: I,
+    /// The chunk size
+    remove_len: usize,
+    /// The position of the chunk
+    remove_pos: usize,
+}
+
+/// The counter for giving this stage unique id
+static mut TRIM_STAGE_ID: usize = 0;
+/// The name for trim stage
+pub static TRIM_STAGE_NAME: &str = "trim";
+
+/// A stage that trims each corpus entry once, removing chunks of exponentially decreasing size
+/// and keeping every reduction that the feedback created by the given [`FeedbackFactory`] still accepts.
+///
+/// The factory usually is a [`crate::stages::MapEqualityFactory`], so the trimmed input keeps the exact same coverage.
+/// The reduced input replaces the original testcase, updating its exec time.
+/// Its metadata is kept, except for the entries referring to positions in the original input.
+#[derive(Clone, Debug)]
+pub struct TrimStage<E, EM, F, FF, Z> {
+    name: Cow<'static, str>,
+    factory: FF,
+    phantom: PhantomData<(E, EM, F, Z)>,
+}
+
+impl<E, EM, F, FF, Z> UsesState for TrimStage<E, EM, F, FF, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, F, FF, Z> Named for TrimStage<E, EM, F, FF, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, F, FF, Z> Stage<E, EM, Z> for TrimStage<E, EM, F, FF, Z>
+where
+    E: HasObservers<State = Self::State>,
+    EM: EventFirer<State = Self::State>,
+    F: Feedback<Self::State>,
+    FF: FeedbackFactory<F, E::Observers>,
+    Z: ExecutesInput<E, EM> + HasScheduler,
+    Z::Scheduler: RemovableScheduler,
+    Self::Input: Trimmable,
+    Self::State: HasCorpus + HasExecutions + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(corpus_id) = state.current_corpus_id()? else {
+            return Err(Error::illegal_state(
+                "state is not currently processing a corpus index",
+            ));
+        };
+
+        if state.current_testcase()?.has_metadata::<TrimmedMetadata>() {
+            return Ok(());
+        }
+
+        let orig = state.current_input_cloned()?;
+        let orig_len = orig.len();
+
+        // Resume after the candidate we executed last, if we crashed while trimming this entry before
+        let checkpoint: Option<TrimCheckpoint<Self::Input>> =
+            CheckpointRestartHelper::load(state, &self.name)?;
+        let (mut base, mut remove_len, mut remove_pos) = match checkpoint {
+            Some(checkpoint) => (
+                checkpoint.base,
+                checkpoint.remove_len,
+                checkpoint.remove_pos + checkpoint.remove_len,
+            ),
+            None => (orig, 0, 0),
+        };
+
+        if orig_len > TRIM_MIN_ELEMENTS {
+            // Run the input once, so the feedback knows what to compare against
+            fuzzer.execute_input(state, executor, manager, &base)?;
+            let mut feedback = self.factory.create_feedback(&*executor.observers());
+
+            let mut len_p2 = base.len().next_power_of_two();
+            if remove_len == 0 {
+                remove_len = (len_p2 / TRIM_START_STEPS).max(TRIM_MIN_ELEMENTS);
+            }
+
+            while remove_len >= (len_p2 / TRIM_END_STEPS).max(TRIM_MIN_ELEMENTS) {
+                // Sweep linearly over the input, removing one chunk at a time
+                while remove_pos < base.len() {
+                    let trim_avail = remove_len.min(base.len() - remove_pos);
+                    if trim_avail == base.len() {
+                        // never trim an input down to nothing
+                        break;
+                    }
+
+                    let mut candidate = base.clone();
+                    candidate.remove_range(remove_pos..remove_pos + trim_avail);
+
+                    CheckpointRestartHelper::store(
+                        state,
+                        &self.name,
+                        &TrimCheckpoint {
+                            base: &base,
+                            remove_len,
+                            remove_pos,
+                        },
+                    )?;
+                    let exit_kind = fuzzer.execute_input(state, executor, manager, &candidate)?;
+                    let observers = executor.observers();
+
+                    if exit_kind == ExitKind::Ok
+                        && feedback.is_interesting(
+                            state,
+                            manager,
+                            &candidate,
+                            &*observers,
+                            &exit_kind,
+                        )?
+                    {
+                        // The chunk didn't matter, keep the smaller input and retry at the same position
+                        base = candidate;
+                        len_p2 = base.len().next_power_of_two();
+                    } else {
+                        remove_pos += remove_len;
+                    }
+                }
+
+                remove_len >>= 1;
+                remove_pos = 0;
+            }
+        }
+
+        if base.len() == orig_len {
+            state
+                .current_testcase_mut()?
+                .add_metadata(TrimmedMetadata { orig_len });
+            return Ok(());
+        }
+
+        // Run the final input once more, to get its exec time
+        let start = current_time();
+        fuzzer.execute_input(state, executor, manager, &base)?;
+        let exec_time = current_time() - start;
+
+        let mut testcase = reduced_testcase(&mut *state.current_testcase_mut()?, base);
+        testcase.set_exec_time(exec_time);
+        testcase.add_metadata(TrimmedMetadata { orig_len });
+
+        let prev = state.corpus_mut().replace(corpus_id, testcase)?;
+        fuzzer.scheduler_mut().on_replace(state, corpus_id, &prev)?;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        // Continue after the candidate that crashed or timed out
+        CheckpointRestartHelper::should_restart(state, &self.name)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        CheckpointRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<E, EM, F, FF, Z> TrimStage<E, EM, F, FF, Z> {
+    /// Creates a new [`TrimStage`], keeping only reductions the feedback created by the `factory` considers interesting
+    #[must_use]
+    pub fn new(factory: FF) -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = TRIM_STAGE_ID;
+            TRIM_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(TRIM_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str()),
+            factory,
+            phantom: PhantomData,
+        }
+    }
+}
@@ -15,10 +15,52 @@ use crate::{
     HasMetadata,
 };
 
+/// The class of the chars in a [`UnicodeSpan`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnicodeSpanClass {
+    /// ASCII letters, `a-z` and `A-Z`
+    AsciiLetter,
+    /// ASCII digits, `0-9`
+    AsciiDigit,
+    /// All other ASCII chars, like whitespace, punctuation, and control chars
+    AsciiOther,
+    /// All non-ASCII chars, i.e., the chars of other scripts, combining marks, symbols, etc.
+    NonAscii,
+}
+
+impl UnicodeSpanClass {
+    /// The class of the given char
+    #[must_use]
+    pub fn of(c: char) -> Self {
+        if c.is_ascii_alphabetic() {
+            Self::AsciiLetter
+        } else if c.is_ascii_digit() {
+            Self::AsciiDigit
+        } else if c.is_ascii() {
+            Self::AsciiOther
+        } else {
+            Self::NonAscii
+        }
+    }
+}
+
+/// A maximal run of chars of the same [`UnicodeSpanClass`] within one of the string-like ranges of an input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnicodeSpan {
+    /// The byte offset of the first char of this span in the input
+    pub start: usize,
+    /// The byte offset right after the last char of this span in the input
+    pub end: usize,
+    /// The class of all chars in this span
+    pub class: UnicodeSpanClass,
+}
+
 /// Metadata which stores the list of pre-computed string-like ranges in the input
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct UnicodeIdentificationMetadata {
     ranges: Rc<Vec<(usize, BitVec)>>,
+    #[serde(default)]
+    spans: Rc<Vec<UnicodeSpan>>,
 }
 
 impl_serdeany!(UnicodeIdentificationMetadata);
@@ -29,10 +71,38 @@ impl UnicodeIdentificationMetadata {
     pub fn ranges(&self) -> &Vec<(usize, BitVec)> {
         self.ranges.as_ref()
     }
+
+    /// The string-like ranges in the input, split into spans of chars of the same [`UnicodeSpanClass`]
+    #[must_use]
+    pub fn spans(&self) -> &Vec<UnicodeSpan> {
+        self.spans.as_ref()
+    }
+}
+
+/// Splits a string starting at byte offset `base` into [`UnicodeSpan`]s
+fn push_spans(spans: &mut Vec<UnicodeSpan>, base: usize, s: &str) {
+    let mut current: Option<UnicodeSpan> = None;
+    for (c_idx, c) in s.char_indices() {
+        let class = UnicodeSpanClass::of(c);
+        let end = base + c_idx + c.len_utf8();
+        match &mut current {
+            Some(span) if span.class == class => span.end = end,
+            _ => {
+                spans.extend(current.take());
+                current = Some(UnicodeSpan {
+                    start: base + c_idx,
+                    end,
+                    class,
+                });
+            }
+        }
+    }
+    spans.extend(current);
 }
 
 pub(crate) fn extract_metadata(bytes: &[u8]) -> UnicodeIdentificationMetadata {
     let mut ranges = Vec::new();
+    let mut spans = Vec::new();
 
     if !bytes.is_empty() {
         let mut queue = VecDeque::new();
@@ -60,16 +130,18 @@ pub(crate) fn extract_metadata(bytes: &[u8]) -> UnicodeIdentificationMetadata {
                     queue.push_back(unset);
                 }
                 ranges.push((i, entries));
+                push_spans(&mut spans, i, s);
             }
         }
     }
 
     UnicodeIdentificationMetadata {
         ranges: Rc::new(ranges),
+        spans: Rc::new(spans),
     }
 }
 
-/// Stage which identifies potential strings in the provided input
+/// Stage which identifies potential strings in the provided input, and categorizes their chars into [`UnicodeSpan`]s
 #[derive(Debug)]
 pub struct UnicodeIdentificationStage<S> {
     phantom: PhantomData<S>,
@@ -138,3 +210,25 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::stages::unicode::{extract_metadata, UnicodeSpan, UnicodeSpanClass};
+
+    #[test]
+    fn test_unicode_spans() {
+        let metadata = extract_metadata("ab12 été".as_bytes());
+        let span = |start, end, class| UnicodeSpan { start, end, class };
+        assert_eq!(
+            metadata.spans(),
+            &[
+                span(0, 2, UnicodeSpanClass::AsciiLetter),
+                span(2, 4, UnicodeSpanClass::AsciiDigit),
+                span(4, 5, UnicodeSpanClass::AsciiOther),
+                span(5, 7, UnicodeSpanClass::NonAscii),
+                span(7, 8, UnicodeSpanClass::AsciiLetter),
+                span(8, 10, UnicodeSpanClass::NonAscii),
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Resume a campaign from the output directory of an AFL++ instance, see [`StdState::resume_from_afl`].
+//!
+//! The queue becomes the corpus, the crashes (and optionally the hangs) become solutions,
+//! the `fuzzer_stats` restore the executions and the start time, and the `fuzz_bitmap`,
+//! the virgin bits of AFL++, seeds the history of a map feedback, so coverage discovery does not start over.
+
+use alloc::{string::String, vec::Vec};
+use core::time::Duration;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hashbrown::HashMap;
+
+use crate::{
+    corpus::{Corpus, Testcase},
+    events::EventFirer,
+    feedbacks::MapFeedbackMetadata,
+    fuzzer::Evaluator,
+    inputs::{Input, UsesInput},
+    state::{HasExecutions, HasSolutions, HasStartTime, StdState, UsesState},
+    Error, HasNamedMetadata,
+};
+
+/// The parsed `fuzzer_stats` file of an AFL++ instance
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AflFuzzerStats {
+    entries: HashMap<String, String>,
+}
+
+impl AflFuzzerStats {
+    /// Parse the `key : value` lines of a `fuzzer_stats` file
+    #[must_use]
+    pub fn parse(stats: &str) -> Self {
+        let entries = stats
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().into(), value.trim().into()))
+            .collect();
+        Self { entries }
+    }
+
+    /// Read and parse a `fuzzer_stats` file
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// The raw value of `key`
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// The value of `key`, as a number
+    #[must_use]
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.get(key).and_then(|value| value.parse().ok())
+    }
+
+    /// The executions so far
+    #[must_use]
+    pub fn execs_done(&self) -> Option<u64> {
+        self.get_u64("execs_done")
+    }
+
+    /// The time the campaign started, since the unix epoch
+    #[must_use]
+    pub fn start_time(&self) -> Option<Duration> {
+        self.get_u64("start_time").map(Duration::from_secs)
+    }
+}
+
+/// The highest hitcount bucket AFL++ has seen for an entry, given its virgin bits.
+/// AFL++ clears the bit of each bucket it sees, and the buckets match the ones of
+/// [`crate::observers::HitcountsMapObserver`].
+fn max_bucket_seen(virgin: u8) -> u8 {
+    let seen = !virgin;
+    if seen == 0 {
+        0
+    } else {
+        1 << seen.ilog2()
+    }
+}
+
+/// The files of an AFL++ directory such as `queue` or `crashes`, in the order AFL++ created them
+fn afl_dir_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        // Skips `.state`, and the `README.txt` of the crashes
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_file() && name.starts_with("id:") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Where and what to resume from, see [`StdState::resume_from_afl`]
+#[derive(Debug, Clone)]
+pub struct AflResume {
+    dir: PathBuf,
+    map_feedback: Option<String>,
+    hangs: bool,
+}
+
+impl AflResume {
+    /// Resume from the output directory `dir` of a single AFL++ instance, for example `out/default`
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            dir: dir.into(),
+            map_feedback: None,
+            hangs: false,
+        }
+    }
+
+    /// Seed the history of the map feedback called `name` from the `fuzz_bitmap` of AFL++.
+    /// The feedback must observe a hitcounts map of `u8` of the same size as the one of AFL++.
+    #[must_use]
+    pub fn map_feedback(mut self, name: &str) -> Self {
+        self.map_feedback = Some(name.into());
+        self
+    }
+
+    /// Also add the hangs to the solutions
+    #[must_use]
+    pub fn hangs(mut self, hangs: bool) -> Self {
+        self.hangs = hangs;
+        self
+    }
+}
+
+/// What [`StdState::resume_from_afl`] restored
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AflResumeReport {
+    /// The testcases of the queue added to the corpus
+    pub corpus: usize,
+    /// The crashes and hangs added to the solutions
+    pub solutions: usize,
+    /// The map entries AFL++ had covered, if a map feedback was seeded
+    pub covered: Option<usize>,
+    /// The parsed `fuzzer_stats`, if there were any
+    pub stats: Option<AflFuzzerStats>,
+}
+
+impl<I, C, R, SC> StdState<I, C, R, SC>
+where
+    I: Input,
+    C: Corpus<Input = I>,
+    SC: Corpus<Input = I>,
+    Self: UsesInput<Input = I>,
+{
+    /// Resume the campaign of an AFL++ instance, instead of loading the initial inputs.
+    ///
+    /// The testcases of the queue are executed once, like AFL++ does on resume, and added to the corpus,
+    /// even if they are not interesting. The crashes are added to the solutions as they are.
+    pub fn resume_from_afl<E, EM, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        resume: &AflResume,
+    ) -> Result<AflResumeReport, Error>
+    where
+        E: UsesState<State = Self>,
+        EM: EventFirer<State = Self>,
+        Z: Evaluator<E, EM, State = Self>,
+    {
+        let mut report = AflResumeReport::default();
+
+        // Seed the coverage first, so the queue does not look new
+        if let Some(name) = &resume.map_feedback {
+            let virgin_bits = fs::read(resume.dir.join("fuzz_bitmap"))?;
+            let history: Vec<u8> = virgin_bits.iter().copied().map(max_bucket_seen).collect();
+            if let Ok(metadata) = self.named_metadata::<MapFeedbackMetadata<u8>>(name) {
+                if metadata.history_map.len() != history.len() {
+                    return Err(Error::illegal_argument(format!(
+                        "The map of feedback {name} has {} entries, the fuzz_bitmap of AFL++ {}",
+                        metadata.history_map.len(),
+                        history.len()
+                    )));
+                }
+            }
+            let metadata = MapFeedbackMetadata::with_history_map(history, 0);
+            report.covered = Some(metadata.num_covered_map_indexes);
+            self.add_named_metadata(name, metadata);
+        }
+
+        for path in afl_dir_files(&resume.dir.join("queue"))? {
+            log::info!("Resuming AFL++ testcase {}", path.display());
+            fuzzer.add_input(self, executor, manager, I::from_file(&path)?)?;
+            report.corpus += 1;
+        }
+
+        let mut solutions = afl_dir_files(&resume.dir.join("crashes"))?;
+        if resume.hangs {
+            solutions.extend(afl_dir_files(&resume.dir.join("hangs"))?);
+        }
+        for path in solutions {
+            let filename = path.file_name().unwrap().to_string_lossy().into();
+            let testcase = Testcase::with_filename(I::from_file(&path)?, filename);
+            self.solutions_mut().add(testcase)?;
+            report.solutions += 1;
+        }
+
+        let stats_path = resume.dir.join("fuzzer_stats");
+        if stats_path.is_file() {
+            let stats = AflFuzzerStats::from_file(stats_path)?;
+            // Count the executions of the queue on top
+            if let Some(execs_done) = stats.execs_done() {
+                *self.executions_mut() += execs_done;
+            }
+            if let Some(start_time) = stats.start_time() {
+                *self.start_time_mut() = start_time;
+            }
+            report.stats = Some(stats);
+        }
+
+        log::info!(
+            "Resumed {} testcases and {} solutions from AFL++",
+            report.corpus,
+            report.solutions
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use libafl_bolts::rands::StdRand;
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus},
+        events::NopEventManager,
+        executors::{test::NopExecutor, WithObservers},
+        feedbacks::{ConstFeedback, MapFeedbackMetadata},
+        fuzzer::StdFuzzer,
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{
+            afl::{AflFuzzerStats, AflResume},
+            HasCorpus, HasExecutions, HasSolutions, StdState,
+        },
+        HasNamedMetadata,
+    };
+
+    #[test]
+    fn test_afl_fuzzer_stats() {
+        let stats = AflFuzzerStats::parse("start_time        : 1700000000\nexecs_done        : 4321\nafl_version       : ++4.10c\n");
+        assert_eq!(stats.execs_done(), Some(4321));
+        assert_eq!(stats.start_time().unwrap().as_secs(), 1_700_000_000);
+        assert_eq!(stats.get("afl_version"), Some("++4.10c"));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_resume_from_afl() {
+        let dir = env::temp_dir().join(format!("libafl_afl_resume_test_{}", process::id()));
+        fs::create_dir_all(dir.join("queue/.state")).unwrap();
+        fs::create_dir_all(dir.join("crashes")).unwrap();
+        fs::write(
+            dir.join("queue/id:000000,time:0,execs:0,orig:seed"),
+            b"seed",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("queue/id:000001,src:000000,op:havoc,+cov"),
+            b"more",
+        )
+        .unwrap();
+        fs::write(dir.join("crashes/README.txt"), b"not a crash").unwrap();
+        fs::write(dir.join("crashes/id:000000,sig:11,src:000001"), b"boom").unwrap();
+        fs::write(
+            dir.join("fuzzer_stats"),
+            "start_time : 1700000000\nexecs_done : 1000\n",
+        )
+        .unwrap();
+        fs::write(dir.join("fuzz_bitmap"), [0xff, 0xfe, 0xff, 0xf0]).unwrap();
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::<_, _, _, ()>::new(QueueScheduler::new(), feedback, objective);
+        let mut executor = WithObservers::new(NopExecutor::new(), ());
+        let mut manager = NopEventManager::new();
+
+        let report = state
+            .resume_from_afl(
+                &mut fuzzer,
+                &mut executor,
+                &mut manager,
+                &AflResume::new(&dir).map_feedback("edges"),
+            )
+            .unwrap();
+        assert_eq!(report.corpus, 2);
+        assert_eq!(report.solutions, 1);
+        assert_eq!(report.covered, Some(2));
+        assert_eq!(state.corpus().count(), 2);
+        assert_eq!(state.solutions().count(), 1);
+        assert_eq!(*state.executions(), 1002);
+        let history = state
+            .named_metadata::<MapFeedbackMetadata<u8>>("edges")
+            .unwrap();
+        assert_eq!(history.history_map, vec![0, 1, 0, 8]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
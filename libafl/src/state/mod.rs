@@ -23,6 +23,15 @@ use libafl_bolts::{
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+#[cfg(feature = "std")]
+pub mod afl;
+#[cfg(feature = "std")]
+pub use afl::AflResume;
+pub mod reset;
+pub use reset::SoftReset;
+pub mod snapshot;
+pub mod summary;
+pub use summary::{HasStateSummary, StateSummary};
 mod stack;
 pub use stack::StageStack;
 
@@ -0,0 +1,207 @@
+//! Soft resets of a [`StdState`], to escape plateaus in the middle of a campaign.
+//!
+//! A soft reset forgets what the fuzzer learned about how to schedule its corpus,
+//! but keeps the corpus, the solutions, and the random number generator.
+
+use alloc::{string::String, vec::Vec};
+
+use libafl_bolts::serdeany::{SerdeAny, SerdeAnyMap};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    corpus::{Corpus, SchedulerTestcaseMetadata},
+    feedbacks::MapFeedbackMetadata,
+    schedulers::powersched::SchedulerMetadata,
+    state::{StageStack, StdState},
+    Error, HasMetadata, HasNamedMetadata,
+};
+
+/// Which parts of the state [`StdState::soft_reset`] resets
+#[derive(Debug, Clone)]
+pub struct SoftReset {
+    scheduling: bool,
+    feedback_histories: bool,
+    stage_progress: bool,
+    removed_metadata: Vec<fn(&mut SerdeAnyMap)>,
+    removed_namespaces: Vec<String>,
+}
+
+impl Default for SoftReset {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoftReset {
+    /// Create a new [`SoftReset`] of the scheduling state, the feedback histories and the stage progress
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            scheduling: true,
+            feedback_histories: true,
+            stage_progress: true,
+            removed_metadata: vec![],
+            removed_namespaces: vec![],
+        }
+    }
+
+    /// Reset the power schedule, that is the energies of the testcases and the path frequencies,
+    /// as well as how often each testcase was scheduled. All testcases get calibrated again.
+    #[must_use]
+    pub fn scheduling(mut self, scheduling: bool) -> Self {
+        self.scheduling = scheduling;
+        self
+    }
+
+    /// Reset the history maps of all map feedbacks, so the corpus gets rebuilt from scratch, next to the old one
+    #[must_use]
+    pub fn feedback_histories(mut self, feedback_histories: bool) -> Self {
+        self.feedback_histories = feedback_histories;
+        self
+    }
+
+    /// Forget the stage and the testcase the fuzzer was in, for states saved in the middle of an iteration
+    #[must_use]
+    pub fn stage_progress(mut self, stage_progress: bool) -> Self {
+        self.stage_progress = stage_progress;
+        self
+    }
+
+    /// Also remove the metadata of type `M`, for example the [`crate::schedulers::minimizer::TopRatedsMetadata`]
+    #[must_use]
+    pub fn remove_metadata<M>(mut self) -> Self
+    where
+        M: SerdeAny,
+    {
+        self.removed_metadata.push(|map| drop(map.remove::<M>()));
+        self
+    }
+
+    /// Also remove all named metadata in `namespace`, see [`HasNamedMetadata::remove_metadata_namespace`]
+    #[must_use]
+    pub fn remove_namespace(mut self, namespace: &str) -> Self {
+        self.removed_namespaces.push(namespace.into());
+        self
+    }
+}
+
+/// Reset all history maps of map feedbacks with elements of type `T`
+fn reset_feedback_histories<S, T>(state: &mut S) -> Result<(), Error>
+where
+    S: HasNamedMetadata,
+    T: Default + Copy + 'static + Serialize + DeserializeOwned + PartialEq,
+    MapFeedbackMetadata<T>: SerdeAny,
+{
+    if let Some(histories) = state
+        .named_metadata_map_mut()
+        .get_all_mut::<MapFeedbackMetadata<T>>()
+    {
+        for history in histories {
+            history.reset()?;
+        }
+    }
+    Ok(())
+}
+
+impl<I, C, R, SC> StdState<I, C, R, SC>
+where
+    C: Corpus,
+{
+    /// Reset the volatile parts of this state, as chosen by `reset`, keeping the corpus, the solutions,
+    /// and the random number generator.
+    ///
+    /// Call it between two iterations of the fuzzer, not from within a stage.
+    pub fn soft_reset(&mut self, reset: &SoftReset) -> Result<(), Error> {
+        if reset.scheduling {
+            if let Ok(metadata) = self.metadata_mut::<SchedulerMetadata>() {
+                *metadata = SchedulerMetadata::new(metadata.strat());
+            }
+            for i in 0..self.corpus.count_all() {
+                let id = self.corpus.nth_from_all(i);
+                let mut testcase = self.corpus.get_from_all(id)?.borrow_mut();
+                testcase.set_scheduled_count(0);
+                if let Ok(metadata) = testcase.metadata_mut::<SchedulerTestcaseMetadata>() {
+                    metadata.set_handicap(0);
+                }
+            }
+        }
+
+        if reset.feedback_histories {
+            reset_feedback_histories::<Self, u8>(self)?;
+            reset_feedback_histories::<Self, u16>(self)?;
+            reset_feedback_histories::<Self, u32>(self)?;
+            reset_feedback_histories::<Self, u64>(self)?;
+            reset_feedback_histories::<Self, usize>(self)?;
+            reset_feedback_histories::<Self, bool>(self)?;
+        }
+
+        if reset.stage_progress {
+            self.stage_stack = StageStack::default();
+            self.corpus_id = None;
+        }
+
+        for remove in &reset.removed_metadata {
+            remove(&mut self.metadata);
+        }
+        for namespace in &reset.removed_namespaces {
+            self.remove_metadata_namespace(namespace);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::{Corpus, Testcase},
+        feedbacks::MapFeedbackMetadata,
+        inputs::BytesInput,
+        schedulers::powersched::{PowerSchedule, SchedulerMetadata},
+        stages::DeterministicDoneMetadata,
+        state::{reset::SoftReset, test::test_std_state, HasCorpus},
+        HasMetadata, HasNamedMetadata,
+    };
+
+    #[test]
+    fn test_soft_reset() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut testcase = Testcase::new(BytesInput::new(vec![1]));
+        testcase.set_scheduled_count(5);
+        let id = state.corpus_mut().add(testcase).unwrap();
+
+        let mut metadata = SchedulerMetadata::new(Some(PowerSchedule::FAST));
+        metadata.set_queue_cycles(3);
+        state.add_metadata(metadata);
+        state.add_metadata(DeterministicDoneMetadata);
+        let mut history = MapFeedbackMetadata::<u8>::new(4);
+        history.history_map[1] = 1;
+        history.num_covered_map_indexes = 1;
+        state.add_named_metadata("edges", history);
+        state.add_namespaced_metadata("plugin", "runs", DeterministicDoneMetadata);
+
+        state
+            .soft_reset(
+                &SoftReset::new()
+                    .remove_metadata::<DeterministicDoneMetadata>()
+                    .remove_namespace("plugin"),
+            )
+            .unwrap();
+
+        assert_eq!(state.corpus().count(), 1);
+        assert_eq!(
+            state.corpus().get(id).unwrap().borrow().scheduled_count(),
+            0
+        );
+        let metadata = state.metadata::<SchedulerMetadata>().unwrap();
+        assert_eq!(metadata.queue_cycles(), 0);
+        assert_eq!(metadata.strat(), Some(PowerSchedule::FAST));
+        assert!(!state.has_metadata::<DeterministicDoneMetadata>());
+        assert!(state.metadata_namespaces().is_empty());
+        let history = state
+            .named_metadata::<MapFeedbackMetadata<u8>>("edges")
+            .unwrap();
+        assert_eq!(history.num_covered_map_indexes, 0);
+        assert_eq!(history.history_map[1], 0);
+    }
+}
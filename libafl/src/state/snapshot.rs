@@ -0,0 +1,342 @@
+//! Versioned snapshots of a [`StdState`], that can be restored after the metadata types of the fuzzer changed.
+//!
+//! The state and its corpora are serialized as a whole, but each metadata entry is serialized on its own,
+//! keyed by the name of its type. When restoring a snapshot written by an older build of the fuzzer,
+//! a [`StateMigration`] can upgrade each entry, step by step, to the current version.
+//! Entries that still cannot be restored are dropped, instead of invalidating the whole snapshot.
+//!
+//! The metadata of the testcases in the corpora is not covered and, unless the `unsafe_stable_anymap` feature
+//! of `libafl_bolts` is used, depends on the exact build of the fuzzer.
+//!
+//! Snapshots can be written in any [`SerializationFormat`], for example as JSON to debug a broken state.
+//! The format is recorded in the header of the snapshot, so restoring it does not need to know it.
+
+use alloc::{string::String, vec::Vec};
+use core::mem;
+
+use libafl_bolts::serdeany::SerdeAny;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    common::serialization::{read_header, serialize_with_header, SerializationFormat},
+    state::StdState,
+    Error,
+};
+
+/// The version of the snapshot format written by [`StdState::to_snapshot`]
+pub const STATE_SNAPSHOT_FORMAT_VERSION: u32 = 2;
+
+/// A metadata entry of a state snapshot, handed to [`StateMigration::migrate`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataEntry {
+    /// The name of the metadata type, as given by [`core::any::type_name`]
+    pub type_name: String,
+    /// The name of the entry, for named metadata
+    pub name: Option<String>,
+    /// The value, serialized with `postcard`, whatever the format of the snapshot,
+    /// as that is what the metadata registry expects
+    pub bytes: Vec<u8>,
+}
+
+impl MetadataEntry {
+    /// Create an entry for `value`, for example to replace an entry of an old metadata type in a migration
+    pub fn new<T>(name: Option<String>, value: &T) -> Result<Self, Error>
+    where
+        T: SerdeAny + Serialize,
+    {
+        Ok(Self {
+            type_name: value.type_name().into(),
+            name,
+            bytes: postcard::to_allocvec(value)?,
+        })
+    }
+
+    /// Deserialize the value, for example as the old layout of a metadata type
+    pub fn deserialize<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(postcard::from_bytes(&self.bytes)?)
+    }
+}
+
+/// Upgrades the metadata of state snapshots written by older builds of a fuzzer
+pub trait StateMigration {
+    /// The version of the state of the running fuzzer.
+    /// Bump it whenever a metadata type changes incompatibly, and handle the old layout in [`StateMigration::migrate`].
+    fn version(&self) -> u32;
+
+    /// Migrate a metadata entry from `from_version` to `from_version + 1`, or return `None` to drop it.
+    /// By default, entries are kept as they are.
+    fn migrate(
+        &self,
+        _from_version: u32,
+        entry: MetadataEntry,
+    ) -> Result<Option<MetadataEntry>, Error> {
+        Ok(Some(entry))
+    }
+}
+
+/// No migrations, for fuzzers that never changed their state
+impl StateMigration for () {
+    fn version(&self) -> u32 {
+        0
+    }
+}
+
+/// The serialized snapshot
+#[derive(Debug, Serialize, Deserialize)]
+struct StateSnapshot {
+    format_version: u32,
+    version: u32,
+    /// The state, without its metadata, in the format of the snapshot
+    state: Vec<u8>,
+    metadata: Vec<MetadataEntry>,
+}
+
+impl<I, C, R, SC> StdState<I, C, R, SC>
+where
+    Self: Serialize + DeserializeOwned,
+{
+    /// Serialize this state into a snapshot, tagged with the version of `migration`.
+    pub fn to_snapshot<M>(&mut self, migration: &M) -> Result<Vec<u8>, Error>
+    where
+        M: StateMigration,
+    {
+        self.to_snapshot_with_format(migration, SerializationFormat::Postcard)
+    }
+
+    /// Serialize this state into a snapshot in the given `format`, tagged with the version of `migration`.
+    pub fn to_snapshot_with_format<M>(
+        &mut self,
+        migration: &M,
+        format: SerializationFormat,
+    ) -> Result<Vec<u8>, Error>
+    where
+        M: StateMigration,
+    {
+        let mut metadata: Vec<MetadataEntry> = self
+            .metadata
+            .to_serialized_entries()?
+            .into_iter()
+            .map(|(type_name, bytes)| MetadataEntry {
+                type_name,
+                name: None,
+                bytes,
+            })
+            .collect();
+        metadata.extend(
+            self.named_metadata
+                .to_serialized_entries()?
+                .into_iter()
+                .map(|(type_name, name, bytes)| MetadataEntry {
+                    type_name,
+                    name: Some(name),
+                    bytes,
+                }),
+        );
+
+        // Serialize the rest of the state without the metadata, and put it back afterwards
+        let state_metadata = mem::take(&mut self.metadata);
+        let state_named_metadata = mem::take(&mut self.named_metadata);
+        let state = format.serialize(self);
+        self.metadata = state_metadata;
+        self.named_metadata = state_named_metadata;
+
+        let snapshot = StateSnapshot {
+            format_version: STATE_SNAPSHOT_FORMAT_VERSION,
+            version: migration.version(),
+            state: state?,
+            metadata,
+        };
+        serialize_with_header(format, &snapshot)
+    }
+
+    /// Restore a state from a snapshot written by [`StdState::to_snapshot`] or [`StdState::to_snapshot_with_format`],
+    /// migrating its metadata from the version it was written with to the version of `migration`.
+    ///
+    /// Metadata entries that cannot be restored, because their type is gone or changed without a migration,
+    /// are dropped with a warning. Their type names are returned, so that the fuzzer can recompute them.
+    pub fn from_snapshot<M>(bytes: &[u8], migration: &M) -> Result<(Self, Vec<String>), Error>
+    where
+        M: StateMigration,
+    {
+        let (format, payload) = read_header(bytes)?;
+        let snapshot: StateSnapshot = format.deserialize(payload)?;
+        if snapshot.format_version != STATE_SNAPSHOT_FORMAT_VERSION {
+            return Err(Error::illegal_argument(format!(
+                "Unsupported state snapshot format {}, expected {STATE_SNAPSHOT_FORMAT_VERSION}",
+                snapshot.format_version
+            )));
+        }
+        let version = migration.version();
+        if snapshot.version > version {
+            return Err(Error::illegal_argument(format!(
+                "The state snapshot has version {}, newer than the version {version} of this fuzzer",
+                snapshot.version
+            )));
+        }
+
+        let mut state: Self = format.deserialize(&snapshot.state)?;
+        let mut dropped = vec![];
+        'entries: for mut entry in snapshot.metadata {
+            for from_version in snapshot.version..version {
+                let type_name = entry.type_name.clone();
+                let Some(migrated) = migration.migrate(from_version, entry)? else {
+                    dropped.push(type_name);
+                    continue 'entries;
+                };
+                entry = migrated;
+            }
+
+            let restored = match &entry.name {
+                None => state
+                    .metadata
+                    .insert_serialized(&entry.type_name, &entry.bytes),
+                Some(name) => {
+                    state
+                        .named_metadata
+                        .insert_serialized(&entry.type_name, name, &entry.bytes)
+                }
+            };
+            if let Err(err) = restored {
+                log::warn!(
+                    "Dropping metadata {} from the state snapshot: {err}",
+                    entry.type_name
+                );
+                dropped.push(entry.type_name);
+            }
+        }
+
+        Ok((state, dropped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use libafl_bolts::{impl_serdeany, rands::StdRand, serdeany::RegistryBuilder};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        common::serialization::{
+            deserialize_with_header, serialize_with_header, SerializationFormat,
+        },
+        corpus::InMemoryCorpus,
+        inputs::BytesInput,
+        state::{
+            snapshot::{MetadataEntry, StateMigration},
+            test::test_std_state,
+            StdState,
+        },
+        Error, HasMetadata, HasNamedMetadata,
+    };
+
+    type TestState =
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct OldMetadata {
+        count: u32,
+    }
+    impl_serdeany!(OldMetadata);
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct NewMetadata {
+        count: u64,
+        seen: bool,
+    }
+    impl_serdeany!(NewMetadata);
+
+    /// Replaces [`OldMetadata`] with [`NewMetadata`] in version 1
+    struct Migration;
+
+    impl StateMigration for Migration {
+        fn version(&self) -> u32 {
+            1
+        }
+
+        fn migrate(
+            &self,
+            from_version: u32,
+            entry: MetadataEntry,
+        ) -> Result<Option<MetadataEntry>, Error> {
+            if from_version == 0 && entry.type_name.ends_with("OldMetadata") {
+                let old: OldMetadata = entry.deserialize()?;
+                let new = NewMetadata {
+                    count: old.count.into(),
+                    seen: true,
+                };
+                return Ok(Some(MetadataEntry::new(entry.name, &new)?));
+            }
+            Ok(Some(entry))
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_state_snapshot_migration() {
+        unsafe {
+            RegistryBuilder::register::<OldMetadata>();
+            RegistryBuilder::register::<NewMetadata>();
+        }
+
+        let mut state = test_std_state::<BytesInput>();
+        state.add_metadata(OldMetadata { count: 3 });
+        state.add_named_metadata("old", OldMetadata { count: 5 });
+        let snapshot = state.to_snapshot(&()).unwrap();
+        // Taking the snapshot keeps the metadata
+        assert!(state.has_metadata::<OldMetadata>());
+
+        let (restored, dropped) = TestState::from_snapshot(&snapshot, &Migration).unwrap();
+        assert!(dropped.is_empty());
+        assert!(!restored.has_metadata::<OldMetadata>());
+        assert_eq!(restored.metadata::<NewMetadata>().unwrap().count, 3);
+        assert!(restored.named_metadata::<NewMetadata>("old").unwrap().seen);
+
+        // Snapshots of newer fuzzers are rejected
+        let snapshot = state.to_snapshot(&Migration).unwrap();
+        assert!(TestState::from_snapshot(&snapshot, &()).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_state_snapshot_drops_unknown() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut snapshot = state.to_snapshot(&()).unwrap();
+
+        // Append an entry of a type that does not exist (anymore)
+        let mut parsed: super::StateSnapshot = deserialize_with_header(&snapshot).unwrap();
+        parsed.metadata.push(MetadataEntry {
+            type_name: "gone::Metadata".to_string(),
+            name: None,
+            bytes: vec![1, 2, 3],
+        });
+        snapshot = serialize_with_header(SerializationFormat::Postcard, &parsed).unwrap();
+
+        let (_restored, dropped) = TestState::from_snapshot(&snapshot, &()).unwrap();
+        assert_eq!(dropped, vec!["gone::Metadata".to_string()]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[cfg_attr(miri, ignore)]
+    fn test_state_snapshot_json() {
+        let mut state = test_std_state::<BytesInput>();
+        state.add_named_metadata("old", OldMetadata { count: 5 });
+        let snapshot = state
+            .to_snapshot_with_format(&(), SerializationFormat::Json)
+            .unwrap();
+        // The state can be read behind the header
+        let json = core::str::from_utf8(&snapshot[5..]).unwrap();
+        assert!(json.contains("\"format_version\":2"));
+
+        let (restored, dropped) = TestState::from_snapshot(&snapshot, &()).unwrap();
+        assert!(dropped.is_empty());
+        assert_eq!(
+            restored.named_metadata::<OldMetadata>("old").unwrap().count,
+            5
+        );
+    }
+}
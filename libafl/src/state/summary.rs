@@ -0,0 +1,176 @@
+//! A structured summary of a state, to inspect a running fuzzer or a serialized state after the fact,
+//! without knowing the concrete types of its metadata.
+
+use alloc::{string::String, vec::Vec};
+use core::{cmp::Reverse, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Corpus,
+    feedbacks::MapFeedbackMetadata,
+    state::{
+        HasCorpus, HasExecutions, HasImported, HasMaxSize, HasSolutions, HasStartTime, StdState,
+    },
+    Error, HasMetadata, HasNamedMetadata,
+};
+
+/// A metadata entry of the state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataSummary {
+    /// The name of the metadata type
+    pub type_name: String,
+    /// The name of the entry, for named metadata
+    pub name: Option<String>,
+    /// The size of the entry, serialized with `postcard`
+    pub size: usize,
+}
+
+/// The history map of a [`crate::feedbacks::MapFeedback`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedbackHistorySummary {
+    /// The name of the feedback
+    pub name: String,
+    /// The size of the history map
+    pub map_size: usize,
+    /// The entries of the history map that have been hit so far
+    pub covered: usize,
+}
+
+/// A summary of a state, see [`HasStateSummary`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSummary {
+    /// The executions so far
+    pub executions: u64,
+    /// The time the fuzzer started
+    pub start_time: Duration,
+    /// The testcases imported from other fuzzers
+    pub imported: usize,
+    /// The enabled testcases in the corpus
+    pub corpus_count: usize,
+    /// The disabled testcases in the corpus
+    pub corpus_disabled: usize,
+    /// The solutions found so far
+    pub solutions_count: usize,
+    /// The maximum size of a testcase
+    pub max_size: usize,
+    /// All metadata entries, named or not, largest first
+    pub metadata: Vec<MetadataSummary>,
+    /// The history maps of all map feedbacks
+    pub feedback_histories: Vec<FeedbackHistorySummary>,
+}
+
+impl StateSummary {
+    /// The size of all metadata, serialized
+    #[must_use]
+    pub fn metadata_size(&self) -> usize {
+        self.metadata.iter().map(|entry| entry.size).sum()
+    }
+}
+
+/// A state that can summarize itself, for introspection
+pub trait HasStateSummary {
+    /// Summarize this state.
+    /// This serializes all metadata to measure it, so it is not meant to be called on every iteration.
+    fn summary(&self) -> Result<StateSummary, Error>;
+}
+
+/// The coverage of the history map of feedback `name`, if it is a map of `T`
+fn feedback_history<S, T>(state: &S, name: &str) -> Option<FeedbackHistorySummary>
+where
+    S: HasNamedMetadata,
+    T: Default + Copy + 'static + Serialize,
+    MapFeedbackMetadata<T>: libafl_bolts::serdeany::SerdeAny,
+{
+    state
+        .named_metadata_map()
+        .get::<MapFeedbackMetadata<T>>(name)
+        .map(|history| FeedbackHistorySummary {
+            name: name.into(),
+            map_size: history.history_map.len(),
+            covered: history.num_covered_map_indexes,
+        })
+}
+
+impl<I, C, R, SC> HasStateSummary for StdState<I, C, R, SC>
+where
+    Self: HasCorpus<Corpus = C> + HasSolutions<Solutions = SC>,
+    C: Corpus,
+    SC: Corpus,
+{
+    fn summary(&self) -> Result<StateSummary, Error> {
+        let mut metadata: Vec<MetadataSummary> = self
+            .metadata_map()
+            .to_serialized_entries()?
+            .into_iter()
+            .map(|(type_name, bytes)| MetadataSummary {
+                type_name,
+                name: None,
+                size: bytes.len(),
+            })
+            .collect();
+
+        let mut feedback_histories = vec![];
+        for (type_name, name, bytes) in self.named_metadata_map().to_serialized_entries()? {
+            if type_name.contains("MapFeedbackMetadata") {
+                // The usual element types of coverage maps
+                let history = feedback_history::<Self, u8>(self, &name)
+                    .or_else(|| feedback_history::<Self, u16>(self, &name))
+                    .or_else(|| feedback_history::<Self, u32>(self, &name))
+                    .or_else(|| feedback_history::<Self, u64>(self, &name))
+                    .or_else(|| feedback_history::<Self, usize>(self, &name))
+                    .or_else(|| feedback_history::<Self, bool>(self, &name));
+                feedback_histories.extend(history);
+            }
+            metadata.push(MetadataSummary {
+                type_name,
+                name: Some(name),
+                size: bytes.len(),
+            });
+        }
+        metadata.sort_by_key(|entry| Reverse(entry.size));
+        feedback_histories.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(StateSummary {
+            executions: *self.executions(),
+            start_time: *self.start_time(),
+            imported: *self.imported(),
+            corpus_count: self.corpus().count(),
+            corpus_disabled: self.corpus().count_disabled(),
+            solutions_count: self.solutions().count(),
+            max_size: self.max_size(),
+            metadata,
+            feedback_histories,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        feedbacks::MapFeedbackMetadata,
+        inputs::BytesInput,
+        stages::DeterministicDoneMetadata,
+        state::{summary::HasStateSummary, test::test_std_state},
+        HasMetadata, HasNamedMetadata,
+    };
+
+    #[test]
+    fn test_state_summary() {
+        let mut state = test_std_state::<BytesInput>();
+        state.add_metadata(DeterministicDoneMetadata);
+        let mut history = MapFeedbackMetadata::<u8>::new(16);
+        history.history_map[3] = 1;
+        history.num_covered_map_indexes = 1;
+        state.add_named_metadata("edges", history);
+
+        let summary = state.summary().unwrap();
+        assert_eq!(summary.corpus_count, 0);
+        assert_eq!(summary.metadata.len(), 2);
+        assert!(summary.metadata_size() > 16);
+        assert_eq!(summary.feedback_histories.len(), 1);
+        assert_eq!(summary.feedback_histories[0].name, "edges");
+        assert_eq!(summary.feedback_histories[0].map_size, 16);
+        assert_eq!(summary.feedback_histories[0].covered, 1);
+    }
+}
@@ -27,6 +27,22 @@
 //! }
 //! ```
 //!
+//! # Config files
+//!
+//! Campaign settings can be kept in a `.toml` file, or a `.yaml` file with the `cli_yaml` feature, passed with `--config`.
+//! The keys are the long names of the flags, and flags given on the command line take precedence.
+//!
+//! ```toml
+//! cores = "0-7"
+//! broker-port = 1337
+//! timeout = 1000
+//! input = ["corpus/"]
+//! output = "solutions/"
+//! schedule = "explore"
+//! disabled-stages = ["cmplog"]
+//! monitor = "tui"
+//! ```
+//!
 //! ## Example (`libafl_qemu`)
 //!
 //! ```ignore
@@ -64,13 +80,26 @@
 //!```
 
 #[cfg(feature = "frida_cli")]
-use alloc::{boxed::Box, string::ToString};
-use alloc::{string::String, vec::Vec};
+use alloc::boxed::Box;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 #[cfg(feature = "frida_cli")]
 use std::error;
-use std::{net::SocketAddr, path::PathBuf, time::Duration};
-
-use clap::{Command, CommandFactory, Parser};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{
+    error::ErrorKind, parser::ValueSource, ArgMatches, Command, CommandFactory, FromArgMatches,
+    Parser, ValueEnum,
+};
 use serde::{Deserialize, Serialize};
 
 use super::core_affinity::Cores;
@@ -104,6 +133,40 @@ fn parse_instrumentation_location(
     ))
 }
 
+/// The scheduler a fuzzer should use, and its power schedule
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Schedule {
+    /// Go through the corpus in order, without a power schedule
+    Queue,
+    /// The `explore` power schedule
+    Explore,
+    /// The `exploit` power schedule
+    Exploit,
+    /// The `fast` power schedule
+    Fast,
+    /// The `coe` power schedule
+    Coe,
+    /// The `lin` power schedule
+    Lin,
+    /// The `quad` power schedule
+    Quad,
+}
+
+/// The monitor a fuzzer should report its progress to
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MonitorKind {
+    /// A single line for all clients
+    Simple,
+    /// A line for each client
+    Multi,
+    /// The terminal user interface
+    Tui,
+    /// No reports at all
+    Nop,
+}
+
 /// Top-level container for cli options/arguments/subcommands
 #[derive(Parser, Clone, Debug, Serialize, Deserialize)]
 #[command(
@@ -129,6 +192,11 @@ pub struct FuzzerOptions {
     #[arg(long, default_value = "default configuration")]
     pub configuration: String,
 
+    /// A `.toml` (or, with the `cli_yaml` feature, `.yaml`) file to read the options from.
+    /// Options given on the command line take precedence.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
     /// Enable Address Sanitizer (`ASan`)
     #[arg(short = 'A', long, help_heading = "Fuzz Options")]
     pub asan: bool,
@@ -253,6 +321,23 @@ pub struct FuzzerOptions {
     #[arg(last = true)]
     pub qemu_args: Vec<String>,
 
+    /// The scheduler to use, the fuzzer picks one if not set
+    #[arg(long, value_enum, help_heading = "Fuzz Options")]
+    pub schedule: Option<Schedule>,
+
+    /// Names of stages to leave out, ex: 'cmplog,trim'. The names known depend on the fuzzer.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "STAGES",
+        help_heading = "Fuzz Options"
+    )]
+    pub disabled_stages: Vec<String>,
+
+    /// The monitor to report to, the fuzzer picks one if not set
+    #[arg(long, value_enum)]
+    pub monitor: Option<MonitorKind>,
+
     /// Paths to fuzzer token files (aka 'dictionaries')
     #[arg(short = 'x', long, help_heading = "Fuzz Options")]
     pub tokens: Vec<PathBuf>,
@@ -302,6 +387,10 @@ pub struct FuzzerOptions {
         requires = "replay"
     )]
     pub repeat: Option<usize>,
+
+    /// Path to a single input to run, with all observers, printing a report on what happened instead of fuzzing
+    #[arg(long, value_name = "FILE", help_heading = "Replay Options")]
+    pub reproduce: Option<PathBuf>,
 }
 
 impl FuzzerOptions {
@@ -350,14 +439,127 @@ impl FuzzerOptions {
         let command: Command = Self::command();
         command.subcommand(mode)
     }
+
+    /// If the stage called `name` was not turned off with `--disabled-stages`
+    #[must_use]
+    pub fn stage_enabled(&self, name: &str) -> bool {
+        !self.disabled_stages.iter().any(|stage| stage == name)
+    }
+
+    /// Parse the given args, then fill in the options not given on the command line from the `--config` file
+    pub fn try_parse_with_config_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = Self::command().try_get_matches_from(args)?;
+        let mut options = Self::from_arg_matches(&matches)?;
+        if let Some(path) = options.config.clone() {
+            ConfigFile::load(&path)
+                .and_then(|file| file.merge_into(&mut options, &matches))
+                .map_err(|err| {
+                    Self::command().error(
+                        ErrorKind::InvalidValue,
+                        format!("invalid config file {}: {err}", path.display()),
+                    )
+                })?;
+        }
+        Ok(options)
+    }
+}
+
+/// The options which can be set in a config file, keyed by the long name of their flag
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigFile {
+    timeout: Option<u64>,
+    verbose: Option<bool>,
+    stdout: Option<String>,
+    configuration: Option<String>,
+    asan: Option<bool>,
+    iterations: Option<usize>,
+    harness: Option<PathBuf>,
+    cmplog: Option<bool>,
+    schedule: Option<Schedule>,
+    disabled_stages: Option<Vec<String>>,
+    monitor: Option<MonitorKind>,
+    tokens: Option<Vec<PathBuf>>,
+    input: Option<Vec<PathBuf>>,
+    output: Option<PathBuf>,
+    cores: Option<String>,
+    broker_port: Option<u16>,
+    remote_broker_addr: Option<SocketAddr>,
+}
+
+impl ConfigFile {
+    /// Read a config file, in YAML for `.yaml` and `.yml` files, in TOML otherwise
+    fn load(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(OsStr::to_str) {
+            #[cfg(feature = "cli_yaml")]
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&content).map_err(|err| Error::serialize(err.to_string()))
+            }
+            #[cfg(not(feature = "cli_yaml"))]
+            Some("yaml" | "yml") => Err(Error::unsupported(
+                "YAML config files need the `cli_yaml` feature",
+            )),
+            _ => toml::from_str(&content).map_err(|err| Error::serialize(err.to_string())),
+        }
+    }
+
+    /// Set the options that were not given on the command line
+    fn merge_into(self, options: &mut FuzzerOptions, matches: &ArgMatches) -> Result<(), Error> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        macro_rules! merge {
+            ($($field:ident),*) => {$(
+                if let Some(value) = self.$field {
+                    if !from_cli(stringify!($field)) {
+                        options.$field = value.into();
+                    }
+                }
+            )*};
+        }
+        merge!(
+            verbose,
+            stdout,
+            configuration,
+            asan,
+            iterations,
+            harness,
+            cmplog,
+            schedule,
+            disabled_stages,
+            monitor,
+            tokens,
+            input,
+            output,
+            broker_port,
+            remote_broker_addr
+        );
+
+        if let Some(timeout) = self.timeout {
+            if !from_cli("timeout") {
+                options.timeout = Duration::from_millis(timeout);
+            }
+        }
+        if let Some(cores) = self.cores {
+            if !from_cli("cores") {
+                options.cores = Cores::from_cmdline(&cores)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Parse from `std::env::args_os()`, exit on error
 ///
+/// Options not given on the command line are read from the `--config` file, if any.
 /// For more information, see the [cli](super::cli) documentation
 #[must_use]
 pub fn parse_args() -> FuzzerOptions {
-    FuzzerOptions::parse()
+    FuzzerOptions::try_parse_with_config_from(std::env::args_os()).unwrap_or_else(|err| err.exit())
 }
 
 #[cfg(all(
@@ -430,4 +632,97 @@ mod tests {
     fn parse_timeout_gives_correct_values() {
         assert_eq!(parse_timeout("1525").unwrap(), Duration::from_millis(1525));
     }
+
+    /// pass a config file and a flag it also sets; expect the flag to win, and the rest to come
+    /// from the file
+    #[test]
+    #[cfg(feature = "cli")]
+    fn config_file_is_merged_with_flags() {
+        let dir = std::env::temp_dir().join(format!("libafl_cli_config_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("campaign.toml");
+        fs::write(
+            &toml_path,
+            "cores = \"1-3\"\nbroker-port = 1400\ntimeout = 250\ninput = [\"seeds\"]\n\
+             schedule = \"explore\"\ndisabled-stages = [\"cmplog\"]\nmonitor = \"tui\"\n",
+        )
+        .unwrap();
+
+        let parsed = FuzzerOptions::try_parse_with_config_from([
+            "some-command",
+            "--broker-port",
+            "1336",
+            "--config",
+            toml_path.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(parsed.broker_port, 1336);
+        assert_eq!(parsed.cores, Cores::from_cmdline("1-3").unwrap());
+        assert_eq!(parsed.timeout, Duration::from_millis(250));
+        assert_eq!(parsed.input, [PathBuf::from("seeds")]);
+        assert_eq!(parsed.schedule, Some(Schedule::Explore));
+        assert!(!parsed.stage_enabled("cmplog"));
+        assert!(parsed.stage_enabled("trim"));
+        assert_eq!(parsed.monitor, Some(MonitorKind::Tui));
+
+        let parsed = FuzzerOptions::try_parse_with_config_from([
+            "some-command",
+            "--schedule",
+            "fast",
+            "--disabled-stages",
+            "trim,calibration",
+            "--config",
+            toml_path.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(parsed.schedule, Some(Schedule::Fast));
+        assert!(parsed.stage_enabled("cmplog"));
+        assert!(!parsed.stage_enabled("trim"));
+        assert!(!parsed.stage_enabled("calibration"));
+        assert_eq!(parsed.monitor, Some(MonitorKind::Tui));
+
+        fs::write(&toml_path, "no-such-option = 1\n").unwrap();
+        assert!(FuzzerOptions::try_parse_with_config_from([
+            "some-command",
+            "--config",
+            toml_path.to_str().unwrap()
+        ])
+        .is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// pass a yaml config file; expect it to be read like a toml one
+    #[test]
+    #[cfg(feature = "cli_yaml")]
+    fn yaml_config_file() {
+        let dir = std::env::temp_dir().join(format!("libafl_cli_yaml_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let yaml_path = dir.join("campaign.yaml");
+        fs::write(
+            &yaml_path,
+            "cores: 1-3\ntimeout: 250\ninput:\n  - seeds\nschedule: coe\n\
+             disabled-stages:\n  - cmplog\nmonitor: multi\n",
+        )
+        .unwrap();
+
+        let parsed = FuzzerOptions::try_parse_with_config_from([
+            "some-command",
+            "--timeout",
+            "500",
+            "--config",
+            yaml_path.to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(parsed.cores, Cores::from_cmdline("1-3").unwrap());
+        assert_eq!(parsed.timeout, Duration::from_millis(500));
+        assert_eq!(parsed.input, [PathBuf::from("seeds")]);
+        assert_eq!(parsed.schedule, Some(Schedule::Coe));
+        assert!(!parsed.stage_enabled("cmplog"));
+        assert_eq!(parsed.monitor, Some(MonitorKind::Multi));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -1,23 +1,28 @@
 //! Compression of events passed between a broker and clients.
-//! Currently we use the gzip compression algorithm for its fast decompression performance.
+//! By default, we use the gzip compression algorithm for its fast decompression performance.
+//! With the `llmp_lz4` and `llmp_zstd` features, llmp links may negotiate a [`CompressionAlgorithm`] instead.
 
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
+#[cfg(feature = "gzip")]
 use miniz_oxide::{
     deflate::{compress_to_vec, CompressionLevel},
-    inflate::decompress_to_vec,
+    inflate::{decompress_to_vec, decompress_to_vec_with_limit},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::Error;
 
 /// Compression for your stream compression needs.
+#[cfg(feature = "gzip")]
 #[derive(Debug)]
 pub struct GzipCompressor {
     /// If less bytes than threshold are being passed to `compress`, the payload is not getting compressed.
     threshold: usize,
 }
 
+#[cfg(feature = "gzip")]
 impl GzipCompressor {
     /// If the buffer is at least larger as large as the `threshold` value, we compress the buffer.
     /// When given a `threshold` of `0`, the `GzipCompressor` will always compress.
@@ -33,12 +38,14 @@ impl GzipCompressor {
     }
 }
 
+#[cfg(feature = "gzip")]
 impl Default for GzipCompressor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "gzip")]
 impl GzipCompressor {
     /// Compression.
     /// If the buffer is smaller than the threshold of this compressor, `None` will be returned.
@@ -70,11 +77,119 @@ impl GzipCompressor {
             Err(_) => Err(Error::compression()),
         }
     }
+
+    /// Decompression, failing if the decompressed buffer would be larger than `max_len` bytes.
+    #[allow(clippy::unused_self)]
+    pub fn decompress_with_limit(&self, buf: &[u8], max_len: usize) -> Result<Vec<u8>, Error> {
+        decompress_to_vec_with_limit(buf, max_len).map_err(|_| Error::compression())
+    }
+}
+
+/// A compression algorithm, negotiated per connection.
+///
+/// All variants exist regardless of the enabled features, so that peers built with different features
+/// still agree on the wire format. Use [`CompressionAlgorithm::is_available`] to check for support.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompressionAlgorithm {
+    /// Gzip, always available
+    Gzip,
+    /// LZ4, very fast at a lower ratio. Needs the `llmp_lz4` feature.
+    Lz4,
+    /// Zstandard, a good ratio at reasonable speed. Needs the `llmp_zstd` feature.
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// All algorithms supported by this build, the most preferred one first
+    #[must_use]
+    pub fn available() -> Vec<Self> {
+        [Self::Zstd, Self::Lz4, Self::Gzip]
+            .into_iter()
+            .filter(|algorithm| algorithm.is_available())
+            .collect()
+    }
+
+    /// Whether this algorithm was compiled into this build
+    #[must_use]
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Gzip => cfg!(feature = "gzip"),
+            Self::Lz4 => cfg!(feature = "llmp_lz4"),
+            Self::Zstd => cfg!(feature = "llmp_zstd"),
+        }
+    }
+
+    /// Compresses the buffer using this algorithm.
+    /// Fails if the algorithm is not available in this build.
+    #[cfg_attr(not(feature = "gzip"), allow(unused_variables))]
+    pub fn compress(self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(GzipCompressor::new().compress(buf)),
+            #[cfg(feature = "llmp_lz4")]
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(buf)),
+            #[cfg(feature = "llmp_zstd")]
+            Self::Zstd => zstd::bulk::compress(buf, 0).map_err(|_| Error::compression()),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::illegal_argument(format!(
+                "Compression algorithm {self:?} is not available in this build"
+            ))),
+        }
+    }
+
+    /// Decompresses a buffer previously compressed using this algorithm.
+    ///
+    /// The buffer usually comes from a remote peer, so decompression fails before allocating
+    /// more than `max_len` bytes, instead of trusting the sizes in the buffer.
+    /// Fails if the algorithm is not available in this build.
+    #[cfg_attr(not(feature = "gzip"), allow(unused_variables))]
+    pub fn decompress(self, buf: &[u8], max_len: usize) -> Result<Vec<u8>, Error> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => GzipCompressor::new().decompress_with_limit(buf, max_len),
+            #[cfg(feature = "llmp_lz4")]
+            Self::Lz4 => {
+                // `compress_prepend_size` prepends the decompressed size as little-endian u32
+                if buf.len() < 4 {
+                    return Err(Error::compression());
+                }
+                let (size, compressed) = buf.split_at(4);
+                let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+                if size > max_len {
+                    return Err(Error::illegal_argument(format!(
+                        "Decompressed size {size} exceeds the maximum of {max_len} bytes"
+                    )));
+                }
+                lz4_flex::decompress(compressed, size).map_err(|_| Error::compression())
+            }
+            #[cfg(feature = "llmp_zstd")]
+            Self::Zstd => {
+                use std::io::Read;
+
+                let mut decompressed = vec![];
+                zstd::stream::read::Decoder::new(buf)
+                    .map_err(|_| Error::compression())?
+                    .take(max_len as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .map_err(|_| Error::compression())?;
+                if decompressed.len() > max_len {
+                    return Err(Error::illegal_argument(format!(
+                        "Decompressed size exceeds the maximum of {max_len} bytes"
+                    )));
+                }
+                Ok(decompressed)
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::illegal_argument(format!(
+                "Compression algorithm {self:?} is not available in this build"
+            ))),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::compress::GzipCompressor;
+    use crate::compress::{CompressionAlgorithm, GzipCompressor};
 
     #[test]
     fn test_compression() {
@@ -93,4 +208,16 @@ mod tests {
         assert!(compressor.maybe_compress(&[1u8; 1023]).is_none());
         assert!(compressor.maybe_compress(&[1u8; 1024]).is_some());
     }
+
+    #[test]
+    fn test_algorithms_roundtrip() {
+        let buf = [7u8; 4096];
+        for algorithm in CompressionAlgorithm::available() {
+            let compressed = algorithm.compress(&buf).unwrap();
+            assert!(compressed.len() < buf.len());
+            assert_eq!(algorithm.decompress(&compressed, buf.len()).unwrap(), buf);
+            // a peer may not make us allocate more than we allow
+            assert!(algorithm.decompress(&compressed, buf.len() - 1).is_err());
+        }
+    }
 }
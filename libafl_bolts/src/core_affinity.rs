@@ -72,6 +72,83 @@ impl CoreId {
     pub fn set_affinity_forced(&self) -> Result<(), Error> {
         set_for_current_helper(*self)
     }
+
+    /// The id of the NUMA node this core is attached to, see [`get_numa_nodes`]
+    #[cfg(feature = "std")]
+    pub fn numa_node(&self) -> Result<Option<usize>, Error> {
+        Ok(get_numa_nodes()?
+            .iter()
+            .find(|node| node.cores.contains(self))
+            .map(|node| node.id))
+    }
+
+    /// Set the affinity of the current process to this [`CoreId`], and prefer the memory of its NUMA node
+    /// for all allocations from now on, so that shared maps created afterwards are local to this core.
+    /// The memory policy is inherited by forked and `exec`ed children.
+    ///
+    /// Like [`CoreId::set_affinity`], this will *_not_* fail if the platform does not support NUMA.
+    #[cfg(feature = "std")]
+    pub fn set_affinity_numa(&self) -> Result<(), Error> {
+        self.set_affinity()?;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(node) = self.numa_node()? {
+            match numa::prefer_node(node) {
+                Ok(()) | Err(Error::Unsupported(_, _)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// The [`CoreKind`] of this core.
+    ///
+    /// On systems where all cores are the same, every core is a [`CoreKind::Performance`] core.
+    pub fn kind(&self) -> Result<CoreKind, Error> {
+        #[cfg(all(target_vendor = "apple", target_arch = "aarch64"))]
+        {
+            apple::core_kind(*self)
+        }
+        #[cfg(not(all(target_vendor = "apple", target_arch = "aarch64")))]
+        {
+            Ok(CoreKind::Performance)
+        }
+    }
+}
+
+/// The kind of a core, on systems mixing fast and slow cores, like Apple Silicon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum CoreKind {
+    /// A fast core
+    Performance,
+    /// A slower, energy efficient core
+    Efficiency,
+}
+
+/// A NUMA node of this system, with the cores attached to it
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct NumaNode {
+    /// The numerical `id` of the node
+    pub id: usize,
+    /// The cores attached to this node, empty for memory-only nodes
+    pub cores: Vec<CoreId>,
+}
+
+/// Retrieve the NUMA topology of this system, sorted by node id.
+/// Only cores we are allowed to run on are reported.
+///
+/// On systems without NUMA, or where the topology cannot be detected,
+/// all cores are reported as part of a single node `0`.
+#[cfg(feature = "std")]
+pub fn get_numa_nodes() -> Result<Vec<NumaNode>, Error> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Some(nodes) = numa::get_numa_nodes()? {
+        return Ok(nodes);
+    }
+    Ok(vec![NumaNode {
+        id: 0,
+        cores: get_core_ids()?,
+    }])
 }
 
 impl From<usize> for CoreId {
@@ -164,6 +241,79 @@ impl Cores {
         self.ids.contains(&core_id)
     }
 
+    /// Pick `count` of these cores, taking one from each NUMA node in turn,
+    /// so that the clients are spread evenly across all nodes.
+    pub fn spread_across_numa_nodes(&self, count: usize) -> Result<Self, Error> {
+        if count > self.ids.len() {
+            return Err(Error::illegal_argument(format!(
+                "Cannot pick {count} cores out of {} chosen cores",
+                self.ids.len()
+            )));
+        }
+
+        let nodes = get_numa_nodes()?;
+        let mut per_node: Vec<Vec<CoreId>> = vec![vec![]; nodes.len() + 1];
+        for core_id in &self.ids {
+            // Cores that do not show up in the topology get a node of their own
+            let node = nodes
+                .iter()
+                .position(|node| node.cores.contains(core_id))
+                .unwrap_or(nodes.len());
+            per_node[node].push(*core_id);
+        }
+
+        let mut ids = vec![];
+        let mut round = 0;
+        while ids.len() < count {
+            for cores in &per_node {
+                if let Some(core_id) = cores.get(round) {
+                    if ids.len() < count {
+                        ids.push(core_id.0);
+                    }
+                }
+            }
+            round += 1;
+        }
+        Ok(Self::from(ids))
+    }
+
+    /// Only keep the cores attached to one of the given NUMA `nodes`
+    pub fn on_numa_nodes(&self, nodes: &[usize]) -> Result<Self, Error> {
+        let numa_nodes = get_numa_nodes()?;
+        let ids: Vec<usize> = self
+            .ids
+            .iter()
+            .filter(|core_id| {
+                numa_nodes
+                    .iter()
+                    .any(|node| nodes.contains(&node.id) && node.cores.contains(core_id))
+            })
+            .map(|core_id| core_id.0)
+            .collect();
+        if ids.is_empty() {
+            return Err(Error::illegal_argument(format!(
+                "None of the chosen cores is attached to NUMA nodes {nodes:?}"
+            )));
+        }
+        Ok(Self::from(ids))
+    }
+
+    /// Only keep the cores of the given [`CoreKind`], for example to keep all clients on the fast cores
+    pub fn of_kind(&self, kind: CoreKind) -> Result<Self, Error> {
+        let mut ids = vec![];
+        for core_id in &self.ids {
+            if core_id.kind()? == kind {
+                ids.push(core_id.0);
+            }
+        }
+        if ids.is_empty() {
+            return Err(Error::illegal_argument(format!(
+                "None of the chosen cores is a {kind:?} core"
+            )));
+        }
+        Ok(Self::from(ids))
+    }
+
     /// Returns the index/position of the given [`CoreId`] in this cores.ids list.
     /// Will return `None`, if [`CoreId`] wasn't found.
     #[must_use]
@@ -205,42 +355,24 @@ impl TryFrom<&str> for Cores {
 
 // Linux Section
 
-#[cfg(any(
-    target_os = "android",
-    target_os = "linux",
-    target_os = "dragonfly",
-    target_os = "freebsd"
-))]
+#[cfg(any(target_os = "android", target_os = "linux", target_os = "dragonfly"))]
 #[inline]
 fn get_core_ids_helper() -> Result<Vec<CoreId>, Error> {
     linux::get_core_ids()
 }
 
-#[cfg(any(
-    target_os = "android",
-    target_os = "linux",
-    target_os = "dragonfly",
-    target_os = "freebsd"
-))]
+#[cfg(any(target_os = "android", target_os = "linux", target_os = "dragonfly"))]
 #[inline]
 fn set_for_current_helper(core_id: CoreId) -> Result<(), Error> {
     linux::set_for_current(core_id)
 }
 
-#[cfg(any(
-    target_os = "android",
-    target_os = "linux",
-    target_os = "dragonfly",
-    target_os = "freebsd"
-))]
+#[cfg(any(target_os = "android", target_os = "linux", target_os = "dragonfly"))]
 mod linux {
     use alloc::{string::ToString, vec::Vec};
     use core::mem::{size_of, zeroed};
 
-    #[cfg(not(target_os = "freebsd"))]
     use libc::cpu_set_t;
-    #[cfg(target_os = "freebsd")]
-    use libc::cpuset_t as cpu_set_t;
     #[cfg(target_os = "dragonfly")]
     use libc::{sched_getaffinity, sched_setaffinity, CPU_ISSET, CPU_SET};
     #[cfg(not(target_os = "dragonfly"))]
@@ -355,6 +487,186 @@ mod linux {
     }
 }
 
+// NUMA, for Linux
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+mod numa {
+    use alloc::{format, vec, vec::Vec};
+    use std::{fs, io};
+
+    use super::{get_core_ids, Cores, NumaNode};
+    use crate::Error;
+
+    /// Where the kernel lists the NUMA nodes
+    const NODE_DIR: &str = "/sys/devices/system/node";
+    /// The `MPOL_PREFERRED` mode of `set_mempolicy`
+    const MPOL_PREFERRED: libc::c_int = 1;
+    /// The maximum number of nodes we can put into a node mask
+    const MAX_NODES: usize = 1024;
+    const MASK_BITS: usize = libc::c_ulong::BITS as usize;
+
+    /// Read the topology from `sysfs`, or `None` if the kernel does not report any nodes
+    pub fn get_numa_nodes() -> Result<Option<Vec<NumaNode>>, Error> {
+        let Ok(entries) = fs::read_dir(NODE_DIR) else {
+            return Ok(None);
+        };
+        let available = get_core_ids()?;
+
+        let mut nodes = vec![];
+        for entry in entries {
+            let entry = entry?;
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("node"))
+                .and_then(|id| id.parse().ok())
+            else {
+                continue;
+            };
+            let cpulist = fs::read_to_string(entry.path().join("cpulist"))?;
+            let cpulist = cpulist.trim();
+            let cores = if cpulist.is_empty() {
+                vec![]
+            } else {
+                Cores::from_cmdline(cpulist)?
+                    .ids
+                    .into_iter()
+                    .filter(|core_id| available.contains(core_id))
+                    .collect()
+            };
+            nodes.push(NumaNode { id, cores });
+        }
+
+        if nodes.is_empty() {
+            return Ok(None);
+        }
+        nodes.sort_by_key(|node| node.id);
+        Ok(Some(nodes))
+    }
+
+    /// Prefer allocating memory on `node` for the current process
+    pub fn prefer_node(node: usize) -> Result<(), Error> {
+        if node >= MAX_NODES {
+            return Err(Error::illegal_argument(format!(
+                "NUMA node {node} is out of range"
+            )));
+        }
+        let mut mask: [libc::c_ulong; MAX_NODES / MASK_BITS] = [0; MAX_NODES / MASK_BITS];
+        mask[node / MASK_BITS] |= 1 << (node % MASK_BITS);
+
+        // The kernel reads `maxnode - 1` bits of the mask
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_set_mempolicy,
+                MPOL_PREFERRED,
+                mask.as_ptr(),
+                MAX_NODES + 1,
+            )
+        };
+        if result < 0 {
+            let err = io::Error::last_os_error();
+            // Kernels without NUMA support, or sandboxes that forbid it
+            if matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EPERM)) {
+                return Err(Error::unsupported(format!(
+                    "Cannot set the memory policy: {err}"
+                )));
+            }
+            return Err(Error::unknown(format!(
+                "Failed to prefer the memory of NUMA node {node}: {err}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+// FreeBSD Section
+
+#[cfg(target_os = "freebsd")]
+#[inline]
+fn get_core_ids_helper() -> Result<Vec<CoreId>, Error> {
+    freebsd::get_core_ids()
+}
+
+#[cfg(target_os = "freebsd")]
+#[inline]
+fn set_for_current_helper(core_id: CoreId) -> Result<(), Error> {
+    freebsd::set_for_current(core_id)
+}
+
+#[cfg(target_os = "freebsd")]
+mod freebsd {
+    use alloc::vec::Vec;
+    use core::mem::{size_of, zeroed};
+    use std::io;
+
+    use libc::{
+        cpuset_getaffinity, cpuset_setaffinity, cpuset_t, CPU_ISSET, CPU_LEVEL_WHICH, CPU_SET,
+        CPU_SETSIZE, CPU_WHICH_PID,
+    };
+
+    use super::CoreId;
+    use crate::Error;
+
+    /// The cores of the `cpuset` of the current process, which may be restricted by a jail or `cpuset(1)`
+    #[allow(trivial_numeric_casts)]
+    pub fn get_core_ids() -> Result<Vec<CoreId>, Error> {
+        let mut set = unsafe { zeroed::<cpuset_t>() };
+        // The id `-1` is the current process
+        let result = unsafe {
+            cpuset_getaffinity(
+                CPU_LEVEL_WHICH,
+                CPU_WHICH_PID,
+                -1,
+                size_of::<cpuset_t>(),
+                &mut set,
+            )
+        };
+        if result != 0 {
+            return Err(Error::unknown(format!(
+                "Failed to retrieve affinity using cpuset_getaffinity: {}",
+                io::Error::last_os_error()
+            )));
+        }
+
+        Ok((0..CPU_SETSIZE as usize)
+            .filter(|&i| unsafe { CPU_ISSET(i, &set) })
+            .map(CoreId)
+            .collect())
+    }
+
+    /// Bind all threads of the current process to the given core
+    #[allow(trivial_numeric_casts)]
+    pub fn set_for_current(core_id: CoreId) -> Result<(), Error> {
+        if core_id.0 >= CPU_SETSIZE as usize {
+            return Err(Error::illegal_argument(format!(
+                "Core {} is out of range for a cpuset",
+                core_id.0
+            )));
+        }
+        let mut set = unsafe { zeroed::<cpuset_t>() };
+        unsafe { CPU_SET(core_id.0, &mut set) };
+
+        let result = unsafe {
+            cpuset_setaffinity(
+                CPU_LEVEL_WHICH,
+                CPU_WHICH_PID,
+                -1,
+                size_of::<cpuset_t>(),
+                &set,
+            )
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(Error::unknown(format!(
+                "Failed to bind to core {} using cpuset_setaffinity: {}",
+                core_id.0,
+                io::Error::last_os_error()
+            )))
+        }
+    }
+}
+
 // Haiku
 // FIXME: no sense of cpu granularity (yet ?)
 
@@ -595,6 +907,8 @@ mod apple {
     use alloc::vec::Vec;
     #[cfg(target_arch = "x86_64")]
     use core::ptr::addr_of_mut;
+    #[cfg(target_arch = "aarch64")]
+    use core::{ffi::CStr, mem::size_of, ptr};
     use std::thread::available_parallelism;
 
     #[cfg(target_arch = "x86_64")]
@@ -604,9 +918,14 @@ mod apple {
         THREAD_AFFINITY_POLICY_COUNT,
     };
     #[cfg(all(target_arch = "aarch64", not(miri)))]
-    use libc::{pthread_set_qos_class_self_np, qos_class_t::QOS_CLASS_USER_INITIATED};
+    use libc::{
+        pthread_set_qos_class_self_np,
+        qos_class_t::{QOS_CLASS_BACKGROUND, QOS_CLASS_USER_INITIATED},
+    };
 
     use super::CoreId;
+    #[cfg(target_arch = "aarch64")]
+    use super::CoreKind;
     use crate::Error;
 
     #[cfg(target_arch = "x86_64")]
@@ -635,8 +954,9 @@ mod apple {
 
     #[cfg(target_arch = "x86_64")]
     pub fn set_for_current(core_id: CoreId) -> Result<(), Error> {
+        // The tag `0` is `THREAD_AFFINITY_TAG_NULL`, which would remove the affinity hint for core `0`
         let mut info = thread_affinity_policy_data_t {
-            affinity_tag: core_id.0.try_into().unwrap(),
+            affinity_tag: (core_id.0 + 1).try_into().unwrap(),
         };
 
         unsafe {
@@ -658,19 +978,63 @@ mod apple {
         }
     }
 
+    /// Read an integer `sysctl`, or `None` if it does not exist on this system
+    #[cfg(target_arch = "aarch64")]
+    fn sysctl_usize(name: &CStr) -> Option<usize> {
+        let mut val: libc::c_int = 0;
+        let mut size = size_of::<libc::c_int>();
+        let result = unsafe {
+            libc::sysctlbyname(
+                name.as_ptr(),
+                ptr::addr_of_mut!(val).cast(),
+                &mut size,
+                ptr::null_mut(),
+                0,
+            )
+        };
+        if result == 0 {
+            val.try_into().ok()
+        } else {
+            None
+        }
+    }
+
+    /// The kind of the given core.
+    ///
+    /// The kernel numbers the cores by performance level, so the first `hw.perflevel0.logicalcpu`
+    /// cores are the performance cores, followed by the efficiency cores.
     #[cfg(target_arch = "aarch64")]
     #[allow(clippy::unnecessary_wraps)]
-    pub fn set_for_current(_core_id: CoreId) -> Result<(), Error> {
+    pub fn core_kind(core_id: CoreId) -> Result<CoreKind, Error> {
+        if sysctl_usize(CStr::from_bytes_with_nul(b"hw.nperflevels\0").unwrap()).unwrap_or(1) < 2 {
+            return Ok(CoreKind::Performance);
+        }
+        match sysctl_usize(CStr::from_bytes_with_nul(b"hw.perflevel0.logicalcpu\0").unwrap()) {
+            Some(performance_cores) if core_id.0 >= performance_cores => Ok(CoreKind::Efficiency),
+            _ => Ok(CoreKind::Performance),
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    pub fn set_for_current(core_id: CoreId) -> Result<(), Error> {
         // This is the best we can do, unlike on intel architecture
         // the system does not allow to pin a process/thread to specific cpu.
-        // We just tell the system that we want performance.
+        // We tell the system which kind of core we want instead:
+        // The background class confines us to the efficiency cores, the others prefer the performance cores.
         //
         // Furthermore, this seems to fail on background threads, so we ignore errors (result != 0).
+        let kind = core_kind(core_id)?;
 
         #[cfg(not(miri))]
         unsafe {
-            let _result = pthread_set_qos_class_self_np(QOS_CLASS_USER_INITIATED, 0);
+            let qos_class = match kind {
+                CoreKind::Performance => QOS_CLASS_USER_INITIATED,
+                CoreKind::Efficiency => QOS_CLASS_BACKGROUND,
+            };
+            let _result = pthread_set_qos_class_self_np(qos_class, 0);
         }
+        #[cfg(miri)]
+        let _ = kind;
 
         Ok(())
     }
@@ -781,29 +1145,59 @@ fn set_for_current_helper(core_id: CoreId) -> Result<(), Error> {
 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
 mod solaris {
     use alloc::vec::Vec;
-    use std::thread::available_parallelism;
+    use std::{io, thread::available_parallelism};
 
     use super::CoreId;
     use crate::Error;
 
-    #[allow(clippy::unnecessary_wraps)]
+    /// `p_online` flag to query the state of a processor without changing it
+    const P_STATUS: libc::c_int = 3;
+    /// The processor is online
+    const P_ONLINE: libc::c_int = 2;
+    /// The processor is online, but does not handle interrupts
+    const P_NOINTR: libc::c_int = 6;
+
+    extern "C" {
+        fn p_online(processorid: libc::processorid_t, flag: libc::c_int) -> libc::c_int;
+    }
+
+    /// The online processors. Their ids may have gaps, for example after offlining a processor with `psradm(8)`.
     pub fn get_core_ids() -> Result<Vec<CoreId>, Error> {
-        Ok((0..(usize::from(available_parallelism()?)))
+        let max_id = unsafe { libc::sysconf(libc::_SC_CPUID_MAX) };
+        let Ok(max_id) = libc::processorid_t::try_from(max_id) else {
+            return Ok((0..(usize::from(available_parallelism()?)))
+                .map(CoreId)
+                .collect::<Vec<_>>());
+        };
+
+        Ok((0..=max_id)
+            .filter(|&id| matches!(unsafe { p_online(id, P_STATUS) }, P_ONLINE | P_NOINTR))
+            .filter_map(|id| usize::try_from(id).ok())
             .map(CoreId)
-            .collect::<Vec<_>>())
+            .collect())
     }
 
     pub fn set_for_current(core_id: CoreId) -> Result<(), Error> {
+        let Ok(processor_id) = core_id.0.try_into() else {
+            return Err(Error::illegal_argument(format!(
+                "Core {} is out of range for processor_bind",
+                core_id.0
+            )));
+        };
         let result = unsafe {
             libc::processor_bind(
                 libc::P_PID,
                 libc::getpid(),
-                core_id.0.try_into().unwrap(),
+                processor_id,
                 std::ptr::null_mut(),
             )
         };
         if result < 0 {
-            Err(Error::unknown("Failed to processor_bind"))
+            Err(Error::unknown(format!(
+                "Failed to bind to core {} using processor_bind: {}",
+                core_id.0,
+                io::Error::last_os_error()
+            )))
         } else {
             Ok(())
         }
@@ -832,4 +1226,45 @@ mod tests {
 
         ids[0].set_affinity().unwrap();
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_numa_spread() {
+        let nodes = get_numa_nodes().unwrap();
+        assert!(!nodes.is_empty());
+
+        let cores = Cores::all().unwrap();
+        let spread = cores.spread_across_numa_nodes(cores.ids.len()).unwrap();
+        assert_eq!(spread.ids.len(), cores.ids.len());
+        assert!(cores.ids.iter().all(|core_id| spread.contains(*core_id)));
+
+        if let Some(node) = nodes.iter().find(|node| !node.cores.is_empty()) {
+            let on_node = cores.on_numa_nodes(&[node.id]).unwrap();
+            assert!(on_node
+                .ids
+                .iter()
+                .all(|core_id| node.cores.contains(core_id)));
+        }
+
+        cores.ids[0].set_affinity_numa().unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_core_kinds() {
+        let cores = Cores::all().unwrap();
+        // Every system has at least one fast core
+        let performance = cores.of_kind(CoreKind::Performance).unwrap();
+        assert!(performance
+            .ids
+            .iter()
+            .all(|core_id| core_id.kind().unwrap() == CoreKind::Performance));
+
+        if let Ok(efficiency) = cores.of_kind(CoreKind::Efficiency) {
+            assert_eq!(
+                performance.ids.len() + efficiency.ids.len(),
+                cores.ids.len()
+            );
+        }
+    }
 }
@@ -103,6 +103,9 @@ pub extern crate alloc;
 #[cfg(feature = "ctor")]
 #[doc(hidden)]
 pub use ctor::ctor;
+#[cfg(feature = "serdeany_linkme")]
+#[doc(hidden)]
+pub use linkme;
 #[cfg(feature = "alloc")]
 pub mod anymap;
 #[cfg(feature = "std")]
@@ -112,7 +115,7 @@ pub mod build_id;
     feature = "std"
 ))]
 pub mod cli;
-#[cfg(feature = "gzip")]
+#[cfg(feature = "alloc")]
 pub mod compress;
 #[cfg(feature = "std")]
 pub mod core_affinity;
@@ -147,7 +150,7 @@ pub mod bolts_prelude {
         feature = "std"
     ))]
     pub use super::cli::*;
-    #[cfg(feature = "gzip")]
+    #[cfg(feature = "alloc")]
     pub use super::compress::*;
     #[cfg(feature = "std")]
     pub use super::core_affinity::*;
@@ -544,6 +547,14 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+/// Stringify the TLS error
+#[cfg(feature = "llmp_tls")]
+impl From<rustls::Error> for Error {
+    fn from(err: rustls::Error) -> Self {
+        Self::illegal_state(format!("TLS error: {err}"))
+    }
+}
+
 #[cfg(all(unix, feature = "std"))]
 impl From<nix::Error> for Error {
     fn from(err: nix::Error) -> Self {
@@ -60,6 +60,8 @@ Check out the `llmp_test` example in ./examples, or build it with `cargo run --e
 #[cfg(feature = "std")]
 use alloc::string::ToString;
 use alloc::{string::String, vec::Vec};
+#[cfg(feature = "llmp_tls")]
+use core::ops::DerefMut;
 #[cfg(not(target_pointer_width = "64"))]
 use core::sync::atomic::AtomicU32;
 #[cfg(target_pointer_width = "64")]
@@ -75,6 +77,8 @@ use core::{
     sync::atomic::{fence, AtomicU16, Ordering},
     time::Duration,
 };
+#[cfg(feature = "llmp_tls")]
+use std::sync::{mpsc::Receiver, Arc};
 #[cfg(feature = "std")]
 use std::{
     boxed::Box,
@@ -87,6 +91,8 @@ use std::{
 
 #[cfg(all(debug_assertions, feature = "llmp_debug", feature = "std"))]
 use backtrace::Backtrace;
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use hashbrown::HashMap;
 #[cfg(all(unix, feature = "std"))]
 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
 use nix::sys::socket::{self, sockopt::ReusePort};
@@ -94,6 +100,21 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use tuple_list::tuple_list;
 
+#[cfg(feature = "llmp_auth")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "llmp_tls")]
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms},
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    server::danger::{ClientCertVerified, ClientCertVerifier},
+    CertificateError, ClientConfig, ClientConnection, ConnectionCommon, DigitallySignedStruct,
+    DistinguishedName, ServerConfig, ServerConnection, SideData, SignatureScheme, StreamOwned,
+};
+#[cfg(feature = "llmp_auth")]
+use sha2::Sha256;
+
+use crate::compress::CompressionAlgorithm;
 #[cfg(all(unix, not(miri)))]
 use crate::os::unix_signals::setup_signal_handler;
 #[cfg(unix)]
@@ -143,11 +164,20 @@ pub const LLMP_FLAG_INITIALIZED: Flags = Flags(0x0);
 pub const LLMP_FLAG_COMPRESSED: Flags = Flags(0x1);
 /// From another broker.
 pub const LLMP_FLAG_FROM_B2B: Flags = Flags(0x2);
+/// The payload was serialized with `bincode`, instead of the default `postcard`
+pub const LLMP_FLAG_BINCODE: Flags = Flags(0x4);
+/// The payload was serialized as JSON, instead of the default `postcard`
+pub const LLMP_FLAG_JSON: Flags = Flags(0x8);
 
 /// Timt the broker 2 broker connection waits for incoming data,
 /// before checking for own data to forward again.
 const _LLMP_B2B_BLOCK_TIME: Duration = Duration::from_millis(3_000);
 
+/// Time a peer gets to complete the TLS handshake, before the listener gives up on it
+/// and accepts the next connection.
+#[cfg(feature = "llmp_tls")]
+const LLMP_TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// If broker2broker is enabled, bind to public IP
 #[cfg(feature = "llmp_bind_public")]
 const _LLMP_BIND_ADDR: &str = "0.0.0.0";
@@ -209,6 +239,12 @@ impl Debug for Flags {
         if *self & LLMP_FLAG_FROM_B2B == LLMP_FLAG_FROM_B2B {
             f.write_str("FROM_B2B")?;
         }
+        if *self & LLMP_FLAG_BINCODE == LLMP_FLAG_BINCODE {
+            f.write_str("BINCODE")?;
+        }
+        if *self & LLMP_FLAG_JSON == LLMP_FLAG_JSON {
+            f.write_str("JSON")?;
+        }
         f.write_str(" )")
     }
 }
@@ -248,6 +284,29 @@ pub struct MessageId(u64);
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct MessageId(u32);
 
+/// How many zero bytes to append to tcp messages that end early, see [`from_tcp_bytes`].
+/// Enough for the longest field appended since, the auth nonce of [`TcpResponse::BrokerConnectHello`].
+const LLMP_TCP_COMPAT_PADDING: usize = LLMP_AUTH_NONCE_LEN;
+
+/// Deserializes a tcp message, tolerating messages sent by peers of older versions.
+///
+/// New fields are only ever appended to the end of tcp messages: `postcard` ignores trailing bytes,
+/// so older peers simply skip them. Messages of older peers lack them, so they are read as if all of them were zero,
+/// i.e., `None`, empty, or all zeroes, the same as a peer built without the feature the field belongs to.
+fn from_tcp_bytes<T>(bytes: &[u8]) -> Result<T, Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    match postcard::from_bytes(bytes) {
+        Err(postcard::Error::DeserializeUnexpectedEnd) => {
+            let mut padded = bytes.to_vec();
+            padded.resize(bytes.len() + LLMP_TCP_COMPAT_PADDING, 0);
+            Ok(postcard::from_bytes(&padded)?)
+        }
+        res => Ok(res?),
+    }
+}
+
 /// This is for the server the broker will spawn.
 /// If an llmp connection is local - use sharedmaps
 /// or remote (broker2broker) - forwarded via tcp
@@ -262,6 +321,9 @@ pub enum TcpRequest {
     RemoteBrokerHello {
         /// The hostname of our broker, trying to connect.
         hostname: String,
+        /// The compression algorithms we support for this link, the most preferred one first.
+        /// Always on the wire, empty if we were built without `llmp_compression`.
+        compression: Vec<CompressionAlgorithm>,
     },
     /// Notify the broker the the othe side is dying so remove this client
     /// `client_id` is the pid of the very initial client
@@ -269,13 +331,21 @@ pub enum TcpRequest {
         /// Tell the broker that remove the client with this `client_id`. `client_id` is equal to the one of event restarter
         client_id: ClientId,
     },
+    /// Another request, authenticated with a pre-shared key, see `LlmpAuth`.
+    /// Always on the wire, rejected by brokers built without `llmp_auth`.
+    Authenticated {
+        /// The serialized inner request
+        request: Vec<u8>,
+        /// The HMAC over the nonce of the broker hello and the inner request
+        mac: Vec<u8>,
+    },
 }
 
 impl TryFrom<&Vec<u8>> for TcpRequest {
     type Error = Error;
 
     fn try_from(bytes: &Vec<u8>) -> Result<Self, Error> {
-        Ok(postcard::from_bytes(bytes)?)
+        from_tcp_bytes(bytes)
     }
 }
 
@@ -283,7 +353,7 @@ impl TryFrom<Vec<u8>> for TcpRequest {
     type Error = Error;
 
     fn try_from(bytes: Vec<u8>) -> Result<Self, Error> {
-        Ok(postcard::from_bytes(&bytes)?)
+        from_tcp_bytes(&bytes)
     }
 }
 
@@ -298,13 +368,15 @@ pub struct TcpRemoteNewMessage {
     flags: Flags,
     // The actual content of the message
     payload: Vec<u8>,
+    // The algorithm the payload was compressed with for this link, if any
+    compression: Option<CompressionAlgorithm>,
 }
 
 impl TryFrom<&Vec<u8>> for TcpRemoteNewMessage {
     type Error = Error;
 
     fn try_from(bytes: &Vec<u8>) -> Result<Self, Error> {
-        Ok(postcard::from_bytes(bytes)?)
+        from_tcp_bytes(bytes)
     }
 }
 
@@ -312,7 +384,7 @@ impl TryFrom<Vec<u8>> for TcpRemoteNewMessage {
     type Error = Error;
 
     fn try_from(bytes: Vec<u8>) -> Result<Self, Error> {
-        Ok(postcard::from_bytes(&bytes)?)
+        from_tcp_bytes(&bytes)
     }
 }
 
@@ -325,6 +397,9 @@ pub enum TcpResponse {
         broker_shmem_description: ShMemDescription,
         /// This broker's hostname
         hostname: String,
+        /// A fresh nonce for this connection, to authenticate the following request with.
+        /// Always on the wire, all zeroes if the broker was built without `llmp_auth`.
+        nonce: [u8; LLMP_AUTH_NONCE_LEN],
     },
     /// Notify the client on the other side that it has been accepted.
     LocalClientAccepted {
@@ -336,6 +411,8 @@ pub enum TcpResponse {
     RemoteBrokerAccepted {
         /// The broker id of this element
         broker_id: BrokerId,
+        /// The compression algorithm picked for this link, if any.
+        compression: Option<CompressionAlgorithm>,
     },
     /// Something went wrong when processing the request.
     Error {
@@ -348,7 +425,7 @@ impl TryFrom<&Vec<u8>> for TcpResponse {
     type Error = Error;
 
     fn try_from(bytes: &Vec<u8>) -> Result<Self, Error> {
-        Ok(postcard::from_bytes(bytes)?)
+        from_tcp_bytes(bytes)
     }
 }
 
@@ -356,7 +433,7 @@ impl TryFrom<Vec<u8>> for TcpResponse {
     type Error = Error;
 
     fn try_from(bytes: Vec<u8>) -> Result<Self, Error> {
-        Ok(postcard::from_bytes(&bytes)?)
+        from_tcp_bytes(&bytes)
     }
 }
 
@@ -366,6 +443,9 @@ impl TryFrom<Vec<u8>> for TcpResponse {
 pub enum Listener {
     /// Listener listening on `tcp`.
     Tcp(TcpListener),
+    /// Listener listening on `tcp`, encrypting all connections with TLS.
+    #[cfg(feature = "llmp_tls")]
+    Tls(TcpListener, LlmpTlsConfig),
 }
 
 /// A listener stream abstraction
@@ -374,21 +454,616 @@ pub enum Listener {
 pub enum ListenerStream {
     /// Listener listening on `tcp`.
     Tcp(TcpStream, SocketAddr),
+    /// Listener listening on `tcp`, with TLS.
+    #[cfg(feature = "llmp_tls")]
+    Tls(Box<StreamOwned<ServerConnection, TcpStream>>, SocketAddr),
     /// No listener provided.
     Empty(),
 }
 
 #[cfg(feature = "std")]
 impl Listener {
+    /// Starts accepting connections on this listener.
+    /// For TLS, each handshake runs on its own thread, so a slow peer never holds up the others.
+    fn into_acceptor(self) -> Acceptor {
+        match self {
+            Listener::Tcp(inner) => Acceptor::Tcp(inner),
+            #[cfg(feature = "llmp_tls")]
+            Listener::Tls(inner, tls_config) => {
+                let (send, recv) = channel();
+                thread::spawn(move || loop {
+                    let (stream, addr) = match inner.accept() {
+                        Ok(res) => res,
+                        Err(err) => {
+                            log::warn!("Ignoring failed accept: {err:?}");
+                            continue;
+                        }
+                    };
+                    let tls_config = tls_config.clone();
+                    let send = send.clone();
+                    thread::spawn(move || match tls_config.accept(stream) {
+                        Ok(stream) => {
+                            // The receiver only goes away together with the listener thread
+                            drop(send.send((Box::new(stream), addr)));
+                        }
+                        Err(err) => {
+                            log::warn!("Ignoring failed TLS handshake with {addr}: {err:?}");
+                        }
+                    });
+                });
+                Acceptor::Tls(recv)
+            }
+        }
+    }
+}
+
+/// The accepting end of a [`Listener`], used by the listener thread of a broker
+#[cfg(feature = "std")]
+#[derive(Debug)]
+enum Acceptor {
+    /// Accepts plain `tcp` connections
+    Tcp(TcpListener),
+    /// Receives connections that completed their TLS handshake
+    #[cfg(feature = "llmp_tls")]
+    Tls(Receiver<(Box<StreamOwned<ServerConnection, TcpStream>>, SocketAddr)>),
+}
+
+#[cfg(feature = "std")]
+impl Acceptor {
     fn accept(&self) -> ListenerStream {
         match self {
-            Listener::Tcp(inner) => match inner.accept() {
+            Acceptor::Tcp(inner) => match inner.accept() {
                 Ok(res) => ListenerStream::Tcp(res.0, res.1),
                 Err(err) => {
                     log::warn!("Ignoring failed accept: {err:?}");
                     ListenerStream::Empty()
                 }
             },
+            #[cfg(feature = "llmp_tls")]
+            Acceptor::Tls(incoming) => {
+                let (stream, addr) = incoming
+                    .recv()
+                    .expect("The TLS accept thread of the listener exited");
+                ListenerStream::Tls(stream, addr)
+            }
+        }
+    }
+}
+
+/// A connected llmp tcp stream, optionally encrypted with TLS
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum LlmpStream {
+    /// A plain tcp stream
+    Tcp(TcpStream),
+    /// A TLS stream, accepted by a [`Listener::Tls`]
+    #[cfg(feature = "llmp_tls")]
+    TlsServer(Box<StreamOwned<ServerConnection, TcpStream>>),
+    /// A TLS stream, connected to a [`Listener::Tls`]
+    #[cfg(feature = "llmp_tls")]
+    TlsClient(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+#[cfg(feature = "std")]
+impl LlmpStream {
+    /// The underlying [`TcpStream`]
+    #[must_use]
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            Self::Tcp(stream) => stream,
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsServer(stream) => &stream.sock,
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsClient(stream) => &stream.sock,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Read for LlmpStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsServer(stream) => stream.read(buf),
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsClient(stream) => stream.read(buf),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for LlmpStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsServer(stream) => stream.write(buf),
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsClient(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsServer(stream) => stream.flush(),
+            #[cfg(feature = "llmp_tls")]
+            Self::TlsClient(stream) => stream.flush(),
+        }
+    }
+}
+
+/// The compression settings for broker-to-broker connections.
+///
+/// When two brokers connect, the connecting broker offers its algorithms,
+/// and the accepting broker picks the first one it supports, too.
+/// Both sides then compress the payloads they forward over this link, if they are large enough.
+/// Messages already compressed by the sender (see [`LLMP_FLAG_COMPRESSED`]) are forwarded as-is.
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+#[derive(Debug, Clone)]
+pub struct LlmpCompressionConfig {
+    /// The algorithms to offer or accept, the most preferred one first
+    algorithms: Vec<CompressionAlgorithm>,
+    /// Payloads at least this large get compressed, unless their tag has its own threshold
+    threshold: usize,
+    /// Thresholds for individual message tags
+    tag_thresholds: HashMap<Tag, usize>,
+    /// The largest payload a remote broker may make us decompress
+    max_decompressed_len: usize,
+}
+
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+impl Default for LlmpCompressionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+impl LlmpCompressionConfig {
+    /// The default threshold, in bytes, at which payloads get compressed
+    pub const DEFAULT_THRESHOLD: usize = 1024;
+
+    /// The default maximum size, in bytes, of a decompressed payload
+    pub const DEFAULT_MAX_DECOMPRESSED_LEN: usize = 1 << 30;
+
+    /// Creates a new [`LlmpCompressionConfig`] offering all algorithms available in this build,
+    /// compressing payloads of at least [`Self::DEFAULT_THRESHOLD`] bytes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            algorithms: CompressionAlgorithm::available(),
+            threshold: Self::DEFAULT_THRESHOLD,
+            tag_thresholds: HashMap::new(),
+            max_decompressed_len: Self::DEFAULT_MAX_DECOMPRESSED_LEN,
+        }
+    }
+
+    /// Creates a new [`LlmpCompressionConfig`] that never compresses b2b traffic
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            algorithms: vec![],
+            ..Self::new()
+        }
+    }
+
+    /// Only offers or accepts the given algorithms, the most preferred one first.
+    /// Algorithms not available in this build are ignored.
+    #[must_use]
+    pub fn with_algorithms(mut self, algorithms: Vec<CompressionAlgorithm>) -> Self {
+        self.algorithms = algorithms
+            .into_iter()
+            .filter(|algorithm| algorithm.is_available())
+            .collect();
+        self
+    }
+
+    /// Compresses payloads of at least `threshold` bytes
+    #[must_use]
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Compresses payloads with the given `tag` only if they are at least `threshold` bytes large.
+    /// Use [`usize::MAX`] to never compress messages with this tag.
+    #[must_use]
+    pub fn with_tag_threshold(mut self, tag: Tag, threshold: usize) -> Self {
+        self.tag_thresholds.insert(tag, threshold);
+        self
+    }
+
+    /// Drops compressed payloads from remote brokers that would decompress to more than `max_len` bytes,
+    /// before allocating memory for them. This should be at least as large as the largest message sent by any client.
+    #[must_use]
+    pub fn with_max_decompressed_len(mut self, max_len: usize) -> Self {
+        self.max_decompressed_len = max_len;
+        self
+    }
+
+    /// The largest payload a remote broker may make us decompress
+    #[must_use]
+    pub fn max_decompressed_len(&self) -> usize {
+        self.max_decompressed_len
+    }
+
+    /// The algorithms offered or accepted by this config
+    #[must_use]
+    pub fn algorithms(&self) -> &[CompressionAlgorithm] {
+        &self.algorithms
+    }
+
+    /// The threshold for messages with the given `tag`
+    #[must_use]
+    pub fn threshold_for(&self, tag: Tag) -> usize {
+        self.tag_thresholds
+            .get(&tag)
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+
+    /// Picks the first of the `offered` algorithms we also accept, if any
+    #[must_use]
+    pub fn negotiate(&self, offered: &[CompressionAlgorithm]) -> Option<CompressionAlgorithm> {
+        offered
+            .iter()
+            .copied()
+            .find(|algorithm| self.algorithms.contains(algorithm))
+    }
+
+    /// Compresses a `payload` about to be forwarded over a link using `algorithm`, if it's worth it.
+    /// Returns `None` if the payload should be sent as-is.
+    fn maybe_compress(
+        &self,
+        algorithm: CompressionAlgorithm,
+        tag: Tag,
+        flags: Flags,
+        payload: &[u8],
+    ) -> Option<Vec<u8>> {
+        if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED
+            || payload.len() < self.threshold_for(tag)
+        {
+            return None;
+        }
+        match algorithm.compress(payload) {
+            Ok(compressed) if compressed.len() < payload.len() => Some(compressed),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("B2B: Failed to compress payload with {algorithm:?}: {e}");
+                None
+            }
+        }
+    }
+}
+
+/// The TLS configuration for broker-to-broker connections.
+///
+/// Each broker presents its own certificate, and only accepts peers presenting one of the pinned certificates,
+/// in both directions (mutual TLS). No certificate authorities or host names are involved,
+/// so self-signed certificates, distributed to all machines of a campaign, work best.
+#[cfg(feature = "llmp_tls")]
+#[derive(Debug, Clone)]
+pub struct LlmpTlsConfig {
+    server: Arc<ServerConfig>,
+    client: Arc<ClientConfig>,
+}
+
+#[cfg(feature = "llmp_tls")]
+impl LlmpTlsConfig {
+    /// Creates a new [`LlmpTlsConfig`] from our own DER-encoded certificate chain and private key,
+    /// and the DER-encoded certificates of the peers we trust.
+    pub fn new(
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+        pinned_certs: Vec<CertificateDer<'static>>,
+    ) -> Result<Self, Error> {
+        if pinned_certs.is_empty() {
+            return Err(Error::illegal_argument(
+                "At least one certificate needs to be pinned for llmp TLS",
+            ));
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier = Arc::new(PinnedCertVerifier {
+            pinned_certs,
+            algorithms: provider.signature_verification_algorithms,
+        });
+
+        let server = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .with_client_cert_verifier(verifier.clone())
+            .with_single_cert(cert_chain.clone(), key.clone_key())?;
+        let client = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(verifier)
+            .with_client_auth_cert(cert_chain, key)?;
+
+        Ok(Self {
+            server: Arc::new(server),
+            client: Arc::new(client),
+        })
+    }
+
+    /// Creates a new [`LlmpTlsConfig`] from DER-encoded files, see [`LlmpTlsConfig::new`].
+    pub fn from_der_files<P, IT>(
+        cert_file: P,
+        key_file: P,
+        pinned_cert_files: IT,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<std::path::Path>,
+        IT: IntoIterator<Item = P>,
+    {
+        let cert = CertificateDer::from(std::fs::read(cert_file)?);
+        let key = PrivateKeyDer::try_from(std::fs::read(key_file)?)
+            .map_err(|err| Error::illegal_argument(format!("Invalid private key: {err}")))?;
+        let pinned_certs = pinned_cert_files
+            .into_iter()
+            .map(|file| Ok(CertificateDer::from(std::fs::read(file)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Self::new(vec![cert], key, pinned_certs)
+    }
+
+    /// Performs the TLS handshake for an accepted `stream`.
+    ///
+    /// The listener runs every handshake on its own thread, which gives up after [`LLMP_TLS_HANDSHAKE_TIMEOUT`].
+    fn accept(&self, stream: TcpStream) -> Result<StreamOwned<ServerConnection, TcpStream>, Error> {
+        let conn = ServerConnection::new(self.server.clone())?;
+        Self::handshake(conn, stream)
+    }
+
+    /// Performs the TLS handshake for a `stream` connected to a remote broker
+    fn connect(
+        &self,
+        stream: TcpStream,
+    ) -> Result<StreamOwned<ClientConnection, TcpStream>, Error> {
+        // The certificates are pinned, the name is never checked
+        let name = ServerName::try_from("llmp-broker")
+            .map_err(|err| Error::illegal_argument(format!("Invalid server name: {err}")))?;
+        let conn = ClientConnection::new(self.client.clone(), name)?;
+        Self::handshake(conn, stream)
+    }
+
+    /// Completes the handshake of `conn` on `stream`, failing if it takes longer than [`LLMP_TLS_HANDSHAKE_TIMEOUT`]
+    fn handshake<C, D>(
+        mut conn: C,
+        mut stream: TcpStream,
+    ) -> Result<StreamOwned<C, TcpStream>, Error>
+    where
+        C: DerefMut<Target = ConnectionCommon<D>>,
+        D: SideData,
+    {
+        let deadline = current_time() + LLMP_TLS_HANDSHAKE_TIMEOUT;
+        let (read_timeout, write_timeout) = (stream.read_timeout()?, stream.write_timeout()?);
+        while conn.is_handshaking() {
+            let remaining = deadline
+                .checked_sub(current_time())
+                .filter(|remaining| !remaining.is_zero())
+                .ok_or_else(|| Error::illegal_state("TLS handshake timed out"))?;
+            stream.set_read_timeout(Some(remaining))?;
+            stream.set_write_timeout(Some(remaining))?;
+            conn.complete_io(&mut stream)?;
+        }
+        stream.set_read_timeout(read_timeout)?;
+        stream.set_write_timeout(write_timeout)?;
+        Ok(StreamOwned::new(conn, stream))
+    }
+}
+
+/// Accepts exactly the pinned certificates, on both sides of a TLS connection
+#[cfg(feature = "llmp_tls")]
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pinned_certs: Vec<CertificateDer<'static>>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+#[cfg(feature = "llmp_tls")]
+impl PinnedCertVerifier {
+    fn check_pinned(&self, end_entity: &CertificateDer<'_>) -> Result<(), rustls::Error> {
+        if self
+            .pinned_certs
+            .iter()
+            .any(|pinned| pinned.as_ref() == end_entity.as_ref())
+        {
+            Ok(())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "llmp_tls")]
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.check_pinned(end_entity)?;
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(feature = "llmp_tls")]
+impl ClientCertVerifier for PinnedCertVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.check_pinned(end_entity)?;
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// The env var [`LlmpAuth::from_env`] reads the pre-shared key for llmp tcp connections from
+#[cfg(feature = "llmp_auth")]
+pub const LLMP_AUTH_KEY_ENV: &str = "LIBAFL_LLMP_AUTH_KEY";
+
+/// The length of the nonce a broker sends to each new tcp connection
+pub const LLMP_AUTH_NONCE_LEN: usize = 32;
+
+#[cfg(feature = "llmp_auth")]
+type HmacSha256 = Hmac<Sha256>;
+
+/// A pre-shared key, authenticating clients and brokers that connect to a broker over tcp.
+///
+/// The broker greets each new connection with a fresh random nonce.
+/// The peer then wraps its request in a [`TcpRequest::Authenticated`],
+/// together with an HMAC-SHA256 over the nonce and the request, keyed with the pre-shared key.
+/// A broker with a key rejects all other requests, so nobody without the key can join a campaign
+/// and inject inputs or stats. The connections themselves are not encrypted, use `llmp_tls` for that.
+#[cfg(feature = "llmp_auth")]
+#[derive(Clone)]
+pub struct LlmpAuth {
+    key: Vec<u8>,
+}
+
+#[cfg(feature = "llmp_auth")]
+impl Debug for LlmpAuth {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // Never leak the key into logs
+        f.debug_struct("LlmpAuth").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "llmp_auth")]
+impl LlmpAuth {
+    /// Creates a new [`LlmpAuth`] from a pre-shared key, which may not be empty.
+    pub fn new<K>(key: K) -> Result<Self, Error>
+    where
+        K: Into<Vec<u8>>,
+    {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(Error::illegal_argument(
+                "The llmp auth key may not be empty",
+            ));
+        }
+        Ok(Self { key })
+    }
+
+    /// Reads the pre-shared key from the [`LLMP_AUTH_KEY_ENV`] env var, if it is set and not empty.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        env::var(LLMP_AUTH_KEY_ENV)
+            .ok()
+            .and_then(|key| Self::new(key).ok())
+    }
+
+    /// Exports the key to the [`LLMP_AUTH_KEY_ENV`] env var, so that brokers and clients created from now on,
+    /// including the ones in spawned or forked child processes, use it, too.
+    /// Only keys that are valid utf-8 can be exported.
+    pub fn export_to_env(&self) -> Result<(), Error> {
+        let key = core::str::from_utf8(&self.key).map_err(|_| {
+            Error::illegal_argument("Only utf-8 llmp auth keys can be exported to the env")
+        })?;
+        env::set_var(LLMP_AUTH_KEY_ENV, key);
+        Ok(())
+    }
+
+    /// Generates a fresh random nonce for a new connection
+    fn new_nonce() -> Result<[u8; LLMP_AUTH_NONCE_LEN], Error> {
+        let mut nonce = [0; LLMP_AUTH_NONCE_LEN];
+        getrandom::getrandom(&mut nonce)
+            .map_err(|e| Error::unknown(format!("Could not generate an llmp auth nonce: {e}")))?;
+        Ok(nonce)
+    }
+
+    fn mac(&self, nonce: &[u8], request: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(nonce);
+        mac.update(request);
+        mac
+    }
+
+    /// Wraps the `request` into a [`TcpRequest::Authenticated`], in response to the broker's `hello`.
+    pub fn authenticate(
+        &self,
+        hello: &TcpResponse,
+        request: &TcpRequest,
+    ) -> Result<TcpRequest, Error> {
+        let TcpResponse::BrokerConnectHello { nonce, .. } = hello else {
+            return Err(Error::illegal_argument(
+                "Requests can only be authenticated in response to a broker hello",
+            ));
+        };
+        let request = postcard::to_allocvec(request)?;
+        let mac = self.mac(nonce, &request).finalize().into_bytes().to_vec();
+        Ok(TcpRequest::Authenticated { request, mac })
+    }
+
+    /// Checks that the `request` was authenticated with our key, for the `nonce` we sent, and returns the inner request.
+    pub fn verify(&self, nonce: &[u8], request: TcpRequest) -> Result<TcpRequest, Error> {
+        let TcpRequest::Authenticated { request, mac } = request else {
+            return Err(Error::illegal_state("The peer did not authenticate"));
+        };
+        self.mac(nonce, &request)
+            .verify_slice(&mac)
+            .map_err(|_| Error::illegal_state("The peer sent a wrong authentication code"))?;
+        match request.try_into()? {
+            TcpRequest::Authenticated { .. } => Err(Error::illegal_state(
+                "The peer sent a nested authenticated request",
+            )),
+            request => Ok(request),
         }
     }
 }
@@ -460,9 +1135,10 @@ fn tcp_bind(port: u16) -> Result<TcpListener, Error> {
 
 /// Send one message as `u32` len and `[u8;len]` bytes
 #[cfg(feature = "std")]
-pub fn send_tcp_msg<T>(stream: &mut TcpStream, msg: &T) -> Result<(), Error>
+pub fn send_tcp_msg<T, W>(stream: &mut W, msg: &T) -> Result<(), Error>
 where
     T: Serialize,
+    W: Write,
 {
     let msg = postcard::to_allocvec(msg)?;
     if msg.len() > u32::MAX as usize {
@@ -485,16 +1161,52 @@ where
     Ok(())
 }
 
+/// Send a `request` to a broker, in response to its `hello`.
+/// With the `llmp_auth` feature, the request is authenticated with the key in [`LLMP_AUTH_KEY_ENV`], if set.
+/// Use [`send_tcp_request_with_auth`] to pass the key directly.
+#[cfg(feature = "std")]
+#[cfg_attr(not(feature = "llmp_auth"), allow(unused_variables))]
+pub fn send_tcp_request<W>(
+    stream: &mut W,
+    hello: &TcpResponse,
+    request: &TcpRequest,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    #[cfg(feature = "llmp_auth")]
+    return send_tcp_request_with_auth(stream, hello, request, LlmpAuth::from_env().as_ref());
+    #[cfg(not(feature = "llmp_auth"))]
+    send_tcp_msg(stream, request)
+}
+
+/// Send a `request` to a broker, in response to its `hello`, authenticated with `auth`, if given.
+#[cfg(feature = "llmp_auth")]
+pub fn send_tcp_request_with_auth<W>(
+    stream: &mut W,
+    hello: &TcpResponse,
+    request: &TcpRequest,
+    auth: Option<&LlmpAuth>,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    match auth {
+        Some(auth) => send_tcp_msg(stream, &auth.authenticate(hello, request)?),
+        None => send_tcp_msg(stream, request),
+    }
+}
+
 /// Receive one message of `u32` len and `[u8; len]` bytes
 #[cfg(feature = "std")]
-pub fn recv_tcp_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
+pub fn recv_tcp_msg<R>(stream: &mut R) -> Result<Vec<u8>, Error>
+where
+    R: Read,
+{
     // Always receive one be u32 of size, then the command.
 
     #[cfg(feature = "llmp_debug")]
-    log::trace!(
-        "LLMP TCP: Waiting for packet... (Timeout: {:?})",
-        stream.read_timeout().unwrap_or(None)
-    );
+    log::trace!("LLMP TCP: Waiting for packet...");
 
     let mut size_bytes = [0_u8; 4];
     stream.read_exact(&mut size_bytes)?;
@@ -2063,6 +2775,12 @@ where
     clients_to_remove: Vec<ClientId>,
     /// The `ShMemProvider` to use
     shmem_provider: SP,
+    /// The compression settings for broker-to-broker connections
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    b2b_compression: LlmpCompressionConfig,
+    /// The pre-shared key tcp peers need to know, if any
+    #[cfg(feature = "llmp_auth")]
+    auth: Option<LlmpAuth>,
 }
 
 /// The broker (node 0)
@@ -2841,6 +3559,10 @@ where
             exit_cleanly_after: None,
             num_clients_seen: 0,
             shmem_provider,
+            #[cfg(all(feature = "std", feature = "llmp_compression"))]
+            b2b_compression: LlmpCompressionConfig::new(),
+            #[cfg(feature = "llmp_auth")]
+            auth: LlmpAuth::from_env(),
         })
     }
 
@@ -2891,6 +3613,22 @@ where
         self.exit_cleanly_after = Some(n_clients);
     }
 
+    /// Sets the compression settings for broker-to-broker connections.
+    /// Only affects connections established (or listeners launched) after this call.
+    #[cfg(all(feature = "std", feature = "llmp_compression"))]
+    pub fn set_b2b_compression(&mut self, config: LlmpCompressionConfig) {
+        self.b2b_compression = config;
+    }
+
+    /// Sets the pre-shared key clients and other brokers need to know to connect to this broker over tcp,
+    /// and that this broker uses to connect to other brokers.
+    /// Defaults to the key in [`LLMP_AUTH_KEY_ENV`], if set. `None` accepts everyone.
+    /// Only affects connections established (or listeners launched) after this call.
+    #[cfg(feature = "llmp_auth")]
+    pub fn set_auth(&mut self, auth: Option<LlmpAuth>) {
+        self.auth = auth;
+    }
+
     /// Add a client to this broker.
     /// Will set an appropriate [`ClientId`] before pushing the client to the internal vec.
     /// Will increase `num_clients_seen`.
@@ -2936,14 +3674,32 @@ where
     where
         A: ToSocketAddrs,
     {
-        let mut stream = TcpStream::connect(addr)?;
+        let stream = TcpStream::connect(addr)?;
         log::info!("B2B: Connected to {stream:?}");
+        self.connect_b2b_on(LlmpStream::Tcp(stream))
+    }
 
-        match recv_tcp_msg(&mut stream)?.try_into()? {
-            TcpResponse::BrokerConnectHello {
-                broker_shmem_description: _,
-                hostname,
-            } => log::info!("B2B: Connected to {hostname}"),
+    /// Connects to a broker running on another machine, listening with [`LlmpBrokerInner::launch_tls_listener_on`].
+    /// The connection is encrypted, and both brokers need to present a certificate pinned by the other one.
+    #[cfg(feature = "llmp_tls")]
+    pub fn connect_b2b_tls<A>(&mut self, addr: A, tls_config: &LlmpTlsConfig) -> Result<(), Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let stream = TcpStream::connect(addr)?;
+        log::info!("B2B: Connected to {stream:?}, starting TLS handshake");
+        let stream = tls_config.connect(stream)?;
+        self.connect_b2b_on(LlmpStream::TlsClient(Box::new(stream)))
+    }
+
+    /// Sets up a broker to broker connection on an already connected `stream`
+    #[cfg(feature = "std")]
+    fn connect_b2b_on(&mut self, mut stream: LlmpStream) -> Result<(), Error> {
+        let hello: TcpResponse = recv_tcp_msg(&mut stream)?.try_into()?;
+        match &hello {
+            TcpResponse::BrokerConnectHello { hostname, .. } => {
+                log::info!("B2B: Connected to {hostname}");
+            }
             _ => {
                 return Err(Error::illegal_state(
                     "Unexpected response from B2B server received.".to_string(),
@@ -2956,19 +3712,38 @@ where
             .to_string_lossy()
             .into();
 
-        send_tcp_msg(&mut stream, &TcpRequest::RemoteBrokerHello { hostname })?;
+        #[cfg(feature = "llmp_compression")]
+        let offered = self.b2b_compression.algorithms().to_vec();
+        #[cfg(not(feature = "llmp_compression"))]
+        let offered = vec![];
+        let request = TcpRequest::RemoteBrokerHello {
+            hostname,
+            compression: offered.clone(),
+        };
+        #[cfg(feature = "llmp_auth")]
+        let request = match &self.auth {
+            Some(auth) => auth.authenticate(&hello, &request)?,
+            None => request,
+        };
+        send_tcp_msg(&mut stream, &request)?;
 
-        let broker_id = match recv_tcp_msg(&mut stream)?.try_into()? {
-            TcpResponse::RemoteBrokerAccepted { broker_id } => {
-                log::info!("B2B: Got Connection Ack, broker_id {broker_id:?}");
-                broker_id
-            }
-            _ => {
-                return Err(Error::illegal_state(
-                    "Unexpected response from B2B server received.".to_string(),
-                ));
-            }
+        let TcpResponse::RemoteBrokerAccepted {
+            broker_id,
+            compression,
+        } = recv_tcp_msg(&mut stream)?.try_into()?
+        else {
+            return Err(Error::illegal_state(
+                "Unexpected response from B2B server received.".to_string(),
+            ));
         };
+        log::info!("B2B: Got Connection Ack, broker_id {broker_id:?}");
+
+        if compression.is_some_and(|algorithm| !offered.contains(&algorithm)) {
+            return Err(Error::illegal_state(format!(
+                "Remote broker picked compression {compression:?}, which we never offered."
+            )));
+        }
+        log::info!("B2B: Using compression {compression:?}");
 
         // TODO: use broker ids!
         log::info!("B2B: We are broker {broker_id:?}");
@@ -2984,6 +3759,8 @@ where
                 .unwrap()
                 .shmem
                 .description(),
+            #[cfg(feature = "llmp_compression")]
+            compression.map(|algorithm| (algorithm, self.b2b_compression.clone())),
         )?;
 
         let new_shmem = LlmpSharedMap::existing(
@@ -3061,6 +3838,36 @@ where
         self.launch_listener(Listener::Tcp(listener))
     }
 
+    /// Launches a thread using a TLS listener socket, on which remote brokers may connect to this broker,
+    /// using [`LlmpBrokerInner::connect_b2b_tls`].
+    /// Like [`LlmpBrokerInner::launch_tcp_listener_on`], this only binds to all interfaces with the `llmp_bind_public` feature,
+    /// use [`LlmpBrokerInner::launch_tls_listener_on_addr`] to pick the address.
+    #[cfg(feature = "llmp_tls")]
+    pub fn launch_tls_listener_on(
+        &mut self,
+        port: u16,
+        tls_config: LlmpTlsConfig,
+    ) -> Result<thread::JoinHandle<()>, Error> {
+        let listener = tcp_bind(port)?;
+        log::info!("TLS server listening on port {port}");
+        self.launch_listener(Listener::Tls(listener, tls_config))
+    }
+
+    /// Launches a thread using a TLS listener socket bound to `addr`, see [`LlmpBrokerInner::launch_tls_listener_on`].
+    #[cfg(feature = "llmp_tls")]
+    pub fn launch_tls_listener_on_addr<A>(
+        &mut self,
+        addr: A,
+        tls_config: LlmpTlsConfig,
+    ) -> Result<thread::JoinHandle<()>, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr)?;
+        log::info!("TLS server listening on {:?}", listener.local_addr()?);
+        self.launch_listener(Listener::Tls(listener, tls_config))
+    }
+
     /// Announces a new client on the given shared map.
     /// Called from a background thread, typically.
     /// Upon receiving this message, the broker should map the announced page and start tracking it for new messages.
@@ -3105,9 +3912,13 @@ where
     #[cfg(feature = "std")]
     #[allow(clippy::let_and_return, clippy::too_many_lines)]
     fn b2b_thread_on(
-        mut stream: TcpStream,
+        mut stream: LlmpStream,
         b2b_client_id: ClientId,
         broker_shmem_description: &ShMemDescription,
+        #[cfg(feature = "llmp_compression")] compression: Option<(
+            CompressionAlgorithm,
+            LlmpCompressionConfig,
+        )>,
     ) -> Result<ShMemDescription, Error> {
         let broker_shmem_description = *broker_shmem_description;
 
@@ -3124,6 +3935,7 @@ where
 
             // The background thread blocks on the incoming connection for 15 seconds (if no data is available), then checks if it should forward own messages, then blocks some more.
             stream
+                .tcp()
                 .set_read_timeout(Some(_LLMP_B2B_BLOCK_TIME))
                 .expect("Failed to set tcp stream timeout");
 
@@ -3151,7 +3963,7 @@ where
             #[cfg(feature = "llmp_debug")]
             log::info!("B2B: Starting proxy loop :)");
 
-            let peer_address = stream.peer_addr().unwrap();
+            let peer_address = stream.tcp().peer_addr().unwrap();
 
             loop {
                 // first, forward all data we have.
@@ -3171,6 +3983,19 @@ where
                                 "Fowarding message ({} bytes) via broker2broker connection",
                                 payload.len()
                             );
+                            #[cfg(feature = "llmp_compression")]
+                            let (payload, compression) = match &compression {
+                                Some((algorithm, config)) => {
+                                    match config.maybe_compress(*algorithm, tag, flags, payload) {
+                                        Some(compressed) => (compressed, Some(*algorithm)),
+                                        None => (payload.to_vec(), None),
+                                    }
+                                }
+                                None => (payload.to_vec(), None),
+                            };
+                            #[cfg(not(feature = "llmp_compression"))]
+                            let (payload, compression) = (payload.to_vec(), None);
+
                             // We got a new message! Forward...
                             if let Err(e) = send_tcp_msg(
                                 &mut stream,
@@ -3178,7 +4003,8 @@ where
                                     client_id,
                                     tag,
                                     flags,
-                                    payload: payload.to_vec(),
+                                    payload,
+                                    compression,
                                 },
                             ) {
                                 log::info!("Got error {e} while trying to forward a message to broker {peer_address}, exiting thread");
@@ -3201,9 +4027,35 @@ where
                 // Instead, we catch stream close when/if we next try to send.
                 match recv_tcp_msg(&mut stream) {
                     Ok(val) => {
-                        let msg: TcpRemoteNewMessage = val.try_into().expect(
-                            "Illegal message received from broker 2 broker connection - shutting down.",
-                        );
+                        #[allow(unused_mut)]
+                        let mut msg: TcpRemoteNewMessage = match val.try_into() {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                log::warn!(
+                                    "B2B: Dropping malformed message from {peer_address}: {e}"
+                                );
+                                continue;
+                            }
+                        };
+
+                        #[cfg(feature = "llmp_compression")]
+                        if let Some(algorithm) = msg.compression {
+                            let max_len = compression
+                                .as_ref()
+                                .map_or(0, |(_, config)| config.max_decompressed_len());
+                            match algorithm.decompress(&msg.payload, max_len) {
+                                Ok(payload) => msg.payload = payload,
+                                Err(e) => {
+                                    log::warn!("B2B: Dropping message from {peer_address} that failed to decompress with {algorithm:?}: {e}");
+                                    continue;
+                                }
+                            }
+                        }
+                        #[cfg(not(feature = "llmp_compression"))]
+                        if let Some(algorithm) = msg.compression {
+                            log::warn!("B2B: Dropping message from {peer_address} compressed with {algorithm:?}, which this build never offered");
+                            continue;
+                        }
 
                         #[cfg(feature = "llmp_debug")]
                         log::info!(
@@ -3251,11 +4103,12 @@ where
     /// handles a single tcp request in the current context.
     #[cfg(feature = "std")]
     fn handle_tcp_request(
-        mut stream: TcpStream,
+        mut stream: LlmpStream,
         request: &TcpRequest,
         current_client_id: &mut ClientId,
         sender: &mut LlmpSender<SP>,
         broker_shmem_description: &ShMemDescription,
+        #[cfg(feature = "llmp_compression")] b2b_compression: &LlmpCompressionConfig,
     ) {
         match request {
             TcpRequest::ClientQuit { client_id } => {
@@ -3281,14 +4134,28 @@ where
                 };
                 current_client_id.0 += 1;
             }
-            TcpRequest::RemoteBrokerHello { hostname } => {
+            TcpRequest::RemoteBrokerHello {
+                hostname,
+                compression: offered,
+            } => {
                 log::info!("B2B new client: {hostname}");
 
+                #[cfg(feature = "llmp_compression")]
+                let compression = b2b_compression.negotiate(offered);
+                // We can't compress, whatever the remote broker offers
+                #[cfg(not(feature = "llmp_compression"))]
+                let compression = {
+                    let _ = offered;
+                    None
+                };
+                log::info!("B2B: Using compression {compression:?} for {hostname}");
+
                 // TODO: Clean up broker ids.
                 if send_tcp_msg(
                     &mut stream,
                     &TcpResponse::RemoteBrokerAccepted {
                         broker_id: BrokerId(current_client_id.0),
+                        compression,
                     },
                 )
                 .is_err()
@@ -3297,15 +4164,32 @@ where
                     return;
                 }
 
-                if let Ok(shmem_description) =
-                    Self::b2b_thread_on(stream, *current_client_id, broker_shmem_description)
-                {
+                if let Ok(shmem_description) = Self::b2b_thread_on(
+                    stream,
+                    *current_client_id,
+                    broker_shmem_description,
+                    #[cfg(feature = "llmp_compression")]
+                    compression.map(|algorithm| (algorithm, b2b_compression.clone())),
+                ) {
                     if Self::announce_new_client(sender, &shmem_description).is_err() {
                         log::info!("B2B: Error announcing client {shmem_description:?}");
                     };
                     current_client_id.0 += 1;
                 }
             }
+            TcpRequest::Authenticated { .. } => {
+                // Authenticated requests get unwrapped before, unless we don't have a key ourselves,
+                // or were built without `llmp_auth`.
+                log::warn!("Received an authenticated request, but no llmp auth key is set for this broker, rejecting it.");
+                if let Err(e) = send_tcp_msg(
+                    &mut stream,
+                    &TcpResponse::Error {
+                        description: "This broker has no auth key set".into(),
+                    },
+                ) {
+                    log::info!("An error occurred sending via tcp {e}");
+                }
+            }
         };
     }
 
@@ -3319,14 +4203,10 @@ where
 
         let client_out_shmem_mem = &self.llmp_out.out_shmems.first().unwrap().shmem;
         let broker_shmem_description = client_out_shmem_mem.description();
-        let hostname = hostname::get()
+        let hostname: String = hostname::get()
             .unwrap_or_else(|_| "<unknown>".into())
             .to_string_lossy()
             .into();
-        let broker_hello = TcpResponse::BrokerConnectHello {
-            broker_shmem_description,
-            hostname,
-        };
 
         let llmp_tcp_id = self.peek_next_client_id();
 
@@ -3338,12 +4218,19 @@ where
         let tcp_out_shmem_description = tcp_out_shmem.shmem.description();
         let listener_id = self.register_client(tcp_out_shmem);
 
+        #[cfg(feature = "llmp_compression")]
+        let b2b_compression = self.b2b_compression.clone();
+        #[cfg(feature = "llmp_auth")]
+        let auth = self.auth.clone();
+
         let ret = thread::spawn(move || {
             // Create a new ShMemProvider for this background thread.
             let mut shmem_provider_bg = SP::new().unwrap();
 
             let mut current_client_id = ClientId(llmp_tcp_id.0 + 1);
 
+            let acceptor = listener.into_acceptor();
+
             let mut tcp_incoming_sender = LlmpSender {
                 id: llmp_tcp_id,
                 last_msg_sent: ptr::null_mut(),
@@ -3360,14 +4247,38 @@ where
             };
 
             loop {
-                match listener.accept() {
-                    ListenerStream::Tcp(mut stream, addr) => {
+                let stream = match acceptor.accept() {
+                    ListenerStream::Tcp(stream, addr) => Some((LlmpStream::Tcp(stream), addr)),
+                    #[cfg(feature = "llmp_tls")]
+                    ListenerStream::Tls(stream, addr) => {
+                        Some((LlmpStream::TlsServer(stream), addr))
+                    }
+                    ListenerStream::Empty() => None,
+                };
+                match stream {
+                    Some((mut stream, addr)) => {
                         log::info!(
                             "New connection: {:?}/{:?}",
                             addr,
-                            stream.peer_addr().unwrap()
+                            stream.tcp().peer_addr().unwrap()
                         );
 
+                        #[cfg(feature = "llmp_auth")]
+                        let nonce = match LlmpAuth::new_nonce() {
+                            Ok(nonce) => nonce,
+                            Err(e) => {
+                                log::error!("{e}");
+                                continue;
+                            }
+                        };
+                        #[cfg(not(feature = "llmp_auth"))]
+                        let nonce = [0; LLMP_AUTH_NONCE_LEN];
+                        let broker_hello = TcpResponse::BrokerConnectHello {
+                            broker_shmem_description,
+                            hostname: hostname.clone(),
+                            nonce,
+                        };
+
                         // Send initial information, without anyone asking.
                         // This makes it a tiny bit easier to map the broker map for new Clients.
                         match send_tcp_msg(&mut stream, &broker_hello) {
@@ -3395,15 +4306,37 @@ where
                             }
                         };
 
+                        #[cfg(feature = "llmp_auth")]
+                        let req = match &auth {
+                            Some(auth) => match auth.verify(&nonce, req) {
+                                Ok(req) => req,
+                                Err(e) => {
+                                    log::warn!("Rejecting connection from {addr:?}: {e}");
+                                    if let Err(e) = send_tcp_msg(
+                                        &mut stream,
+                                        &TcpResponse::Error {
+                                            description: "Authentication failed".into(),
+                                        },
+                                    ) {
+                                        log::info!("An error occurred sending via tcp {e}");
+                                    }
+                                    continue;
+                                }
+                            },
+                            None => req,
+                        };
+
                         Self::handle_tcp_request(
                             stream,
                             &req,
                             &mut current_client_id,
                             &mut tcp_incoming_sender,
                             &broker_shmem_description,
+                            #[cfg(feature = "llmp_compression")]
+                            &b2b_compression,
                         );
                     }
-                    ListenerStream::Empty() => {
+                    None => {
                         continue;
                     }
                 };
@@ -3685,7 +4618,33 @@ where
     #[cfg(feature = "std")]
     /// Create a [`LlmpClient`], getting the ID from a given port, then also tell the restarter's ID so we ask to be removed later
     /// This is called when, for the first time, the restarter attaches to this process.
-    pub fn create_attach_to_tcp(mut shmem_provider: SP, port: u16) -> Result<Self, Error> {
+    /// With the `llmp_auth` feature, the client authenticates with the key in [`LLMP_AUTH_KEY_ENV`], if set.
+    pub fn create_attach_to_tcp(shmem_provider: SP, port: u16) -> Result<Self, Error> {
+        Self::attach_to_tcp(
+            shmem_provider,
+            port,
+            #[cfg(feature = "llmp_auth")]
+            LlmpAuth::from_env().as_ref(),
+        )
+    }
+
+    /// Create a [`LlmpClient`] like [`LlmpClient::create_attach_to_tcp`],
+    /// but authenticate with the given key, or not at all, regardless of the [`LLMP_AUTH_KEY_ENV`] env var.
+    #[cfg(feature = "llmp_auth")]
+    pub fn create_attach_to_tcp_with_auth(
+        shmem_provider: SP,
+        port: u16,
+        auth: Option<&LlmpAuth>,
+    ) -> Result<Self, Error> {
+        Self::attach_to_tcp(shmem_provider, port, auth)
+    }
+
+    #[cfg(feature = "std")]
+    fn attach_to_tcp(
+        mut shmem_provider: SP,
+        port: u16,
+        #[cfg(feature = "llmp_auth")] auth: Option<&LlmpAuth>,
+    ) -> Result<Self, Error> {
         let mut stream = match TcpStream::connect((IP_LOCALHOST, port)) {
             Ok(stream) => stream,
             Err(e) => {
@@ -3709,10 +4668,11 @@ where
         };
         log::info!("Connected to port {port}");
 
+        let hello: TcpResponse = recv_tcp_msg(&mut stream)?.try_into()?;
         let TcpResponse::BrokerConnectHello {
             broker_shmem_description,
-            hostname: _,
-        } = recv_tcp_msg(&mut stream)?.try_into()?
+            ..
+        } = hello
         else {
             return Err(Error::illegal_state(
                 "Received unexpected Broker Hello".to_string(),
@@ -3730,6 +4690,9 @@ where
         let client_hello_req = TcpRequest::LocalClientHello {
             shmem_description: ret.sender.out_shmems.first().unwrap().shmem.description(),
         };
+        #[cfg(feature = "llmp_auth")]
+        send_tcp_request_with_auth(&mut stream, &hello, &client_hello_req, auth)?;
+        #[cfg(not(feature = "llmp_auth"))]
         send_tcp_msg(&mut stream, &client_hello_req)?;
 
         // The broker accepted the client, and sent back an ID.
@@ -3817,4 +4780,126 @@ mod tests {
         // We want at least the tcp and sender clients.
         assert_eq!(broker.inner.llmp_clients.len(), 2);
     }
+    #[test]
+    #[cfg(feature = "llmp_auth")]
+    pub fn test_llmp_auth() {
+        use super::{LlmpAuth, TcpRequest, TcpResponse};
+        use crate::{shmem::ShMem, ClientId};
+
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let hello = TcpResponse::BrokerConnectHello {
+            broker_shmem_description: shmem_provider
+                .clone()
+                .new_shmem(1024)
+                .unwrap()
+                .description(),
+            hostname: "test".into(),
+            nonce: [42; super::LLMP_AUTH_NONCE_LEN],
+        };
+        let request = TcpRequest::ClientQuit {
+            client_id: ClientId(1),
+        };
+
+        let auth = LlmpAuth::new("secret").unwrap();
+        let authenticated = auth.authenticate(&hello, &request).unwrap();
+        assert!(matches!(
+            auth.verify(&[42; super::LLMP_AUTH_NONCE_LEN], authenticated.clone())
+                .unwrap(),
+            TcpRequest::ClientQuit {
+                client_id: ClientId(1)
+            }
+        ));
+
+        // Wrong nonce, wrong key, or no authentication at all
+        assert!(auth
+            .verify(&[0; super::LLMP_AUTH_NONCE_LEN], authenticated.clone())
+            .is_err());
+        assert!(LlmpAuth::new("other")
+            .unwrap()
+            .verify(&[42; super::LLMP_AUTH_NONCE_LEN], authenticated)
+            .is_err());
+        assert!(auth
+            .verify(&[42; super::LLMP_AUTH_NONCE_LEN], request)
+            .is_err());
+        assert!(LlmpAuth::new("").is_err());
+    }
+
+    #[test]
+    pub fn test_llmp_tcp_compat() {
+        use alloc::{string::String, vec::Vec};
+
+        use serde::Serialize;
+
+        use super::{
+            ClientId, Flags, TcpRemoteNewMessage, TcpRequest, TcpResponse, LLMP_AUTH_NONCE_LEN,
+        };
+        use crate::shmem::{ShMem, ShMemDescription};
+
+        /// A [`TcpRemoteNewMessage`], as sent by brokers before compression was negotiated
+        #[derive(Serialize)]
+        struct OldRemoteNewMessage {
+            client_id: ClientId,
+            tag: Tag,
+            flags: Flags,
+            payload: Vec<u8>,
+        }
+
+        let old = postcard::to_allocvec(&OldRemoteNewMessage {
+            client_id: ClientId(3),
+            tag: Tag(7),
+            flags: Flags(0),
+            payload: vec![1, 2, 3],
+        })
+        .unwrap();
+        let msg = TcpRemoteNewMessage::try_from(old).unwrap();
+        assert_eq!(msg.client_id, ClientId(3));
+        assert_eq!(msg.payload, [1, 2, 3]);
+        assert!(msg.compression.is_none());
+
+        /// A `TcpRequest::RemoteBrokerHello`, as sent by brokers before compression was negotiated
+        #[derive(Serialize)]
+        enum OldRequest {
+            #[allow(dead_code)]
+            LocalClientHello,
+            RemoteBrokerHello {
+                hostname: String,
+            },
+        }
+
+        let old = postcard::to_allocvec(&OldRequest::RemoteBrokerHello {
+            hostname: "old".into(),
+        })
+        .unwrap();
+        assert!(matches!(
+            TcpRequest::try_from(old).unwrap(),
+            TcpRequest::RemoteBrokerHello { hostname, compression } if hostname == "old" && compression.is_empty()
+        ));
+
+        /// A `TcpResponse::BrokerConnectHello`, as sent by brokers before authentication
+        #[derive(Serialize)]
+        enum OldResponse {
+            BrokerConnectHello {
+                broker_shmem_description: ShMemDescription,
+                hostname: String,
+            },
+        }
+
+        let broker_shmem_description = StdShMemProvider::new()
+            .unwrap()
+            .new_shmem(1024)
+            .unwrap()
+            .description();
+        let old = postcard::to_allocvec(&OldResponse::BrokerConnectHello {
+            broker_shmem_description,
+            hostname: "old".into(),
+        })
+        .unwrap();
+        assert!(matches!(
+            TcpResponse::try_from(old).unwrap(),
+            TcpResponse::BrokerConnectHello { hostname, nonce, .. } if hostname == "old" && nonce == [0; LLMP_AUTH_NONCE_LEN]
+        ));
+
+        // Messages that are not just shorter are still rejected
+        assert!(TcpRequest::try_from(vec![42]).is_err());
+    }
 }
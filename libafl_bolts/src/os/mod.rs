@@ -15,6 +15,9 @@ pub use unix_signals::CTRL_C_EXIT;
 #[cfg(all(unix, feature = "alloc"))]
 pub mod pipes;
 
+#[cfg(feature = "std")]
+pub mod supervisor;
+
 #[cfg(all(unix, feature = "std"))]
 use alloc::borrow::Cow;
 #[cfg(all(unix, feature = "std"))]
@@ -58,6 +61,19 @@ impl ChildHandle {
         }
         libc::WEXITSTATUS(status)
     }
+
+    /// Check if the child exited, without blocking.
+    /// Returns the status code if it did, `None` if it is still running.
+    #[must_use]
+    pub fn try_status(&self) -> Option<i32> {
+        let mut status = -1;
+        let res = unsafe { libc::waitpid(self.pid, &mut status, libc::WNOHANG) };
+        if res == self.pid {
+            Some(libc::WEXITSTATUS(status))
+        } else {
+            None
+        }
+    }
 }
 
 /// The `ForkResult` (result of a fork)
@@ -0,0 +1,474 @@
+//! Spawn and supervise child processes, the same way on all platforms.
+//!
+//! A [`Supervisor`] holds the limits for a child: a deadline, a memory ceiling, whether to capture
+//! its output, and whether to clean up everything it spawned itself.
+//! It configures a [`Command`] before the spawn, then wraps the [`Child`] in a [`SupervisedChild`],
+//! which enforces the deadline and classifies how the child exited as a [`ChildExit`].
+//!
+//! On unix, [`Supervisor::kill_tree`] puts the child into its own process group, and the memory ceiling is set with `setrlimit`.
+//! On `Windows`, the child is put into a job object for both.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::{
+    io::Read,
+    process::{Child, ChildStdin, Command, Stdio},
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use wait_timeout::ChildExt;
+
+use crate::Error;
+
+/// How a supervised child process exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildExit {
+    /// The child exited with status `0`
+    Ok,
+    /// The child exited normally, with the given non-zero status
+    Failed(i32),
+    /// The child was killed by the given signal, or, on `Windows`, exited with the given exception code
+    Crashed(i32),
+    /// The child ran out of memory, or was killed by the OOM killer
+    OutOfMemory,
+    /// The child did not exit before the deadline, and was killed
+    Timeout,
+}
+
+impl ChildExit {
+    /// Classify the exit status of a child we did not kill ourselves
+    #[must_use]
+    pub fn from_status(status: std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        if let Some(signal) = status.signal() {
+            // The OOM killer uses `SIGKILL`, and we don't kill our children with it unless they time out.
+            return if signal == libc::SIGKILL {
+                Self::OutOfMemory
+            } else {
+                Self::Crashed(signal)
+            };
+        }
+
+        match status.code() {
+            Some(0) => Self::Ok,
+            #[cfg(windows)]
+            Some(code) if code as u32 == STATUS_NO_MEMORY => Self::OutOfMemory,
+            // `NTSTATUS` error codes, like `STATUS_ACCESS_VIOLATION`
+            #[cfg(windows)]
+            Some(code) if code as u32 >= 0xC000_0000 => Self::Crashed(code),
+            Some(code) => Self::Failed(code),
+            // Only happens on unix, where we already handled signals
+            None => Self::Crashed(0),
+        }
+    }
+
+    /// If the child exited on its own, with or without error
+    #[must_use]
+    pub fn is_normal(&self) -> bool {
+        matches!(self, Self::Ok | Self::Failed(_))
+    }
+}
+
+/// The `NTSTATUS` of a process that failed to allocate memory
+#[cfg(windows)]
+const STATUS_NO_MEMORY: u32 = 0xC000_0017;
+
+/// The result of a finished [`SupervisedChild`]
+#[derive(Debug, Clone)]
+pub struct ChildOutcome {
+    /// How the child exited
+    pub exit: ChildExit,
+    /// How long the child ran
+    pub duration: Duration,
+    /// The captured stdout, if the [`Supervisor`] captures it
+    pub stdout: Option<Vec<u8>>,
+    /// The captured stderr, if the [`Supervisor`] captures it
+    pub stderr: Option<Vec<u8>>,
+}
+
+/// Spawns child processes with a deadline, a memory ceiling and output capture, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Supervisor {
+    timeout: Option<Duration>,
+    memory_limit: Option<usize>,
+    capture_stdout: bool,
+    capture_stderr: bool,
+    kill_tree: bool,
+}
+
+impl Supervisor {
+    /// Creates a new [`Supervisor`], without any limits
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kill the child if it did not exit after `timeout`
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Limit the memory of the child to `bytes`.
+    ///
+    /// On unix, this limits the address space, so it does not work with sanitizers that reserve huge shadow maps.
+    /// On `macOS`, which does not enforce address space limits, this limits the data segment instead.
+    #[must_use]
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Capture the stdout of the child, into [`ChildOutcome::stdout`]
+    #[must_use]
+    pub fn capture_stdout(mut self, capture: bool) -> Self {
+        self.capture_stdout = capture;
+        self
+    }
+
+    /// Capture the stderr of the child, into [`ChildOutcome::stderr`]
+    #[must_use]
+    pub fn capture_stderr(mut self, capture: bool) -> Self {
+        self.capture_stderr = capture;
+        self
+    }
+
+    /// Kill everything the child spawned itself as well, when the child is killed or exits.
+    ///
+    /// On unix, the child gets its own process group for this, so it does no longer receive `SIGINT` from the terminal.
+    #[must_use]
+    pub fn kill_tree(mut self, kill_tree: bool) -> Self {
+        self.kill_tree = kill_tree;
+        self
+    }
+
+    /// The deadline of the child, if any
+    #[must_use]
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Prepares `command` for supervision. Call this before spawning it, then pass the child to [`Self::supervise`].
+    ///
+    /// If [`Self::spawn`] is all you need, it does both.
+    pub fn configure(&self, command: &mut Command) {
+        if self.capture_stdout {
+            command.stdout(Stdio::piped());
+        }
+        if self.capture_stderr {
+            command.stderr(Stdio::piped());
+        }
+
+        #[cfg(unix)]
+        {
+            if self.kill_tree {
+                command.process_group(0);
+            }
+            if let Some(limit) = self.memory_limit {
+                #[cfg(target_vendor = "apple")]
+                let resource = libc::RLIMIT_DATA;
+                #[cfg(not(target_vendor = "apple"))]
+                let resource = libc::RLIMIT_AS;
+                let rlimit = libc::rlimit {
+                    rlim_cur: limit as libc::rlim_t,
+                    rlim_max: limit as libc::rlim_t,
+                };
+                // # Safety
+                // `setrlimit` is async-signal-safe, and we don't allocate.
+                unsafe {
+                    command.pre_exec(move || {
+                        if libc::setrlimit(resource, &rlimit) == 0 {
+                            Ok(())
+                        } else {
+                            Err(std::io::Error::last_os_error())
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Supervises a `child` spawned from a [`Command`] prepared with [`Self::configure`]
+    pub fn supervise(&self, mut child: Child) -> Result<SupervisedChild, Error> {
+        let start = Instant::now();
+
+        #[cfg(windows)]
+        let job = if self.kill_tree || self.memory_limit.is_some() {
+            match windows_job::Job::new(self.memory_limit)
+                .and_then(|job| job.assign(&child).map(|()| job))
+            {
+                Ok(job) => Some(job),
+                Err(err) => {
+                    drop(child.kill());
+                    drop(child.wait());
+                    return Err(err);
+                }
+            }
+        } else {
+            None
+        };
+
+        let stdout = if self.capture_stdout {
+            child.stdout.take().map(read_to_end_in_thread)
+        } else {
+            None
+        };
+        let stderr = if self.capture_stderr {
+            child.stderr.take().map(read_to_end_in_thread)
+        } else {
+            None
+        };
+
+        Ok(SupervisedChild {
+            child,
+            start,
+            timeout: self.timeout,
+            kill_tree: self.kill_tree,
+            stdout,
+            stderr,
+            #[cfg(windows)]
+            job,
+        })
+    }
+
+    /// Spawns the `command` and supervises the child
+    pub fn spawn(&self, command: &mut Command) -> Result<SupervisedChild, Error> {
+        self.configure(command);
+        let child = command.spawn()?;
+        self.supervise(child)
+    }
+}
+
+/// Reads the pipe in a separate thread, so the child can't block on a full pipe while we wait for it to exit
+fn read_to_end_in_thread<R>(mut pipe: R) -> JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        // Whatever we got until the pipe broke
+        drop(pipe.read_to_end(&mut buf));
+        buf
+    })
+}
+
+/// A child process spawned by a [`Supervisor`]
+#[derive(Debug)]
+pub struct SupervisedChild {
+    child: Child,
+    start: Instant,
+    timeout: Option<Duration>,
+    kill_tree: bool,
+    stdout: Option<JoinHandle<Vec<u8>>>,
+    stderr: Option<JoinHandle<Vec<u8>>>,
+    #[cfg(windows)]
+    job: Option<windows_job::Job>,
+}
+
+impl SupervisedChild {
+    /// The OS-assigned process id of the child
+    #[must_use]
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// The stdin of the child, if it was [`Stdio::piped`]
+    pub fn stdin(&mut self) -> Option<&mut ChildStdin> {
+        self.child.stdin.as_mut()
+    }
+
+    /// Closes the stdin of the child, so it sees the end of its input
+    pub fn close_stdin(&mut self) {
+        drop(self.child.stdin.take());
+    }
+
+    /// Kills the child, and everything it spawned if the [`Supervisor`] was set to [`Supervisor::kill_tree`]
+    pub fn kill(&mut self) -> Result<(), Error> {
+        self.kill_tree();
+        match self.child.kill() {
+            // The child already exited
+            Err(err) if err.kind() == std::io::ErrorKind::InvalidInput => Ok(()),
+            res => Ok(res?),
+        }
+    }
+
+    /// Kills whatever is left of the tree of this child
+    fn kill_tree(&mut self) {
+        if !self.kill_tree {
+            return;
+        }
+        #[cfg(unix)]
+        // # Safety
+        // The child leads its own process group, so this does not hit anything else, even if the child is gone.
+        unsafe {
+            #[allow(clippy::cast_possible_wrap)]
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            job.terminate();
+        }
+    }
+
+    /// Waits for the child to exit, killing it at the deadline, and collects its output
+    pub fn wait(mut self) -> Result<ChildOutcome, Error> {
+        let status = match self.timeout {
+            Some(timeout) => {
+                let remaining = timeout.saturating_sub(self.start.elapsed());
+                self.child.wait_timeout(remaining)?
+            }
+            None => Some(self.child.wait()?),
+        };
+
+        let exit = if let Some(status) = status {
+            // Don't leave anything behind, and close the pipes the grandchildren may still hold
+            self.kill_tree();
+            ChildExit::from_status(status)
+        } else {
+            // if this fails, there is not much we can do. let's hope it failed because the process finished
+            // in the meantime.
+            drop(self.kill());
+            // finally, try to wait to properly clean up system resources.
+            drop(self.child.wait());
+            ChildExit::Timeout
+        };
+        let duration = self.start.elapsed();
+
+        let stdout = self.stdout.take().map(|reader| reader.join().unwrap());
+        let stderr = self.stderr.take().map(|reader| reader.join().unwrap());
+
+        Ok(ChildOutcome {
+            exit,
+            duration,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod windows_job {
+    use core::{ffi::c_void, mem::size_of};
+    use std::{os::windows::io::AsRawHandle, process::Child};
+
+    use windows::{
+        core::PCSTR,
+        Win32::{
+            Foundation::{CloseHandle, HANDLE},
+            System::JobObjects::{
+                AssignProcessToJobObject, CreateJobObjectA, JobObjectExtendedLimitInformation,
+                SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, JOB_OBJECT_LIMIT_PROCESS_MEMORY,
+            },
+        },
+    };
+
+    use crate::Error;
+
+    /// A job object holding a supervised child and everything it spawns.
+    /// Closing the job kills all of them.
+    #[derive(Debug)]
+    pub(super) struct Job {
+        handle: HANDLE,
+    }
+
+    impl Job {
+        pub(super) fn new(memory_limit: Option<usize>) -> Result<Self, Error> {
+            let handle = unsafe { CreateJobObjectA(None, PCSTR::null()) }
+                .map_err(|err| Error::unknown(format!("Failed to create a job object: {err}")))?;
+            let job = Self { handle };
+
+            let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(limit) = memory_limit {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+                info.ProcessMemoryLimit = limit;
+            }
+            unsafe {
+                SetInformationJobObject(
+                    job.handle,
+                    JobObjectExtendedLimitInformation,
+                    core::ptr::addr_of!(info).cast::<c_void>(),
+                    size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                )
+            }
+            .map_err(|err| Error::unknown(format!("Failed to set the job limits: {err}")))?;
+
+            Ok(job)
+        }
+
+        /// Puts the `child` into this job. Whatever the child spawned before is not part of it.
+        pub(super) fn assign(&self, child: &Child) -> Result<(), Error> {
+            unsafe { AssignProcessToJobObject(self.handle, HANDLE(child.as_raw_handle() as isize)) }
+                .map_err(|err| {
+                    Error::unknown(format!("Failed to assign the child to its job: {err}"))
+                })
+        }
+
+        /// Kills all processes in the job
+        pub(super) fn terminate(&self) {
+            drop(unsafe { TerminateJobObject(self.handle, 1) });
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            drop(unsafe { CloseHandle(self.handle) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::process::Command;
+
+    use crate::os::supervisor::{ChildExit, Supervisor};
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_supervisor() {
+        let outcome = Supervisor::new()
+            .capture_stdout(true)
+            .spawn(Command::new("sh").args(["-c", "echo hi; exit 3"]))
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(outcome.exit, ChildExit::Failed(3));
+        assert_eq!(outcome.stdout.as_deref(), Some(&b"hi\n"[..]));
+        assert!(outcome.stderr.is_none());
+
+        // More output than fits into a pipe
+        let outcome = Supervisor::new()
+            .capture_stdout(true)
+            .timeout(Duration::from_secs(10))
+            .spawn(Command::new("sh").args(["-c", "head -c 1000000 /dev/zero"]))
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(outcome.exit, ChildExit::Ok);
+        assert_eq!(outcome.stdout.unwrap().len(), 1_000_000);
+
+        let outcome = Supervisor::new()
+            .timeout(Duration::from_millis(100))
+            .kill_tree(true)
+            .spawn(Command::new("sh").args(["-c", "sleep 10 & sleep 10"]))
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(outcome.exit, ChildExit::Timeout);
+        assert!(outcome.duration < Duration::from_secs(5));
+
+        let outcome = Supervisor::new()
+            .spawn(Command::new("sh").args(["-c", "kill -SEGV $$"]))
+            .unwrap()
+            .wait()
+            .unwrap();
+        assert_eq!(outcome.exit, ChildExit::Crashed(libc::SIGSEGV));
+    }
+}
@@ -254,7 +254,7 @@ use libc::ssize_t;
 pub use libc::ucontext_t;
 use libc::{
     c_int, SIGABRT, SIGALRM, SIGBUS, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGKILL, SIGPIPE, SIGQUIT,
-    SIGSEGV, SIGTERM, SIGTRAP, SIGUSR2,
+    SIGSEGV, SIGTERM, SIGTRAP, SIGUSR1, SIGUSR2,
 };
 pub use libc::{c_void, siginfo_t};
 #[cfg(feature = "alloc")]
@@ -289,6 +289,8 @@ pub enum Signal {
     SigPipe = SIGPIPE,
     /// `SIGSEGV` signal id
     SigSegmentationFault = SIGSEGV,
+    /// `SIGUSR1` signal id
+    SigUser1 = SIGUSR1,
     /// `SIGUSR2` signal id
     SigUser2 = SIGUSR2,
     /// `SIGALARM` signal id
@@ -331,6 +333,7 @@ impl TryFrom<&str> for Signal {
             "SIGILL" => Signal::SigIllegalInstruction,
             "SIGPIPE" => Signal::SigPipe,
             "SIGSEGV" => Signal::SigSegmentationFault,
+            "SIGUSR1" => Signal::SigUser1,
             "SIGUSR2" => Signal::SigUser2,
             "SIGALRM" => Signal::SigAlarm,
             "SIGHUP" => Signal::SigHangUp,
@@ -371,6 +374,7 @@ impl Display for Signal {
             Signal::SigIllegalInstruction => write!(f, "SIGILL")?,
             Signal::SigPipe => write!(f, "SIGPIPE")?,
             Signal::SigSegmentationFault => write!(f, "SIGSEGV")?,
+            Signal::SigUser1 => write!(f, "SIGUSR1")?,
             Signal::SigUser2 => write!(f, "SIGUSR2")?,
             Signal::SigAlarm => write!(f, "SIGALRM")?,
             Signal::SigHangUp => write!(f, "SIGHUP")?,
@@ -1,6 +1,8 @@
 //! Wrappers that abstracts references (or pointers) and owned data accesses.
 // The serialization is towards owned, allowing to serialize pointers without troubles.
 
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
 use alloc::{
     boxed::Box,
     slice::{Iter, IterMut},
@@ -9,7 +11,7 @@ use alloc::{
 use core::{
     clone::Clone,
     fmt::Debug,
-    ops::{Deref, DerefMut},
+    ops::{Bound, Deref, DerefMut, Range, RangeBounds},
     slice,
 };
 
@@ -351,6 +353,28 @@ where
     }
 }
 
+/// Resolve `range` for a slice of length `len`.
+///
+/// # Panics
+/// Panics if the range is out of bounds, like indexing a slice would.
+fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end + 1,
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end && end <= len,
+        "range {start}..{end} out of bounds for a slice of length {len}"
+    );
+    start..end
+}
+
 /// Wrap a slice and convert to a Vec on serialize
 #[derive(Clone, Debug)]
 enum OwnedSliceInner<'a, T: 'a + Sized> {
@@ -360,6 +384,9 @@ enum OwnedSliceInner<'a, T: 'a + Sized> {
     Ref(&'a [T]),
     /// A ref to an owned [`Vec`]
     Owned(Vec<T>),
+    /// A view into a reference-counted slice, shared by all clones
+    #[cfg(target_has_atomic = "ptr")]
+    Shared(Arc<[T]>, Range<usize>),
 }
 
 impl<'a, T: 'a + Sized + Serialize> Serialize for OwnedSliceInner<'a, T> {
@@ -373,6 +400,8 @@ impl<'a, T: 'a + Sized + Serialize> Serialize for OwnedSliceInner<'a, T> {
             },
             OwnedSliceInner::Ref(r) => r.serialize(se),
             OwnedSliceInner::Owned(b) => b.serialize(se),
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(arc, range) => arc[range.clone()].serialize(se),
         }
     }
 }
@@ -399,7 +428,14 @@ pub struct OwnedSlice<'a, T: 'a + Sized> {
 }
 
 impl<'a, T: 'a + Clone> Clone for OwnedSlice<'a, T> {
+    /// Clones the contents, unless they are [`OwnedSlice::shared`] already
     fn clone(&self) -> Self {
+        #[cfg(target_has_atomic = "ptr")]
+        if let OwnedSliceInner::Shared(arc, range) = &self.inner {
+            return Self {
+                inner: OwnedSliceInner::Shared(arc.clone(), range.clone()),
+            };
+        }
         Self {
             inner: OwnedSliceInner::Owned(self.as_slice().to_vec()),
         }
@@ -450,6 +486,16 @@ impl<'a, T> OwnedSlice<'a, T> {
                     None
                 }
             }
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(_arc, range) => {
+                let tmp = range.len();
+                if new_len <= tmp {
+                    range.end = range.start + new_len;
+                    Some(tmp)
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -457,6 +503,93 @@ impl<'a, T> OwnedSlice<'a, T> {
     pub fn iter(&self) -> Iter<'_, T> {
         <&Self as IntoIterator>::into_iter(self)
     }
+
+    /// Returns a view of the given `range` of this slice, without copying.
+    /// Views of a [`OwnedSlice::shared`] slice are shared as well, and can outlive this slice.
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds.
+    #[must_use]
+    pub fn sub_slice<R: RangeBounds<usize>>(&self, range: R) -> OwnedSlice<'_, T> {
+        let range = resolve_range(range, self.len());
+        #[cfg(target_has_atomic = "ptr")]
+        if let OwnedSliceInner::Shared(arc, outer) = &self.inner {
+            return OwnedSlice {
+                inner: OwnedSliceInner::Shared(
+                    arc.clone(),
+                    outer.start + range.start..outer.start + range.end,
+                ),
+            };
+        }
+        OwnedSlice {
+            inner: OwnedSliceInner::Ref(&self.as_slice()[range]),
+        }
+    }
+
+    /// Returns a mutable reference to the contents, copying them first unless this slice owns them exclusively.
+    pub fn to_mut(&mut self) -> &mut [T]
+    where
+        T: Clone,
+    {
+        #[cfg(target_has_atomic = "ptr")]
+        let exclusive = matches!(
+            &self.inner,
+            OwnedSliceInner::Shared(arc, range) if range.start == 0
+                && range.end == arc.len()
+                && Arc::strong_count(arc) == 1
+                && Arc::weak_count(arc) == 0
+        );
+        #[cfg(not(target_has_atomic = "ptr"))]
+        let exclusive = false;
+
+        if !exclusive && !matches!(self.inner, OwnedSliceInner::Owned(_)) {
+            self.inner = OwnedSliceInner::Owned(self.as_slice().to_vec());
+        }
+        match &mut self.inner {
+            OwnedSliceInner::Owned(v) => v,
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(arc, _) => Arc::get_mut(arc).unwrap(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<'a, T> OwnedSlice<'a, T> {
+    /// Create a new [`OwnedSlice`] sharing the given contents.
+    /// Clones and [`OwnedSlice::sub_slice`]s of it are cheap, as they only increase a reference count.
+    #[must_use]
+    pub fn shared(contents: Arc<[T]>) -> Self {
+        let len = contents.len();
+        Self {
+            inner: OwnedSliceInner::Shared(contents, 0..len),
+        }
+    }
+
+    /// Turn this slice into a shared one, see [`OwnedSlice::shared`].
+    /// Copies the contents once, unless they are shared already.
+    #[must_use]
+    pub fn into_shared(self) -> Self
+    where
+        T: Clone,
+    {
+        match self.inner {
+            OwnedSliceInner::RefRaw(rr, len, _) => {
+                Self::shared(Arc::from(unsafe { slice::from_raw_parts(rr, len) }))
+            }
+            OwnedSliceInner::Ref(r) => Self::shared(Arc::from(r)),
+            OwnedSliceInner::Owned(v) => Self::shared(Arc::from(v)),
+            OwnedSliceInner::Shared(arc, range) => Self {
+                inner: OwnedSliceInner::Shared(arc, range),
+            },
+        }
+    }
+
+    /// Returns `true` if this slice is [`OwnedSlice::shared`]
+    #[must_use]
+    pub fn is_shared(&self) -> bool {
+        matches!(self.inner, OwnedSliceInner::Shared(..))
+    }
 }
 
 impl<'a, 'it, T> IntoIterator for &'it OwnedSlice<'a, T> {
@@ -495,6 +628,14 @@ impl<'a, T> From<&'a [T]> for OwnedSlice<'a, T> {
     }
 }
 
+/// Create a new [`OwnedSlice`] sharing the contents of an [`Arc`]
+#[cfg(target_has_atomic = "ptr")]
+impl<'a, T> From<Arc<[T]>> for OwnedSlice<'a, T> {
+    fn from(arc: Arc<[T]>) -> Self {
+        Self::shared(arc)
+    }
+}
+
 /// Create a new [`OwnedSlice`] from a [`OwnedMutSlice`]
 impl<'a, T> From<OwnedMutSlice<'a, T>> for OwnedSlice<'a, T> {
     fn from(mut_slice: OwnedMutSlice<'a, T>) -> Self {
@@ -518,6 +659,8 @@ impl<'a, T: Sized> Deref for OwnedSlice<'a, T> {
             OwnedSliceInner::Ref(r) => r,
             OwnedSliceInner::RefRaw(rr, len, _) => unsafe { slice::from_raw_parts(*rr, *len) },
             OwnedSliceInner::Owned(v) => v.as_slice(),
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(arc, range) => &arc[range.clone()],
         }
     }
 }
@@ -531,6 +674,8 @@ where
         match self.inner {
             OwnedSliceInner::RefRaw(..) | OwnedSliceInner::Ref(_) => false,
             OwnedSliceInner::Owned(_) => true,
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(..) => true,
         }
     }
 
@@ -546,6 +691,10 @@ where
             OwnedSliceInner::Owned(v) => Self {
                 inner: OwnedSliceInner::Owned(v),
             },
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(arc, range) => Self {
+                inner: OwnedSliceInner::Shared(arc, range),
+            },
         }
     }
 }
@@ -559,6 +708,8 @@ where
         let slice = slice.into_owned();
         match slice.inner {
             OwnedSliceInner::Owned(vec) => vec,
+            #[cfg(target_has_atomic = "ptr")]
+            OwnedSliceInner::Shared(arc, range) => arc[range].to_vec(),
             _ => panic!("Could not own slice!"),
         }
     }
@@ -691,6 +842,26 @@ impl<'a, T: 'a + Sized> OwnedMutSlice<'a, T> {
     pub fn iter_mut(&mut self) -> IterMut<'_, T> {
         <&mut Self as IntoIterator>::into_iter(self)
     }
+
+    /// Returns a view of the given `range` of this slice, without copying.
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds.
+    #[must_use]
+    pub fn sub_slice<R: RangeBounds<usize>>(&self, range: R) -> OwnedSlice<'_, T> {
+        let range = resolve_range(range, self.len());
+        OwnedSlice::from(&self.as_slice()[range])
+    }
+
+    /// Returns a mutable view of the given `range` of this slice, without copying.
+    /// Writes to the view end up in this slice.
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds.
+    pub fn sub_slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> OwnedMutSlice<'_, T> {
+        let range = resolve_range(range, self.len());
+        OwnedMutSlice::from(&mut self.as_slice_mut()[range])
+    }
 }
 
 impl<'a, T: Sized> Deref for OwnedMutSlice<'a, T> {
@@ -957,3 +1128,43 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{sync::Arc, vec};
+
+    use crate::ownedref::{OwnedMutSlice, OwnedSlice};
+
+    #[test]
+    fn test_owned_slice_shared() {
+        let shared = OwnedSlice::from(vec![1, 2, 3, 4, 5]).into_shared();
+        assert!(shared.is_shared());
+
+        // Clones and sub slices don't copy
+        let clone = shared.clone();
+        let mut sub = clone.sub_slice(1..4);
+        assert!(sub.is_shared());
+        assert_eq!(&*sub, &[2, 3, 4]);
+        assert_eq!(&*sub.sub_slice(1..), &[3, 4]);
+
+        // Writes copy the shared contents
+        sub.to_mut()[0] = 42;
+        assert!(!sub.is_shared());
+        assert_eq!(&*sub, &[42, 3, 4]);
+        assert_eq!(&*shared, &[1, 2, 3, 4, 5]);
+
+        // Exclusive contents are written in place
+        let mut exclusive = OwnedSlice::from(Arc::from(vec![1, 2, 3]));
+        exclusive.to_mut()[0] = 42;
+        assert!(exclusive.is_shared());
+        assert_eq!(&*exclusive, &[42, 2, 3]);
+    }
+
+    #[test]
+    fn test_owned_mut_sub_slice() {
+        let mut slice = OwnedMutSlice::from(vec![1, 2, 3, 4, 5]);
+        slice.sub_slice_mut(3..).fill(0);
+        assert_eq!(&*slice.sub_slice(..=3), &[1, 2, 3, 0]);
+        assert_eq!(&*slice, &[1, 2, 3, 0, 0]);
+    }
+}
@@ -8,6 +8,8 @@ use core::{
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use crate::ClientId;
+
 #[cfg(feature = "alloc")]
 pub mod loaded_dice;
 
@@ -201,6 +203,17 @@ pub trait Rand: Debug + Serialize + DeserializeOwned {
     }
 }
 
+/// A [`Rand`] that can be split into independent streams, all derived from a single seed.
+///
+/// Streams come in groups, such as one group per machine, and one stream per client on it.
+/// Every `(group, stream)` pair generates its own sequence, which does not overlap with the others,
+/// and which is the same every time for the same seed.
+/// This makes multi-client campaigns reproducible, see `LibAFL`'s `Launcher`.
+pub trait StreamRand: Rand {
+    /// Creates the rand for the given `stream` in the given `group`, derived from `seed`
+    fn for_stream(seed: u64, group: u64, stream: u64) -> Self;
+}
+
 macro_rules! impl_default_new {
     ($rand: ty) => {
         impl Default for $rand {
@@ -226,6 +239,7 @@ impl_default_new!(Lehmer64Rand);
 impl_default_new!(RomuTrioRand);
 impl_default_new!(RomuDuoJrRand);
 impl_default_new!(Sfc64Rand);
+impl_default_new!(ChaChaRand);
 
 macro_rules! impl_rng_core {
     ($rand: ty) => {
@@ -256,6 +270,7 @@ impl_rng_core!(Lehmer64Rand);
 impl_rng_core!(RomuTrioRand);
 impl_rng_core!(RomuDuoJrRand);
 impl_rng_core!(Sfc64Rand);
+impl_rng_core!(ChaChaRand);
 
 /// xoshiro256++ PRNG: <https://prng.di.unimi.it/>
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -300,6 +315,58 @@ impl Xoshiro256PlusPlusRand {
         rand.set_seed(seed);
         rand
     }
+
+    /// Advances the state as if [`Rand::next`] had been called 2^128 times.
+    /// The sequences between two jumps do not overlap, so this gives up to 2^128 independent streams.
+    pub fn jump(&mut self) {
+        self.jump_by(&[
+            0x180e_c6d3_3cfd_0aba,
+            0xd5a6_1266_f0c9_392c,
+            0xa958_2618_e03f_c9aa,
+            0x39ab_dc45_29b1_661c,
+        ]);
+    }
+
+    /// Advances the state as if [`Rand::next`] had been called 2^192 times.
+    /// Each of the 2^64 starting points this gives can be split into 2^64 streams using [`Self::jump`].
+    pub fn long_jump(&mut self) {
+        self.jump_by(&[
+            0x76e1_5d3e_fefd_cbbf,
+            0xc500_4e44_1c52_2fb3,
+            0x7771_0069_854e_e241,
+            0x3910_9bb0_2acb_e635,
+        ]);
+    }
+
+    // https://prng.di.unimi.it/xoshiro256plusplus.c
+    fn jump_by(&mut self, polynomial: &[u64; 4]) {
+        let mut s = [0; 4];
+        for word in polynomial {
+            for bit in 0..64 {
+                if word & (1 << bit) != 0 {
+                    for (s, state) in s.iter_mut().zip(self.s) {
+                        *s ^= state;
+                    }
+                }
+                self.next();
+            }
+        }
+        self.s = s;
+    }
+}
+
+impl StreamRand for Xoshiro256PlusPlusRand {
+    /// Long-jumps `group` times, then jumps `stream` times, so this is linear in both
+    fn for_stream(seed: u64, group: u64, stream: u64) -> Self {
+        let mut rand = Self::with_seed(seed);
+        for _ in 0..group {
+            rand.long_jump();
+        }
+        for _ in 0..stream {
+            rand.jump();
+        }
+        rand
+    }
 }
 
 /// Xorshift64 PRNG
@@ -492,6 +559,163 @@ impl Rand for Sfc64Rand {
     }
 }
 
+/// The number of 32-bit words in a `ChaCha` block
+const CHACHA_BLOCK_WORDS: usize = 16;
+
+/// The `ChaCha20` stream cipher by Daniel J. Bernstein, used as a PRNG.
+///
+/// Much slower than the other rands in this module, but its output is statistically independent
+/// for every (seed, stream) pair. Give each client of a multi-client campaign the same seed and its own stream,
+/// see [`ChaChaRand::for_client`], and the whole campaign is exactly reproducible from that one seed,
+/// without the clients ever running into correlated sequences.
+///
+/// The block layout is the original one, with a 64-bit block counter followed by the 64-bit stream id as nonce.
+/// See <https://cr.yp.to/chacha.html>.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ChaChaRand {
+    key: [u32; 8],
+    stream: u64,
+    counter: u64,
+    block: [u32; CHACHA_BLOCK_WORDS],
+    idx: usize,
+}
+
+impl ChaChaRand {
+    /// Creates a new [`ChaChaRand`] with the given seed, on stream `0`.
+    #[must_use]
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_seed_and_stream(seed, 0)
+    }
+
+    /// Creates a new [`ChaChaRand`] with the given seed, on the given stream.
+    #[must_use]
+    pub fn with_seed_and_stream(seed: u64, stream: u64) -> Self {
+        let mut rand = Self {
+            key: [0; 8],
+            stream,
+            counter: 0,
+            block: [0; CHACHA_BLOCK_WORDS],
+            idx: CHACHA_BLOCK_WORDS,
+        };
+        rand.set_seed(seed);
+        rand
+    }
+
+    /// Creates a new [`ChaChaRand`] for the given client, deriving its stream from the client id.
+    ///
+    /// All clients started with the same `seed` produce independent sequences,
+    /// and restarting a client with the same `seed` reproduces its sequence.
+    #[must_use]
+    pub fn for_client(seed: u64, client_id: ClientId) -> Self {
+        Self::with_seed_and_stream(seed, u64::from(client_id.0))
+    }
+
+    /// The stream this rand currently generates
+    #[must_use]
+    pub fn stream(&self) -> u64 {
+        self.stream
+    }
+
+    /// Switches to the given stream, restarting it from its first block.
+    pub fn set_stream(&mut self, stream: u64) {
+        self.stream = stream;
+        self.counter = 0;
+        self.idx = CHACHA_BLOCK_WORDS;
+    }
+
+    #[inline]
+    fn quarter_round(
+        state: &mut [u32; CHACHA_BLOCK_WORDS],
+        a: usize,
+        b: usize,
+        c: usize,
+        d: usize,
+    ) {
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(16);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(12);
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(8);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(7);
+    }
+
+    /// Computes the next block of the current stream
+    #[allow(clippy::cast_possible_truncation)]
+    fn refill(&mut self) {
+        // "expand 32-byte k"
+        let mut input = [
+            0x6170_7865,
+            0x3320_646e,
+            0x7962_2d32,
+            0x6b20_6574,
+            self.key[0],
+            self.key[1],
+            self.key[2],
+            self.key[3],
+            self.key[4],
+            self.key[5],
+            self.key[6],
+            self.key[7],
+            self.counter as u32,
+            (self.counter >> 32) as u32,
+            self.stream as u32,
+            (self.stream >> 32) as u32,
+        ];
+        let mut x = input;
+        for _ in 0..10 {
+            Self::quarter_round(&mut x, 0, 4, 8, 12);
+            Self::quarter_round(&mut x, 1, 5, 9, 13);
+            Self::quarter_round(&mut x, 2, 6, 10, 14);
+            Self::quarter_round(&mut x, 3, 7, 11, 15);
+            Self::quarter_round(&mut x, 0, 5, 10, 15);
+            Self::quarter_round(&mut x, 1, 6, 11, 12);
+            Self::quarter_round(&mut x, 2, 7, 8, 13);
+            Self::quarter_round(&mut x, 3, 4, 9, 14);
+        }
+        for (word, x) in input.iter_mut().zip(x) {
+            *word = word.wrapping_add(x);
+        }
+
+        self.block = input;
+        self.counter = self.counter.wrapping_add(1);
+        self.idx = 0;
+    }
+}
+
+impl StreamRand for ChaChaRand {
+    /// Uses the upper 32 bits of the stream id for the `group`, and the lower 32 bits for the `stream`
+    fn for_stream(seed: u64, group: u64, stream: u64) -> Self {
+        Self::with_seed_and_stream(seed, group << 32 | (stream & 0xffff_ffff))
+    }
+}
+
+impl Rand for ChaChaRand {
+    /// Derives the key from the seed, and restarts the current stream
+    #[allow(clippy::cast_possible_truncation)]
+    fn set_seed(&mut self, mut seed: u64) {
+        for pair in self.key.chunks_exact_mut(2) {
+            let val = splitmix64(&mut seed);
+            pair[0] = val as u32;
+            pair[1] = (val >> 32) as u32;
+        }
+        self.counter = 0;
+        self.idx = CHACHA_BLOCK_WORDS;
+    }
+
+    #[inline]
+    fn next(&mut self) -> u64 {
+        if self.idx >= CHACHA_BLOCK_WORDS {
+            self.refill();
+        }
+        let lo = u64::from(self.block[self.idx]);
+        let hi = u64::from(self.block[self.idx + 1]);
+        self.idx += 2;
+        hi << 32 | lo
+    }
+}
+
 /// fake rand, for testing purposes
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 #[allow(clippy::upper_case_acronyms)]
@@ -528,9 +752,12 @@ impl XkcdRand {
 
 #[cfg(test)]
 mod tests {
-    use crate::rands::{
-        Rand, RomuDuoJrRand, RomuTrioRand, Sfc64Rand, StdRand, XorShift64Rand,
-        Xoshiro256PlusPlusRand,
+    use crate::{
+        rands::{
+            ChaChaRand, Rand, RomuDuoJrRand, RomuTrioRand, Sfc64Rand, StdRand, StreamRand,
+            XorShift64Rand, Xoshiro256PlusPlusRand,
+        },
+        ClientId,
     };
 
     fn test_single_rand<R: Rand>(rand: &mut R) {
@@ -550,6 +777,7 @@ mod tests {
         test_single_rand(&mut XorShift64Rand::with_seed(0));
         test_single_rand(&mut Xoshiro256PlusPlusRand::with_seed(0));
         test_single_rand(&mut Sfc64Rand::with_seed(0));
+        test_single_rand(&mut ChaChaRand::with_seed(0));
     }
 
     #[test]
@@ -647,6 +875,90 @@ mod tests {
             assert_eq!(v, u);
         }
     }
+
+    #[test]
+    fn test_chacha_golden() {
+        // https://www.rfc-editor.org/rfc/rfc7539#section-2.3.2
+        // The 32-bit counter and 96-bit nonce of the RFC map onto our 64-bit counter and 64-bit stream
+        let golden: [u64; 8] = [
+            0x15593bd1e4e7f110,
+            0xc47120a31fdd0f50,
+            0x0368c033c7f4d1c7,
+            0x4e6cd4c39aaa2204,
+            0x09aa9f07466482d2,
+            0xa2028bd905d7c214,
+            0xb94e16ded19c12b5,
+            0x4e3c50a2e883d0cb,
+        ];
+
+        let mut s = ChaChaRand::with_seed(0);
+        s.key = [
+            0x03020100, 0x07060504, 0x0b0a0908, 0x0f0e0d0c, 0x13121110, 0x17161514, 0x1b1a1918,
+            0x1f1e1d1c,
+        ];
+        s.stream = 0x4a000000;
+        s.counter = 1 | (0x09000000 << 32);
+        for v in golden {
+            let u = s.next();
+            assert_eq!(v, u);
+        }
+    }
+
+    #[test]
+    fn test_chacha_streams() {
+        let mut first = [0; 32];
+        let mut s = ChaChaRand::for_client(1337, ClientId(0));
+        first.fill_with(|| s.next());
+
+        let mut second = [0; 32];
+        let mut s = ChaChaRand::for_client(1337, ClientId(1));
+        second.fill_with(|| s.next());
+        assert_ne!(first, second);
+
+        // Same seed and client, same sequence, also after switching streams back and forth
+        let mut s = ChaChaRand::for_client(1337, ClientId(1));
+        s.set_stream(0);
+        s.next();
+        s.set_stream(1);
+        let mut again = [0; 32];
+        again.fill_with(|| s.next());
+        assert_eq!(second, again);
+
+        // Reseeding restarts the stream
+        s.set_seed(1337);
+        assert_eq!(s.stream(), 1);
+        assert_eq!(s.next(), second[0]);
+
+        assert_eq!(ChaChaRand::for_stream(1337, 0, 1).next(), second[0]);
+        assert_ne!(ChaChaRand::for_stream(1337, 1, 1).next(), second[0]);
+    }
+
+    #[test]
+    fn test_xoshiro_jump_golden() {
+        // Computed with https://prng.di.unimi.it/xoshiro256plusplus.c
+        let mut s = Xoshiro256PlusPlusRand { s: [1, 2, 3, 4] };
+        s.jump();
+        assert_eq!(s.next(), 0xec879073673df437);
+
+        let mut s = Xoshiro256PlusPlusRand { s: [1, 2, 3, 4] };
+        s.long_jump();
+        assert_eq!(s.next(), 0xb5c4ea370b330bf5);
+    }
+
+    #[test]
+    fn test_xoshiro_streams() {
+        let mut s = Xoshiro256PlusPlusRand::with_seed(1337);
+        s.jump();
+        assert_eq!(
+            Xoshiro256PlusPlusRand::for_stream(1337, 0, 1).next(),
+            s.next()
+        );
+
+        let first = Xoshiro256PlusPlusRand::for_stream(1337, 0, 0).next();
+        assert_ne!(Xoshiro256PlusPlusRand::for_stream(1337, 0, 1).next(), first);
+        assert_ne!(Xoshiro256PlusPlusRand::for_stream(1337, 1, 0).next(), first);
+        assert_eq!(Xoshiro256PlusPlusRand::for_stream(1337, 0, 0).next(), first);
+    }
 }
 
 #[cfg(feature = "python")]
@@ -3,7 +3,6 @@
 use alloc::boxed::Box;
 #[cfg(feature = "unsafe_stable_anymap")]
 use alloc::string::{String, ToString};
-#[cfg(feature = "unsafe_stable_anymap")]
 use core::any::type_name;
 #[cfg(not(feature = "unsafe_stable_anymap"))]
 use core::any::TypeId;
@@ -57,6 +56,10 @@ pub trait SerdeAny: Any + erased_serde::Serialize + Debug {
     fn as_any_mut(&mut self) -> &mut dyn Any;
     /// returns this as boxed Any trait
     fn as_any_boxed(self: Box<Self>) -> Box<dyn Any>;
+    /// returns the name of this type, as used by [`SerdeAnyMap::to_serialized_entries`]
+    fn type_name(&self) -> &'static str {
+        type_name::<Self>()
+    }
 }
 
 /// Wrap a type for serialization
@@ -111,9 +114,22 @@ pub mod serdeany_registry {
 
     use alloc::{
         boxed::Box,
+        format,
         string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+    use core::{
+        any::TypeId,
+        borrow::Borrow,
+        fmt,
+        hash::{BuildHasherDefault, Hash},
+    };
+    #[cfg(feature = "serdeany_linkme")]
+    use core::{
+        ptr,
+        sync::atomic::{AtomicPtr, Ordering},
     };
-    use core::{any::TypeId, fmt, hash::BuildHasherDefault};
 
     use hashbrown::{
         hash_map::{Values, ValuesMut},
@@ -124,7 +140,7 @@ pub mod serdeany_registry {
     use crate::{
         serdeany::{
             type_repr, type_repr_owned, DeserializeCallback, DeserializeCallbackSeed, SerdeAny,
-            TypeRepr,
+            TypeRepr, Wrap,
         },
         Error,
     };
@@ -132,6 +148,189 @@ pub mod serdeany_registry {
     /// A [`HashMap`] that maps from [`TypeRepr`] to a deserializer and its [`TypeId`].
     type DeserializeCallbackMap = HashMap<TypeRepr, (DeserializeCallback<dyn SerdeAny>, TypeId)>;
 
+    /// A [`SerdeAny`] type registered at compile time, collected in the [`SERDEANY_STATIC_REGISTRY`].
+    ///
+    /// Created by [`crate::impl_serdeany`] with the `serdeany_linkme` feature, you should not need to create it yourself.
+    #[cfg(feature = "serdeany_linkme")]
+    #[derive(Debug)]
+    pub struct StaticRegistration {
+        type_name: fn() -> &'static str,
+        type_id: fn() -> TypeId,
+        deserialize: DeserializeCallback<dyn SerdeAny>,
+    }
+
+    #[cfg(feature = "serdeany_linkme")]
+    impl StaticRegistration {
+        /// The registration of the type `T`
+        #[must_use]
+        pub const fn of<T>() -> Self
+        where
+            T: SerdeAny + Serialize + serde::de::DeserializeOwned,
+        {
+            Self {
+                type_name: core::any::type_name::<T>,
+                type_id: TypeId::of::<T>,
+                deserialize: deserialize_boxed::<T>,
+            }
+        }
+
+        /// The name of the registered type
+        #[must_use]
+        pub fn type_name(&self) -> &'static str {
+            (self.type_name)()
+        }
+
+        /// The [`TypeRepr`] of the registered type
+        #[must_use]
+        pub fn type_repr(&self) -> TypeRepr {
+            #[cfg(not(feature = "unsafe_stable_anymap"))]
+            {
+                crate::anymap::unpack_type_id((self.type_id)())
+            }
+            #[cfg(feature = "unsafe_stable_anymap")]
+            {
+                self.type_name().to_string()
+            }
+        }
+    }
+
+    /// All [`SerdeAny`] types registered at compile time, across all crates linked into the binary.
+    ///
+    /// Unlike the [`RegistryBuilder`], this needs no code to run at startup, so it also works in `no_std`,
+    /// and for types of plugin crates nobody remembers to register in `main()`.
+    #[cfg(feature = "serdeany_linkme")]
+    #[linkme::distributed_slice]
+    pub static SERDEANY_STATIC_REGISTRY: [StaticRegistration];
+
+    /// The positions of the entries of the [`SERDEANY_STATIC_REGISTRY`], by [`TypeRepr`] and by name
+    #[cfg(feature = "serdeany_linkme")]
+    #[derive(Debug)]
+    struct StaticRegistryIndex {
+        by_type_repr: HashMap<TypeRepr, usize>,
+        by_name: HashMap<&'static str, usize>,
+    }
+
+    /// The [`StaticRegistryIndex`], built on first use
+    #[cfg(feature = "serdeany_linkme")]
+    static STATIC_REGISTRY_INDEX: AtomicPtr<StaticRegistryIndex> = AtomicPtr::new(ptr::null_mut());
+
+    /// The [`StaticRegistryIndex`], so lookups do not scan the whole [`SERDEANY_STATIC_REGISTRY`].
+    ///
+    /// Threads racing to build it each build one, the first to finish wins, and the others drop theirs.
+    #[cfg(feature = "serdeany_linkme")]
+    fn static_registry_index() -> &'static StaticRegistryIndex {
+        let index = STATIC_REGISTRY_INDEX.load(Ordering::Acquire);
+        if !index.is_null() {
+            // # Safety
+            // Once set, the index is never freed, nor changed
+            return unsafe { &*index };
+        }
+
+        let built = Box::into_raw(Box::new(StaticRegistryIndex {
+            by_type_repr: SERDEANY_STATIC_REGISTRY
+                .iter()
+                .enumerate()
+                .map(|(idx, registration)| (registration.type_repr(), idx))
+                .collect(),
+            by_name: SERDEANY_STATIC_REGISTRY
+                .iter()
+                .enumerate()
+                .map(|(idx, registration)| (registration.type_name(), idx))
+                .collect(),
+        }));
+        match STATIC_REGISTRY_INDEX.compare_exchange(
+            ptr::null_mut(),
+            built,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            // # Safety
+            // The index is leaked on purpose, to live for the rest of the program
+            Ok(_) => unsafe { &*built },
+            Err(existing) => {
+                // # Safety
+                // `built` was never shared, and `existing` is never freed
+                unsafe {
+                    drop(Box::from_raw(built));
+                    &*existing
+                }
+            }
+        }
+    }
+
+    /// Deserialize a boxed `T`, for the deserializer of a [`StaticRegistration`]
+    #[cfg(feature = "serdeany_linkme")]
+    fn deserialize_boxed<T>(
+        de: &mut dyn erased_serde::Deserializer,
+    ) -> Result<Box<dyn SerdeAny>, erased_serde::Error>
+    where
+        T: SerdeAny + serde::de::DeserializeOwned,
+    {
+        Ok(Box::new(erased_serde::deserialize::<T>(de)?))
+    }
+
+    /// The deserializer for the type with the given [`TypeRepr`],
+    /// looked up in the [`RegistryBuilder`] first, then in the compile-time registry, if enabled.
+    fn deserializer_for<Q>(type_repr: &Q) -> Option<DeserializeCallback<dyn SerdeAny>>
+    where
+        TypeRepr: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let registry = unsafe { &*core::ptr::addr_of!(REGISTRY) };
+        if let Some((cb, _)) = registry
+            .deserializers
+            .as_ref()
+            .and_then(|deserializers| deserializers.get(type_repr))
+        {
+            return Some(*cb);
+        }
+        #[cfg(feature = "serdeany_linkme")]
+        if let Some(idx) = static_registry_index().by_type_repr.get(type_repr) {
+            return Some(SERDEANY_STATIC_REGISTRY[*idx].deserialize);
+        }
+        None
+    }
+
+    /// The [`TypeRepr`] of the registered type called `type_name`
+    #[allow(clippy::clone_on_copy)]
+    fn type_repr_by_name(type_name: &str) -> Option<TypeRepr> {
+        let registry = unsafe { &*core::ptr::addr_of!(REGISTRY) };
+        if let Some(type_repr) = registry
+            .names
+            .as_ref()
+            .and_then(|names| names.get(type_name))
+        {
+            return Some(type_repr.clone());
+        }
+        #[cfg(feature = "serdeany_linkme")]
+        if let Some(idx) = static_registry_index().by_name.get(type_name) {
+            return Some(SERDEANY_STATIC_REGISTRY[*idx].type_repr());
+        }
+        None
+    }
+
+    /// Deserialize a value of the registered type called `type_name` from `bytes`, serialized with `postcard`.
+    fn deserialize_by_name(
+        type_name: &str,
+        bytes: &[u8],
+    ) -> Result<(TypeRepr, Box<dyn SerdeAny>), Error> {
+        let Some((type_repr, cb)) = type_repr_by_name(type_name).and_then(|type_repr| {
+            let cb = deserializer_for(&type_repr)?;
+            Some((type_repr, cb))
+        }) else {
+            return Err(Error::key_not_found(format!(
+                "Type {type_name} is not registered"
+            )));
+        };
+
+        let mut deserializer = postcard::Deserializer::from_bytes(bytes);
+        let value = cb(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))
+        .map_err(|err| Error::serialize(format!("Failed to deserialize {type_name}: {err}")))?;
+        Ok((type_repr, value))
+    }
+
     /// Visitor object used internally for the [`crate::serdeany::SerdeAny`] registry.
     #[derive(Debug)]
     pub struct BoxDynVisitor {}
@@ -148,15 +347,11 @@ pub mod serdeany_registry {
             V: serde::de::SeqAccess<'de>,
         {
             let id: TypeRepr = visitor.next_element()?.unwrap();
-            let cb = unsafe {
-                REGISTRY
-                    .deserializers
-                    .as_ref()
-                    .expect("Empty types registry")
-                    .get(&id)
-                    .expect("Cannot deserialize an unregistered type")
-                    .0
-            };
+            let cb = deserializer_for(&id).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "Cannot deserialize the unregistered type {id:?}, register it or enable serdeany_autoreg or serdeany_linkme"
+                ))
+            })?;
             let seed = DeserializeCallbackSeed::<dyn crate::serdeany::SerdeAny> { cb };
             let obj: Self::Value = visitor.next_element_seed(seed)?.unwrap();
             Ok(obj)
@@ -166,6 +361,8 @@ pub mod serdeany_registry {
     #[allow(unused_qualifications)]
     struct Registry {
         deserializers: Option<DeserializeCallbackMap>,
+        /// The [`TypeRepr`] of each registered type, by its name
+        names: Option<HashMap<&'static str, TypeRepr>>,
         finalized: bool,
     }
 
@@ -189,6 +386,10 @@ pub mod serdeany_registry {
 
             #[cfg(feature = "unsafe_stable_anymap")]
             assert_eq!(_entry.1, TypeId::of::<T>(), "Fatal safety error: TypeId of type {} is not equals to the deserializer's TypeId for this type! Two registered types have the same type_name!", type_repr::<T>());
+
+            self.names
+                .get_or_insert_with(HashMap::default)
+                .insert(core::any::type_name::<T>(), type_repr_owned::<T>());
         }
 
         pub fn finalize(&mut self) {
@@ -198,6 +399,7 @@ pub mod serdeany_registry {
 
     static mut REGISTRY: Registry = Registry {
         deserializers: None,
+        names: None,
         finalized: false,
     };
 
@@ -355,15 +557,8 @@ pub mod serdeany_registry {
             let type_repr = &type_repr;
 
             assert!(
-                        unsafe {
-                            REGISTRY
-                                .deserializers
-                                .as_ref()
-                                .expect("Empty types registry")
-                                .get(type_repr)
-                                .is_some()
-                        },
-                        "Type {} was inserted without registration! Call RegistryBuilder::register::<{}>() or use serdeany_autoreg.",
+                        deserializer_for(type_repr).is_some(),
+                        "Type {} was inserted without registration! Call RegistryBuilder::register::<{}>() or use serdeany_autoreg or serdeany_linkme.",
                         core::any::type_name::<T>(),
                         core::any::type_name::<T>()
                     );
@@ -416,6 +611,29 @@ pub mod serdeany_registry {
             self.map.contains_key(type_repr)
         }
 
+        /// Serialize each element on its own, as its type name and its value serialized with `postcard`.
+        /// Unlike the serialized map, the entries do not depend on the build of the binary,
+        /// and can be inspected, migrated, or dropped one by one, see [`SerdeAnyMap::insert_serialized`].
+        pub fn to_serialized_entries(&self) -> Result<Vec<(String, Vec<u8>)>, Error> {
+            self.map
+                .values()
+                .map(|value| {
+                    Ok((
+                        value.type_name().to_string(),
+                        postcard::to_allocvec(&Wrap(value.as_ref()))?,
+                    ))
+                })
+                .collect()
+        }
+
+        /// Deserialize an element from an entry of [`SerdeAnyMap::to_serialized_entries`] and insert it.
+        /// Fails if no type called `type_name` is registered, or if `bytes` do not match that type.
+        pub fn insert_serialized(&mut self, type_name: &str, bytes: &[u8]) -> Result<(), Error> {
+            let (type_repr, value) = deserialize_by_name(type_name, bytes)?;
+            self.map.insert(type_repr, value);
+            Ok(())
+        }
+
         /// Create a new [`SerdeAnyMap`].
         #[must_use]
         pub fn new() -> Self {
@@ -618,15 +836,8 @@ pub mod serdeany_registry {
             let type_repr = &type_repr;
 
             assert!(
-                        unsafe {
-                            REGISTRY
-                                .deserializers
-                                .as_ref()
-                                .expect("Empty types registry")
-                                .get(type_repr)
-                                .is_some()
-                        },
-                        "Type {} was inserted without registration! Call RegistryBuilder::register::<{}>() or use serdeany_autoreg.",
+                        deserializer_for(type_repr).is_some(),
+                        "Type {} was inserted without registration! Call RegistryBuilder::register::<{}>() or use serdeany_autoreg or serdeany_linkme.",
                         core::any::type_name::<T>(),
                         core::any::type_name::<T>()
                     );
@@ -744,6 +955,62 @@ pub mod serdeany_registry {
             }
         }
 
+        /// Iterate over the names of all elements in this map, of any type.
+        /// A name used by elements of several types is returned once per type.
+        pub fn names(&self) -> impl Iterator<Item = &str> {
+            self.map
+                .values()
+                .flat_map(|values| values.keys().map(String::as_str))
+        }
+
+        /// Remove all elements, of any type, whose name does not satisfy `keep`.
+        /// Returns the number of removed elements.
+        pub fn retain_names<F>(&mut self, mut keep: F) -> usize
+        where
+            F: FnMut(&str) -> bool,
+        {
+            let mut removed = 0;
+            for values in self.map.values_mut() {
+                let len = values.len();
+                values.retain(|name, _| keep(name));
+                removed += len - values.len();
+            }
+            self.map.retain(|_, values| !values.is_empty());
+            removed
+        }
+
+        /// Serialize each element on its own, as its type name, its name, and its value serialized with `postcard`,
+        /// see [`SerdeAnyMap::to_serialized_entries`].
+        pub fn to_serialized_entries(&self) -> Result<Vec<(String, String, Vec<u8>)>, Error> {
+            let mut entries = vec![];
+            for values in self.map.values() {
+                for (name, value) in values {
+                    entries.push((
+                        value.type_name().to_string(),
+                        name.clone(),
+                        postcard::to_allocvec(&Wrap(value.as_ref()))?,
+                    ));
+                }
+            }
+            Ok(entries)
+        }
+
+        /// Deserialize an element from an entry of [`NamedSerdeAnyMap::to_serialized_entries`] and insert it under `name`.
+        /// Fails if no type called `type_name` is registered, or if `bytes` do not match that type.
+        pub fn insert_serialized(
+            &mut self,
+            type_name: &str,
+            name: &str,
+            bytes: &[u8],
+        ) -> Result<(), Error> {
+            let (type_repr, value) = deserialize_by_name(type_name, bytes)?;
+            self.map
+                .entry(type_repr)
+                .or_default()
+                .insert(name.into(), value);
+            Ok(())
+        }
+
         /// Create a new `SerdeAny` map.
         #[must_use]
         pub fn new() -> Self {
@@ -786,11 +1053,31 @@ impl<'de> Deserialize<'de> for Box<dyn crate::serdeany::SerdeAny> {
     }
 }
 
+/// Register a `SerdeAny` type in the [`SERDEANY_STATIC_REGISTRY`], at compile time
+///
+/// Used with the `serdeany_linkme` feature, which takes precedence over `serdeany_autoreg`.
+#[cfg(feature = "serdeany_linkme")]
+#[macro_export]
+macro_rules! create_register {
+    ($struct_type:ty) => {
+        const _: () = {
+            #[$crate::linkme::distributed_slice($crate::serdeany::SERDEANY_STATIC_REGISTRY)]
+            #[linkme(crate = $crate::linkme)]
+            static REGISTRATION: $crate::serdeany::StaticRegistration =
+                $crate::serdeany::StaticRegistration::of::<$struct_type>();
+        };
+    };
+}
+
 /// Register a `SerdeAny` type in the [`RegistryBuilder`]
 ///
 /// Do nothing for without the `serdeany_autoreg` feature, you'll have to register it manually
 /// in `main()` with [`RegistryBuilder::register`] or using `<T>::register()`.
-#[cfg(all(feature = "serdeany_autoreg", not(miri)))]
+#[cfg(all(
+    feature = "serdeany_autoreg",
+    not(feature = "serdeany_linkme"),
+    not(miri)
+))]
 #[macro_export]
 macro_rules! create_register {
     ($struct_type:ty) => {
@@ -812,7 +1099,10 @@ macro_rules! create_register {
 ///
 /// Do nothing for without the `serdeany_autoreg` feature, you'll have to register it manually
 /// in `main()` with [`RegistryBuilder::register`] or using `<T>::register()`.
-#[cfg(not(all(feature = "serdeany_autoreg", not(miri))))]
+#[cfg(not(any(
+    feature = "serdeany_linkme",
+    all(feature = "serdeany_autoreg", not(miri))
+)))]
 #[macro_export]
 macro_rules! create_register {
     ($struct_type:ty) => {};
@@ -929,4 +1219,25 @@ mod tests {
         );
         assert!(postcard::from_bytes::<inner::MyType>(&serialized).is_err());
     }
+
+    #[test]
+    #[cfg(feature = "serdeany_linkme")]
+    fn test_static_registry() {
+        use crate::serdeany::{SerdeAnyMap, SERDEANY_STATIC_REGISTRY};
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct StaticType(u64);
+        impl_serdeany!(StaticType);
+
+        assert!(SERDEANY_STATIC_REGISTRY
+            .iter()
+            .any(|registration| registration.type_name() == core::any::type_name::<StaticType>()));
+
+        // Never registered at runtime
+        let mut map = SerdeAnyMap::new();
+        map.insert(StaticType(42));
+        let serialized = postcard::to_allocvec(&map).unwrap();
+        let map: SerdeAnyMap = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(map.get::<StaticType>().unwrap().0, 42);
+    }
 }
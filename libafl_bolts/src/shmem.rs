@@ -18,6 +18,8 @@ use std::io::Read;
 use std::io::Write;
 
 use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use unix_shmem::memfd::{MemfdShMem, MemfdShMemProvider};
 #[cfg(all(
     feature = "std",
     unix,
@@ -27,7 +29,7 @@ pub use unix_shmem::{MmapShMem, MmapShMemProvider};
 #[cfg(all(feature = "std", unix, not(target_os = "haiku")))]
 pub use unix_shmem::{UnixShMem, UnixShMemProvider};
 #[cfg(all(windows, feature = "std"))]
-pub use win32_shmem::{Win32ShMem, Win32ShMemProvider};
+pub use win32_shmem::{Win32ShMem, Win32ShMemNamespace, Win32ShMemProvider};
 
 #[cfg(all(unix, feature = "std", not(target_os = "haiku")))]
 use crate::os::pipes::Pipe;
@@ -316,6 +318,23 @@ pub trait ShMemProvider: Clone + Default + Debug {
     }
 }
 
+/// If and how the maps of a [`ShMemProvider`] are backed by huge pages.
+///
+/// Many large maps, like the LLMP pages of a broker with many clients, put a lot of pressure on the TLB.
+/// Huge pages take it off, at the cost of rounding the maps up to the huge page size for [`HugePages::Explicit`].
+/// Providers fall back to regular pages if huge pages are not available.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HugePages {
+    /// Regular pages
+    #[default]
+    Off,
+    /// Ask the kernel for transparent huge pages, see `/sys/kernel/mm/transparent_hugepage/shmem_enabled`.
+    /// This is only a hint, and does not waste any memory.
+    Transparent,
+    /// Allocate the maps from the reserved huge pages, see `/proc/sys/vm/nr_hugepages`
+    Explicit,
+}
+
 /// An [`ShMemProvider`] that does not provide any [`ShMem`].
 /// This is mainly for testing and type magic.
 /// The resulting [`NopShMem`] is backed by a simple byte buffer to do some simple non-shared things with.
@@ -642,6 +661,42 @@ pub mod unix_shmem {
     #[cfg(not(target_os = "android"))]
     pub type UnixShMem = default::CommonUnixShMem;
 
+    /// Ask the kernel to back the mapping with transparent huge pages.
+    /// This is only a hint, so failures are logged and ignored.
+    #[cfg(target_os = "linux")]
+    fn advise_transparent_huge_pages(map: *mut u8, len: usize) {
+        if unsafe { libc::madvise(map.cast(), len, libc::MADV_HUGEPAGE) } != 0 {
+            log::debug!(
+                "Could not advise transparent huge pages: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    /// Transparent huge pages are only supported on Linux
+    #[cfg(not(target_os = "linux"))]
+    fn advise_transparent_huge_pages(_map: *mut u8, _len: usize) {}
+
+    /// The size of the default huge pages, as reported by `/proc/meminfo`
+    #[cfg(target_os = "linux")]
+    fn huge_page_size() -> Result<usize, crate::Error> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo")?;
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix("Hugepagesize:"))
+            .and_then(|size| size.trim().strip_suffix("kB"))
+            .and_then(|size| size.trim().parse::<usize>().ok())
+            .map(|size| size * 1024)
+            .ok_or_else(|| crate::Error::unsupported("This system does not support huge pages"))
+    }
+
+    /// `map_size`, rounded up to the size of the default huge pages
+    #[cfg(target_os = "linux")]
+    fn round_up_to_huge_page(map_size: usize) -> Result<usize, crate::Error> {
+        let huge_page_size = huge_page_size()?;
+        Ok((map_size + huge_page_size - 1) / huge_page_size * huge_page_size)
+    }
+
     #[cfg(all(unix, feature = "std", not(target_os = "android")))]
     mod default {
         #[cfg(target_vendor = "apple")]
@@ -658,9 +713,12 @@ pub mod unix_shmem {
             shmdt, shmget,
         };
 
+        use super::advise_transparent_huge_pages;
+        #[cfg(target_os = "linux")]
+        use super::round_up_to_huge_page;
         use crate::{
             rands::{Rand, StdRand},
-            shmem::{ShMem, ShMemId, ShMemProvider},
+            shmem::{HugePages, ShMem, ShMemId, ShMemProvider},
             Error,
         };
 
@@ -830,7 +888,26 @@ pub mod unix_shmem {
         /// A [`ShMemProvider`] which uses [`shm_open`] and [`mmap`] to provide shared memory mappings.
         #[cfg(unix)]
         #[derive(Clone, Debug)]
-        pub struct MmapShMemProvider {}
+        pub struct MmapShMemProvider {
+            huge_pages: HugePages,
+        }
+
+        impl MmapShMemProvider {
+            /// Back the maps with huge pages, see [`HugePages`].
+            /// Files opened with [`shm_open`] cannot be allocated from the reserved huge pages,
+            /// so [`HugePages::Explicit`] falls back to [`HugePages::Transparent`].
+            #[must_use]
+            pub fn with_huge_pages(mut self, huge_pages: HugePages) -> Self {
+                self.huge_pages = huge_pages;
+                self
+            }
+
+            fn advise(&self, shmem: &MmapShMem) {
+                if self.huge_pages != HugePages::Off {
+                    advise_transparent_huge_pages(shmem.map, shmem.map_size);
+                }
+            }
+        }
 
         unsafe impl Send for MmapShMemProvider {}
 
@@ -847,12 +924,16 @@ pub mod unix_shmem {
             type ShMem = MmapShMem;
 
             fn new() -> Result<Self, Error> {
-                Ok(Self {})
+                Ok(Self {
+                    huge_pages: HugePages::Off,
+                })
             }
             fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
                 let mut rand = StdRand::with_seed(crate::rands::random_seed());
                 let id = rand.next() as u32;
-                MmapShMem::new(map_size, id)
+                let shmem = MmapShMem::new(map_size, id)?;
+                self.advise(&shmem);
+                Ok(shmem)
             }
 
             fn shmem_from_id_and_size(
@@ -860,7 +941,9 @@ pub mod unix_shmem {
                 id: ShMemId,
                 size: usize,
             ) -> Result<Self::ShMem, Error> {
-                MmapShMem::shmem_from_id_and_size(id, size)
+                let shmem = MmapShMem::shmem_from_id_and_size(id, size)?;
+                self.advise(&shmem);
+                Ok(shmem)
             }
 
             fn release_shmem(&mut self, shmem: &mut Self::ShMem) {
@@ -932,8 +1015,34 @@ pub mod unix_shmem {
 
         impl CommonUnixShMem {
             /// Create a new shared memory mapping, using shmget/shmat
-            #[allow(unused_qualifications)]
             pub fn new(map_size: usize) -> Result<Self, Error> {
+                Self::with_huge_pages(map_size, HugePages::Off)
+            }
+
+            /// Create a new shared memory mapping, using shmget/shmat, backed by huge pages.
+            /// With [`HugePages::Explicit`], the segment is allocated from the reserved huge pages (on Linux),
+            /// falling back to regular pages with a warning if there are not enough of them.
+            pub fn with_huge_pages(map_size: usize, huge_pages: HugePages) -> Result<Self, Error> {
+                #[cfg(target_os = "linux")]
+                if huge_pages == HugePages::Explicit {
+                    match round_up_to_huge_page(map_size).and_then(|segment_size| {
+                        Self::create(segment_size, map_size, libc::SHM_HUGETLB)
+                    }) {
+                        Ok(shmem) => return Ok(shmem),
+                        Err(err) => log::warn!("Could not allocate a shared mapping backed by huge pages, falling back to regular pages: {err}"),
+                    }
+                }
+
+                let shmem = Self::create(map_size, map_size, 0)?;
+                if huge_pages != HugePages::Off {
+                    advise_transparent_huge_pages(shmem.map, shmem.map_size);
+                }
+                Ok(shmem)
+            }
+
+            /// Allocate a segment of `segment_size` bytes, of which `map_size` bytes are used.
+            #[allow(unused_qualifications)]
+            fn create(segment_size: usize, map_size: usize, flags: c_int) -> Result<Self, Error> {
                 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
                 const SHM_R: libc::c_int = 0o400;
                 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
@@ -946,17 +1055,18 @@ pub mod unix_shmem {
                 unsafe {
                     let os_id = shmget(
                         libc::IPC_PRIVATE,
-                        map_size,
-                        libc::IPC_CREAT | libc::IPC_EXCL | SHM_R | SHM_W,
+                        segment_size,
+                        libc::IPC_CREAT | libc::IPC_EXCL | SHM_R | SHM_W | flags,
                     );
 
                     if os_id < 0_i32 {
-                        return Err(Error::unknown(format!("Failed to allocate a shared mapping of size {map_size} - check OS limits (i.e shmall, shmmax)")));
+                        return Err(Error::unknown(format!("Failed to allocate a shared mapping of size {segment_size} - check OS limits (i.e shmall, shmmax)")));
                     }
 
                     let map = shmat(os_id, ptr::null(), 0) as *mut c_uchar;
 
                     if map as c_int == -1 || map.is_null() {
+                        shmctl(os_id, libc::IPC_RMID, ptr::null_mut());
                         return Err(Error::last_os_error("Failed to map the shared mapping"));
                     }
 
@@ -1022,7 +1132,18 @@ pub mod unix_shmem {
         /// A [`ShMemProvider`] which uses `shmget`/`shmat`/`shmctl` to provide shared memory mappings.
         #[cfg(unix)]
         #[derive(Clone, Debug)]
-        pub struct CommonUnixShMemProvider {}
+        pub struct CommonUnixShMemProvider {
+            huge_pages: HugePages,
+        }
+
+        impl CommonUnixShMemProvider {
+            /// Back the maps with huge pages, see [`HugePages`]
+            #[must_use]
+            pub fn with_huge_pages(mut self, huge_pages: HugePages) -> Self {
+                self.huge_pages = huge_pages;
+                self
+            }
+        }
 
         unsafe impl Send for CommonUnixShMemProvider {}
 
@@ -1039,10 +1160,12 @@ pub mod unix_shmem {
             type ShMem = CommonUnixShMem;
 
             fn new() -> Result<Self, Error> {
-                Ok(Self {})
+                Ok(Self {
+                    huge_pages: HugePages::Off,
+                })
             }
             fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
-                CommonUnixShMem::new(map_size)
+                CommonUnixShMem::with_huge_pages(map_size, self.huge_pages)
             }
 
             fn shmem_from_id_and_size(
@@ -1050,7 +1173,326 @@ pub mod unix_shmem {
                 id: ShMemId,
                 size: usize,
             ) -> Result<Self::ShMem, Error> {
-                CommonUnixShMem::shmem_from_id_and_size(id, size)
+                let shmem = CommonUnixShMem::shmem_from_id_and_size(id, size)?;
+                if self.huge_pages == HugePages::Transparent {
+                    advise_transparent_huge_pages(shmem.map, shmem.map_size);
+                }
+                Ok(shmem)
+            }
+        }
+    }
+
+    /// Module containing `memfd` shared memory support for Linux.
+    ///
+    /// Maps are anonymous files created with [`libc::memfd_create`], so nothing is left behind in `/dev/shm`
+    /// if a fuzzer dies. Their size is sealed right after creation, so no process mapping them can shrink
+    /// the file under the others (which would `SIGBUS` them), or grow it unnoticed.
+    /// Other processes open a map through `/proc/<pid>/fd/<fd>` of its creator, so the creator needs to be alive,
+    /// and keep its map, until everybody mapped it, and other processes need to be allowed to look at its file descriptors.
+    ///
+    /// Hence, ids can not be used across process lifetimes: an id stored by a process that exited since, for example
+    /// in a state saved to disk, is rejected, even if its pid got reused. Use the [`super::ServedShMemProvider`],
+    /// which passes the file descriptors over a unix socket, if maps need to outlive their creator.
+    #[cfg(target_os = "linux")]
+    pub mod memfd {
+        use core::{
+            ops::{Deref, DerefMut},
+            ptr, slice,
+        };
+        use std::{ffi::CString, fs, os::unix::ffi::OsStrExt, process};
+
+        use libc::{
+            c_int, c_uint, close, fcntl, fstat, ftruncate, memfd_create, mmap, munmap, open,
+            F_ADD_SEALS, F_GET_SEALS, F_SEAL_GROW, F_SEAL_SEAL, F_SEAL_SHRINK, MAP_FAILED,
+            MAP_SHARED, MFD_ALLOW_SEALING, MFD_CLOEXEC, MFD_HUGETLB, O_CLOEXEC, O_RDWR, PROT_READ,
+            PROT_WRITE,
+        };
+
+        use super::{advise_transparent_huge_pages, round_up_to_huge_page};
+        use crate::{
+            shmem::{HugePages, ShMem, ShMemId, ShMemProvider},
+            Error,
+        };
+
+        /// The name of the memfds, only visible in `/proc/<pid>/fd`
+        const MEMFD_NAME: &[u8] = b"libafl\0";
+
+        /// The seals every map needs to carry, so its size can never change
+        const SIZE_SEALS: c_int = F_SEAL_SHRINK | F_SEAL_GROW;
+
+        /// A [`ShMem`] backed by a sealed `memfd`
+        #[derive(Debug)]
+        pub struct MemfdShMem {
+            id: ShMemId,
+            fd: c_int,
+            map: *mut u8,
+            /// The size requested by the user
+            map_size: usize,
+            /// The size of the actual mapping, rounded up to the huge page size for huge pages
+            mapping_len: usize,
+        }
+
+        impl MemfdShMem {
+            /// Create a new sealed [`MemfdShMem`] of `map_size` bytes.
+            ///
+            /// With [`HugePages::Explicit`], the map is backed by huge pages if the system has enough reserved,
+            /// otherwise, it falls back to regular pages with a warning.
+            pub fn new(map_size: usize, huge_pages: HugePages) -> Result<Self, Error> {
+                let (fd, map, mapping_len) = match huge_pages {
+                    HugePages::Off => Self::create_and_map(map_size, false)?,
+                    HugePages::Transparent => {
+                        let (fd, map, mapping_len) = Self::create_and_map(map_size, false)?;
+                        advise_transparent_huge_pages(map, mapping_len);
+                        (fd, map, mapping_len)
+                    }
+                    HugePages::Explicit => match Self::create_and_map(map_size, true) {
+                        Ok(created) => created,
+                        Err(err) => {
+                            log::warn!("Could not create a memfd backed by huge pages, falling back to regular pages: {err}");
+                            Self::create_and_map(map_size, false)?
+                        }
+                    },
+                };
+
+                Ok(Self {
+                    id: ShMemId::from_string(&format!("{}:{fd}", process::id())),
+                    fd,
+                    map,
+                    map_size,
+                    mapping_len,
+                })
+            }
+
+            /// Create, seal and map the memfd, returns the fd, the map and the size of the mapping
+            fn create_and_map(
+                map_size: usize,
+                huge_pages: bool,
+            ) -> Result<(c_int, *mut u8, usize), Error> {
+                let (fd, mapping_len) = Self::create(map_size, huge_pages)?;
+                // Huge page memfds fail here, and not on creation, if there are not enough huge pages
+                match Self::map(fd, mapping_len) {
+                    Ok(map) => Ok((fd, map, mapping_len)),
+                    Err(err) => {
+                        unsafe { close(fd) };
+                        Err(err)
+                    }
+                }
+            }
+
+            /// Create and seal the memfd, returns the fd and the size of the file
+            fn create(map_size: usize, huge_pages: bool) -> Result<(c_int, usize), Error> {
+                let mut flags: c_uint = MFD_CLOEXEC | MFD_ALLOW_SEALING;
+                let mut mapping_len = map_size;
+                if huge_pages {
+                    flags |= MFD_HUGETLB;
+                    mapping_len = round_up_to_huge_page(map_size)?;
+                }
+
+                let fd = unsafe { memfd_create(MEMFD_NAME.as_ptr().cast(), flags) };
+                if fd == -1 {
+                    return Err(Error::last_os_error("Failed to create a memfd"));
+                }
+
+                if unsafe { ftruncate(fd, mapping_len.try_into()?) } != 0 {
+                    let err = Error::last_os_error(format!(
+                        "Failed to resize the memfd to {mapping_len} bytes"
+                    ));
+                    unsafe { close(fd) };
+                    return Err(err);
+                }
+
+                // Huge page memfds don't support growing and shrinking to begin with, but the seals still tell others.
+                if unsafe { fcntl(fd, F_ADD_SEALS, SIZE_SEALS | F_SEAL_SEAL) } != 0 {
+                    let err = Error::last_os_error("Failed to seal the memfd");
+                    unsafe { close(fd) };
+                    return Err(err);
+                }
+
+                Ok((fd, mapping_len))
+            }
+
+            fn map(fd: c_int, mapping_len: usize) -> Result<*mut u8, Error> {
+                let map = unsafe {
+                    mmap(
+                        ptr::null_mut(),
+                        mapping_len,
+                        PROT_READ | PROT_WRITE,
+                        MAP_SHARED,
+                        fd,
+                        0,
+                    )
+                };
+                if map == MAP_FAILED || map.is_null() {
+                    return Err(Error::last_os_error(format!(
+                        "Failed to map {mapping_len} bytes of memfd {fd}"
+                    )));
+                }
+                Ok(map.cast())
+            }
+
+            /// Map the existing [`MemfdShMem`] with the given id.
+            ///
+            /// Fails if the map is not sealed against resizing, or smaller than `map_size`,
+            /// so a misbehaving process can't make us access memory past the end of the file.
+            pub fn shmem_from_id_and_size(id: ShMemId, map_size: usize) -> Result<Self, Error> {
+                let Some((pid, fd)) = id.as_str().split_once(':') else {
+                    return Err(Error::illegal_argument(format!(
+                        "{id} is not the id of a memfd map"
+                    )));
+                };
+                let path = CString::new(format!("/proc/{pid}/fd/{fd}")).unwrap();
+                let fd = unsafe { open(path.as_ptr(), O_RDWR | O_CLOEXEC) };
+                if fd == -1 {
+                    return Err(Error::last_os_error(format!(
+                        "Failed to open the memfd map with id {id}, its creator (pid {pid}) may have exited or dropped it"
+                    )));
+                }
+
+                match Self::check_libafl_memfd(fd, id)
+                    .and_then(|()| Self::check_sealed(fd, map_size))
+                    .and_then(|mapping_len| {
+                        let map = Self::map(fd, mapping_len)?;
+                        Ok((map, mapping_len))
+                    }) {
+                    Ok((map, mapping_len)) => Ok(Self {
+                        id,
+                        fd,
+                        map,
+                        map_size,
+                        mapping_len,
+                    }),
+                    Err(err) => {
+                        unsafe { close(fd) };
+                        Err(err)
+                    }
+                }
+            }
+
+            /// Check that `fd` is a memfd created by [`MemfdShMem::new`], and not any file a reused pid happens to have open
+            fn check_libafl_memfd(fd: c_int, id: ShMemId) -> Result<(), Error> {
+                let target = fs::read_link(format!("/proc/self/fd/{fd}"))?;
+                let expected = format!(
+                    "/memfd:{}",
+                    core::str::from_utf8(&MEMFD_NAME[..MEMFD_NAME.len() - 1]).unwrap()
+                );
+                if target
+                    .as_os_str()
+                    .as_bytes()
+                    .starts_with(expected.as_bytes())
+                {
+                    Ok(())
+                } else {
+                    Err(Error::illegal_state(format!(
+                        "{id} does not refer to a memfd map, but to {}, its creator exited and the pid got reused",
+                        target.display()
+                    )))
+                }
+            }
+
+            /// Check that `fd` is sealed against resizing and large enough, returns the size of the file
+            fn check_sealed(fd: c_int, map_size: usize) -> Result<usize, Error> {
+                let seals = unsafe { fcntl(fd, F_GET_SEALS) };
+                if seals == -1 {
+                    return Err(Error::last_os_error(format!(
+                        "Failed to get the seals of fd {fd}, is it a memfd?"
+                    )));
+                }
+                if seals & SIZE_SEALS != SIZE_SEALS {
+                    return Err(Error::illegal_state(format!(
+                        "The memfd {fd} is not sealed against resizing"
+                    )));
+                }
+
+                let mut stat = unsafe { core::mem::zeroed::<libc::stat>() };
+                if unsafe { fstat(fd, &mut stat) } != 0 {
+                    return Err(Error::last_os_error(format!("Failed to stat memfd {fd}")));
+                }
+                let mapping_len: usize = stat.st_size.try_into()?;
+                if mapping_len < map_size {
+                    return Err(Error::illegal_argument(format!(
+                        "The memfd {fd} has {mapping_len} bytes, but {map_size} bytes were requested"
+                    )));
+                }
+                Ok(mapping_len)
+            }
+        }
+
+        impl ShMem for MemfdShMem {
+            fn id(&self) -> ShMemId {
+                self.id
+            }
+        }
+
+        impl Clone for MemfdShMem {
+            fn clone(&self) -> Self {
+                Self::shmem_from_id_and_size(self.id, self.map_size)
+                    .expect("Failed to map the memfd again")
+            }
+        }
+
+        impl Deref for MemfdShMem {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                unsafe { slice::from_raw_parts(self.map, self.map_size) }
+            }
+        }
+
+        impl DerefMut for MemfdShMem {
+            fn deref_mut(&mut self) -> &mut [u8] {
+                unsafe { slice::from_raw_parts_mut(self.map, self.map_size) }
+            }
+        }
+
+        /// [`Drop`] implementation for [`MemfdShMem`], which unmaps and closes the memfd.
+        /// The memory is freed once the last process closed it.
+        impl Drop for MemfdShMem {
+            fn drop(&mut self) {
+                unsafe {
+                    munmap(self.map.cast(), self.mapping_len);
+                    close(self.fd);
+                }
+            }
+        }
+
+        /// A [`ShMemProvider`] which uses sealed `memfd`s to provide shared memory mappings,
+        /// see the [module documentation](self).
+        #[derive(Clone, Debug, Default)]
+        pub struct MemfdShMemProvider {
+            huge_pages: HugePages,
+        }
+
+        impl MemfdShMemProvider {
+            /// Back the maps with huge pages, see [`HugePages`]
+            #[must_use]
+            pub fn with_huge_pages(mut self, huge_pages: HugePages) -> Self {
+                self.huge_pages = huge_pages;
+                self
+            }
+        }
+
+        /// Implement [`ShMemProvider`] for [`MemfdShMemProvider`]
+        impl ShMemProvider for MemfdShMemProvider {
+            type ShMem = MemfdShMem;
+
+            fn new() -> Result<Self, Error> {
+                Ok(Self::default())
+            }
+
+            fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
+                MemfdShMem::new(map_size, self.huge_pages)
+            }
+
+            fn shmem_from_id_and_size(
+                &mut self,
+                id: ShMemId,
+                size: usize,
+            ) -> Result<Self::ShMem, Error> {
+                let shmem = MemfdShMem::shmem_from_id_and_size(id, size)?;
+                if self.huge_pages == HugePages::Transparent {
+                    advise_transparent_huge_pages(shmem.map, shmem.mapping_len);
+                }
+                Ok(shmem)
             }
         }
     }
@@ -1279,24 +1721,50 @@ pub mod unix_shmem {
 }
 
 /// Then `win32` implementation for shared memory.
+///
+/// Mappings are named, so any process that knows the [`ShMemId`] can open them, not only children of the creator.
+/// The [`Win32ShMemProvider`] can additionally create mappings in the global namespace, restrict or widen access
+/// to them with a security descriptor, control handle inheritance, and back them with large pages.
 #[cfg(all(feature = "std", windows))]
 pub mod win32_shmem {
-    use alloc::string::String;
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
     use core::{
         ffi::c_void,
         fmt::{self, Debug, Formatter},
+        mem::size_of,
         ops::{Deref, DerefMut},
-        slice,
+        ptr, slice,
     };
+    use std::ffi::CString;
 
     use uuid::Uuid;
     use windows::{
-        core::PCSTR,
+        core::{PCSTR, PCWSTR},
         Win32::{
-            Foundation::{CloseHandle, BOOL, HANDLE},
-            System::Memory::{
-                CreateFileMappingA, MapViewOfFile, OpenFileMappingA, UnmapViewOfFile,
-                FILE_MAP_ALL_ACCESS, MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+            Foundation::{
+                CloseHandle, GetLastError, LocalFree, BOOL, ERROR_NOT_ALL_ASSIGNED, HANDLE, HLOCAL,
+                LUID,
+            },
+            Security::{
+                AdjustTokenPrivileges,
+                Authorization::{
+                    ConvertStringSecurityDescriptorToSecurityDescriptorA, SDDL_REVISION_1,
+                },
+                LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, PSECURITY_DESCRIPTOR,
+                SECURITY_ATTRIBUTES, SE_LOCK_MEMORY_NAME, SE_PRIVILEGE_ENABLED,
+                TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES,
+            },
+            System::{
+                Memory::{
+                    CreateFileMappingA, GetLargePageMinimum, MapViewOfFile, OpenFileMappingA,
+                    UnmapViewOfFile, VirtualQuery, FILE_MAP_ALL_ACCESS, FILE_MAP_LARGE_PAGES,
+                    MEMORY_BASIC_INFORMATION, MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+                    SEC_COMMIT, SEC_LARGE_PAGES,
+                },
+                Threading::{GetCurrentProcess, OpenProcessToken},
             },
         },
     };
@@ -1308,6 +1776,26 @@ pub mod win32_shmem {
 
     const INVALID_HANDLE_VALUE: isize = -1;
 
+    /// The id prefix of mappings in the session namespace
+    const SESSION_ID_PREFIX: &str = "libafl_";
+    /// The id prefix of mappings in the global namespace
+    const GLOBAL_ID_PREFIX: &str = "libaflg";
+
+    /// The namespace a [`Win32ShMem`] is created in.
+    ///
+    /// The namespace is encoded in the [`ShMemId`], so processes opening the map don't need to know it.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+    pub enum Win32ShMemNamespace {
+        /// The namespace of the creator's session.
+        /// All processes of the same session can open the map.
+        #[default]
+        Session,
+        /// The global namespace, shared by all sessions.
+        /// Use this to share maps with a process in a different session, such as a service running in session 0.
+        /// Creating global maps requires the `SeCreateGlobalPrivilege`, held by services and administrators.
+        Global,
+    }
+
     /// The default [`ShMem`] impl for Windows using `shmctl` & `shmget`
     #[derive(Clone)]
     pub struct Win32ShMem {
@@ -1328,33 +1816,144 @@ pub mod win32_shmem {
         }
     }
 
+    /// The kernel object name of the mapping with the given id, as nul-terminated string
+    fn mapping_name(id: &ShMemId) -> Vec<u8> {
+        let id = &id.as_array()[..id.null_pos()];
+        let mut name = Vec::with_capacity(id.len() + 8);
+        if id.starts_with(GLOBAL_ID_PREFIX.as_bytes()) {
+            name.extend_from_slice(b"Global\\");
+        }
+        name.extend_from_slice(id);
+        name.push(0);
+        name
+    }
+
+    /// A security descriptor, freed on [`Drop`]
+    struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+    impl SecurityDescriptor {
+        /// Parses a security descriptor from its [SDDL](https://learn.microsoft.com/en-us/windows/win32/secauthz/security-descriptor-string-format) string
+        fn from_sddl(sddl: &str) -> Result<Self, Error> {
+            let sddl = CString::new(sddl).map_err(|_| {
+                Error::illegal_argument("Security descriptor string contains a nul byte")
+            })?;
+            let mut descriptor = PSECURITY_DESCRIPTOR::default();
+            unsafe {
+                ConvertStringSecurityDescriptorToSecurityDescriptorA(
+                    PCSTR(sddl.as_ptr().cast()),
+                    SDDL_REVISION_1,
+                    &mut descriptor,
+                    None,
+                )?;
+            }
+            Ok(Self(descriptor))
+        }
+    }
+
+    impl Drop for SecurityDescriptor {
+        fn drop(&mut self) {
+            unsafe {
+                if let Err(err) = LocalFree(HLOCAL(self.0 .0 as isize)) {
+                    log::warn!("Failed to free security descriptor: {err}");
+                }
+            }
+        }
+    }
+
+    /// Enables the `SeLockMemoryPrivilege` for this process and returns the large page size.
+    ///
+    /// The privilege has to be granted to the user first, in the "Lock pages in memory" local security policy.
+    fn enable_large_pages() -> Result<usize, Error> {
+        unsafe {
+            let page_size = GetLargePageMinimum();
+            if page_size == 0 {
+                return Err(Error::unsupported("Large pages are not supported"));
+            }
+
+            let mut token = HANDLE::default();
+            OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES, &mut token)?;
+            let mut luid = LUID::default();
+            let res = LookupPrivilegeValueW(PCWSTR::null(), SE_LOCK_MEMORY_NAME, &mut luid)
+                .and_then(|()| {
+                    let privileges = TOKEN_PRIVILEGES {
+                        PrivilegeCount: 1,
+                        Privileges: [LUID_AND_ATTRIBUTES {
+                            Luid: luid,
+                            Attributes: SE_PRIVILEGE_ENABLED,
+                        }],
+                    };
+                    AdjustTokenPrivileges(token, BOOL(0), Some(&privileges), 0, None, None)
+                });
+            // AdjustTokenPrivileges succeeds even if the privilege was not granted
+            let not_granted = GetLastError() == ERROR_NOT_ALL_ASSIGNED;
+            if let Err(err) = CloseHandle(token) {
+                log::warn!("Failed to close token handle {token:?}: {err}");
+            }
+            res?;
+            if not_granted {
+                return Err(Error::illegal_state(
+                    "SeLockMemoryPrivilege is not granted to this user",
+                ));
+            }
+
+            Ok(page_size)
+        }
+    }
+
     impl Win32ShMem {
-        fn new_shmem(map_size: usize) -> Result<Self, Error> {
+        fn new_shmem(config: &Win32ShMemProvider, map_size: usize) -> Result<Self, Error> {
+            let prefix = match config.namespace {
+                Win32ShMemNamespace::Session => SESSION_ID_PREFIX,
+                Win32ShMemNamespace::Global => GLOBAL_ID_PREFIX,
+            };
+            let mut id_str = format!("{prefix}{}", Uuid::new_v4().simple());
+            id_str.truncate(19); // Leave room for the nul byte in the 20 bytes of the id
+            let id = ShMemId::from_string(&id_str);
+            let name = mapping_name(&id);
+
+            let mut protection = PAGE_READWRITE;
+            let mut access = FILE_MAP_ALL_ACCESS;
+            let mut section_size = map_size;
+            if let Some(page_size) = config.large_page_size {
+                protection |= SEC_COMMIT | SEC_LARGE_PAGES;
+                access |= FILE_MAP_LARGE_PAGES;
+                section_size = (map_size + page_size - 1) / page_size * page_size;
+            }
+            let section_size = section_size as u64;
+
+            let descriptor = config
+                .sddl
+                .as_deref()
+                .map(SecurityDescriptor::from_sddl)
+                .transpose()?;
+            let attributes = SECURITY_ATTRIBUTES {
+                nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+                lpSecurityDescriptor: descriptor.as_ref().map_or(ptr::null_mut(), |sd| sd.0 .0),
+                bInheritHandle: BOOL::from(config.inheritable),
+            };
+
             unsafe {
-                let uuid = Uuid::new_v4();
-                let mut map_str = format!("libafl_{}", uuid.simple());
-                let map_str_bytes = map_str.as_mut_vec();
-                map_str_bytes[19] = 0; // Trucate to size 20
                 let handle = CreateFileMappingA(
                     HANDLE(INVALID_HANDLE_VALUE),
-                    None,
-                    PAGE_READWRITE,
-                    0,
-                    map_size as u32,
-                    PCSTR(map_str_bytes.as_mut_ptr()),
+                    Some(&attributes),
+                    protection,
+                    (section_size >> 32) as u32,
+                    section_size as u32,
+                    PCSTR(name.as_ptr()),
                 )?;
 
-                let map =
-                    MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, map_size).Value as *mut u8;
+                // Map the whole section, which may be rounded up to whole large pages
+                let map = MapViewOfFile(handle, access, 0, 0, 0).Value as *mut u8;
                 if map.is_null() {
+                    let _ = CloseHandle(handle);
                     return Err(Error::unknown(format!(
                         "Cannot map shared memory {}",
-                        String::from_utf8_lossy(map_str_bytes)
+                        id.as_str()
                     )));
                 }
 
                 Ok(Self {
-                    id: ShMemId::try_from_slice(map_str_bytes).unwrap(),
+                    id,
                     handle,
                     map,
                     map_size,
@@ -1362,24 +1961,47 @@ pub mod win32_shmem {
             }
         }
 
-        fn shmem_from_id_and_size(id: ShMemId, map_size: usize) -> Result<Self, Error> {
+        fn shmem_from_id_and_size(
+            config: &Win32ShMemProvider,
+            id: ShMemId,
+            map_size: usize,
+        ) -> Result<Self, Error> {
+            let name = mapping_name(&id);
             unsafe {
-                let map_str_bytes = id.id;
                 // Unlike MapViewOfFile this one needs u32
                 let handle = OpenFileMappingA(
                     FILE_MAP_ALL_ACCESS.0,
-                    BOOL(0),
-                    PCSTR(map_str_bytes.as_ptr() as *mut _),
+                    BOOL::from(config.inheritable),
+                    PCSTR(name.as_ptr()),
                 )?;
 
-                let map =
-                    MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, map_size).Value as *mut u8;
+                // Map the whole section: views of large page sections must span whole large pages
+                let map = MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, 0).Value as *mut u8;
                 if map.is_null() {
+                    let _ = CloseHandle(handle);
                     return Err(Error::unknown(format!(
                         "Cannot map shared memory {}",
-                        String::from_utf8_lossy(&map_str_bytes)
+                        id.as_str()
+                    )));
+                }
+
+                let mut info = MEMORY_BASIC_INFORMATION::default();
+                let info_size = VirtualQuery(
+                    Some(map as *const c_void),
+                    &mut info,
+                    size_of::<MEMORY_BASIC_INFORMATION>(),
+                );
+                if info_size == 0 || info.RegionSize < map_size {
+                    let _ = UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                        Value: map as *mut c_void,
+                    });
+                    let _ = CloseHandle(handle);
+                    return Err(Error::illegal_argument(format!(
+                        "Shared memory {} is smaller than the requested {map_size} bytes",
+                        id.as_str()
                     )));
                 }
+
                 Ok(Self {
                     id,
                     handle,
@@ -1429,8 +2051,17 @@ pub mod win32_shmem {
     }
 
     /// A [`ShMemProvider`] which uses `win32` functions to provide shared memory mappings.
+    ///
+    /// By default, maps are created in the session namespace with the default security descriptor of the process,
+    /// their handles are not inherited, and they use regular pages.
     #[derive(Clone, Debug)]
-    pub struct Win32ShMemProvider {}
+    pub struct Win32ShMemProvider {
+        namespace: Win32ShMemNamespace,
+        sddl: Option<String>,
+        inheritable: bool,
+        /// The size of large pages, if new maps use them
+        large_page_size: Option<usize>,
+    }
 
     impl Default for Win32ShMemProvider {
         fn default() -> Self {
@@ -1438,15 +2069,67 @@ pub mod win32_shmem {
         }
     }
 
+    impl Win32ShMemProvider {
+        /// Sets the namespace new maps are created in
+        #[must_use]
+        pub fn with_namespace(mut self, namespace: Win32ShMemNamespace) -> Self {
+            self.namespace = namespace;
+            self
+        }
+
+        /// Sets the security descriptor of new maps, in [SDDL](https://learn.microsoft.com/en-us/windows/win32/secauthz/security-descriptor-string-format) format.
+        ///
+        /// The default descriptor only grants access to the creating user. A service sharing maps with
+        /// processes of other users has to grant them access, for example `D:(A;;GA;;;AU)` for all authenticated users.
+        pub fn with_sddl(mut self, sddl: &str) -> Result<Self, Error> {
+            // Fail early on a malformed descriptor, instead of on the first map
+            SecurityDescriptor::from_sddl(sddl)?;
+            self.sddl = Some(sddl.to_string());
+            Ok(self)
+        }
+
+        /// Sets whether the handles of created and opened maps are inherited by child processes
+        #[must_use]
+        pub fn with_inheritable(mut self, inheritable: bool) -> Self {
+            self.inheritable = inheritable;
+            self
+        }
+
+        /// Sets whether new maps are backed by large pages, rounding their size up to whole large pages.
+        ///
+        /// This needs the "Lock pages in memory" privilege, which is enabled for the process right away,
+        /// falling back to regular pages if it is missing.
+        #[must_use]
+        pub fn with_large_pages(mut self, large_pages: bool) -> Self {
+            self.large_page_size = if large_pages {
+                match enable_large_pages() {
+                    Ok(page_size) => Some(page_size),
+                    Err(err) => {
+                        log::warn!("Cannot use large pages, falling back to regular pages: {err}");
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            self
+        }
+    }
+
     /// Implement [`ShMemProvider`] for [`Win32ShMemProvider`]
     impl ShMemProvider for Win32ShMemProvider {
         type ShMem = Win32ShMem;
 
         fn new() -> Result<Self, Error> {
-            Ok(Self {})
+            Ok(Self {
+                namespace: Win32ShMemNamespace::Session,
+                sddl: None,
+                inheritable: false,
+                large_page_size: None,
+            })
         }
         fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
-            Win32ShMem::new_shmem(map_size)
+            Win32ShMem::new_shmem(self, map_size)
         }
 
         fn shmem_from_id_and_size(
@@ -1454,7 +2137,7 @@ pub mod win32_shmem {
             id: ShMemId,
             size: usize,
         ) -> Result<Self::ShMem, Error> {
-            Win32ShMem::shmem_from_id_and_size(id, size)
+            Win32ShMem::shmem_from_id_and_size(self, id, size)
         }
     }
 }
@@ -1582,4 +2265,97 @@ mod tests {
         map.as_slice_mut()[0] = 1;
         assert!(map.as_slice()[0] == 1);
     }
+
+    #[test]
+    #[serial]
+    #[cfg(all(unix, not(target_os = "android")))]
+    #[cfg_attr(miri, ignore)]
+    fn test_huge_pages_shmem() {
+        use crate::shmem::{HugePages, ShMem, UnixShMemProvider};
+
+        for huge_pages in [HugePages::Transparent, HugePages::Explicit] {
+            let mut provider = UnixShMemProvider::new()
+                .unwrap()
+                .with_huge_pages(huge_pages);
+            let mut map = provider.new_shmem(1024).unwrap();
+            assert_eq!(map.len(), 1024);
+            map.as_slice_mut()[1023] = 1;
+
+            let other = provider.shmem_from_id_and_size(map.id(), 1024).unwrap();
+            assert_eq!(other.as_slice()[1023], 1);
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(miri, ignore)]
+    fn test_memfd_shmem() {
+        use crate::shmem::{HugePages, MemfdShMemProvider, ShMem, ShMemId};
+
+        let mut provider = MemfdShMemProvider::new().unwrap();
+        let mut map = provider.new_shmem(1024).unwrap();
+        map.as_slice_mut()[0] = 1;
+
+        let mut other = provider.shmem_from_id_and_size(map.id(), 1024).unwrap();
+        assert_eq!(other.as_slice()[0], 1);
+        other.as_slice_mut()[1] = 2;
+        assert_eq!(map.as_slice()[1], 2);
+
+        // Larger than the sealed file
+        assert!(provider.shmem_from_id_and_size(map.id(), 1 << 20).is_err());
+
+        // Any other file open under the id is rejected
+        let path = std::env::temp_dir().join(format!("libafl_memfd_test_{}", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        let id = ShMemId::from_string(&format!(
+            "{}:{}",
+            std::process::id(),
+            std::os::unix::io::AsRawFd::as_raw_fd(&file)
+        ));
+        assert!(provider.shmem_from_id_and_size(id, 0).is_err());
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+
+        // The map is gone once all its holders dropped it
+        let id = map.id();
+        drop(other);
+        drop(map);
+        assert!(provider.shmem_from_id_and_size(id, 1024).is_err());
+
+        let mut provider = MemfdShMemProvider::new()
+            .unwrap()
+            .with_huge_pages(HugePages::Explicit);
+        let mut map = provider.new_shmem(1024).unwrap();
+        map.as_slice_mut()[1023] = 1;
+        assert_eq!(map.len(), 1024);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(windows)]
+    #[cfg_attr(miri, ignore)]
+    fn test_win32_shmem_options() {
+        use crate::shmem::{ShMem, Win32ShMemProvider};
+
+        let mut provider = Win32ShMemProvider::new()
+            .unwrap()
+            .with_sddl("D:(A;;GA;;;AU)")
+            .unwrap()
+            .with_inheritable(true)
+            .with_large_pages(true);
+        let mut map = provider.new_shmem(1024).unwrap();
+        map.as_slice_mut()[0] = 1;
+
+        // Opening with a default provider works, the options only matter to the creator
+        let mut other = Win32ShMemProvider::new().unwrap();
+        let opened = other.shmem_from_id_and_size(map.id(), 1024).unwrap();
+        assert_eq!(opened.as_slice()[0], 1);
+        assert!(other.shmem_from_id_and_size(map.id(), 1 << 30).is_err());
+
+        assert!(Win32ShMemProvider::new()
+            .unwrap()
+            .with_sddl("not a descriptor")
+            .is_err());
+    }
 }
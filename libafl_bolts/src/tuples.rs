@@ -57,6 +57,15 @@ pub fn type_eq<T: ?Sized, U: ?Sized>() -> bool {
     res[0].0.get()
 }
 
+/// The name of the type `T`, without its module path and generic parameters, such as `StdMutationalStage`.
+/// A stable and readable name for components that are not `Named`, for example in introspection output.
+#[must_use]
+pub fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = core::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
 /// Borrow each member of the tuple
 pub trait SplitBorrow<'a> {
     /// The Resulting [`TupleList`], of an [`SplitBorrow::borrow()`] call
@@ -897,7 +906,7 @@ mod test {
 
     #[cfg(feature = "alloc")]
     use crate::ownedref::OwnedMutSlice;
-    use crate::tuples::{type_eq, Map, MappingFunctor};
+    use crate::tuples::{short_type_name, type_eq, Map, MappingFunctor};
 
     #[test]
     #[allow(unused_qualifications)] // for type name tests
@@ -942,6 +951,15 @@ mod test {
         >());
     }
 
+    #[test]
+    fn test_short_type_name() {
+        struct Stage<T>(T);
+
+        assert_eq!(short_type_name::<u8>(), "u8");
+        assert_eq!(short_type_name::<Stage<Stage<u8>>>(), "Stage");
+        assert_eq!(short_type_name::<crate::rands::StdRand>(), "RomuDuoJrRand");
+    }
+
     #[test]
     fn test_mapper() {
         struct W<T>(T);
@@ -166,7 +166,7 @@ macro_rules! fuzz_with {
                 CalibrationStage, GeneralizationStage, IfStage, StdMutationalStage,
                 StdPowerMutationalStage, UnicodeIdentificationStage, TracingStage,
             },
-            state::{HasCorpus, StdState},
+            state::{HasCorpus, HasMaxSize, StdState},
             StdFuzzer,
         };
         use libafl_targets::{CmpLogObserver, LLVMCustomMutator, OomFeedback, OomObserver};
@@ -287,6 +287,11 @@ macro_rules! fuzz_with {
             });
             state.metadata_map_mut().insert_boxed(grimoire_metadata);
 
+            // Mutations will not grow the inputs beyond `-max_len`
+            if let Some(max_len) = $options.max_len() {
+                state.set_max_size(max_len);
+            }
+
             // Set up a string category analysis stage for unicode mutations
             let unicode_used = $options.unicode();
             let unicode_mutator = StdScheduledMutator::new(
@@ -446,8 +451,9 @@ macro_rules! fuzz_with {
                     println!("We imported {} inputs from disk.", state.corpus().count());
                 }
                 if state.corpus().count() < 1 {
-                    // Generator of bytearrays of max size 64
-                    let mut generator = RandBytesGenerator::from(RandBytesGenerator::new(64));
+                    // Generator of bytearrays of max size 64, or `-max_len` if smaller
+                    let max_len = $options.max_len().map_or(64, |max_len| max_len.min(64));
+                    let mut generator = RandBytesGenerator::from(RandBytesGenerator::new(max_len));
 
                     // Generate 1024 initial inputs
                     state
@@ -109,6 +109,7 @@ pub struct LibfuzzerOptions {
     grimoire: Option<bool>,
     unicode: bool,
     forks: Option<usize>,
+    max_len: Option<usize>,
     dict: Option<Tokens>,
     dirs: Vec<PathBuf>,
     ignore_crashes: bool,
@@ -171,6 +172,10 @@ impl LibfuzzerOptions {
         self.forks
     }
 
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
     pub fn dict(&self) -> Option<&Tokens> {
         self.dict.as_ref()
     }
@@ -237,6 +242,7 @@ struct LibfuzzerOptionsBuilder<'a> {
     grimoire: Option<bool>,
     unicode: Option<bool>,
     forks: Option<usize>,
+    max_len: Option<usize>,
     dict: Option<&'a str>,
     dirs: Vec<&'a str>,
     ignore_crashes: Option<bool>,
@@ -308,6 +314,7 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
                                     OptionsParseError::OptionValueParseFailed(name, value)
                                 })?);
                         }
+                        "max_len" => self.max_len = Some(parse_or_bail!(name, value, usize)),
                         "dict" => self.dict = Some(value),
                         "fork" | "jobs" => {
                             self.forks = Some(parse_or_bail!(name, value, usize));
@@ -373,6 +380,8 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
             grimoire: self.grimoire,
             unicode: self.unicode.unwrap_or(true),
             forks: self.forks,
+            // as in libfuzzer, 0 means that the maximum length is not limited explicitly
+            max_len: self.max_len.filter(|&max_len| max_len > 0),
             dict: self.dict.map(|path| {
                 Tokens::from_file(path).expect("Couldn't load tokens from specified dictionary")
             }),
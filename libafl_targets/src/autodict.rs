@@ -0,0 +1,223 @@
+//! Static extraction of dictionary [`Tokens`] from target binaries, without running them.
+//!
+//! The tokens are taken from
+//! - the `libafl_token` section, written by the `autotokens` LTO pass of `libafl_cc`,
+//! - printable strings in the read-only data sections (`.rodata` in ELF, `.rdata` in PE files),
+//! - 32-bit immediates of `cmp` instructions in the code sections, for x86 and x86-64 targets.
+//!
+//! This is the static counterpart to AFL++'s `AFL_LLVM_DICT2FILE`. Add the result to the state
+//! before fuzzing, so the token mutators can use it: `state.add_metadata(tokens)`.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+use std::{fs, path::Path};
+
+use goblin::{
+    elf::{
+        header::{EM_386, EM_X86_64},
+        section_header::SHF_EXECINSTR,
+    },
+    pe::{
+        header::{COFF_MACHINE_X86, COFF_MACHINE_X86_64},
+        section_table::IMAGE_SCN_CNT_CODE,
+    },
+    Object,
+};
+use libafl::{mutators::Tokens, Error};
+
+/// The name of the section the `autotokens` pass of `libafl_cc` writes its tokens to
+pub const AUTOTOKENS_SECTION: &str = "libafl_token";
+
+/// The sections of a binary that are relevant for the [`AutodictExtractor`], as file ranges
+#[derive(Debug, Default)]
+struct Sections {
+    autotokens: Vec<Range<usize>>,
+    rodata: Vec<Range<usize>>,
+    x86_code: Vec<Range<usize>>,
+}
+
+/// Extracts dictionary [`Tokens`] from ELF and PE binaries, see the [module documentation](self)
+#[derive(Debug, Clone, Copy)]
+pub struct AutodictExtractor {
+    min_len: usize,
+    max_len: usize,
+    cmp_constants: bool,
+}
+
+impl Default for AutodictExtractor {
+    fn default() -> Self {
+        Self {
+            min_len: 3,
+            max_len: 32,
+            cmp_constants: true,
+        }
+    }
+}
+
+impl AutodictExtractor {
+    /// Creates a new [`AutodictExtractor`], keeping strings of 3 to 32 bytes, like AFL++'s autodictionary
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimum length of the extracted strings
+    #[must_use]
+    pub fn with_min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len.max(1);
+        self
+    }
+
+    /// Sets the maximum length of the extracted strings, longer strings are usually messages, not tokens
+    #[must_use]
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Sets whether the immediates of `cmp` instructions are extracted, which is on by default
+    #[must_use]
+    pub fn with_cmp_constants(mut self, cmp_constants: bool) -> Self {
+        self.cmp_constants = cmp_constants;
+        self
+    }
+
+    /// Extracts the tokens of the binary at `path`
+    pub fn extract<P>(&self, path: P) -> Result<Tokens, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.extract_from_bytes(&fs::read(path)?)
+    }
+
+    /// Extracts the tokens of a binary, already read into memory
+    pub fn extract_from_bytes(&self, binary: &[u8]) -> Result<Tokens, Error> {
+        let sections = find_sections(binary)?;
+        let mut tokens = Tokens::new();
+
+        for range in &sections.autotokens {
+            let section = &binary[range.clone()];
+            tokens.parse_autodict(section, section.len())?;
+        }
+        for range in &sections.rodata {
+            self.add_strings(&mut tokens, &binary[range.clone()]);
+        }
+        if self.cmp_constants {
+            for range in &sections.x86_code {
+                add_x86_cmp_immediates(&mut tokens, &binary[range.clone()]);
+            }
+        }
+
+        log::info!("Extracted {} tokens from the binary", tokens.len());
+        Ok(tokens)
+    }
+
+    /// Adds all runs of printable ASCII chars of the allowed lengths
+    fn add_strings(&self, tokens: &mut Tokens, data: &[u8]) {
+        for run in data.split(|b| !(b.is_ascii_graphic() || *b == b' ' || *b == b'\t')) {
+            if (self.min_len..=self.max_len).contains(&run.len()) {
+                tokens.add_token(&run.to_vec());
+            }
+        }
+    }
+}
+
+/// Adds the 32-bit immediates of `cmp eax, imm32` and `cmp r/m32, imm32` instructions, as little endian bytes.
+///
+/// This is a linear sweep without decoding, so it may also pick up some bytes that only look like a `cmp`.
+/// Immediates with a single non-zero byte are skipped, the havoc mutations find those on their own.
+fn add_x86_cmp_immediates(tokens: &mut Tokens, code: &[u8]) {
+    let mut add = |imm: &[u8]| {
+        if imm.iter().filter(|b| **b != 0 && **b != 0xff).count() >= 2 {
+            tokens.add_token(&imm.to_vec());
+        }
+    };
+    for (i, opcode) in code.iter().enumerate() {
+        match opcode {
+            // cmp eax, imm32
+            0x3d => {
+                if let Some(imm) = code.get(i + 1..i + 5) {
+                    add(imm);
+                }
+            }
+            // cmp r32, imm32, i.e., a ModRM byte with mod = 3 and reg = 7
+            0x81 => {
+                if let (Some(modrm), Some(imm)) = (code.get(i + 1), code.get(i + 2..i + 6)) {
+                    if modrm & 0xf8 == 0xf8 {
+                        add(imm);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Finds the relevant sections of an ELF or PE binary
+fn find_sections(binary: &[u8]) -> Result<Sections, Error> {
+    let object = Object::parse(binary)
+        .map_err(|e| Error::illegal_argument(format!("Could not parse the binary: {e}")))?;
+    let in_file = |start: usize, len: usize| {
+        let range = start..start.saturating_add(len);
+        (range.end <= binary.len()).then_some(range)
+    };
+
+    let mut sections = Sections::default();
+    match object {
+        Object::Elf(elf) => {
+            let x86 = matches!(elf.header.e_machine, EM_X86_64 | EM_386);
+            for header in &elf.section_headers {
+                let Some(range) = header.file_range() else {
+                    continue;
+                };
+                let Some(range) = in_file(range.start, range.len()) else {
+                    continue;
+                };
+                let name = elf.shdr_strtab.get_at(header.sh_name).unwrap_or_default();
+                if name == AUTOTOKENS_SECTION {
+                    sections.autotokens.push(range);
+                } else if name == ".rodata" || name.starts_with(".rodata.") {
+                    sections.rodata.push(range);
+                } else if x86 && header.sh_flags & u64::from(SHF_EXECINSTR) != 0 {
+                    sections.x86_code.push(range);
+                }
+            }
+        }
+        Object::PE(pe) => {
+            let x86 = matches!(
+                pe.header.coff_header.machine,
+                COFF_MACHINE_X86_64 | COFF_MACHINE_X86
+            );
+            for section in &pe.sections {
+                let Some(range) = in_file(
+                    section.pointer_to_raw_data as usize,
+                    section.size_of_raw_data as usize,
+                ) else {
+                    continue;
+                };
+                let name = section.name().unwrap_or_default();
+                if name == AUTOTOKENS_SECTION {
+                    sections.autotokens.push(range);
+                } else if name == ".rdata" {
+                    sections.rodata.push(range);
+                } else if x86 && section.characteristics & IMAGE_SCN_CNT_CODE != 0 {
+                    sections.x86_code.push(range);
+                }
+            }
+        }
+        _ => {
+            return Err(Error::illegal_argument(
+                "Only ELF and PE binaries are supported",
+            ))
+        }
+    }
+    Ok(sections)
+}
+
+/// Extracts the tokens of the binary at `path`, with the default [`AutodictExtractor`]
+pub fn autodict_from_binary<P>(path: P) -> Result<Tokens, Error>
+where
+    P: AsRef<Path>,
+{
+    AutodictExtractor::new().extract(path)
+}
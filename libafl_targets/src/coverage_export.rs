@@ -0,0 +1,368 @@
+//! Export the coverage of the `SanitizerCoverage` edges map as [lcov](https://github.com/linux-test-project/lcov)
+//! tracefiles or raw `.sancov` files, so standard coverage tooling can show the progress of a campaign against the source.
+//!
+//! The entries of the edges map are mapped to PCs using the `pc-table` of `SanitizerCoverage`,
+//! so the target must be built with `-fsanitize-coverage=trace-pc-guard,pc-table`.
+//! This mapping only holds if the target is a single instrumented module: the edges map numbers the guards of all
+//! modules in one sequence, but only the `pc-table` of the last module loaded is kept, starting again at index 0.
+//! Raw `.sancov` files can be symbolized into `.symcov` files with `sancov -symbolize <binary> <file>.sancov`,
+//! for `sancov -html-report` or LLVM's `coverage-report-server.py`.
+
+use alloc::{borrow::Cow, collections::BTreeMap, vec::Vec};
+#[cfg(unix)]
+use core::mem::MaybeUninit;
+use core::{ffi::c_void, marker::PhantomData, time::Duration};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use libafl::{
+    feedbacks::MapFeedbackMetadata,
+    stages::Stage,
+    state::{HasCorpus, UsesState},
+    Error, HasNamedMetadata,
+};
+use libafl_bolts::{current_time, Named};
+
+use crate::sancov_pcguard::{sanitizer_cov_pc_table, PcTableEntry};
+
+/// The magic number of raw `.sancov` files with 64 bit offsets
+const SANCOV_MAGIC_64: u64 = 0xC0BF_FFFF_FFFF_FF64;
+
+/// The symbolized source locations of the PCs
+#[derive(Debug, Clone)]
+struct SourceLines {
+    /// The distinct source files
+    files: Vec<PathBuf>,
+    /// The index into `files` and the line of each PC, if it has debug info
+    lines: Vec<Option<(usize, u32)>>,
+}
+
+/// Maps the entries of the edges map to the PCs of the target, and writes the covered ones in standard coverage formats.
+///
+/// The entry at index `i` of the edges map is taken to be the PC at index `i`, which only holds for a single
+/// instrumented module, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct CoverageExporter {
+    /// The PC of each entry of the edges map
+    pcs: Vec<usize>,
+    /// The base address of the module the PCs are in
+    module_base: usize,
+    /// The source locations of the entries, once symbolized
+    source_lines: Option<SourceLines>,
+}
+
+impl CoverageExporter {
+    /// Create a new [`CoverageExporter`] for the `pc-table` of the running target,
+    /// which must consist of a single instrumented module
+    pub fn new() -> Result<Self, Error> {
+        let table = sanitizer_cov_pc_table().ok_or_else(|| {
+            Error::illegal_state(
+                "No SanitizerCoverage pc-table found, build the target with -fsanitize-coverage=pc-table",
+            )
+        })?;
+        let pcs: Vec<usize> = table.iter().map(PcTableEntry::addr).collect();
+        let module_base = pcs.first().map_or(0, |&pc| module_base(pc));
+        Ok(Self::with_pcs(pcs, module_base))
+    }
+
+    /// Create a new [`CoverageExporter`] with the PC of each map entry, and the base address of their module
+    #[must_use]
+    pub fn with_pcs(pcs: Vec<usize>, module_base: usize) -> Self {
+        Self {
+            pcs,
+            module_base,
+            source_lines: None,
+        }
+    }
+
+    /// The PCs of the entries that are set in the given edges `map`
+    pub fn covered_pcs<'a>(&'a self, map: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        self.pcs
+            .iter()
+            .zip(map)
+            .filter(|(_, &hits)| hits != 0)
+            .map(|(&pc, _)| pc)
+    }
+
+    /// Write the covered PCs into a raw `.sancov` file, as 64 bit offsets into their module
+    pub fn write_sancov<P>(&self, path: P, map: &[u8]) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&SANCOV_MAGIC_64.to_le_bytes())?;
+        for pc in self.covered_pcs(map) {
+            let offset = pc.wrapping_sub(self.module_base) as u64;
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write an lcov tracefile with the source lines of all entries, and whether they are set in the edges `map`.
+    ///
+    /// The PCs are symbolized with the debug info of the running binary once, entries without it are left out.
+    pub fn write_lcov<P>(&mut self, path: P, test_name: &str, map: &[u8]) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let SourceLines { files, lines } = self
+            .source_lines
+            .get_or_insert_with(|| symbolize(&self.pcs));
+
+        // file -> line -> hit
+        let mut records: BTreeMap<usize, BTreeMap<u32, bool>> = BTreeMap::new();
+        for (idx, source_line) in lines.iter().enumerate() {
+            if let Some((file, line)) = *source_line {
+                let hit = matches!(map.get(idx), Some(&hits) if hits != 0);
+                *records.entry(file).or_default().entry(line).or_default() |= hit;
+            }
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "TN:{test_name}")?;
+        for (file, lines) in records {
+            writeln!(writer, "SF:{}", files[file].display())?;
+            for (line, hit) in &lines {
+                writeln!(writer, "DA:{line},{}", u8::from(*hit))?;
+            }
+            writeln!(writer, "LF:{}", lines.len())?;
+            writeln!(writer, "LH:{}", lines.values().filter(|&&hit| hit).count())?;
+            writeln!(writer, "end_of_record")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Look up the source file and line of each PC
+fn symbolize(pcs: &[usize]) -> SourceLines {
+    let mut files = vec![];
+    let mut file_ids = BTreeMap::new();
+    let lines = pcs
+        .iter()
+        .map(|&pc| {
+            let mut source_line = None;
+            backtrace::resolve(pc as *mut c_void, |symbol| {
+                // for inlined code, keep the innermost location
+                if source_line.is_some() {
+                    return;
+                }
+                if let (Some(file), Some(line)) = (symbol.filename(), symbol.lineno()) {
+                    let id = *file_ids.entry(file.to_path_buf()).or_insert_with(|| {
+                        files.push(file.to_path_buf());
+                        files.len() - 1
+                    });
+                    source_line = Some((id, line));
+                }
+            });
+            source_line
+        })
+        .collect();
+    SourceLines { files, lines }
+}
+
+/// The base address of the module containing `pc`
+#[cfg(unix)]
+fn module_base(pc: usize) -> usize {
+    let mut info = MaybeUninit::<libc::Dl_info>::zeroed();
+    // # Safety
+    // `dladdr` only writes to the info struct
+    if unsafe { libc::dladdr(pc as *const c_void, info.as_mut_ptr()) } == 0 {
+        return 0;
+    }
+    unsafe { info.assume_init() }.dli_fbase as usize
+}
+
+/// The base address of the module containing `pc`
+#[cfg(not(unix))]
+fn module_base(_pc: usize) -> usize {
+    0
+}
+
+/// A stage which exports the coverage accumulated in the history of a map feedback every `interval`,
+/// in the formats it was configured for.
+#[derive(Debug)]
+pub struct CoverageExportStage<E, EM, Z> {
+    exporter: CoverageExporter,
+    map_name: Cow<'static, str>,
+    lcov_path: Option<PathBuf>,
+    sancov_path: Option<PathBuf>,
+    interval: Duration,
+    last_export: Duration,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> UsesState for CoverageExportStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for CoverageExportStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    Self::State: HasCorpus + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let cur = current_time();
+        if cur.checked_sub(self.last_export).unwrap_or_default() >= self.interval {
+            self.export(state)?;
+            self.last_export = cur;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not running the target so we wont't crash/timeout and, hence, don't need to restore anything
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not running the target so we wont't crash/timeout and, hence, don't need to restore anything
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> CoverageExportStage<E, EM, Z> {
+    /// Create a new [`CoverageExportStage`] for the map feedback of the given edges map observer.
+    ///
+    /// Nothing is exported until an lcov or sancov path is set.
+    #[must_use]
+    pub fn new<O>(exporter: CoverageExporter, map_observer: &O, interval: Duration) -> Self
+    where
+        O: Named,
+    {
+        Self {
+            exporter,
+            map_name: map_observer.name().clone(),
+            lcov_path: None,
+            sancov_path: None,
+            interval,
+            last_export: current_time(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Export an lcov tracefile to the given path
+    #[must_use]
+    pub fn with_lcov<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.lcov_path = Some(path.into());
+        self
+    }
+
+    /// Export a raw `.sancov` file to the given path
+    #[must_use]
+    pub fn with_sancov<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.sancov_path = Some(path.into());
+        self
+    }
+
+    /// Export the coverage accumulated so far right away
+    pub fn export<S>(&mut self, state: &S) -> Result<(), Error>
+    where
+        S: HasNamedMetadata,
+    {
+        let Some(meta) = state
+            .named_metadata_map()
+            .get::<MapFeedbackMetadata<u8>>(&self.map_name)
+        else {
+            return Ok(());
+        };
+        if let Some(path) = &self.lcov_path {
+            self.exporter
+                .write_lcov(path, "libafl", &meta.history_map)?;
+        }
+        if let Some(path) = &self.sancov_path {
+            self.exporter.write_sancov(path, &meta.history_map)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::{env, fs, process};
+
+    use super::{CoverageExporter, SANCOV_MAGIC_64};
+
+    #[inline(never)]
+    fn covered_function() -> usize {
+        1
+    }
+
+    #[inline(never)]
+    fn uncovered_function() -> usize {
+        2
+    }
+
+    #[test]
+    fn test_covered_pcs() {
+        let exporter = CoverageExporter::with_pcs(vec![0x1010, 0x1020, 0x1030], 0x1000);
+        assert_eq!(
+            exporter.covered_pcs(&[0, 1, 3]).collect::<Vec<_>>(),
+            [0x1020, 0x1030]
+        );
+        // entries past the end of a shorter map are not covered
+        assert_eq!(exporter.covered_pcs(&[1]).collect::<Vec<_>>(), [0x1010]);
+    }
+
+    #[test]
+    fn test_write_sancov() {
+        let path = env::temp_dir().join(format!("libafl_coverage_export_{}.sancov", process::id()));
+        let exporter = CoverageExporter::with_pcs(vec![0x1010, 0x1020, 0x1030], 0x1000);
+        exporter.write_sancov(&path, &[1, 0, 2]).unwrap();
+
+        let sancov = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let words: Vec<u64> = sancov
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        assert_eq!(sancov.len(), 3 * 8);
+        assert_eq!(words, [SANCOV_MAGIC_64, 0x10, 0x30]);
+    }
+
+    #[test]
+    fn test_write_lcov() {
+        let path = env::temp_dir().join(format!("libafl_coverage_export_{}.info", process::id()));
+        let pcs = vec![
+            covered_function as usize,
+            uncovered_function as usize,
+            // without debug info, left out
+            0,
+        ];
+        let mut exporter = CoverageExporter::with_pcs(pcs, 0);
+        exporter.write_lcov(&path, "test", &[1, 0, 1]).unwrap();
+
+        let lcov = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = lcov.lines().collect();
+        assert_eq!(lines[0], "TN:test");
+        assert!(lines[1].starts_with("SF:") && lines[1].ends_with("coverage_export.rs"));
+        assert!(lines[2].starts_with("DA:") && lines[2].ends_with(",1"));
+        assert!(lines[3].starts_with("DA:") && lines[3].ends_with(",0"));
+        assert_eq!(lines[4..], ["LF:2", "LH:1", "end_of_record"]);
+    }
+}
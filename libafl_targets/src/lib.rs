@@ -135,6 +135,22 @@ pub use cmps::*;
 #[cfg(feature = "std")]
 pub mod drcov;
 
+#[cfg(feature = "autodict")]
+pub mod autodict;
+#[cfg(feature = "autodict")]
+pub use autodict::*;
+
+#[cfg(all(
+    feature = "coverage_export",
+    any(feature = "sancov_pcguard_edges", feature = "sancov_pcguard_hitcounts")
+))]
+pub mod coverage_export;
+#[cfg(all(
+    feature = "coverage_export",
+    any(feature = "sancov_pcguard_edges", feature = "sancov_pcguard_hitcounts")
+))]
+pub use coverage_export::*;
+
 #[cfg(all(windows, feature = "std", feature = "windows_asan"))]
 pub mod windows_asan;
 #[cfg(all(windows, feature = "std", feature = "windows_asan"))]
@@ -0,0 +1,104 @@
+//! Compare the speed of the buffer helpers and the moving havoc mutations across input sizes
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use libafl::{
+    corpus::InMemoryCorpus,
+    inputs::BytesInput,
+    mutators::{
+        buffer_copy, buffer_insert_fill, buffer_self_copy, buffer_set, BytesCopyMutator,
+        BytesDeleteMutator, BytesExpandMutator, BytesInsertCopyMutator, BytesInsertMutator,
+        BytesSwapMutator, Mutator,
+    },
+    state::{HasMaxSize, StdState},
+};
+use libafl_bolts::rands::{Rand, StdRand};
+
+/// The state the mutators run on
+type BenchState =
+    StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+/// The input sizes to compare, from a small packet to a large file
+const SIZES: [usize; 4] = [64, 4096, 256 * 1024, 1024 * 1024];
+
+fn random_bytes(rand: &mut StdRand, len: usize) -> Vec<u8> {
+    (0..len).map(|_| rand.below(256) as u8).collect()
+}
+
+fn buffer_helpers(c: &mut Criterion) {
+    let mut rand = StdRand::with_seed(0);
+    let mut group = c.benchmark_group("buffer_helpers");
+    for size in SIZES {
+        let src = random_bytes(&mut rand, size);
+        let mut dst = vec![0; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("copy", size), &size, |b, &size| {
+            b.iter(|| buffer_copy(black_box(&mut dst), black_box(&src), 0, 0, size));
+        });
+        group.bench_with_input(BenchmarkId::new("self_copy", size), &size, |b, &size| {
+            b.iter(|| buffer_self_copy(black_box(&mut dst), 0, size / 2, size / 2));
+        });
+        group.bench_with_input(BenchmarkId::new("set", size), &size, |b, &size| {
+            b.iter(|| buffer_set(black_box(&mut dst), 0, size, 0x41));
+        });
+        group.bench_with_input(BenchmarkId::new("insert_fill", size), &size, |b, &size| {
+            b.iter_batched(
+                || BytesInput::new(src.clone()),
+                |mut input| {
+                    buffer_insert_fill(&mut input, size / 2, 16, 0x41);
+                    input
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn bench_mutator<M>(c: &mut Criterion, name: &str, mut mutator: M)
+where
+    M: Mutator<BytesInput, BenchState>,
+{
+    let mut rand = StdRand::with_seed(0);
+    let mut state: BenchState = StdState::new(
+        StdRand::with_seed(0),
+        InMemoryCorpus::<BytesInput>::new(),
+        InMemoryCorpus::new(),
+        &mut (),
+        &mut (),
+    )
+    .unwrap();
+
+    let mut group = c.benchmark_group(name);
+    for size in SIZES {
+        // Leave room for the mutations that grow the input
+        state.set_max_size(2 * size);
+        let bytes = random_bytes(&mut rand, size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter_batched(
+                || BytesInput::new(bytes.clone()),
+                |mut input| {
+                    mutator.mutate(&mut state, &mut input).unwrap();
+                    input
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn mutators(c: &mut Criterion) {
+    bench_mutator(c, "BytesDeleteMutator", BytesDeleteMutator::new());
+    bench_mutator(c, "BytesExpandMutator", BytesExpandMutator::new());
+    bench_mutator(c, "BytesInsertMutator", BytesInsertMutator::new());
+    bench_mutator(c, "BytesCopyMutator", BytesCopyMutator::new());
+    bench_mutator(c, "BytesInsertCopyMutator", BytesInsertCopyMutator::new());
+    bench_mutator(c, "BytesSwapMutator", BytesSwapMutator::new());
+}
+
+criterion_group!(benches, buffer_helpers, mutators);
+criterion_main!(benches);